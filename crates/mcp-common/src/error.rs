@@ -22,6 +22,15 @@ pub enum McpError {
     #[error("Policy violation: {0}")]
     PolicyViolation(String),
 
+    /// Policy evaluation errors with an explicit error code and machine-readable details
+    /// (returned to clients in `ErrorDetail.details`)
+    #[error("Policy violation: {message}")]
+    DetailedPolicyViolation {
+        code: u32,
+        message: String,
+        details: Value,
+    },
+
     /// Sandbox execution errors
     #[error("Sandbox error: {0}")]
     Sandbox(String),
@@ -41,6 +50,10 @@ pub enum McpError {
     /// External service communication errors
     #[error("External service error: {0}")]
     ExternalService(String),
+
+    /// Capacity errors (e.g. a full task queue; retry later)
+    #[error("Resource exhausted: {0}")]
+    ResourceExhausted(String),
 }
 
 /// Error code range definitions
@@ -60,11 +73,16 @@ pub mod error_code {
     pub const POLICY_NETWORK_ACCESS_DENIED: u32 = 3002;
     pub const POLICY_FILE_ACCESS_DENIED: u32 = 3003;
     pub const POLICY_RESOURCE_LIMIT_EXCEEDED: u32 = 3004;
+    pub const POLICY_TOOL_CALL_DENIED: u32 = 3005;
 
     // Sandbox errors (4000-4999)
     pub const SANDBOX_SETUP_FAILED: u32 = 4001;
     pub const SANDBOX_EXECUTION_FAILED: u32 = 4002;
     pub const SANDBOX_RESOURCE_LIMIT_EXCEEDED: u32 = 4003;
+    pub const SANDBOX_DISK_QUOTA_EXCEEDED: u32 = 4004;
+    pub const SANDBOX_CPU_TIME_EXCEEDED: u32 = 4005;
+    pub const SANDBOX_OUT_OF_MEMORY: u32 = 4006;
+    pub const SANDBOX_QUEUE_FULL: u32 = 4007;
 
     // Internal errors (5000-5999)
     pub const INTERNAL_UNEXPECTED: u32 = 5001;
@@ -138,12 +156,18 @@ impl McpError {
             
             McpError::NotFound(_) => error_code::RESOURCE_NOT_FOUND,
             
+            // Checked first since these messages also name the command and limits such as max_files
+            McpError::PolicyViolation(msg) if msg.contains("resource limits") => error_code::POLICY_RESOURCE_LIMIT_EXCEEDED,
             McpError::PolicyViolation(msg) if msg.contains("command") => error_code::POLICY_COMMAND_NOT_ALLOWED,
             McpError::PolicyViolation(msg) if msg.contains("network") => error_code::POLICY_NETWORK_ACCESS_DENIED,
             McpError::PolicyViolation(msg) if msg.contains("file") => error_code::POLICY_FILE_ACCESS_DENIED,
             McpError::PolicyViolation(msg) if msg.contains("resource") => error_code::POLICY_RESOURCE_LIMIT_EXCEEDED,
             McpError::PolicyViolation(_) => error_code::POLICY_COMMAND_NOT_ALLOWED,
+            McpError::DetailedPolicyViolation { code, .. } => *code,
             
+            McpError::Sandbox(msg) if msg.contains("Disk quota") => error_code::SANDBOX_DISK_QUOTA_EXCEEDED,
+            McpError::Sandbox(msg) if msg.contains("CPU time limit") => error_code::SANDBOX_CPU_TIME_EXCEEDED,
+            McpError::Sandbox(msg) if msg.contains("out of memory") => error_code::SANDBOX_OUT_OF_MEMORY,
             McpError::Sandbox(msg) if msg.contains("setup") => error_code::SANDBOX_SETUP_FAILED,
             McpError::Sandbox(msg) if msg.contains("resource") => error_code::SANDBOX_RESOURCE_LIMIT_EXCEEDED,
             McpError::Sandbox(_) => error_code::SANDBOX_EXECUTION_FAILED,
@@ -156,6 +180,23 @@ impl McpError {
             
             McpError::Temporary(_) => error_code::INTERNAL_UNEXPECTED,
             McpError::ExternalService(_) => error_code::INTERNAL_DEPENDENCY_FAILED,
+            McpError::ResourceExhausted(_) => error_code::SANDBOX_QUEUE_FULL,
+        }
+    }
+
+    /// Machine-readable details of the error, if any
+    pub fn details(&self) -> Option<&Value> {
+        match self {
+            McpError::DetailedPolicyViolation { details, .. } => Some(details),
+            _ => None,
+        }
+    }
+
+    /// Message of a policy violation (with or without details), `None` for other errors
+    pub fn policy_violation_message(&self) -> Option<&str> {
+        match self {
+            McpError::PolicyViolation(message) | McpError::DetailedPolicyViolation { message, .. } => Some(message),
+            _ => None,
         }
     }
 
@@ -165,7 +206,7 @@ impl McpError {
             error: ErrorDetail {
                 code: self.code(),
                 message: self.to_string(),
-                details: None,
+                details: self.details().cloned(),
             }
         }
     }
@@ -182,16 +223,17 @@ impl McpError {
     }
     
     /// Create policy violation error with custom code
-    pub fn policy_violation(message: impl Into<String>, _code: u32, details: Option<Value>) -> Self {
-        // Create basic error
-        let error = McpError::PolicyViolation(message.into());
-        
-        // Log details
-        if let Some(details_json) = &details {
-            debug!("Policy violation details: {}", details_json);
+    ///
+    /// The code and details are kept only if details are given.
+    pub fn policy_violation(message: impl Into<String>, code: u32, details: Option<Value>) -> Self {
+        match details {
+            Some(details) => McpError::DetailedPolicyViolation {
+                code,
+                message: message.into(),
+                details,
+            },
+            None => McpError::PolicyViolation(message.into()),
         }
-        
-        error
     }
     
     /// Create sandbox error with custom code
@@ -326,12 +368,18 @@ where
             McpError::Internal(e) => McpError::Internal(format!("{}: {}", msg.into(), e)),
             McpError::InvalidRequest(e) => McpError::InvalidRequest(format!("{}: {}", msg.into(), e)),
             McpError::PolicyViolation(e) => McpError::PolicyViolation(format!("{}: {}", msg.into(), e)),
+            McpError::DetailedPolicyViolation { code, message, details } => McpError::DetailedPolicyViolation {
+                code,
+                message: format!("{}: {}", msg.into(), message),
+                details,
+            },
             McpError::Auth(e) => McpError::Auth(format!("{}: {}", msg.into(), e)),
             McpError::NotFound(e) => McpError::NotFound(format!("{}: {}", msg.into(), e)),
             McpError::Sandbox(e) => McpError::Sandbox(format!("{}: {}", msg.into(), e)),
             McpError::Execution(e) => McpError::Execution(format!("{}: {}", msg.into(), e)),
             McpError::Temporary(e) => McpError::Temporary(format!("{}: {}", msg.into(), e)),
             McpError::ExternalService(e) => McpError::ExternalService(format!("{}: {}", msg.into(), e)),
+            McpError::ResourceExhausted(e) => McpError::ResourceExhausted(format!("{}: {}", msg.into(), e)),
         }
     }
 }
@@ -357,4 +405,21 @@ mod tests {
         assert!(matches!(not_found.to_mcp_error(), McpError::NotFound(_)));
         assert!(matches!(perm_denied.to_mcp_error(), McpError::PolicyViolation(_)));
     }
+
+    #[test]
+    fn test_detailed_policy_violation() {
+        let details = serde_json::json!({ "reasons": ["denied"] });
+        let code = error_code::POLICY_FILE_ACCESS_DENIED;
+        let error = McpError::policy_violation("Command 'rm' was denied", code, Some(details.clone()));
+
+        assert_eq!(error.code(), error_code::POLICY_FILE_ACCESS_DENIED);
+        assert_eq!(error.to_string(), "Policy violation: Command 'rm' was denied");
+        assert_eq!(error.policy_violation_message(), Some("Command 'rm' was denied"));
+        assert_eq!(error.to_response().error.details, Some(details));
+
+        // Without details the error code is derived from the message
+        let error = McpError::policy_violation("Command 'rm' was denied", code, None);
+        assert_eq!(error.code(), error_code::POLICY_COMMAND_NOT_ALLOWED);
+        assert!(error.details().is_none());
+    }
 } 
\ No newline at end of file
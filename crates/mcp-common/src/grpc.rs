@@ -15,12 +15,15 @@ impl IntoStatus for McpError {
             McpError::Auth(_) => Status::new(Code::Unauthenticated, self.to_string()),
             McpError::InvalidRequest(_) => Status::new(Code::InvalidArgument, self.to_string()),
             McpError::NotFound(_) => Status::new(Code::NotFound, self.to_string()),
-            McpError::PolicyViolation(_) => Status::new(Code::PermissionDenied, self.to_string()),
+            McpError::PolicyViolation(_) | McpError::DetailedPolicyViolation { .. } => {
+                Status::new(Code::PermissionDenied, self.to_string())
+            }
             McpError::Sandbox(_) => Status::new(Code::FailedPrecondition, self.to_string()),
             McpError::Execution(_) => Status::new(Code::Internal, self.to_string()),
             McpError::Internal(_) => Status::new(Code::Internal, self.to_string()),
             McpError::Temporary(_) => Status::new(Code::Unavailable, self.to_string()),
             McpError::ExternalService(_) => Status::new(Code::Unavailable, self.to_string()),
+            McpError::ResourceExhausted(_) => Status::new(Code::ResourceExhausted, self.to_string()),
         }
     }
 }
@@ -51,11 +54,16 @@ pub fn get_status_code_from_error_code(error_code: u32) -> Code {
         error_code::POLICY_NETWORK_ACCESS_DENIED => Code::PermissionDenied,
         error_code::POLICY_FILE_ACCESS_DENIED => Code::PermissionDenied,
         error_code::POLICY_RESOURCE_LIMIT_EXCEEDED => Code::ResourceExhausted,
+        error_code::POLICY_TOOL_CALL_DENIED => Code::PermissionDenied,
         
         // サンドボックスエラー
         error_code::SANDBOX_SETUP_FAILED => Code::Internal,
         error_code::SANDBOX_EXECUTION_FAILED => Code::Aborted,
         error_code::SANDBOX_RESOURCE_LIMIT_EXCEEDED => Code::ResourceExhausted,
+        error_code::SANDBOX_DISK_QUOTA_EXCEEDED => Code::ResourceExhausted,
+        error_code::SANDBOX_CPU_TIME_EXCEEDED => Code::ResourceExhausted,
+        error_code::SANDBOX_OUT_OF_MEMORY => Code::ResourceExhausted,
+        error_code::SANDBOX_QUEUE_FULL => Code::ResourceExhausted,
         
         // 内部エラー
         error_code::INTERNAL_UNEXPECTED => Code::Internal,
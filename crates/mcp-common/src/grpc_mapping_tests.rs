@@ -42,6 +42,7 @@ mod tests {
             matrix.add(error_code::POLICY_NETWORK_ACCESS_DENIED, Code::PermissionDenied);
             matrix.add(error_code::POLICY_FILE_ACCESS_DENIED, Code::PermissionDenied);
             matrix.add(error_code::POLICY_RESOURCE_LIMIT_EXCEEDED, Code::ResourceExhausted);
+            matrix.add(error_code::POLICY_TOOL_CALL_DENIED, Code::PermissionDenied);
             
             // サンドボックスエラー
             matrix.add(error_code::SANDBOX_SETUP_FAILED, Code::Internal);
@@ -147,6 +148,7 @@ mod tests {
             (McpError::Internal("内部エラー".to_string()), Code::Internal),
             (McpError::Temporary("一時エラー".to_string()), Code::Unavailable),
             (McpError::ExternalService("外部サービスエラー".to_string()), Code::Unavailable),
+            (McpError::ResourceExhausted("キューが満杯".to_string()), Code::ResourceExhausted),
         ];
         
         for (error, expected_code) in errors {
@@ -224,6 +226,7 @@ mod tests {
             error_code::POLICY_NETWORK_ACCESS_DENIED,
             error_code::POLICY_FILE_ACCESS_DENIED,
             error_code::POLICY_RESOURCE_LIMIT_EXCEEDED,
+            error_code::POLICY_TOOL_CALL_DENIED,
             
             // サンドボックスエラー
             error_code::SANDBOX_SETUP_FAILED,
@@ -1,4 +1,5 @@
 use crate::error::{McpError, McpResult};
+use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
@@ -57,6 +58,44 @@ pub fn get_env_var_or(name: &str, default: &str) -> String {
     std::env::var(name).unwrap_or_else(|_| default.to_string())
 }
 
+/// Parse a comma-separated `key=value` list (e.g. `"npm=600,python=120"`)
+///
+/// Empty entries are skipped; entries without `=` or with an empty key are rejected.
+pub fn parse_key_value_pairs(s: &str) -> McpResult<Vec<(String, String)>> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                Ok((key.trim().to_string(), value.trim().to_string()))
+            }
+            _ => Err(McpError::InvalidRequest(format!(
+                "Invalid key=value format: '{}'",
+                entry
+            ))),
+        })
+        .collect()
+}
+
+/// Parse a setting that has to be a positive number (`name` names the setting in the error)
+pub fn parse_positive<T>(name: &str, value: &str) -> McpResult<T>
+where
+    T: FromStr + Default + PartialOrd,
+{
+    match value.trim().parse::<T>() {
+        Ok(number) if number > T::default() => Ok(number),
+        _ => Err(McpError::InvalidRequest(format!(
+            "{} must be a positive number: '{}'",
+            name, value
+        ))),
+    }
+}
+
+/// Encode bytes as lowercase hexadecimal
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -88,4 +127,35 @@ mod tests {
         let result: McpResult<TestStruct> = parse_json(invalid_json);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_parse_key_value_pairs() {
+        let pairs = parse_key_value_pairs("npm=600, python = 120,,").unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                ("npm".to_string(), "600".to_string()),
+                ("python".to_string(), "120".to_string()),
+            ]
+        );
+
+        assert!(parse_key_value_pairs("").unwrap().is_empty());
+        assert!(parse_key_value_pairs("npm").is_err());
+        assert!(parse_key_value_pairs("=600").is_err());
+    }
+
+    #[test]
+    fn test_parse_positive() {
+        assert_eq!(parse_positive::<u64>("MCP_LIMIT", " 42 ").unwrap(), 42);
+        assert_eq!(parse_positive::<usize>("MCP_LIMIT", "1").unwrap(), 1);
+        assert!(parse_positive::<u64>("MCP_LIMIT", "0").is_err());
+        assert!(parse_positive::<u64>("MCP_LIMIT", "-1").is_err());
+        assert!(parse_positive::<u64>("MCP_LIMIT", "ten").is_err());
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex(&[0x00, 0x0f, 0xab]), "000fab");
+        assert_eq!(hex(&[]), "");
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,34 @@
+-- Tasks and their results, shared by PostgreSQL and SQLite.
+-- The encoded protobuf messages are authoritative; the other columns serve reporting queries.
+
+CREATE TABLE IF NOT EXISTS tasks (
+    task_id TEXT PRIMARY KEY,
+    task_type TEXT NOT NULL,
+    status TEXT NOT NULL,
+    -- Audit references: requester and break-glass override reason
+    tenant_id TEXT,
+    user_id TEXT,
+    break_glass TEXT,
+    -- ISO 8601 timestamps
+    created_at TEXT NOT NULL,
+    started_at TEXT,
+    completed_at TEXT,
+    -- Task metadata as a JSON object
+    metadata TEXT NOT NULL,
+    task BYTEA NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS tasks_tenant_created_at ON tasks (tenant_id, created_at);
+CREATE INDEX IF NOT EXISTS tasks_status ON tasks (status);
+
+CREATE TABLE IF NOT EXISTS task_results (
+    task_id TEXT PRIMARY KEY,
+    exit_code INTEGER NOT NULL,
+    execution_time_ms BIGINT NOT NULL,
+    -- Resource usage (NULL if not measured)
+    cpu_time_ms BIGINT,
+    max_memory_kb BIGINT,
+    io_read_bytes BIGINT,
+    io_write_bytes BIGINT,
+    result BYTEA NOT NULL
+);
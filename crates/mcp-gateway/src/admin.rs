@@ -0,0 +1,223 @@
+//! 管理API（AdminService）の実装
+
+use crate::api_keys::ApiKeyStore;
+use crate::error::ErrorHandler;
+use crate::proto::{
+    AdminService, ApiKeyInfo, CreateApiKeyRequest, CreateApiKeyResponse, ListApiKeysRequest, ListApiKeysResponse,
+    PolicyDiagnostic, ReloadPoliciesRequest, ReloadPoliciesResponse, RevokeApiKeyRequest,
+};
+use mcp_common::{McpError, McpResult};
+use mcp_policy::engine::PolicyEngine;
+use mcp_policy::models::UserInfo;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+/// 管理サービスの実装
+#[derive(Debug, Clone)]
+pub struct AdminServiceImpl {
+    // MCPサービスと共有するポリシーエンジン（複製でも評価器は共有される）
+    policy_engine: PolicyEngine,
+    // gRPCの認証と共有するAPIキー（APIキー認証が無効な場合はNone）
+    api_keys: Option<Arc<ApiKeyStore>>,
+}
+
+impl AdminServiceImpl {
+    /// ポリシーエンジンを操作する管理サービスを作成
+    pub fn new(policy_engine: PolicyEngine) -> Self {
+        Self {
+            policy_engine,
+            api_keys: None,
+        }
+    }
+
+    /// APIキーを管理できるようにする
+    pub fn with_api_keys(mut self, api_keys: Arc<ApiKeyStore>) -> Self {
+        self.api_keys = Some(api_keys);
+        self
+    }
+
+    fn api_keys(&self) -> McpResult<&ApiKeyStore> {
+        self.api_keys.as_deref().ok_or_else(|| {
+            McpError::InvalidRequest("APIキー認証が有効ではありません（MCP_API_KEYS_FILEを設定してください）".to_string())
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl AdminService for AdminServiceImpl {
+    /// ポリシーの再コンパイルと切り替え
+    async fn reload_policies(
+        &self,
+        _request: Request<ReloadPoliciesRequest>,
+    ) -> Result<Response<ReloadPoliciesResponse>, Status> {
+        info!("ポリシー再読み込みリクエスト");
+
+        let result: McpResult<ReloadPoliciesResponse> = async {
+            // ポリシーの読み込みとコンパイルはブロッキング処理
+            let policy_engine = self.policy_engine.clone();
+            let reload = tokio::task::spawn_blocking(move || policy_engine.reload_from_env())
+                .await
+                .map_err(|e| McpError::Internal(format!("ポリシーの再読み込みに失敗しました: {}", e)))?;
+
+            // コンパイルエラーはエラーではなく診断情報として返す（以前のポリシーは有効なまま）
+            // 再読み込みの成否はポリシーエンジンがメトリクスに記録する
+            if !reload.is_success() {
+                warn!("ポリシーの再読み込みに失敗しました: 診断 {} 件", reload.diagnostics.len());
+            }
+
+            Ok(ReloadPoliciesResponse {
+                success: reload.is_success(),
+                evaluator: reload.evaluator,
+                tenant_ids: reload.tenant_ids,
+                diagnostics: reload
+                    .diagnostics
+                    .into_iter()
+                    .map(|diagnostic| PolicyDiagnostic {
+                        scope: diagnostic.scope,
+                        message: diagnostic.message,
+                    })
+                    .collect(),
+            })
+        }
+        .await;
+
+        ErrorHandler::handle(result)
+    }
+
+    /// APIキーの作成（シークレットはこのレスポンスでのみ返す）
+    async fn create_api_key(
+        &self,
+        request: Request<CreateApiKeyRequest>,
+    ) -> Result<Response<CreateApiKeyResponse>, Status> {
+        let req = request.into_inner();
+        info!("APIキー作成リクエスト: user_id={}, tenant_id={}", req.user_id, req.tenant_id);
+
+        let result = self.api_keys().and_then(|api_keys| {
+            let user = UserInfo {
+                id: req.user_id,
+                tenant_id: req.tenant_id,
+                roles: req.roles,
+                attributes: HashMap::new(),
+            };
+            let (key, secret) = api_keys.create(&user, req.scopes, &req.description)?;
+            Ok(CreateApiKeyResponse {
+                key: Some(ApiKeyInfo::from(&key)),
+                secret,
+            })
+        });
+
+        ErrorHandler::handle(result)
+    }
+
+    /// APIキーの一覧（シークレットのハッシュは返さない）
+    async fn list_api_keys(
+        &self,
+        request: Request<ListApiKeysRequest>,
+    ) -> Result<Response<ListApiKeysResponse>, Status> {
+        let req = request.into_inner();
+        let result = self.api_keys().map(|api_keys| ListApiKeysResponse {
+            keys: api_keys
+                .list()
+                .iter()
+                .filter(|key| req.tenant_id.is_empty() || key.tenant_id == req.tenant_id)
+                .map(ApiKeyInfo::from)
+                .collect(),
+        });
+
+        ErrorHandler::handle(result)
+    }
+
+    /// APIキーの失効
+    async fn revoke_api_key(&self, request: Request<RevokeApiKeyRequest>) -> Result<Response<ApiKeyInfo>, Status> {
+        let req = request.into_inner();
+        info!("APIキー失効リクエスト: key_id={}", req.key_id);
+
+        let result = self
+            .api_keys()
+            .and_then(|api_keys| api_keys.revoke(&req.key_id))
+            .map(|key| ApiKeyInfo::from(&key));
+
+        ErrorHandler::handle(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_policy::models::{CommandInfo, PolicyInput};
+    use mcp_policy::FailClosedEvaluator;
+
+    // ポリシー再読み込みのテスト（環境変数でポリシーを指定していない場合はスタブ評価器になる）
+    #[tokio::test]
+    async fn test_reload_policies() {
+        let policy_engine = PolicyEngine::with_evaluator(FailClosedEvaluator::new("起動時の読み込みエラー"));
+        let admin = AdminServiceImpl::new(policy_engine.clone());
+        let input = PolicyInput {
+            user: Default::default(),
+            command: CommandInfo {
+                name: "ls".to_string(),
+                ..Default::default()
+            },
+            file: None,
+            network: None,
+            resources: Default::default(),
+            context: Default::default(),
+        };
+        assert!(policy_engine.check_command_execution(&input).await.is_err());
+
+        let response = admin
+            .reload_policies(Request::new(ReloadPoliciesRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.success);
+        assert_eq!(response.evaluator, "stub");
+        assert!(response.diagnostics.is_empty());
+
+        // 共有しているエンジンにも反映される
+        assert!(policy_engine.check_command_execution(&input).await.is_ok());
+    }
+
+    // APIキーの作成・一覧・失効のテスト
+    #[tokio::test]
+    async fn test_api_keys() {
+        let admin = AdminServiceImpl::new(PolicyEngine::new());
+        let request = CreateApiKeyRequest {
+            user_id: "alice".to_string(),
+            tenant_id: "tenant-a".to_string(),
+            roles: vec!["developer".to_string()],
+            scopes: vec!["mcp.McpService/*".to_string()],
+            description: "CI".to_string(),
+        };
+        // APIキー認証が無効な場合は作成できない
+        let status = admin.create_api_key(Request::new(request.clone())).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let dir = tempfile::tempdir().unwrap();
+        let api_keys = Arc::new(ApiKeyStore::open(dir.path().join("api_keys.json")).unwrap());
+        let admin = admin.with_api_keys(api_keys.clone());
+        let created = admin.create_api_key(Request::new(request)).await.unwrap().into_inner();
+        let key = created.key.unwrap();
+        assert_eq!(api_keys.authenticate(&created.secret).unwrap().id, key.key_id);
+        assert_eq!(key.scopes, vec!["mcp.McpService/*"]);
+
+        let list = |tenant_id: &str| ListApiKeysRequest {
+            tenant_id: tenant_id.to_string(),
+        };
+        let keys = admin.list_api_keys(Request::new(list("tenant-a"))).await.unwrap().into_inner().keys;
+        assert_eq!(keys, vec![key.clone()]);
+        let keys = admin.list_api_keys(Request::new(list("tenant-b"))).await.unwrap().into_inner().keys;
+        assert!(keys.is_empty());
+
+        let revoke = RevokeApiKeyRequest { key_id: key.key_id.clone() };
+        let revoked = admin.revoke_api_key(Request::new(revoke)).await.unwrap().into_inner();
+        assert!(revoked.revoked_at.is_some());
+        assert!(api_keys.authenticate(&created.secret).is_err());
+
+        let revoke = RevokeApiKeyRequest { key_id: "key-missing".to_string() };
+        let status = admin.revoke_api_key(Request::new(revoke)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+}
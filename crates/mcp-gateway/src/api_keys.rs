@@ -0,0 +1,513 @@
+//! API key authentication
+//!
+//! When a key file is configured (`MCP_API_KEYS_FILE`), gRPC requests can authenticate with an
+//! API key in the `x-api-key` header (or as `authorization: Bearer <key>`, see [`crate::auth`]).
+//! A key maps to a user, its tenant and its roles, which the request is then handled as, and can
+//! be limited to specific RPCs with scopes:
+//!
+//! * `ExecuteCommand` - the method of any service
+//! * `mcp.McpService/ExecuteCommand` - one method of one service
+//! * `mcp.McpService/*` - every method of a service
+//!
+//! A key without scopes may call every RPC. `AdminService` additionally requires the `admin`
+//! role.
+//!
+//! Only the SHA-256 hash of a key is stored. Keys are provisioned by writing them to the key file
+//! (e.g. with `mcp-gateway api-key create`) or created and revoked at runtime through
+//! `AdminService`, which saves the file again.
+//!
+//! The gateway and the CLI may change the file at the same time. Every change therefore takes a
+//! lock file next to the key file, reads the file again and applies the change to what it holds,
+//! so no process overwrites the changes of another. A running gateway checks the file for
+//! changes every `MCP_API_KEYS_RELOAD_SECS` (default 10), so keys revoked with the CLI are
+//! rejected without a restart.
+
+use crate::proto;
+use chrono::Utc;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{hex, parse_positive};
+use mcp_policy::models::UserInfo;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Header carrying the API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Prefix of generated keys, which makes leaked keys easy to find
+const KEY_PREFIX: &str = "mcp_";
+
+/// Role required for `AdminService`
+pub const ADMIN_ROLE: &str = "admin";
+
+/// Service whose RPCs require the admin role
+const ADMIN_SERVICE: &str = "mcp.AdminService";
+
+/// Whether a user with `roles` may call the RPC at `path` (`AdminService` needs the admin role)
+pub fn role_allows(roles: &[String], path: &str) -> bool {
+    let service = path.trim_start_matches('/').split('/').next().unwrap_or_default();
+    service != ADMIN_SERVICE || roles.iter().any(|role| role == ADMIN_ROLE)
+}
+
+/// User attribute naming the key a request was authenticated with
+pub const API_KEY_ID_ATTRIBUTE: &str = "api_key_id";
+
+/// Default interval at which the key file is checked for changes
+pub const DEFAULT_RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// API key settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyConfig {
+    /// JSON file holding the keys (authentication is disabled if `None`)
+    pub keys_file: Option<PathBuf>,
+    /// Interval at which the key file is checked for changes made by other processes
+    pub reload_interval: Duration,
+}
+
+impl Default for ApiKeyConfig {
+    fn default() -> Self {
+        Self {
+            keys_file: None,
+            reload_interval: DEFAULT_RELOAD_INTERVAL,
+        }
+    }
+}
+
+impl ApiKeyConfig {
+    /// Build the settings from environment variables
+    ///
+    /// * `MCP_API_KEYS_FILE` - key file; requests must carry a key when set
+    /// * `MCP_API_KEYS_RELOAD_SECS` - interval at which the key file is checked for changes
+    pub fn from_env() -> McpResult<Self> {
+        let mut config = Self {
+            keys_file: std::env::var("MCP_API_KEYS_FILE")
+                .ok()
+                .filter(|path| !path.trim().is_empty())
+                .map(|path| PathBuf::from(path.trim())),
+            ..Default::default()
+        };
+        if let Ok(value) = std::env::var("MCP_API_KEYS_RELOAD_SECS") {
+            config.reload_interval = Duration::from_secs(parse_positive("MCP_API_KEYS_RELOAD_SECS", &value)?);
+        }
+        Ok(config)
+    }
+}
+
+/// Stored API key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKey {
+    /// Key ID (not secret)
+    pub id: String,
+    /// SHA-256 of the key, hex encoded
+    pub key_hash: String,
+    /// User the key authenticates as
+    pub user_id: String,
+    /// Tenant of the user
+    pub tenant_id: String,
+    /// Roles of the user
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// RPCs the key may call (all if empty)
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Description
+    #[serde(default)]
+    pub description: String,
+    /// Creation time (RFC 3339)
+    pub created_at: String,
+    /// Revocation time (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+}
+
+impl ApiKey {
+    /// User the key authenticates as
+    pub fn user(&self) -> UserInfo {
+        UserInfo {
+            id: self.user_id.clone(),
+            tenant_id: self.tenant_id.clone(),
+            roles: self.roles.clone(),
+            attributes: HashMap::from([(API_KEY_ID_ATTRIBUTE.to_string(), self.id.clone())]),
+        }
+    }
+
+    /// Whether the key may call the RPC at `path` (`/<service>/<method>`)
+    pub fn allows(&self, path: &str) -> bool {
+        if !path.trim_start_matches('/').contains('/') || !role_allows(&self.roles, path) {
+            return false;
+        }
+        self.scopes.is_empty() || self.scopes.iter().any(|scope| scope_matches(scope, path))
+    }
+}
+
+impl From<&ApiKey> for proto::ApiKeyInfo {
+    fn from(key: &ApiKey) -> Self {
+        Self {
+            key_id: key.id.clone(),
+            user_id: key.user_id.clone(),
+            tenant_id: key.tenant_id.clone(),
+            roles: key.roles.clone(),
+            scopes: key.scopes.clone(),
+            description: key.description.clone(),
+            created_at: key.created_at.clone(),
+            revoked_at: key.revoked_at.clone(),
+        }
+    }
+}
+
+/// Content of the key file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct KeyFile {
+    #[serde(default)]
+    keys: Vec<ApiKey>,
+}
+
+/// API keys, saved to the key file on every change
+#[derive(Debug)]
+pub struct ApiKeyStore {
+    path: PathBuf,
+    keys: RwLock<Vec<ApiKey>>,
+    // Modification time of the key file the keys were last read from or written to
+    loaded_from: RwLock<Option<SystemTime>>,
+}
+
+impl ApiKeyStore {
+    /// Load the keys from `path` (a missing file holds no keys yet)
+    pub fn open(path: impl Into<PathBuf>) -> McpResult<Self> {
+        let path = path.into();
+        let loaded_from = modified(&path);
+        let keys = read_keys(&path)?;
+        info!("Loaded {} API keys from {}", keys.len(), path.display());
+        Ok(Self {
+            path,
+            keys: RwLock::new(keys),
+            loaded_from: RwLock::new(loaded_from),
+        })
+    }
+
+    /// Check the key file for changes every `interval` (must be called within a Tokio runtime)
+    ///
+    /// The check stops when the store has been dropped.
+    pub fn watch(self: &Arc<Self>, interval: Duration) {
+        let watched = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                let Some(store) = watched.upgrade() else {
+                    return;
+                };
+                if let Err(e) = store.reload_if_changed() {
+                    warn!("Keeping the current API keys: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Read the key file again if it changed, returning whether the keys were replaced
+    ///
+    /// Revocations made by this process are kept even if the file lost them.
+    pub fn reload_if_changed(&self) -> McpResult<bool> {
+        let modified = modified(&self.path);
+        if *self.loaded_from.read().unwrap() == modified {
+            return Ok(false);
+        }
+        let mut keys = self.keys.write().unwrap();
+        // Remember the change even if reading fails, so a broken file is reported once
+        *self.loaded_from.write().unwrap() = modified;
+        *keys = merge(read_keys(&self.path)?, &keys);
+        info!("Reloaded {} API keys from {}", keys.len(), self.path.display());
+        Ok(true)
+    }
+
+    /// Create a key for `user`, returning it with its secret (which is not stored)
+    pub fn create(&self, user: &UserInfo, scopes: Vec<String>, description: &str) -> McpResult<(ApiKey, String)> {
+        if user.id.trim().is_empty() || user.tenant_id.trim().is_empty() {
+            return Err(McpError::InvalidRequest("The user ID or tenant ID of the API key is missing".to_string()));
+        }
+        if let Some(scope) = scopes.iter().find(|scope| !is_valid_scope(scope)) {
+            return Err(McpError::InvalidRequest(format!("Invalid API key scope: '{}'", scope)));
+        }
+
+        let secret = format!("{}{}{}", KEY_PREFIX, Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let key = ApiKey {
+            id: format!("key-{}", &Uuid::new_v4().simple().to_string()[..12]),
+            key_hash: hash_secret(&secret),
+            user_id: user.id.clone(),
+            tenant_id: user.tenant_id.clone(),
+            roles: user.roles.clone(),
+            scopes,
+            description: description.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            revoked_at: None,
+        };
+
+        self.update(|keys| {
+            keys.push(key.clone());
+            Ok(())
+        })?;
+        info!("Created API key {} for user {} of tenant {}", key.id, key.user_id, key.tenant_id);
+        Ok((key, secret))
+    }
+
+    /// Revoke the key with the ID `id` (revoking a revoked key keeps the first revocation time)
+    pub fn revoke(&self, id: &str) -> McpResult<ApiKey> {
+        let (key, revoked) = self.update(|keys| {
+            let key = keys
+                .iter_mut()
+                .find(|key| key.id == id)
+                .ok_or_else(|| McpError::NotFound(format!("API key {}", id)))?;
+            let revoked = key.revoked_at.is_none();
+            key.revoked_at.get_or_insert_with(|| Utc::now().to_rfc3339());
+            Ok((key.clone(), revoked))
+        })?;
+        if revoked {
+            info!("Revoked API key {}", id);
+        }
+        Ok(key)
+    }
+
+    /// Keys in creation order
+    pub fn list(&self) -> Vec<ApiKey> {
+        self.keys.read().unwrap().clone()
+    }
+
+    /// Key with the secret `secret`, unless it is unknown or revoked
+    pub fn authenticate(&self, secret: &str) -> McpResult<ApiKey> {
+        let hash = hash_secret(secret);
+        let keys = self.keys.read().unwrap();
+        match keys.iter().find(|key| key.key_hash == hash) {
+            Some(key) if key.revoked_at.is_some() => Err(McpError::Auth(format!("API key {} was revoked", key.id))),
+            Some(key) => Ok(key.clone()),
+            None => Err(McpError::Auth("invalid API key".to_string())),
+        }
+    }
+
+    /// Apply `change` to the keys of the key file and save them
+    ///
+    /// The lock file keeps other processes from changing the key file in between, and reading
+    /// the file first keeps the changes they made before.
+    fn update<T>(&self, change: impl FnOnce(&mut Vec<ApiKey>) -> McpResult<T>) -> McpResult<T> {
+        let mut keys = self.keys.write().unwrap();
+        let _lock = self.lock()?;
+        let mut updated = merge(read_keys(&self.path)?, &keys);
+        let result = change(&mut updated)?;
+        self.save(&updated)?;
+        *self.loaded_from.write().unwrap() = modified(&self.path);
+        *keys = updated;
+        Ok(result)
+    }
+
+    /// Take the lock file next to the key file (released when the returned file is dropped)
+    fn lock(&self) -> McpResult<File> {
+        let lock_path = sibling_path(&self.path, "lock");
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(&lock_path)
+            .and_then(|file| file.lock().map(|_| file));
+        file.map_err(|e| McpError::Internal(format!("Failed to lock API key file {}: {}", lock_path.display(), e)))
+    }
+
+    /// Write the keys to a temporary file readable only by the owner and move it into place
+    fn save(&self, keys: &[ApiKey]) -> McpResult<()> {
+        let content = serde_json::to_vec_pretty(&KeyFile { keys: keys.to_vec() })
+            .map_err(|e| McpError::Internal(format!("Failed to serialize API keys: {}", e)))?;
+        let temp_path = sibling_path(&self.path, "tmp");
+        let written = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&temp_path)
+            .and_then(|mut file| file.write_all(&content).and_then(|_| file.sync_all()))
+            .and_then(|_| std::fs::rename(&temp_path, &self.path));
+        written.map_err(|e| {
+            let _ = std::fs::remove_file(&temp_path);
+            McpError::Internal(format!("Failed to save API key file {}: {}", self.path.display(), e))
+        })
+    }
+}
+
+/// Keys of the key file at `path` (none if it does not exist yet)
+fn read_keys(path: &Path) -> McpResult<Vec<ApiKey>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str::<KeyFile>(&content)
+            .map(|file| file.keys)
+            .map_err(|e| McpError::InvalidRequest(format!("Invalid API key file {}: {}", path.display(), e))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(McpError::Internal(format!("Failed to read API key file {}: {}", path.display(), e))),
+    }
+}
+
+/// Keys of the key file, with the revocations of `known` that the file does not have
+///
+/// Keys missing from the file were removed from it and stay removed.
+fn merge(mut keys: Vec<ApiKey>, known: &[ApiKey]) -> Vec<ApiKey> {
+    for key in &mut keys {
+        if key.revoked_at.is_none() {
+            key.revoked_at = known
+                .iter()
+                .find(|known| known.id == key.id)
+                .and_then(|known| known.revoked_at.clone());
+        }
+    }
+    keys
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Hidden file next to `path` (`.<name>.<extension>`)
+fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{}", name, extension))
+}
+
+/// SHA-256 of a secret, hex encoded
+pub fn hash_secret(secret: &str) -> String {
+    hex(&Sha256::digest(secret.as_bytes()))
+}
+
+/// Whether `scope` covers the RPC at `path` (`/<service>/<method>`)
+pub fn scope_matches(scope: &str, path: &str) -> bool {
+    let Some((service, method)) = path.trim_start_matches('/').split_once('/') else {
+        return false;
+    };
+    match scope.split_once('/') {
+        Some((scope_service, scope_method)) => {
+            scope_service == service && (scope_method == "*" || scope_method == method)
+        }
+        None => scope == method,
+    }
+}
+
+/// Whether `scope` names a method, a method of a service, or every method of a service
+pub fn is_valid_scope(scope: &str) -> bool {
+    let valid_name = |name: &str| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_')
+    };
+    match scope.split_once('/') {
+        Some((service, method)) => valid_name(service) && (method == "*" || valid_name(method)),
+        None => valid_name(scope),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn user(roles: &[&str]) -> UserInfo {
+        UserInfo {
+            id: "alice".to_string(),
+            tenant_id: "tenant-a".to_string(),
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+            attributes: HashMap::new(),
+        }
+    }
+
+    // Test for creating, authenticating with, revoking and reloading keys
+    #[test]
+    fn test_key_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("api_keys.json");
+        let store = ApiKeyStore::open(&path).unwrap();
+        assert!(store.list().is_empty());
+
+        let (key, secret) = store.create(&user(&["developer"]), vec![], "CI").unwrap();
+        assert!(secret.starts_with(KEY_PREFIX));
+        assert_eq!(store.authenticate(&secret).unwrap(), key);
+        assert_eq!(key.user().tenant_id, "tenant-a");
+        assert_eq!(key.user().attributes[API_KEY_ID_ATTRIBUTE], key.id);
+        assert!(matches!(store.authenticate("mcp_wrong"), Err(McpError::Auth(_))));
+
+        // Only the hash is stored
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains(&secret));
+        assert!(content.contains(&hash_secret(&secret)));
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        let revoked = store.revoke(&key.id).unwrap();
+        assert!(revoked.revoked_at.is_some());
+        assert!(store.authenticate(&secret).is_err());
+        assert!(matches!(store.revoke("key-missing"), Err(McpError::NotFound(_))));
+
+        // The revocation survives a restart
+        let reopened = ApiKeyStore::open(&path).unwrap();
+        assert_eq!(reopened.list(), vec![revoked]);
+        assert!(reopened.authenticate(&secret).is_err());
+
+        assert!(store.create(&UserInfo::default(), vec![], "").is_err());
+        assert!(store.create(&user(&[]), vec!["mcp.McpService/".to_string()], "").is_err());
+    }
+
+    // Test for keeping the changes of another process holding the same key file
+    #[test]
+    fn test_concurrent_stores() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("api_keys.json");
+        let gateway = ApiKeyStore::open(&path).unwrap();
+        let cli = ApiKeyStore::open(&path).unwrap();
+        let (first, first_secret) = gateway.create(&user(&[]), vec![], "gateway").unwrap();
+        let (second, _) = cli.create(&user(&[]), vec![], "cli").unwrap();
+
+        // Saving from a stale list keeps the key the other process created
+        gateway.create(&user(&[]), vec![], "gateway").unwrap();
+        let saved: Vec<_> = ApiKeyStore::open(&path).unwrap().list().into_iter().map(|key| key.id).collect();
+        assert_eq!(saved.len(), 3);
+        assert!(saved.contains(&second.id));
+
+        // A revocation made by the other process takes effect once the file is read again
+        assert!(gateway.authenticate(&first_secret).is_ok());
+        cli.revoke(&first.id).unwrap();
+        // Not to depend on the resolution of modification times
+        *gateway.loaded_from.write().unwrap() = None;
+        assert!(gateway.reload_if_changed().unwrap());
+        assert!(gateway.authenticate(&first_secret).is_err());
+        assert!(!gateway.reload_if_changed().unwrap());
+    }
+
+    // Test for the scopes of keys
+    #[test]
+    fn test_scopes() {
+        let key = |roles: &[&str], scopes: &[&str]| ApiKey {
+            id: "key-1".to_string(),
+            key_hash: String::new(),
+            user_id: "alice".to_string(),
+            tenant_id: "tenant-a".to_string(),
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            description: String::new(),
+            created_at: String::new(),
+            revoked_at: None,
+        };
+
+        let unscoped = key(&[], &[]);
+        assert!(unscoped.allows("/mcp.McpService/ExecuteCommand"));
+        assert!(!unscoped.allows("/mcp.AdminService/CreateApiKey"));
+        assert!(key(&[ADMIN_ROLE], &[]).allows("/mcp.AdminService/CreateApiKey"));
+
+        let scoped = key(&[], &["GetTaskStatus", "mcp.McpService/StreamTaskOutput"]);
+        assert!(scoped.allows("/mcp.McpService/GetTaskStatus"));
+        assert!(scoped.allows("/mcp.McpService/StreamTaskOutput"));
+        assert!(!scoped.allows("/mcp.McpService/ExecuteCommand"));
+
+        let service = key(&[ADMIN_ROLE], &["mcp.AdminService/*"]);
+        assert!(service.allows("/mcp.AdminService/RevokeApiKey"));
+        assert!(!service.allows("/mcp.McpService/ExecuteCommand"));
+        assert!(!service.allows("invalid"));
+    }
+}
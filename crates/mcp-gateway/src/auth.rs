@@ -0,0 +1,365 @@
+//! Authentication of gRPC requests
+//!
+//! Requests carry either an API key (see [`crate::api_keys`]) in the `x-api-key` header, or a
+//! bearer token in the `authorization` header. Bearer tokens that are JWTs are validated against
+//! the configured OIDC issuers (see [`crate::oidc`]), anything else is treated as an API key. The
+//! request is then handled as the authenticated user (see [`current_user`]).
+//!
+//! `AdminService` requires the `admin` role, and `Health` can be called without credentials so
+//! that load balancers can probe the gateway.
+//!
+//! HTTP endpoints serving gRPC methods in another form (the task output WebSocket, see
+//! [`crate::task_output_ws`], and the MCP streamable HTTP transport, see [`crate::mcp_http`])
+//! authenticate with the same headers through [`Authenticator::authenticate_http`].
+
+use crate::api_keys::{self, ApiKeyStore, API_KEY_HEADER};
+use crate::oidc::OidcValidator;
+use mcp_common::grpc::IntoStatus;
+use mcp_policy::models::UserInfo;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tonic::body::BoxBody;
+use tonic::codegen::{http, BoxFuture, Service};
+use tonic::{Code, Status};
+use tower::Layer;
+use tracing::warn;
+
+/// RPCs callable without credentials
+const PUBLIC_RPCS: &[&str] = &["/mcp.McpService/Health"];
+
+tokio::task_local! {
+    static CALLER: UserInfo;
+}
+
+/// User the current request was authenticated as (`None` without authentication)
+pub fn current_user() -> Option<UserInfo> {
+    CALLER.try_with(Clone::clone).ok()
+}
+
+/// Run `future` as `user`, or as an unauthenticated request without one
+pub async fn scope<F: Future>(user: Option<UserInfo>, future: F) -> F::Output {
+    match user {
+        Some(user) => CALLER.scope(user, future).await,
+        None => future.await,
+    }
+}
+
+/// Credentials accepted by the gateway
+#[derive(Debug, Clone, Default)]
+pub struct Authenticator {
+    api_keys: Option<Arc<ApiKeyStore>>,
+    oidc: Option<Arc<OidcValidator>>,
+}
+
+impl Authenticator {
+    /// Authenticator accepting no credentials (authentication disabled)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept the API keys of `store`
+    pub fn with_api_keys(mut self, store: Arc<ApiKeyStore>) -> Self {
+        self.api_keys = Some(store);
+        self
+    }
+
+    /// Accept tokens of the OIDC issuers of `validator`
+    pub fn with_oidc(mut self, validator: Arc<OidcValidator>) -> Self {
+        self.oidc = Some(validator);
+        self
+    }
+
+    /// Whether requests have to be authenticated
+    pub fn is_enabled(&self) -> bool {
+        self.api_keys.is_some() || self.oidc.is_some()
+    }
+
+    /// User a request to `path` is authenticated as (`None` for public RPCs called without credentials)
+    pub async fn authenticate(
+        &self,
+        path: &str,
+        headers: &http::HeaderMap,
+    ) -> Result<Option<UserInfo>, Box<Status>> {
+        let header = |name| headers.get(name).and_then(|value: &http::HeaderValue| value.to_str().ok());
+        self.authenticate_headers(path, header(API_KEY_HEADER), header(http::header::AUTHORIZATION.as_str()))
+            .await
+    }
+
+    /// User an HTTP request made for the RPC at `path` is authenticated as
+    ///
+    /// Returns `None` if authentication is disabled, and the HTTP status and message to answer
+    /// rejected requests with.
+    pub async fn authenticate_http(
+        &self,
+        path: &str,
+        headers: &axum::http::HeaderMap,
+    ) -> Result<Option<UserInfo>, (axum::http::StatusCode, String)> {
+        if !self.is_enabled() {
+            return Ok(None);
+        }
+        let header = |name| headers.get(name).and_then(|value: &axum::http::HeaderValue| value.to_str().ok());
+        let authenticated = self.authenticate_headers(path, header(API_KEY_HEADER), header("authorization")).await;
+        authenticated.map_err(|status| {
+            let code = match status.code() {
+                Code::Unauthenticated => axum::http::StatusCode::UNAUTHORIZED,
+                Code::PermissionDenied => axum::http::StatusCode::FORBIDDEN,
+                Code::Unavailable => axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                _ => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (code, status.message().to_string())
+        })
+    }
+
+    async fn authenticate_headers(
+        &self,
+        path: &str,
+        api_key: Option<&str>,
+        authorization: Option<&str>,
+    ) -> Result<Option<UserInfo>, Box<Status>> {
+        let api_key = api_key.map(str::trim);
+        let bearer = authorization.and_then(|value| value.strip_prefix("Bearer ")).map(str::trim);
+
+        let user = match (api_key, bearer, &self.oidc) {
+            (Some(secret), _, _) => self.authenticate_api_key(path, secret)?,
+            (None, Some(token), Some(oidc)) if is_jwt(token) => {
+                let user = oidc.validate(token).await.map_err(|e| {
+                    warn!("Rejected a request to {}: {}", path, e);
+                    Box::new(e.into_status())
+                })?;
+                if !api_keys::role_allows(&user.roles, path) {
+                    warn!("User {} may not call {}", user.id, path);
+                    return Err(Box::new(Status::permission_denied(format!("User {} may not call {}", user.id, path))));
+                }
+                user
+            }
+            (None, Some(secret), _) => self.authenticate_api_key(path, secret)?,
+            (None, None, _) => {
+                if PUBLIC_RPCS.contains(&path) {
+                    return Ok(None);
+                }
+                let message = match (&self.api_keys, &self.oidc) {
+                    (Some(_), None) => format!("An API key is required ({} header)", API_KEY_HEADER),
+                    (None, Some(_)) => "A bearer token is required (authorization header)".to_string(),
+                    _ => format!("An API key ({} header) or a bearer token is required", API_KEY_HEADER),
+                };
+                return Err(Box::new(Status::unauthenticated(message)));
+            }
+        };
+        Ok(Some(user))
+    }
+
+    fn authenticate_api_key(&self, path: &str, secret: &str) -> Result<UserInfo, Box<Status>> {
+        let Some(store) = &self.api_keys else {
+            return Err(Box::new(Status::unauthenticated("API keys are not accepted")));
+        };
+        let key = store.authenticate(secret).map_err(|e| {
+            warn!("Rejected a request to {}: {}", path, e);
+            Box::new(e.into_status())
+        })?;
+        if !key.allows(path) {
+            warn!("API key {} may not call {}", key.id, path);
+            return Err(Box::new(Status::permission_denied(format!("API key {} may not call {}", key.id, path))));
+        }
+        Ok(key.user())
+    }
+}
+
+/// Whether a bearer token looks like a JWT (three dot-separated parts) rather than an API key
+fn is_jwt(token: &str) -> bool {
+    token.split('.').count() == 3
+}
+
+/// Layer authenticating gRPC requests
+#[derive(Debug, Clone)]
+pub struct AuthLayer {
+    authenticator: Arc<Authenticator>,
+}
+
+impl AuthLayer {
+    /// Authenticate requests with `authenticator`
+    pub fn new(authenticator: Authenticator) -> Self {
+        Self {
+            authenticator: Arc::new(authenticator),
+        }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService {
+            inner,
+            authenticator: self.authenticator.clone(),
+        }
+    }
+}
+
+/// Service rejecting requests without valid credentials for the called RPC
+#[derive(Debug, Clone)]
+pub struct AuthService<S> {
+    inner: S,
+    authenticator: Arc<Authenticator>,
+}
+
+impl<S, B> Service<http::Request<B>> for AuthService<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        // The ready service handles this request and the clone handles the next one
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let authenticator = self.authenticator.clone();
+        Box::pin(async move {
+            match authenticator.authenticate(request.uri().path(), request.headers()).await {
+                // Inner layers and the handler run inside the scope, so they see the caller (`call`
+                // must run there too, as layers like RBAC read the caller in it)
+                Ok(user) => scope(user, async move { inner.call(request).await }).await,
+                Err(status) => Ok(status.to_http()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::{HealthRequest, McpServiceClient, McpServiceServer, ReadFileRequest, TaskStatusRequest};
+    use crate::tenant_files::TenantFilesConfig;
+    use crate::McpServiceImpl;
+    use mcp_policy::PolicyEngine;
+    use mcp_sandbox::CommandExecutor;
+    use std::collections::HashMap;
+    use std::time::SystemTime;
+    use tonic::metadata::MetadataValue;
+    use tonic::transport::{Channel, Server};
+    use tonic::Code;
+
+    fn user(roles: &[&str]) -> UserInfo {
+        UserInfo {
+            id: "alice".to_string(),
+            tenant_id: "tenant-a".to_string(),
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+            attributes: HashMap::new(),
+        }
+    }
+
+    // Test for authenticating gRPC requests
+    #[tokio::test]
+    async fn test_layer() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ApiKeyStore::open(dir.path().join("api_keys.json")).unwrap());
+        let (_, secret) = store.create(&user(&[]), vec![], "").unwrap();
+        let (_, health_only) = store.create(&user(&[]), vec!["Health".to_string()], "").unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now());
+        tokio::spawn(
+            Server::builder()
+                .layer(AuthLayer::new(Authenticator::new().with_api_keys(store.clone())))
+                .add_service(McpServiceServer::new(service))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        let channel = Channel::from_shared(format!("http://{}", addr)).unwrap().connect().await.unwrap();
+        let mut client = McpServiceClient::new(channel);
+        let status_request = |secret: Option<&str>| {
+            let mut request = tonic::Request::new(TaskStatusRequest { task_id: "task-missing".to_string() });
+            if let Some(secret) = secret {
+                request.metadata_mut().insert(API_KEY_HEADER, MetadataValue::try_from(secret).unwrap());
+            }
+            request
+        };
+
+        // Authenticated requests reach the service
+        let status = client.get_task_status(status_request(Some(&secret))).await.unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        let mut bearer = tonic::Request::new(TaskStatusRequest { task_id: "task-missing".to_string() });
+        let authorization = MetadataValue::try_from(format!("Bearer {}", secret)).unwrap();
+        bearer.metadata_mut().insert("authorization", authorization);
+        assert_eq!(client.get_task_status(bearer).await.unwrap_err().code(), Code::NotFound);
+
+        let status = client.get_task_status(status_request(None)).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        let status = client.get_task_status(status_request(Some("mcp_wrong"))).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        let status = client.get_task_status(status_request(Some(&health_only))).await.unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+
+        // Health checks need no credentials
+        assert!(client.health(HealthRequest {}).await.is_ok());
+    }
+
+    // Test for telling bearer tokens apart
+    #[tokio::test]
+    async fn test_bearer_dispatch() {
+        assert!(is_jwt("eyJhbGciOiJFUzI1NiJ9.eyJzdWIiOiJhbGljZSJ9.c2ln"));
+        assert!(!is_jwt("mcp_0123456789abcdef"));
+
+        // Without OIDC, JWTs are rejected as unknown API keys instead of being validated
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ApiKeyStore::open(dir.path().join("api_keys.json")).unwrap());
+        let authenticator = Authenticator::new().with_api_keys(store);
+        let mut headers = http::HeaderMap::new();
+        headers.insert(http::header::AUTHORIZATION, "Bearer a.b.c".parse().unwrap());
+        let status = authenticator.authenticate("/mcp.McpService/GetTaskStatus", &headers).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert!(!Authenticator::new().is_enabled());
+    }
+
+    // The caller is visible while the request is handled
+    #[tokio::test]
+    async fn test_current_user() {
+        assert!(current_user().is_none());
+        let caller = CALLER.scope(user(&[]), async { current_user() }).await;
+        assert_eq!(caller.unwrap().id, "alice");
+    }
+
+    // Test for handling gRPC requests as the authenticated caller
+    #[tokio::test]
+    async fn test_handler_sees_caller() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ApiKeyStore::open(dir.path().join("api_keys.json")).unwrap());
+        let (_, secret) = store.create(&user(&[]), vec![], "").unwrap();
+        let root = dir.path().join("tenants");
+        std::fs::create_dir_all(root.join("tenant-a/workspace")).unwrap();
+        std::fs::write(root.join("tenant-a/workspace/owner.txt"), "tenant-a").unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now())
+            .with_tenant_files_config(TenantFilesConfig {
+                root: Some(root),
+                ..Default::default()
+            });
+        tokio::spawn(
+            Server::builder()
+                .layer(AuthLayer::new(Authenticator::new().with_api_keys(store)))
+                .add_service(McpServiceServer::new(service))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        let channel = Channel::from_shared(format!("http://{}", addr)).unwrap().connect().await.unwrap();
+        let mut client = McpServiceClient::new(channel);
+
+        // The file is read from the root of the key's tenant, not of the default user
+        let mut request = tonic::Request::new(ReadFileRequest {
+            path: "owner.txt".to_string(),
+            ..Default::default()
+        });
+        request.metadata_mut().insert(API_KEY_HEADER, MetadataValue::try_from(secret.as_str()).unwrap());
+        let response = client.read_file(request).await.unwrap().into_inner();
+        assert_eq!(response.content, b"tenant-a");
+    }
+}
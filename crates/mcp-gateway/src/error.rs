@@ -34,7 +34,7 @@ impl ErrorHandler {
                 
                 // Change log level based on error type
                 match &err {
-                    McpError::Auth(_) | McpError::PolicyViolation(_) => {
+                    McpError::Auth(_) | McpError::PolicyViolation(_) | McpError::DetailedPolicyViolation { .. } => {
                         // Authentication/policy violations at warn level
                         warn!("Request denied: {}", err);
                     },
@@ -49,13 +49,7 @@ impl ErrorHandler {
                 }
                 
                 // Add detailed information (may need filtering in production)
-                let error_response = ErrorResponse {
-                    error: ErrorDetail {
-                        code: err.code(),
-                        message: err.to_string(),
-                        details: None,
-                    }
-                };
+                let error_response = err.to_response();
                 
                 // Convert error to gRPC Status
                 let mut status = err.into_status();
@@ -93,7 +87,7 @@ impl ErrorHandler {
                 
                 // Change log level based on error type
                 match &mcp_err {
-                    McpError::Auth(_) | McpError::PolicyViolation(_) => {
+                    McpError::Auth(_) | McpError::PolicyViolation(_) | McpError::DetailedPolicyViolation { .. } => {
                         // Authentication/policy violations at warn level
                         warn!("Request denied: {}", mcp_err);
                     },
@@ -108,15 +102,7 @@ impl ErrorHandler {
                 }
                 
                 // Add detailed information
-                let error_code = mcp_err.code();
-                let error_message = mcp_err.to_string();
-                let error_response = ErrorResponse {
-                    error: ErrorDetail {
-                        code: error_code,
-                        message: error_message,
-                        details: None,
-                    }
-                };
+                let error_response = mcp_err.to_response();
                 
                 // Convert error to gRPC Status
                 let mut status = mcp_err.into_status();
@@ -155,12 +141,13 @@ impl ErrorHandler {
             McpError::Auth(_) => "auth",
             McpError::InvalidRequest(_) => "invalid_request",
             McpError::NotFound(_) => "not_found",
-            McpError::PolicyViolation(_) => "policy_violation",
+            McpError::PolicyViolation(_) | McpError::DetailedPolicyViolation { .. } => "policy_violation",
             McpError::Sandbox(_) => "sandbox",
             McpError::Execution(_) => "execution",
             McpError::Internal(_) => "internal",
             McpError::Temporary(_) => "temporary",
             McpError::ExternalService(_) => "external_service",
+            McpError::ResourceExhausted(_) => "resource_exhausted",
         };
         
         // Count by error code as well
@@ -206,7 +193,7 @@ impl ErrorHandler {
                 
                 // Change log level based on error type
                 match &err {
-                    McpError::Auth(_) | McpError::PolicyViolation(_) => {
+                    McpError::Auth(_) | McpError::PolicyViolation(_) | McpError::DetailedPolicyViolation { .. } => {
                         warn!("Request denied: {}", err);
                     },
                     McpError::InvalidRequest(_) => {
@@ -217,10 +204,10 @@ impl ErrorHandler {
                     }
                 }
                 
-                // Create error response with detailed information
+                // Create error response with detailed information (falling back to the details of the error)
                 let error_code = err.code();
                 let error_message = err.to_string();
-                let error_response = if let Some(details_value) = details {
+                let error_response = if let Some(details_value) = details.or_else(|| err.details().cloned()) {
                     ErrorResponse {
                         error: ErrorDetail {
                             code: error_code,
@@ -405,4 +392,27 @@ mod tests {
         let metadata = err.metadata();
         assert!(metadata.contains_key("error-details"));
     }
+    
+    #[test]
+    #[serial_test::serial]
+    fn test_policy_violation_details() {
+        // Details of a policy violation are returned in the error details
+        let details = serde_json::json!({
+            "deny_reasons": [{
+                "code": "command_denied",
+                "rule_id": "commands.deny",
+                "message": "Command 'rm' is forbidden as it is dangerous",
+                "subject": "rm"
+            }]
+        });
+        let message = "Command 'rm' execution was denied by policy";
+        let error = McpError::policy_violation(message, 3001, Some(details.clone()));
+        let err = ErrorHandler::handle(Err::<(), _>(error)).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+        
+        let json = err.metadata().get("error-details").unwrap().to_str().unwrap();
+        let response: ErrorResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.error.code, 3001);
+        assert_eq!(response.error.details, Some(details));
+    }
 } 
\ No newline at end of file
@@ -2,28 +2,245 @@
 //!
 //! gRPCおよびRESTインターフェースを提供するゲートウェイサービス
 
+pub mod admin;
+pub mod api_keys;
+pub mod auth;
 pub mod error;
+pub mod mcp_http;
+pub mod mcp_proxy;
+pub mod mcp_tools;
 pub mod metrics;
+pub mod oidc;
+pub mod rbac;
 pub mod server;
 pub mod service;
+pub mod shutdown;
+pub mod stdio;
 pub mod proto;
+pub mod result_cache;
+pub mod sandbox_policy;
+pub mod task_output_ws;
+pub mod task_queue;
+pub mod task_retention;
+pub mod task_store;
+pub mod tenant_files;
+pub mod timeout;
+pub mod tls;
 pub mod tracing;
 
 pub use crate::proto::mcp;
+pub use crate::admin::AdminServiceImpl;
 pub use crate::service::McpServiceImpl;
 pub use crate::error::ErrorHandler;
+pub use crate::proto::admin_service_server::AdminServiceServer;
 pub use crate::proto::mcp_service_server::McpServiceServer;
 
+use mcp_common::utils::get_env_var_or;
+use mcp_common::McpResult;
 use mcp_policy::engine::PolicyEngine;
-use mcp_sandbox::CommandExecutor;
+use mcp_policy::{
+    BreakGlass, BundleConfig, CommandNormalizer, ContentScan, DecisionCache, DecisionCacheConfig, EnvPolicy,
+    ExecutableHashEnricher, FailClosedEvaluator, GeoIpEnricher, PathCanonicalizer, ResourceLimitPolicy, WebhookConfig,
+    WebhookNotifier, WorkingHoursEnricher,
+};
+use mcp_sandbox::{
+    CommandExecutor, ConcurrencyLimiter, ConcurrencyLimits, HostFingerprint, OutputLogConfig, RetryPolicy,
+};
+use crate::result_cache::ResultCacheConfig;
+use crate::task_queue::TaskQueueConfig;
+use crate::task_retention::TaskRetentionConfig;
+use crate::task_store::{SqlTaskStore, TaskStoreConfig};
+use crate::tenant_files::TenantFilesConfig;
+use crate::timeout::TimeoutPolicy;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::SystemTime;
 
 pub fn create_server(service: McpServiceImpl) -> McpServiceServer<McpServiceImpl> {
     server::create_server(service)
 }
 
-pub fn new_service(start_time: SystemTime) -> McpServiceImpl {
-    McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), start_time)
+/// 管理サービスのgRPCサーバーを作成
+pub fn create_admin_server(service: AdminServiceImpl) -> AdminServiceServer<AdminServiceImpl> {
+    server::create_admin_server(service)
+}
+
+pub fn new_service(start_time: SystemTime) -> McpResult<McpServiceImpl> {
+    // ポリシーの読み込み（コンパイル）に失敗した場合は起動しない
+    // MCP_POLICY_FAIL_CLOSED=true の場合は全てのリクエストを拒否して起動し、管理APIでの再読み込みを待つ
+    let policy_engine = match PolicyEngine::from_env() {
+        Ok(policy_engine) => policy_engine,
+        Err(e) if get_env_var_or("MCP_POLICY_FAIL_CLOSED", "false") == "true" => {
+            ::tracing::error!("ポリシーを読み込めないため、全てのリクエストを拒否します: {}", e);
+            PolicyEngine::with_evaluator(FailClosedEvaluator::new(e.to_string()))
+        }
+        Err(e) => return Err(e),
+    };
+
+    // 判定キャッシュと監査ログはウォッチャーなどが複製を作る前に設定する
+    let decision_cache_config = DecisionCacheConfig::from_env().unwrap_or_else(|e| {
+        ::tracing::warn!("ポリシー判定キャッシュ設定が不正なため、キャッシュを無効にします: {}", e);
+        DecisionCacheConfig::default()
+    });
+    let mut policy_engine = policy_engine
+        .with_decision_cache(DecisionCache::new(decision_cache_config))
+        // 評価時間・判定結果・キャッシュヒット率・再読み込みをメトリクスに記録する
+        .with_metrics(metrics::PolicyEngineMetrics)
+        // カナリアと現行バージョンの判定の一致・相違を記録する
+        .with_canary_observer(metrics::increment_policy_canary_evaluations);
+
+    // ポリシー判定の監査ログ（出力先の設定誤りの場合は起動しない）
+    for sink in mcp_policy::audit::sinks_from_env()? {
+        policy_engine = policy_engine.with_audit_sink(sink);
+    }
+
+    // 環境変数による秘密情報の持ち出しを防ぐ（設定誤りの場合は起動しない）
+    policy_engine = policy_engine.with_env_policy(EnvPolicy::from_env()?);
+
+    // ファイルパスをシンボリックリンクも含めて正規化してから判定する（設定誤りの場合は起動しない）
+    policy_engine = policy_engine.with_path_canonicalizer(PathCanonicalizer::from_env()?);
+
+    // busybox や env 経由の実行を実際のコマンドに正規化してから判定する（拒否リストの回避防止）
+    policy_engine = policy_engine.with_command_normalizer(CommandNormalizer::from_env()?);
+
+    // 要求できるリソース制限の上限（設定誤りの場合は起動しない）
+    policy_engine = policy_engine.with_resource_limit_policy(ResourceLimitPolicy::from_env()?);
+
+    // 書き込むファイルの内容をスキャンし、検出結果をポリシー評価に渡す（秘密鍵などの書き込み防止）
+    policy_engine = policy_engine.with_content_scan(ContentScan::from_env()?);
+
+    // ポリシー違反をWebhookに通知する（設定誤りの場合は起動しない）
+    if let Some(webhook_config) = WebhookConfig::from_env()? {
+        policy_engine = policy_engine.with_violation_notifier(WebhookNotifier::start(webhook_config)?);
+    }
+
+    // 緊急時にポリシーの拒否を上書きするブレークグラストークン（設定誤りの場合は起動しない）
+    if let Some(break_glass) = BreakGlass::from_env()? {
+        policy_engine = policy_engine
+            .with_break_glass(break_glass.with_observer(metrics::increment_policy_break_glass_overrides));
+    }
+
+    // 勤務時間内かどうかをポリシーの入力コンテキストに追加する（設定誤りの場合は起動しない）
+    if let Some(enricher) = WorkingHoursEnricher::from_env()? {
+        policy_engine = policy_engine.with_input_enricher(enricher);
+    }
+
+    // 実行ファイルのSHA-256ダイジェストをポリシーの入力コンテキストに追加する（改ざんされたバイナリの検出）
+    if let Some(enricher) = ExecutableHashEnricher::from_env()? {
+        policy_engine = policy_engine.with_input_enricher(enricher);
+    }
+
+    // 接続先ホストの国とAS番号をポリシーの入力コンテキストに追加する（GeoIPによる送信先制限）
+    if let Some(enricher) = GeoIpEnricher::from_env()? {
+        policy_engine = policy_engine.with_input_enricher(enricher);
+    }
+
+    // ポリシーファイルの変更を監視して再起動なしで反映する
+    let mut policy_watchers = Vec::new();
+    if get_env_var_or("MCP_POLICY_HOT_RELOAD", "true") != "false" {
+        if let Ok(dir) = std::env::var("MCP_POLICY_DIR") {
+            policy_watchers.extend(
+                policy_engine
+                    .watch_policy_dir(&dir)
+                    .map_err(|e| ::tracing::warn!("ポリシーの監視を開始できませんでした: {}", e))
+                    .ok(),
+            );
+        }
+        // テナントごとのポリシーも個別に監視する
+        if let Ok(dir) = std::env::var("MCP_POLICY_TENANT_DIR") {
+            for (tenant_id, tenant_dir) in mcp_policy::engine::tenant_policy_dirs(Path::new(&dir))? {
+                policy_watchers.extend(
+                    policy_engine
+                        .watch_tenant_policy_dir(tenant_id.as_str(), &tenant_dir)
+                        .map_err(|e| {
+                            ::tracing::warn!("テナント {} のポリシーの監視を開始できませんでした: {}", tenant_id, e)
+                        })
+                        .ok(),
+                );
+            }
+        }
+    }
+
+    // バンドルサーバーから定期的にポリシーを取得する（設定誤りの場合は起動しない）
+    let policy_bundle = match BundleConfig::from_env()? {
+        Some(config) => Some(policy_engine.poll_bundle(config, |revision| {
+            // 初回の取得はメトリクスサーバーの起動前に完了することがある
+            metrics::init_metrics();
+            metrics::record_policy_bundle_activation(revision);
+        })?),
+        None => None,
+    };
+
+    let timeout_policy = TimeoutPolicy::from_env().unwrap_or_else(|e| {
+        ::tracing::warn!("タイムアウト設定が不正なため、デフォルト値を使用します: {}", e);
+        TimeoutPolicy::default()
+    });
+
+    let output_log_config = OutputLogConfig::from_env().unwrap_or_else(|e| {
+        ::tracing::warn!("タスク出力ログ設定が不正なため、デフォルト値を使用します: {}", e);
+        OutputLogConfig::default()
+    });
+
+    let result_cache_config = ResultCacheConfig::from_env().unwrap_or_else(|e| {
+        ::tracing::warn!("結果キャッシュ設定が不正なため、キャッシュを無効にします: {}", e);
+        ResultCacheConfig::default()
+    });
+
+    // ファイル操作はテナントのルート内でのみ行う（未設定の場合は拒否）
+    let tenant_files_config = TenantFilesConfig::from_env().unwrap_or_else(|e| {
+        ::tracing::warn!("テナントのファイル設定が不正なため、デフォルト値を使用します: {}", e);
+        TenantFilesConfig::default()
+    });
+
+    // タスクはキューで実行を待ち、上限数のワーカーで実行する
+    let task_queue_config = TaskQueueConfig::from_env().unwrap_or_else(|e| {
+        ::tracing::warn!("タスクキューの設定が不正なため、デフォルト値を使用します: {}", e);
+        TaskQueueConfig::default()
+    });
+
+    // 終了したタスクは保持期間を過ぎるとメモリから削除する
+    let task_retention_config = TaskRetentionConfig::from_env().unwrap_or_else(|e| {
+        ::tracing::warn!("タスク保持期間の設定が不正なため、デフォルト値を使用します: {}", e);
+        TaskRetentionConfig::default()
+    });
+
+    // 同時実行数の上限（超えたコマンドは待ち行列に入る）
+    let concurrency_limits = ConcurrencyLimits::from_env().unwrap_or_else(|e| {
+        ::tracing::warn!("同時実行数の設定が不正なため、上限なしで実行します: {}", e);
+        ConcurrencyLimits::default()
+    });
+    let concurrency_limiter = ConcurrencyLimiter::new(concurrency_limits).with_metrics(metrics::SandboxQueueMetrics);
+    // 一時的な障害で失敗したコマンドの再実行
+    let retry_policy = RetryPolicy::from_env().unwrap_or_else(|e| {
+        ::tracing::warn!("再実行の設定が不正なため、デフォルト値を使用します: {}", e);
+        RetryPolicy::default()
+    });
+    let command_executor = CommandExecutor::new()
+        .with_concurrency_limiter(concurrency_limiter)
+        .with_retry_policy(retry_policy);
+
+    let mut service = McpServiceImpl::new(policy_engine, command_executor, start_time)
+        .with_timeout_policy(timeout_policy)
+        .with_output_log_config(output_log_config)
+        .with_result_cache_config(result_cache_config)
+        .with_tenant_files_config(tenant_files_config)
+        .with_task_queue_config(task_queue_config)
+        .with_task_retention(task_retention_config)
+        .with_host_fingerprint(HostFingerprint::current().clone());
+    for policy_watcher in policy_watchers {
+        service = service.with_policy_watcher(policy_watcher);
+    }
+    if let Some(policy_bundle) = policy_bundle {
+        service = service.with_policy_bundle(policy_bundle);
+    }
+
+    // タスクストアの設定誤りは起動を止める（タスクの記録が黙って失われないように）
+    let task_store_config = TaskStoreConfig::from_env()?;
+    if task_store_config.url.is_some() {
+        service = service.with_task_store(Arc::new(SqlTaskStore::connect_lazy(&task_store_config)?));
+    }
+
+    Ok(service)
 }
 
 #[cfg(test)]
@@ -1,12 +1,97 @@
-use mcp_gateway::{create_server, new_service};
+use mcp_gateway::{create_admin_server, new_service, AdminServiceImpl};
+use mcp_gateway::api_keys::{ApiKeyConfig, ApiKeyStore};
+use mcp_gateway::auth::Authenticator;
+use mcp_gateway::mcp_http::{McpHttpConfig, McpHttpServer};
+use mcp_gateway::mcp_proxy::{McpProxy, McpProxyConfig};
+use mcp_gateway::oidc::{OidcConfig, OidcValidator};
+use mcp_gateway::rbac::RbacConfig;
 use mcp_gateway::server::run_server;
+use mcp_gateway::shutdown::{self, ShutdownConfig};
+use mcp_gateway::stdio::StdioServer;
+use mcp_gateway::tls::{TlsCertificate, TlsConfig};
 use mcp_gateway::tracing::{init_tracing, shutdown_tracing, TracingConfig};
+use clap::{Parser, Subcommand};
+use mcp_policy::bench::{load_corpus, PolicyBenchmark};
+use mcp_policy::engine::PolicyEngine;
+use mcp_policy::models::UserInfo;
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::SystemTime;
-use tracing::info;
+use tracing::{error, info, warn};
+
+#[derive(Parser)]
+#[command(version, about = "MCPセキュリティゲートウェイ")]
+struct Cli {
+    /// gRPCの代わりに標準入出力でMCP（JSON-RPC）を提供する（MCPクライアントから起動する場合）
+    #[arg(long)]
+    stdio: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 記録されたポリシー入力を現在のポリシーで評価し、レイテンシを計測する（ロールアウト前の性能確認用）
+    #[command(hide = true)]
+    BenchPolicy {
+        /// ポリシー入力または監査ログのJSON Linesファイル
+        corpus: PathBuf,
+        /// 計測するコーパスの周回数
+        #[arg(long, default_value_t = 10)]
+        iterations: usize,
+        /// 計測前に評価する周回数
+        #[arg(long, default_value_t = 1)]
+        warmup: usize,
+        /// p99レイテンシの上限（ミリ秒）。超えた場合は失敗する
+        #[arg(long)]
+        max_p99_ms: Option<f64>,
+    },
+    /// APIキーをキーファイル（MCP_API_KEYS_FILE）で管理する（起動前のプロビジョニング用）
+    ApiKey {
+        #[command(subcommand)]
+        command: ApiKeyCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ApiKeyCommand {
+    /// APIキーを作成し、シークレットを表示する（キーファイルにはハッシュのみ保存される）
+    Create {
+        /// キーで認証するユーザー
+        #[arg(long)]
+        user: String,
+        /// ユーザーのテナント
+        #[arg(long)]
+        tenant: String,
+        /// ユーザーのロール（複数指定可）
+        #[arg(long = "role")]
+        roles: Vec<String>,
+        /// 呼び出せるRPC（"ExecuteCommand"、"mcp.McpService/*"など。複数指定可、省略時はすべて）
+        #[arg(long = "scope")]
+        scopes: Vec<String>,
+        /// 説明
+        #[arg(long, default_value = "")]
+        description: String,
+    },
+    /// APIキーを失効させる
+    Revoke {
+        /// キーID
+        key_id: String,
+    },
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::BenchPolicy { corpus, iterations, warmup, max_p99_ms }) => {
+            return bench_policy(corpus, iterations, warmup, max_p99_ms).await;
+        }
+        Some(Command::ApiKey { command }) => return manage_api_keys(command),
+        None => {}
+    }
+
     // トレース設定を環境変数から構築
     let tracing_config = TracingConfig {
         enabled: std::env::var("OTEL_ENABLED")
@@ -31,6 +116,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .unwrap_or(1.0),
         log_level: std::env::var("RUST_LOG")
             .unwrap_or_else(|_| "info".to_string()),
+        // stdioモードでは標準出力をプロトコルに使う
+        log_to_stderr: cli.stdio,
     };
     
     // トレーシングシステムを初期化
@@ -42,22 +129,139 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let start_time = SystemTime::now();
     
     // サービス実装を作成
-    let service = new_service(start_time);
-    
+    let service = Arc::new(new_service(start_time)?);
+
+    // 上流MCPサーバーのツールをポリシーチェック付きで中継（MCP_PROXY_CONFIGを設定した場合のみ）
+    let proxy = Arc::new(McpProxy::connect(&McpProxyConfig::from_env()?).await);
+
+    // シグナルを受けてから実行中のタスクを待つ時間
+    let shutdown_config = ShutdownConfig::from_env()?;
+
+    // stdioモードでは標準入力が閉じられるか、シグナルを受けるまでMCPクライアントに応答する
+    if cli.stdio {
+        info!("標準入出力でMCPを提供します");
+        let stdio_server = StdioServer::new(service.clone()).with_proxy(proxy);
+        tokio::select! {
+            result = stdio_server.serve_stdio() => result?,
+            _ = shutdown::signal() => {}
+        }
+        service.drain(shutdown_config.drain_timeout).await;
+        shutdown_tracing();
+        return Ok(());
+    }
+
     // バインドするアドレス
     let addr = std::env::var("MCP_BIND_ADDRESS")
         .unwrap_or_else(|_| "127.0.0.1:8081".to_string())
         .parse::<SocketAddr>()?;
     
-    // gRPCサービスを作成
-    let grpc_service = create_server(service);
-    
+    // APIキー認証（キーファイルを設定した場合のみ）
+    let api_key_config = ApiKeyConfig::from_env()?;
+    let api_keys = api_key_config.keys_file.map(ApiKeyStore::open).transpose()?.map(Arc::new);
+    if let Some(api_keys) = &api_keys {
+        // CLIで失効させたキーを再起動せずに拒否するため、キーファイルの変更を監視する
+        api_keys.watch(api_key_config.reload_interval);
+    }
+
+    // 管理サービスはMCPサービスとポリシーエンジン、APIキーを共有する
+    let mut admin_service = AdminServiceImpl::new(service.policy_engine().clone());
+    if let Some(api_keys) = &api_keys {
+        admin_service = admin_service.with_api_keys(api_keys.clone());
+    }
+    let admin_service = create_admin_server(admin_service);
+
+    // OIDCトークンによる認証（発行者を設定した場合のみ）
+    let mut authenticator = Authenticator::new();
+    if let Some(api_keys) = api_keys {
+        authenticator = authenticator.with_api_keys(api_keys);
+    }
+    if let Some(oidc_config) = OidcConfig::from_env()? {
+        authenticator = authenticator.with_oidc(OidcValidator::start(oidc_config).await);
+    }
+
+    // ロールによるメソッドの認可（ルールを設定した場合のみ、MCPのHTTPとタスク出力のWebSocketにも適用する）
+    let rbac = RbacConfig::from_env()?.map(Arc::new);
+    if rbac.is_some() && !authenticator.is_enabled() {
+        warn!("認証が無効なため、RBACのルールは呼び出し元をロールなしとして評価します");
+    }
+
+    // ストリーマブルHTTPでのMCP（アドレスを設定した場合のみ、gRPCと同じ認証と認可を要求する）
+    let mcp_http_config = McpHttpConfig::from_env()?;
+    if let Some(mcp_http_addr) = mcp_http_config.bind_address {
+        let mut mcp_http_server = McpHttpServer::new(service.clone(), mcp_http_config)
+            .with_proxy(proxy)
+            .with_authenticator(authenticator.clone());
+        if let Some(rbac) = &rbac {
+            mcp_http_server = mcp_http_server.with_rbac(rbac.clone());
+        }
+        // 認証なしで外部に公開しないよう、起動前に確認する
+        mcp_http_server.check_bind_address(mcp_http_addr)?;
+        tokio::spawn(async move {
+            if let Err(e) = mcp_http_server.serve(mcp_http_addr).await {
+                error!("MCPのHTTPサーバーの起動に失敗しました: {}", e);
+            }
+        });
+    }
+
+    // gRPCとメトリクスサーバーのTLS（証明書を設定した場合のみ）
+    let tls_certificate = TlsConfig::from_env()?.map(TlsCertificate::load).transpose()?;
+
     // サーバーを起動
     info!("サーバーを開始します: {}", addr);
-    run_server(addr, grpc_service).await?;
+    run_server(addr, service, admin_service, shutdown_config, tls_certificate, authenticator, rbac).await?;
     
-    // トレーシングをシャットダウン
+    // 残りのトレースを送信してトレーシングをシャットダウン
     shutdown_tracing();
     
     Ok(())
-} 
\ No newline at end of file
+}
+
+/// キーファイルのAPIキーを作成・失効させる
+fn manage_api_keys(command: ApiKeyCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let keys_file = ApiKeyConfig::from_env()?.keys_file.ok_or("MCP_API_KEYS_FILEを設定してください")?;
+    let api_keys = ApiKeyStore::open(keys_file)?;
+    match command {
+        ApiKeyCommand::Create { user, tenant, roles, scopes, description } => {
+            let user = UserInfo {
+                id: user,
+                tenant_id: tenant,
+                roles,
+                attributes: Default::default(),
+            };
+            let (key, secret) = api_keys.create(&user, scopes, &description)?;
+            // シークレットは再表示できないため標準出力にのみ書く
+            eprintln!("APIキー {} を作成しました", key.id);
+            println!("{}", secret);
+        }
+        ApiKeyCommand::Revoke { key_id } => {
+            api_keys.revoke(&key_id)?;
+            eprintln!("APIキー {} を失効させました", key_id);
+        }
+    }
+    Ok(())
+}
+
+/// 環境変数で設定されたポリシー（MCP_POLICY_DIRなど）でコーパスを評価し、結果を表示する
+async fn bench_policy(
+    corpus: PathBuf,
+    iterations: usize,
+    warmup: usize,
+    max_p99_ms: Option<f64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let inputs = load_corpus(&corpus)?;
+    let evaluator = PolicyEngine::from_env()?.active_evaluator();
+    let report = PolicyBenchmark::new()
+        .with_iterations(iterations)
+        .with_warmup(warmup)
+        .run(&inputs, evaluator.as_ref())
+        .await?;
+    println!("{}", report);
+
+    if let Some(max_p99_ms) = max_p99_ms {
+        let p99 = report.percentile(99.0);
+        if p99.as_secs_f64() * 1000.0 > max_p99_ms {
+            return Err(format!("p99レイテンシ {:.2?} が上限 {}ms を超えています", p99, max_p99_ms).into());
+        }
+    }
+    Ok(())
+}
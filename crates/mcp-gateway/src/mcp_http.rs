@@ -0,0 +1,676 @@
+//! MCP streamable HTTP transport
+//!
+//! Remote MCP clients reach the tools of [`McpToolServer`] with JSON-RPC messages posted to
+//! `/mcp`. A client starts a session with an `initialize` request; the response carries the
+//! session ID in the `Mcp-Session-Id` header, which the client sends with every later message
+//! until it ends the session with `DELETE /mcp`. Sessions unused for longer than the idle
+//! timeout expire, and a client sending an unknown or expired session ID gets `404 Not Found`
+//! so that it starts a new session.
+//!
+//! Requests are answered with a JSON body, or with a server-sent event stream if the client
+//! accepts `text/event-stream`; the stream is kept alive with comments while a tool runs.
+//! Notifications and responses are accepted with `202 Accepted`. The server sends no requests
+//! or notifications of its own, so the stream of `GET /mcp` is not offered.
+//!
+//! Browsers send an `Origin` header, and requests carrying one are rejected unless the origin
+//! is allowed, which guards local deployments against DNS rebinding. The transport is disabled
+//! unless a bind address is configured.
+//!
+//! With an authenticator (see [`crate::auth`]), every request needs an API key or bearer token,
+//! and tools run as the authenticated user. Keys limited by scopes need the scope
+//! `mcp.McpHttp/*` to use the transport. Without one, the transport refuses to listen on
+//! addresses other than loopback. With RBAC rules (see [`crate::rbac`]), tool calls also need
+//! the roles of the RPC backing the tool.
+
+use crate::auth::{self, Authenticator};
+use crate::mcp_proxy::McpProxy;
+use crate::mcp_tools::{error_response, McpToolServer, RpcError, PARSE_ERROR};
+use crate::rbac::RbacConfig;
+use crate::service::McpServiceImpl;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use dashmap::DashMap;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::parse_positive;
+use mcp_policy::models::UserInfo;
+use serde_json::Value;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::info;
+use uuid::Uuid;
+
+/// Header carrying the session ID
+pub const SESSION_ID_HEADER: &str = "mcp-session-id";
+
+/// Header carrying the negotiated MCP revision
+pub const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// RPC path requests to the transport are authenticated for (see [`crate::api_keys`] for scopes)
+pub const TRANSPORT_RPC: &str = "/mcp.McpHttp/Request";
+
+/// Streamable HTTP transport settings
+#[derive(Debug, Clone)]
+pub struct McpHttpConfig {
+    /// Address to listen on (the transport is disabled if unset)
+    pub bind_address: Option<SocketAddr>,
+    /// Sessions unused for this long expire
+    pub session_idle_timeout: Duration,
+    /// Maximum number of open sessions
+    pub max_sessions: usize,
+    /// Origins allowed to send requests
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for McpHttpConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: None,
+            session_idle_timeout: Duration::from_secs(60 * 60),
+            max_sessions: 1024,
+            allowed_origins: Vec::new(),
+        }
+    }
+}
+
+impl McpHttpConfig {
+    /// Build the settings from environment variables
+    ///
+    /// * `MCP_HTTP_BIND_ADDRESS` - address to listen on (`127.0.0.1:8082`)
+    /// * `MCP_HTTP_SESSION_IDLE_SECS` - seconds after which unused sessions expire
+    /// * `MCP_HTTP_MAX_SESSIONS` - maximum number of open sessions
+    /// * `MCP_HTTP_ALLOWED_ORIGINS` - allowed origins (`https://a.example,https://b.example`)
+    pub fn from_env() -> McpResult<Self> {
+        let mut config = Self::default();
+
+        if let Ok(value) = std::env::var("MCP_HTTP_BIND_ADDRESS") {
+            let address = value.trim().parse().map_err(|_| {
+                McpError::InvalidRequest(format!("MCP_HTTP_BIND_ADDRESS must be a socket address: '{}'", value))
+            })?;
+            config.bind_address = Some(address);
+        }
+        if let Ok(value) = std::env::var("MCP_HTTP_SESSION_IDLE_SECS") {
+            config.session_idle_timeout = Duration::from_secs(parse_positive("MCP_HTTP_SESSION_IDLE_SECS", &value)?);
+        }
+        if let Ok(value) = std::env::var("MCP_HTTP_MAX_SESSIONS") {
+            config.max_sessions = parse_positive("MCP_HTTP_MAX_SESSIONS", &value)?;
+        }
+        if let Ok(value) = std::env::var("MCP_HTTP_ALLOWED_ORIGINS") {
+            config.allowed_origins = value
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+
+        Ok(config)
+    }
+}
+
+/// Open session
+#[derive(Debug)]
+struct Session {
+    /// MCP revision negotiated by `initialize`
+    protocol_version: String,
+    /// User that opened the session (empty without authentication)
+    user_id: String,
+    /// Tenant of that user
+    tenant_id: String,
+    last_used: Instant,
+}
+
+impl Session {
+    /// Whether `caller` opened the session
+    fn is_owned_by(&self, caller: Option<&UserInfo>) -> bool {
+        let (user_id, tenant_id) = caller.map_or(("", ""), |caller| (caller.id.as_str(), caller.tenant_id.as_str()));
+        self.user_id == user_id && self.tenant_id == tenant_id
+    }
+}
+
+/// Open sessions, expiring when unused
+#[derive(Debug)]
+struct Sessions {
+    sessions: DashMap<String, Session>,
+    idle_timeout: Duration,
+    max_sessions: usize,
+}
+
+impl Sessions {
+    /// Open a session of `caller`, returning its ID
+    fn open(&self, protocol_version: &str, caller: Option<&UserInfo>) -> McpResult<String> {
+        let now = Instant::now();
+        self.sessions
+            .retain(|_, session| now.duration_since(session.last_used) < self.idle_timeout);
+        if self.sessions.len() >= self.max_sessions {
+            return Err(McpError::ResourceExhausted(format!(
+                "Too many MCP sessions (maximum {})",
+                self.max_sessions
+            )));
+        }
+
+        let session_id = Uuid::new_v4().simple().to_string();
+        self.sessions.insert(
+            session_id.clone(),
+            Session {
+                protocol_version: protocol_version.to_string(),
+                user_id: caller.map(|caller| caller.id.clone()).unwrap_or_default(),
+                tenant_id: caller.map(|caller| caller.tenant_id.clone()).unwrap_or_default(),
+                last_used: now,
+            },
+        );
+        Ok(session_id)
+    }
+
+    /// Mark a session of `caller` as used, returning its negotiated revision
+    ///
+    /// Fails if the session is unknown, expired or was opened by another user.
+    fn touch(&self, session_id: &str, caller: Option<&UserInfo>) -> Result<String, (StatusCode, String)> {
+        let now = Instant::now();
+        {
+            let Some(mut session) = self.sessions.get_mut(session_id) else {
+                return Err(unknown_session());
+            };
+            if !session.is_owned_by(caller) {
+                return Err(foreign_session());
+            }
+            if now.duration_since(session.last_used) < self.idle_timeout {
+                session.last_used = now;
+                return Ok(session.protocol_version.clone());
+            }
+        }
+        self.sessions.remove(session_id);
+        Err(unknown_session())
+    }
+
+    /// Close a session of `caller`
+    fn close(&self, session_id: &str, caller: Option<&UserInfo>) -> Result<(), (StatusCode, String)> {
+        if self.sessions.remove_if(session_id, |_, session| session.is_owned_by(caller)).is_some() {
+            return Ok(());
+        }
+        match self.sessions.contains_key(session_id) {
+            true => Err(foreign_session()),
+            false => Err(unknown_session()),
+        }
+    }
+}
+
+fn unknown_session() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Unknown or expired session".to_string())
+}
+
+fn foreign_session() -> (StatusCode, String) {
+    (StatusCode::FORBIDDEN, "Session belongs to another user".to_string())
+}
+
+/// MCP server on the streamable HTTP transport
+#[derive(Debug)]
+pub struct McpHttpServer {
+    tools: McpToolServer,
+    sessions: Sessions,
+    allowed_origins: Vec<String>,
+    authenticator: Authenticator,
+}
+
+impl McpHttpServer {
+    pub fn new(service: Arc<McpServiceImpl>, config: McpHttpConfig) -> Self {
+        Self {
+            tools: McpToolServer::new(service),
+            sessions: Sessions {
+                sessions: DashMap::new(),
+                idle_timeout: config.session_idle_timeout,
+                max_sessions: config.max_sessions,
+            },
+            allowed_origins: config.allowed_origins,
+            authenticator: Authenticator::new(),
+        }
+    }
+
+    /// Require the credentials `authenticator` accepts and run tools as the authenticated user
+    pub fn with_authenticator(mut self, authenticator: Authenticator) -> Self {
+        self.authenticator = authenticator;
+        self
+    }
+
+    /// Also offer the tools of upstream MCP servers
+    pub fn with_proxy(mut self, proxy: Arc<McpProxy>) -> Self {
+        self.tools = self.tools.with_proxy(proxy);
+        self
+    }
+
+    /// Authorize tool calls with the rules of `rbac`
+    pub fn with_rbac(mut self, rbac: Arc<RbacConfig>) -> Self {
+        self.tools = self.tools.with_rbac(rbac);
+        self
+    }
+
+    /// Routes of the transport
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/mcp", post(handle_post).get(handle_get).delete(handle_delete))
+            .with_state(Arc::new(self))
+    }
+
+    /// Check that the transport may listen on `addr` (only loopback unless requests are authenticated)
+    pub fn check_bind_address(&self, addr: SocketAddr) -> McpResult<()> {
+        if addr.ip().is_loopback() || self.authenticator.is_enabled() {
+            return Ok(());
+        }
+        Err(McpError::InvalidRequest(format!(
+            "The MCP HTTP transport only listens on {} with API keys or OIDC tokens configured",
+            addr
+        )))
+    }
+
+    /// Serve clients on `addr` (see [`Self::check_bind_address`])
+    pub async fn serve(self, addr: SocketAddr) -> McpResult<()> {
+        self.check_bind_address(addr)?;
+        info!("Serving MCP over streamable HTTP on {}", addr);
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        axum::serve(listener, self.router()).await?;
+        Ok(())
+    }
+
+    /// Reject requests from origins that are not allowed, and authenticate the others
+    ///
+    /// Returns the user the request is handled as.
+    async fn check_request(&self, headers: &HeaderMap) -> Result<Option<UserInfo>, (StatusCode, String)> {
+        self.check_origin(headers)?;
+        self.authenticator.authenticate_http(TRANSPORT_RPC, headers).await
+    }
+
+    /// Reject requests from origins that are not allowed
+    fn check_origin(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        let Some(origin) = headers.get(header::ORIGIN) else {
+            return Ok(());
+        };
+        if origin.to_str().is_ok_and(|origin| self.allowed_origins.iter().any(|allowed| allowed == origin)) {
+            return Ok(());
+        }
+        Err((StatusCode::FORBIDDEN, "Origin is not allowed".to_string()))
+    }
+
+    /// Check the session of a request after `initialize` (only the caller that opened it may use it)
+    fn check_session(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        let Some(session_id) = headers.get(SESSION_ID_HEADER).and_then(|value| value.to_str().ok()) else {
+            return Err((StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header".to_string()));
+        };
+        let protocol_version = self.sessions.touch(session_id, auth::current_user().as_ref())?;
+        match headers.get(PROTOCOL_VERSION_HEADER).map(HeaderValue::to_str) {
+            None => Ok(()),
+            Some(Ok(version)) if version == protocol_version => Ok(()),
+            Some(_) => Err((
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported MCP-Protocol-Version (the session uses {})", protocol_version),
+            )),
+        }
+    }
+}
+
+/// `POST /mcp`: a message from the client
+async fn handle_post(State(server): State<Arc<McpHttpServer>>, headers: HeaderMap, body: String) -> Response {
+    let caller = match server.check_request(&headers).await {
+        Ok(caller) => caller,
+        Err(rejection) => return rejection.into_response(),
+    };
+    auth::scope(caller, handle_message(server, headers, body)).await
+}
+
+/// Handle a message as the caller of the request
+async fn handle_message(server: Arc<McpHttpServer>, headers: HeaderMap, body: String) -> Response {
+    let message: Value = match serde_json::from_str(&body) {
+        Ok(message) => message,
+        Err(e) => {
+            let error = error_response(Value::Null, RpcError::new(PARSE_ERROR, e.to_string()));
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    };
+
+    let method = message.get("method").and_then(Value::as_str);
+    if method == Some("initialize") {
+        return initialize(&server, &message).await;
+    }
+    if let Err(rejection) = server.check_session(&headers) {
+        return rejection.into_response();
+    }
+
+    // Notifications and responses
+    if method.is_none() || message.get("id").is_none() {
+        return match server.tools.handle(&message).await {
+            Some(error) => (StatusCode::BAD_REQUEST, Json(error)).into_response(),
+            None => StatusCode::ACCEPTED.into_response(),
+        };
+    }
+
+    let accepts_stream = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+    if !accepts_stream {
+        return match server.tools.handle(&message).await {
+            Some(response) => Json(response).into_response(),
+            None => StatusCode::ACCEPTED.into_response(),
+        };
+    }
+
+    // The response is sent as an event once the request has been handled
+    let (sender, receiver) = mpsc::channel::<Result<Event, Infallible>>(1);
+    let tools = server.tools.clone();
+    // The spawned task does not inherit the caller
+    let caller = auth::current_user();
+    tokio::spawn(auth::scope(caller, async move {
+        if let Some(response) = tools.handle(&message).await {
+            let _ = sender.send(Ok(Event::default().data(response.to_string()))).await;
+        }
+    }));
+    Sse::new(ReceiverStream::new(receiver)).keep_alive(KeepAlive::default()).into_response()
+}
+
+/// Start a session with an `initialize` request
+async fn initialize(server: &McpHttpServer, message: &Value) -> Response {
+    let Some(response) = server.tools.handle(message).await else {
+        return StatusCode::ACCEPTED.into_response();
+    };
+    let Some(protocol_version) = response["result"]["protocolVersion"].as_str() else {
+        return (StatusCode::BAD_REQUEST, Json(response)).into_response();
+    };
+    match server.sessions.open(protocol_version, auth::current_user().as_ref()) {
+        Ok(session_id) => ([(SESSION_ID_HEADER, session_id)], Json(response)).into_response(),
+        Err(e) => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response(),
+    }
+}
+
+/// `GET /mcp`: the server sends no messages of its own
+async fn handle_get(State(server): State<Arc<McpHttpServer>>, headers: HeaderMap) -> Response {
+    if let Err(rejection) = server.check_request(&headers).await {
+        return rejection.into_response();
+    }
+    (StatusCode::METHOD_NOT_ALLOWED, [(header::ALLOW, "POST, DELETE")]).into_response()
+}
+
+/// `DELETE /mcp`: end a session of the caller
+async fn handle_delete(State(server): State<Arc<McpHttpServer>>, headers: HeaderMap) -> Response {
+    let caller = match server.check_request(&headers).await {
+        Ok(caller) => caller,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let Some(session_id) = headers.get(SESSION_ID_HEADER).and_then(|value| value.to_str().ok()) else {
+        return (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header").into_response();
+    };
+    match server.sessions.close(session_id, caller.as_ref()) {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_keys::{ApiKeyStore, API_KEY_HEADER};
+    use axum::body::Body;
+    use axum::http::Request;
+    use mcp_policy::PolicyEngine;
+    use mcp_sandbox::CommandExecutor;
+    use serde_json::json;
+    use std::time::SystemTime;
+    use tower::ServiceExt;
+
+    fn router(config: McpHttpConfig) -> Router {
+        let service = McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now());
+        McpHttpServer::new(Arc::new(service), config).router()
+    }
+
+    async fn send(router: &Router, method: &str, headers: &[(&str, &str)], body: Option<Value>) -> Response {
+        let mut request = Request::builder().method(method).uri("/mcp");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+        router.clone().oneshot(request.body(body).unwrap()).await.unwrap()
+    }
+
+    async fn body(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    fn initialize() -> Value {
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "protocolVersion": "2025-03-26" } })
+    }
+
+    // Test for the session lifecycle
+    #[tokio::test]
+    async fn test_session() {
+        let router = router(McpHttpConfig::default());
+        let ping = json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" });
+
+        let response = send(&router, "POST", &[], Some(initialize())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let session_id = response.headers()[SESSION_ID_HEADER].to_str().unwrap().to_string();
+        let result: Value = serde_json::from_str(&body(response).await).unwrap();
+        assert_eq!(result["result"]["protocolVersion"], "2025-03-26");
+        let session = [(SESSION_ID_HEADER, session_id.as_str())];
+
+        // Requests need the session
+        let response = send(&router, "POST", &[], Some(ping.clone())).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = send(&router, "POST", &[(SESSION_ID_HEADER, "unknown")], Some(ping.clone())).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        let response = send(&router, "POST", &session, Some(notification)).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let response = send(&router, "POST", &session, Some(ping.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(serde_json::from_str::<Value>(&body(response).await).unwrap()["id"], 2);
+
+        // The revision must match the negotiated one
+        let headers = [session[0], (PROTOCOL_VERSION_HEADER, "2024-11-05")];
+        let response = send(&router, "POST", &headers, Some(ping.clone())).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        assert_eq!(send(&router, "GET", &session, None).await.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(send(&router, "DELETE", &session, None).await.status(), StatusCode::NO_CONTENT);
+        let response = send(&router, "POST", &session, Some(ping)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(send(&router, "DELETE", &session, None).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    // Test for answering requests with an event stream
+    #[tokio::test]
+    async fn test_event_stream() {
+        let router = router(McpHttpConfig::default());
+        let response = send(&router, "POST", &[], Some(initialize())).await;
+        let session_id = response.headers()[SESSION_ID_HEADER].to_str().unwrap().to_string();
+
+        let headers = [
+            (SESSION_ID_HEADER, session_id.as_str()),
+            ("accept", "application/json, text/event-stream"),
+        ];
+        let request = json!({ "jsonrpc": "2.0", "id": 7, "method": "tools/list" });
+        let response = send(&router, "POST", &headers, Some(request)).await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/event-stream");
+        let body = body(response).await;
+        let data = body.lines().find_map(|line| line.strip_prefix("data: ")).unwrap();
+        let response: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"]["tools"].as_array().unwrap().len(), 4);
+    }
+
+    // Test for the origin check and the session limits
+    #[tokio::test]
+    async fn test_origin_and_limits() {
+        let router = router(McpHttpConfig {
+            max_sessions: 1,
+            allowed_origins: vec!["https://allowed.example".to_string()],
+            ..Default::default()
+        });
+
+        let origin = |origin| [("origin", origin)];
+        let response = send(&router, "POST", &origin("https://evil.example"), Some(initialize())).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send(&router, "POST", &origin("https://allowed.example"), Some(initialize())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(&router, "POST", &[], Some(initialize())).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    // Test for authenticating requests
+    #[tokio::test]
+    async fn test_authentication() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ApiKeyStore::open(dir.path().join("api_keys.json")).unwrap());
+        let user = UserInfo {
+            id: "alice".to_string(),
+            tenant_id: "tenant-a".to_string(),
+            ..Default::default()
+        };
+        let (_, secret) = store.create(&user, vec![], "").unwrap();
+        let (_, transport) = store.create(&user, vec!["mcp.McpHttp/*".to_string()], "").unwrap();
+        let (_, grpc_only) = store.create(&user, vec!["mcp.McpService/*".to_string()], "").unwrap();
+        let service = McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now());
+        let server = McpHttpServer::new(Arc::new(service), McpHttpConfig::default())
+            .with_authenticator(Authenticator::new().with_api_keys(store));
+        let router = server.router();
+
+        let expected = [
+            (None, StatusCode::UNAUTHORIZED),
+            (Some("mcp_wrong"), StatusCode::UNAUTHORIZED),
+            // Keys limited to gRPC methods cannot use the transport
+            (Some(grpc_only.as_str()), StatusCode::FORBIDDEN),
+            (Some(transport.as_str()), StatusCode::OK),
+            (Some(secret.as_str()), StatusCode::OK),
+        ];
+        for (secret, status) in expected {
+            let headers: Vec<_> = secret.map(|secret| (API_KEY_HEADER, secret)).into_iter().collect();
+            let response = send(&router, "POST", &headers, Some(initialize())).await;
+            assert_eq!(response.status(), status, "{:?}", secret);
+        }
+    }
+
+    // Test for authorizing tool calls with the roles of the caller
+    #[tokio::test]
+    async fn test_authorization() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ApiKeyStore::open(dir.path().join("api_keys.json")).unwrap());
+        let user = |role: &str| UserInfo {
+            id: "alice".to_string(),
+            tenant_id: "tenant-a".to_string(),
+            roles: vec![role.to_string()],
+            ..Default::default()
+        };
+        let (_, developer) = store.create(&user("developer"), vec![], "").unwrap();
+        let (_, viewer) = store.create(&user("viewer"), vec![], "").unwrap();
+        let rbac: RbacConfig = serde_json::from_value(json!({
+            "rules": [
+                {"methods": ["ExecuteCommand", "WriteFile"], "roles": ["developer"]},
+                {"methods": ["mcp.McpService/*"], "roles": ["developer", "viewer"]},
+            ],
+        }))
+        .unwrap();
+        let service = McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now());
+        let router = McpHttpServer::new(Arc::new(service), McpHttpConfig::default())
+            .with_authenticator(Authenticator::new().with_api_keys(store))
+            .with_rbac(Arc::new(rbac))
+            .router();
+
+        let call = |secret: &str, name: &str, arguments: Value| {
+            let router = router.clone();
+            let secret = secret.to_string();
+            let message = json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "tools/call",
+                "params": { "name": name, "arguments": arguments },
+            });
+            async move {
+                let response = send(&router, "POST", &[(API_KEY_HEADER, &secret)], Some(initialize())).await;
+                let session_id = response.headers()[SESSION_ID_HEADER].to_str().unwrap().to_string();
+                let headers = [(API_KEY_HEADER, secret.as_str()), (SESSION_ID_HEADER, session_id.as_str())];
+                let response = send(&router, "POST", &headers, Some(message)).await;
+                let result: Value = serde_json::from_str(&body(response).await).unwrap();
+                result["result"]["content"][0]["text"].as_str().unwrap().to_string()
+            }
+        };
+
+        let denied = "alice may not call /mcp.McpService/ExecuteCommand";
+        let text = call(&viewer, "execute_command", json!({ "command": "echo" })).await;
+        assert!(text.contains(denied), "{}", text);
+        let text = call(&viewer, "write_file", json!({ "path": "a.txt", "content": "" })).await;
+        assert!(text.contains("may not call /mcp.McpService/WriteFile"), "{}", text);
+        let text = call(&viewer, "list_directory", json!({})).await;
+        assert!(!text.contains("may not call"), "{}", text);
+        let text = call(&developer, "execute_command", json!({ "command": "echo" })).await;
+        assert!(!text.contains(denied), "{}", text);
+    }
+
+    // Test for binding sessions to the user that opened them
+    #[tokio::test]
+    async fn test_session_owner() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ApiKeyStore::open(dir.path().join("api_keys.json")).unwrap());
+        let user = |id: &str, tenant_id: &str| UserInfo {
+            id: id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            ..Default::default()
+        };
+        let (_, alice) = store.create(&user("alice", "tenant-a"), vec![], "").unwrap();
+        let (_, bob) = store.create(&user("bob", "tenant-a"), vec![], "").unwrap();
+        let (_, other_tenant) = store.create(&user("alice", "tenant-b"), vec![], "").unwrap();
+        let service = McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now());
+        let router = McpHttpServer::new(Arc::new(service), McpHttpConfig::default())
+            .with_authenticator(Authenticator::new().with_api_keys(store))
+            .router();
+
+        let response = send(&router, "POST", &[(API_KEY_HEADER, &alice)], Some(initialize())).await;
+        let session_id = response.headers()[SESSION_ID_HEADER].to_str().unwrap().to_string();
+        let ping = json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" });
+
+        // Other users, also of the same user ID in another tenant, can neither use nor end the session
+        for secret in [&bob, &other_tenant] {
+            let headers = [(API_KEY_HEADER, secret.as_str()), (SESSION_ID_HEADER, session_id.as_str())];
+            assert_eq!(send(&router, "POST", &headers, Some(ping.clone())).await.status(), StatusCode::FORBIDDEN);
+            assert_eq!(send(&router, "DELETE", &headers, None).await.status(), StatusCode::FORBIDDEN);
+        }
+        let headers = [(API_KEY_HEADER, alice.as_str()), (SESSION_ID_HEADER, session_id.as_str())];
+        assert_eq!(send(&router, "POST", &headers, Some(ping)).await.status(), StatusCode::OK);
+        assert_eq!(send(&router, "DELETE", &headers, None).await.status(), StatusCode::NO_CONTENT);
+    }
+
+    // Without authentication, the transport only listens on loopback
+    #[tokio::test]
+    async fn test_unauthenticated_bind() {
+        let service = McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now());
+        let server = McpHttpServer::new(Arc::new(service), McpHttpConfig::default());
+        assert!(server.check_bind_address("127.0.0.1:8082".parse().unwrap()).is_ok());
+        assert!(server.check_bind_address("[::1]:8082".parse().unwrap()).is_ok());
+        let error = server.serve("0.0.0.0:0".parse().unwrap()).await.unwrap_err();
+        assert!(matches!(error, McpError::InvalidRequest(_)), "{:?}", error);
+    }
+
+    // Test for expiring unused sessions
+    #[test]
+    fn test_session_expiry() {
+        let sessions = Sessions {
+            sessions: DashMap::new(),
+            idle_timeout: Duration::from_millis(20),
+            max_sessions: 1,
+        };
+        let session_id = sessions.open("2025-06-18", None).unwrap();
+        assert_eq!(sessions.touch(&session_id, None).unwrap(), "2025-06-18");
+        assert!(sessions.open("2025-06-18", None).is_err());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(sessions.touch(&session_id, None).unwrap_err().0, StatusCode::NOT_FOUND);
+        assert!(sessions.open("2025-06-18", None).is_ok());
+    }
+}
@@ -0,0 +1,639 @@
+//! MCP proxy for upstream servers
+//!
+//! Puts other MCP servers behind the policies of the gateway. The tools of the upstream servers
+//! configured in `MCP_PROXY_CONFIG` are offered by the stdio and HTTP transports as
+//! `<server>__<tool>`, and every call is checked with `PolicyEngine::check_tool_call`, which
+//! audits the decision, before it is forwarded. Denied calls never reach the upstream server.
+//!
+//! The configuration uses the `mcpServers` format of MCP clients. Servers with a `command` are
+//! launched as subprocesses speaking MCP on stdin and stdout; servers with a `url` are reached
+//! over streamable HTTP:
+//!
+//! ```json
+//! {
+//!   "mcpServers": {
+//!     "github": { "command": "github-mcp-server", "args": ["stdio"], "env": { "GITHUB_TOKEN": "..." } },
+//!     "search": { "url": "https://search.example.com/mcp", "headers": { "Authorization": "Bearer ..." } }
+//!   }
+//! }
+//! ```
+//!
+//! Tool rules are matched against `server/tool` (see `mcp_policy::ToolRules`), and no tool can
+//! be called unless the policy allows it. Upstream servers that fail to start are left out, and
+//! requests of upstream servers to the gateway (sampling, roots) are declined.
+
+use crate::mcp_http::{PROTOCOL_VERSION_HEADER, SESSION_ID_HEADER};
+use crate::mcp_tools::{error_response, RpcError, METHOD_NOT_FOUND, PROTOCOL_VERSION};
+use crate::metrics;
+use crate::service::McpServiceImpl;
+use dashmap::DashMap;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::parse_positive;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::oneshot;
+use tracing::{debug, error, info, warn};
+
+/// Separator of the server and tool names of a proxied tool
+pub const TOOL_NAME_SEPARATOR: &str = "__";
+
+/// Upstream MCP server
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum UpstreamConfig {
+    /// Server launched as a subprocess speaking MCP on stdin and stdout
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: HashMap<String, String>,
+    },
+    /// Server reached over streamable HTTP
+    Http {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+}
+
+/// Proxy settings
+#[derive(Debug, Clone)]
+pub struct McpProxyConfig {
+    /// Upstream servers by name
+    pub servers: BTreeMap<String, UpstreamConfig>,
+    /// Time an upstream server has to answer a request
+    pub request_timeout: Duration,
+}
+
+impl Default for McpProxyConfig {
+    fn default() -> Self {
+        Self {
+            servers: BTreeMap::new(),
+            request_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Configuration file in the `mcpServers` format
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProxyFile {
+    mcp_servers: BTreeMap<String, UpstreamConfig>,
+}
+
+impl McpProxyConfig {
+    /// Build the settings from environment variables
+    ///
+    /// * `MCP_PROXY_CONFIG` - JSON file listing the upstream servers (no proxy if unset)
+    /// * `MCP_PROXY_TIMEOUT_SECS` - seconds an upstream server has to answer a request
+    pub fn from_env() -> McpResult<Self> {
+        let mut config = match std::env::var("MCP_PROXY_CONFIG") {
+            Ok(path) if !path.trim().is_empty() => Self::from_file(path.trim())?,
+            _ => Self::default(),
+        };
+        if let Ok(value) = std::env::var("MCP_PROXY_TIMEOUT_SECS") {
+            config.request_timeout = Duration::from_secs(parse_positive("MCP_PROXY_TIMEOUT_SECS", &value)?);
+        }
+        Ok(config)
+    }
+
+    /// Load the upstream servers from a JSON file in the `mcpServers` format
+    pub fn from_file(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| McpError::Internal(format!("Failed to read proxy configuration {}: {}", path.display(), e)))?;
+        let file: ProxyFile = serde_json::from_str(&content).map_err(|e| {
+            McpError::InvalidRequest(format!("Invalid proxy configuration {}: {}", path.display(), e))
+        })?;
+
+        // The separator would make the names of proxied tools ambiguous
+        if let Some(name) = file.mcp_servers.keys().find(|name| name.is_empty() || name.contains(TOOL_NAME_SEPARATOR)) {
+            return Err(McpError::InvalidRequest(format!(
+                "Invalid upstream server name '{}' in {}: names may not be empty or contain '{}'",
+                name,
+                path.display(),
+                TOOL_NAME_SEPARATOR
+            )));
+        }
+
+        Ok(Self {
+            servers: file.mcp_servers,
+            ..Self::default()
+        })
+    }
+}
+
+/// Subprocess speaking MCP on stdin and stdout
+#[derive(Debug)]
+struct StdioTransport {
+    // Killed when the proxy is dropped
+    _child: Child,
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    // Requests waiting for their response, by ID
+    pending: Arc<DashMap<u64, oneshot::Sender<Value>>>,
+}
+
+impl StdioTransport {
+    fn spawn(name: &str, command: &str, args: &[String], env: &HashMap<String, String>) -> McpResult<Self> {
+        let mut child = Command::new(command)
+            .args(args)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| McpError::ExternalService(format!("Failed to start '{}': {}", command, e)))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(McpError::Internal(format!("Pipes of '{}' are not available", command)));
+        };
+
+        let transport = Self {
+            _child: child,
+            stdin: Arc::new(tokio::sync::Mutex::new(stdin)),
+            pending: Arc::new(DashMap::new()),
+        };
+
+        let name = name.to_string();
+        let stdin = transport.stdin.clone();
+        let pending = transport.pending.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                    warn!("Upstream MCP server '{}' sent an invalid message", name);
+                    continue;
+                };
+                match (message.get("id"), message.get("method")) {
+                    (Some(id), None) => {
+                        if let Some((_, sender)) = id.as_u64().and_then(|id| pending.remove(&id)) {
+                            let _ = sender.send(message);
+                        }
+                    }
+                    (Some(id), Some(method)) => {
+                        debug!("Declining request {} of upstream MCP server '{}'", method, name);
+                        let error = RpcError::new(METHOD_NOT_FOUND, format!("Method not found: {}", method));
+                        let _ = write_line(&stdin, &error_response(id.clone(), error)).await;
+                    }
+                    _ => debug!("Notification of upstream MCP server '{}': {}", name, message["method"]),
+                }
+            }
+            warn!("Upstream MCP server '{}' closed its output", name);
+            // Fail the requests still waiting
+            pending.clear();
+        });
+
+        Ok(transport)
+    }
+
+    async fn request(&self, id: u64, message: &Value, timeout: Duration) -> McpResult<Value> {
+        let (sender, receiver) = oneshot::channel();
+        self.pending.insert(id, sender);
+        let response = async {
+            write_line(&self.stdin, message).await?;
+            receiver
+                .await
+                .map_err(|_| McpError::ExternalService("Upstream MCP server exited".to_string()))
+        };
+        let response = tokio::time::timeout(timeout, response).await;
+        self.pending.remove(&id);
+        response.map_err(|_| McpError::Temporary(format!("Upstream MCP server did not answer within {:?}", timeout)))?
+    }
+
+    async fn notify(&self, message: &Value) -> McpResult<()> {
+        write_line(&self.stdin, message).await
+    }
+}
+
+async fn write_line(stdin: &tokio::sync::Mutex<ChildStdin>, message: &Value) -> McpResult<()> {
+    let mut line = message.to_string();
+    line.push('\n');
+    let mut stdin = stdin.lock().await;
+    stdin.write_all(line.as_bytes()).await?;
+    stdin.flush().await?;
+    Ok(())
+}
+
+/// Server reached over streamable HTTP
+#[derive(Debug)]
+struct HttpTransport {
+    agent: ureq::Agent,
+    url: String,
+    headers: HashMap<String, String>,
+    // Session ID and MCP revision assigned by the server on initialization
+    session_id: Mutex<Option<String>>,
+    protocol_version: Mutex<Option<String>>,
+}
+
+impl HttpTransport {
+    fn new(url: &str, headers: &HashMap<String, String>) -> Self {
+        Self {
+            agent: ureq::Agent::new(),
+            url: url.to_string(),
+            headers: headers.clone(),
+            session_id: Mutex::new(None),
+            protocol_version: Mutex::new(None),
+        }
+    }
+
+    // Post a message, returning the response to the request with the given ID
+    async fn post(&self, message: &Value, id: Option<u64>, timeout: Duration) -> McpResult<Option<Value>> {
+        let mut request = self
+            .agent
+            .post(&self.url)
+            .timeout(timeout)
+            .set("Content-Type", "application/json")
+            .set("Accept", "application/json, text/event-stream");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        if let Some(session_id) = self.session_id.lock().unwrap().as_deref() {
+            request = request.set(SESSION_ID_HEADER, session_id);
+        }
+        if let Some(protocol_version) = self.protocol_version.lock().unwrap().as_deref() {
+            request = request.set(PROTOCOL_VERSION_HEADER, protocol_version);
+        }
+
+        let body = message.to_string();
+        let url = self.url.clone();
+        let response = tokio::task::spawn_blocking(move || {
+            let response = match request.send_string(&body) {
+                Ok(response) => response,
+                Err(ureq::Error::Status(status, response)) => {
+                    let text = response.into_string().unwrap_or_default();
+                    return Err(McpError::ExternalService(format!("{} answered {}: {}", url, status, text.trim())));
+                }
+                Err(e) => return Err(McpError::ExternalService(format!("Failed to reach {}: {}", url, e))),
+            };
+            let session_id = response.header(SESSION_ID_HEADER).map(str::to_string);
+            let message = match id {
+                None => None,
+                Some(id) if response.content_type() == "text/event-stream" => {
+                    Some(read_event_stream(std::io::BufReader::new(response.into_reader()), id)?)
+                }
+                Some(_) => Some(
+                    serde_json::from_reader(response.into_reader())
+                        .map_err(|e| McpError::ExternalService(format!("Invalid response from {}: {}", url, e)))?,
+                ),
+            };
+            Ok((session_id, message))
+        })
+        .await
+        .map_err(|e| McpError::Internal(format!("HTTP request task failed: {}", e)))?;
+
+        let (session_id, message) = response?;
+        if session_id.is_some() {
+            *self.session_id.lock().unwrap() = session_id;
+        }
+        Ok(message)
+    }
+}
+
+/// Response to the request with the given ID in a server-sent event stream
+fn read_event_stream(reader: impl BufRead, id: u64) -> McpResult<Value> {
+    let mut data = String::new();
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(value) = line.strip_prefix("data:") {
+            data.push_str(value.strip_prefix(' ').unwrap_or(value));
+            data.push('\n');
+            continue;
+        }
+        // A blank line ends the event
+        if !line.is_empty() || data.is_empty() {
+            continue;
+        }
+        let message = serde_json::from_str::<Value>(&data).ok();
+        data.clear();
+        if let Some(message) = message.filter(|message| message["id"] == id && message.get("method").is_none()) {
+            return Ok(message);
+        }
+    }
+    Err(McpError::ExternalService("Event stream ended without a response".to_string()))
+}
+
+#[derive(Debug)]
+enum Transport {
+    Stdio(StdioTransport),
+    Http(HttpTransport),
+}
+
+/// Connected upstream server
+#[derive(Debug)]
+struct Upstream {
+    name: String,
+    transport: Transport,
+    next_id: AtomicU64,
+    timeout: Duration,
+    tools: Vec<Value>,
+}
+
+impl Upstream {
+    async fn connect(name: &str, config: &UpstreamConfig, timeout: Duration) -> McpResult<Self> {
+        let transport = match config {
+            UpstreamConfig::Stdio { command, args, env } => {
+                Transport::Stdio(StdioTransport::spawn(name, command, args, env)?)
+            }
+            UpstreamConfig::Http { url, headers } => Transport::Http(HttpTransport::new(url, headers)),
+        };
+        let mut upstream = Self {
+            name: name.to_string(),
+            transport,
+            next_id: AtomicU64::new(1),
+            timeout,
+            tools: Vec::new(),
+        };
+
+        let params = json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "mcp-security-gateway", "version": env!("CARGO_PKG_VERSION") },
+        });
+        let result = upstream.request("initialize", params).await?;
+        if let (Transport::Http(http), Some(version)) = (&upstream.transport, result["protocolVersion"].as_str()) {
+            *http.protocol_version.lock().unwrap() = Some(version.to_string());
+        }
+        upstream.notify("notifications/initialized").await?;
+
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = cursor.map_or_else(|| json!({}), |cursor| json!({ "cursor": cursor }));
+            let result = upstream.request("tools/list", params).await?;
+            upstream.tools.extend(result["tools"].as_array().into_iter().flatten().cloned());
+            match result["nextCursor"].as_str() {
+                Some(next) => cursor = Some(next.to_string()),
+                None => break,
+            }
+        }
+        Ok(upstream)
+    }
+
+    // Send a request, returning its result
+    async fn request(&self, method: &str, params: Value) -> McpResult<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let response = match &self.transport {
+            Transport::Stdio(stdio) => stdio.request(id, &message, self.timeout).await?,
+            Transport::Http(http) => http
+                .post(&message, Some(id), self.timeout)
+                .await?
+                .ok_or_else(|| McpError::ExternalService("No response".to_string()))?,
+        };
+
+        if let Some(error) = response.get("error") {
+            return Err(McpError::ExternalService(format!(
+                "Upstream MCP server '{}' failed {}: {}",
+                self.name, method, error["message"]
+            )));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    async fn notify(&self, method: &str) -> McpResult<()> {
+        let message = json!({ "jsonrpc": "2.0", "method": method });
+        match &self.transport {
+            Transport::Stdio(stdio) => stdio.notify(&message).await,
+            Transport::Http(http) => http.post(&message, None, self.timeout).await.map(|_| ()),
+        }
+    }
+}
+
+/// Proxy for the tools of upstream MCP servers
+#[derive(Debug, Default)]
+pub struct McpProxy {
+    upstreams: BTreeMap<String, Upstream>,
+}
+
+impl McpProxy {
+    /// Connect to the configured upstream servers and list their tools
+    ///
+    /// Servers that fail to start or to initialize are logged and left out.
+    pub async fn connect(config: &McpProxyConfig) -> Self {
+        let mut upstreams = BTreeMap::new();
+        for (name, upstream_config) in &config.servers {
+            match Upstream::connect(name, upstream_config, config.request_timeout).await {
+                Ok(upstream) => {
+                    info!("Proxying {} tools of upstream MCP server '{}'", upstream.tools.len(), name);
+                    upstreams.insert(name.clone(), upstream);
+                }
+                Err(e) => error!("Failed to connect to upstream MCP server '{}': {}", name, e),
+            }
+        }
+        Self { upstreams }
+    }
+
+    /// Names of the connected upstream servers
+    pub fn servers(&self) -> Vec<&str> {
+        self.upstreams.keys().map(String::as_str).collect()
+    }
+
+    /// Definitions of the proxied tools, named `<server>__<tool>`
+    pub fn tools(&self) -> Vec<Value> {
+        self.upstreams
+            .values()
+            .flat_map(|upstream| {
+                upstream.tools.iter().filter_map(|tool| {
+                    let name = tool["name"].as_str()?;
+                    let mut tool = tool.clone();
+                    tool["name"] = json!(format!("{}{}{}", upstream.name, TOOL_NAME_SEPARATOR, name));
+                    Some(tool)
+                })
+            })
+            .collect()
+    }
+
+    /// Call a proxied tool once the policy allows it, returning the result of the tool call
+    ///
+    /// Returns `None` if the tool is not a proxied tool. Denials and failures to reach the
+    /// upstream server are tool results with `isError` set, like failures of the tool itself.
+    pub async fn call_tool(&self, service: &McpServiceImpl, name: &str, arguments: &Value) -> Option<Value> {
+        let (server, tool) = name.split_once(TOOL_NAME_SEPARATOR)?;
+        let upstream = self.upstreams.get(server)?;
+        let start_time = Instant::now();
+
+        if let Err(e) = service.check_tool_call(server, tool, arguments).await {
+            metrics::observe_proxy_tool_call(server, "denied", start_time);
+            return Some(error_result(&e));
+        }
+
+        let params = json!({ "name": tool, "arguments": arguments });
+        let (result, outcome) = match upstream.request("tools/call", params).await {
+            Ok(result) if result["isError"] == true => (result, "error"),
+            Ok(result) => (result, "success"),
+            Err(e) => {
+                warn!("Proxied call of tool '{}' failed: {}", name, e);
+                (error_result(&e), "failed")
+            }
+        };
+        metrics::observe_proxy_tool_call(server, outcome, start_time);
+        Some(result)
+    }
+}
+
+fn error_result(error: &McpError) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": format!("Error: {}", error) }],
+        "isError": true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp_http::{McpHttpConfig, McpHttpServer};
+    use mcp_policy::{ArgPattern, PolicyEngine, RuleBasedEvaluator, RuleConfig};
+    use mcp_sandbox::CommandExecutor;
+    use std::time::SystemTime;
+
+    // Upstream server answering initialize, tools/list (in two pages) and tools/call
+    const UPSTREAM_SCRIPT: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  [ -z "$id" ] && continue
+  case "$line" in
+    *'"initialize"'*) result='{"protocolVersion":"2025-06-18","capabilities":{"tools":{}}}' ;;
+    *'"cursor":"2"'*) result='{"tools":[{"name":"delete","inputSchema":{"type":"object"}}]}' ;;
+    *'"tools/list"'*) result='{"tools":[{"name":"echo","inputSchema":{"type":"object"}}],"nextCursor":"2"}' ;;
+    *'"tools/call"'*) result='{"content":[{"type":"text","text":"echoed"}],"isError":false}' ;;
+  esac
+  printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$id" "$result"
+done
+"#;
+
+    fn service(allowed_tools: &[&str]) -> McpServiceImpl {
+        let mut config = RuleConfig::default();
+        config.tools.allow = allowed_tools.iter().map(|tool| ArgPattern::parse(tool).unwrap()).collect();
+        let engine = PolicyEngine::with_evaluator(RuleBasedEvaluator::new(config));
+        McpServiceImpl::new(engine, CommandExecutor::new(), SystemTime::now())
+    }
+
+    fn text(result: &Value) -> &str {
+        result["content"][0]["text"].as_str().unwrap()
+    }
+
+    // Test for loading the upstream servers
+    #[test]
+    fn test_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("proxy.json");
+        std::fs::write(
+            &path,
+            r#"{"mcpServers": {
+                "github": {"command": "github-mcp-server", "args": ["stdio"]},
+                "search": {"url": "https://search.example.com/mcp", "headers": {"Authorization": "Bearer x"}}
+            }}"#,
+        )
+        .unwrap();
+        let config = McpProxyConfig::from_file(&path).unwrap();
+        assert_eq!(
+            config.servers["github"],
+            UpstreamConfig::Stdio {
+                command: "github-mcp-server".to_string(),
+                args: vec!["stdio".to_string()],
+                env: HashMap::new(),
+            }
+        );
+        assert!(matches!(&config.servers["search"], UpstreamConfig::Http { url, .. } if url.ends_with("/mcp")));
+
+        std::fs::write(&path, r#"{"mcpServers": {"a__b": {"command": "server"}}}"#).unwrap();
+        assert!(McpProxyConfig::from_file(&path).is_err());
+        std::fs::write(&path, r#"{"mcpServers": {"a": {"args": []}}}"#).unwrap();
+        assert!(McpProxyConfig::from_file(&path).is_err());
+    }
+
+    // Test for reading the response from a server-sent event stream
+    #[test]
+    fn test_read_event_stream() {
+        let stream = concat!(
+            ": keep-alive\n\n",
+            "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n",
+            "data: {\"jsonrpc\":\"2.0\",\"id\":3,\n",
+            "data: \"result\":{}}\n\n",
+        );
+        let message = read_event_stream(stream.as_bytes(), 3).unwrap();
+        assert_eq!(message["result"], json!({}));
+        assert!(read_event_stream(stream.as_bytes(), 4).is_err());
+    }
+
+    // Test for proxying the tools of a stdio server
+    #[tokio::test]
+    async fn test_stdio_upstream() {
+        let mut config = McpProxyConfig::default();
+        config.servers.insert(
+            "local".to_string(),
+            UpstreamConfig::Stdio {
+                command: "sh".to_string(),
+                args: vec!["-c".to_string(), UPSTREAM_SCRIPT.to_string()],
+                env: HashMap::new(),
+            },
+        );
+        config.servers.insert(
+            "missing".to_string(),
+            UpstreamConfig::Stdio {
+                command: "/nonexistent/mcp-server".to_string(),
+                args: Vec::new(),
+                env: HashMap::new(),
+            },
+        );
+        let proxy = McpProxy::connect(&config).await;
+        assert_eq!(proxy.servers(), vec!["local"]);
+        let names: Vec<_> = proxy.tools().iter().map(|tool| tool["name"].as_str().unwrap().to_string()).collect();
+        assert_eq!(names, vec!["local__echo", "local__delete"]);
+
+        let service = service(&["local/echo"]);
+        let result = proxy.call_tool(&service, "local__echo", &json!({ "text": "hi" })).await.unwrap();
+        assert_eq!(result["isError"], false);
+        assert_eq!(text(&result), "echoed");
+
+        // Denied calls are not forwarded
+        let result = proxy.call_tool(&service, "local__delete", &json!({})).await.unwrap();
+        assert_eq!(result["isError"], true);
+        assert!(text(&result).contains("denied by policy"));
+
+        assert!(proxy.call_tool(&service, "execute_command", &json!({})).await.is_none());
+        assert!(proxy.call_tool(&service, "other__echo", &json!({})).await.is_none());
+    }
+
+    // Test for proxying the tools of a streamable HTTP server (another gateway)
+    #[tokio::test]
+    async fn test_http_upstream() {
+        let upstream = McpHttpServer::new(Arc::new(service(&[])), McpHttpConfig::default());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, upstream.router()).await });
+
+        let mut config = McpProxyConfig::default();
+        config.servers.insert(
+            "gateway".to_string(),
+            UpstreamConfig::Http {
+                url: format!("http://{}/mcp", addr),
+                headers: HashMap::new(),
+            },
+        );
+        let proxy = McpProxy::connect(&config).await;
+        let names: Vec<_> = proxy.tools().iter().map(|tool| tool["name"].as_str().unwrap().to_string()).collect();
+        assert!(names.contains(&"gateway__list_directory".to_string()));
+
+        let service = service(&["gateway/list_*"]);
+        // Forwarded within the session; the upstream gateway has no tenant file root
+        let result = proxy.call_tool(&service, "gateway__list_directory", &json!({})).await.unwrap();
+        assert_eq!(result["isError"], true);
+        assert!(!text(&result).contains("denied by policy"));
+
+        let result = proxy
+            .call_tool(&service, "gateway__write_file", &json!({ "path": "a", "content": "" }))
+            .await
+            .unwrap();
+        assert!(text(&result).contains("Call of tool 'write_file' of server 'gateway' was denied by policy"));
+    }
+}
@@ -0,0 +1,518 @@
+//! MCP tools of the gateway
+//!
+//! Handles the Model Context Protocol messages (JSON-RPC 2.0) of the stdio and streamable HTTP
+//! transports. The tools are backed by the same [`McpServiceImpl`] as the gRPC service, so
+//! every command and file access is checked by the policy engine and commands run in the
+//! sandbox:
+//!
+//! * `execute_command` - run a command and wait for its result
+//! * `read_file` / `write_file` - read and write a file in the tenant root
+//! * `list_directory` - list a directory in the tenant root
+//!
+//! With a [`McpProxy`] the tools of upstream MCP servers are offered as well.
+//!
+//! With [`RbacConfig`] rules, the caller needs the roles of the RPC backing a tool to call it
+//! (`ExecuteCommand`, `ReadFile`, `WriteFile` and `ListDirectory` of `mcp.McpService`). Tools of
+//! upstream servers are only checked by the policy engine.
+
+use crate::auth;
+use crate::mcp_proxy::McpProxy;
+use crate::proto::{CommandRequest, McpService, ReadFileRequest, TaskResult, TaskStatusRequest, WriteFileRequest};
+use crate::rbac::RbacConfig;
+use crate::service::{is_terminal_status, McpServiceImpl};
+use mcp_common::error::McpResult;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::{Request, Status};
+use tracing::debug;
+
+/// Latest MCP revision implemented by the server
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// MCP revisions the server can negotiate, the latest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: [&str; 3] = [PROTOCOL_VERSION, "2025-03-26", "2024-11-05"];
+
+/// Interval at which a command task is checked for completion
+const TASK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// JSON-RPC error codes
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const INVALID_REQUEST: i64 = -32600;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+
+/// Error response of a JSON-RPC request
+#[derive(Debug)]
+pub(crate) struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    pub(crate) fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Arguments of the `execute_command` tool
+#[derive(Debug, Deserialize)]
+struct ExecuteCommandArgs {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    cwd: Option<String>,
+    #[serde(default)]
+    timeout: u32,
+}
+
+/// Arguments of the `read_file` tool
+#[derive(Debug, Deserialize)]
+struct ReadFileArgs {
+    path: String,
+    #[serde(default)]
+    offset: u64,
+    length: Option<u64>,
+}
+
+/// Arguments of the `write_file` tool
+#[derive(Debug, Deserialize)]
+struct WriteFileArgs {
+    path: String,
+    content: String,
+    #[serde(default)]
+    create_dirs: bool,
+    #[serde(default)]
+    mode: u32,
+}
+
+/// Arguments of the `list_directory` tool
+#[derive(Debug, Deserialize)]
+struct ListDirectoryArgs {
+    path: Option<String>,
+}
+
+/// Handler of MCP messages exposing the gateway service as tools
+#[derive(Debug, Clone)]
+pub struct McpToolServer {
+    service: Arc<McpServiceImpl>,
+    proxy: Option<Arc<McpProxy>>,
+    rbac: Option<Arc<RbacConfig>>,
+}
+
+impl McpToolServer {
+    pub fn new(service: Arc<McpServiceImpl>) -> Self {
+        Self {
+            service,
+            proxy: None,
+            rbac: None,
+        }
+    }
+
+    /// Also offer the tools of upstream MCP servers
+    pub fn with_proxy(mut self, proxy: Arc<McpProxy>) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Authorize tool calls with the rules of `rbac`
+    pub fn with_rbac(mut self, rbac: Arc<RbacConfig>) -> Self {
+        self.rbac = Some(rbac);
+        self
+    }
+
+    /// Handle one serialized message, returning the response unless none is due
+    pub async fn handle_message(&self, message: &str) -> Option<Value> {
+        match serde_json::from_str(message) {
+            Ok(message) => self.handle(&message).await,
+            Err(e) => Some(error_response(Value::Null, RpcError::new(PARSE_ERROR, e.to_string()))),
+        }
+    }
+
+    /// Handle one message, returning the response unless it is a notification or a response
+    pub async fn handle(&self, message: &Value) -> Option<Value> {
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // The server sends no requests, so responses from the client are ignored
+            if message.get("result").is_some() || message.get("error").is_some() {
+                return None;
+            }
+            let error = RpcError::new(INVALID_REQUEST, "Message has no method");
+            return Some(error_response(id.unwrap_or(Value::Null), error));
+        };
+        let Some(id) = id else {
+            debug!("MCP notification: {}", method);
+            return None;
+        };
+        debug!("MCP request: method={}, id={}", method, id);
+
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": negotiate_version(&params),
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "mcp-security-gateway", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": self.tools() })),
+            "tools/call" => self.call_tool(&params).await,
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => error_response(id, error),
+        })
+    }
+
+    fn tools(&self) -> Value {
+        let mut tools = tools();
+        if let (Some(proxy), Some(list)) = (&self.proxy, tools.as_array_mut()) {
+            list.extend(proxy.tools());
+        }
+        tools
+    }
+
+    async fn call_tool(&self, params: &Value) -> Result<Value, RpcError> {
+        let Some(name) = params.get("name").and_then(Value::as_str) else {
+            return Err(RpcError::new(INVALID_PARAMS, "Tool name is missing"));
+        };
+        let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+        if let Err(e) = self.authorize(name) {
+            return Ok(tool_result(format!("Error: {}", e), true));
+        }
+        let output = match name {
+            "execute_command" => self.execute_command(parse_arguments(arguments)?).await,
+            "read_file" => self.read_file(parse_arguments(arguments)?).await,
+            "write_file" => self.write_file(parse_arguments(arguments)?).await,
+            "list_directory" => self.list_directory(parse_arguments(arguments)?).await,
+            _ => {
+                let proxy = self.proxy.as_ref();
+                return match proxy.map(|proxy| proxy.call_tool(&self.service, name, &arguments)) {
+                    Some(call) => call.await,
+                    None => None,
+                }
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Unknown tool: {}", name)));
+            }
+        };
+
+        // Failures of the tool itself (policy violations included) are results the model can see
+        let (text, is_error) = match output {
+            Ok(output) => output,
+            Err(status) => (format!("Error: {}", status.message()), true),
+        };
+        Ok(tool_result(text, is_error))
+    }
+
+    /// Check that the caller may call the RPC backing the tool `name`
+    fn authorize(&self, name: &str) -> McpResult<()> {
+        let Some(rbac) = &self.rbac else {
+            return Ok(());
+        };
+        let path = match name {
+            "execute_command" => "/mcp.McpService/ExecuteCommand",
+            "read_file" => "/mcp.McpService/ReadFile",
+            "write_file" => "/mcp.McpService/WriteFile",
+            "list_directory" => "/mcp.McpService/ListDirectory",
+            _ => return Ok(()),
+        };
+        rbac.authorize(auth::current_user().as_ref(), path)
+    }
+
+    async fn execute_command(&self, args: ExecuteCommandArgs) -> Result<(String, bool), Status> {
+        let request = CommandRequest {
+            command: args.command,
+            args: args.args,
+            env: args.env,
+            cwd: args.cwd,
+            timeout: args.timeout,
+            ..Default::default()
+        };
+        let task_id = self.service.execute_command(Request::new(request)).await?.into_inner().task_id;
+
+        // The command runs as a task; its timeout bounds the wait
+        loop {
+            let request = Request::new(TaskStatusRequest { task_id: task_id.clone() });
+            let status = self.service.get_task_status(request).await?.into_inner();
+            if status.task_info.as_ref().is_some_and(|task| is_terminal_status(task.status)) {
+                let result = status.result.unwrap_or_default();
+                return Ok((command_output(&result), result.exit_code != 0));
+            }
+            tokio::time::sleep(TASK_POLL_INTERVAL).await;
+        }
+    }
+
+    async fn read_file(&self, args: ReadFileArgs) -> Result<(String, bool), Status> {
+        let request = ReadFileRequest {
+            path: args.path,
+            offset: args.offset,
+            length: args.length,
+        };
+        let response = self.service.read_file(Request::new(request)).await?.into_inner();
+        let read = response.content.len() as u64;
+        let Ok(mut text) = String::from_utf8(response.content) else {
+            return Ok((format!("'{}' is not a UTF-8 text file", response.path), true));
+        };
+        if args.offset + read < response.file_size {
+            let _ = write!(
+                text,
+                "\n[{} of {} bytes from offset {}; read the rest with a larger offset]",
+                read, response.file_size, args.offset
+            );
+        }
+        Ok((text, false))
+    }
+
+    async fn write_file(&self, args: WriteFileArgs) -> Result<(String, bool), Status> {
+        let request = WriteFileRequest {
+            path: args.path,
+            content: args.content.into_bytes(),
+            create_dirs: args.create_dirs,
+            mode: args.mode,
+        };
+        let response = self.service.write_file(Request::new(request)).await?.into_inner();
+        Ok((format!("Wrote {} bytes to {}", response.bytes_written, response.path), false))
+    }
+
+    async fn list_directory(&self, args: ListDirectoryArgs) -> Result<(String, bool), Status> {
+        let (path, entries) = self.service.list_directory(args.path.as_deref().unwrap_or(".")).await?;
+        let mut text = format!("{}:", path);
+        if entries.is_empty() {
+            text.push_str("\n(empty)");
+        }
+        for entry in entries {
+            let _ = if entry.is_dir {
+                write!(text, "\n{}/", entry.name)
+            } else {
+                write!(text, "\n{} ({} bytes)", entry.name, entry.size)
+            };
+        }
+        Ok((text, false))
+    }
+}
+
+/// Definitions of the tools of the gateway
+fn tools() -> Value {
+    json!([
+        {
+            "name": "execute_command",
+            "description": "Run a command in the sandbox of the gateway and return its exit code and output. \
+                The command is subject to the security policy.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string", "description": "Command to run" },
+                    "args": { "type": "array", "items": { "type": "string" }, "description": "Arguments" },
+                    "env": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                        "description": "Environment variables",
+                    },
+                    "cwd": { "type": "string", "description": "Working directory" },
+                    "timeout": { "type": "integer", "minimum": 0, "description": "Timeout in seconds" },
+                },
+                "required": ["command"],
+            },
+        },
+        {
+            "name": "read_file",
+            "description": "Read a text file. Relative paths are resolved against the workspace.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "File path" },
+                    "offset": { "type": "integer", "minimum": 0, "description": "Offset of the first byte" },
+                    "length": { "type": "integer", "minimum": 0, "description": "Number of bytes to read" },
+                },
+                "required": ["path"],
+            },
+        },
+        {
+            "name": "write_file",
+            "description": "Write a text file, replacing its content. Relative paths are resolved against \
+                the workspace.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "File path" },
+                    "content": { "type": "string", "description": "New content of the file" },
+                    "create_dirs": { "type": "boolean", "description": "Create missing parent directories" },
+                    "mode": { "type": "integer", "minimum": 0, "description": "Permission bits (e.g. 420 for 0644)" },
+                },
+                "required": ["path", "content"],
+            },
+        },
+        {
+            "name": "list_directory",
+            "description": "List the entries of a directory. Relative paths are resolved against the workspace.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Directory path (the workspace if omitted)" },
+                },
+            },
+        },
+    ])
+}
+
+/// Result of a tool call
+fn tool_result(text: String, is_error: bool) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+fn parse_arguments<T: DeserializeOwned>(arguments: Value) -> Result<T, RpcError> {
+    serde_json::from_value(arguments).map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid arguments: {}", e)))
+}
+
+pub(crate) fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+/// Text result of a command: the exit code followed by the output streams that are not empty
+fn command_output(result: &TaskResult) -> String {
+    let mut text = format!("Exit code: {}", result.exit_code);
+    for (name, output) in [("stdout", &result.stdout), ("stderr", &result.stderr)] {
+        if !output.is_empty() {
+            let _ = write!(text, "\n\n{}:\n{}", name, output);
+        }
+    }
+    text
+}
+
+/// The revision requested by the client if supported, the latest otherwise
+fn negotiate_version(params: &Value) -> &'static str {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    SUPPORTED_PROTOCOL_VERSIONS
+        .into_iter()
+        .find(|version| Some(*version) == requested)
+        .unwrap_or(PROTOCOL_VERSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tenant_files::TenantFilesConfig;
+    use mcp_policy::models::{PolicyDecision, PolicyInput};
+    use mcp_policy::{PolicyEngine, PolicyEvaluator};
+    use mcp_sandbox::CommandExecutor;
+    use std::time::SystemTime;
+
+    /// Evaluator allowing everything
+    #[derive(Debug)]
+    struct AllowAll;
+
+    impl PolicyEvaluator for AllowAll {
+        fn evaluate(&self, _input: &PolicyInput) -> McpResult<PolicyDecision> {
+            Ok(PolicyDecision {
+                allow: true,
+                warnings: vec![],
+                reasons: vec![],
+                deny_reasons: vec![],
+                metadata: HashMap::new(),
+            })
+        }
+    }
+
+    fn server(root: &tempfile::TempDir) -> McpToolServer {
+        let policy_engine = PolicyEngine::with_evaluator(AllowAll);
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now())
+            .with_tenant_files_config(TenantFilesConfig {
+                root: Some(root.path().to_path_buf()),
+                ..Default::default()
+            });
+        McpToolServer::new(Arc::new(service))
+    }
+
+    async fn call(server: &McpToolServer, name: &str, arguments: Value) -> Value {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": arguments },
+        });
+        server.handle(&request).await.unwrap()["result"].clone()
+    }
+
+    // Test for the protocol messages
+    #[tokio::test]
+    async fn test_handle() {
+        let root = tempfile::tempdir().unwrap();
+        let server = server(&root);
+        let initialize = |version: &str| {
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "protocolVersion": version } })
+        };
+
+        // The requested revision is used if supported
+        let response = server.handle(&initialize("2024-11-05")).await.unwrap();
+        assert_eq!(response["result"]["protocolVersion"], "2024-11-05");
+        let response = server.handle(&initialize("1999-01-01")).await.unwrap();
+        assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSION);
+
+        let response = server.handle(&json!({ "jsonrpc": "2.0", "id": "a", "method": "tools/list" })).await.unwrap();
+        let tools: Vec<_> = response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(tools, ["execute_command", "read_file", "write_file", "list_directory"]);
+
+        // Notifications and responses are not answered
+        assert!(server.handle(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await.is_none());
+        assert!(server.handle(&json!({ "jsonrpc": "2.0", "id": 1, "result": {} })).await.is_none());
+
+        let response = server.handle(&json!({ "jsonrpc": "2.0", "id": 2, "method": "resources/list" })).await.unwrap();
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        let response = server.handle(&json!({ "jsonrpc": "2.0", "id": 3 })).await.unwrap();
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+        let response = server.handle_message("not json").await.unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+    }
+
+    // Test for the tools
+    #[tokio::test]
+    async fn test_tools() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("tenant1/workspace")).unwrap();
+        let server = server(&root);
+
+        let result = call(&server, "write_file", json!({ "path": "a.txt", "content": "hello" })).await;
+        assert_eq!(result["isError"], false);
+        assert_eq!(result["content"][0]["text"], "Wrote 5 bytes to /workspace/a.txt");
+
+        let result = call(&server, "read_file", json!({ "path": "/workspace/a.txt" })).await;
+        assert_eq!(result["content"][0]["text"], "hello");
+        let result = call(&server, "read_file", json!({ "path": "a.txt", "length": 2 })).await;
+        assert!(result["content"][0]["text"].as_str().unwrap().starts_with("he\n[2 of 5 bytes"));
+
+        let result = call(&server, "list_directory", json!({})).await;
+        assert_eq!(result["content"][0]["text"], "/workspace:\na.txt (5 bytes)");
+
+        let result = call(&server, "execute_command", json!({ "command": "echo", "args": ["hello"] })).await;
+        assert_eq!(result["isError"], false);
+        assert_eq!(result["content"][0]["text"], "Exit code: 0\n\nstdout:\nhello\n");
+
+        // Failures are reported as tool results
+        let result = call(&server, "read_file", json!({ "path": "missing.txt" })).await;
+        assert_eq!(result["isError"], true);
+
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": { "name": "read_file" } });
+        let response = server.handle(&request).await.unwrap();
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+}
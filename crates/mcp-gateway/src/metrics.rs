@@ -1,11 +1,13 @@
 #![allow(static_mut_refs)]
 
+use mcp_policy::PolicyMetrics;
+use mcp_sandbox::ConcurrencyMetrics;
 use prometheus::{
-    HistogramOpts, HistogramVec, IntCounterVec, IntGauge,
+    Gauge, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
     Opts, Registry,
 };
 use std::sync::Once;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tracing::error;
 
 static METRICS_INIT: Once = Once::new();
@@ -16,6 +18,26 @@ static mut ACTIVE_TASKS: Option<IntGauge> = None;
 static mut POLICY_EVALUATIONS: Option<IntCounterVec> = None;
 static mut SANDBOX_EXECUTION_TIME: Option<HistogramVec> = None;
 static mut ERROR_COUNTER: Option<IntCounterVec> = None;
+static mut RESULT_CACHE_REQUESTS: Option<IntCounterVec> = None;
+static mut RESULT_CACHE_EVICTIONS: Option<IntCounterVec> = None;
+static mut RESULT_CACHE_ENTRIES: Option<IntGauge> = None;
+static mut POLICY_DECISION_CACHE_REQUESTS: Option<IntCounterVec> = None;
+static mut POLICY_BUNDLE_INFO: Option<IntGaugeVec> = None;
+static mut POLICY_BUNDLE_ACTIVATIONS: Option<IntCounter> = None;
+static mut POLICY_CANARY_EVALUATIONS: Option<IntCounterVec> = None;
+static mut POLICY_BREAK_GLASS_OVERRIDES: Option<IntCounterVec> = None;
+static mut POLICY_RELOADS: Option<IntCounterVec> = None;
+static mut POLICY_EVALUATION_LATENCY: Option<HistogramVec> = None;
+static mut POLICY_DECISIONS: Option<IntCounterVec> = None;
+static mut POLICY_DECISION_CACHE_HIT_RATIO: Option<Gauge> = None;
+static mut SANDBOX_QUEUE_DEPTH: Option<IntGauge> = None;
+static mut SANDBOX_QUEUE_WAIT_TIME: Option<Histogram> = None;
+static mut SANDBOX_RETRIES: Option<IntCounterVec> = None;
+static mut TASK_RETENTION_RECLAIMED: Option<IntCounterVec> = None;
+static mut TASK_QUEUE_DEPTH: Option<IntGauge> = None;
+static mut TASK_QUEUE_REJECTIONS: Option<IntCounter> = None;
+static mut PROXY_TOOL_CALLS: Option<IntCounterVec> = None;
+static mut PROXY_TOOL_CALL_LATENCY: Option<HistogramVec> = None;
 
 /// Metrics initialization
 pub fn init_metrics() {
@@ -66,6 +88,152 @@ pub fn init_metrics() {
         )
         .unwrap();
 
+        // Result cache lookups
+        let result_cache_requests = IntCounterVec::new(
+            Opts::new("mcp_result_cache_requests_total", "Total number of result cache lookups"),
+            &["result"],
+        )
+        .unwrap();
+
+        // Result cache evictions
+        let result_cache_evictions = IntCounterVec::new(
+            Opts::new("mcp_result_cache_evictions_total", "Total number of evicted result cache entries"),
+            &["reason"],
+        )
+        .unwrap();
+
+        // Result cache size
+        let result_cache_entries = IntGauge::new("mcp_result_cache_entries", "Number of result cache entries").unwrap();
+
+        // Policy decision cache lookups
+        let policy_decision_cache_requests = IntCounterVec::new(
+            Opts::new("mcp_policy_decision_cache_requests_total", "Total number of policy decision cache lookups"),
+            &["result"],
+        )
+        .unwrap();
+
+        // Active policy bundle revision (value is always 1)
+        let policy_bundle_info = IntGaugeVec::new(
+            Opts::new("mcp_policy_bundle_info", "Revision of the active policy bundle"),
+            &["revision"],
+        )
+        .unwrap();
+
+        // Policy bundle activations
+        let policy_bundle_activations = IntCounter::new(
+            "mcp_policy_bundle_activations_total",
+            "Total number of activated policy bundles",
+        )
+        .unwrap();
+
+        // Policy canary comparisons
+        let policy_canary_evaluations = IntCounterVec::new(
+            Opts::new(
+                "mcp_policy_canary_evaluations_total",
+                "Total number of policy evaluations compared with the canary version",
+            ),
+            &["result"],
+        )
+        .unwrap();
+
+        // Break-glass overrides of policy denials
+        let policy_break_glass_overrides = IntCounterVec::new(
+            Opts::new(
+                "mcp_policy_break_glass_overrides_total",
+                "Total number of break-glass tokens presented to override policy denials",
+            ),
+            &["result"],
+        )
+        .unwrap();
+
+        // Policy reloads (admin API, file watcher and bundle server)
+        let policy_reloads = IntCounterVec::new(
+            Opts::new("mcp_policy_reloads_total", "Total number of policy reloads"),
+            &["source", "result"],
+        )
+        .unwrap();
+
+        // Policy engine evaluation time
+        let policy_evaluation_latency = HistogramVec::new(
+            HistogramOpts::new("mcp_policy_evaluation_latency_ms", "Policy engine evaluation time (milliseconds)")
+                .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0]),
+            &["check", "outcome"],
+        )
+        .unwrap();
+
+        // Policy engine decisions
+        let policy_decisions = IntCounterVec::new(
+            Opts::new("mcp_policy_decisions_total", "Total number of policy engine decisions"),
+            &["check", "outcome"],
+        )
+        .unwrap();
+
+        // Share of policy decision cache lookups that were hits
+        let policy_decision_cache_hit_ratio = Gauge::new(
+            "mcp_policy_decision_cache_hit_ratio",
+            "Ratio of policy decision cache hits to lookups",
+        )
+        .unwrap();
+
+        // Commands waiting for a concurrency permit
+        let sandbox_queue_depth = IntGauge::new(
+            "mcp_sandbox_queue_depth",
+            "Number of commands waiting for a sandbox concurrency permit",
+        )
+        .unwrap();
+
+        // Time commands waited for a concurrency permit
+        let sandbox_queue_wait_time = Histogram::with_opts(
+            HistogramOpts::new("mcp_sandbox_queue_wait_ms", "Sandbox concurrency permit wait time (milliseconds)")
+                .buckets(vec![0.0, 1.0, 10.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 30000.0]),
+        )
+        .unwrap();
+
+        // Retries of commands after transient failures
+        let sandbox_retries = IntCounterVec::new(
+            Opts::new("mcp_sandbox_retries_total", "Total number of command retries after transient failures"),
+            &["result"],
+        )
+        .unwrap();
+
+        // Tasks, results and output logs removed after their retention period
+        let task_retention_reclaimed = IntCounterVec::new(
+            Opts::new(
+                "mcp_task_retention_reclaimed_total",
+                "Total number of tasks, results and output logs removed after their retention period",
+            ),
+            &["kind"],
+        )
+        .unwrap();
+
+        // Tasks waiting for a worker of the task queue
+        let task_queue_depth =
+            IntGauge::new("mcp_task_queue_depth", "Number of tasks waiting for a worker").unwrap();
+
+        // Tasks rejected because the task queue was full
+        let task_queue_rejections = IntCounter::new(
+            "mcp_task_queue_rejections_total",
+            "Total number of tasks rejected because the task queue was full",
+        )
+        .unwrap();
+
+        // Tool calls proxied to upstream MCP servers
+        let proxy_tool_calls = IntCounterVec::new(
+            Opts::new("mcp_proxy_tool_calls_total", "Total number of tool calls proxied to upstream MCP servers"),
+            &["server", "outcome"],
+        )
+        .unwrap();
+
+        // Time of tool calls proxied to upstream MCP servers
+        let proxy_tool_call_latency = HistogramVec::new(
+            HistogramOpts::new("mcp_proxy_tool_call_latency_ms", "Proxied tool call time (milliseconds)")
+                .buckets(vec![
+                    5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 30000.0,
+                ]),
+            &["server", "outcome"],
+        )
+        .unwrap();
+
         // Register metrics with registry
         registry.register(Box::new(api_requests.clone())).unwrap();
         registry
@@ -79,6 +247,54 @@ pub fn init_metrics() {
             .register(Box::new(sandbox_execution_time.clone()))
             .unwrap();
         registry.register(Box::new(error_counter.clone())).unwrap();
+        registry
+            .register(Box::new(result_cache_requests.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(result_cache_evictions.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(result_cache_entries.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(policy_decision_cache_requests.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(policy_bundle_info.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(policy_bundle_activations.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(policy_canary_evaluations.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(policy_break_glass_overrides.clone()))
+            .unwrap();
+        registry.register(Box::new(policy_reloads.clone())).unwrap();
+        registry
+            .register(Box::new(policy_evaluation_latency.clone()))
+            .unwrap();
+        registry.register(Box::new(policy_decisions.clone())).unwrap();
+        registry
+            .register(Box::new(policy_decision_cache_hit_ratio.clone()))
+            .unwrap();
+        registry.register(Box::new(sandbox_queue_depth.clone())).unwrap();
+        registry
+            .register(Box::new(sandbox_queue_wait_time.clone()))
+            .unwrap();
+        registry.register(Box::new(sandbox_retries.clone())).unwrap();
+        registry
+            .register(Box::new(task_retention_reclaimed.clone()))
+            .unwrap();
+        registry.register(Box::new(task_queue_depth.clone())).unwrap();
+        registry
+            .register(Box::new(task_queue_rejections.clone()))
+            .unwrap();
+        registry.register(Box::new(proxy_tool_calls.clone())).unwrap();
+        registry
+            .register(Box::new(proxy_tool_call_latency.clone()))
+            .unwrap();
 
         // Process metrics are only added on Linux (using feature="process")
         #[cfg(target_os = "linux")]
@@ -99,6 +315,26 @@ pub fn init_metrics() {
             POLICY_EVALUATIONS = Some(policy_evaluations);
             SANDBOX_EXECUTION_TIME = Some(sandbox_execution_time);
             ERROR_COUNTER = Some(error_counter);
+            RESULT_CACHE_REQUESTS = Some(result_cache_requests);
+            RESULT_CACHE_EVICTIONS = Some(result_cache_evictions);
+            RESULT_CACHE_ENTRIES = Some(result_cache_entries);
+            POLICY_DECISION_CACHE_REQUESTS = Some(policy_decision_cache_requests);
+            POLICY_BUNDLE_INFO = Some(policy_bundle_info);
+            POLICY_BUNDLE_ACTIVATIONS = Some(policy_bundle_activations);
+            POLICY_CANARY_EVALUATIONS = Some(policy_canary_evaluations);
+            POLICY_BREAK_GLASS_OVERRIDES = Some(policy_break_glass_overrides);
+            POLICY_RELOADS = Some(policy_reloads);
+            POLICY_EVALUATION_LATENCY = Some(policy_evaluation_latency);
+            POLICY_DECISIONS = Some(policy_decisions);
+            POLICY_DECISION_CACHE_HIT_RATIO = Some(policy_decision_cache_hit_ratio);
+            SANDBOX_QUEUE_DEPTH = Some(sandbox_queue_depth);
+            SANDBOX_QUEUE_WAIT_TIME = Some(sandbox_queue_wait_time);
+            SANDBOX_RETRIES = Some(sandbox_retries);
+            TASK_RETENTION_RECLAIMED = Some(task_retention_reclaimed);
+            TASK_QUEUE_DEPTH = Some(task_queue_depth);
+            TASK_QUEUE_REJECTIONS = Some(task_queue_rejections);
+            PROXY_TOOL_CALLS = Some(proxy_tool_calls);
+            PROXY_TOOL_CALL_LATENCY = Some(proxy_tool_call_latency);
         }
     });
 }
@@ -187,6 +423,202 @@ pub fn increment_error_counter(error_type: &str, error_code: &str) {
     }
 }
 
+/// Count result cache lookup ("hit" or "miss")
+pub fn increment_result_cache_requests(result: &str) {
+    unsafe {
+        if let Some(counter) = RESULT_CACHE_REQUESTS.as_ref() {
+            counter.with_label_values(&[result]).inc();
+        }
+    }
+}
+
+/// Count result cache eviction
+pub fn increment_result_cache_evictions(reason: &str) {
+    unsafe {
+        if let Some(counter) = RESULT_CACHE_EVICTIONS.as_ref() {
+            counter.with_label_values(&[reason]).inc();
+        }
+    }
+}
+
+/// Set result cache size
+pub fn set_result_cache_entries(count: i64) {
+    unsafe {
+        if let Some(gauge) = RESULT_CACHE_ENTRIES.as_ref() {
+            gauge.set(count);
+        }
+    }
+}
+
+/// Count policy decision cache lookup ("hit" or "miss") and update the hit ratio
+pub fn increment_policy_decision_cache_requests(result: &str) {
+    unsafe {
+        if let Some(counter) = POLICY_DECISION_CACHE_REQUESTS.as_ref() {
+            counter.with_label_values(&[result]).inc();
+
+            let hits = counter.with_label_values(&["hit"]).get();
+            let misses = counter.with_label_values(&["miss"]).get();
+            if let Some(gauge) = POLICY_DECISION_CACHE_HIT_RATIO.as_ref() {
+                gauge.set(hits as f64 / (hits + misses).max(1) as f64);
+            }
+        }
+    }
+}
+
+/// Record the activation of a policy bundle revision
+pub fn record_policy_bundle_activation(revision: &str) {
+    unsafe {
+        if let Some(gauge) = POLICY_BUNDLE_INFO.as_ref() {
+            // Only the active revision is exported
+            gauge.reset();
+            gauge.with_label_values(&[revision]).set(1);
+        }
+        if let Some(counter) = POLICY_BUNDLE_ACTIVATIONS.as_ref() {
+            counter.inc();
+        }
+    }
+}
+
+/// Count policy canary comparison ("match" or "divergence")
+pub fn increment_policy_canary_evaluations(result: &str) {
+    unsafe {
+        if let Some(counter) = POLICY_CANARY_EVALUATIONS.as_ref() {
+            counter.with_label_values(&[result]).inc();
+        }
+    }
+}
+
+/// Count break-glass override attempt ("granted" or "rejected")
+pub fn increment_policy_break_glass_overrides(result: &str) {
+    unsafe {
+        if let Some(counter) = POLICY_BREAK_GLASS_OVERRIDES.as_ref() {
+            counter.with_label_values(&[result]).inc();
+        }
+    }
+}
+
+/// Count policy reload ("admin", "watcher" or "bundle"; "success" or "failure")
+pub fn increment_policy_reloads(source: &str, result: &str) {
+    unsafe {
+        if let Some(counter) = POLICY_RELOADS.as_ref() {
+            counter.with_label_values(&[source, result]).inc();
+        }
+    }
+}
+
+/// Record policy engine evaluation ("allow", "deny" or "error")
+pub fn observe_policy_evaluation(check: &str, outcome: &str, latency: Duration) {
+    unsafe {
+        if let Some(histogram) = POLICY_EVALUATION_LATENCY.as_ref() {
+            histogram
+                .with_label_values(&[check, outcome])
+                .observe(latency.as_secs_f64() * 1000.0);
+        }
+        if let Some(counter) = POLICY_DECISIONS.as_ref() {
+            counter.with_label_values(&[check, outcome]).inc();
+        }
+    }
+}
+
+/// Set the number of commands waiting for a concurrency permit
+pub fn set_sandbox_queue_depth(depth: i64) {
+    unsafe {
+        if let Some(gauge) = SANDBOX_QUEUE_DEPTH.as_ref() {
+            gauge.set(depth);
+        }
+    }
+}
+
+/// Record the time a command waited for a concurrency permit
+pub fn observe_sandbox_queue_wait_time(wait: Duration) {
+    unsafe {
+        if let Some(histogram) = SANDBOX_QUEUE_WAIT_TIME.as_ref() {
+            histogram.observe(wait.as_secs_f64() * 1000.0);
+        }
+    }
+}
+
+/// Count the retries of a command by the result of the task
+pub fn increment_sandbox_retries(result: &str, retries: u32) {
+    unsafe {
+        if let Some(counter) = SANDBOX_RETRIES.as_ref() {
+            counter.with_label_values(&[result]).inc_by(retries as u64);
+        }
+    }
+}
+
+/// Count the entries removed after their retention period ("task" or "result")
+pub fn increment_reclaimed_entries(kind: &str, count: usize) {
+    unsafe {
+        if let Some(counter) = TASK_RETENTION_RECLAIMED.as_ref() {
+            counter.with_label_values(&[kind]).inc_by(count as u64);
+        }
+    }
+}
+
+/// Set the number of tasks waiting for a worker
+pub fn set_task_queue_depth(depth: i64) {
+    unsafe {
+        if let Some(gauge) = TASK_QUEUE_DEPTH.as_ref() {
+            gauge.set(depth);
+        }
+    }
+}
+
+/// Count a task rejected because the task queue was full
+pub fn increment_task_queue_rejections() {
+    unsafe {
+        if let Some(counter) = TASK_QUEUE_REJECTIONS.as_ref() {
+            counter.inc();
+        }
+    }
+}
+
+/// Record a tool call proxied to an upstream server ("success", "error", "denied" or "failed")
+pub fn observe_proxy_tool_call(server: &str, outcome: &str, start_time: Instant) {
+    let duration_ms = start_time.elapsed().as_secs_f64() * 1000.0;
+    unsafe {
+        if let Some(counter) = PROXY_TOOL_CALLS.as_ref() {
+            counter.with_label_values(&[server, outcome]).inc();
+        }
+        if let Some(histogram) = PROXY_TOOL_CALL_LATENCY.as_ref() {
+            histogram.with_label_values(&[server, outcome]).observe(duration_ms);
+        }
+    }
+}
+
+/// Exports the measurements of the policy engine to the registry
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PolicyEngineMetrics;
+
+impl PolicyMetrics for PolicyEngineMetrics {
+    fn observe_evaluation(&self, check: &str, outcome: &str, latency: Duration) {
+        observe_policy_evaluation(check, outcome, latency);
+    }
+
+    fn observe_cache_lookup(&self, result: &str) {
+        increment_policy_decision_cache_requests(result);
+    }
+
+    fn observe_reload(&self, source: &str, result: &str) {
+        increment_policy_reloads(source, result);
+    }
+}
+
+/// Exports the measurements of the sandbox concurrency limiter to the registry
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SandboxQueueMetrics;
+
+impl ConcurrencyMetrics for SandboxQueueMetrics {
+    fn observe_queue_depth(&self, depth: usize) {
+        set_sandbox_queue_depth(depth as i64);
+    }
+
+    fn observe_queue_wait(&self, wait: Duration) {
+        observe_sandbox_queue_wait_time(wait);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -209,6 +641,25 @@ mod tests {
             assert!(POLICY_EVALUATIONS.is_some(), "POLICY_EVALUATIONS has not been initialized");
             assert!(SANDBOX_EXECUTION_TIME.is_some(), "SANDBOX_EXECUTION_TIME has not been initialized");
             assert!(ERROR_COUNTER.is_some(), "ERROR_COUNTER has not been initialized");
+            assert!(RESULT_CACHE_REQUESTS.is_some(), "RESULT_CACHE_REQUESTS has not been initialized");
+            assert!(RESULT_CACHE_EVICTIONS.is_some(), "RESULT_CACHE_EVICTIONS has not been initialized");
+            assert!(RESULT_CACHE_ENTRIES.is_some(), "RESULT_CACHE_ENTRIES has not been initialized");
+            assert!(POLICY_DECISION_CACHE_REQUESTS.is_some(), "POLICY_DECISION_CACHE_REQUESTS has not been initialized");
+            assert!(POLICY_BUNDLE_INFO.is_some(), "POLICY_BUNDLE_INFO has not been initialized");
+            assert!(POLICY_BUNDLE_ACTIVATIONS.is_some(), "POLICY_BUNDLE_ACTIVATIONS has not been initialized");
+            assert!(POLICY_CANARY_EVALUATIONS.is_some(), "POLICY_CANARY_EVALUATIONS has not been initialized");
+            assert!(
+                POLICY_BREAK_GLASS_OVERRIDES.is_some(),
+                "POLICY_BREAK_GLASS_OVERRIDES has not been initialized"
+            );
+            assert!(POLICY_EVALUATION_LATENCY.is_some(), "POLICY_EVALUATION_LATENCY has not been initialized");
+            assert!(POLICY_DECISIONS.is_some(), "POLICY_DECISIONS has not been initialized");
+            assert!(
+                POLICY_DECISION_CACHE_HIT_RATIO.is_some(),
+                "POLICY_DECISION_CACHE_HIT_RATIO has not been initialized"
+            );
+            assert!(SANDBOX_QUEUE_DEPTH.is_some(), "SANDBOX_QUEUE_DEPTH has not been initialized");
+            assert!(SANDBOX_QUEUE_WAIT_TIME.is_some(), "SANDBOX_QUEUE_WAIT_TIME has not been initialized");
         }
     }
 
@@ -272,4 +723,42 @@ mod tests {
         // Verify that registry is obtained
         assert!(!registry.gather().is_empty(), "Registry is not correctly initialized");
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_policy_engine_metrics() {
+        // Initialize metrics
+        init_metrics();
+
+        // Record measurements of the policy engine
+        let metrics = PolicyEngineMetrics;
+        metrics.observe_evaluation("command_execution", "allow", Duration::from_micros(300));
+        metrics.observe_cache_lookup("miss");
+        metrics.observe_cache_lookup("hit");
+        metrics.observe_reload("watcher", "success");
+
+        // Verify that the hit ratio follows the lookup counter
+        unsafe {
+            let counter = POLICY_DECISION_CACHE_REQUESTS.as_ref().unwrap();
+            let hits = counter.with_label_values(&["hit"]).get() as f64;
+            let misses = counter.with_label_values(&["miss"]).get() as f64;
+            let ratio = POLICY_DECISION_CACHE_HIT_RATIO.as_ref().unwrap().get();
+            assert!((ratio - hits / (hits + misses)).abs() < 1e-9);
+            assert!(POLICY_DECISIONS.as_ref().unwrap().with_label_values(&["command_execution", "allow"]).get() >= 1);
+        }
+    }
+
+    #[test]
+    fn test_sandbox_queue_metrics() {
+        // Initialize metrics
+        init_metrics();
+
+        // Record measurements of the concurrency limiter
+        let metrics = SandboxQueueMetrics;
+        metrics.observe_queue_depth(3);
+        metrics.observe_queue_wait(Duration::from_millis(20));
+
+        unsafe {
+            assert!(SANDBOX_QUEUE_WAIT_TIME.as_ref().unwrap().get_sample_count() >= 1);
+        }
+    }
+}
@@ -0,0 +1,607 @@
+//! OIDC token validation
+//!
+//! Bearer tokens issued by the configured OpenID Connect providers (Okta, Keycloak, Entra ID
+//! and the like) authenticate gRPC requests. For every issuer the discovery document
+//! (`<issuer>/.well-known/openid-configuration`) names the JWKS, whose keys are cached and
+//! fetched again at the refresh interval. A token signed with a key that is not cached triggers
+//! an early refresh (at most every [`MIN_REFRESH_INTERVAL`]), so rotated keys are picked up
+//! without waiting for the next refresh.
+//!
+//! Tokens must be signed with an asymmetric algorithm, be issued by the issuer of the key, be
+//! unexpired, and name the audience of the issuer, which every issuer has to configure. Claims
+//! are mapped to the user as follows:
+//!
+//! * `user_claim` (default `sub`) - user ID
+//! * `tenant_claim` (default `tenant_id`, e.g. `tid` for Entra ID) - tenant ID, or
+//!   `default_tenant` if the token has none
+//! * `roles_claim` (default `roles`, e.g. `groups` for Okta or `realm_access.roles` for
+//!   Keycloak) - roles, as an array or a space-separated string
+//! * the standard claims `email`, `email_verified`, `name`, `preferred_username`, `given_name`
+//!   and `family_name`, the issuer (`issuer`), and the claims listed in `claims` (attribute name
+//!   to claim path) - user attributes
+//!
+//! Claim paths separate nested objects with dots. The issuers are read from the JSON file named
+//! by `MCP_OIDC_CONFIG`:
+//!
+//! ```json
+//! {
+//!   "issuers": [
+//!     {
+//!       "issuer": "https://keycloak.example.com/realms/mcp",
+//!       "audience": "mcp-gateway",
+//!       "roles_claim": "realm_access.roles",
+//!       "default_tenant": "default",
+//!       "claims": {"department": "department"}
+//!     }
+//!   ],
+//!   "jwks_refresh_secs": 3600
+//! }
+//! ```
+
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{AlgorithmParameters, Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::parse_positive;
+use mcp_policy::models::UserInfo;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Shortest interval between two refreshes of the keys of an issuer
+pub const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time an issuer has to answer a discovery or JWKS request
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Standard claims copied to the user attributes
+const STANDARD_CLAIMS: &[&str] =
+    &["email", "email_verified", "name", "preferred_username", "given_name", "family_name"];
+
+/// User attribute naming the issuer of the token
+pub const ISSUER_ATTRIBUTE: &str = "issuer";
+
+/// Algorithms accepted for tokens (symmetric keys cannot be published in a JWKS)
+const ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// OIDC settings
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OidcConfig {
+    /// Trusted issuers
+    pub issuers: Vec<IssuerConfig>,
+    /// Interval at which the keys of every issuer are fetched again
+    #[serde(rename = "jwks_refresh_secs", with = "seconds", default = "default_refresh_interval")]
+    pub refresh_interval: Duration,
+}
+
+fn default_refresh_interval() -> Duration {
+    Duration::from_secs(3600)
+}
+
+mod seconds {
+    use serde::{Deserialize, Deserializer};
+    use std::time::Duration;
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        match u64::deserialize(deserializer)? {
+            0 => Err(serde::de::Error::custom("must be a positive number of seconds")),
+            seconds => Ok(Duration::from_secs(seconds)),
+        }
+    }
+}
+
+/// Settings of one issuer
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IssuerConfig {
+    /// Issuer URL, as in the `iss` claim
+    pub issuer: String,
+    /// Audience tokens must name in `aud`
+    pub audience: String,
+    /// Claim holding the user ID
+    #[serde(default = "default_user_claim")]
+    pub user_claim: String,
+    /// Claim holding the tenant ID
+    #[serde(default = "default_tenant_claim")]
+    pub tenant_claim: String,
+    /// Tenant of tokens without the tenant claim (such tokens are rejected if `None`)
+    #[serde(default)]
+    pub default_tenant: Option<String>,
+    /// Claim holding the roles
+    #[serde(default = "default_roles_claim")]
+    pub roles_claim: String,
+    /// Additional user attributes, by attribute name, and the claims they are read from
+    #[serde(default)]
+    pub claims: BTreeMap<String, String>,
+}
+
+fn default_user_claim() -> String {
+    "sub".to_string()
+}
+
+fn default_tenant_claim() -> String {
+    "tenant_id".to_string()
+}
+
+fn default_roles_claim() -> String {
+    "roles".to_string()
+}
+
+impl IssuerConfig {
+    /// Settings of `issuer` for tokens naming `audience`, with the default claims
+    pub fn new(issuer: impl Into<String>, audience: impl Into<String>) -> Self {
+        Self {
+            issuer: issuer.into(),
+            audience: audience.into(),
+            user_claim: default_user_claim(),
+            tenant_claim: default_tenant_claim(),
+            default_tenant: None,
+            roles_claim: default_roles_claim(),
+            claims: BTreeMap::new(),
+        }
+    }
+}
+
+impl OidcConfig {
+    /// Build the settings from environment variables (`None` if no issuer is configured)
+    ///
+    /// * `MCP_OIDC_CONFIG` - JSON file with the issuers
+    /// * `MCP_OIDC_JWKS_REFRESH_SECS` - overrides `jwks_refresh_secs` of the file
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let mut config = match std::env::var("MCP_OIDC_CONFIG") {
+            Ok(path) if !path.trim().is_empty() => Self::from_file(path.trim())?,
+            _ => return Ok(None),
+        };
+        if let Ok(value) = std::env::var("MCP_OIDC_JWKS_REFRESH_SECS") {
+            config.refresh_interval = Duration::from_secs(parse_positive("MCP_OIDC_JWKS_REFRESH_SECS", &value)?);
+        }
+        Ok(Some(config))
+    }
+
+    /// Load the issuers from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| McpError::Internal(format!("Failed to read OIDC configuration {}: {}", path.display(), e)))?;
+        let config: Self = serde_json::from_str(&content)
+            .map_err(|e| McpError::InvalidRequest(format!("Invalid OIDC configuration {}: {}", path.display(), e)))?;
+        if config.issuers.is_empty() {
+            return Err(McpError::InvalidRequest(format!("No OIDC issuer in {}", path.display())));
+        }
+        // Without an audience, tokens the issuer grants to any other client would be accepted
+        if let Some(issuer) = config.issuers.iter().find(|issuer| issuer.audience.trim().is_empty()) {
+            return Err(McpError::InvalidRequest(format!(
+                "OIDC issuer {} in {} needs an audience",
+                issuer.issuer,
+                path.display()
+            )));
+        }
+        Ok(config)
+    }
+}
+
+/// Discovery document of an issuer (only the fields used here)
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    issuer: String,
+    jwks_uri: String,
+}
+
+/// Issuer with its cached keys
+#[derive(Debug)]
+struct Issuer {
+    config: IssuerConfig,
+    keys: RwLock<Vec<Jwk>>,
+    // Time of the last refresh, successful or not
+    refreshed_at: Mutex<Option<Instant>>,
+    // Held while the keys are fetched, so that concurrent requests fetch them once
+    refreshing: tokio::sync::Mutex<()>,
+    agent: ureq::Agent,
+}
+
+impl Issuer {
+    fn new(config: IssuerConfig) -> Self {
+        Self {
+            config,
+            keys: RwLock::new(Vec::new()),
+            refreshed_at: Mutex::new(None),
+            refreshing: tokio::sync::Mutex::new(()),
+            agent: ureq::AgentBuilder::new().timeout(FETCH_TIMEOUT).build(),
+        }
+    }
+
+    /// Fetch the discovery document and the JWKS
+    async fn refresh(&self) -> McpResult<()> {
+        let _refreshing = self.refreshing.lock().await;
+        self.fetch_keys().await
+    }
+
+    async fn fetch_keys(&self) -> McpResult<()> {
+        *self.refreshed_at.lock().unwrap() = Some(Instant::now());
+        let issuer = self.config.issuer.trim_end_matches('/').to_string();
+        let agent = self.agent.clone();
+        let keys = tokio::task::spawn_blocking(move || {
+            let discovery: Discovery = get_json(&agent, &format!("{}/.well-known/openid-configuration", issuer))?;
+            // A document naming another issuer could hand out keys for tokens it does not own
+            if discovery.issuer.trim_end_matches('/') != issuer {
+                return Err(McpError::ExternalService(format!(
+                    "Discovery document of {} names the issuer {}",
+                    issuer, discovery.issuer
+                )));
+            }
+            get_json::<JwkSet>(&agent, &discovery.jwks_uri)
+        })
+        .await
+        .map_err(|e| McpError::Internal(format!("OIDC key refresh task failed: {}", e)))??;
+
+        info!("Fetched {} signing keys of OIDC issuer {}", keys.keys.len(), self.config.issuer);
+        *self.keys.write().unwrap() = keys.keys;
+        Ok(())
+    }
+
+    /// Refresh for a token signed with the key `kid`, unless the keys were refreshed less than
+    /// `MIN_REFRESH_INTERVAL` ago
+    async fn refresh_for(&self, kid: Option<&str>) {
+        let _refreshing = self.refreshing.lock().await;
+        // Another request may have fetched the key meanwhile
+        if !self.keys_for(kid).is_empty() {
+            return;
+        }
+        let stale = self.refreshed_at.lock().unwrap().is_none_or(|at| at.elapsed() >= MIN_REFRESH_INTERVAL);
+        if stale {
+            if let Err(e) = self.fetch_keys().await {
+                warn!("Failed to fetch the keys of OIDC issuer {}: {}", self.config.issuer, e);
+            }
+        }
+    }
+
+    /// Cached keys that may have signed a token with the key ID `kid`
+    fn keys_for(&self, kid: Option<&str>) -> Vec<Jwk> {
+        let keys = self.keys.read().unwrap();
+        keys.iter()
+            .filter(|key| kid.is_none() || key.common.key_id.as_deref() == kid)
+            .filter(|key| !matches!(key.algorithm, AlgorithmParameters::OctetKey(_)))
+            .cloned()
+            .collect()
+    }
+
+    /// Claims of `token` if one of `keys` signed it for this issuer
+    fn verify(&self, token: &str, algorithm: Algorithm, keys: &[Jwk]) -> McpResult<Value> {
+        let mut validation = Validation::new(algorithm);
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.audience]);
+
+        let mut error = McpError::Auth("invalid token: no matching signing key".to_string());
+        for key in keys {
+            let decoding_key = DecodingKey::from_jwk(key)
+                .map_err(|e| McpError::Auth(format!("invalid signing key of {}: {}", self.config.issuer, e)))?;
+            match jsonwebtoken::decode::<Value>(token, &decoding_key, &validation) {
+                Ok(data) => return Ok(data.claims),
+                Err(e) if matches!(e.kind(), ErrorKind::ExpiredSignature) => {
+                    return Err(McpError::Auth("token expired".to_string()))
+                }
+                Err(e) => error = McpError::Auth(format!("invalid token: {}", e)),
+            }
+        }
+        Err(error)
+    }
+
+    /// User the verified claims map to
+    fn user(&self, claims: &Value) -> McpResult<UserInfo> {
+        let id = claim(claims, &self.config.user_claim)
+            .and_then(Value::as_str)
+            .ok_or_else(|| McpError::Auth(format!("invalid token: no '{}' claim", self.config.user_claim)))?;
+        let tenant_id = match claim(claims, &self.config.tenant_claim).and_then(Value::as_str) {
+            Some(tenant_id) => tenant_id,
+            None => self.config.default_tenant.as_deref().ok_or_else(|| {
+                McpError::Auth(format!("invalid token: no '{}' claim", self.config.tenant_claim))
+            })?,
+        };
+        let roles = match claim(claims, &self.config.roles_claim) {
+            Some(Value::Array(roles)) => roles.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+            Some(Value::String(roles)) => roles.split_whitespace().map(str::to_string).collect(),
+            _ => Vec::new(),
+        };
+
+        let mut attributes = HashMap::from([(ISSUER_ATTRIBUTE.to_string(), self.config.issuer.clone())]);
+        let standard = STANDARD_CLAIMS.iter().map(|name| (*name, *name));
+        let custom = self.config.claims.iter().map(|(name, path)| (name.as_str(), path.as_str()));
+        for (name, path) in standard.chain(custom) {
+            match claim(claims, path) {
+                Some(Value::String(value)) => attributes.insert(name.to_string(), value.clone()),
+                Some(Value::Null) | None => None,
+                Some(value) => attributes.insert(name.to_string(), value.to_string()),
+            };
+        }
+
+        Ok(UserInfo {
+            id: id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            roles,
+            attributes,
+        })
+    }
+}
+
+fn get_json<T: serde::de::DeserializeOwned>(agent: &ureq::Agent, url: &str) -> McpResult<T> {
+    let response = agent
+        .get(url)
+        .call()
+        .map_err(|e| McpError::ExternalService(format!("Failed to fetch {}: {}", url, e)))?;
+    serde_json::from_reader(response.into_reader())
+        .map_err(|e| McpError::ExternalService(format!("Invalid response from {}: {}", url, e)))
+}
+
+/// Claim at a dot-separated path
+fn claim<'a>(claims: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(claims, |value, name| value.get(name))
+}
+
+/// Validator of tokens issued by the configured issuers
+#[derive(Debug)]
+pub struct OidcValidator {
+    issuers: Vec<Arc<Issuer>>,
+}
+
+impl OidcValidator {
+    /// Validator for the configured issuers, with their keys fetched
+    ///
+    /// Issuers that cannot be reached are logged and tried again when a token needs their keys.
+    pub async fn start(config: OidcConfig) -> Arc<Self> {
+        let validator = Arc::new(Self {
+            issuers: config.issuers.into_iter().map(|config| Arc::new(Issuer::new(config))).collect(),
+        });
+        for issuer in &validator.issuers {
+            if let Err(e) = issuer.refresh().await {
+                warn!("Failed to fetch the keys of OIDC issuer {}: {}", issuer.config.issuer, e);
+            }
+        }
+
+        let watched = Arc::downgrade(&validator);
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(config.refresh_interval);
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                let Some(validator) = watched.upgrade() else {
+                    return;
+                };
+                for issuer in &validator.issuers {
+                    if let Err(e) = issuer.refresh().await {
+                        warn!("Failed to fetch the keys of OIDC issuer {}: {}", issuer.config.issuer, e);
+                    }
+                }
+            }
+        });
+        validator
+    }
+
+    /// User the token authenticates as
+    pub async fn validate(&self, token: &str) -> McpResult<UserInfo> {
+        let header = jsonwebtoken::decode_header(token).map_err(|e| McpError::Auth(format!("invalid token: {}", e)))?;
+        if !ALGORITHMS.contains(&header.alg) {
+            return Err(McpError::Auth(format!("invalid token: algorithm {:?} is not accepted", header.alg)));
+        }
+        let kid = header.kid.as_deref();
+
+        // Keys unknown to every issuer may have been rotated in since the last refresh
+        if self.issuers.iter().all(|issuer| issuer.keys_for(kid).is_empty()) {
+            debug!("No cached OIDC key with ID {:?}, refreshing", kid);
+            for issuer in &self.issuers {
+                issuer.refresh_for(kid).await;
+            }
+        }
+
+        let mut error = McpError::Auth("invalid token: signed by an unknown key".to_string());
+        for issuer in &self.issuers {
+            let keys = issuer.keys_for(kid);
+            if keys.is_empty() {
+                continue;
+            }
+            match issuer.verify(token, header.alg, &keys) {
+                Ok(claims) => return issuer.user(&claims),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Signing key of a test issuer
+    struct SigningKey {
+        kid: String,
+        encoding_key: EncodingKey,
+        jwk: Value,
+    }
+
+    impl SigningKey {
+        fn generate(kid: &str) -> Self {
+            let key_pair = rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256).unwrap();
+            // Uncompressed point: 0x04, then the x and y coordinates
+            let point = key_pair.public_key_raw();
+            Self {
+                kid: kid.to_string(),
+                encoding_key: EncodingKey::from_ec_pem(key_pair.serialize_pem().as_bytes()).unwrap(),
+                jwk: json!({
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "kid": kid,
+                    "use": "sig",
+                    "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+                    "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+                }),
+            }
+        }
+
+        fn sign(&self, claims: &Value) -> String {
+            let mut header = Header::new(Algorithm::ES256);
+            header.kid = Some(self.kid.clone());
+            jsonwebtoken::encode(&header, claims, &self.encoding_key).unwrap()
+        }
+    }
+
+    /// Issuer serving its discovery document and the JWKS, counting the JWKS requests
+    async fn serve_issuer(jwks: Arc<RwLock<Value>>, fetches: Arc<AtomicUsize>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let discovery = json!({ "issuer": issuer, "jwks_uri": format!("{}/jwks", issuer) });
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(move || async move { Json(discovery) }))
+            .route(
+                "/jwks",
+                get(move || async move {
+                    fetches.fetch_add(1, Ordering::SeqCst);
+                    Json(jwks.read().unwrap().clone())
+                }),
+            );
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        issuer
+    }
+
+    fn claims(issuer: &str, extra: Value) -> Value {
+        let now = mcp_common::utils::current_timestamp_ms() / 1000;
+        let mut claims = json!({ "iss": issuer, "aud": "mcp-gateway", "sub": "alice", "iat": now, "exp": now + 300 });
+        claims.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        claims
+    }
+
+    // Test for validating tokens and mapping their claims
+    #[tokio::test]
+    async fn test_validate() {
+        let key = SigningKey::generate("key-1");
+        let jwks = Arc::new(RwLock::new(json!({ "keys": [key.jwk] })));
+        let issuer = serve_issuer(jwks, Arc::new(AtomicUsize::new(0))).await;
+        let mut issuer_config = IssuerConfig::new(&issuer, "mcp-gateway");
+        issuer_config.roles_claim = "realm_access.roles".to_string();
+        issuer_config.claims = BTreeMap::from([("department".to_string(), "org.department".to_string())]);
+        let validator = OidcValidator::start(OidcConfig {
+            issuers: vec![issuer_config],
+            refresh_interval: default_refresh_interval(),
+        })
+        .await;
+
+        let token = key.sign(&claims(
+            &issuer,
+            json!({
+                "tenant_id": "tenant-a",
+                "realm_access": { "roles": ["developer", "admin"] },
+                "email": "alice@example.com",
+                "email_verified": true,
+                "org": { "department": "security" },
+            }),
+        ));
+        let user = validator.validate(&token).await.unwrap();
+        assert_eq!(user.id, "alice");
+        assert_eq!(user.tenant_id, "tenant-a");
+        assert_eq!(user.roles, vec!["developer", "admin"]);
+        assert_eq!(user.attributes["email"], "alice@example.com");
+        assert_eq!(user.attributes["email_verified"], "true");
+        assert_eq!(user.attributes["department"], "security");
+        assert_eq!(user.attributes[ISSUER_ATTRIBUTE], issuer);
+
+        // Tokens without a tenant, for another audience or issuer, expired or forged are rejected
+        let rejected = [
+            claims(&issuer, json!({})),
+            claims(&issuer, json!({ "tenant_id": "tenant-a", "aud": "other" })),
+            claims(&issuer, json!({ "tenant_id": "tenant-a", "iss": "https://other.example.com" })),
+            claims(&issuer, json!({ "tenant_id": "tenant-a", "exp": 1 })),
+        ];
+        for claims in rejected {
+            let error = validator.validate(&key.sign(&claims)).await.unwrap_err();
+            assert!(matches!(error, McpError::Auth(_)), "{:?}", error);
+        }
+        let forged = SigningKey::generate("key-1").sign(&claims(&issuer, json!({ "tenant_id": "tenant-a" })));
+        assert!(validator.validate(&forged).await.is_err());
+        assert!(validator.validate("not a token").await.is_err());
+    }
+
+    // Test for picking up rotated keys
+    #[tokio::test]
+    async fn test_key_rotation() {
+        let old_key = SigningKey::generate("key-old");
+        let jwks = Arc::new(RwLock::new(json!({ "keys": [old_key.jwk] })));
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let issuer = serve_issuer(jwks.clone(), fetches.clone()).await;
+        let mut issuer_config = IssuerConfig::new(&issuer, "mcp-gateway");
+        issuer_config.default_tenant = Some("default".to_string());
+        let validator = OidcValidator::start(OidcConfig {
+            issuers: vec![issuer_config],
+            refresh_interval: default_refresh_interval(),
+        })
+        .await;
+
+        let user = validator.validate(&old_key.sign(&claims(&issuer, json!({})))).await.unwrap();
+        assert_eq!(user.tenant_id, "default");
+
+        // A token signed with a new key makes the validator fetch the keys again
+        let new_key = SigningKey::generate("key-new");
+        *jwks.write().unwrap() = json!({ "keys": [new_key.jwk] });
+        *validator.issuers[0].refreshed_at.lock().unwrap() = Some(Instant::now() - MIN_REFRESH_INTERVAL);
+        let fetched = fetches.load(Ordering::SeqCst);
+        assert!(validator.validate(&new_key.sign(&claims(&issuer, json!({})))).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), fetched + 1);
+
+        // Unknown keys do not make the validator fetch the keys more often than the minimum interval
+        let unknown_key = SigningKey::generate("key-unknown");
+        assert!(validator.validate(&unknown_key.sign(&claims(&issuer, json!({})))).await.is_err());
+        assert_eq!(fetches.load(Ordering::SeqCst), fetched + 1);
+    }
+
+    // Test for loading the issuers
+    #[test]
+    fn test_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("oidc.json");
+        std::fs::write(
+            &path,
+            r#"{"issuers": [{"issuer": "https://login.microsoftonline.com/x/v2.0", "audience": "api://mcp",
+                "tenant_claim": "tid"}]}"#,
+        )
+        .unwrap();
+        let config = OidcConfig::from_file(&path).unwrap();
+        assert_eq!(config.refresh_interval, Duration::from_secs(3600));
+        assert_eq!(config.issuers[0].tenant_claim, "tid");
+        assert_eq!(config.issuers[0].user_claim, "sub");
+        assert_eq!(config.issuers[0].audience, "api://mcp");
+
+        std::fs::write(&path, r#"{"issuers": []}"#).unwrap();
+        assert!(OidcConfig::from_file(&path).is_err());
+        std::fs::write(&path, r#"{"issuers": [{"issuer": "https://a", "audience": "b", "role_claim": "x"}]}"#).unwrap();
+        assert!(OidcConfig::from_file(&path).is_err());
+        std::fs::write(&path, r#"{"issuers": [{"issuer": "https://a", "audience": "b"}], "jwks_refresh_secs": 0}"#)
+            .unwrap();
+        assert!(OidcConfig::from_file(&path).is_err());
+
+        // Every issuer needs an audience
+        std::fs::write(&path, r#"{"issuers": [{"issuer": "https://a"}]}"#).unwrap();
+        assert!(OidcConfig::from_file(&path).is_err());
+        std::fs::write(&path, r#"{"issuers": [{"issuer": "https://a", "audience": " "}]}"#).unwrap();
+        assert!(matches!(OidcConfig::from_file(&path), Err(McpError::InvalidRequest(_))));
+    }
+}
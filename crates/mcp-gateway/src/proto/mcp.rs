@@ -1,285 +1,993 @@
 // This file is @generated by prost-build.
-/// ヘルスチェックリクエスト
+/// Health check request
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct HealthRequest {}
-/// ヘルスチェックレスポンス
+/// Health check response
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct HealthResponse {
-    /// サービスの状態
+    /// Service status
     #[prost(string, tag = "1")]
     pub status: ::prost::alloc::string::String,
-    /// バージョン情報
+    /// Version information
     #[prost(string, tag = "2")]
     pub version: ::prost::alloc::string::String,
-    /// アップタイム（秒）
+    /// Uptime in seconds
     #[prost(uint64, tag = "3")]
     pub uptime_seconds: u64,
+    /// Host environment fingerprint (kernel, bwrap, seccomp/landlock, cgroup mode, capacity)
+    #[prost(map = "string, string", tag = "4")]
+    pub host: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// Revision of the active policy bundle (empty when no bundle is used)
+    #[prost(string, tag = "5")]
+    pub policy_revision: ::prost::alloc::string::String,
+    /// Signature verification of the last policy bundle ("verified (<key id>)", "unverified" or
+    /// "rejected: <reason>"; empty when no bundle is used)
+    #[prost(string, tag = "6")]
+    pub policy_signature: ::prost::alloc::string::String,
 }
-/// コマンド実行リクエスト
+/// Command execution request
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CommandRequest {
-    /// 実行するコマンド
+    /// Command to execute
     #[prost(string, tag = "1")]
     pub command: ::prost::alloc::string::String,
-    /// コマンド引数
+    /// Command arguments
     #[prost(string, repeated, tag = "2")]
     pub args: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
-    /// 環境変数
+    /// Environment variables
     #[prost(map = "string, string", tag = "3")]
     pub env: ::std::collections::HashMap<
         ::prost::alloc::string::String,
         ::prost::alloc::string::String,
     >,
-    /// 作業ディレクトリ
+    /// Working directory
     #[prost(string, optional, tag = "4")]
     pub cwd: ::core::option::Option<::prost::alloc::string::String>,
-    /// タイムアウト（秒）
+    /// Timeout in seconds
     #[prost(uint32, tag = "5")]
     pub timeout: u32,
-    /// タスクのメタデータ
+    /// Task metadata
     #[prost(map = "string, string", tag = "6")]
     pub metadata: ::std::collections::HashMap<
         ::prost::alloc::string::String,
         ::prost::alloc::string::String,
     >,
-    /// サンドボックス設定
+    /// Sandbox configuration requested for the task (bounded by the policy; cannot disable the sandbox)
     #[prost(message, optional, tag = "7")]
     pub sandbox_config: ::core::option::Option<SandboxConfig>,
+    /// Evaluate the policy and build the sandbox invocation without running the command; the task
+    /// completes at once with the invocation in its result
+    #[prost(bool, tag = "8")]
+    pub dry_run: bool,
+    /// Report the files the command adds, modifies or deletes in its read-write paths in the
+    /// result
+    #[prost(bool, tag = "9")]
+    pub diff_workspace: bool,
+    /// Priority in the task queue; tasks with higher values start first (default 0)
+    #[prost(int32, tag = "10")]
+    pub priority: i32,
+}
+/// Script execution request: the script is written into the workspace of the task (per-task
+/// workspaces must be configured) and run as `interpreter interpreter_args... script args...`
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ScriptRequest {
+    /// Interpreter running the script (e.g. sh, python3)
+    #[prost(string, tag = "1")]
+    pub interpreter: ::prost::alloc::string::String,
+    /// Interpreter arguments before the script path
+    #[prost(string, repeated, tag = "2")]
+    pub interpreter_args: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Script body
+    #[prost(string, tag = "3")]
+    pub script: ::prost::alloc::string::String,
+    /// Script arguments
+    #[prost(string, repeated, tag = "4")]
+    pub args: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Environment variables
+    #[prost(map = "string, string", tag = "5")]
+    pub env: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// Working directory
+    #[prost(string, optional, tag = "6")]
+    pub cwd: ::core::option::Option<::prost::alloc::string::String>,
+    /// Timeout in seconds
+    #[prost(uint32, tag = "7")]
+    pub timeout: u32,
+    /// Task metadata
+    #[prost(map = "string, string", tag = "8")]
+    pub metadata: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// Sandbox configuration requested for the task (bounded by the policy; cannot disable the sandbox)
+    #[prost(message, optional, tag = "9")]
+    pub sandbox_config: ::core::option::Option<SandboxConfig>,
+    /// Priority in the task queue; tasks with higher values start first (default 0)
+    #[prost(int32, tag = "10")]
+    pub priority: i32,
+}
+/// Plan execution request: steps run one at a time after the steps they depend on have
+/// succeeded, sharing the workspace of the task
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PlanRequest {
+    /// Steps of the plan
+    #[prost(message, repeated, tag = "1")]
+    pub steps: ::prost::alloc::vec::Vec<PlanStep>,
+    /// Task metadata
+    #[prost(map = "string, string", tag = "2")]
+    pub metadata: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// Sandbox configuration requested for all steps (bounded by the policy; cannot disable the sandbox)
+    #[prost(message, optional, tag = "3")]
+    pub sandbox_config: ::core::option::Option<SandboxConfig>,
+    /// Priority in the task queue; tasks with higher values start first (default 0)
+    #[prost(int32, tag = "4")]
+    pub priority: i32,
 }
-/// サンドボックス設定
+/// Step of a plan
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PlanStep {
+    /// Step ID, unique within the plan
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    /// Command of the step (its metadata, sandbox configuration and priority are ignored)
+    #[prost(message, optional, tag = "2")]
+    pub command: ::core::option::Option<CommandRequest>,
+    /// IDs of the steps that must succeed before this step runs
+    #[prost(string, repeated, tag = "3")]
+    pub depends_on: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// Sandbox configuration
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SandboxConfig {
-    /// サンドボックスが有効かどうか
+    /// Whether sandbox is enabled
     #[prost(bool, tag = "1")]
     pub enabled: bool,
-    /// ネットワークアクセス設定
+    /// Network access configuration
     #[prost(enumeration = "NetworkAccess", tag = "2")]
     pub network_access: i32,
-    /// リソース制限
+    /// Resource limits
     #[prost(message, optional, tag = "3")]
     pub resource_limits: ::core::option::Option<ResourceLimits>,
-    /// 読み書き許可パス
+    /// Paths with read-write permission
     #[prost(string, repeated, tag = "4")]
     pub rw_paths: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
-    /// 読み取り専用許可パス
+    /// Paths with read-only permission
     #[prost(string, repeated, tag = "5")]
     pub ro_paths: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
-    /// アクセス禁止パス
+    /// Denied paths
     #[prost(string, repeated, tag = "6")]
     pub denied_paths: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Name of a language runtime preset (`python`, `node` or `rust-build`) providing default
+    /// mounts, environment variables, seccomp profile and resource limits
+    #[prost(string, tag = "7")]
+    pub preset: ::prost::alloc::string::String,
+    /// Name of a seccomp profile of the gateway, replacing the one of the preset (only applied
+    /// when the policy does not name a profile)
+    #[prost(string, tag = "8")]
+    pub seccomp_profile: ::prost::alloc::string::String,
 }
-/// リソース制限
+/// Resource limits
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ResourceLimits {
-    /// CPU制限（コア数）
+    /// CPU limit (cores)
     #[prost(float, tag = "1")]
     pub cpu_limit: f32,
-    /// メモリ制限（バイト）
+    /// Memory limit (bytes)
     #[prost(uint64, tag = "2")]
     pub memory_limit: u64,
-    /// プロセス数制限
+    /// Process count limit
     #[prost(uint32, tag = "3")]
     pub pids_limit: u32,
-    /// IOウェイト（優先度）
+    /// IO weight (priority)
     #[prost(uint32, tag = "4")]
     pub io_weight: u32,
 }
-/// タスク作成レスポンス
+/// Task creation response
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TaskCreatedResponse {
-    /// タスクID
+    /// Task ID
     #[prost(string, tag = "1")]
     pub task_id: ::prost::alloc::string::String,
-    /// タスクの状態
+    /// Task status
     #[prost(enumeration = "TaskStatus", tag = "2")]
     pub status: i32,
-    /// タスクの作成日時（ISO 8601形式）
+    /// Task creation time (ISO 8601 format)
     #[prost(string, tag = "3")]
     pub created_at: ::prost::alloc::string::String,
 }
-/// タスク状態リクエスト
+/// Task status request
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TaskStatusRequest {
-    /// タスクID
+    /// Task ID
     #[prost(string, tag = "1")]
     pub task_id: ::prost::alloc::string::String,
 }
-/// タスク状態レスポンス
+/// Task status response
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TaskStatusResponse {
-    /// タスク情報
+    /// Task information
     #[prost(message, optional, tag = "1")]
     pub task_info: ::core::option::Option<TaskInfo>,
-    /// 結果（完了している場合）
+    /// Result (if completed)
     #[prost(message, optional, tag = "2")]
     pub result: ::core::option::Option<TaskResult>,
+    /// Live resource usage of the running command (only while it runs in a task cgroup)
+    #[prost(message, optional, tag = "3")]
+    pub live_usage: ::core::option::Option<ResourceSample>,
 }
-/// タスク情報
+/// Task information
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TaskInfo {
-    /// タスクID
+    /// Task ID
     #[prost(string, tag = "1")]
     pub task_id: ::prost::alloc::string::String,
-    /// タスクのタイプ
+    /// Task type
     #[prost(enumeration = "TaskType", tag = "2")]
     pub task_type: i32,
-    /// タスクの状態
+    /// Task status
     #[prost(enumeration = "TaskStatus", tag = "3")]
     pub status: i32,
-    /// タスクの作成日時（ISO 8601形式）
+    /// Task creation time (ISO 8601 format)
     #[prost(string, tag = "4")]
     pub created_at: ::prost::alloc::string::String,
-    /// タスクの開始日時（ISO 8601形式）
+    /// Task start time (ISO 8601 format)
     #[prost(string, optional, tag = "5")]
     pub started_at: ::core::option::Option<::prost::alloc::string::String>,
-    /// タスクの完了日時（ISO 8601形式）
+    /// Task completion time (ISO 8601 format)
     #[prost(string, optional, tag = "6")]
     pub completed_at: ::core::option::Option<::prost::alloc::string::String>,
-    /// タスクのメタデータ
+    /// Task metadata
     #[prost(map = "string, string", tag = "7")]
     pub metadata: ::std::collections::HashMap<
         ::prost::alloc::string::String,
         ::prost::alloc::string::String,
     >,
 }
-/// タスク結果
+/// Task result
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TaskResult {
-    /// 終了コード
+    /// Exit code
     #[prost(int32, tag = "1")]
     pub exit_code: i32,
-    /// 標準出力
+    /// Standard output
     #[prost(string, tag = "2")]
     pub stdout: ::prost::alloc::string::String,
-    /// 標準エラー出力
+    /// Standard error output
     #[prost(string, tag = "3")]
     pub stderr: ::prost::alloc::string::String,
-    /// リソース使用量
+    /// Resource usage
     #[prost(message, optional, tag = "4")]
     pub resource_usage: ::core::option::Option<ResourceUsage>,
-    /// 実行時間（ミリ秒）
+    /// Execution time (milliseconds)
     #[prost(uint64, tag = "5")]
     pub execution_time_ms: u64,
+    /// Processes spawned by the command, the command first
+    #[prost(message, repeated, tag = "6")]
+    pub process_tree: ::prost::alloc::vec::Vec<ProcessInfo>,
+    /// Events recorded by the eBPF runtime monitor, in the order they occurred
+    #[prost(message, repeated, tag = "7")]
+    pub runtime_events: ::prost::alloc::vec::Vec<RuntimeEvent>,
+    /// Results of the steps of a plan, in the order they ran
+    #[prost(message, repeated, tag = "8")]
+    pub steps: ::prost::alloc::vec::Vec<StepResult>,
+    /// Sandbox invocation of a dry run (unset for executed commands)
+    #[prost(message, optional, tag = "9")]
+    pub dry_run: ::core::option::Option<SandboxDryRun>,
+    /// Files changed by the command (unset unless requested, or if the read-write paths have too
+    /// many files to compare)
+    #[prost(message, optional, tag = "10")]
+    pub workspace_diff: ::core::option::Option<WorkspaceDiff>,
+    /// Complete standard output when `stdout` only holds its beginning (unset for small output)
+    #[prost(message, optional, tag = "11")]
+    pub stdout_overflow: ::core::option::Option<OutputOverflow>,
+    /// Complete standard error output when `stderr` only holds its beginning
+    #[prost(message, optional, tag = "12")]
+    pub stderr_overflow: ::core::option::Option<OutputOverflow>,
+}
+/// Output stream too large to be returned inline
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OutputOverflow {
+    /// Size of the complete stream (bytes)
+    #[prost(uint64, tag = "1")]
+    pub size_bytes: u64,
+    /// Output log artifacts holding the complete stream, in order (see DownloadTaskArtifact;
+    /// empty for the steps of a plan, whose output is only logged for the whole plan)
+    #[prost(string, repeated, tag = "2")]
+    pub artifacts: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// Result of a plan step
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StepResult {
+    /// Step ID
+    #[prost(string, tag = "1")]
+    pub step_id: ::prost::alloc::string::String,
+    /// Step status
+    #[prost(enumeration = "StepStatus", tag = "2")]
+    pub status: i32,
+    /// Result of the command (unset if it did not run to completion)
+    #[prost(message, optional, tag = "3")]
+    pub result: ::core::option::Option<TaskResult>,
+    /// Why the command failed or the step was skipped
+    #[prost(string, optional, tag = "4")]
+    pub error: ::core::option::Option<::prost::alloc::string::String>,
+}
+/// How the command of a dry run would be started
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SandboxDryRun {
+    /// Backend that would run the command ("bubblewrap", "container" or "host")
+    #[prost(string, tag = "1")]
+    pub backend: ::prost::alloc::string::String,
+    /// Command line that would be started, the program first
+    #[prost(string, repeated, tag = "2")]
+    pub argv: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Mounts of the sandbox (none on the host)
+    #[prost(message, repeated, tag = "3")]
+    pub mounts: ::prost::alloc::vec::Vec<SandboxMount>,
+    /// Name of the seccomp profile (unset if none would be applied)
+    #[prost(string, optional, tag = "4")]
+    pub seccomp_profile: ::core::option::Option<::prost::alloc::string::String>,
+    /// Profile file the backend would load
+    #[prost(string, optional, tag = "5")]
+    pub seccomp_profile_path: ::core::option::Option<::prost::alloc::string::String>,
+    /// Resource limits of the sandbox
+    #[prost(message, optional, tag = "6")]
+    pub resource_limits: ::core::option::Option<ResourceLimits>,
+    /// Disk quota for writes (bytes, 0 for none)
+    #[prost(uint64, tag = "7")]
+    pub disk_limit: u64,
+    /// CPU time limit (seconds, 0 for none)
+    #[prost(uint64, tag = "8")]
+    pub cpu_time_limit: u64,
+    /// Timeout in seconds
+    #[prost(uint32, tag = "9")]
+    pub timeout: u32,
+    /// Network access of the sandbox
+    #[prost(enumeration = "NetworkAccess", tag = "10")]
+    pub network_access: i32,
+    /// Hosts reachable with restricted network access
+    #[prost(string, repeated, tag = "11")]
+    pub allowed_hosts: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Per-process rlimits by their ulimit name ("nofile", "fsize", "core" or "stack")
+    #[prost(map = "string, uint64", tag = "12")]
+    pub rlimits: ::std::collections::HashMap<::prost::alloc::string::String, u64>,
+}
+/// Files changed by a command
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WorkspaceDiff {
+    /// Changed files, ordered by path
+    #[prost(message, repeated, tag = "1")]
+    pub changes: ::prost::alloc::vec::Vec<FileChange>,
 }
-/// リソース使用量
+/// File changed by a command
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FileChange {
+    /// Path in the sandbox
+    #[prost(string, tag = "1")]
+    pub path: ::prost::alloc::string::String,
+    /// Kind of the change ("added", "modified" or "deleted")
+    #[prost(string, tag = "2")]
+    pub kind: ::prost::alloc::string::String,
+    /// Size after the command (bytes, unset if deleted)
+    #[prost(uint64, optional, tag = "3")]
+    pub size: ::core::option::Option<u64>,
+    /// SHA-256 hash after the command (hex, unset if deleted)
+    #[prost(string, optional, tag = "4")]
+    pub sha256: ::core::option::Option<::prost::alloc::string::String>,
+    /// Size before the command (bytes, unset if added)
+    #[prost(uint64, optional, tag = "5")]
+    pub previous_size: ::core::option::Option<u64>,
+    /// SHA-256 hash before the command (hex, unset if added)
+    #[prost(string, optional, tag = "6")]
+    pub previous_sha256: ::core::option::Option<::prost::alloc::string::String>,
+}
+/// Mount of a sandbox
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SandboxMount {
+    /// Host path (empty for tmpfs mounts)
+    #[prost(string, tag = "1")]
+    pub source: ::prost::alloc::string::String,
+    /// Path in the sandbox
+    #[prost(string, tag = "2")]
+    pub target: ::prost::alloc::string::String,
+    /// Kind of the mount ("rw", "ro", "overlay" or "tmpfs")
+    #[prost(string, tag = "3")]
+    pub kind: ::prost::alloc::string::String,
+}
+/// Process spawned during a task
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProcessInfo {
+    /// Process ID
+    #[prost(uint32, tag = "1")]
+    pub pid: u32,
+    /// Parent process ID (unset for the command)
+    #[prost(uint32, optional, tag = "2")]
+    pub parent_pid: ::core::option::Option<u32>,
+    /// Executable name
+    #[prost(string, tag = "3")]
+    pub name: ::prost::alloc::string::String,
+    /// Command line
+    #[prost(string, repeated, tag = "4")]
+    pub command_line: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Exit code (unset if the exit was not observed)
+    #[prost(int32, optional, tag = "5")]
+    pub exit_code: ::core::option::Option<i32>,
+    /// Terminating signal (unset if the process was not observed being killed)
+    #[prost(int32, optional, tag = "6")]
+    pub signal: ::core::option::Option<i32>,
+}
+/// Event recorded by the eBPF runtime monitor
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RuntimeEvent {
+    /// Time the event was received (milliseconds since the Unix epoch)
+    #[prost(uint64, tag = "1")]
+    pub timestamp_ms: u64,
+    /// Process ID
+    #[prost(uint32, tag = "2")]
+    pub pid: u32,
+    /// Executable name of the process
+    #[prost(string, tag = "3")]
+    pub name: ::prost::alloc::string::String,
+    /// Event kind (exec, connect or open)
+    #[prost(string, tag = "4")]
+    pub kind: ::prost::alloc::string::String,
+    /// Executed file, opened path or connected address
+    #[prost(string, tag = "5")]
+    pub target: ::prost::alloc::string::String,
+}
+/// Resource usage
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ResourceUsage {
-    /// CPU使用時間（ミリ秒）
+    /// CPU usage time (milliseconds)
     #[prost(uint64, tag = "1")]
     pub cpu_time_ms: u64,
-    /// 最大メモリ使用量（キロバイト）
+    /// Maximum memory usage (kilobytes)
     #[prost(uint64, tag = "2")]
     pub max_memory_kb: u64,
-    /// 読み込みバイト数
+    /// Number of bytes read
     #[prost(uint64, tag = "3")]
     pub io_read_bytes: u64,
-    /// 書き込みバイト数
+    /// Number of bytes written
+    #[prost(uint64, tag = "4")]
+    pub io_write_bytes: u64,
+}
+/// Resource usage of a running command at one point in time
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResourceSample {
+    /// Time the sample was taken (milliseconds since the Unix epoch)
+    #[prost(uint64, tag = "1")]
+    pub timestamp_ms: u64,
+    /// CPU usage time so far (milliseconds)
+    #[prost(uint64, tag = "2")]
+    pub cpu_time_ms: u64,
+    /// Current memory usage (kilobytes)
+    #[prost(uint64, tag = "3")]
+    pub memory_kb: u64,
+    /// Maximum memory usage so far (kilobytes)
     #[prost(uint64, tag = "4")]
+    pub max_memory_kb: u64,
+    /// Number of bytes read so far
+    #[prost(uint64, tag = "5")]
+    pub io_read_bytes: u64,
+    /// Number of bytes written so far
+    #[prost(uint64, tag = "6")]
     pub io_write_bytes: u64,
 }
-/// タスク出力チャンク
+/// Task output chunk
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TaskOutputChunk {
-    /// タスクID
+    /// Task ID
     #[prost(string, tag = "1")]
     pub task_id: ::prost::alloc::string::String,
-    /// チャンクタイプ
+    /// Chunk type
     #[prost(enumeration = "OutputChunkType", tag = "2")]
     pub r#type: i32,
-    /// チャンクデータ
+    /// Chunk data
     #[prost(bytes = "vec", tag = "3")]
     pub data: ::prost::alloc::vec::Vec<u8>,
-    /// タイムスタンプ（ミリ秒）
+    /// Timestamp (milliseconds)
     #[prost(uint64, tag = "4")]
     pub timestamp_ms: u64,
+    /// Resource usage sample (telemetry chunks only)
+    #[prost(message, optional, tag = "5")]
+    pub resource_sample: ::core::option::Option<ResourceSample>,
+}
+/// Task artifact
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TaskArtifact {
+    /// Artifact name (unique within the task)
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// Artifact kind (e.g. "stdout_segment", "stderr_segment")
+    #[prost(string, tag = "2")]
+    pub kind: ::prost::alloc::string::String,
+    /// Size in bytes
+    #[prost(uint64, tag = "3")]
+    pub size_bytes: u64,
+    /// Creation time (milliseconds)
+    #[prost(uint64, tag = "4")]
+    pub created_at_ms: u64,
+    /// Whether the artifact is still being written
+    #[prost(bool, tag = "5")]
+    pub live: bool,
+}
+/// Task artifact list
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TaskArtifactList {
+    /// Task ID
+    #[prost(string, tag = "1")]
+    pub task_id: ::prost::alloc::string::String,
+    /// Artifacts
+    #[prost(message, repeated, tag = "2")]
+    pub artifacts: ::prost::alloc::vec::Vec<TaskArtifact>,
+}
+/// Task artifact download request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TaskArtifactRequest {
+    /// Task ID
+    #[prost(string, tag = "1")]
+    pub task_id: ::prost::alloc::string::String,
+    /// Artifact name
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+}
+/// Task artifact chunk
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TaskArtifactChunk {
+    /// Artifact name
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// Offset of the chunk within the artifact
+    #[prost(uint64, tag = "2")]
+    pub offset: u64,
+    /// Chunk data
+    #[prost(bytes = "vec", tag = "3")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
 }
-/// ファイル読み取りリクエスト
+/// Result cache invalidation request
+/// Entries matching all specified filters are removed; no filters removes everything
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InvalidateResultCacheRequest {
+    /// Only entries for this command
+    #[prost(string, optional, tag = "1")]
+    pub command: ::core::option::Option<::prost::alloc::string::String>,
+    /// Only entries for this tenant
+    #[prost(string, optional, tag = "2")]
+    pub tenant_id: ::core::option::Option<::prost::alloc::string::String>,
+    /// Only entries whose working directory is under this path
+    #[prost(string, optional, tag = "3")]
+    pub path_prefix: ::core::option::Option<::prost::alloc::string::String>,
+}
+/// Result cache invalidation response
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InvalidateResultCacheResponse {
+    /// Number of removed entries
+    #[prost(uint64, tag = "1")]
+    pub removed: u64,
+}
+/// Rule that contributed to a policy decision
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PolicyRuleMatch {
+    /// Rule identifier (e.g. "commands.deny", "data.mcp.command.deny_reasons" or a Cedar policy ID)
+    #[prost(string, tag = "1")]
+    pub rule: ::prost::alloc::string::String,
+    /// Where the rule is defined (policy file or rule file)
+    #[prost(string, tag = "2")]
+    pub source: ::prost::alloc::string::String,
+    /// Effect of the rule ("allow", "deny" or "warn")
+    #[prost(string, tag = "3")]
+    pub effect: ::prost::alloc::string::String,
+    /// Reason, warning or description of the match
+    #[prost(string, tag = "4")]
+    pub message: ::prost::alloc::string::String,
+}
+/// Policy decision with the rules that produced it
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PolicyExplanation {
+    /// Whether the request is allowed
+    #[prost(bool, tag = "1")]
+    pub allow: bool,
+    /// Denial reasons
+    #[prost(string, repeated, tag = "2")]
+    pub reasons: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Warning messages
+    #[prost(string, repeated, tag = "3")]
+    pub warnings: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Name of the evaluator that made the decision
+    #[prost(string, tag = "4")]
+    pub evaluator: ::prost::alloc::string::String,
+    /// Matched rules in evaluation order
+    #[prost(message, repeated, tag = "5")]
+    pub rules: ::prost::alloc::vec::Vec<PolicyRuleMatch>,
+}
+/// Pre-flight policy check request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EvaluatePolicyRequest {
+    /// Request to evaluate (as it would be sent to the corresponding RPC)
+    #[prost(oneof = "evaluate_policy_request::Action", tags = "1, 2, 3, 4")]
+    pub action: ::core::option::Option<evaluate_policy_request::Action>,
+}
+/// Nested message and enum types in `EvaluatePolicyRequest`.
+pub mod evaluate_policy_request {
+    /// Request to evaluate (as it would be sent to the corresponding RPC)
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Action {
+        #[prost(message, tag = "1")]
+        Command(super::CommandRequest),
+        #[prost(message, tag = "2")]
+        ReadFile(super::ReadFileRequest),
+        #[prost(message, tag = "3")]
+        WriteFile(super::WriteFileRequest),
+        #[prost(message, tag = "4")]
+        DeleteFile(super::DeleteFileRequest),
+    }
+}
+/// Policy decision
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PolicyDecision {
+    /// Whether the request is allowed
+    #[prost(bool, tag = "1")]
+    pub allow: bool,
+    /// Denial reasons
+    #[prost(string, repeated, tag = "2")]
+    pub reasons: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Warning messages
+    #[prost(string, repeated, tag = "3")]
+    pub warnings: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Decision metadata (values are JSON encoded)
+    #[prost(map = "string, string", tag = "4")]
+    pub metadata: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+}
+/// Policy data document update request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdatePolicyDataRequest {
+    /// Dot separated path below `data` (e.g. "organization.allowed_hosts"); empty for the whole document
+    #[prost(string, tag = "1")]
+    pub path: ::prost::alloc::string::String,
+    /// New document (JSON)
+    #[prost(string, tag = "2")]
+    pub document: ::prost::alloc::string::String,
+    /// Only the policies of this tenant (the default policies if unset)
+    #[prost(string, optional, tag = "3")]
+    pub tenant_id: ::core::option::Option<::prost::alloc::string::String>,
+}
+/// Policy data document update response
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdatePolicyDataResponse {
+    /// Updated path
+    #[prost(string, tag = "1")]
+    pub path: ::prost::alloc::string::String,
+}
+/// Policy reload request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReloadPoliciesRequest {}
+/// Problem found while compiling policies
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PolicyDiagnostic {
+    /// Policy set ("default", "tenants" or "tenant:<id>")
+    #[prost(string, tag = "1")]
+    pub scope: ::prost::alloc::string::String,
+    /// Compilation or load error
+    #[prost(string, tag = "2")]
+    pub message: ::prost::alloc::string::String,
+}
+/// Policy reload response
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReloadPoliciesResponse {
+    /// Whether the new policies were activated (the previous policies stay active otherwise)
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    /// Name of the new default evaluator
+    #[prost(string, tag = "2")]
+    pub evaluator: ::prost::alloc::string::String,
+    /// Tenants whose policies were reloaded
+    #[prost(string, repeated, tag = "3")]
+    pub tenant_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Problems that prevented the reload
+    #[prost(message, repeated, tag = "4")]
+    pub diagnostics: ::prost::alloc::vec::Vec<PolicyDiagnostic>,
+}
+/// API key without its secret
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ApiKeyInfo {
+    /// Key ID
+    #[prost(string, tag = "1")]
+    pub key_id: ::prost::alloc::string::String,
+    /// User the key authenticates as
+    #[prost(string, tag = "2")]
+    pub user_id: ::prost::alloc::string::String,
+    /// Tenant of the user
+    #[prost(string, tag = "3")]
+    pub tenant_id: ::prost::alloc::string::String,
+    /// Roles of the user
+    #[prost(string, repeated, tag = "4")]
+    pub roles: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// RPCs the key may call ("ExecuteCommand", "mcp.McpService/ExecuteCommand" or "mcp.McpService/*"; all if empty)
+    #[prost(string, repeated, tag = "5")]
+    pub scopes: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Description
+    #[prost(string, tag = "6")]
+    pub description: ::prost::alloc::string::String,
+    /// Creation time (ISO 8601)
+    #[prost(string, tag = "7")]
+    pub created_at: ::prost::alloc::string::String,
+    /// Revocation time (ISO 8601, unset while the key is valid)
+    #[prost(string, optional, tag = "8")]
+    pub revoked_at: ::core::option::Option<::prost::alloc::string::String>,
+}
+/// API key creation request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateApiKeyRequest {
+    /// User the key authenticates as
+    #[prost(string, tag = "1")]
+    pub user_id: ::prost::alloc::string::String,
+    /// Tenant of the user
+    #[prost(string, tag = "2")]
+    pub tenant_id: ::prost::alloc::string::String,
+    /// Roles of the user
+    #[prost(string, repeated, tag = "3")]
+    pub roles: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// RPCs the key may call (all if empty)
+    #[prost(string, repeated, tag = "4")]
+    pub scopes: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Description
+    #[prost(string, tag = "5")]
+    pub description: ::prost::alloc::string::String,
+}
+/// API key creation response
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateApiKeyResponse {
+    /// Created key
+    #[prost(message, optional, tag = "1")]
+    pub key: ::core::option::Option<ApiKeyInfo>,
+    /// Secret to send in the x-api-key header (only stored as a hash)
+    #[prost(string, tag = "2")]
+    pub secret: ::prost::alloc::string::String,
+}
+/// API key list request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListApiKeysRequest {
+    /// Only list the keys of this tenant (all if empty)
+    #[prost(string, tag = "1")]
+    pub tenant_id: ::prost::alloc::string::String,
+}
+/// API key list response
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListApiKeysResponse {
+    /// Keys in creation order
+    #[prost(message, repeated, tag = "1")]
+    pub keys: ::prost::alloc::vec::Vec<ApiKeyInfo>,
+}
+/// API key revocation request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RevokeApiKeyRequest {
+    /// Key ID
+    #[prost(string, tag = "1")]
+    pub key_id: ::prost::alloc::string::String,
+}
+/// File read request
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ReadFileRequest {
-    /// ファイルパス
+    /// File path
     #[prost(string, tag = "1")]
     pub path: ::prost::alloc::string::String,
+    /// Offset of the first byte to read
+    #[prost(uint64, tag = "2")]
+    pub offset: u64,
+    /// Number of bytes to read (to the end of the file if unset, capped by the gateway)
+    #[prost(uint64, optional, tag = "3")]
+    pub length: ::core::option::Option<u64>,
 }
-/// ファイル読み取りレスポンス
+/// File read response
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ReadFileResponse {
-    /// ファイルパス
+    /// File path
     #[prost(string, tag = "1")]
     pub path: ::prost::alloc::string::String,
-    /// ファイル内容
+    /// File content
     #[prost(bytes = "vec", tag = "2")]
     pub content: ::prost::alloc::vec::Vec<u8>,
-    /// MIMEタイプ
+    /// MIME type
     #[prost(string, tag = "3")]
     pub mime_type: ::prost::alloc::string::String,
+    /// Error message (if any)
+    #[prost(string, optional, tag = "4")]
+    pub error: ::core::option::Option<::prost::alloc::string::String>,
+    /// Size of the whole file (bytes)
+    #[prost(uint64, tag = "5")]
+    pub file_size: u64,
 }
-/// ファイル書き込みリクエスト
+/// File write request
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct WriteFileRequest {
-    /// ファイルパス
+    /// File path
     #[prost(string, tag = "1")]
     pub path: ::prost::alloc::string::String,
-    /// ファイル内容
+    /// File content
     #[prost(bytes = "vec", tag = "2")]
     pub content: ::prost::alloc::vec::Vec<u8>,
-    /// 追記モード
+    /// Whether to create parent directories if they don't exist
     #[prost(bool, tag = "3")]
-    pub append: bool,
+    pub create_dirs: bool,
+    /// File mode (permissions, octal format)
+    #[prost(uint32, tag = "4")]
+    pub mode: u32,
 }
-/// ファイル書き込みレスポンス
+/// File write response
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct WriteFileResponse {
-    /// ファイルパス
+    /// File path
     #[prost(string, tag = "1")]
     pub path: ::prost::alloc::string::String,
-    /// 書き込みバイト数
+    /// Number of bytes written
     #[prost(uint64, tag = "2")]
     pub bytes_written: u64,
+    /// Error message (if any)
+    #[prost(string, optional, tag = "3")]
+    pub error: ::core::option::Option<::prost::alloc::string::String>,
+    /// Metadata of the written file
+    #[prost(message, optional, tag = "4")]
+    pub metadata: ::core::option::Option<FileMetadata>,
+}
+/// File metadata
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FileMetadata {
+    /// Size (bytes)
+    #[prost(uint64, tag = "1")]
+    pub size: u64,
+    /// Permission bits
+    #[prost(uint32, tag = "2")]
+    pub mode: u32,
+    /// Last modification time (ISO 8601 format)
+    #[prost(string, tag = "3")]
+    pub modified_at: ::prost::alloc::string::String,
+}
+/// Chunked file read request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReadFileStreamRequest {
+    /// File path
+    #[prost(string, tag = "1")]
+    pub path: ::prost::alloc::string::String,
+    /// Offset of the first byte to read
+    #[prost(uint64, tag = "2")]
+    pub offset: u64,
+    /// Number of bytes to read (to the end of the file if unset)
+    #[prost(uint64, optional, tag = "3")]
+    pub length: ::core::option::Option<u64>,
+    /// Chunk size (bytes; the gateway default if 0, capped by the gateway)
+    #[prost(uint32, tag = "4")]
+    pub chunk_size: u32,
+}
+/// Chunk of a file
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FileChunk {
+    /// Offset of the chunk in the file
+    #[prost(uint64, tag = "1")]
+    pub offset: u64,
+    /// Chunk content
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+    /// SHA-256 digest of the chunk content (hex)
+    #[prost(string, tag = "3")]
+    pub sha256: ::prost::alloc::string::String,
+    /// Size of the whole file (bytes; set on reads)
+    #[prost(uint64, tag = "4")]
+    pub file_size: u64,
+}
+/// Message of a chunked file write; the file options are taken from the first message
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WriteFileStreamRequest {
+    /// File path
+    #[prost(string, tag = "1")]
+    pub path: ::prost::alloc::string::String,
+    /// Whether to create parent directories if they don't exist
+    #[prost(bool, tag = "2")]
+    pub create_dirs: bool,
+    /// File mode (permissions, octal format)
+    #[prost(uint32, tag = "3")]
+    pub mode: u32,
+    /// Next chunk of the content (its offset must follow the previous chunk)
+    #[prost(message, optional, tag = "4")]
+    pub chunk: ::core::option::Option<FileChunk>,
 }
-/// ファイル削除リクエスト
+/// File delete request
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DeleteFileRequest {
-    /// ファイルパス
+    /// File path
     #[prost(string, tag = "1")]
     pub path: ::prost::alloc::string::String,
+    /// Whether to recursively delete directories
+    #[prost(bool, tag = "2")]
+    pub recursive: bool,
 }
-/// ファイル削除レスポンス
+/// File delete response
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DeleteFileResponse {
-    /// ファイルパス
+    /// File path
     #[prost(string, tag = "1")]
     pub path: ::prost::alloc::string::String,
-    /// 削除成功
+    /// Whether the deletion was successful
     #[prost(bool, tag = "2")]
     pub success: bool,
+    /// Error message (if any)
+    #[prost(string, optional, tag = "3")]
+    pub error: ::core::option::Option<::prost::alloc::string::String>,
 }
-/// ネットワークアクセス設定
+/// Network access configuration
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum NetworkAccess {
-    /// ネットワークへのアクセスを許可しない
+    /// No network access allowed
     NetworkNone = 0,
-    /// ホストと同じネットワークへのアクセスを許可
+    /// Access to the same network as the host
     NetworkHost = 1,
-    /// 特定のホストへのアクセスのみ許可
+    /// Access only to specific hosts
     NetworkRestricted = 2,
 }
 impl NetworkAccess {
@@ -304,24 +1012,26 @@ impl NetworkAccess {
         }
     }
 }
-/// タスクの状態
+/// Task status
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum TaskStatus {
-    /// タスクが作成された
+    /// Task created
     TaskCreated = 0,
-    /// タスクがキューに入った
+    /// Task queued
     TaskQueued = 1,
-    /// タスクが実行中
+    /// Task running
     TaskRunning = 2,
-    /// タスクが完了した
+    /// Task completed
     TaskCompleted = 3,
-    /// タスクが失敗した
+    /// Task failed
     TaskFailed = 4,
-    /// タスクがキャンセルされた
+    /// Task cancelled
     TaskCancelled = 5,
-    /// タスクがタイムアウトした
+    /// Task timed out
     TaskTimedOut = 6,
+    /// Task paused
+    TaskPaused = 7,
 }
 impl TaskStatus {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -337,6 +1047,7 @@ impl TaskStatus {
             TaskStatus::TaskFailed => "TASK_FAILED",
             TaskStatus::TaskCancelled => "TASK_CANCELLED",
             TaskStatus::TaskTimedOut => "TASK_TIMED_OUT",
+            TaskStatus::TaskPaused => "TASK_PAUSED",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -349,20 +1060,23 @@ impl TaskStatus {
             "TASK_FAILED" => Some(Self::TaskFailed),
             "TASK_CANCELLED" => Some(Self::TaskCancelled),
             "TASK_TIMED_OUT" => Some(Self::TaskTimedOut),
+            "TASK_PAUSED" => Some(Self::TaskPaused),
             _ => None,
         }
     }
 }
-/// タスクのタイプ
+/// Task type
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum TaskType {
-    /// コマンド実行タスク
+    /// Command execution task
     TaskCommand = 0,
-    /// ファイル操作タスク
+    /// File operation task
     TaskFile = 1,
-    /// HTTPリクエストタスク
+    /// HTTP request task
     TaskHttpRequest = 2,
+    /// Plan execution task
+    TaskPlan = 3,
 }
 impl TaskType {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -374,6 +1088,7 @@ impl TaskType {
             TaskType::TaskCommand => "TASK_COMMAND",
             TaskType::TaskFile => "TASK_FILE",
             TaskType::TaskHttpRequest => "TASK_HTTP_REQUEST",
+            TaskType::TaskPlan => "TASK_PLAN",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -382,22 +1097,58 @@ impl TaskType {
             "TASK_COMMAND" => Some(Self::TaskCommand),
             "TASK_FILE" => Some(Self::TaskFile),
             "TASK_HTTP_REQUEST" => Some(Self::TaskHttpRequest),
+            "TASK_PLAN" => Some(Self::TaskPlan),
             _ => None,
         }
     }
 }
-/// 出力チャンクタイプ
+/// Plan step status
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
-pub enum OutputChunkType {
-    /// 標準出力
-    ChunkStdout = 0,
-    /// 標準エラー出力
-    ChunkStderr = 1,
-    /// 終了コード
-    ChunkExitCode = 2,
-    /// イベント
-    ChunkEvent = 3,
+pub enum StepStatus {
+    /// The command exited with 0
+    StepSucceeded = 0,
+    /// The command exited with another code or could not be executed
+    StepFailed = 1,
+    /// The step did not run because a dependency did not succeed or the task was cancelled
+    StepSkipped = 2,
+}
+impl StepStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            StepStatus::StepSucceeded => "STEP_SUCCEEDED",
+            StepStatus::StepFailed => "STEP_FAILED",
+            StepStatus::StepSkipped => "STEP_SKIPPED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "STEP_SUCCEEDED" => Some(Self::StepSucceeded),
+            "STEP_FAILED" => Some(Self::StepFailed),
+            "STEP_SKIPPED" => Some(Self::StepSkipped),
+            _ => None,
+        }
+    }
+}
+/// Output chunk type
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum OutputChunkType {
+    /// Standard output
+    ChunkStdout = 0,
+    /// Standard error output
+    ChunkStderr = 1,
+    /// Exit code
+    ChunkExitCode = 2,
+    /// Event
+    ChunkEvent = 3,
+    /// Resource usage telemetry
+    ChunkTelemetry = 4,
 }
 impl OutputChunkType {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -410,6 +1161,7 @@ impl OutputChunkType {
             OutputChunkType::ChunkStderr => "CHUNK_STDERR",
             OutputChunkType::ChunkExitCode => "CHUNK_EXIT_CODE",
             OutputChunkType::ChunkEvent => "CHUNK_EVENT",
+            OutputChunkType::ChunkTelemetry => "CHUNK_TELEMETRY",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -419,6 +1171,7 @@ impl OutputChunkType {
             "CHUNK_STDERR" => Some(Self::ChunkStderr),
             "CHUNK_EXIT_CODE" => Some(Self::ChunkExitCode),
             "CHUNK_EVENT" => Some(Self::ChunkEvent),
+            "CHUNK_TELEMETRY" => Some(Self::ChunkTelemetry),
             _ => None,
         }
     }
@@ -428,7 +1181,8 @@ pub mod mcp_service_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
     use tonic::codegen::*;
     use tonic::codegen::http::Uri;
-    /// MCPセキュリティゲートウェイサービス
+    /// MCP (Managed Command Platform) Service
+    /// Secure gateway for executing commands and managing files
     #[derive(Debug, Clone)]
     pub struct McpServiceClient<T> {
         inner: tonic::client::Grpc<T>,
@@ -509,7 +1263,7 @@ pub mod mcp_service_client {
             self.inner = self.inner.max_encoding_message_size(limit);
             self
         }
-        /// ヘルスチェック
+        /// Health check for the service
         pub async fn health(
             &mut self,
             request: impl tonic::IntoRequest<super::HealthRequest>,
@@ -529,7 +1283,7 @@ pub mod mcp_service_client {
             req.extensions_mut().insert(GrpcMethod::new("mcp.McpService", "Health"));
             self.inner.unary(req, path, codec).await
         }
-        /// コマンド実行
+        /// Execute a command in a sandbox
         pub async fn execute_command(
             &mut self,
             request: impl tonic::IntoRequest<super::CommandRequest>,
@@ -555,7 +1309,59 @@ pub mod mcp_service_client {
                 .insert(GrpcMethod::new("mcp.McpService", "ExecuteCommand"));
             self.inner.unary(req, path, codec).await
         }
-        /// タスク状態取得
+        /// Execute a plan of commands with dependencies under one task
+        pub async fn execute_plan(
+            &mut self,
+            request: impl tonic::IntoRequest<super::PlanRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::TaskCreatedResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.McpService/ExecutePlan",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.McpService", "ExecutePlan"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Upload a script into the task workspace and run it with an interpreter
+        pub async fn execute_script(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ScriptRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::TaskCreatedResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.McpService/ExecuteScript",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.McpService", "ExecuteScript"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Get the status of a task
         pub async fn get_task_status(
             &mut self,
             request: impl tonic::IntoRequest<super::TaskStatusRequest>,
@@ -581,7 +1387,7 @@ pub mod mcp_service_client {
                 .insert(GrpcMethod::new("mcp.McpService", "GetTaskStatus"));
             self.inner.unary(req, path, codec).await
         }
-        /// タスク出力ストリーミング
+        /// Stream the output of a task in real-time
         pub async fn stream_task_output(
             &mut self,
             request: impl tonic::IntoRequest<super::TaskStatusRequest>,
@@ -607,7 +1413,7 @@ pub mod mcp_service_client {
                 .insert(GrpcMethod::new("mcp.McpService", "StreamTaskOutput"));
             self.inner.server_streaming(req, path, codec).await
         }
-        /// タスクキャンセル
+        /// Cancel a running task
         pub async fn cancel_task(
             &mut self,
             request: impl tonic::IntoRequest<super::TaskStatusRequest>,
@@ -632,12 +1438,12 @@ pub mod mcp_service_client {
             req.extensions_mut().insert(GrpcMethod::new("mcp.McpService", "CancelTask"));
             self.inner.unary(req, path, codec).await
         }
-        /// ファイル操作
-        pub async fn read_file(
+        /// Pause a running task (its processes are stopped until the task is resumed)
+        pub async fn pause_task(
             &mut self,
-            request: impl tonic::IntoRequest<super::ReadFileRequest>,
+            request: impl tonic::IntoRequest<super::TaskStatusRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::ReadFileResponse>,
+            tonic::Response<super::TaskStatusResponse>,
             tonic::Status,
         > {
             self.inner
@@ -650,16 +1456,19 @@ pub mod mcp_service_client {
                     )
                 })?;
             let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static("/mcp.McpService/ReadFile");
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.McpService/PauseTask",
+            );
             let mut req = request.into_request();
-            req.extensions_mut().insert(GrpcMethod::new("mcp.McpService", "ReadFile"));
+            req.extensions_mut().insert(GrpcMethod::new("mcp.McpService", "PauseTask"));
             self.inner.unary(req, path, codec).await
         }
-        pub async fn write_file(
+        /// Resume a paused task
+        pub async fn resume_task(
             &mut self,
-            request: impl tonic::IntoRequest<super::WriteFileRequest>,
+            request: impl tonic::IntoRequest<super::TaskStatusRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::WriteFileResponse>,
+            tonic::Response<super::TaskStatusResponse>,
             tonic::Status,
         > {
             self.inner
@@ -672,16 +1481,19 @@ pub mod mcp_service_client {
                     )
                 })?;
             let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static("/mcp.McpService/WriteFile");
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.McpService/ResumeTask",
+            );
             let mut req = request.into_request();
-            req.extensions_mut().insert(GrpcMethod::new("mcp.McpService", "WriteFile"));
+            req.extensions_mut().insert(GrpcMethod::new("mcp.McpService", "ResumeTask"));
             self.inner.unary(req, path, codec).await
         }
-        pub async fn delete_file(
+        /// List the artifacts of a task (e.g. rotated output log segments)
+        pub async fn list_task_artifacts(
             &mut self,
-            request: impl tonic::IntoRequest<super::DeleteFileRequest>,
+            request: impl tonic::IntoRequest<super::TaskStatusRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::DeleteFileResponse>,
+            tonic::Response<super::TaskArtifactList>,
             tonic::Status,
         > {
             self.inner
@@ -695,130 +1507,335 @@ pub mod mcp_service_client {
                 })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/mcp.McpService/DeleteFile",
+                "/mcp.McpService/ListTaskArtifacts",
             );
             let mut req = request.into_request();
-            req.extensions_mut().insert(GrpcMethod::new("mcp.McpService", "DeleteFile"));
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.McpService", "ListTaskArtifacts"));
             self.inner.unary(req, path, codec).await
         }
-    }
-}
-/// Generated server implementations.
-pub mod mcp_service_server {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with McpServiceServer.
-    #[async_trait]
-    pub trait McpService: Send + Sync + 'static {
-        /// ヘルスチェック
-        async fn health(
-            &self,
-            request: tonic::Request<super::HealthRequest>,
-        ) -> std::result::Result<tonic::Response<super::HealthResponse>, tonic::Status>;
-        /// コマンド実行
-        async fn execute_command(
-            &self,
-            request: tonic::Request<super::CommandRequest>,
+        /// Download a task artifact
+        pub async fn download_task_artifact(
+            &mut self,
+            request: impl tonic::IntoRequest<super::TaskArtifactRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::TaskCreatedResponse>,
+            tonic::Response<tonic::codec::Streaming<super::TaskArtifactChunk>>,
             tonic::Status,
-        >;
-        /// タスク状態取得
-        async fn get_task_status(
-            &self,
-            request: tonic::Request<super::TaskStatusRequest>,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.McpService/DownloadTaskArtifact",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.McpService", "DownloadTaskArtifact"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// Invalidate cached command results
+        pub async fn invalidate_result_cache(
+            &mut self,
+            request: impl tonic::IntoRequest<super::InvalidateResultCacheRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::TaskStatusResponse>,
+            tonic::Response<super::InvalidateResultCacheResponse>,
             tonic::Status,
-        >;
-        /// Server streaming response type for the StreamTaskOutput method.
-        type StreamTaskOutputStream: tonic::codegen::tokio_stream::Stream<
-                Item = std::result::Result<super::TaskOutputChunk, tonic::Status>,
-            >
-            + Send
-            + 'static;
-        /// タスク出力ストリーミング
-        async fn stream_task_output(
-            &self,
-            request: tonic::Request<super::TaskStatusRequest>,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.McpService/InvalidateResultCache",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.McpService", "InvalidateResultCache"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Explain the policy decision on a command execution request without executing it
+        pub async fn explain_policy(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CommandRequest>,
         ) -> std::result::Result<
-            tonic::Response<Self::StreamTaskOutputStream>,
+            tonic::Response<super::PolicyExplanation>,
             tonic::Status,
-        >;
-        /// タスクキャンセル
-        async fn cancel_task(
-            &self,
-            request: tonic::Request<super::TaskStatusRequest>,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.McpService/ExplainPolicy",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.McpService", "ExplainPolicy"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Evaluate the policy on a request without executing it (pre-flight check)
+        pub async fn evaluate_policy(
+            &mut self,
+            request: impl tonic::IntoRequest<super::EvaluatePolicyRequest>,
+        ) -> std::result::Result<tonic::Response<super::PolicyDecision>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.McpService/EvaluatePolicy",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.McpService", "EvaluatePolicy"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Replace a policy data document (e.g. team to command mappings) at runtime
+        pub async fn update_policy_data(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdatePolicyDataRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::TaskStatusResponse>,
+            tonic::Response<super::UpdatePolicyDataResponse>,
             tonic::Status,
-        >;
-        /// ファイル操作
-        async fn read_file(
-            &self,
-            request: tonic::Request<super::ReadFileRequest>,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.McpService/UpdatePolicyData",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.McpService", "UpdatePolicyData"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Read a file
+        pub async fn read_file(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ReadFileRequest>,
         ) -> std::result::Result<
             tonic::Response<super::ReadFileResponse>,
             tonic::Status,
-        >;
-        async fn write_file(
-            &self,
-            request: tonic::Request<super::WriteFileRequest>,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/mcp.McpService/ReadFile");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("mcp.McpService", "ReadFile"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Write to a file
+        pub async fn write_file(
+            &mut self,
+            request: impl tonic::IntoRequest<super::WriteFileRequest>,
         ) -> std::result::Result<
             tonic::Response<super::WriteFileResponse>,
             tonic::Status,
-        >;
-        async fn delete_file(
-            &self,
-            request: tonic::Request<super::DeleteFileRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::DeleteFileResponse>,
-            tonic::Status,
-        >;
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/mcp.McpService/WriteFile");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("mcp.McpService", "WriteFile"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Read a file in chunks
+        pub async fn read_file_stream(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ReadFileStreamRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::FileChunk>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.McpService/ReadFileStream",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.McpService", "ReadFileStream"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// Write a file sent in chunks
+        pub async fn write_file_stream(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<
+                Message = super::WriteFileStreamRequest,
+            >,
+        ) -> std::result::Result<
+            tonic::Response<super::WriteFileResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.McpService/WriteFileStream",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.McpService", "WriteFileStream"));
+            self.inner.client_streaming(req, path, codec).await
+        }
+        /// Delete a file
+        pub async fn delete_file(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteFileRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DeleteFileResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.McpService/DeleteFile",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("mcp.McpService", "DeleteFile"));
+            self.inner.unary(req, path, codec).await
+        }
     }
-    /// MCPセキュリティゲートウェイサービス
-    #[derive(Debug)]
-    pub struct McpServiceServer<T: McpService> {
-        inner: _Inner<T>,
-        accept_compression_encodings: EnabledCompressionEncodings,
-        send_compression_encodings: EnabledCompressionEncodings,
-        max_decoding_message_size: Option<usize>,
-        max_encoding_message_size: Option<usize>,
+}
+/// Generated client implementations.
+pub mod admin_service_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    /// Administrative operations of the gateway
+    #[derive(Debug, Clone)]
+    pub struct AdminServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
     }
-    struct _Inner<T>(Arc<T>);
-    impl<T: McpService> McpServiceServer<T> {
+    impl AdminServiceClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> AdminServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
         pub fn new(inner: T) -> Self {
-            Self::from_arc(Arc::new(inner))
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
         }
-        pub fn from_arc(inner: Arc<T>) -> Self {
-            let inner = _Inner(inner);
-            Self {
-                inner,
-                accept_compression_encodings: Default::default(),
-                send_compression_encodings: Default::default(),
-                max_decoding_message_size: None,
-                max_encoding_message_size: None,
-            }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
         }
         pub fn with_interceptor<F>(
             inner: T,
             interceptor: F,
-        ) -> InterceptedService<Self, F>
+        ) -> AdminServiceClient<InterceptedService<T, F>>
         where
             F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
         {
-            InterceptedService::new(Self::new(inner), interceptor)
+            AdminServiceClient::new(InterceptedService::new(inner, interceptor))
         }
-        /// Enable decompressing requests with the given encoding.
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
         #[must_use]
-        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.accept_compression_encodings.enable(encoding);
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
             self
         }
-        /// Compress responses with the given encoding, if the client supports it.
+        /// Enable decompressing responses.
         #[must_use]
-        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.send_compression_encodings.enable(encoding);
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
             self
         }
         /// Limits the maximum size of a decoded message.
@@ -826,7 +1843,7 @@ pub mod mcp_service_server {
         /// Default: `4MB`
         #[must_use]
         pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
-            self.max_decoding_message_size = Some(limit);
+            self.inner = self.inner.max_decoding_message_size(limit);
             self
         }
         /// Limits the maximum size of an encoded message.
@@ -834,45 +1851,1131 @@ pub mod mcp_service_server {
         /// Default: `usize::MAX`
         #[must_use]
         pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
-            self.max_encoding_message_size = Some(limit);
+            self.inner = self.inner.max_encoding_message_size(limit);
             self
         }
-    }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for McpServiceServer<T>
-    where
-        T: McpService,
-        B: Body + Send + 'static,
-        B::Error: Into<StdError> + Send + 'static,
-    {
-        type Response = http::Response<tonic::body::BoxBody>;
-        type Error = std::convert::Infallible;
-        type Future = BoxFuture<Self::Response, Self::Error>;
-        fn poll_ready(
+        /// Recompile the configured policies and swap them in atomically
+        pub async fn reload_policies(
             &mut self,
-            _cx: &mut Context<'_>,
-        ) -> Poll<std::result::Result<(), Self::Error>> {
-            Poll::Ready(Ok(()))
+            request: impl tonic::IntoRequest<super::ReloadPoliciesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReloadPoliciesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.AdminService/ReloadPolicies",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.AdminService", "ReloadPolicies"));
+            self.inner.unary(req, path, codec).await
         }
-        fn call(&mut self, req: http::Request<B>) -> Self::Future {
-            let inner = self.inner.clone();
-            match req.uri().path() {
-                "/mcp.McpService/Health" => {
+        /// Create an API key (the secret is only returned in the response)
+        pub async fn create_api_key(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreateApiKeyRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CreateApiKeyResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.AdminService/CreateApiKey",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.AdminService", "CreateApiKey"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// List the API keys without their secrets
+        pub async fn list_api_keys(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListApiKeysRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListApiKeysResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.AdminService/ListApiKeys",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.AdminService", "ListApiKeys"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Revoke an API key (requests with it are rejected from then on)
+        pub async fn revoke_api_key(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RevokeApiKeyRequest>,
+        ) -> std::result::Result<tonic::Response<super::ApiKeyInfo>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mcp.AdminService/RevokeApiKey",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("mcp.AdminService", "RevokeApiKey"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod mcp_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with McpServiceServer.
+    #[async_trait]
+    pub trait McpService: Send + Sync + 'static {
+        /// Health check for the service
+        async fn health(
+            &self,
+            request: tonic::Request<super::HealthRequest>,
+        ) -> std::result::Result<tonic::Response<super::HealthResponse>, tonic::Status>;
+        /// Execute a command in a sandbox
+        async fn execute_command(
+            &self,
+            request: tonic::Request<super::CommandRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::TaskCreatedResponse>,
+            tonic::Status,
+        >;
+        /// Execute a plan of commands with dependencies under one task
+        async fn execute_plan(
+            &self,
+            request: tonic::Request<super::PlanRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::TaskCreatedResponse>,
+            tonic::Status,
+        >;
+        /// Upload a script into the task workspace and run it with an interpreter
+        async fn execute_script(
+            &self,
+            request: tonic::Request<super::ScriptRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::TaskCreatedResponse>,
+            tonic::Status,
+        >;
+        /// Get the status of a task
+        async fn get_task_status(
+            &self,
+            request: tonic::Request<super::TaskStatusRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::TaskStatusResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the StreamTaskOutput method.
+        type StreamTaskOutputStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::TaskOutputChunk, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Stream the output of a task in real-time
+        async fn stream_task_output(
+            &self,
+            request: tonic::Request<super::TaskStatusRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::StreamTaskOutputStream>,
+            tonic::Status,
+        >;
+        /// Cancel a running task
+        async fn cancel_task(
+            &self,
+            request: tonic::Request<super::TaskStatusRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::TaskStatusResponse>,
+            tonic::Status,
+        >;
+        /// Pause a running task (its processes are stopped until the task is resumed)
+        async fn pause_task(
+            &self,
+            request: tonic::Request<super::TaskStatusRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::TaskStatusResponse>,
+            tonic::Status,
+        >;
+        /// Resume a paused task
+        async fn resume_task(
+            &self,
+            request: tonic::Request<super::TaskStatusRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::TaskStatusResponse>,
+            tonic::Status,
+        >;
+        /// List the artifacts of a task (e.g. rotated output log segments)
+        async fn list_task_artifacts(
+            &self,
+            request: tonic::Request<super::TaskStatusRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::TaskArtifactList>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the DownloadTaskArtifact method.
+        type DownloadTaskArtifactStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::TaskArtifactChunk, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Download a task artifact
+        async fn download_task_artifact(
+            &self,
+            request: tonic::Request<super::TaskArtifactRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::DownloadTaskArtifactStream>,
+            tonic::Status,
+        >;
+        /// Invalidate cached command results
+        async fn invalidate_result_cache(
+            &self,
+            request: tonic::Request<super::InvalidateResultCacheRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::InvalidateResultCacheResponse>,
+            tonic::Status,
+        >;
+        /// Explain the policy decision on a command execution request without executing it
+        async fn explain_policy(
+            &self,
+            request: tonic::Request<super::CommandRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PolicyExplanation>,
+            tonic::Status,
+        >;
+        /// Evaluate the policy on a request without executing it (pre-flight check)
+        async fn evaluate_policy(
+            &self,
+            request: tonic::Request<super::EvaluatePolicyRequest>,
+        ) -> std::result::Result<tonic::Response<super::PolicyDecision>, tonic::Status>;
+        /// Replace a policy data document (e.g. team to command mappings) at runtime
+        async fn update_policy_data(
+            &self,
+            request: tonic::Request<super::UpdatePolicyDataRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::UpdatePolicyDataResponse>,
+            tonic::Status,
+        >;
+        /// Read a file
+        async fn read_file(
+            &self,
+            request: tonic::Request<super::ReadFileRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReadFileResponse>,
+            tonic::Status,
+        >;
+        /// Write to a file
+        async fn write_file(
+            &self,
+            request: tonic::Request<super::WriteFileRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::WriteFileResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the ReadFileStream method.
+        type ReadFileStreamStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::FileChunk, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Read a file in chunks
+        async fn read_file_stream(
+            &self,
+            request: tonic::Request<super::ReadFileStreamRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::ReadFileStreamStream>,
+            tonic::Status,
+        >;
+        /// Write a file sent in chunks
+        async fn write_file_stream(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::WriteFileStreamRequest>>,
+        ) -> std::result::Result<
+            tonic::Response<super::WriteFileResponse>,
+            tonic::Status,
+        >;
+        /// Delete a file
+        async fn delete_file(
+            &self,
+            request: tonic::Request<super::DeleteFileRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DeleteFileResponse>,
+            tonic::Status,
+        >;
+    }
+    /// MCP (Managed Command Platform) Service
+    /// Secure gateway for executing commands and managing files
+    #[derive(Debug)]
+    pub struct McpServiceServer<T: McpService> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: McpService> McpServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for McpServiceServer<T>
+    where
+        T: McpService,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/mcp.McpService/Health" => {
+                    #[allow(non_camel_case_types)]
+                    struct HealthSvc<T: McpService>(pub Arc<T>);
+                    impl<T: McpService> tonic::server::UnaryService<super::HealthRequest>
+                    for HealthSvc<T> {
+                        type Response = super::HealthResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::HealthRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::health(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HealthSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/ExecuteCommand" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExecuteCommandSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::CommandRequest>
+                    for ExecuteCommandSvc<T> {
+                        type Response = super::TaskCreatedResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CommandRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::execute_command(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ExecuteCommandSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/ExecutePlan" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExecutePlanSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::PlanRequest>
+                    for ExecutePlanSvc<T> {
+                        type Response = super::TaskCreatedResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PlanRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::execute_plan(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ExecutePlanSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/ExecuteScript" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExecuteScriptSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::ScriptRequest>
+                    for ExecuteScriptSvc<T> {
+                        type Response = super::TaskCreatedResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ScriptRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::execute_script(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ExecuteScriptSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/GetTaskStatus" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetTaskStatusSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::TaskStatusRequest>
+                    for GetTaskStatusSvc<T> {
+                        type Response = super::TaskStatusResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TaskStatusRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::get_task_status(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetTaskStatusSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/StreamTaskOutput" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamTaskOutputSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::ServerStreamingService<super::TaskStatusRequest>
+                    for StreamTaskOutputSvc<T> {
+                        type Response = super::TaskOutputChunk;
+                        type ResponseStream = T::StreamTaskOutputStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TaskStatusRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::stream_task_output(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = StreamTaskOutputSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/CancelTask" => {
+                    #[allow(non_camel_case_types)]
+                    struct CancelTaskSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::TaskStatusRequest>
+                    for CancelTaskSvc<T> {
+                        type Response = super::TaskStatusResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TaskStatusRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::cancel_task(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = CancelTaskSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/PauseTask" => {
+                    #[allow(non_camel_case_types)]
+                    struct PauseTaskSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::TaskStatusRequest>
+                    for PauseTaskSvc<T> {
+                        type Response = super::TaskStatusResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TaskStatusRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::pause_task(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = PauseTaskSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/ResumeTask" => {
+                    #[allow(non_camel_case_types)]
+                    struct ResumeTaskSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::TaskStatusRequest>
+                    for ResumeTaskSvc<T> {
+                        type Response = super::TaskStatusResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TaskStatusRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::resume_task(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ResumeTaskSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/ListTaskArtifacts" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListTaskArtifactsSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::TaskStatusRequest>
+                    for ListTaskArtifactsSvc<T> {
+                        type Response = super::TaskArtifactList;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TaskStatusRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::list_task_artifacts(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListTaskArtifactsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/DownloadTaskArtifact" => {
+                    #[allow(non_camel_case_types)]
+                    struct DownloadTaskArtifactSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::ServerStreamingService<super::TaskArtifactRequest>
+                    for DownloadTaskArtifactSvc<T> {
+                        type Response = super::TaskArtifactChunk;
+                        type ResponseStream = T::DownloadTaskArtifactStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TaskArtifactRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::download_task_artifact(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = DownloadTaskArtifactSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/InvalidateResultCache" => {
+                    #[allow(non_camel_case_types)]
+                    struct InvalidateResultCacheSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::InvalidateResultCacheRequest>
+                    for InvalidateResultCacheSvc<T> {
+                        type Response = super::InvalidateResultCacheResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::InvalidateResultCacheRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::invalidate_result_cache(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = InvalidateResultCacheSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/ExplainPolicy" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExplainPolicySvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::CommandRequest>
+                    for ExplainPolicySvc<T> {
+                        type Response = super::PolicyExplanation;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CommandRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::explain_policy(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ExplainPolicySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/EvaluatePolicy" => {
+                    #[allow(non_camel_case_types)]
+                    struct EvaluatePolicySvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::EvaluatePolicyRequest>
+                    for EvaluatePolicySvc<T> {
+                        type Response = super::PolicyDecision;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::EvaluatePolicyRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::evaluate_policy(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = EvaluatePolicySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/UpdatePolicyData" => {
+                    #[allow(non_camel_case_types)]
+                    struct UpdatePolicyDataSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::UpdatePolicyDataRequest>
+                    for UpdatePolicyDataSvc<T> {
+                        type Response = super::UpdatePolicyDataResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdatePolicyDataRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::update_policy_data(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = UpdatePolicyDataSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/ReadFile" => {
+                    #[allow(non_camel_case_types)]
+                    struct ReadFileSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::ReadFileRequest>
+                    for ReadFileSvc<T> {
+                        type Response = super::ReadFileResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ReadFileRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as McpService>::read_file(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ReadFileSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mcp.McpService/WriteFile" => {
                     #[allow(non_camel_case_types)]
-                    struct HealthSvc<T: McpService>(pub Arc<T>);
-                    impl<T: McpService> tonic::server::UnaryService<super::HealthRequest>
-                    for HealthSvc<T> {
-                        type Response = super::HealthResponse;
+                    struct WriteFileSvc<T: McpService>(pub Arc<T>);
+                    impl<
+                        T: McpService,
+                    > tonic::server::UnaryService<super::WriteFileRequest>
+                    for WriteFileSvc<T> {
+                        type Response = super::WriteFileResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::HealthRequest>,
+                            request: tonic::Request<super::WriteFileRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as McpService>::health(&inner, request).await
+                                <T as McpService>::write_file(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -884,7 +2987,7 @@ pub mod mcp_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = HealthSvc(inner);
+                        let method = WriteFileSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -900,25 +3003,26 @@ pub mod mcp_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/mcp.McpService/ExecuteCommand" => {
+                "/mcp.McpService/ReadFileStream" => {
                     #[allow(non_camel_case_types)]
-                    struct ExecuteCommandSvc<T: McpService>(pub Arc<T>);
+                    struct ReadFileStreamSvc<T: McpService>(pub Arc<T>);
                     impl<
                         T: McpService,
-                    > tonic::server::UnaryService<super::CommandRequest>
-                    for ExecuteCommandSvc<T> {
-                        type Response = super::TaskCreatedResponse;
+                    > tonic::server::ServerStreamingService<super::ReadFileStreamRequest>
+                    for ReadFileStreamSvc<T> {
+                        type Response = super::FileChunk;
+                        type ResponseStream = T::ReadFileStreamStream;
                         type Future = BoxFuture<
-                            tonic::Response<Self::Response>,
+                            tonic::Response<Self::ResponseStream>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::CommandRequest>,
+                            request: tonic::Request<super::ReadFileStreamRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as McpService>::execute_command(&inner, request).await
+                                <T as McpService>::read_file_stream(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -930,7 +3034,7 @@ pub mod mcp_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = ExecuteCommandSvc(inner);
+                        let method = ReadFileStreamSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -941,30 +3045,33 @@ pub mod mcp_service_server {
                                 max_decoding_message_size,
                                 max_encoding_message_size,
                             );
-                        let res = grpc.unary(method, req).await;
+                        let res = grpc.server_streaming(method, req).await;
                         Ok(res)
                     };
                     Box::pin(fut)
                 }
-                "/mcp.McpService/GetTaskStatus" => {
+                "/mcp.McpService/WriteFileStream" => {
                     #[allow(non_camel_case_types)]
-                    struct GetTaskStatusSvc<T: McpService>(pub Arc<T>);
+                    struct WriteFileStreamSvc<T: McpService>(pub Arc<T>);
                     impl<
                         T: McpService,
-                    > tonic::server::UnaryService<super::TaskStatusRequest>
-                    for GetTaskStatusSvc<T> {
-                        type Response = super::TaskStatusResponse;
+                    > tonic::server::ClientStreamingService<
+                        super::WriteFileStreamRequest,
+                    > for WriteFileStreamSvc<T> {
+                        type Response = super::WriteFileResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::TaskStatusRequest>,
+                            request: tonic::Request<
+                                tonic::Streaming<super::WriteFileStreamRequest>,
+                            >,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as McpService>::get_task_status(&inner, request).await
+                                <T as McpService>::write_file_stream(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -976,7 +3083,7 @@ pub mod mcp_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = GetTaskStatusSvc(inner);
+                        let method = WriteFileStreamSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -987,31 +3094,30 @@ pub mod mcp_service_server {
                                 max_decoding_message_size,
                                 max_encoding_message_size,
                             );
-                        let res = grpc.unary(method, req).await;
+                        let res = grpc.client_streaming(method, req).await;
                         Ok(res)
                     };
                     Box::pin(fut)
                 }
-                "/mcp.McpService/StreamTaskOutput" => {
+                "/mcp.McpService/DeleteFile" => {
                     #[allow(non_camel_case_types)]
-                    struct StreamTaskOutputSvc<T: McpService>(pub Arc<T>);
+                    struct DeleteFileSvc<T: McpService>(pub Arc<T>);
                     impl<
                         T: McpService,
-                    > tonic::server::ServerStreamingService<super::TaskStatusRequest>
-                    for StreamTaskOutputSvc<T> {
-                        type Response = super::TaskOutputChunk;
-                        type ResponseStream = T::StreamTaskOutputStream;
+                    > tonic::server::UnaryService<super::DeleteFileRequest>
+                    for DeleteFileSvc<T> {
+                        type Response = super::DeleteFileResponse;
                         type Future = BoxFuture<
-                            tonic::Response<Self::ResponseStream>,
+                            tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::TaskStatusRequest>,
+                            request: tonic::Request<super::DeleteFileRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as McpService>::stream_task_output(&inner, request).await
+                                <T as McpService>::delete_file(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1023,7 +3129,7 @@ pub mod mcp_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = StreamTaskOutputSvc(inner);
+                        let method = DeleteFileSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1034,30 +3140,188 @@ pub mod mcp_service_server {
                                 max_decoding_message_size,
                                 max_encoding_message_size,
                             );
-                        let res = grpc.server_streaming(method, req).await;
+                        let res = grpc.unary(method, req).await;
                         Ok(res)
                     };
                     Box::pin(fut)
                 }
-                "/mcp.McpService/CancelTask" => {
+                _ => {
+                    Box::pin(async move {
+                        Ok(
+                            http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", "12")
+                                .header("content-type", "application/grpc")
+                                .body(empty_body())
+                                .unwrap(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+    impl<T: McpService> Clone for McpServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    impl<T: McpService> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(Arc::clone(&self.0))
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: McpService> tonic::server::NamedService for McpServiceServer<T> {
+        const NAME: &'static str = "mcp.McpService";
+    }
+}
+/// Generated server implementations.
+pub mod admin_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with AdminServiceServer.
+    #[async_trait]
+    pub trait AdminService: Send + Sync + 'static {
+        /// Recompile the configured policies and swap them in atomically
+        async fn reload_policies(
+            &self,
+            request: tonic::Request<super::ReloadPoliciesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReloadPoliciesResponse>,
+            tonic::Status,
+        >;
+        /// Create an API key (the secret is only returned in the response)
+        async fn create_api_key(
+            &self,
+            request: tonic::Request<super::CreateApiKeyRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CreateApiKeyResponse>,
+            tonic::Status,
+        >;
+        /// List the API keys without their secrets
+        async fn list_api_keys(
+            &self,
+            request: tonic::Request<super::ListApiKeysRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListApiKeysResponse>,
+            tonic::Status,
+        >;
+        /// Revoke an API key (requests with it are rejected from then on)
+        async fn revoke_api_key(
+            &self,
+            request: tonic::Request<super::RevokeApiKeyRequest>,
+        ) -> std::result::Result<tonic::Response<super::ApiKeyInfo>, tonic::Status>;
+    }
+    /// Administrative operations of the gateway
+    #[derive(Debug)]
+    pub struct AdminServiceServer<T: AdminService> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: AdminService> AdminServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for AdminServiceServer<T>
+    where
+        T: AdminService,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/mcp.AdminService/ReloadPolicies" => {
                     #[allow(non_camel_case_types)]
-                    struct CancelTaskSvc<T: McpService>(pub Arc<T>);
+                    struct ReloadPoliciesSvc<T: AdminService>(pub Arc<T>);
                     impl<
-                        T: McpService,
-                    > tonic::server::UnaryService<super::TaskStatusRequest>
-                    for CancelTaskSvc<T> {
-                        type Response = super::TaskStatusResponse;
+                        T: AdminService,
+                    > tonic::server::UnaryService<super::ReloadPoliciesRequest>
+                    for ReloadPoliciesSvc<T> {
+                        type Response = super::ReloadPoliciesResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::TaskStatusRequest>,
+                            request: tonic::Request<super::ReloadPoliciesRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as McpService>::cancel_task(&inner, request).await
+                                <T as AdminService>::reload_policies(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1069,7 +3333,7 @@ pub mod mcp_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = CancelTaskSvc(inner);
+                        let method = ReloadPoliciesSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1085,25 +3349,25 @@ pub mod mcp_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/mcp.McpService/ReadFile" => {
+                "/mcp.AdminService/CreateApiKey" => {
                     #[allow(non_camel_case_types)]
-                    struct ReadFileSvc<T: McpService>(pub Arc<T>);
+                    struct CreateApiKeySvc<T: AdminService>(pub Arc<T>);
                     impl<
-                        T: McpService,
-                    > tonic::server::UnaryService<super::ReadFileRequest>
-                    for ReadFileSvc<T> {
-                        type Response = super::ReadFileResponse;
+                        T: AdminService,
+                    > tonic::server::UnaryService<super::CreateApiKeyRequest>
+                    for CreateApiKeySvc<T> {
+                        type Response = super::CreateApiKeyResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ReadFileRequest>,
+                            request: tonic::Request<super::CreateApiKeyRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as McpService>::read_file(&inner, request).await
+                                <T as AdminService>::create_api_key(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1115,7 +3379,7 @@ pub mod mcp_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = ReadFileSvc(inner);
+                        let method = CreateApiKeySvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1131,25 +3395,25 @@ pub mod mcp_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/mcp.McpService/WriteFile" => {
+                "/mcp.AdminService/ListApiKeys" => {
                     #[allow(non_camel_case_types)]
-                    struct WriteFileSvc<T: McpService>(pub Arc<T>);
+                    struct ListApiKeysSvc<T: AdminService>(pub Arc<T>);
                     impl<
-                        T: McpService,
-                    > tonic::server::UnaryService<super::WriteFileRequest>
-                    for WriteFileSvc<T> {
-                        type Response = super::WriteFileResponse;
+                        T: AdminService,
+                    > tonic::server::UnaryService<super::ListApiKeysRequest>
+                    for ListApiKeysSvc<T> {
+                        type Response = super::ListApiKeysResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::WriteFileRequest>,
+                            request: tonic::Request<super::ListApiKeysRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as McpService>::write_file(&inner, request).await
+                                <T as AdminService>::list_api_keys(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1161,7 +3425,7 @@ pub mod mcp_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = WriteFileSvc(inner);
+                        let method = ListApiKeysSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1177,25 +3441,25 @@ pub mod mcp_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/mcp.McpService/DeleteFile" => {
+                "/mcp.AdminService/RevokeApiKey" => {
                     #[allow(non_camel_case_types)]
-                    struct DeleteFileSvc<T: McpService>(pub Arc<T>);
+                    struct RevokeApiKeySvc<T: AdminService>(pub Arc<T>);
                     impl<
-                        T: McpService,
-                    > tonic::server::UnaryService<super::DeleteFileRequest>
-                    for DeleteFileSvc<T> {
-                        type Response = super::DeleteFileResponse;
+                        T: AdminService,
+                    > tonic::server::UnaryService<super::RevokeApiKeyRequest>
+                    for RevokeApiKeySvc<T> {
+                        type Response = super::ApiKeyInfo;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::DeleteFileRequest>,
+                            request: tonic::Request<super::RevokeApiKeyRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as McpService>::delete_file(&inner, request).await
+                                <T as AdminService>::revoke_api_key(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1207,7 +3471,7 @@ pub mod mcp_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = DeleteFileSvc(inner);
+                        let method = RevokeApiKeySvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1238,7 +3502,7 @@ pub mod mcp_service_server {
             }
         }
     }
-    impl<T: McpService> Clone for McpServiceServer<T> {
+    impl<T: AdminService> Clone for AdminServiceServer<T> {
         fn clone(&self) -> Self {
             let inner = self.inner.clone();
             Self {
@@ -1250,7 +3514,7 @@ pub mod mcp_service_server {
             }
         }
     }
-    impl<T: McpService> Clone for _Inner<T> {
+    impl<T: AdminService> Clone for _Inner<T> {
         fn clone(&self) -> Self {
             Self(Arc::clone(&self.0))
         }
@@ -1260,7 +3524,7 @@ pub mod mcp_service_server {
             write!(f, "{:?}", self.0)
         }
     }
-    impl<T: McpService> tonic::server::NamedService for McpServiceServer<T> {
-        const NAME: &'static str = "mcp.McpService";
+    impl<T: AdminService> tonic::server::NamedService for AdminServiceServer<T> {
+        const NAME: &'static str = "mcp.AdminService";
     }
 }
@@ -0,0 +1,307 @@
+//! Role-based authorization of gRPC methods
+//!
+//! Rules read from the JSON file named by `MCP_RBAC_CONFIG` map RPC methods to the roles allowed
+//! to call them. They are checked after authentication (see [`crate::auth`]) and before the
+//! request reaches the service, so the domain policy engine only sees requests whose caller may
+//! call the method at all. The other transports are checked against the same rules: tool calls
+//! of the MCP HTTP transport as the RPC backing the tool (see [`crate::mcp_tools`]), and the task
+//! output WebSocket as `StreamTaskOutput`:
+//!
+//! ```json
+//! {
+//!   "rules": [
+//!     {"methods": ["Health"], "roles": ["*"]},
+//!     {"methods": ["CancelTask", "PauseTask", "ResumeTask"], "roles": ["operator"]},
+//!     {"methods": ["mcp.AdminService/*"], "roles": ["admin"]}
+//!   ],
+//!   "default": "allow"
+//! }
+//! ```
+//!
+//! Methods use the syntax of API key scopes (`ExecuteCommand`, `mcp.McpService/ExecuteCommand`
+//! or `mcp.McpService/*`). The first rule naming the called method decides: the caller needs one
+//! of its roles, and `*` admits every caller, including requests to public RPCs made without
+//! credentials. Methods no rule names are allowed or denied according to `default`.
+
+use crate::api_keys::{is_valid_scope, scope_matches};
+use crate::auth;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::grpc::IntoStatus;
+use mcp_policy::models::UserInfo;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tonic::body::BoxBody;
+use tonic::codegen::{http, BoxFuture, Service};
+use tower::Layer;
+use tracing::warn;
+
+/// Role admitting every caller
+pub const ANY_ROLE: &str = "*";
+
+/// Decision for methods no rule names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultAction {
+    /// Every caller may call the method
+    #[default]
+    Allow,
+    /// No caller may call the method
+    Deny,
+}
+
+/// Roles allowed to call a set of methods
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RbacRule {
+    /// Methods the rule applies to
+    pub methods: Vec<String>,
+    /// Roles allowed to call them (`*` for every caller)
+    pub roles: Vec<String>,
+}
+
+/// Method-level authorization rules
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RbacConfig {
+    /// Rules, in the order they are checked
+    pub rules: Vec<RbacRule>,
+    /// Decision for methods no rule names
+    #[serde(default)]
+    pub default: DefaultAction,
+}
+
+impl RbacConfig {
+    /// Build the settings from environment variables (`None` if no rules are configured)
+    ///
+    /// * `MCP_RBAC_CONFIG` - JSON file with the rules
+    pub fn from_env() -> McpResult<Option<Self>> {
+        match std::env::var("MCP_RBAC_CONFIG") {
+            Ok(path) if !path.trim().is_empty() => Self::from_file(path.trim()).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Load the rules from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| McpError::Internal(format!("Failed to read RBAC configuration {}: {}", path.display(), e)))?;
+        let config: Self = serde_json::from_str(&content)
+            .map_err(|e| McpError::InvalidRequest(format!("Invalid RBAC configuration {}: {}", path.display(), e)))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check that every rule names valid methods and at least one role
+    pub fn validate(&self) -> McpResult<()> {
+        for (index, rule) in self.rules.iter().enumerate() {
+            if rule.methods.is_empty() || rule.roles.is_empty() {
+                return Err(McpError::InvalidRequest(format!("RBAC rule {} needs methods and roles", index)));
+            }
+            if let Some(method) = rule.methods.iter().find(|method| !is_valid_scope(method)) {
+                return Err(McpError::InvalidRequest(format!("Invalid method in RBAC rule {}: {}", index, method)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a caller with `roles` may call the RPC at `path` (`/<service>/<method>`)
+    pub fn allows(&self, roles: &[String], path: &str) -> bool {
+        let rule = self
+            .rules
+            .iter()
+            .find(|rule| rule.methods.iter().any(|method| scope_matches(method, path)));
+        match rule {
+            Some(rule) => rule.roles.iter().any(|role| role == ANY_ROLE || roles.contains(role)),
+            None => self.default == DefaultAction::Allow,
+        }
+    }
+
+    /// Check that `caller` may call the RPC at `path`, logging denials
+    pub fn authorize(&self, caller: Option<&UserInfo>, path: &str) -> McpResult<()> {
+        let roles = caller.map(|user| user.roles.as_slice()).unwrap_or_default();
+        if self.allows(roles, path) {
+            return Ok(());
+        }
+
+        let caller_id = caller.map_or("anonymous caller", |user| user.id.as_str());
+        warn!("RBAC denied {} to {} (roles: {:?})", path, caller_id, roles);
+        Err(McpError::PolicyViolation(format!("{} may not call {}", caller_id, path)))
+    }
+}
+
+/// Layer rejecting requests whose caller has none of the roles allowed for the method
+#[derive(Debug, Clone)]
+pub struct RbacLayer {
+    config: Arc<RbacConfig>,
+}
+
+impl RbacLayer {
+    /// Authorize requests with the rules of `config`
+    pub fn new(config: impl Into<Arc<RbacConfig>>) -> Self {
+        Self { config: config.into() }
+    }
+}
+
+impl<S> Layer<S> for RbacLayer {
+    type Service = RbacService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RbacService {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Service checking the caller's roles against the rules
+///
+/// Has to run inside [`crate::auth::AuthLayer`], which makes the caller known.
+#[derive(Debug, Clone)]
+pub struct RbacService<S> {
+    inner: S,
+    config: Arc<RbacConfig>,
+}
+
+impl<S, B> Service<http::Request<B>> for RbacService<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        match self.config.authorize(auth::current_user().as_ref(), request.uri().path()) {
+            Ok(()) => Box::pin(self.inner.call(request)),
+            Err(e) => Box::pin(async move { Ok(e.into_status().to_http()) }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_keys::{ApiKeyStore, API_KEY_HEADER};
+    use crate::auth::{AuthLayer, Authenticator};
+    use crate::proto::{HealthRequest, McpServiceClient, McpServiceServer, TaskStatusRequest};
+    use crate::McpServiceImpl;
+    use mcp_policy::PolicyEngine;
+    use mcp_sandbox::CommandExecutor;
+    use std::collections::HashMap;
+    use std::time::SystemTime;
+    use tonic::metadata::MetadataValue;
+    use tonic::transport::{Channel, Server};
+    use tonic::Code;
+
+    fn config(default: &str) -> RbacConfig {
+        serde_json::from_value(serde_json::json!({
+            "rules": [
+                {"methods": ["Health"], "roles": ["*"]},
+                {"methods": ["mcp.McpService/CancelTask", "PauseTask"], "roles": ["operator"]},
+                {"methods": ["mcp.AdminService/*"], "roles": ["admin"]},
+                {"methods": ["mcp.McpService/*"], "roles": ["developer", "operator"]},
+            ],
+            "default": default,
+        }))
+        .unwrap()
+    }
+
+    fn roles(roles: &[&str]) -> Vec<String> {
+        roles.iter().map(|role| role.to_string()).collect()
+    }
+
+    // Test for the first matching rule deciding
+    #[test]
+    fn test_allows() {
+        let config = config("deny");
+        assert!(config.allows(&roles(&["operator"]), "/mcp.McpService/CancelTask"));
+        assert!(!config.allows(&roles(&["developer"]), "/mcp.McpService/CancelTask"));
+        assert!(!config.allows(&roles(&["developer"]), "/mcp.McpService/PauseTask"));
+        assert!(config.allows(&roles(&["developer"]), "/mcp.McpService/ExecuteCommand"));
+        assert!(config.allows(&roles(&["admin"]), "/mcp.AdminService/ReloadPolicies"));
+        assert!(!config.allows(&roles(&["admin"]), "/mcp.McpService/ExecuteCommand"));
+        assert!(config.allows(&[], "/mcp.McpService/Health"));
+
+        // Methods no rule names
+        assert!(!config.allows(&roles(&["admin"]), "/grpc.health.v1.Health/Check"));
+        let config = RbacConfig {
+            default: DefaultAction::Allow,
+            ..config
+        };
+        assert!(config.allows(&[], "/grpc.health.v1.Health/Check"));
+    }
+
+    // Test for loading the rules
+    #[test]
+    fn test_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rbac.json");
+        std::fs::write(&path, r#"{"rules": [{"methods": ["CancelTask"], "roles": ["operator"]}]}"#).unwrap();
+        let config = RbacConfig::from_file(&path).unwrap();
+        assert_eq!(config.default, DefaultAction::Allow);
+        assert_eq!(config.rules[0].roles, vec!["operator"]);
+
+        for invalid in [
+            r#"{"rules": [{"methods": ["mcp.McpService/"], "roles": ["operator"]}]}"#,
+            r#"{"rules": [{"methods": ["CancelTask"], "roles": []}]}"#,
+            r#"{"rules": [], "default": "block"}"#,
+            r#"{"rules": [], "defaults": "deny"}"#,
+        ] {
+            std::fs::write(&path, invalid).unwrap();
+            assert!(matches!(RbacConfig::from_file(&path), Err(McpError::InvalidRequest(_))), "{}", invalid);
+        }
+        assert!(RbacConfig::from_file(dir.path().join("missing.json")).is_err());
+    }
+
+    // Test for authorizing authenticated gRPC requests
+    #[tokio::test]
+    async fn test_layer() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ApiKeyStore::open(dir.path().join("api_keys.json")).unwrap());
+        let user = |roles: &[&str]| UserInfo {
+            id: "alice".to_string(),
+            tenant_id: "tenant-a".to_string(),
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+            attributes: HashMap::new(),
+        };
+        let (_, operator) = store.create(&user(&["operator"]), vec![], "").unwrap();
+        let (_, developer) = store.create(&user(&["developer"]), vec![], "").unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now());
+        tokio::spawn(
+            Server::builder()
+                .layer(AuthLayer::new(Authenticator::new().with_api_keys(store)))
+                .layer(RbacLayer::new(config("deny")))
+                .add_service(McpServiceServer::new(service))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        let channel = Channel::from_shared(format!("http://{}", addr)).unwrap().connect().await.unwrap();
+        let mut client = McpServiceClient::new(channel);
+        let cancel_request = |secret: &str| {
+            let mut request = tonic::Request::new(TaskStatusRequest { task_id: "task-missing".to_string() });
+            request.metadata_mut().insert(API_KEY_HEADER, MetadataValue::try_from(secret).unwrap());
+            request
+        };
+
+        // Only the operator reaches the service
+        let status = client.cancel_task(cancel_request(&operator)).await.unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        let status = client.cancel_task(cancel_request(&developer)).await.unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert!(status.message().contains("CancelTask"));
+
+        // Public RPCs stay callable without credentials
+        assert!(client.health(HealthRequest {}).await.is_ok());
+    }
+}
@@ -0,0 +1,524 @@
+//! Content-addressed result cache for idempotent commands
+//!
+//! Results of read-only commands (marked cacheable by the policy) are stored under a key
+//! derived from the command, its arguments, a configurable subset of its environment,
+//! the tenant and a snapshot hash of the working directory. Any change to the workspace
+//! contents (path, size or modification time of an entry) therefore produces a new key.
+//! Below [`WORKSPACE_MOUNT_POINT`], the snapshot is taken of the host directory the sandbox
+//! mounts there (see [`SandboxConfig::host_path`]), not of the gateway's path of that name.
+//! Files outside the working directory are not part of the snapshot; TTLs and the
+//! invalidation API cover those.
+//!
+//! The cache is opt-in and disabled by default.
+
+use crate::metrics;
+use crate::proto;
+use dashmap::DashMap;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{get_env_var_or, hex, parse_positive};
+use mcp_sandbox::models::SandboxConfig;
+use mcp_sandbox::workspace::WORKSPACE_MOUNT_POINT;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Task metadata key holding the cache outcome ("hit" or "miss")
+pub const METADATA_RESULT_CACHE: &str = "result_cache";
+
+/// Result cache settings
+#[derive(Debug, Clone)]
+pub struct ResultCacheConfig {
+    /// Whether the cache is used at all
+    pub enabled: bool,
+    /// Lifetime of an entry
+    pub ttl: Duration,
+    /// Maximum number of entries (the oldest entry is evicted first)
+    pub max_entries: usize,
+    /// Environment variables that take part in the cache key
+    pub env_keys: Vec<String>,
+    /// Workspaces with more entries than this are not cached
+    pub max_snapshot_entries: usize,
+}
+
+impl Default for ResultCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl: Duration::from_secs(300),
+            max_entries: 1024,
+            env_keys: ["PATH", "HOME", "LANG", "LC_ALL"]
+                .iter()
+                .map(|key| key.to_string())
+                .collect(),
+            max_snapshot_entries: 10_000,
+        }
+    }
+}
+
+impl ResultCacheConfig {
+    /// Build the settings from environment variables
+    ///
+    /// * `MCP_RESULT_CACHE_ENABLED` - `true` to enable the cache
+    /// * `MCP_RESULT_CACHE_TTL_SECS` - entry lifetime
+    /// * `MCP_RESULT_CACHE_MAX_ENTRIES` - maximum number of entries
+    /// * `MCP_RESULT_CACHE_ENV_KEYS` - environment variables in the key (`PATH,LANG`)
+    pub fn from_env() -> McpResult<Self> {
+        let mut config = Self {
+            enabled: get_env_var_or("MCP_RESULT_CACHE_ENABLED", "false") == "true",
+            ..Self::default()
+        };
+
+        if let Ok(value) = std::env::var("MCP_RESULT_CACHE_TTL_SECS") {
+            config.ttl = Duration::from_secs(parse_positive("MCP_RESULT_CACHE_TTL_SECS", &value)?);
+        }
+        if let Ok(value) = std::env::var("MCP_RESULT_CACHE_MAX_ENTRIES") {
+            config.max_entries = parse_positive("MCP_RESULT_CACHE_MAX_ENTRIES", &value)?;
+        }
+        if let Ok(value) = std::env::var("MCP_RESULT_CACHE_ENV_KEYS") {
+            config.env_keys = value
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+
+        Ok(config)
+    }
+}
+
+/// Inputs that identify a command execution
+#[derive(Debug, Clone, Copy)]
+pub struct CacheKeyInput<'a> {
+    /// Tenant ID (results are never shared across tenants)
+    pub tenant_id: &'a str,
+    /// Command name
+    pub command: &'a str,
+    /// Command arguments
+    pub args: &'a [String],
+    /// Environment variables of the request
+    pub env: &'a HashMap<String, String>,
+    /// Working directory (the gateway's directory when unset)
+    pub cwd: Option<&'a str>,
+    /// Sandbox configuration of the task, which maps the workspace to its host directory
+    pub sandbox_config: &'a SandboxConfig,
+}
+
+/// Filter for invalidation; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct InvalidationFilter {
+    /// Command name
+    pub command: Option<String>,
+    /// Tenant ID
+    pub tenant_id: Option<String>,
+    /// Working directory prefix
+    pub path_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    result: proto::TaskResult,
+    tenant_id: String,
+    command: String,
+    cwd: String,
+    inserted_at: Instant,
+}
+
+impl InvalidationFilter {
+    fn matches(&self, entry: &CacheEntry) -> bool {
+        self.command.iter().all(|command| &entry.command == command)
+            && self.tenant_id.iter().all(|tenant| &entry.tenant_id == tenant)
+            && self
+                .path_prefix
+                .iter()
+                .all(|prefix| Path::new(&entry.cwd).starts_with(prefix))
+    }
+}
+
+/// Cache of successful command results
+#[derive(Debug, Default)]
+pub struct ResultCache {
+    config: ResultCacheConfig,
+    entries: DashMap<String, CacheEntry>,
+}
+
+impl ResultCache {
+    /// Create a cache with the given settings
+    pub fn new(config: ResultCacheConfig) -> Self {
+        Self {
+            config,
+            entries: DashMap::new(),
+        }
+    }
+
+    /// Whether the cache is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Number of stored entries (including expired ones not yet removed)
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Compute the cache key of an execution
+    ///
+    /// Returns `None` when the workspace is too large to snapshot.
+    pub fn key(&self, input: &CacheKeyInput<'_>) -> McpResult<Option<String>> {
+        let cwd = resolve_cwd(input.cwd)?;
+        let host_dir = host_dir(input.sandbox_config, &cwd);
+        let snapshot = match workspace_snapshot(&host_dir, self.config.max_snapshot_entries)? {
+            Some(snapshot) => snapshot,
+            None => return Ok(None),
+        };
+
+        let mut hasher = Sha256::new();
+        // Length-prefix every field so that different splits never collide
+        let mut field = |value: &[u8]| {
+            hasher.update((value.len() as u64).to_le_bytes());
+            hasher.update(value);
+        };
+
+        field(input.tenant_id.as_bytes());
+        field(input.command.as_bytes());
+        field(&(input.args.len() as u64).to_le_bytes());
+        for arg in input.args {
+            field(arg.as_bytes());
+        }
+
+        let mut env_keys: Vec<&String> = self.config.env_keys.iter().collect();
+        env_keys.sort();
+        env_keys.dedup();
+        for key in env_keys {
+            field(key.as_bytes());
+            match input.env.get(key) {
+                Some(value) => field(value.as_bytes()),
+                None => field(b"\0unset"),
+            }
+        }
+
+        field(cwd.to_string_lossy().as_bytes());
+        field(&snapshot);
+
+        Ok(Some(hex(&hasher.finalize())))
+    }
+
+    /// Look up a cached result
+    pub fn get(&self, key: &str) -> Option<proto::TaskResult> {
+        let expired = match self.entries.get(key) {
+            Some(entry) if entry.inserted_at.elapsed() < self.config.ttl => {
+                metrics::increment_result_cache_requests("hit");
+                return Some(entry.result.clone());
+            }
+            Some(_) => true,
+            None => false,
+        };
+
+        if expired && self.entries.remove(key).is_some() {
+            metrics::increment_result_cache_evictions("expired");
+        }
+        metrics::increment_result_cache_requests("miss");
+        self.update_entries_gauge();
+        None
+    }
+
+    /// Store a result
+    ///
+    /// The tenant, command and working directory are kept for invalidation.
+    pub fn insert(
+        &self,
+        key: String,
+        tenant_id: &str,
+        command: &str,
+        cwd: Option<&str>,
+        result: proto::TaskResult,
+    ) {
+        if self.entries.len() >= self.config.max_entries && !self.entries.contains_key(&key) {
+            self.evict_oldest();
+        }
+
+        let cwd = resolve_cwd(cwd)
+            .map(|cwd| cwd.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.entries.insert(
+            key,
+            CacheEntry {
+                result,
+                tenant_id: tenant_id.to_string(),
+                command: command.to_string(),
+                cwd,
+                inserted_at: Instant::now(),
+            },
+        );
+        self.update_entries_gauge();
+    }
+
+    /// Remove entries matching the filter and return how many were removed
+    pub fn invalidate(&self, filter: &InvalidationFilter) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| !filter.matches(entry));
+        let removed = before.saturating_sub(self.entries.len());
+
+        for _ in 0..removed {
+            metrics::increment_result_cache_evictions("invalidated");
+        }
+        self.update_entries_gauge();
+        removed
+    }
+
+    fn evict_oldest(&self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|entry| entry.inserted_at)
+            .map(|entry| entry.key().clone());
+
+        if let Some(key) = oldest {
+            self.entries.remove(&key);
+            metrics::increment_result_cache_evictions("capacity");
+        }
+    }
+
+    fn update_entries_gauge(&self) {
+        metrics::set_result_cache_entries(self.entries.len() as i64);
+    }
+}
+
+fn resolve_cwd(cwd: Option<&str>) -> McpResult<PathBuf> {
+    match cwd {
+        Some(cwd) if !cwd.is_empty() => Ok(PathBuf::from(cwd)),
+        _ => std::env::current_dir()
+            .map_err(|e| McpError::Internal(format!("Failed to get current directory: {}", e))),
+    }
+}
+
+/// Host directory behind a working directory of the sandbox
+fn host_dir(config: &SandboxConfig, cwd: &Path) -> PathBuf {
+    let mount = Path::new(WORKSPACE_MOUNT_POINT);
+    match cwd.strip_prefix(mount) {
+        Ok(relative) => config.host_path(mount).join(relative),
+        Err(_) => cwd.to_path_buf(),
+    }
+}
+
+/// Hash the path, size and modification time of every entry below `root`
+///
+/// Returns `None` when the tree has more than `max_entries` entries.
+fn workspace_snapshot(root: &Path, max_entries: usize) -> McpResult<Option<Vec<u8>>> {
+    let mut entries = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let read_dir = match std::fs::read_dir(&dir) {
+            Ok(read_dir) => read_dir,
+            // A missing working directory makes the command fail anyway
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(McpError::Internal(format!(
+                    "Failed to read directory {}: {}",
+                    dir.display(),
+                    e
+                )))
+            }
+        };
+
+        for entry in read_dir {
+            let entry = entry
+                .map_err(|e| McpError::Internal(format!("Failed to read directory entry: {}", e)))?;
+            let metadata = entry
+                .metadata()
+                .map_err(|e| McpError::Internal(format!("Failed to read metadata: {}", e)))?;
+
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_nanos())
+                .unwrap_or_default();
+            let path = entry.path();
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().into_owned();
+            entries.push((relative, metadata.len(), modified, metadata.is_dir()));
+
+            if entries.len() > max_entries {
+                return Ok(None);
+            }
+            // Symlinked directories are not followed (entry.metadata() does not traverse links)
+            if metadata.is_dir() {
+                pending.push(path);
+            }
+        }
+    }
+
+    entries.sort();
+
+    let mut hasher = Sha256::new();
+    for (path, len, modified, is_dir) in entries {
+        hasher.update((path.len() as u64).to_le_bytes());
+        hasher.update(path.as_bytes());
+        hasher.update(len.to_le_bytes());
+        hasher.update(modified.to_le_bytes());
+        hasher.update([is_dir as u8]);
+    }
+
+    Ok(Some(hasher.finalize().to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(ttl: Duration, max_entries: usize) -> ResultCache {
+        ResultCache::new(ResultCacheConfig {
+            enabled: true,
+            ttl,
+            max_entries,
+            env_keys: vec!["LANG".to_string()],
+            max_snapshot_entries: 100,
+        })
+    }
+
+    fn result(stdout: &str) -> proto::TaskResult {
+        proto::TaskResult {
+            exit_code: 0,
+            stdout: stdout.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_key_changes_with_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_str().unwrap();
+        let cache = cache(Duration::from_secs(60), 10);
+        let args = vec!["-la".to_string()];
+        let env = HashMap::new();
+        let sandbox_config = SandboxConfig::default();
+        let input = CacheKeyInput {
+            tenant_id: "tenant1",
+            command: "ls",
+            args: &args,
+            env: &env,
+            cwd: Some(cwd),
+            sandbox_config: &sandbox_config,
+        };
+        let key = cache.key(&input).unwrap().unwrap();
+
+        // Same inputs produce the same key
+        assert_eq!(cache.key(&input).unwrap().unwrap(), key);
+
+        // Variables outside the configured subset are ignored
+        let other_env = HashMap::from([("OTHER".to_string(), "1".to_string())]);
+        let input = CacheKeyInput { env: &other_env, ..input };
+        assert_eq!(cache.key(&input).unwrap().unwrap(), key);
+
+        let lang_env = HashMap::from([("LANG".to_string(), "C".to_string())]);
+        let input = CacheKeyInput { env: &lang_env, ..input };
+        assert_ne!(cache.key(&input).unwrap().unwrap(), key);
+
+        let other_tenant = CacheKeyInput { tenant_id: "tenant2", ..input };
+        assert_ne!(cache.key(&other_tenant).unwrap(), cache.key(&input).unwrap());
+
+        // Workspace changes produce a new key
+        let before = cache.key(&input).unwrap().unwrap();
+        std::fs::write(dir.path().join("file.txt"), "data").unwrap();
+        assert_ne!(cache.key(&input).unwrap().unwrap(), before);
+    }
+
+    #[test]
+    fn test_large_workspace_is_not_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..5 {
+            std::fs::write(dir.path().join(format!("{}.txt", i)), "").unwrap();
+        }
+
+        let mut cache = cache(Duration::from_secs(60), 10);
+        cache.config.max_snapshot_entries = 3;
+        let env = HashMap::new();
+        let input = CacheKeyInput {
+            tenant_id: "tenant1",
+            command: "ls",
+            args: &[],
+            env: &env,
+            cwd: dir.path().to_str(),
+            sandbox_config: &SandboxConfig::default(),
+        };
+        assert!(cache.key(&input).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_key_snapshots_task_workspace() {
+        let workspace = tempfile::tempdir().unwrap();
+        std::fs::create_dir(workspace.path().join("src")).unwrap();
+        let sandbox_config = SandboxConfig {
+            workspace_dir: Some(workspace.path().to_path_buf()),
+            ..Default::default()
+        };
+        let cache = cache(Duration::from_secs(60), 10);
+        let env = HashMap::new();
+
+        for cwd in ["/workspace", "/workspace/src"] {
+            let input = CacheKeyInput {
+                tenant_id: "tenant1",
+                command: "ls",
+                args: &[],
+                env: &env,
+                cwd: Some(cwd),
+                sandbox_config: &sandbox_config,
+            };
+            let key = cache.key(&input).unwrap().unwrap();
+            cache.insert(key.clone(), "tenant1", "ls", Some(cwd), result("before"));
+            assert!(cache.get(&key).is_some());
+
+            // A changed file of the task's workspace misses the cache
+            std::fs::write(workspace.path().join("src").join("main.rs"), cwd).unwrap();
+            let changed = cache.key(&input).unwrap().unwrap();
+            assert_ne!(changed, key);
+            assert!(cache.get(&changed).is_none());
+        }
+    }
+
+    #[test]
+    fn test_ttl_and_capacity() {
+        let insert = |cache: &ResultCache, key: &str| {
+            cache.insert(key.to_string(), "tenant1", "ls", Some("/tmp"), result(key));
+        };
+
+        let cache = cache(Duration::from_millis(10), 2);
+        insert(&cache, "a");
+        assert_eq!(cache.get("a").unwrap().stdout, "a");
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(cache.get("a").is_none());
+        assert!(cache.is_empty());
+
+        let cache = self::cache(Duration::from_secs(60), 2);
+        insert(&cache, "a");
+        insert(&cache, "b");
+        insert(&cache, "c");
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("c").is_some());
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let cache = cache(Duration::from_secs(60), 10);
+        cache.insert("ls".to_string(), "tenant1", "ls", Some("/workspace/a"), result("ls"));
+        cache.insert("cat".to_string(), "tenant1", "cat", Some("/workspace/b"), result("cat"));
+        cache.insert("other".to_string(), "tenant2", "ls", Some("/workspace/a"), result("other"));
+
+        let filter = InvalidationFilter { path_prefix: Some("/workspace/b".to_string()), ..Default::default() };
+        assert_eq!(cache.invalidate(&filter), 1);
+
+        let filter = InvalidationFilter { tenant_id: Some("tenant1".to_string()), ..Default::default() };
+        assert_eq!(cache.invalidate(&filter), 1);
+
+        assert_eq!(cache.invalidate(&InvalidationFilter::default()), 1);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,768 @@
+//! Sandbox directives in policy decisions
+//!
+//! Policies control the isolation of each command through the metadata of their
+//! decision. The following keys are translated into the `SandboxConfig` of the task and
+//! replace the defaults of the executor:
+//!
+//! | Key              | Value                                                         |
+//! |------------------|---------------------------------------------------------------|
+//! | `network_access` | `"none"`, `"host"` or `"restricted"`                          |
+//! | `network_hosts`  | hosts reachable with `"restricted"` network access (see [`mcp_sandbox::egress`]) |
+//! | `rw_paths`       | paths with read-write permission                              |
+//! | `ro_paths`       | paths with read-only permission                               |
+//! | `denied_paths`   | denied paths                                                  |
+//! | `cpu_limit`      | CPU limit (cores)                                             |
+//! | `memory_limit`   | memory limit in bytes, or a string with a `K`, `M` or `G` suffix |
+//! | `pids_limit`     | process count limit                                           |
+//! | `io_weight`      | IO weight                                                     |
+//! | `disk_limit`     | disk quota for writes (see [`mcp_sandbox::quota`]), bytes or a size such as `"1G"` |
+//! | `cpu_time_limit` | CPU time limit in seconds, independent of the wall-clock timeout (see [`mcp_sandbox::usage`]) |
+//! | `open_files_limit` | maximum number of open files of each process (see [`mcp_sandbox::rlimit`]) |
+//! | `file_size_limit` | maximum size of a file written by a process, bytes or a size such as `"100M"` |
+//! | `core_dump_limit` | maximum size of a core dump, bytes or a size (core dumps are disabled otherwise) |
+//! | `stack_limit`    | maximum stack size of each process, bytes or a size such as `"8M"` |
+//! | `sandbox_backend` | `"bubblewrap"`, `"container"` or `"firecracker"` (microVM, e.g. for untrusted tenants) |
+//! | `seccomp_profile` | name of a seccomp profile (see [`mcp_sandbox::seccomp`]), e.g. `"basic"` |
+//! | `workspace_mode` | `"direct"`, `"ephemeral"` or `"commit_on_success"` (see [`mcp_sandbox::overlay`]) |
+//! | `retained_capabilities` | capabilities kept for trusted profiles (see [`mcp_sandbox::capabilities`]) |
+//! | `apparmor_profile` | AppArmor profile the command is confined by (see [`mcp_sandbox::mac`]) |
+//! | `selinux_context` | SELinux context the command is confined by, `user:role:type[:level]` |
+//! | `share_ipc`      | `true` to share the IPC namespace of the host instead of isolating it |
+//! | `share_uts`      | `true` to share the UTS namespace (and hostname) of the host  |
+//! | `hostname`       | hostname of the sandbox unless the UTS namespace is shared (default `"mcp-sandbox"`) |
+//! | `extra_bwrap_args` | additional bubblewrap flags such as `"--new-session"` (see [`ALLOWED_EXTRA_BWRAP_ARGS`]) |
+//!
+//! Other metadata keys are ignored. A malformed directive fails the request, so that a
+//! mistake in a policy never silently loosens the isolation of a command.
+//!
+//! The `command_limits` of a decision (see [`CommandLimits`]) are applied on top of the
+//! directives: the CPU and memory limits requested by the client are used up to the maximums
+//! of the policy, clamped with a warning above them, and replaced by the policy defaults when
+//! they are not requested.
+//!
+//! Clients may request their own sandbox settings per task (see [`RequestedSandbox`]).
+//! The configuration resulting from the executor defaults and the policy is the ceiling of
+//! such requests: extra mounts are accepted unless they fall under a denied path, the network
+//! access may only be narrowed, and resource limits above the configured ones are clamped with
+//! a warning. Requests can never loosen the isolation the policy imposes. A seccomp profile
+//! requested by name or by a preset only applies when the policy does not name one.
+
+use mcp_common::error::{McpError, McpResult};
+use mcp_policy::models::parse_memory_size;
+use mcp_policy::CommandLimits;
+use mcp_sandbox::bubblewrap::{validate_extra_args, validate_hostname, ALLOWED_EXTRA_BWRAP_ARGS};
+use mcp_sandbox::capabilities::canonical_names;
+use mcp_sandbox::egress::EgressRule;
+use mcp_sandbox::mac::validate_label;
+use mcp_sandbox::seccomp::validate_profile_name;
+use mcp_sandbox::models::{MacProfile, NetworkAccess, ResourceLimits, SandboxBackend, WorkspaceMode};
+use mcp_sandbox::SandboxConfig;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Task metadata key listing the applied sandbox directives (comma separated)
+pub const METADATA_SANDBOX_DIRECTIVES: &str = "sandbox_directives";
+/// Task metadata key with the warnings about requested limits clamped by the policy ("; " separated)
+pub const METADATA_LIMIT_WARNINGS: &str = "limit_warnings";
+
+/// Network access mode
+pub const DIRECTIVE_NETWORK_ACCESS: &str = "network_access";
+/// Hosts reachable with restricted network access
+pub const DIRECTIVE_NETWORK_HOSTS: &str = "network_hosts";
+/// Read-write paths
+pub const DIRECTIVE_RW_PATHS: &str = "rw_paths";
+/// Read-only paths
+pub const DIRECTIVE_RO_PATHS: &str = "ro_paths";
+/// Denied paths
+pub const DIRECTIVE_DENIED_PATHS: &str = "denied_paths";
+/// CPU limit (cores)
+pub const DIRECTIVE_CPU_LIMIT: &str = "cpu_limit";
+/// Memory limit (bytes)
+pub const DIRECTIVE_MEMORY_LIMIT: &str = "memory_limit";
+/// Process count limit
+pub const DIRECTIVE_PIDS_LIMIT: &str = "pids_limit";
+/// IO weight
+pub const DIRECTIVE_IO_WEIGHT: &str = "io_weight";
+/// Disk quota for writes (bytes)
+pub const DIRECTIVE_DISK_LIMIT: &str = "disk_limit";
+/// CPU time limit (seconds)
+pub const DIRECTIVE_CPU_TIME_LIMIT: &str = "cpu_time_limit";
+/// Maximum number of open files per process
+pub const DIRECTIVE_OPEN_FILES_LIMIT: &str = "open_files_limit";
+/// Maximum file size per process (bytes)
+pub const DIRECTIVE_FILE_SIZE_LIMIT: &str = "file_size_limit";
+/// Maximum core dump size (bytes)
+pub const DIRECTIVE_CORE_DUMP_LIMIT: &str = "core_dump_limit";
+/// Maximum stack size per process (bytes)
+pub const DIRECTIVE_STACK_LIMIT: &str = "stack_limit";
+/// Isolation backend
+pub const DIRECTIVE_SANDBOX_BACKEND: &str = "sandbox_backend";
+/// Seccomp profile name
+pub const DIRECTIVE_SECCOMP_PROFILE: &str = "seccomp_profile";
+/// Handling of changes to the read-write paths
+pub const DIRECTIVE_WORKSPACE_MODE: &str = "workspace_mode";
+/// Capabilities kept for trusted profiles
+pub const DIRECTIVE_RETAINED_CAPABILITIES: &str = "retained_capabilities";
+/// AppArmor profile of the command
+pub const DIRECTIVE_APPARMOR_PROFILE: &str = "apparmor_profile";
+/// SELinux context of the command
+pub const DIRECTIVE_SELINUX_CONTEXT: &str = "selinux_context";
+/// Whether the IPC namespace of the host is shared
+pub const DIRECTIVE_SHARE_IPC: &str = "share_ipc";
+/// Whether the UTS namespace of the host is shared
+pub const DIRECTIVE_SHARE_UTS: &str = "share_uts";
+/// Hostname of the sandbox
+pub const DIRECTIVE_HOSTNAME: &str = "hostname";
+/// Additional bubblewrap flags
+pub const DIRECTIVE_EXTRA_BWRAP_ARGS: &str = "extra_bwrap_args";
+
+/// Apply the sandbox directives of a decision to a sandbox configuration
+///
+/// Returns the names of the applied directives in the order of the table above.
+pub fn apply_sandbox_directives(
+    config: &mut SandboxConfig,
+    metadata: &HashMap<String, Value>,
+) -> McpResult<Vec<&'static str>> {
+    let mut applied = Vec::new();
+    let mut directive = |name: &'static str| {
+        let value = metadata.get(name);
+        if value.is_some() {
+            applied.push(name);
+        }
+        value
+    };
+
+    let network_access = directive(DIRECTIVE_NETWORK_ACCESS);
+    let network_hosts = directive(DIRECTIVE_NETWORK_HOSTS);
+    if let Some(value) = network_access {
+        config.network_access = match value.as_str() {
+            Some("restricted") => NetworkAccess::Restricted(match network_hosts {
+                Some(hosts) => egress_hosts(hosts)?,
+                None => Vec::new(),
+            }),
+            Some("none") | Some("host") if network_hosts.is_some() => return Err(hosts_without_restriction()),
+            Some("none") => NetworkAccess::None,
+            Some("host") => NetworkAccess::Host,
+            _ => {
+                return Err(invalid(
+                    DIRECTIVE_NETWORK_ACCESS,
+                    value,
+                    "expected \"none\", \"host\" or \"restricted\"",
+                ))
+            }
+        };
+    } else if network_hosts.is_some() {
+        return Err(hosts_without_restriction());
+    }
+
+    if let Some(value) = directive(DIRECTIVE_RW_PATHS) {
+        config.rw_paths = paths(DIRECTIVE_RW_PATHS, value)?;
+    }
+    if let Some(value) = directive(DIRECTIVE_RO_PATHS) {
+        config.ro_paths = paths(DIRECTIVE_RO_PATHS, value)?;
+    }
+    if let Some(value) = directive(DIRECTIVE_DENIED_PATHS) {
+        config.denied_paths = paths(DIRECTIVE_DENIED_PATHS, value)?;
+    }
+
+    let limits = &mut config.resource_limits;
+    if let Some(value) = directive(DIRECTIVE_CPU_LIMIT) {
+        limits.cpu_limit = Some(
+            value
+                .as_f64()
+                .filter(|cores| *cores > 0.0)
+                .ok_or_else(|| invalid(DIRECTIVE_CPU_LIMIT, value, "expected a positive number of cores"))?,
+        );
+    }
+    if let Some(value) = directive(DIRECTIVE_MEMORY_LIMIT) {
+        limits.memory_limit = Some(size_bytes(DIRECTIVE_MEMORY_LIMIT, value)?);
+    }
+    if let Some(value) = directive(DIRECTIVE_PIDS_LIMIT) {
+        limits.pids_limit = Some(positive_u32(DIRECTIVE_PIDS_LIMIT, value)?);
+    }
+    if let Some(value) = directive(DIRECTIVE_IO_WEIGHT) {
+        limits.io_weight = Some(positive_u32(DIRECTIVE_IO_WEIGHT, value)?);
+    }
+    if let Some(value) = directive(DIRECTIVE_DISK_LIMIT) {
+        limits.disk_limit = Some(size_bytes(DIRECTIVE_DISK_LIMIT, value)?);
+    }
+    if let Some(value) = directive(DIRECTIVE_CPU_TIME_LIMIT) {
+        limits.cpu_time_limit = Some(positive_u32(DIRECTIVE_CPU_TIME_LIMIT, value)? as u64);
+    }
+    if let Some(value) = directive(DIRECTIVE_OPEN_FILES_LIMIT) {
+        limits.open_files_limit = Some(positive_u32(DIRECTIVE_OPEN_FILES_LIMIT, value)? as u64);
+    }
+    if let Some(value) = directive(DIRECTIVE_FILE_SIZE_LIMIT) {
+        limits.file_size_limit = Some(size_bytes(DIRECTIVE_FILE_SIZE_LIMIT, value)?);
+    }
+    if let Some(value) = directive(DIRECTIVE_CORE_DUMP_LIMIT) {
+        limits.core_dump_limit = Some(size_bytes(DIRECTIVE_CORE_DUMP_LIMIT, value)?);
+    }
+    if let Some(value) = directive(DIRECTIVE_STACK_LIMIT) {
+        limits.stack_limit = Some(size_bytes(DIRECTIVE_STACK_LIMIT, value)?);
+    }
+
+    if let Some(value) = directive(DIRECTIVE_SANDBOX_BACKEND) {
+        config.backend = match value.as_str() {
+            Some("bubblewrap") => SandboxBackend::Bubblewrap,
+            Some("container") => SandboxBackend::Container,
+            Some("firecracker") => SandboxBackend::Firecracker,
+            _ => {
+                return Err(invalid(
+                    DIRECTIVE_SANDBOX_BACKEND,
+                    value,
+                    "expected \"bubblewrap\", \"container\" or \"firecracker\"",
+                ))
+            }
+        };
+    }
+    if let Some(value) = directive(DIRECTIVE_SECCOMP_PROFILE) {
+        // Whether the profile exists is only known to the runner, which fails unknown profiles
+        config.seccomp_profile_name = Some(
+            value
+                .as_str()
+                .filter(|name| validate_profile_name(name).is_ok())
+                .ok_or_else(|| invalid(DIRECTIVE_SECCOMP_PROFILE, value, "expected a seccomp profile name"))?
+                .to_string(),
+        );
+    }
+    if let Some(value) = directive(DIRECTIVE_WORKSPACE_MODE) {
+        config.workspace_mode = match value.as_str() {
+            Some("direct") => WorkspaceMode::Direct,
+            Some("ephemeral") => WorkspaceMode::Ephemeral,
+            Some("commit_on_success") => WorkspaceMode::CommitOnSuccess,
+            _ => {
+                return Err(invalid(
+                    DIRECTIVE_WORKSPACE_MODE,
+                    value,
+                    "expected \"direct\", \"ephemeral\" or \"commit_on_success\"",
+                ))
+            }
+        };
+    }
+    if let Some(value) = directive(DIRECTIVE_RETAINED_CAPABILITIES) {
+        let capabilities = strings(DIRECTIVE_RETAINED_CAPABILITIES, value)?;
+        if canonical_names(&capabilities).is_err() {
+            return Err(invalid(DIRECTIVE_RETAINED_CAPABILITIES, value, "expected capabilities such as \"CAP_CHOWN\""));
+        }
+        config.retained_capabilities = capabilities;
+    }
+    // Whether the label can be applied on this host is checked by the runner
+    let apparmor_profile = directive(DIRECTIVE_APPARMOR_PROFILE);
+    let selinux_context = directive(DIRECTIVE_SELINUX_CONTEXT);
+    if let (Some(value), Some(_)) = (apparmor_profile, selinux_context) {
+        return Err(invalid(DIRECTIVE_APPARMOR_PROFILE, value, "cannot be combined with selinux_context"));
+    }
+    if let Some(value) = apparmor_profile {
+        config.mac_profile = Some(mac_profile(DIRECTIVE_APPARMOR_PROFILE, value, MacProfile::AppArmor)?);
+    }
+    if let Some(value) = selinux_context {
+        config.mac_profile = Some(mac_profile(DIRECTIVE_SELINUX_CONTEXT, value, MacProfile::SeLinux)?);
+    }
+    if let Some(value) = directive(DIRECTIVE_SHARE_IPC) {
+        config.share_ipc = boolean(DIRECTIVE_SHARE_IPC, value)?;
+    }
+    if let Some(value) = directive(DIRECTIVE_SHARE_UTS) {
+        config.share_uts = boolean(DIRECTIVE_SHARE_UTS, value)?;
+    }
+    if let Some(value) = directive(DIRECTIVE_HOSTNAME) {
+        config.hostname = value
+            .as_str()
+            .filter(|hostname| validate_hostname(hostname).is_ok())
+            .ok_or_else(|| invalid(DIRECTIVE_HOSTNAME, value, "expected a hostname such as \"build-sandbox\""))?
+            .to_string();
+    }
+    if let Some(value) = directive(DIRECTIVE_EXTRA_BWRAP_ARGS) {
+        let args = strings(DIRECTIVE_EXTRA_BWRAP_ARGS, value)?;
+        if validate_extra_args(&args).is_err() {
+            return Err(invalid(
+                DIRECTIVE_EXTRA_BWRAP_ARGS,
+                value,
+                &format!("expected flags of {}", ALLOWED_EXTRA_BWRAP_ARGS.join(", ")),
+            ));
+        }
+        config.extra_bwrap_args = args;
+    }
+
+    Ok(applied)
+}
+
+/// Apply the per-command limits of a decision to the requested CPU and memory limits
+///
+/// Returns a warning for every requested value that was clamped to a maximum.
+pub fn apply_command_limits(
+    config: &mut SandboxConfig,
+    command_limits: &CommandLimits,
+    requested: &ResourceLimits,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let limits = &mut config.resource_limits;
+
+    limits.cpu_limit = clamp(
+        &mut warnings,
+        "cpu_limit",
+        requested.cpu_limit,
+        command_limits.default_cpu_cores.or(limits.cpu_limit),
+        command_limits.max_cpu_cores,
+    );
+    limits.memory_limit = clamp(
+        &mut warnings,
+        "memory_limit",
+        requested.memory_limit,
+        command_limits.default_memory.or(limits.memory_limit),
+        command_limits.max_memory,
+    );
+
+    warnings
+}
+
+/// Sandbox settings requested by a client for one task
+#[derive(Debug, Clone, Default)]
+pub struct RequestedSandbox {
+    /// Requested network access (`None` keeps the configured access)
+    pub network_access: Option<NetworkAccess>,
+    /// Extra paths with read-write permission
+    pub rw_paths: Vec<PathBuf>,
+    /// Extra paths with read-only permission
+    pub ro_paths: Vec<PathBuf>,
+    /// Extra denied paths
+    pub denied_paths: Vec<PathBuf>,
+    /// Requested resource limits
+    pub resource_limits: ResourceLimits,
+    /// Seccomp profile requested by name or by a preset (see [`mcp_sandbox::presets`]), used
+    /// unless the executor or the policy names one
+    pub seccomp_profile: Option<String>,
+}
+
+/// Apply the sandbox settings requested by a client, bounded by a sandbox configuration
+///
+/// `config` holds the executor defaults with the policy directives applied. The CPU and
+/// memory limits are bounded by the `command_limits` of the decision if there are any (see
+/// [`apply_command_limits`]), otherwise by the configured limits. Returns a warning for every
+/// requested value that was clamped; a mount under a denied path fails the request.
+pub fn apply_requested_sandbox(
+    config: &mut SandboxConfig,
+    requested: &RequestedSandbox,
+    command_limits: Option<&CommandLimits>,
+) -> McpResult<Vec<String>> {
+    let mut warnings = Vec::new();
+
+    for path in requested.rw_paths.iter().chain(&requested.ro_paths).chain(&requested.denied_paths) {
+        if !path.is_absolute() {
+            return Err(McpError::InvalidRequest(format!(
+                "Sandbox path {} must be absolute",
+                path.display()
+            )));
+        }
+    }
+    for path in requested.rw_paths.iter().chain(&requested.ro_paths) {
+        if let Some(denied) = config.denied_paths.iter().find(|denied| path.starts_with(denied)) {
+            return Err(McpError::PolicyViolation(format!(
+                "Sandbox mount {} is under the denied path {}",
+                path.display(),
+                denied.display()
+            )));
+        }
+    }
+    config.rw_paths.extend(requested.rw_paths.iter().cloned());
+    config.ro_paths.extend(requested.ro_paths.iter().cloned());
+    config.denied_paths.extend(requested.denied_paths.iter().cloned());
+
+    if let Some(network_access) = &requested.network_access {
+        if network_rank(network_access) > network_rank(&config.network_access) {
+            warnings.push(format!(
+                "network_access {} exceeds the policy maximum {} and was clamped",
+                network_name(network_access),
+                network_name(&config.network_access)
+            ));
+        } else if !matches!(
+            (network_access, &config.network_access),
+            (NetworkAccess::Restricted(_), NetworkAccess::Restricted(_))
+        ) {
+            // Restricted access keeps the hosts allowed by the policy, if any
+            config.network_access = network_access.clone();
+        }
+    }
+
+    if config.seccomp_profile_name.is_none() {
+        config.seccomp_profile_name = requested.seccomp_profile.clone();
+    }
+
+    let requested_limits = &requested.resource_limits;
+    match command_limits {
+        Some(command_limits) => warnings.extend(apply_command_limits(config, command_limits, requested_limits)),
+        None => {
+            let limits = &mut config.resource_limits;
+            bound(&mut warnings, "cpu_limit", requested_limits.cpu_limit, &mut limits.cpu_limit);
+            bound(&mut warnings, "memory_limit", requested_limits.memory_limit, &mut limits.memory_limit);
+        }
+    }
+    let limits = &mut config.resource_limits;
+    bound(&mut warnings, "pids_limit", requested_limits.pids_limit, &mut limits.pids_limit);
+    bound(&mut warnings, "io_weight", requested_limits.io_weight, &mut limits.io_weight);
+
+    Ok(warnings)
+}
+
+// Permissiveness of a network access mode
+fn network_rank(network_access: &NetworkAccess) -> u8 {
+    match network_access {
+        NetworkAccess::None => 0,
+        NetworkAccess::Restricted(_) => 1,
+        NetworkAccess::Host => 2,
+    }
+}
+
+fn network_name(network_access: &NetworkAccess) -> &'static str {
+    match network_access {
+        NetworkAccess::None => "none",
+        NetworkAccess::Restricted(_) => "restricted",
+        NetworkAccess::Host => "host",
+    }
+}
+
+// Requested value bounded by the configured one
+fn bound<T: PartialOrd + Copy + std::fmt::Display>(
+    warnings: &mut Vec<String>,
+    name: &str,
+    requested: Option<T>,
+    configured: &mut Option<T>,
+) {
+    *configured = clamp(warnings, name, requested, *configured, *configured);
+}
+
+// Requested value (or the default) bounded by the maximum
+fn clamp<T: PartialOrd + Copy + std::fmt::Display>(
+    warnings: &mut Vec<String>,
+    name: &str,
+    requested: Option<T>,
+    default: Option<T>,
+    maximum: Option<T>,
+) -> Option<T> {
+    let value = requested.or(default)?;
+    match maximum {
+        Some(maximum) if value > maximum => {
+            if requested.is_some() {
+                warnings.push(format!("{} {} exceeds the policy maximum {} and was clamped", name, value, maximum));
+            }
+            Some(maximum)
+        }
+        _ => Some(value),
+    }
+}
+
+fn hosts_without_restriction() -> McpError {
+    McpError::Sandbox(format!(
+        "Sandbox directive '{}' requires '{}' to be \"restricted\"",
+        DIRECTIVE_NETWORK_HOSTS, DIRECTIVE_NETWORK_ACCESS
+    ))
+}
+
+fn invalid(name: &str, value: &Value, expected: &str) -> McpError {
+    McpError::Sandbox(format!("Invalid sandbox directive '{}' in policy decision: {}, {}", name, value, expected))
+}
+
+fn boolean(name: &str, value: &Value) -> McpResult<bool> {
+    value.as_bool().ok_or_else(|| invalid(name, value, "expected true or false"))
+}
+
+fn strings(name: &str, value: &Value) -> McpResult<Vec<String>> {
+    value
+        .as_array()
+        .and_then(|values| values.iter().map(|value| value.as_str().map(str::to_string)).collect())
+        .ok_or_else(|| invalid(name, value, "expected a list of strings"))
+}
+
+// AppArmor profile or SELinux context
+fn mac_profile(name: &str, value: &Value, label: fn(String) -> MacProfile) -> McpResult<MacProfile> {
+    value
+        .as_str()
+        .map(|value| label(value.to_string()))
+        .filter(|profile| validate_label(profile).is_ok())
+        .ok_or_else(|| invalid(name, value, "expected an AppArmor profile name or an SELinux context"))
+}
+
+// Allowlist entries of the egress proxy
+fn egress_hosts(value: &Value) -> McpResult<Vec<String>> {
+    let hosts = strings(DIRECTIVE_NETWORK_HOSTS, value)?;
+    if hosts.iter().any(|host| EgressRule::parse(host).is_err()) {
+        return Err(invalid(DIRECTIVE_NETWORK_HOSTS, value, "expected hosts such as \"api.example.com:443\""));
+    }
+    Ok(hosts)
+}
+
+fn paths(name: &str, value: &Value) -> McpResult<Vec<PathBuf>> {
+    let paths = strings(name, value)?;
+    if paths.iter().any(|path| !path.starts_with('/')) {
+        return Err(invalid(name, value, "expected absolute paths"));
+    }
+    Ok(paths.into_iter().map(PathBuf::from).collect())
+}
+
+fn positive_u32(name: &str, value: &Value) -> McpResult<u32> {
+    value
+        .as_u64()
+        .filter(|number| *number > 0)
+        .and_then(|number| u32::try_from(number).ok())
+        .ok_or_else(|| invalid(name, value, "expected a positive integer"))
+}
+
+// Bytes, or a number with a binary K, M or G suffix
+fn size_bytes(name: &str, value: &Value) -> McpResult<u64> {
+    let error = || invalid(name, value, "expected bytes or a size such as \"512M\"");
+
+    let bytes = match value {
+        Value::Number(number) => number.as_u64(),
+        Value::String(size) => parse_memory_size(size),
+        _ => None,
+    };
+    bytes.filter(|bytes| *bytes > 0).ok_or_else(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn apply(metadata: Value) -> McpResult<(SandboxConfig, Vec<&'static str>)> {
+        let metadata: HashMap<String, Value> = serde_json::from_value(metadata).unwrap();
+        let mut config = SandboxConfig::default();
+        let applied = apply_sandbox_directives(&mut config, &metadata)?;
+        Ok((config, applied))
+    }
+
+    #[test]
+    fn test_apply_directives() {
+        let (config, applied) = apply(json!({
+            "cacheable": true,
+            "network_access": "restricted",
+            "network_hosts": ["api.example.com"],
+            "rw_paths": ["/workspace/project"],
+            "memory_limit": "512M",
+            "cpu_limit": 0.5,
+            "pids_limit": 64,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            applied,
+            vec!["network_access", "network_hosts", "rw_paths", "cpu_limit", "memory_limit", "pids_limit"]
+        );
+        assert_eq!(config.network_access, NetworkAccess::Restricted(vec!["api.example.com".to_string()]));
+        assert_eq!(config.rw_paths, vec![PathBuf::from("/workspace/project")]);
+        // Paths without a directive keep the defaults
+        assert_eq!(config.ro_paths, SandboxConfig::default().ro_paths);
+        assert_eq!(config.resource_limits.memory_limit, Some(512 * 1024 * 1024));
+        assert_eq!(config.resource_limits.cpu_limit, Some(0.5));
+        assert_eq!(config.resource_limits.pids_limit, Some(64));
+        assert_eq!(config.resource_limits.io_weight, None);
+
+        let (config, applied) = apply(json!({ "network_access": "host", "memory_limit": 1048576 })).unwrap();
+        assert_eq!(config.network_access, NetworkAccess::Host);
+        assert_eq!(config.resource_limits.memory_limit, Some(1048576));
+        assert_eq!(applied, vec!["network_access", "memory_limit"]);
+        assert_eq!(config.backend, SandboxBackend::Bubblewrap);
+
+        let (config, applied) = apply(json!({ "sandbox_backend": "firecracker" })).unwrap();
+        assert_eq!(config.backend, SandboxBackend::Firecracker);
+        assert_eq!(applied, vec!["sandbox_backend"]);
+        let (config, _) = apply(json!({ "sandbox_backend": "container" })).unwrap();
+        assert_eq!(config.backend, SandboxBackend::Container);
+        let (config, applied) = apply(json!({ "seccomp_profile": "build-tools" })).unwrap();
+        assert_eq!(config.seccomp_profile_name.as_deref(), Some("build-tools"));
+        assert_eq!(applied, vec!["seccomp_profile"]);
+        let (config, applied) = apply(json!({ "workspace_mode": "commit_on_success" })).unwrap();
+        assert_eq!(config.workspace_mode, WorkspaceMode::CommitOnSuccess);
+        assert_eq!(applied, vec!["workspace_mode"]);
+        assert_eq!(SandboxConfig::default().workspace_mode, WorkspaceMode::Direct);
+        let (config, applied) = apply(json!({ "retained_capabilities": ["CAP_NET_BIND_SERVICE"] })).unwrap();
+        assert_eq!(config.retained_capabilities, vec!["CAP_NET_BIND_SERVICE".to_string()]);
+        assert_eq!(applied, vec!["retained_capabilities"]);
+        assert!(SandboxConfig::default().retained_capabilities.is_empty());
+        let (config, applied) = apply(json!({ "apparmor_profile": "mcp-sandbox" })).unwrap();
+        assert_eq!(config.mac_profile, Some(MacProfile::AppArmor("mcp-sandbox".to_string())));
+        assert_eq!(applied, vec!["apparmor_profile"]);
+        let (config, _) = apply(json!({ "selinux_context": "system_u:system_r:container_t:s0" })).unwrap();
+        assert_eq!(config.mac_profile, Some(MacProfile::SeLinux("system_u:system_r:container_t:s0".to_string())));
+        assert!(SandboxConfig::default().mac_profile.is_none());
+        let (config, applied) = apply(json!({ "disk_limit": "1G", "io_weight": 10 })).unwrap();
+        assert_eq!(config.resource_limits.disk_limit, Some(1 << 30));
+        assert_eq!(applied, vec!["io_weight", "disk_limit"]);
+        let (config, applied) = apply(json!({ "cpu_time_limit": 30 })).unwrap();
+        assert_eq!(config.resource_limits.cpu_time_limit, Some(30));
+        assert_eq!(applied, vec!["cpu_time_limit"]);
+        let (config, applied) =
+            apply(json!({ "stack_limit": "8M", "open_files_limit": 256, "file_size_limit": 1048576 })).unwrap();
+        assert_eq!(config.resource_limits.open_files_limit, Some(256));
+        assert_eq!(config.resource_limits.file_size_limit, Some(1 << 20));
+        assert_eq!(config.resource_limits.stack_limit, Some(8 << 20));
+        assert_eq!(applied, vec!["open_files_limit", "file_size_limit", "stack_limit"]);
+        let (config, _) = apply(json!({ "core_dump_limit": "1M" })).unwrap();
+        assert_eq!(config.resource_limits.core_dump_limit, Some(1 << 20));
+        let (config, applied) = apply(json!({ "share_ipc": true, "hostname": "build-sandbox" })).unwrap();
+        assert!(config.share_ipc);
+        assert!(!config.share_uts);
+        assert_eq!(config.hostname, "build-sandbox");
+        assert_eq!(applied, vec!["share_ipc", "hostname"]);
+        let (config, _) = apply(json!({ "share_uts": true })).unwrap();
+        assert!(config.share_uts);
+        assert_eq!(SandboxConfig::default().hostname, "mcp-sandbox");
+        let (config, applied) = apply(json!({ "extra_bwrap_args": ["--new-session", "--as-pid-1"] })).unwrap();
+        assert_eq!(config.extra_bwrap_args, vec!["--new-session", "--as-pid-1"]);
+        assert_eq!(applied, vec!["extra_bwrap_args"]);
+
+        // No directives leave the configuration unchanged
+        let (config, applied) = apply(json!({ "cacheable": true })).unwrap();
+        assert!(applied.is_empty());
+        assert_eq!(config.network_access, NetworkAccess::None);
+    }
+
+    #[test]
+    fn test_apply_command_limits() {
+        let command_limits = CommandLimits {
+            max_cpu_cores: Some(2.0),
+            default_memory: Some(1 << 30),
+            max_memory: Some(2 << 30),
+            ..Default::default()
+        };
+
+        // Requested values are clamped to the maximums with a warning
+        let mut config = SandboxConfig::default();
+        let requested = ResourceLimits {
+            cpu_limit: Some(4.0),
+            memory_limit: Some(512 << 20),
+            ..Default::default()
+        };
+        let warnings = apply_command_limits(&mut config, &command_limits, &requested);
+        assert_eq!(config.resource_limits.cpu_limit, Some(2.0));
+        assert_eq!(config.resource_limits.memory_limit, Some(512 << 20));
+        assert_eq!(warnings, vec!["cpu_limit 4 exceeds the policy maximum 2 and was clamped"]);
+
+        // Unrequested values fall back to the policy default, then to the configured value
+        let mut config = SandboxConfig::default();
+        config.resource_limits.cpu_limit = Some(8.0);
+        let warnings = apply_command_limits(&mut config, &command_limits, &ResourceLimits::default());
+        assert_eq!(config.resource_limits.cpu_limit, Some(2.0));
+        assert_eq!(config.resource_limits.memory_limit, Some(1 << 30));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_apply_requested_sandbox() {
+        let (mut config, _) = apply(json!({ "network_access": "host", "cpu_limit": 2.0, "pids_limit": 64 })).unwrap();
+        let requested = RequestedSandbox {
+            network_access: Some(NetworkAccess::Restricted(vec![])),
+            rw_paths: vec![PathBuf::from("/workspace/cache")],
+            ro_paths: vec![PathBuf::from("/opt/sdk")],
+            denied_paths: vec![PathBuf::from("/workspace/secrets")],
+            resource_limits: ResourceLimits {
+                cpu_limit: Some(4.0),
+                memory_limit: Some(1 << 30),
+                pids_limit: Some(32),
+                ..Default::default()
+            },
+            seccomp_profile: None,
+        };
+        let warnings = apply_requested_sandbox(&mut config, &requested, None).unwrap();
+        assert_eq!(warnings, vec!["cpu_limit 4 exceeds the policy maximum 2 and was clamped"]);
+        assert_eq!(config.network_access, NetworkAccess::Restricted(vec![]));
+        assert!(config.rw_paths.contains(&PathBuf::from("/workspace/cache")));
+        assert!(config.ro_paths.contains(&PathBuf::from("/opt/sdk")));
+        assert!(config.denied_paths.contains(&PathBuf::from("/workspace/secrets")));
+        assert_eq!(config.resource_limits.cpu_limit, Some(2.0));
+        // Limits without a configured value are taken as requested
+        assert_eq!(config.resource_limits.memory_limit, Some(1 << 30));
+        assert_eq!(config.resource_limits.pids_limit, Some(32));
+
+        // The network access can only be narrowed
+        let mut config = SandboxConfig::default();
+        let requested = RequestedSandbox {
+            network_access: Some(NetworkAccess::Host),
+            ..Default::default()
+        };
+        let warnings = apply_requested_sandbox(&mut config, &requested, None).unwrap();
+        assert_eq!(warnings, vec!["network_access host exceeds the policy maximum none and was clamped"]);
+        assert_eq!(config.network_access, NetworkAccess::None);
+
+        // Restricted access keeps the hosts of the policy
+        let (mut config, _) =
+            apply(json!({ "network_access": "restricted", "network_hosts": ["api.example.com"] })).unwrap();
+        let requested = RequestedSandbox {
+            network_access: Some(NetworkAccess::Restricted(vec![])),
+            ..Default::default()
+        };
+        assert!(apply_requested_sandbox(&mut config, &requested, None).unwrap().is_empty());
+        assert_eq!(config.network_access, NetworkAccess::Restricted(vec!["api.example.com".to_string()]));
+
+        // The seccomp profile of a preset does not replace the one of the policy
+        let requested = RequestedSandbox {
+            seccomp_profile: Some("network".to_string()),
+            ..Default::default()
+        };
+        let mut config = SandboxConfig::default();
+        apply_requested_sandbox(&mut config, &requested, None).unwrap();
+        assert_eq!(config.seccomp_profile_name.as_deref(), Some("network"));
+        let (mut config, _) = apply(json!({ "seccomp_profile": "basic" })).unwrap();
+        apply_requested_sandbox(&mut config, &requested, None).unwrap();
+        assert_eq!(config.seccomp_profile_name.as_deref(), Some("basic"));
+
+        // Mounts under denied paths and relative paths are rejected
+        for (path, invalid_request) in [("/etc/ssl", false), ("/home", false), ("relative", true)] {
+            let requested = RequestedSandbox {
+                ro_paths: vec![PathBuf::from(path)],
+                ..Default::default()
+            };
+            match apply_requested_sandbox(&mut SandboxConfig::default(), &requested, None) {
+                Err(McpError::InvalidRequest(_)) if invalid_request => {}
+                Err(McpError::PolicyViolation(_)) if !invalid_request => {}
+                other => panic!("unexpected result for {}: {:?}", path, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_directives() {
+        for metadata in [
+            json!({ "network_access": "internet" }),
+            json!({ "network_hosts": ["api.example.com"] }),
+            json!({ "network_access": "host", "network_hosts": ["api.example.com"] }),
+            json!({ "network_access": "restricted", "network_hosts": "api.example.com" }),
+            json!({ "network_access": "restricted", "network_hosts": ["api.example.com:https"] }),
+            json!({ "rw_paths": ["relative/path"] }),
+            json!({ "ro_paths": "/usr" }),
+            json!({ "memory_limit": "lots" }),
+            json!({ "memory_limit": 0 }),
+            json!({ "disk_limit": "full" }),
+            json!({ "cpu_limit": -1 }),
+            json!({ "pids_limit": 4294967296u64 }),
+            json!({ "io_weight": "high" }),
+            json!({ "cpu_time_limit": 0 }),
+            json!({ "cpu_time_limit": "1m" }),
+            json!({ "open_files_limit": 0 }),
+            json!({ "file_size_limit": "huge" }),
+            json!({ "stack_limit": -1 }),
+            json!({ "sandbox_backend": "docker" }),
+            json!({ "seccomp_profile": "../basic" }),
+            json!({ "seccomp_profile": 1 }),
+            json!({ "workspace_mode": "overlay" }),
+            json!({ "retained_capabilities": ["CAP_EVERYTHING"] }),
+            json!({ "retained_capabilities": "CAP_CHOWN" }),
+            json!({ "apparmor_profile": "" }),
+            json!({ "apparmor_profile": ["mcp-sandbox"] }),
+            json!({ "selinux_context": "container_t" }),
+            json!({ "apparmor_profile": "mcp-sandbox", "selinux_context": "system_u:system_r:container_t" }),
+            json!({ "share_ipc": "yes" }),
+            json!({ "share_uts": 1 }),
+            json!({ "hostname": "my_sandbox" }),
+            json!({ "hostname": "" }),
+            json!({ "extra_bwrap_args": "--new-session" }),
+            json!({ "extra_bwrap_args": ["--new-session", "--bind", "/", "/host"] }),
+        ] {
+            match apply(metadata.clone()) {
+                Err(McpError::Sandbox(_)) => {}
+                other => panic!("unexpected result for {}: {:?}", metadata, other.map(|(_, applied)| applied)),
+            }
+        }
+    }
+}
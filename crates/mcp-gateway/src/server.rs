@@ -1,12 +1,22 @@
+use crate::proto::admin_service_server::AdminServiceServer;
 use crate::proto::mcp_service_server::McpServiceServer;
-use crate::McpServiceImpl;
+use crate::{AdminServiceImpl, McpServiceImpl};
+use std::future::Future;
 use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
 use tonic::transport::Server;
-use tracing::info;
+use std::time::Duration;
+use tracing::{info, warn};
 use axum::{Router, routing::get, response::Response, body::Body, http::{header, StatusCode}};
 use prometheus::Encoder;
 use prometheus::TextEncoder;
 use crate::metrics;
+use crate::shutdown::{self, ShutdownConfig};
+use crate::task_output_ws;
+use crate::tls::{self, TlsCertificate};
+use crate::auth::{AuthLayer, Authenticator};
+use crate::rbac::{RbacConfig, RbacLayer};
 
 /// gRPCサーバーの作成
 ///
@@ -19,38 +29,114 @@ pub fn create_server(service: McpServiceImpl) -> McpServiceServer<McpServiceImpl
     McpServiceServer::new(service)
 }
 
+/// 管理サービスのgRPCサーバーの作成
+pub fn create_admin_server(service: AdminServiceImpl) -> AdminServiceServer<AdminServiceImpl> {
+    AdminServiceServer::new(service)
+}
+
+/// シャットダウン時に、タスクの終了後も残っているリクエストを待つ時間
+const SHUTDOWN_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// サーバーを実行する
+///
+/// SIGTERMまたはSIGINTを受けると新しい接続とタスクを受け付けなくなり、実行中のタスクを
+/// `shutdown_config`の期限まで待ってから戻る。`tls_certificate`を渡すと、gRPCとメトリクスの
+/// 両サーバーをTLSで提供する。`authenticator`が有効な場合、gRPCのリクエストとタスク出力のWebSocketに
+/// APIキーまたはOIDCトークンを要求する。
+/// `rbac`を渡すと、認証したユーザーのロールでメソッドの呼び出しを認可する（ポリシーエンジンの評価より前）。
+/// タスク出力のWebSocketも`StreamTaskOutput`として認可する。
 pub async fn run_server(
     addr: SocketAddr,
-    service: McpServiceServer<McpServiceImpl>,
+    service: Arc<McpServiceImpl>,
+    admin_service: AdminServiceServer<AdminServiceImpl>,
+    shutdown_config: ShutdownConfig,
+    tls_certificate: Option<Arc<TlsCertificate>>,
+    authenticator: Authenticator,
+    rbac: Option<Arc<RbacConfig>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    info!("gRPCサーバーを起動します: {}", addr);
+    info!("gRPCサーバーを起動します: {} (TLS: {})", addr, tls_certificate.is_some());
 
     // メトリクスを初期化
     metrics::init_metrics();
 
-    // メトリクスサーバーを起動
-    start_metrics_server();
+    // メトリクスサーバー（タスク出力のWebSocketも提供）を起動
+    start_metrics_server(service.clone(), tls_certificate.clone(), authenticator.clone(), rbac.clone());
 
-    Server::builder()
-        .add_service(service)
-        .serve(addr)
-        .await?;
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let stopped = async {
+        let _ = stopped.await;
+    };
+    let router = Server::builder()
+        .layer(tower::util::option_layer(authenticator.is_enabled().then(|| AuthLayer::new(authenticator))))
+        // 認証で呼び出し元が決まった後に評価する
+        .layer(tower::util::option_layer(rbac.map(RbacLayer::new)))
+        .add_service(McpServiceServer::from_arc(service.clone()))
+        .add_service(admin_service);
+    let server: Pin<Box<dyn Future<Output = Result<(), tonic::transport::Error>> + Send>> = match tls_certificate {
+        // gRPCはHTTP/2のみ
+        Some(certificate) => {
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            let incoming = tls::incoming(listener, certificate.server_config(&[b"h2"]));
+            Box::pin(router.serve_with_incoming_shutdown(incoming, stopped))
+        }
+        None => Box::pin(router.serve_with_shutdown(addr, stopped)),
+    };
+    tokio::pin!(server);
+
+    tokio::select! {
+        result = &mut server => return Ok(result?),
+        _ = shutdown::signal() => {}
+    }
 
+    // 新しい接続を受け付けず、処理中のリクエスト（出力ストリームなど）の間にタスクを終わらせる
+    info!("シャットダウンを開始します");
+    let _ = stop.send(());
+    let report = service.drain(shutdown_config.drain_timeout).await;
+    info!(
+        "タスクを終了しました: finished={}, unstarted={}, interrupted={}",
+        report.finished, report.unstarted, report.interrupted
+    );
+
+    match tokio::time::timeout(SHUTDOWN_REQUEST_TIMEOUT, server).await {
+        Ok(result) => result?,
+        Err(_) => warn!("終了していないリクエストを打ち切ります"),
+    }
     Ok(())
 }
 
 /// メトリクスサーバーを起動する
-fn start_metrics_server() {
+///
+/// タスク出力のWebSocketはgRPCと同じ`authenticator`と`rbac`で認証・認可する（全インターフェースで待ち受けるため）
+fn start_metrics_server(
+    service: Arc<McpServiceImpl>,
+    tls_certificate: Option<Arc<TlsCertificate>>,
+    authenticator: Authenticator,
+    rbac: Option<Arc<RbacConfig>>,
+) {
     // メトリクスサーバーのエンドポイントを定義
     let app = Router::new()
         .route("/metrics", get(metrics_handler))
-        .route("/health", get(health_handler));
+        .route("/health", get(health_handler))
+        .route("/host", get(host_handler))
+        // gRPCのストリームを扱えないクライアント向けのタスク出力
+        .merge(task_output_ws::router(service, authenticator, rbac));
 
     // メトリクスサーバーを別スレッドで起動
     let metrics_addr = std::net::SocketAddr::from(([0, 0, 0, 0], 9090));
     info!("メトリクスサーバーを起動します: {}", metrics_addr);
 
+    // TLSではgRPCと同じ証明書を使う
+    if let Some(certificate) = tls_certificate {
+        tokio::spawn(async move {
+            let config = certificate.server_config(&[b"h2", b"http/1.1"]);
+            match tokio::net::TcpListener::bind(metrics_addr).await {
+                Ok(listener) => tls::serve_router(listener, config, app).await,
+                Err(e) => tracing::error!("メトリクスサーバーのバインドに失敗しました: {}", e),
+            }
+        });
+        return;
+    }
+
     tokio::spawn(async move {
         let listener = match tokio::net::TcpListener::bind(metrics_addr).await {
             Ok(listener) => listener,
@@ -105,6 +191,24 @@ async fn health_handler() -> Response<Body> {
         .unwrap()
 }
 
+/// 実行環境のフィンガープリントを返すハンドラー
+async fn host_handler() -> Response<Body> {
+    match serde_json::to_string(mcp_sandbox::HostFingerprint::current()) {
+        Ok(body) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap(),
+        Err(e) => {
+            tracing::error!("ホスト情報のシリアライズに失敗しました: {}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from("ホスト情報のシリアライズに失敗しました"))
+                .unwrap()
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,31 +1,538 @@
 use crate::proto::{
-    self, CommandRequest, DeleteFileRequest, DeleteFileResponse, HealthRequest, HealthResponse,
-    McpService, ReadFileRequest, ReadFileResponse, TaskCreatedResponse, TaskOutputChunk,
-    TaskStatusRequest, TaskStatusResponse, WriteFileRequest, WriteFileResponse,
+    self, evaluate_policy_request, CommandRequest, DeleteFileRequest, DeleteFileResponse, EvaluatePolicyRequest,
+    FileChunk, HealthRequest, HealthResponse, InvalidateResultCacheRequest, InvalidateResultCacheResponse, McpService,
+    PlanRequest, PolicyExplanation, PolicyRuleMatch,
+    ReadFileRequest, ReadFileResponse, ReadFileStreamRequest, ScriptRequest, TaskArtifact, TaskArtifactChunk,
+    TaskArtifactList, TaskArtifactRequest, TaskCreatedResponse, TaskOutputChunk,
+    TaskStatusRequest, TaskStatusResponse, UpdatePolicyDataRequest, UpdatePolicyDataResponse, WriteFileRequest,
+    WriteFileStreamRequest,
+    WriteFileResponse,
 };
+use crate::auth;
 use crate::error::ErrorHandler;
 use crate::metrics;
+use crate::result_cache::{
+    CacheKeyInput, InvalidationFilter, ResultCache, ResultCacheConfig, METADATA_RESULT_CACHE,
+};
+use crate::sandbox_policy::{
+    apply_requested_sandbox, apply_sandbox_directives, RequestedSandbox, METADATA_LIMIT_WARNINGS,
+    METADATA_SANDBOX_DIRECTIVES,
+};
+use crate::shutdown::DrainReport;
+use crate::task_queue::{TaskQueue, TaskQueueConfig};
+use crate::task_retention::{TaskReaper, TaskRetentionConfig};
+use crate::task_store::{TaskRecorder, TaskStore};
+use crate::tenant_files::{self, DirectoryEntry, TenantFiles, TenantFilesConfig};
+use crate::timeout::{TimeoutPolicy, TimeoutSource};
+use mcp_common::utils::current_timestamp_ms;
 use mcp_common::{McpError, McpResult};
 use mcp_policy::engine::PolicyEngine;
-use mcp_policy::models::{CommandInfo, PolicyInput, UserInfo};
-use mcp_sandbox::CommandExecutor;
+use mcp_policy::break_glass::METADATA_BREAK_GLASS;
+use mcp_policy::{BundlePoller, PolicyWatcher};
+use mcp_policy::models::{CommandInfo, FileInfo, PolicyInput, ResourceLimits, UserInfo};
+use mcp_sandbox::models::NetworkAccess;
+use mcp_sandbox::presets::{SandboxPreset, PRESET_NAMES};
+use mcp_sandbox::rlimit::process_rlimits;
+use mcp_sandbox::seccomp::validate_profile_name;
+use mcp_sandbox::staging::StagedFile;
+use mcp_sandbox::workspace::WORKSPACE_MOUNT_POINT;
+use mcp_sandbox::{
+    CommandExecutor, DryRunReport, ExecutionPlan, ExecutionResult, FileChange, HostFingerprint, OutputChunk,
+    OutputLogConfig, OutputLogReader, OutputLogWriter, OutputStream, PlanResult, PlanStep, ProcessRecord,
+    ResourceSample, RuntimeEvent, SpilledOutput, StepStatus, TailCursor,
+};
 use std::collections::HashMap;
+use std::os::unix::fs::{FileExt, PermissionsExt};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH, Instant, Duration};
 use tokio_stream::wrappers::ReceiverStream;
-use tonic::{Request, Response, Status};
-use tracing::{debug, info};
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+/// 出力ログのポーリング間隔
+const OUTPUT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// シャットダウン時にタスクの終了を確認する間隔
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// シャットダウン時にキャンセルしたタスクが結果を記録するまで待つ時間
+const DRAIN_RECORD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 実行中のタスクのリソース使用量を出力ストリームに送る間隔
+const TELEMETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 実行中のコマンド出力をログに書き込むまでのバッファ（チャンク数）
+const OUTPUT_CHANNEL_CAPACITY: usize = 64;
+
+/// 成果物ダウンロード時のチャンクサイズ
+const ARTIFACT_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// 環境変数ポリシーで除去された変数名（カンマ区切り）を記録するメタデータキー
+pub const METADATA_STRIPPED_ENV: &str = "stripped_env";
+
+/// 一時的な障害でコマンドを再実行した回数を記録するメタデータキー
+pub const METADATA_RETRIES: &str = "retries";
+
+/// ポリシーの拒否を上書きするブレークグラストークンのリクエストヘッダー
+pub const BREAK_GLASS_HEADER: &str = "x-break-glass-token";
+
+/// ドライランで作成したタスク（コマンドは実行していない）を示すメタデータキー
+pub const METADATA_DRY_RUN: &str = "dry_run";
+
+/// 実行したスクリプトのSHA-256ダイジェストを記録するメタデータキー
+pub const METADATA_SCRIPT_SHA256: &str = "script_sha256";
+
+/// アップロードされたスクリプトの最大サイズ（バイト）
+const MAX_SCRIPT_BYTES: usize = 1024 * 1024;
+
+/// タスクを作成したユーザーのテナントを記録するメタデータキー（他のテナントからは参照・操作できない）
+pub const METADATA_TENANT_ID: &str = "tenant_id";
+
+/// スクリプトを書き込むタスクのワークスペース内のファイル名
+const SCRIPT_FILE_NAME: &str = "mcp-script";
+
+/// サンドボックス内から見たスクリプトのパス
+fn script_path() -> String {
+    format!("{}/{}", WORKSPACE_MOUNT_POINT, SCRIPT_FILE_NAME)
+}
+
+/// リクエストのサンドボックス設定から要求リソース制限を取り出す（0は未指定）
+fn requested_resources(sandbox_config: Option<&proto::SandboxConfig>) -> ResourceLimits {
+    let Some(limits) = sandbox_config.and_then(|config| config.resource_limits.as_ref()) else {
+        return ResourceLimits::default();
+    };
+
+    ResourceLimits {
+        cpu_cores: (limits.cpu_limit > 0.0).then_some(limits.cpu_limit as f64),
+        memory_kb: (limits.memory_limit > 0).then(|| limits.memory_limit.div_ceil(1024)),
+        max_processes: (limits.pids_limit > 0).then_some(limits.pids_limit),
+        ..Default::default()
+    }
+}
+
+/// リクエストのサンドボックス設定をタスクごとの要求に変換する（リソース制限の0は未指定）
+///
+/// サンドボックスの無効化（enabled=false）は受け付けず、常にサンドボックス内で実行する。
+fn requested_sandbox(sandbox_config: Option<&proto::SandboxConfig>) -> McpResult<RequestedSandbox> {
+    let Some(config) = sandbox_config else {
+        return Ok(RequestedSandbox::default());
+    };
+
+    let network_access = match proto::NetworkAccess::try_from(config.network_access) {
+        Ok(proto::NetworkAccess::NetworkNone) => NetworkAccess::None,
+        Ok(proto::NetworkAccess::NetworkHost) => NetworkAccess::Host,
+        // 接続先はポリシーが許可したホストに限られる
+        Ok(proto::NetworkAccess::NetworkRestricted) => NetworkAccess::Restricted(Vec::new()),
+        Err(_) => {
+            return Err(McpError::InvalidRequest(format!(
+                "Invalid network access in sandbox config: {}",
+                config.network_access
+            )))
+        }
+    };
+    let limits = config.resource_limits.clone().unwrap_or_default();
+    Ok(RequestedSandbox {
+        network_access: Some(network_access),
+        rw_paths: config.rw_paths.iter().map(PathBuf::from).collect(),
+        ro_paths: config.ro_paths.iter().map(PathBuf::from).collect(),
+        denied_paths: config.denied_paths.iter().map(PathBuf::from).collect(),
+        resource_limits: mcp_sandbox::models::ResourceLimits {
+            cpu_limit: (limits.cpu_limit > 0.0).then_some(limits.cpu_limit as f64),
+            memory_limit: (limits.memory_limit > 0).then_some(limits.memory_limit),
+            pids_limit: (limits.pids_limit > 0).then_some(limits.pids_limit),
+            io_weight: (limits.io_weight > 0).then_some(limits.io_weight),
+            // ディスククォータ、CPU 時間とプロセスごとの rlimit はポリシーでのみ指定できる
+            disk_limit: None,
+            cpu_time_limit: None,
+            open_files_limit: None,
+            file_size_limit: None,
+            core_dump_limit: None,
+            stack_limit: None,
+        },
+        // 名前で要求したseccompプロファイルはプリセットのものより優先する（どちらも実行設定とポリシーが
+        // 指定していない場合にだけ使い、存在しないプロファイルは実行時に失敗する）
+        seccomp_profile: match config.seccomp_profile.as_str() {
+            "" => sandbox_preset(Some(config))?.map(|preset| preset.seccomp_profile),
+            name => {
+                validate_profile_name(name)?;
+                Some(name.to_string())
+            }
+        },
+    })
+}
+
+/// リクエストのサンドボックス設定で指定された言語ランタイムのプリセット（空文字列は未指定）
+fn sandbox_preset(sandbox_config: Option<&proto::SandboxConfig>) -> McpResult<Option<SandboxPreset>> {
+    let Some(name) = sandbox_config.map(|config| config.preset.as_str()).filter(|name| !name.is_empty()) else {
+        return Ok(None);
+    };
+    SandboxPreset::named(name).map(Some).ok_or_else(|| {
+        McpError::InvalidRequest(format!("Unknown sandbox preset: {} (available: {})", name, PRESET_NAMES.join(", ")))
+    })
+}
+
+/// リクエストで指定されたプリセットをリクエストに反映する
+///
+/// 読み取り専用のマウントを追加し、指定されていない環境変数とリソース制限をプリセットの値で補う。
+/// 反映したマウント・環境変数・リソース制限は、クライアントが指定した場合と同じくポリシーで確認する。
+fn apply_sandbox_preset<'a>(
+    sandbox_config: Option<&mut proto::SandboxConfig>,
+    envs: impl IntoIterator<Item = &'a mut HashMap<String, String>>,
+) -> McpResult<()> {
+    let Some(config) = sandbox_config else {
+        return Ok(());
+    };
+    let Some(preset) = sandbox_preset(Some(config))? else {
+        return Ok(());
+    };
+
+    for path in &preset.ro_paths {
+        let path = path.to_string_lossy().to_string();
+        if !config.ro_paths.contains(&path) {
+            config.ro_paths.push(path);
+        }
+    }
+    let limits = config.resource_limits.get_or_insert_default();
+    if limits.cpu_limit <= 0.0 {
+        limits.cpu_limit = preset.resource_limits.cpu_limit.unwrap_or_default() as f32;
+    }
+    if limits.memory_limit == 0 {
+        limits.memory_limit = preset.resource_limits.memory_limit.unwrap_or_default();
+    }
+    if limits.pids_limit == 0 {
+        limits.pids_limit = preset.resource_limits.pids_limit.unwrap_or_default();
+    }
+    for env in envs {
+        preset.apply_env(env);
+    }
+    Ok(())
+}
+
+/// コマンドが起動したプロセスをタスク結果の形式に変換する
+fn process_info(process: ProcessRecord) -> proto::ProcessInfo {
+    proto::ProcessInfo {
+        pid: process.pid,
+        parent_pid: process.parent_pid,
+        name: process.name,
+        command_line: process.command_line,
+        exit_code: process.exit_code,
+        signal: process.signal,
+    }
+}
+
+/// 実行時モニタが記録したイベントをタスク結果の形式に変換する
+fn runtime_event(event: RuntimeEvent) -> proto::RuntimeEvent {
+    proto::RuntimeEvent {
+        timestamp_ms: event.timestamp_ms,
+        pid: event.pid,
+        name: event.name,
+        kind: event.kind.as_str().to_string(),
+        target: event.target,
+    }
+}
+
+/// 実行中のコマンドのリソース使用量を応答の形式に変換する
+fn resource_sample(sample: ResourceSample) -> proto::ResourceSample {
+    proto::ResourceSample {
+        timestamp_ms: sample.timestamp_ms,
+        cpu_time_ms: sample.cpu_time_ms,
+        memory_kb: sample.memory_kb,
+        max_memory_kb: sample.max_memory_kb,
+        io_read_bytes: sample.io_read_bytes,
+        io_write_bytes: sample.io_write_bytes,
+    }
+}
+
+/// ドライランで得たサンドボックスでの起動方法をタスク結果の形式に変換する
+fn sandbox_dry_run(report: DryRunReport) -> proto::SandboxDryRun {
+    let limits = &report.resource_limits;
+    let (network_access, allowed_hosts) = match report.network_access {
+        NetworkAccess::None => (proto::NetworkAccess::NetworkNone, Vec::new()),
+        NetworkAccess::Host => (proto::NetworkAccess::NetworkHost, Vec::new()),
+        NetworkAccess::Restricted(hosts) => (proto::NetworkAccess::NetworkRestricted, hosts),
+    };
+    proto::SandboxDryRun {
+        backend: report.backend.as_str().to_string(),
+        argv: report.argv,
+        mounts: report
+            .mounts
+            .into_iter()
+            .map(|mount| proto::SandboxMount {
+                source: mount.source.map(|path| path.to_string_lossy().to_string()).unwrap_or_default(),
+                target: mount.target.to_string_lossy().to_string(),
+                kind: mount.kind.as_str().to_string(),
+            })
+            .collect(),
+        seccomp_profile: report.seccomp_profile,
+        seccomp_profile_path: report.seccomp_profile_path.map(|path| path.to_string_lossy().to_string()),
+        resource_limits: Some(proto::ResourceLimits {
+            cpu_limit: limits.cpu_limit.unwrap_or_default() as f32,
+            memory_limit: limits.memory_limit.unwrap_or_default(),
+            pids_limit: limits.pids_limit.unwrap_or_default(),
+            io_weight: limits.io_weight.unwrap_or_default(),
+        }),
+        disk_limit: limits.disk_limit.unwrap_or_default(),
+        cpu_time_limit: limits.cpu_time_limit.unwrap_or_default(),
+        timeout: report.timeout,
+        network_access: network_access as i32,
+        allowed_hosts,
+        rlimits: process_rlimits(limits)
+            .into_iter()
+            .map(|(rlimit, value)| (rlimit.name().to_string(), value))
+            .collect(),
+    }
+}
+
+/// コマンドの実行結果をタスク結果の形式に変換する
+fn task_result(output: ExecutionResult) -> proto::TaskResult {
+    proto::TaskResult {
+        exit_code: output.exit_code.unwrap_or(-1),
+        stdout: output.stdout,
+        stderr: output.stderr,
+        resource_usage: Some(proto::ResourceUsage {
+            cpu_time_ms: output.resource_usage.cpu_time_ms,
+            max_memory_kb: output.resource_usage.max_memory_kb,
+            io_read_bytes: output.resource_usage.io_read_bytes,
+            io_write_bytes: output.resource_usage.io_write_bytes,
+        }),
+        execution_time_ms: output.execution_time_ms,
+        process_tree: output.process_tree.into_iter().map(process_info).collect(),
+        runtime_events: output.runtime_events.into_iter().map(runtime_event).collect(),
+        steps: Vec::new(),
+        dry_run: None,
+        workspace_diff: output.workspace_changes.map(workspace_diff),
+        stdout_overflow: output.stdout_spill.as_ref().map(output_overflow),
+        stderr_overflow: output.stderr_spill.as_ref().map(output_overflow),
+    }
+}
+
+/// インラインに収まらなかった出力の参照（成果物は[`link_output_artifacts`]で設定する）
+fn output_overflow(spill: &SpilledOutput) -> proto::OutputOverflow {
+    proto::OutputOverflow {
+        size_bytes: spill.size(),
+        artifacts: Vec::new(),
+    }
+}
+
+/// インラインに収まらなかった出力に、完全な出力を保持する出力ログの成果物を設定する
+fn link_output_artifacts(result: &mut proto::TaskResult, log: &OutputLogWriter) {
+    let index = match log.index() {
+        Ok(index) => index,
+        Err(e) => {
+            warn!("出力ログの成果物を取得できませんでした: dir={:?}, error={}", log.dir(), e);
+            return;
+        }
+    };
+    for (overflow, stream) in [
+        (&mut result.stdout_overflow, OutputStream::Stdout),
+        (&mut result.stderr_overflow, OutputStream::Stderr),
+    ] {
+        if let Some(overflow) = overflow {
+            overflow.artifacts = index.stream_segments(stream).map(|segment| segment.name.clone()).collect();
+        }
+    }
+}
+
+/// プランのステップの出力の合計（いずれかのステップの出力がインラインに収まらなかった場合のみ）
+fn total_overflow<'a>(
+    outputs: impl Iterator<Item = (&'a String, Option<&'a proto::OutputOverflow>)>,
+) -> Option<proto::OutputOverflow> {
+    let mut overflowed = false;
+    let mut size_bytes = 0;
+    for (inline, overflow) in outputs {
+        overflowed |= overflow.is_some();
+        size_bytes += overflow.map_or(inline.len() as u64, |overflow| overflow.size_bytes);
+    }
+    overflowed.then(|| proto::OutputOverflow {
+        size_bytes,
+        artifacts: Vec::new(),
+    })
+}
+
+/// コマンドが変更したファイルをタスク結果の形式に変換する
+fn workspace_diff(changes: Vec<FileChange>) -> proto::WorkspaceDiff {
+    proto::WorkspaceDiff {
+        changes: changes
+            .into_iter()
+            .map(|change| proto::FileChange {
+                path: change.path.to_string_lossy().to_string(),
+                kind: change.kind.as_str().to_string(),
+                size: change.size,
+                sha256: change.sha256,
+                previous_size: change.previous_size,
+                previous_sha256: change.previous_sha256,
+            })
+            .collect(),
+    }
+}
+
+/// プランの実行結果をタスク結果の形式に変換する
+///
+/// 出力は実行順に連結し、リソース使用量はステップの合計（メモリは最大値）とする。
+/// プロセスツリーと実行時イベントは各ステップの結果にのみ含める。
+fn plan_task_result(result: PlanResult) -> proto::TaskResult {
+    let mut aggregate = proto::TaskResult {
+        exit_code: result.exit_code(),
+        stdout: String::new(),
+        stderr: String::new(),
+        resource_usage: Some(proto::ResourceUsage::default()),
+        execution_time_ms: 0,
+        process_tree: Vec::new(),
+        runtime_events: Vec::new(),
+        steps: Vec::new(),
+        dry_run: None,
+        workspace_diff: None,
+        stdout_overflow: None,
+        stderr_overflow: None,
+    };
+    for step in result.steps {
+        let status = match step.status {
+            StepStatus::Succeeded => proto::StepStatus::StepSucceeded,
+            StepStatus::Failed => proto::StepStatus::StepFailed,
+            StepStatus::Skipped => proto::StepStatus::StepSkipped,
+        };
+        let result = step.result.map(task_result);
+        if let Some(result) = &result {
+            aggregate.stdout.push_str(&result.stdout);
+            aggregate.stderr.push_str(&result.stderr);
+            aggregate.execution_time_ms += result.execution_time_ms;
+            if let (Some(total), Some(usage)) = (&mut aggregate.resource_usage, &result.resource_usage) {
+                total.cpu_time_ms += usage.cpu_time_ms;
+                total.max_memory_kb = total.max_memory_kb.max(usage.max_memory_kb);
+                total.io_read_bytes += usage.io_read_bytes;
+                total.io_write_bytes += usage.io_write_bytes;
+            }
+        }
+        aggregate.steps.push(proto::StepResult {
+            step_id: step.step_id,
+            status: status as i32,
+            result,
+            error: step.error,
+        });
+    }
+    let results = || aggregate.steps.iter().filter_map(|step| step.result.as_ref());
+    aggregate.stdout_overflow =
+        total_overflow(results().map(|result| (&result.stdout, result.stdout_overflow.as_ref())));
+    aggregate.stderr_overflow =
+        total_overflow(results().map(|result| (&result.stderr, result.stderr_overflow.as_ref())));
+    aggregate
+}
+
+/// プランのステップで発生したエラーにステップIDを付与する
+fn plan_step_error(step_id: &str, error: McpError) -> McpError {
+    let with_step = |message: String| format!("step '{}': {}", step_id, message);
+    match error {
+        McpError::InvalidRequest(message) => McpError::InvalidRequest(with_step(message)),
+        McpError::PolicyViolation(message) => McpError::PolicyViolation(with_step(message)),
+        McpError::DetailedPolicyViolation { code, message, details } => McpError::DetailedPolicyViolation {
+            code,
+            message: with_step(message),
+            details,
+        },
+        error => error,
+    }
+}
+
+/// コマンド実行リクエストからポリシー評価の入力を作成する
+fn command_policy_input(req: &CommandRequest) -> PolicyInput {
+    PolicyInput {
+        user: request_user(),
+        command: CommandInfo {
+            name: req.command.clone(),
+            args: req.args.clone(),
+            cwd: req.cwd.clone().unwrap_or_default(),
+            env: req.env.clone(),
+            executable: None,
+        },
+        file: None,
+        network: None,
+        resources: requested_resources(req.sandbox_config.as_ref()),
+        context: HashMap::new(),
+    }
+}
+
+/// ファイル操作のポリシー入力を作成（modeは"read"または"write"）
+fn file_policy_input(path: &str, mode: &str) -> PolicyInput {
+    PolicyInput {
+        user: request_user(),
+        command: CommandInfo::default(),
+        file: Some(FileInfo {
+            path: path.to_string(),
+            mode: mode.to_string(),
+        }),
+        network: None,
+        resources: ResourceLimits::default(),
+        context: HashMap::new(),
+    }
+}
+
+/// ファイルのメタデータ
+fn file_metadata(metadata: &std::fs::Metadata) -> proto::FileMetadata {
+    proto::FileMetadata {
+        size: metadata.len(),
+        mode: metadata.permissions().mode() & 0o777,
+        modified_at: metadata
+            .modified()
+            .map(|modified| chrono::DateTime::<chrono::Utc>::from(modified).to_rfc3339())
+            .unwrap_or_default(),
+    }
+}
+
+/// リクエストのユーザー情報（APIキーで認証したユーザー、認証しない場合は既定のユーザー）
+fn request_user() -> UserInfo {
+    auth::current_user().unwrap_or_else(|| UserInfo {
+        id: "user1".to_string(),
+        tenant_id: "tenant1".to_string(),
+        roles: vec!["user".to_string()],
+        attributes: HashMap::new(),
+    })
+}
+
+/// 操作対象のテナント（認証した場合は指定に関わらず呼び出し元のテナントに限る）
+fn caller_tenant(requested: Option<String>) -> McpResult<Option<String>> {
+    let Some(user) = auth::current_user() else {
+        return Ok(requested);
+    };
+    match requested {
+        Some(tenant_id) if tenant_id != user.tenant_id => Err(McpError::PolicyViolation(format!(
+            "テナント {} のユーザーはテナント {} を操作できません",
+            user.tenant_id, tenant_id
+        ))),
+        _ => Ok(Some(user.tenant_id)),
+    }
+}
+
 /// MCPサービスの実装
 #[derive(Debug)]
 pub struct McpServiceImpl {
     policy_engine: PolicyEngine,
     command_executor: CommandExecutor,
     start_time: SystemTime,
-    // タスク状態格納用（本実装ではRedis/PostgreSQLなどに置き換える）
+    // タスク作成時に適用するタイムアウト上限
+    timeout_policy: TimeoutPolicy,
+    // タスク出力のローテーションログ設定
+    output_log_config: OutputLogConfig,
+    // 冪等コマンドの結果キャッシュ（デフォルトは無効）
+    result_cache: Arc<ResultCache>,
+    // テナントのファイルルート（未設定の場合はファイル操作を拒否）
+    tenant_files: TenantFiles,
+    // 実行環境のフィンガープリント（ヘルスチェックとタスクメタデータに付与）
+    host_fingerprint: HostFingerprint,
+    // ポリシーの監視（保持している間だけホットリロードが有効）
+    policy_watchers: Vec<PolicyWatcher>,
+    // ポリシーバンドルのポーリング（保持している間だけ更新が有効）
+    policy_bundle: Option<BundlePoller>,
+    // タスク状態格納用（タスクストアを設定した場合は変更をストアにも書き込む）
     tasks: Arc<dashmap::DashMap<String, proto::TaskInfo>>,
     results: Arc<dashmap::DashMap<String, proto::TaskResult>>,
+    // タスクストアへの書き込み（デフォルトはメモリのみ）
+    task_recorder: TaskRecorder,
+    // 保持期間を過ぎた終了タスクの削除（デフォルトは削除しない）
+    task_reaper: Option<Arc<TaskReaper>>,
+    // 実行を待つタスクのキュー（ワーカー数と待ち行列の長さに上限がある）
+    task_queue: TaskQueue,
 }
 
 impl McpServiceImpl {
@@ -39,11 +546,724 @@ impl McpServiceImpl {
             policy_engine,
             command_executor,
             start_time,
+            timeout_policy: TimeoutPolicy::default(),
+            output_log_config: OutputLogConfig::default(),
+            result_cache: Arc::new(ResultCache::default()),
+            tenant_files: TenantFiles::default(),
+            host_fingerprint: HostFingerprint::default(),
+            policy_watchers: Vec::new(),
+            policy_bundle: None,
             tasks: Arc::new(dashmap::DashMap::new()),
             results: Arc::new(dashmap::DashMap::new()),
+            task_recorder: TaskRecorder::default(),
+            task_reaper: None,
+            task_queue: TaskQueue::default(),
+        }
+    }
+
+    /// タイムアウト上限を設定
+    pub fn with_timeout_policy(mut self, timeout_policy: TimeoutPolicy) -> Self {
+        self.timeout_policy = timeout_policy;
+        self
+    }
+
+    /// タスク出力ログの設定
+    pub fn with_output_log_config(mut self, output_log_config: OutputLogConfig) -> Self {
+        self.output_log_config = output_log_config;
+        self
+    }
+
+    /// 結果キャッシュの設定
+    pub fn with_result_cache_config(mut self, config: ResultCacheConfig) -> Self {
+        self.result_cache = Arc::new(ResultCache::new(config));
+        self
+    }
+
+    /// テナントのファイルルートを設定
+    pub fn with_tenant_files_config(mut self, config: TenantFilesConfig) -> Self {
+        self.tenant_files = TenantFiles::new(config);
+        self
+    }
+
+    /// タスクと結果を永続化するストアを設定（Tokioランタイム内で呼び出す）
+    pub fn with_task_store(mut self, task_store: Arc<dyn TaskStore>) -> Self {
+        self.task_recorder = TaskRecorder::start(task_store);
+        self
+    }
+
+    /// 終了したタスクの保持期間を設定し、期限切れのタスクの削除を開始（Tokioランタイム内で呼び出す）
+    ///
+    /// 期限切れのタスクの出力ログも削除するため、出力ログの設定（[`Self::with_output_log_config`]）の後に呼び出す
+    pub fn with_task_retention(mut self, config: TaskRetentionConfig) -> Self {
+        let task_reaper = Arc::new(TaskReaper::new(config).with_output_logs(self.output_log_config.clone()));
+        task_reaper.clone().start(Arc::downgrade(&self.tasks), Arc::downgrade(&self.results));
+        self.task_reaper = Some(task_reaper);
+        self
+    }
+
+    /// タスクキューのワーカー数と待ち行列の長さを設定
+    pub fn with_task_queue_config(mut self, config: TaskQueueConfig) -> Self {
+        self.task_queue = TaskQueue::new(config);
+        self
+    }
+
+    /// タスクを作成したテナントを記録し、保持期間を決めるテナントとしても関連付ける
+    fn track_task(&self, task_info: &mut proto::TaskInfo, tenant_id: &str) {
+        // クライアントが指定したメタデータで所有者を偽装できないように上書きする
+        task_info.metadata.insert(METADATA_TENANT_ID.to_string(), tenant_id.to_string());
+        if let Some(task_reaper) = &self.task_reaper {
+            task_reaper.track(&task_info.task_id, tenant_id);
         }
     }
 
+    /// 呼び出し元のテナントのタスクかどうかを確認する
+    ///
+    /// 他のテナントのタスクは存在を知られないように、存在しないタスクと同じエラーにする
+    fn check_task_owner(&self, task_info: &proto::TaskInfo) -> McpResult<()> {
+        let tenant_id = request_user().tenant_id;
+        if task_info.metadata.get(METADATA_TENANT_ID) == Some(&tenant_id) {
+            return Ok(());
+        }
+        warn!("他のテナントのタスクへのアクセスを拒否しました: task_id={}, tenant_id={}", task_info.task_id, tenant_id);
+        Err(McpError::NotFound(format!("タスクが見つかりません: {}", task_info.task_id)))
+    }
+
+    /// 呼び出し元のテナントが作成したメモリ上のタスク
+    fn owned_task(&self, task_id: &str) -> McpResult<proto::TaskInfo> {
+        let task_info = self
+            .tasks
+            .get(task_id)
+            .map(|info| info.clone())
+            .ok_or_else(|| McpError::NotFound(format!("タスクが見つかりません: {}", task_id)))?;
+        self.check_task_owner(&task_info)?;
+        Ok(task_info)
+    }
+
+    /// キューから取り除いた（実行されなかった）タスクをキャンセル状態にする
+    fn cancel_queued_task(&self, task_id: &str, reason: &str) {
+        let Some(mut task) = self.tasks.get_mut(task_id) else {
+            return;
+        };
+        task.status = proto::TaskStatus::TaskCancelled as i32;
+        task.completed_at = Some(chrono::Utc::now().to_rfc3339());
+        let task_result = proto::TaskResult {
+            exit_code: -1,
+            stderr: format!("Error: {}", reason),
+            ..Default::default()
+        };
+        self.task_recorder.result(task_id, &task_result);
+        self.task_recorder.updated(&task);
+        self.results.insert(task_id.to_string(), task_result);
+        metrics::decrement_active_tasks();
+    }
+
+    /// 実行環境のフィンガープリントを設定
+    pub fn with_host_fingerprint(mut self, host_fingerprint: HostFingerprint) -> Self {
+        self.host_fingerprint = host_fingerprint;
+        self
+    }
+
+    /// ポリシーのホットリロード用ウォッチャーを保持（テナントごとに複数保持できる）
+    pub fn with_policy_watcher(mut self, policy_watcher: PolicyWatcher) -> Self {
+        self.policy_watchers.push(policy_watcher);
+        self
+    }
+
+    /// ポリシーバンドルのポーリングを保持
+    pub fn with_policy_bundle(mut self, policy_bundle: BundlePoller) -> Self {
+        self.policy_bundle = Some(policy_bundle);
+        self
+    }
+
+    /// シャットダウン前にタスクを終わらせる
+    ///
+    /// 新しいタスクを受け付けないようにし、待ち行列のタスクをキャンセルしてから、実行中のタスクの完了を
+    /// `timeout`まで待つ。期限を過ぎても終わらないタスクはキャンセルし、すべてのタスクの状態と結果を
+    /// タスクストアに書き込んでから戻る
+    pub async fn drain(&self, timeout: Duration) -> DrainReport {
+        let deadline = Instant::now() + timeout;
+        let mut report = DrainReport::default();
+
+        for task_id in self.task_queue.close() {
+            self.cancel_queued_task(&task_id, "Task was cancelled before it started (gateway shutting down)");
+            report.unstarted += 1;
+        }
+
+        let running = self.unfinished_tasks();
+        info!("実行中のタスクの完了を待ちます: tasks={}, timeout={:?}", running.len(), timeout);
+        while !self.unfinished_tasks().is_empty() && Instant::now() < deadline {
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+
+        let remaining = self.unfinished_tasks();
+        report.finished = running.iter().filter(|task_id| !remaining.contains(task_id)).count();
+        for task_id in &remaining {
+            // 閉じる前に予約された場所から投入され、まだ実行を待っているタスク
+            if self.task_queue.cancel(task_id) {
+                self.cancel_queued_task(task_id, "Task was cancelled before it started (gateway shutting down)");
+                continue;
+            }
+            // 実行中のプロセスツリーを終了させる（状態はプロセスの終了後にタスク側で更新される）
+            warn!("シャットダウンのためタスクをキャンセルします: task_id={}", task_id);
+            if let Err(e) = self.command_executor.cancel_task(task_id).await {
+                warn!("タスクをキャンセルできませんでした: task_id={}, error={}", task_id, e);
+            }
+            report.interrupted += 1;
+        }
+
+        // キャンセルしたタスクが結果を記録するまで少しだけ待つ
+        let recorded_deadline = Instant::now() + DRAIN_RECORD_TIMEOUT;
+        while !self.unfinished_tasks().is_empty() && Instant::now() < recorded_deadline {
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+        self.task_recorder.flush().await;
+        report
+    }
+
+    /// 終了していないタスクのID
+    fn unfinished_tasks(&self) -> Vec<String> {
+        self.tasks
+            .iter()
+            .filter(|task| !is_terminal_status(task.status))
+            .map(|task| task.key().clone())
+            .collect()
+    }
+
+    /// ポリシーエンジン（複製は評価器を共有する）
+    pub fn policy_engine(&self) -> &PolicyEngine {
+        &self.policy_engine
+    }
+
+    /// ディレクトリの一覧を取得（正規化したパスと、名前順のエントリを返す）
+    pub async fn list_directory(&self, path: &str) -> McpResult<(String, Vec<DirectoryEntry>)> {
+        // テナントのルート内で正規化したパスで、読み取りとしてポリシーチェック
+        let dir = self.tenant_files.resolve(&request_user().tenant_id, path)?;
+        let policy_input = file_policy_input(&dir.path, "read");
+        self.policy_engine.check_file_access(&policy_input).await?;
+
+        let entries = self.tenant_files.list(&dir)?;
+        Ok((dir.path, entries))
+    }
+
+    /// 上流MCPサーバーのツール呼び出しをポリシーチェック（プロキシから呼び出す）
+    pub async fn check_tool_call(&self, server: &str, tool: &str, arguments: &serde_json::Value) -> McpResult<()> {
+        let mut policy_input = PolicyInput {
+            user: request_user(),
+            command: CommandInfo::default(),
+            file: None,
+            network: None,
+            resources: ResourceLimits::default(),
+            context: HashMap::new(),
+        };
+        policy_input.set_tool_call(server, tool, arguments);
+        self.policy_engine.check_tool_call(&policy_input).await
+    }
+
+    /// コマンドの実行ファイルを絶対パスに解決してポリシー入力に設定する
+    ///
+    /// 相対パスや存在しない実行ファイルは`InvalidRequest`として拒否する。コンテナやmicroVMでは
+    /// 実行環境のファイルシステムを事前に確認できないため設定しない
+    fn resolve_executable(&self, policy_input: &mut PolicyInput) -> McpResult<()> {
+        let command = &policy_input.command;
+        let executable = self.command_executor.resolve_executable(&command.name, &command.env)?;
+        policy_input.command.executable = executable.map(|path| path.to_string_lossy().to_string());
+        Ok(())
+    }
+
+    /// 実行せずに得た結果（キャッシュ済みの結果またはドライラン）から完了済みタスクを作成
+    fn complete_without_execution(
+        &self,
+        metadata: HashMap<String, String>,
+        result: proto::TaskResult,
+    ) -> TaskCreatedResponse {
+        let task_id = self.generate_task_id();
+        let now = self.current_iso8601();
+
+        // ストリーミングと成果物の取得のために出力ログにも書き込む
+        match OutputLogWriter::create(&self.output_log_config, &task_id) {
+            Ok(log) => {
+                let written = log
+                    .append(OutputStream::Stdout, result.stdout.as_bytes())
+                    .and_then(|_| log.append(OutputStream::Stderr, result.stderr.as_bytes()))
+                    .and_then(|_| log.finish());
+                if let Err(e) = written {
+                    warn!("出力ログへの書き込みに失敗しました: dir={:?}, error={}", log.dir(), e);
+                }
+            }
+            Err(e) => warn!("出力ログを作成できませんでした: task_id={}, error={}", task_id, e),
+        }
+
+        let mut task_info = proto::TaskInfo {
+            task_id: task_id.clone(),
+            task_type: proto::TaskType::TaskCommand as i32,
+            status: proto::TaskStatus::TaskCompleted as i32,
+            created_at: now.clone(),
+            started_at: Some(now.clone()),
+            completed_at: Some(now.clone()),
+            metadata,
+        };
+        self.track_task(&mut task_info, &request_user().tenant_id);
+        self.task_recorder.result(&task_id, &result);
+        self.task_recorder.created(&task_info, &request_user());
+        self.tasks.insert(task_id.clone(), task_info);
+        self.results.insert(task_id.clone(), result);
+
+        TaskCreatedResponse {
+            task_id,
+            status: proto::TaskStatus::TaskCompleted as i32,
+            created_at: now,
+        }
+    }
+
+    /// 実行中のタスクを一時停止、または一時停止中のタスクを再開
+    fn set_task_paused(&self, task_id: &str, paused: bool) -> McpResult<TaskStatusResponse> {
+        let (from, to) = if paused {
+            (proto::TaskStatus::TaskRunning as i32, proto::TaskStatus::TaskPaused as i32)
+        } else {
+            (proto::TaskStatus::TaskPaused as i32, proto::TaskStatus::TaskRunning as i32)
+        };
+        let status = self.owned_task(task_id)?.status;
+        if status != from {
+            let action = if paused { "実行中ではないタスクは一時停止できません" } else { "一時停止中ではないタスクは再開できません" };
+            return Err(McpError::InvalidRequest(format!("{}: {}", action, task_id)));
+        }
+
+        // 登録されていないタスクは既に完了している
+        let changed = if paused {
+            self.command_executor.pause_task(task_id)?
+        } else {
+            self.command_executor.resume_task(task_id)?
+        };
+        if changed {
+            // 待機中に完了したタスクの状態は上書きしない
+            if let Some(mut task) = self.tasks.get_mut(task_id) {
+                if task.status == from {
+                    task.status = to;
+                    self.task_recorder.updated(&task);
+                }
+            }
+        }
+
+        let task_info = self
+            .tasks
+            .get(task_id)
+            .map(|info| info.clone())
+            .ok_or_else(|| McpError::NotFound(format!("タスクが見つかりません: {}", task_id)))?;
+        let result = self.results.get(task_id).map(|result| result.clone());
+        Ok(TaskStatusResponse {
+            task_info: Some(task_info),
+            result,
+            live_usage: self.command_executor.sample_task_usage(task_id).map(resource_sample),
+        })
+    }
+
+    /// コマンド実行タスクを作成（ポリシー評価の後に非同期で実行する）
+    ///
+    /// `script`を指定した場合は、スクリプトをタスクのワークスペースに書き込んでから実行する。
+    async fn create_command_task(
+        &self,
+        mut req: CommandRequest,
+        break_glass_token: Option<&str>,
+        script: Option<String>,
+    ) -> McpResult<TaskCreatedResponse> {
+        // 言語ランタイムのプリセットはポリシー評価の前にリクエストへ反映する
+        apply_sandbox_preset(req.sandbox_config.as_mut(), [&mut req.env])?;
+
+        // ポリシーチェック
+        let policy_timer = metrics::start_task_timer();
+        let mut policy_input = command_policy_input(&req);
+
+        // 環境変数ポリシーとリソース制限を確認してからポリシー評価（除去された変数は実行環境にも渡さない）
+        let policy_result: McpResult<_> = async {
+            let stripped_env = self.policy_engine.apply_env_policy(&mut policy_input)?;
+            self.resolve_executable(&mut policy_input)?;
+            self.policy_engine.check_resource_limits(&policy_input)?;
+            // スクリプトはファイル書き込みとして内容を検査し、コマンド評価ではスクリプト内のコマンドも評価する
+            if let Some(script) = &script {
+                let script_path = script_path();
+                self.policy_engine
+                    .check_file_write(&file_policy_input(&script_path, "write"), script.as_bytes())
+                    .await?;
+                policy_input.set_script(&script_path, script);
+            }
+            let decision = self
+                .policy_engine
+                .check_command_execution_with_break_glass(&policy_input, break_glass_token)
+                .await?;
+            Ok((decision, stripped_env))
+        }
+        .await;
+        
+        // ポリシー評価メトリクスを記録
+        let policy_result_str = match &policy_result {
+            Ok(_) => "allowed",
+            Err(_) => "denied",
+        };
+        metrics::increment_policy_evaluations("command_execution", policy_result_str);
+        metrics::observe_task_execution_time(policy_timer, "policy_evaluation", policy_result_str);
+        
+        // エラーがあれば伝搬
+        let (decision, stripped_env) = policy_result?;
+        let env = policy_input.command.env.clone();
+
+        // ポリシーがコマンドに設定したタイムアウト・リソースの既定値と上限（超過は拒否せず上限に丸めて警告する）
+        let command_limits = decision.command_limits()?;
+        let mut limit_warnings = Vec::new();
+
+        // 実効タイムアウトを決定（リクエスト値をそのまま信用しない、ツールの上限はラッパーを除いた実際のコマンドで引く）
+        let tool = self.policy_engine.effective_command_name(&req.command, &req.args)?;
+        let effective_timeout = self.timeout_policy.resolve_with_limits(
+            &policy_input.user.tenant_id,
+            &tool,
+            req.timeout,
+            command_limits.as_ref(),
+        );
+        if effective_timeout.source == TimeoutSource::Policy && req.timeout > 0 {
+            limit_warnings.push(format!(
+                "timeout {}s exceeds the policy maximum {}s and was clamped",
+                req.timeout, effective_timeout.secs
+            ));
+        }
+        if effective_timeout.secs != req.timeout && req.timeout > 0 {
+            info!(
+                "タイムアウトを制限しました: requested={}s, effective={}s, source={}",
+                req.timeout, effective_timeout.secs, effective_timeout.source
+            );
+        }
+        let mut metadata = req.metadata.clone();
+        effective_timeout.record(req.timeout, &mut metadata);
+        // 結果と実行環境を対応付けるためにホスト情報を付与
+        metadata.extend(self.host_fingerprint.to_metadata());
+        if !stripped_env.is_empty() {
+            metadata.insert(METADATA_STRIPPED_ENV.to_string(), stripped_env.join(","));
+        }
+        // ブレークグラスで実行されたタスクには理由を記録する
+        if let Some(break_glass) = decision.metadata.get(METADATA_BREAK_GLASS) {
+            let reason = break_glass["reason"].as_str().unwrap_or_default();
+            metadata.insert(METADATA_BREAK_GLASS.to_string(), reason.to_string());
+        }
+
+        // ポリシーのサンドボックス指定を実行設定に反映する（不正な指定の場合は実行しない）
+        let mut sandbox_config = self.command_executor.sandbox_config().clone();
+        let sandbox_directives = apply_sandbox_directives(&mut sandbox_config, &decision.metadata)?;
+        if !sandbox_directives.is_empty() {
+            info!("ポリシーのサンドボックス指定を適用します: command={}, directives={:?}", req.command, sandbox_directives);
+            metadata.insert(METADATA_SANDBOX_DIRECTIVES.to_string(), sandbox_directives.join(","));
+        }
+
+        // クライアントが要求したサンドボックス設定は、ポリシーで決まった設定を上限として反映する
+        // 追加のマウントはファイルアクセスポリシーでも確認する（拒否された場合は実行しない）
+        let requested = requested_sandbox(req.sandbox_config.as_ref())?;
+        for (paths, mode) in [(&requested.rw_paths, "write"), (&requested.ro_paths, "read")] {
+            for path in paths {
+                let input = file_policy_input(&path.to_string_lossy(), mode);
+                self.policy_engine.check_file_access(&input).await?;
+            }
+        }
+        limit_warnings.extend(apply_requested_sandbox(&mut sandbox_config, &requested, command_limits.as_ref())?);
+        if !limit_warnings.is_empty() {
+            warn!("要求された制限値をポリシーの上限に丸めました: command={}, warnings={:?}", req.command, limit_warnings);
+            metadata.insert(METADATA_LIMIT_WARNINGS.to_string(), limit_warnings.join("; "));
+        }
+
+        // スクリプトはハッシュを記録する（結果はスクリプトの内容にも依存するためキャッシュしない）
+        if let Some(sha256) = policy_input.script_sha256() {
+            metadata.insert(METADATA_SCRIPT_SHA256.to_string(), sha256.to_string());
+        }
+
+        // ドライランはコマンドを実行せず、サンドボックスでの起動方法を結果として返す
+        if req.dry_run {
+            let report = self.command_executor.with_sandbox_config(sandbox_config).dry_run(
+                &req.command,
+                req.args.clone(),
+                env,
+                req.cwd.clone(),
+                Some(effective_timeout.secs),
+            )?;
+            info!("ドライランの結果を返します: command={}, backend={}", req.command, report.backend.as_str());
+            metadata.insert(METADATA_DRY_RUN.to_string(), "true".to_string());
+            let result = proto::TaskResult {
+                exit_code: 0,
+                stdout: String::new(),
+                stderr: String::new(),
+                resource_usage: None,
+                execution_time_ms: 0,
+                process_tree: Vec::new(),
+                runtime_events: Vec::new(),
+                steps: Vec::new(),
+                dry_run: Some(sandbox_dry_run(report)),
+                workspace_diff: None,
+                stdout_overflow: None,
+                stderr_overflow: None,
+            };
+            return Ok(self.complete_without_execution(metadata, result));
+        }
+
+        // ポリシーでキャッシュ可能とされたコマンドは結果キャッシュを参照（変更の報告を求められた場合は実行する）
+        let cacheable = decision.is_cacheable() && script.is_none() && !req.diff_workspace;
+        let cache_key = if self.result_cache.is_enabled() && cacheable {
+            let cache_input = CacheKeyInput {
+                tenant_id: &policy_input.user.tenant_id,
+                command: &req.command,
+                args: &req.args,
+                env: &env,
+                cwd: req.cwd.as_deref(),
+                sandbox_config: &sandbox_config,
+            };
+            self.result_cache.key(&cache_input).unwrap_or_else(|e| {
+                warn!("キャッシュキーを計算できませんでした: command={}, error={}", req.command, e);
+                None
+            })
+        } else {
+            None
+        };
+
+        if let Some(key) = &cache_key {
+            if let Some(cached) = self.result_cache.get(key) {
+                info!("キャッシュ済みの結果を返します: command={}", req.command);
+                metadata.insert(METADATA_RESULT_CACHE.to_string(), "hit".to_string());
+                return Ok(self.complete_without_execution(metadata, cached));
+            }
+            metadata.insert(METADATA_RESULT_CACHE.to_string(), "miss".to_string());
+        }
+
+        // キューが満杯の場合はタスクを作成せずに拒否する
+        let reservation = self.task_queue.reserve()?;
+
+        // タスクIDを生成
+        let task_id = self.generate_task_id();
+        let creation_time = self.current_iso8601();
+
+        // タスク情報を保存（ワーカーが空くまで待ち行列に入る）
+        let mut task_info = proto::TaskInfo {
+            task_id: task_id.clone(),
+            task_type: proto::TaskType::TaskCommand as i32,
+            status: proto::TaskStatus::TaskQueued as i32,
+            created_at: creation_time.clone(), // クローン
+            started_at: None,
+            completed_at: None,
+            metadata,
+        };
+
+        self.track_task(&mut task_info, &policy_input.user.tenant_id);
+        self.task_recorder.created(&task_info, &policy_input.user);
+        self.tasks.insert(task_id.clone(), task_info.clone());
+        
+        // アクティブタスクをカウント
+        metrics::increment_active_tasks();
+
+        // 非同期でタスクを実行（キャンセルできるようにタスクを登録する）
+        let task_guard = self.command_executor.register_task(&task_id)?;
+        let executor = self
+            .command_executor
+            .with_sandbox_config(sandbox_config)
+            .with_task_id(&task_id)
+            .with_tenant_id(&policy_input.user.tenant_id)
+            .with_workspace_diff(req.diff_workspace);
+        // スクリプトはインタプリタが読むため実行権限を付けずに書き込む
+        let executor = match script {
+            Some(script) => executor.with_input_files(vec![StagedFile::from_bytes(SCRIPT_FILE_NAME, script)]),
+            None => executor,
+        };
+        let tasks = self.tasks.clone();
+        let results = self.results.clone();
+        let task_recorder = self.task_recorder.clone();
+        let cmd = req.command.clone();
+        let args = req.args.clone();
+        let cwd = req.cwd.clone();
+        let timeout = Some(effective_timeout.secs);
+        let task_id_clone = task_id.clone();
+        let output_log_config = self.output_log_config.clone();
+        let result_cache = self.result_cache.clone();
+        let tenant_id = policy_input.user.tenant_id.clone();
+
+        // ワーカーで実行（優先度の高いタスクから、テナントごとに順番に実行される）
+        reservation.submit(&task_id, &policy_input.user.tenant_id, req.priority, Box::pin(async move {
+            // サンドボックス実行時間の計測開始
+            let sandbox_timer = metrics::start_sandbox_timer();
+
+            // 出力ログを作成（ストリーミングと成果物の取得に使用）
+            let output_log = OutputLogWriter::create(&output_log_config, &task_id_clone)
+                .map_err(|e| warn!("出力ログを作成できませんでした: task_id={}, error={}", task_id_clone, e))
+                .ok();
+            
+            // タスクを実行中に更新
+            if let Some(mut task) = tasks.get_mut(&task_id_clone) {
+                task.status = proto::TaskStatus::TaskRunning as i32;
+                task.started_at = Some(chrono::Utc::now().to_rfc3339());
+                task_recorder.updated(&task);
+            }
+
+            // コマンドを実行し、出力を読み取った順にログに書き込む（実行中の出力をストリーミングするため）
+            let (output_tx, mut output_rx) = tokio::sync::mpsc::channel::<OutputChunk>(OUTPUT_CHANNEL_CAPACITY);
+            let write_output = async {
+                while let Some(chunk) = output_rx.recv().await {
+                    if let Some(log) = &output_log {
+                        if let Err(e) = log.append(chunk.stream, &chunk.data) {
+                            warn!("出力ログへの書き込みに失敗しました: dir={:?}, error={}", log.dir(), e);
+                        }
+                    }
+                }
+            };
+            let (result, ()) = tokio::join!(
+                executor.execute_streaming(&cmd, args, env, cwd.clone(), timeout, output_tx),
+                write_output
+            );
+                
+            // サンドボックス実行時間を記録
+            metrics::observe_sandbox_execution_time(sandbox_timer, &cmd);
+
+            // 実行エラーをログに書き込む
+            if let (Some(log), Err(e)) = (&output_log, &result) {
+                if let Err(e) = log.append(OutputStream::Stderr, format!("Error: {}", e).as_bytes()) {
+                    warn!("出力ログへの書き込みに失敗しました: dir={:?}, error={}", log.dir(), e);
+                }
+            }
+
+            // 結果を処理
+            if let Some(mut task) = tasks.get_mut(&task_id_clone) {
+                task.completed_at = Some(chrono::Utc::now().to_rfc3339());
+
+                // 一時的な障害で再実行した回数を記録する
+                let retries = executor.task_retries(&task_id_clone);
+                if retries > 0 {
+                    task.metadata.insert(METADATA_RETRIES.to_string(), retries.to_string());
+                    metrics::increment_sandbox_retries(if result.is_ok() { "completed" } else { "failed" }, retries);
+                }
+
+                match result {
+                    Ok(output) => {
+                        // 成功した場合
+                        task.status = proto::TaskStatus::TaskCompleted as i32;
+
+                        // 結果を保存
+                        let execution_time_ms = output.execution_time_ms;
+                        let mut task_result = task_result(output);
+                        if let Some(log) = &output_log {
+                            link_output_artifacts(&mut task_result, log);
+                        }
+
+                        // 正常終了した結果のみキャッシュする（出力の全体を含まない結果は除く）
+                        let complete = task_result.stdout_overflow.is_none() && task_result.stderr_overflow.is_none();
+                        if let Some(key) = cache_key.filter(|_| task_result.exit_code == 0 && complete) {
+                            result_cache.insert(key, &tenant_id, &cmd, cwd.as_deref(), task_result.clone());
+                        }
+
+                        results.insert(task_id_clone.clone(), task_result);
+                        
+                        // 成功メトリクスを記録
+                        metrics::observe_task_execution_time(
+                            Instant::now() - Duration::from_millis(execution_time_ms),
+                            "command",
+                            "completed"
+                        );
+                    }
+                    Err(e) if executor.is_task_cancelled(&task_id_clone) => {
+                        // キャンセルされた場合（プロセスの終了後にキャンセル状態にする）
+                        task.status = proto::TaskStatus::TaskCancelled as i32;
+                        metrics::observe_task_execution_time(sandbox_timer, "command", "cancelled");
+
+                        let task_result = proto::TaskResult {
+                            exit_code: -1,
+                            stdout: String::new(),
+                            stderr: format!("Error: {}", e),
+                            resource_usage: None,
+                            execution_time_ms: 0,
+                            process_tree: Vec::new(),
+                            runtime_events: Vec::new(),
+                            steps: Vec::new(),
+                            dry_run: None,
+                            workspace_diff: None,
+                            stdout_overflow: None,
+                            stderr_overflow: None,
+                        };
+                        results.insert(task_id_clone.clone(), task_result);
+                    }
+                    Err(e) => {
+                        // 失敗した場合
+                        task.status = proto::TaskStatus::TaskFailed as i32;
+                        
+                        // エラーを記録
+                        let error_type = match &e {
+                            McpError::Execution(message) if message.contains("timed out") => "timeout",
+                            McpError::Execution(_) => "command_failed",
+                            McpError::Temporary(_) => "timeout",
+                            McpError::Sandbox(_)
+                                if e.code() == mcp_common::error::error_code::SANDBOX_DISK_QUOTA_EXCEEDED =>
+                            {
+                                "disk_quota_exceeded"
+                            }
+                            McpError::Sandbox(_)
+                                if e.code() == mcp_common::error::error_code::SANDBOX_CPU_TIME_EXCEEDED =>
+                            {
+                                "cpu_time_exceeded"
+                            }
+                            McpError::Sandbox(_)
+                                if e.code() == mcp_common::error::error_code::SANDBOX_OUT_OF_MEMORY =>
+                            {
+                                "out_of_memory"
+                            }
+                            McpError::Sandbox(_) => "sandbox_error",
+                            _ => "other",
+                        };
+                        // タイムアウトのメッセージには出力が含まれるため、ラベルにはエラーコードを使う
+                        metrics::increment_error_counter(error_type, &e.code().to_string());
+                        
+                        // 失敗メトリクスを記録
+                        metrics::observe_task_execution_time(
+                            sandbox_timer,
+                            "command",
+                            "failed"
+                        );
+
+                        // 結果を保存
+                        let task_result = proto::TaskResult {
+                            exit_code: -1,
+                            stdout: String::new(),
+                            stderr: format!("Error: {}", e),
+                            resource_usage: None,
+                            execution_time_ms: 0,
+                            process_tree: Vec::new(),
+                            runtime_events: Vec::new(),
+                            steps: Vec::new(),
+                            dry_run: None,
+                            workspace_diff: None,
+                            stdout_overflow: None,
+                            stderr_overflow: None,
+                        };
+
+                        results.insert(task_id_clone.clone(), task_result);
+                    }
+                }
+
+                // 結果を先に書き込み、完了したタスクに結果がない状態をストアで見せない
+                if let Some(task_result) = results.get(&task_id_clone) {
+                    task_recorder.result(&task_id_clone, &task_result);
+                }
+                task_recorder.updated(&task);
+                
+                // アクティブタスクカウントを減少
+                metrics::decrement_active_tasks();
+            }
+
+            // 結果の保存後にログを完了させる（ストリームは完了を見て終了コードを送る）
+            if let Some(log) = &output_log {
+                if let Err(e) = log.finish() {
+                    warn!("出力ログの完了に失敗しました: dir={:?}, error={}", log.dir(), e);
+                }
+            }
+
+            // タスクの完了を待っているキャンセル要求に通知する
+            drop(task_guard);
+        }));
+
+        // タスク作成応答を返す
+        Ok(TaskCreatedResponse {
+            task_id,
+            status: proto::TaskStatus::TaskQueued as i32,
+            created_at: creation_time,
+        })
+    }
     /// タスクIDを生成
     fn generate_task_id(&self) -> String {
         format!("task-{}", Uuid::new_v4().simple())
@@ -93,6 +1313,19 @@ impl McpService for McpServiceImpl {
                 status: "ok".to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
                 uptime_seconds: uptime,
+                host: self.host_fingerprint.to_map(),
+                policy_revision: self
+                    .policy_bundle
+                    .as_ref()
+                    .and_then(|bundle| bundle.revision())
+                    .unwrap_or_default(),
+                // バンドル署名の検証状態（未署名のポリシーが本番で有効になっていないかの確認用）
+                policy_signature: self
+                    .policy_bundle
+                    .as_ref()
+                    .and_then(|bundle| bundle.state().signature)
+                    .map(|signature| signature.to_string())
+                    .unwrap_or_default(),
             };
             
             // 追加情報をメタデータに含める
@@ -105,191 +1338,396 @@ impl McpService for McpServiceImpl {
                 );
             }
 
-            response
-        });
+            response
+        });
+
+        ErrorHandler::handle(result)
+    }
+
+    /// コマンド実行
+    async fn execute_command(
+        &self,
+        request: Request<CommandRequest>,
+    ) -> Result<Response<TaskCreatedResponse>, Status> {
+        let break_glass_token = request
+            .metadata()
+            .get(BREAK_GLASS_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let req = request.into_inner();
+        info!("コマンド実行リクエスト: command={}", req.command);
+        
+        // タスク実行時間の計測開始
+        let timer = metrics::start_task_timer();
+        
+        // API呼び出しをメトリクスに記録
+        metrics::increment_api_requests("POST", "/execute_command", "200");
+
+        // ErrorHandlerを使用して実装全体を包む（ポリシー評価を待つため非同期関数）
+        let result = self.create_command_task(req, break_glass_token.as_deref(), None).await;
+        
+        // タスク作成の全体時間を記録
+        let status = match &result {
+            Ok(_) => "success",
+            Err(_) => "error",
+        };
+        metrics::observe_task_execution_time(timer, "task_creation", status);
+
+        // エラーハンドリングと応答
+        ErrorHandler::handle(result)
+    }
+
+    /// スクリプト実行（スクリプトをタスクのワークスペースに書き込み、インタプリタで実行）
+    async fn execute_script(
+        &self,
+        request: Request<ScriptRequest>,
+    ) -> Result<Response<TaskCreatedResponse>, Status> {
+        let break_glass_token = request
+            .metadata()
+            .get(BREAK_GLASS_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let req = request.into_inner();
+        info!("スクリプト実行リクエスト: interpreter={}, bytes={}", req.interpreter, req.script.len());
+
+        // タスク実行時間の計測開始
+        let timer = metrics::start_task_timer();
+
+        // API呼び出しをメトリクスに記録
+        metrics::increment_api_requests("POST", "/execute_script", "200");
+
+        let result = async {
+            if req.interpreter.is_empty() {
+                return Err(McpError::InvalidRequest("インタプリタが指定されていません".to_string()));
+            }
+            if req.script.is_empty() {
+                return Err(McpError::InvalidRequest("スクリプトが空です".to_string()));
+            }
+            if req.script.len() > MAX_SCRIPT_BYTES {
+                return Err(McpError::InvalidRequest(format!(
+                    "スクリプトが大きすぎます: {}バイト（上限{}バイト）",
+                    req.script.len(),
+                    MAX_SCRIPT_BYTES
+                )));
+            }
+
+            // インタプリタの引数、スクリプトのパス、スクリプトの引数の順に渡す
+            let mut args = req.interpreter_args;
+            args.push(script_path());
+            args.extend(req.args);
+            let command_request = CommandRequest {
+                command: req.interpreter,
+                args,
+                env: req.env,
+                cwd: req.cwd,
+                timeout: req.timeout,
+                metadata: req.metadata,
+                sandbox_config: req.sandbox_config,
+                dry_run: false,
+                diff_workspace: false,
+                priority: req.priority,
+            };
+            self.create_command_task(command_request, break_glass_token.as_deref(), Some(req.script)).await
+        }
+        .await;
+
+        // タスク作成の全体時間を記録
+        let status = match &result {
+            Ok(_) => "success",
+            Err(_) => "error",
+        };
+        metrics::observe_task_execution_time(timer, "task_creation", status);
 
+        // エラーハンドリングと応答
         ErrorHandler::handle(result)
     }
 
-    /// コマンド実行
-    async fn execute_command(
+    /// プラン実行（依存関係のあるコマンドを1つのタスクとして順に実行）
+    async fn execute_plan(
         &self,
-        request: Request<CommandRequest>,
+        request: Request<PlanRequest>,
     ) -> Result<Response<TaskCreatedResponse>, Status> {
-        let req = request.into_inner();
-        info!("コマンド実行リクエスト: command={}", req.command);
-        
+        let break_glass_token = request
+            .metadata()
+            .get(BREAK_GLASS_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let mut req = request.into_inner();
+        info!("プラン実行リクエスト: steps={}", req.steps.len());
+
         // タスク実行時間の計測開始
         let timer = metrics::start_task_timer();
-        
+
         // API呼び出しをメトリクスに記録
-        metrics::increment_api_requests("POST", "/execute_command", "200");
+        metrics::increment_api_requests("POST", "/execute_plan", "200");
 
-        // ErrorHandlerを使用して実装全体を包む
-        let result: McpResult<TaskCreatedResponse> = (|| {
-            // ポリシーチェック
-            let policy_timer = metrics::start_task_timer();
-            let policy_input = PolicyInput {
-                user: UserInfo {
-                    id: "user1".to_string(), // TODO: 認証から取得
-                    tenant_id: "tenant1".to_string(),
-                    roles: vec!["user".to_string()],
-                    attributes: HashMap::new(),
-                },
-                command: CommandInfo {
-                    name: req.command.clone(),
-                    args: req.args.clone(),
-                    cwd: req.cwd.clone().unwrap_or_default(),
-                    env: req.env.clone(),
-                },
-                file: None,
-                network: None,
-                resources: Default::default(),
-                context: HashMap::new(),
-            };
+        let result: McpResult<TaskCreatedResponse> = async {
+            // 言語ランタイムのプリセットはすべてのステップに反映する
+            let envs = req.steps.iter_mut().filter_map(|step| step.command.as_mut()).map(|command| &mut command.env);
+            apply_sandbox_preset(req.sandbox_config.as_mut(), envs)?;
 
-            // ポリシー評価
-            let policy_result = self.policy_engine.check_command_execution(&policy_input);
-            
-            // ポリシー評価メトリクスを記録
-            let policy_result_str = match &policy_result {
-                Ok(_) => "allowed",
-                Err(_) => "denied",
-            };
-            metrics::increment_policy_evaluations("command_execution", policy_result_str);
-            metrics::observe_task_execution_time(policy_timer, "policy_evaluation", policy_result_str);
-            
-            // エラーがあれば伝搬
-            policy_result?;
+            // ポリシー評価の前にステップの構成（ID・依存関係・循環）を検証する
+            let mut plan = ExecutionPlan::default();
+            let mut commands = Vec::with_capacity(req.steps.len());
+            for step in &req.steps {
+                let command = step.command.as_ref().ok_or_else(|| {
+                    McpError::InvalidRequest(format!("プランのステップにコマンドが指定されていません: {}", step.id))
+                })?;
+                if command.dry_run {
+                    return Err(McpError::InvalidRequest(format!(
+                        "プランのステップはドライランできません: {}",
+                        step.id
+                    )));
+                }
+                if command.diff_workspace {
+                    return Err(McpError::InvalidRequest(format!(
+                        "プランのステップではワークスペースの変更を報告できません: {}",
+                        step.id
+                    )));
+                }
+                plan.steps.push(PlanStep {
+                    cwd: command.cwd.clone(),
+                    depends_on: step.depends_on.clone(),
+                    ..PlanStep::new(&step.id, &command.command, command.args.clone())
+                });
+                commands.push(command);
+            }
+            plan.execution_order()?;
+
+            // クライアントが要求したサンドボックス設定はすべてのステップに適用する
+            // 追加のマウントはファイルアクセスポリシーでも確認する（拒否された場合は実行しない）
+            let requested = requested_sandbox(req.sandbox_config.as_ref())?;
+            for (paths, mode) in [(&requested.rw_paths, "write"), (&requested.ro_paths, "read")] {
+                for path in paths {
+                    let input = file_policy_input(&path.to_string_lossy(), mode);
+                    self.policy_engine.check_file_access(&input).await?;
+                }
+            }
+
+            let mut metadata = req.metadata.clone();
+            metadata.extend(self.host_fingerprint.to_metadata());
+            let mut stripped = Vec::new();
+            let mut limit_warnings = Vec::new();
+            let mut sandbox_directives = Vec::new();
+            let mut break_glass = Vec::new();
+            let mut tenant_id = request_user().tenant_id;
+
+            // 各ステップのコマンドをポリシーで確認する（1つでも拒否されればプラン全体を実行しない）
+            for (step, command) in plan.steps.iter_mut().zip(commands) {
+                let policy_timer = metrics::start_task_timer();
+                let mut policy_input = command_policy_input(command);
+                let policy_result: McpResult<_> = async {
+                    let stripped_env = self.policy_engine.apply_env_policy(&mut policy_input)?;
+                    self.resolve_executable(&mut policy_input)?;
+                    self.policy_engine.check_resource_limits(&policy_input)?;
+                    let decision = self
+                        .policy_engine
+                        .check_command_execution_with_break_glass(&policy_input, break_glass_token.as_deref())
+                        .await?;
+                    Ok((decision, stripped_env))
+                }
+                .await;
+
+                // ポリシー評価メトリクスを記録
+                let policy_result_str = match &policy_result {
+                    Ok(_) => "allowed",
+                    Err(_) => "denied",
+                };
+                metrics::increment_policy_evaluations("command_execution", policy_result_str);
+                metrics::observe_task_execution_time(policy_timer, "policy_evaluation", policy_result_str);
+
+                let (decision, stripped_env) = policy_result.map_err(|e| plan_step_error(&step.id, e))?;
+                step.env = policy_input.command.env.clone();
+                stripped.extend(stripped_env.into_iter().map(|name| format!("{}:{}", step.id, name)));
+                tenant_id = policy_input.user.tenant_id.clone();
+
+                // ステップごとに実効タイムアウトを決定する（上限を超えた値は丸めて警告する）
+                let command_limits = decision.command_limits().map_err(|e| plan_step_error(&step.id, e))?;
+                let tool = self
+                    .policy_engine
+                    .effective_command_name(&command.command, &command.args)
+                    .map_err(|e| plan_step_error(&step.id, e))?;
+                let effective_timeout = self.timeout_policy.resolve_with_limits(
+                    &tenant_id,
+                    &tool,
+                    command.timeout,
+                    command_limits.as_ref(),
+                );
+                if effective_timeout.source == TimeoutSource::Policy && command.timeout > 0 {
+                    limit_warnings.push(format!(
+                        "step {}: timeout {}s exceeds the policy maximum {}s and was clamped",
+                        step.id, command.timeout, effective_timeout.secs
+                    ));
+                }
+                step.timeout = effective_timeout.secs;
+                if let Some(reason) = decision.metadata.get(METADATA_BREAK_GLASS) {
+                    break_glass.push(format!("{}: {}", step.id, reason["reason"].as_str().unwrap_or_default()));
+                }
+
+                // ポリシーのサンドボックス指定と要求された設定はステップごとの実行設定に反映する
+                let mut sandbox_config = self.command_executor.sandbox_config().clone();
+                let directives = apply_sandbox_directives(&mut sandbox_config, &decision.metadata)
+                    .map_err(|e| plan_step_error(&step.id, e))?;
+                sandbox_directives.extend(directives.into_iter().map(|name| format!("{}:{}", step.id, name)));
+                let warnings = apply_requested_sandbox(&mut sandbox_config, &requested, command_limits.as_ref())
+                    .map_err(|e| plan_step_error(&step.id, e))?;
+                limit_warnings.extend(warnings.into_iter().map(|warning| format!("step {}: {}", step.id, warning)));
+                step.sandbox_config = Some(sandbox_config);
+            }
+
+            for (key, values, separator) in [
+                (METADATA_STRIPPED_ENV, &stripped, ","),
+                (METADATA_SANDBOX_DIRECTIVES, &sandbox_directives, ","),
+                (METADATA_LIMIT_WARNINGS, &limit_warnings, "; "),
+                (METADATA_BREAK_GLASS, &break_glass, "; "),
+            ] {
+                if !values.is_empty() {
+                    metadata.insert(key.to_string(), values.join(separator));
+                }
+            }
+            if !limit_warnings.is_empty() {
+                warn!("要求された制限値をポリシーの上限に丸めました: warnings={:?}", limit_warnings);
+            }
+
+            // キューが満杯の場合はタスクを作成せずに拒否する
+            let reservation = self.task_queue.reserve()?;
 
             // タスクIDを生成
             let task_id = self.generate_task_id();
             let creation_time = self.current_iso8601();
 
-            // タスク情報を保存
-            let task_info = proto::TaskInfo {
+            // タスク情報を保存（ワーカーが空くまで待ち行列に入る）
+            let mut task_info = proto::TaskInfo {
                 task_id: task_id.clone(),
-                task_type: proto::TaskType::TaskCommand as i32,
-                status: proto::TaskStatus::TaskCreated as i32,
-                created_at: creation_time.clone(), // クローン
+                task_type: proto::TaskType::TaskPlan as i32,
+                status: proto::TaskStatus::TaskQueued as i32,
+                created_at: creation_time.clone(),
                 started_at: None,
                 completed_at: None,
-                metadata: req.metadata.clone(),
+                metadata,
             };
+            self.track_task(&mut task_info, &tenant_id);
+            self.task_recorder.created(&task_info, &request_user());
+            self.tasks.insert(task_id.clone(), task_info);
 
-            self.tasks.insert(task_id.clone(), task_info.clone());
-            
             // アクティブタスクをカウント
             metrics::increment_active_tasks();
 
-            // 非同期でタスクを実行
-            let executor = self.command_executor.clone();
+            // 非同期でプランを実行（キャンセルできるようにタスクを登録する）
+            let task_guard = self.command_executor.register_task(&task_id)?;
+            let executor = self.command_executor.with_task_id(&task_id).with_tenant_id(&tenant_id);
             let tasks = self.tasks.clone();
             let results = self.results.clone();
-            let cmd = req.command.clone();
-            let args = req.args.clone();
-            let env = req.env.clone();
-            let cwd = req.cwd.clone();
-            let timeout = if req.timeout > 0 { Some(req.timeout) } else { None };
+            let task_recorder = self.task_recorder.clone();
             let task_id_clone = task_id.clone();
+            let output_log_config = self.output_log_config.clone();
 
-            // 別スレッドで実行
-            tokio::spawn(async move {
+            // ワーカーで実行（優先度の高いタスクから、テナントごとに順番に実行される）
+            reservation.submit(&task_id, &tenant_id, req.priority, Box::pin(async move {
                 // サンドボックス実行時間の計測開始
                 let sandbox_timer = metrics::start_sandbox_timer();
-                
+
+                // 出力ログを作成（すべてのステップの出力を実行順に書き込む）
+                let output_log = OutputLogWriter::create(&output_log_config, &task_id_clone)
+                    .map_err(|e| warn!("出力ログを作成できませんでした: task_id={}, error={}", task_id_clone, e))
+                    .ok();
+
                 // タスクを実行中に更新
                 if let Some(mut task) = tasks.get_mut(&task_id_clone) {
                     task.status = proto::TaskStatus::TaskRunning as i32;
                     task.started_at = Some(chrono::Utc::now().to_rfc3339());
+                    task_recorder.updated(&task);
                 }
 
-                // コマンドを実行
-                let result = executor
-                    .execute(&cmd, args, env, cwd, timeout)
-                    .await;
-                    
+                let (output_tx, mut output_rx) = tokio::sync::mpsc::channel::<OutputChunk>(OUTPUT_CHANNEL_CAPACITY);
+                let write_output = async {
+                    while let Some(chunk) = output_rx.recv().await {
+                        if let Some(log) = &output_log {
+                            if let Err(e) = log.append(chunk.stream, &chunk.data) {
+                                warn!("出力ログへの書き込みに失敗しました: dir={:?}, error={}", log.dir(), e);
+                            }
+                        }
+                    }
+                };
+                let (result, ()) = tokio::join!(executor.execute_plan(&plan, Some(output_tx)), write_output);
+
                 // サンドボックス実行時間を記録
-                metrics::observe_sandbox_execution_time(sandbox_timer, &cmd);
+                metrics::observe_sandbox_execution_time(sandbox_timer, "plan");
+
+                // 実行エラーをログに書き込む
+                if let (Some(log), Err(e)) = (&output_log, &result) {
+                    if let Err(e) = log.append(OutputStream::Stderr, format!("Error: {}", e).as_bytes()) {
+                        warn!("出力ログへの書き込みに失敗しました: dir={:?}, error={}", log.dir(), e);
+                    }
+                }
 
-                // 結果を処理
+                // 結果を処理（失敗したステップがあってもプランは完了とし、終了コードとステップの結果で報告する）
                 if let Some(mut task) = tasks.get_mut(&task_id_clone) {
                     task.completed_at = Some(chrono::Utc::now().to_rfc3339());
-
-                    match result {
-                        Ok(output) => {
-                            // 成功した場合
-                            task.status = proto::TaskStatus::TaskCompleted as i32;
-
-                            // 結果を保存
-                            let task_result = proto::TaskResult {
-                                exit_code: output.exit_code.unwrap_or(-1),
-                                stdout: output.stdout,
-                                stderr: output.stderr,
-                                resource_usage: Some(proto::ResourceUsage {
-                                    cpu_time_ms: output.resource_usage.cpu_time_ms,
-                                    max_memory_kb: output.resource_usage.max_memory_kb,
-                                    io_read_bytes: output.resource_usage.io_read_bytes,
-                                    io_write_bytes: output.resource_usage.io_write_bytes,
-                                }),
-                                execution_time_ms: output.execution_time_ms,
+                    let cancelled = executor.is_task_cancelled(&task_id_clone);
+                    let (status, task_result) = match result {
+                        Ok(plan_result) => {
+                            let status = if cancelled {
+                                proto::TaskStatus::TaskCancelled
+                            } else {
+                                proto::TaskStatus::TaskCompleted
                             };
-
-                            results.insert(task_id_clone.clone(), task_result);
-                            
-                            // 成功メトリクスを記録
-                            metrics::observe_task_execution_time(
-                                Instant::now() - Duration::from_millis(output.execution_time_ms),
-                                "command",
-                                "completed"
-                            );
+                            let mut task_result = plan_task_result(plan_result);
+                            if let Some(log) = &output_log {
+                                link_output_artifacts(&mut task_result, log);
+                            }
+                            (status, task_result)
                         }
                         Err(e) => {
-                            // 失敗した場合
-                            task.status = proto::TaskStatus::TaskFailed as i32;
-                            
-                            // エラーを記録
-                            let error_type = match &e {
-                                McpError::Execution(_) => "command_failed",
-                                McpError::Temporary(_) => "timeout",
-                                McpError::Sandbox(_) => "sandbox_error",
-                                _ => "other",
+                            metrics::increment_error_counter("plan_failed", &e.code().to_string());
+                            let status = if cancelled {
+                                proto::TaskStatus::TaskCancelled
+                            } else {
+                                proto::TaskStatus::TaskFailed
                             };
-                            metrics::increment_error_counter(error_type, &e.to_string());
-                            
-                            // 失敗メトリクスを記録
-                            metrics::observe_task_execution_time(
-                                sandbox_timer,
-                                "command",
-                                "failed"
-                            );
-
-                            // 結果を保存
                             let task_result = proto::TaskResult {
                                 exit_code: -1,
-                                stdout: String::new(),
                                 stderr: format!("Error: {}", e),
-                                resource_usage: None,
-                                execution_time_ms: 0,
+                                ..Default::default()
                             };
-
-                            results.insert(task_id_clone, task_result);
+                            (status, task_result)
                         }
-                    }
-                    
+                    };
+                    task.status = status as i32;
+                    let status_str = match status {
+                        proto::TaskStatus::TaskCompleted => "completed",
+                        proto::TaskStatus::TaskCancelled => "cancelled",
+                        _ => "failed",
+                    };
+                    metrics::observe_task_execution_time(sandbox_timer, "plan", status_str);
+                    task_recorder.result(&task_id_clone, &task_result);
+                    task_recorder.updated(&task);
+                    results.insert(task_id_clone.clone(), task_result);
+
                     // アクティブタスクカウントを減少
                     metrics::decrement_active_tasks();
                 }
-            });
+
+                // 結果の保存後にログを完了させる（ストリームは完了を見て終了コードを送る）
+                if let Some(log) = &output_log {
+                    if let Err(e) = log.finish() {
+                        warn!("出力ログの完了に失敗しました: dir={:?}, error={}", log.dir(), e);
+                    }
+                }
+
+                // タスクの完了を待っているキャンセル要求に通知する
+                drop(task_guard);
+            }));
 
             // タスク作成応答を返す
             Ok(TaskCreatedResponse {
                 task_id,
-                status: proto::TaskStatus::TaskCreated as i32,
+                status: proto::TaskStatus::TaskQueued as i32,
                 created_at: creation_time,
             })
-        })();
-        
+        }
+        .await;
+
         // タスク作成の全体時間を記録
         let status = match &result {
             Ok(_) => "success",
@@ -309,21 +1747,35 @@ impl McpService for McpServiceImpl {
         let req = request.into_inner();
         debug!("タスク状態取得リクエスト: task_id={}", req.task_id);
 
-        let result: McpResult<TaskStatusResponse> = (|| {
+        let result: McpResult<TaskStatusResponse> = async {
             // タスク情報を取得
-            let task_info = match self.tasks.get(&req.task_id) {
-                Some(info) => info.clone(),
-                None => return Err(McpError::NotFound(format!("タスクが見つかりません: {}", req.task_id))),
+            let task_info = self.tasks.get(&req.task_id).map(|info| info.clone());
+            let Some(task_info) = task_info else {
+                // 再起動前や他のインスタンスで作成されたタスクはタスクストアから取得する
+                let Some((task_info, result)) = self.task_recorder.load(&req.task_id).await? else {
+                    return Err(McpError::NotFound(format!("タスクが見つかりません: {}", req.task_id)));
+                };
+                self.check_task_owner(&task_info)?;
+                return Ok(TaskStatusResponse {
+                    task_info: Some(task_info),
+                    result,
+                    live_usage: None,
+                });
             };
 
+            self.check_task_owner(&task_info)?;
+
             // 結果を取得（存在する場合）
             let result = self.results.get(&req.task_id).map(|r| r.clone());
 
             Ok(TaskStatusResponse {
                 task_info: Some(task_info),
                 result,
+                // 実行中のコマンドのリソース使用量（タスクcgroupで実行中の場合のみ）
+                live_usage: self.command_executor.sample_task_usage(&req.task_id).map(resource_sample),
             })
-        })();
+        }
+        .await;
 
         ErrorHandler::handle(result)
     }
@@ -340,24 +1792,103 @@ impl McpService for McpServiceImpl {
         
         let result: McpResult<Self::StreamTaskOutputStream> = (|| {
             // タスク情報を確認
-            if !self.tasks.contains_key(&req.task_id) {
-                return Err(McpError::NotFound(format!("タスクが見つかりません: {}", req.task_id)));
-            }
+            self.owned_task(&req.task_id)?;
 
-            // ダミーデータのストリームを作成（実際の実装ではコマンド出力を監視する）
+            let reader = OutputLogReader::new(&self.output_log_config, &req.task_id)?;
             let (tx, rx) = tokio::sync::mpsc::channel(128);
-            
-            // ダミーデータ送信用のタスク
+
+            // 出力ログの最新セグメントを追跡して送信するタスク
             let task_id = req.task_id.clone();
+            let tasks = self.tasks.clone();
+            let results = self.results.clone();
+            let executor = self.command_executor.clone();
             tokio::spawn(async move {
-                // 実装されたら、実際のコマンド出力をstreaming
-                // ここでは単にダミーデータを送信
-                let _ = tx.send(Ok(TaskOutputChunk {
-                    task_id: task_id.clone(),
-                    r#type: proto::OutputChunkType::ChunkStdout as i32,
-                    data: "ストリーミングテスト出力\n".as_bytes().to_vec(),
-                    timestamp_ms: 0,
-                })).await;
+                let mut cursor = TailCursor::default();
+                let mut last_telemetry: Option<Instant> = None;
+                loop {
+                    let index = match reader.index() {
+                        Ok(index) => Some(index),
+                        // まだ実行が開始されていない
+                        Err(McpError::NotFound(_)) => None,
+                        Err(e) => {
+                            let _ = tx.send(Err(ErrorHandler::catch(e))).await;
+                            return;
+                        }
+                    };
+
+                    let mut received = false;
+                    if let Some(index) = &index {
+                        let chunks = match reader.read_new(index, &mut cursor) {
+                            Ok(chunks) => chunks,
+                            Err(e) => {
+                                let _ = tx.send(Err(ErrorHandler::catch(e))).await;
+                                return;
+                            }
+                        };
+
+                        for (stream, data) in chunks {
+                            received = true;
+                            let chunk_type = match stream {
+                                OutputStream::Stdout => proto::OutputChunkType::ChunkStdout,
+                                OutputStream::Stderr => proto::OutputChunkType::ChunkStderr,
+                            };
+                            let chunk = TaskOutputChunk {
+                                task_id: task_id.clone(),
+                                r#type: chunk_type as i32,
+                                data,
+                                timestamp_ms: current_timestamp_ms(),
+                                resource_sample: None,
+                            };
+                            if tx.send(Ok(chunk)).await.is_err() {
+                                // クライアントが切断した
+                                return;
+                            }
+                        }
+                    }
+
+                    // 実行中のタスクのリソース使用量を一定間隔で送る（タスクcgroupで実行中のコマンドのみ）
+                    if last_telemetry.is_none_or(|sent| sent.elapsed() >= TELEMETRY_INTERVAL) {
+                        if let Some(sample) = executor.sample_task_usage(&task_id) {
+                            last_telemetry = Some(Instant::now());
+                            let chunk = TaskOutputChunk {
+                                task_id: task_id.clone(),
+                                r#type: proto::OutputChunkType::ChunkTelemetry as i32,
+                                data: Vec::new(),
+                                timestamp_ms: sample.timestamp_ms,
+                                resource_sample: Some(resource_sample(sample)),
+                            };
+                            if tx.send(Ok(chunk)).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+
+                    if received {
+                        continue;
+                    }
+
+                    // ログが完了しているか、ログが作られないまま終了したタスク
+                    let finished = index.as_ref().map(|i| i.finished).unwrap_or(false);
+                    let terminal = tasks
+                        .get(&task_id)
+                        .map(|task| is_terminal_status(task.status))
+                        .unwrap_or(true);
+                    if finished || (index.is_none() && terminal) {
+                        let exit_code = results.get(&task_id).map(|r| r.exit_code);
+                        if let Some(exit_code) = exit_code {
+                            let _ = tx.send(Ok(TaskOutputChunk {
+                                task_id: task_id.clone(),
+                                r#type: proto::OutputChunkType::ChunkExitCode as i32,
+                                data: exit_code.to_string().into_bytes(),
+                                timestamp_ms: current_timestamp_ms(),
+                                resource_sample: None,
+                            })).await;
+                        }
+                        return;
+                    }
+
+                    tokio::time::sleep(OUTPUT_POLL_INTERVAL).await;
+                }
             });
             
             Ok(ReceiverStream::new(rx))
@@ -374,31 +1905,281 @@ impl McpService for McpServiceImpl {
         let req = request.into_inner();
         info!("タスクキャンセルリクエスト: task_id={}", req.task_id);
         
-        let result: McpResult<TaskStatusResponse> = (|| {
+        let result: McpResult<TaskStatusResponse> = async {
             // タスク情報を取得
-            let mut task_info = match self.tasks.get_mut(&req.task_id) {
-                Some(info) => info,
-                None => return Err(McpError::NotFound(format!("タスクが見つかりません: {}", req.task_id))),
-            };
+            let status = self.owned_task(&req.task_id)?.status;
+
+            // 終了済みのタスクはそのまま返す
+            if !is_terminal_status(status) && self.task_queue.cancel(&req.task_id) {
+                // 実行を待っていたタスクはキューから取り除き、ここでキャンセル状態にする
+                self.cancel_queued_task(&req.task_id, "Task was cancelled before it started");
+            } else if !is_terminal_status(status) {
+                // 実行中のプロセスツリーを終了させ（SIGTERM、猶予期間後にSIGKILL）、タスクの完了を待つ。
+                // キャンセル状態への更新はプロセスの終了後にタスク側で行う
+                if !self.command_executor.cancel_task(&req.task_id).await? {
+                    // 登録されていない（既に完了した）タスク
+                    debug!("キャンセル対象のプロセスがありません: task_id={}", req.task_id);
+                }
+            }
+
+            let task_info = self
+                .tasks
+                .get(&req.task_id)
+                .map(|info| info.clone())
+                .ok_or_else(|| McpError::NotFound(format!("タスクが見つかりません: {}", req.task_id)))?;
+            let result = self.results.get(&req.task_id).map(|result| result.clone());
 
-            // タスクをキャンセル状態に更新
-            task_info.status = proto::TaskStatus::TaskCancelled as i32;
-            task_info.completed_at = Some(self.current_iso8601());
-            
-            let task_info_clone = task_info.clone();
-            
-            // TODO: 実際のタスクをキャンセルする処理を実装
-            
             // レスポンスを返す
             Ok(TaskStatusResponse {
-                task_info: Some(task_info_clone),
-                result: None,
+                task_info: Some(task_info),
+                result,
+                live_usage: None,
+            })
+        }.await;
+
+        ErrorHandler::handle(result)
+    }
+    
+    /// タスクの一時停止
+    async fn pause_task(
+        &self,
+        request: Request<TaskStatusRequest>,
+    ) -> Result<Response<TaskStatusResponse>, Status> {
+        let req = request.into_inner();
+        info!("タスク一時停止リクエスト: task_id={}", req.task_id);
+
+        // プロセスはcgroupフリーザー（またはSIGSTOP）で停止し、状態を保ったまま再開できる
+        ErrorHandler::handle(self.set_task_paused(&req.task_id, true))
+    }
+
+    /// タスクの再開
+    async fn resume_task(
+        &self,
+        request: Request<TaskStatusRequest>,
+    ) -> Result<Response<TaskStatusResponse>, Status> {
+        let req = request.into_inner();
+        info!("タスク再開リクエスト: task_id={}", req.task_id);
+
+        ErrorHandler::handle(self.set_task_paused(&req.task_id, false))
+    }
+
+    /// タスク成果物の一覧取得
+    async fn list_task_artifacts(
+        &self,
+        request: Request<TaskStatusRequest>,
+    ) -> Result<Response<TaskArtifactList>, Status> {
+        let req = request.into_inner();
+        debug!("タスク成果物一覧リクエスト: task_id={}", req.task_id);
+
+        let result: McpResult<TaskArtifactList> = (|| {
+            self.owned_task(&req.task_id)?;
+
+            let reader = OutputLogReader::new(&self.output_log_config, &req.task_id)?;
+            let index = match reader.index() {
+                Ok(index) => index,
+                // 出力ログがまだ無い
+                Err(McpError::NotFound(_)) => Default::default(),
+                Err(e) => return Err(e),
+            };
+
+            let artifacts = index
+                .segments
+                .iter()
+                .map(|segment| TaskArtifact {
+                    name: segment.name.clone(),
+                    kind: format!("{}_segment", segment.stream.as_str()),
+                    size_bytes: reader.segment_size(segment),
+                    created_at_ms: segment.created_at_ms,
+                    live: segment.is_live(),
+                })
+                .collect();
+
+            Ok(TaskArtifactList {
+                task_id: req.task_id.clone(),
+                artifacts,
             })
         })();
 
         ErrorHandler::handle(result)
     }
+
+    /// タスク成果物のダウンロード
+    type DownloadTaskArtifactStream = ReceiverStream<Result<TaskArtifactChunk, Status>>;
+
+    async fn download_task_artifact(
+        &self,
+        request: Request<TaskArtifactRequest>,
+    ) -> Result<Response<Self::DownloadTaskArtifactStream>, Status> {
+        let req = request.into_inner();
+        debug!("タスク成果物ダウンロードリクエスト: task_id={}, name={}", req.task_id, req.name);
+
+        let result: McpResult<Self::DownloadTaskArtifactStream> = (|| {
+            self.owned_task(&req.task_id)?;
+
+            let reader = OutputLogReader::new(&self.output_log_config, &req.task_id)?;
+            let index = reader.index()?;
+            if index.segment(&req.name).is_none() {
+                return Err(McpError::NotFound(format!("成果物が見つかりません: {}", req.name)));
+            }
+
+            let (tx, rx) = tokio::sync::mpsc::channel(16);
+            tokio::spawn(async move {
+                let mut offset = 0;
+                loop {
+                    let data = match reader.read_segment(&index, &req.name, offset, ARTIFACT_CHUNK_SIZE) {
+                        Ok(data) => data,
+                        Err(e) => {
+                            let _ = tx.send(Err(ErrorHandler::catch(e))).await;
+                            return;
+                        }
+                    };
+                    if data.is_empty() {
+                        return;
+                    }
+
+                    let len = data.len() as u64;
+                    let chunk = TaskArtifactChunk {
+                        name: req.name.clone(),
+                        offset,
+                        data,
+                    };
+                    if tx.send(Ok(chunk)).await.is_err() {
+                        return;
+                    }
+                    offset += len;
+                }
+            });
+
+            Ok(ReceiverStream::new(rx))
+        })();
+
+        ErrorHandler::handle(result)
+    }
+    
+    /// 結果キャッシュの無効化
+    async fn invalidate_result_cache(
+        &self,
+        request: Request<InvalidateResultCacheRequest>,
+    ) -> Result<Response<InvalidateResultCacheResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "結果キャッシュ無効化リクエスト: command={:?}, tenant_id={:?}, path_prefix={:?}",
+            req.command, req.tenant_id, req.path_prefix
+        );
+
+        let result = caller_tenant(req.tenant_id).map(|tenant_id| {
+            let filter = InvalidationFilter {
+                command: req.command,
+                tenant_id,
+                path_prefix: req.path_prefix,
+            };
+            InvalidateResultCacheResponse {
+                removed: self.result_cache.invalidate(&filter) as u64,
+            }
+        });
+
+        ErrorHandler::handle(result)
+    }
+    
+    /// ポリシー判定の説明（コマンドは実行しない）
+    async fn explain_policy(
+        &self,
+        request: Request<CommandRequest>,
+    ) -> Result<Response<PolicyExplanation>, Status> {
+        let req = request.into_inner();
+        info!("ポリシー説明リクエスト: command={}", req.command);
+
+        let result: McpResult<PolicyExplanation> = async {
+            // 実行時と同じく環境変数ポリシーとリソース制限を先に確認する（違反はエラーとして返す）
+            let mut policy_input = command_policy_input(&req);
+            self.policy_engine.apply_env_policy(&mut policy_input)?;
+            self.policy_engine.check_resource_limits(&policy_input)?;
+
+            let explanation = self.policy_engine.evaluate_with_explanation(&policy_input).await?;
+            Ok(PolicyExplanation {
+                allow: explanation.decision.allow,
+                reasons: explanation.decision.reasons,
+                warnings: explanation.decision.warnings,
+                evaluator: explanation.evaluator,
+                rules: explanation
+                    .rules
+                    .into_iter()
+                    .map(|rule| PolicyRuleMatch {
+                        rule: rule.rule,
+                        source: rule.source,
+                        effect: rule.effect.as_str().to_string(),
+                        message: rule.message,
+                    })
+                    .collect(),
+            })
+        }
+        .await;
+
+        ErrorHandler::handle(result)
+    }
+
+    /// ポリシーの事前評価（実行せずに判定結果を返す）
+    async fn evaluate_policy(
+        &self,
+        request: Request<EvaluatePolicyRequest>,
+    ) -> Result<Response<proto::PolicyDecision>, Status> {
+        let req = request.into_inner();
+
+        let result: McpResult<proto::PolicyDecision> = async {
+            // 対応するRPCと同じポリシー入力を組み立てる（削除は書き込みとして評価する）
+            let mut policy_input = match req.action {
+                Some(evaluate_policy_request::Action::Command(command)) => command_policy_input(&command),
+                Some(evaluate_policy_request::Action::ReadFile(file)) => file_policy_input(&file.path, "read"),
+                Some(evaluate_policy_request::Action::WriteFile(file)) => file_policy_input(&file.path, "write"),
+                Some(evaluate_policy_request::Action::DeleteFile(file)) => file_policy_input(&file.path, "write"),
+                None => {
+                    return Err(McpError::InvalidRequest("評価する操作が指定されていません".to_string()))
+                }
+            };
+            info!(
+                "ポリシー事前評価リクエスト: command={}, file={:?}",
+                policy_input.command.name,
+                policy_input.file.as_ref().map(|file| &file.path)
+            );
+
+            // 拒否はエラーではなく判定として返す
+            let decision = self.policy_engine.evaluate_preflight(&mut policy_input).await?;
+            Ok(proto::PolicyDecision {
+                allow: decision.allow,
+                reasons: decision.reasons,
+                warnings: decision.warnings,
+                metadata: decision
+                    .metadata
+                    .into_iter()
+                    .map(|(key, value)| (key, value.to_string()))
+                    .collect(),
+            })
+        }
+        .await;
+
+        ErrorHandler::handle(result)
+    }
     
+    /// ポリシーデータドキュメントの更新
+    async fn update_policy_data(
+        &self,
+        request: Request<UpdatePolicyDataRequest>,
+    ) -> Result<Response<UpdatePolicyDataResponse>, Status> {
+        let req = request.into_inner();
+        info!("ポリシーデータ更新リクエスト: path={}, tenant_id={:?}", req.path, req.tenant_id);
+
+        // 認証した場合は呼び出し元のテナントのポリシーだけを更新できる
+        let result: McpResult<UpdatePolicyDataResponse> = caller_tenant(req.tenant_id)
+            .and_then(|tenant_id| {
+                let document = serde_json::from_str(&req.document)
+                    .map_err(|e| McpError::InvalidRequest(format!("ポリシーデータがJSONではありません: {}", e)))?;
+                self.policy_engine.update_policy_data(tenant_id.as_deref(), &req.path, document)
+            })
+            .map(|()| UpdatePolicyDataResponse { path: req.path.clone() });
+
+        ErrorHandler::handle(result)
+    }
+
     /// ファイル読み取り
     async fn read_file(
         &self,
@@ -407,12 +2188,23 @@ impl McpService for McpServiceImpl {
         let req = request.into_inner();
         debug!("ファイル読み取りリクエスト: path={}", req.path);
         
-        let result: McpResult<ReadFileResponse> = {
-            // TODO: ここでポリシーチェックを行う
+        let result: McpResult<ReadFileResponse> = async {
+            // テナントのルート内で正規化したパスでポリシーチェック（ホスト上のパスは直接開かない）
+            let file = self.tenant_files.resolve(&request_user().tenant_id, &req.path)?;
+            let policy_input = file_policy_input(&file.path, "read");
+            self.policy_engine.check_file_access(&policy_input).await?;
 
-            // TODO: 実際のファイル読み取り実装
-            Err(McpError::Internal("ファイル読み取り機能は未実装です".to_string()))
-        };
+            // オフセットと長さを指定した部分読み取り（長さは設定の上限までに制限される）
+            let range = self.tenant_files.read(&file, req.offset, req.length)?;
+            Ok(ReadFileResponse {
+                path: file.path,
+                mime_type: tenant_files::mime_type(&range.content).to_string(),
+                content: range.content,
+                error: None,
+                file_size: range.file_size,
+            })
+        }
+        .await;
 
         ErrorHandler::handle(result)
     }
@@ -425,16 +2217,140 @@ impl McpService for McpServiceImpl {
         let req = request.into_inner();
         debug!("ファイル書き込みリクエスト: path={}", req.path);
         
-        let result: McpResult<WriteFileResponse> = {
-            // TODO: ここでポリシーチェックを行う
+        let result: McpResult<WriteFileResponse> = async {
+            // サイズとモードはスキャンの前に検証する
+            let file = self.tenant_files.resolve(&request_user().tenant_id, &req.path)?;
+            self.tenant_files.validate_write(req.content.len() as u64, req.mode)?;
 
-            // TODO: 実際のファイル書き込み実装
-            Err(McpError::Internal("ファイル書き込み機能は未実装です".to_string()))
-        };
+            // テナントのルート内で正規化したパスで、書き込む内容のスキャン結果を含めてポリシーチェック
+            let policy_input = file_policy_input(&file.path, "write");
+            self.policy_engine.check_file_write(&policy_input, &req.content).await?;
+
+            // 一時ファイルに書き込んでから置き換える（読み取り側に書きかけの内容を見せない）
+            let metadata = self.tenant_files.write(&file, &req.content, req.mode, req.create_dirs)?;
+            Ok(WriteFileResponse {
+                path: file.path,
+                bytes_written: req.content.len() as u64,
+                error: None,
+                metadata: Some(file_metadata(&metadata)),
+            })
+        }
+        .await;
 
         ErrorHandler::handle(result)
     }
     
+    type ReadFileStreamStream = ReceiverStream<Result<FileChunk, Status>>;
+
+    /// ファイルの分割読み取り
+    async fn read_file_stream(
+        &self,
+        request: Request<ReadFileStreamRequest>,
+    ) -> Result<Response<Self::ReadFileStreamStream>, Status> {
+        let req = request.into_inner();
+        debug!("ファイル分割読み取りリクエスト: path={}, chunk_size={}", req.path, req.chunk_size);
+
+        let result: McpResult<Self::ReadFileStreamStream> = async {
+            // テナントのルート内で正規化したパスでポリシーチェック
+            let file = self.tenant_files.resolve(&request_user().tenant_id, &req.path)?;
+            let policy_input = file_policy_input(&file.path, "read");
+            self.policy_engine.check_file_access(&policy_input).await?;
+
+            let (handle, metadata) = self.tenant_files.open(&file)?;
+            let file_size = metadata.len();
+            let end = req.length.map_or(file_size, |length| req.offset.saturating_add(length).min(file_size));
+            let chunk_size = self.tenant_files.chunk_size(req.chunk_size);
+
+            // 読み取る範囲が空でもファイルサイズを返すため、少なくとも1つのチャンクを送信する
+            let (tx, rx) = tokio::sync::mpsc::channel(4);
+            tokio::spawn(async move {
+                let mut offset = req.offset;
+                loop {
+                    let mut data = vec![0; chunk_size.min(end.saturating_sub(offset)) as usize];
+                    if let Err(e) = handle.read_exact_at(&mut data, offset) {
+                        let _ = tx.send(Err(ErrorHandler::catch(McpError::from(e)))).await;
+                        return;
+                    }
+
+                    let len = data.len() as u64;
+                    let chunk = FileChunk {
+                        offset,
+                        sha256: tenant_files::chunk_digest(&data),
+                        data,
+                        file_size,
+                    };
+                    if tx.send(Ok(chunk)).await.is_err() {
+                        return;
+                    }
+                    offset += len;
+                    if offset >= end {
+                        return;
+                    }
+                }
+            });
+
+            Ok(ReceiverStream::new(rx))
+        }
+        .await;
+
+        ErrorHandler::handle(result)
+    }
+
+    /// ファイルの分割書き込み
+    async fn write_file_stream(
+        &self,
+        request: Request<Streaming<WriteFileStreamRequest>>,
+    ) -> Result<Response<WriteFileResponse>, Status> {
+        let mut stream = request.into_inner();
+
+        let result: McpResult<WriteFileResponse> = async {
+            let receive_error = |status: Status| {
+                McpError::InvalidRequest(format!("ファイルの内容を受信できませんでした: {}", status.message()))
+            };
+
+            // パスなどの指定は最初のメッセージから取得する
+            let Some(first) = stream.message().await.map_err(receive_error)? else {
+                return Err(McpError::InvalidRequest("書き込むファイルが指定されていません".to_string()));
+            };
+            debug!("ファイル分割書き込みリクエスト: path={}", first.path);
+
+            // 内容を受信する前にパスをポリシーチェック（親ディレクトリの作成や大きな内容の受信を避ける）
+            let file = self.tenant_files.resolve(&request_user().tenant_id, &first.path)?;
+            let policy_input = file_policy_input(&file.path, "write");
+            self.policy_engine.check_file_access(&policy_input).await?;
+
+            // 各チャンクのチェックサムを検証しながら一時ファイルに書き込む
+            let mut upload = self.tenant_files.begin_upload(&file, first.mode, first.create_dirs)?;
+            let mut message = Some(first);
+            while let Some(WriteFileStreamRequest { chunk, .. }) = message {
+                if let Some(chunk) = chunk {
+                    if !chunk.sha256.eq_ignore_ascii_case(&tenant_files::chunk_digest(&chunk.data)) {
+                        return Err(McpError::InvalidRequest(format!(
+                            "チャンクのチェックサムが一致しません: offset={}",
+                            chunk.offset
+                        )));
+                    }
+                    upload.append(chunk.offset, &chunk.data)?;
+                }
+                message = stream.message().await.map_err(receive_error)?;
+            }
+
+            // 受信した内容の先頭をスキャンしてから置き換える
+            self.policy_engine.check_file_write(&policy_input, &upload.head()?).await?;
+            let bytes_written = upload.written();
+            let metadata = upload.commit()?;
+            Ok(WriteFileResponse {
+                path: file.path,
+                bytes_written,
+                error: None,
+                metadata: Some(file_metadata(&metadata)),
+            })
+        }
+        .await;
+
+        ErrorHandler::handle(result)
+    }
+
     /// ファイル削除
     async fn delete_file(
         &self,
@@ -452,4 +2368,12 @@ impl McpService for McpServiceImpl {
 
         ErrorHandler::handle(result)
     }
-} 
\ No newline at end of file
+}
+
+/// タスクが終了状態かどうか
+pub(crate) fn is_terminal_status(status: i32) -> bool {
+    status == proto::TaskStatus::TaskCompleted as i32
+        || status == proto::TaskStatus::TaskFailed as i32
+        || status == proto::TaskStatus::TaskCancelled as i32
+        || status == proto::TaskStatus::TaskTimedOut as i32
+}
@@ -1,14 +1,39 @@
 #[cfg(test)]
 mod tests {
     use crate::proto::{
-        CommandRequest, HealthRequest, TaskStatusRequest,
+        self, evaluate_policy_request, CommandRequest, DeleteFileRequest, EvaluatePolicyRequest, FileChunk,
+        HealthRequest, InvalidateResultCacheRequest, OutputChunkType, PlanRequest, PlanStep, ReadFileRequest,
+        ReadFileStreamRequest, ScriptRequest, TaskArtifactRequest, TaskStatus, TaskStatusRequest, TaskStatusResponse,
+        UpdatePolicyDataRequest, WriteFileRequest, WriteFileStreamRequest,
     };
     use crate::proto::mcp::mcp_service_server::McpService;
-    use crate::service::McpServiceImpl;
-    use mcp_policy::PolicyEngine;
-    use mcp_sandbox::CommandExecutor;
+    use crate::auth;
+    use crate::proto::McpServiceClient;
+    use crate::result_cache::{ResultCacheConfig, METADATA_RESULT_CACHE};
+    use crate::sandbox_policy::{METADATA_LIMIT_WARNINGS, METADATA_SANDBOX_DIRECTIVES};
+    use crate::service::{
+        McpServiceImpl, BREAK_GLASS_HEADER, METADATA_DRY_RUN, METADATA_SCRIPT_SHA256, METADATA_STRIPPED_ENV,
+        METADATA_TENANT_ID,
+    };
+    use crate::shutdown::DrainReport;
+    use crate::task_queue::TaskQueueConfig;
+    use crate::task_store::{SqlTaskStore, TaskStore, TaskStoreConfig};
+    use crate::tenant_files::{chunk_digest, TenantFilesConfig};
+    use crate::timeout::{TimeoutPolicy, METADATA_EFFECTIVE_TIMEOUT, METADATA_TIMEOUT_SOURCE};
+    use mcp_policy::models::ResourceLimits;
+    use mcp_policy::models::{PolicyDecision, PolicyInput, UserInfo};
+    use mcp_policy::break_glass::METADATA_BREAK_GLASS;
+    use mcp_policy::{
+        BreakGlass, EnvAction, EnvPolicy, PathPattern, PolicyEngine, PolicyEvaluator, ResourceLimitPolicy,
+        RuleBasedEvaluator, RuleConfig,
+    };
+    use mcp_common::McpResult;
+    use mcp_sandbox::output_capture::DEFAULT_INLINE_OUTPUT_BYTES;
+    use mcp_sandbox::{CommandExecutor, HostFingerprint, OutputLogConfig};
     use std::collections::HashMap;
+    use std::sync::Arc;
     use std::time::SystemTime;
+    use tokio_stream::StreamExt;
     use tonic::Request;
     use uuid::Uuid;
     use tracing::info;
@@ -65,6 +90,9 @@ mod tests {
             timeout,
             metadata: metadata.clone(),
             sandbox_config: None,
+            dry_run: false,
+            diff_workspace: false,
+            priority: 0,
         });
         
         // ポリシーエンジンがコマンドをブロックしている可能性があるので、ポリシーチェックをスキップする
@@ -98,4 +126,1543 @@ mod tests {
         let error = result.unwrap_err();
         assert_eq!(error.code(), tonic::Code::NotFound);
     }
-} 
\ No newline at end of file
+
+    // タイムアウトの上限適用のテスト
+    #[tokio::test]
+    async fn test_execute_command_clamps_timeout() {
+        let service = create_service().with_timeout_policy(TimeoutPolicy {
+            global_max_secs: 60,
+            ..Default::default()
+        });
+
+        let request = Request::new(CommandRequest {
+            command: "ls".to_string(),
+            args: vec![],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 3600,
+            metadata: HashMap::new(),
+            sandbox_config: None,
+            dry_run: false,
+            diff_workspace: false,
+            priority: 0,
+        });
+
+        let created = service.execute_command(request).await.unwrap().into_inner();
+        let status = service
+            .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let metadata = status.task_info.unwrap().metadata;
+        assert_eq!(metadata[METADATA_EFFECTIVE_TIMEOUT], "60");
+        assert_eq!(metadata[METADATA_TIMEOUT_SOURCE], "global");
+
+        // ツールの上限はラッパー経由で呼び出しても適用される
+        let service = create_service().with_timeout_policy(TimeoutPolicy {
+            tool_limits: HashMap::from([("ls".to_string(), 20)]),
+            ..Default::default()
+        });
+        let request = Request::new(CommandRequest {
+            command: "env".to_string(),
+            args: vec!["ls".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 3600,
+            metadata: HashMap::new(),
+            sandbox_config: None,
+            dry_run: false,
+            diff_workspace: false,
+            priority: 0,
+        });
+        let created = service.execute_command(request).await.unwrap().into_inner();
+        let status = service
+            .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        let metadata = status.task_info.unwrap().metadata;
+        assert_eq!(metadata[METADATA_EFFECTIVE_TIMEOUT], "20");
+        assert_eq!(metadata[METADATA_TIMEOUT_SOURCE], "tool");
+    }
+
+    // 出力ログのストリーミングと成果物一覧のテスト
+    #[tokio::test]
+    async fn test_stream_task_output_from_log() {
+        let log_dir = tempfile::tempdir().unwrap();
+        let service = create_service().with_output_log_config(OutputLogConfig {
+            base_dir: log_dir.path().to_path_buf(),
+            ..Default::default()
+        });
+
+        let request = Request::new(CommandRequest {
+            command: "echo".to_string(),
+            args: vec!["hello".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            metadata: HashMap::new(),
+            sandbox_config: None,
+            dry_run: false,
+            diff_workspace: false,
+            priority: 0,
+        });
+        let task_id = service.execute_command(request).await.unwrap().into_inner().task_id;
+
+        let mut stream = service
+            .stream_task_output(Request::new(TaskStatusRequest { task_id: task_id.clone() }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let mut stdout = Vec::new();
+        let mut exit_code = None;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.unwrap();
+            if chunk.r#type == OutputChunkType::ChunkStdout as i32 {
+                stdout.extend(chunk.data);
+            } else if chunk.r#type == OutputChunkType::ChunkExitCode as i32 {
+                exit_code = Some(String::from_utf8(chunk.data).unwrap());
+            }
+        }
+        assert_eq!(stdout, b"hello\n");
+        assert_eq!(exit_code.as_deref(), Some("0"));
+
+        let artifacts = service
+            .list_task_artifacts(Request::new(TaskStatusRequest { task_id }))
+            .await
+            .unwrap()
+            .into_inner()
+            .artifacts;
+        let stdout_segment = artifacts.iter().find(|a| a.kind == "stdout_segment").unwrap();
+        assert_eq!(stdout_segment.size_bytes, 6);
+        assert!(!stdout_segment.live);
+    }
+
+    // 結果キャッシュのヒットと無効化のテスト
+    #[tokio::test]
+    async fn test_execute_command_result_cache() {
+        let workspace = tempfile::tempdir().unwrap();
+        std::fs::write(workspace.path().join("file.txt"), "data").unwrap();
+        let service = create_service().with_result_cache_config(ResultCacheConfig {
+            enabled: true,
+            ..Default::default()
+        });
+
+        let request = || Request::new(CommandRequest {
+            command: "ls".to_string(),
+            args: vec![],
+            env: HashMap::new(),
+            cwd: Some(workspace.path().to_string_lossy().into_owned()),
+            timeout: 10,
+            metadata: HashMap::new(),
+            sandbox_config: None,
+            dry_run: false,
+            diff_workspace: false,
+            priority: 0,
+        });
+
+        // 1回目は実行され、完了後に結果がキャッシュされる
+        let first = service.execute_command(request()).await.unwrap().into_inner();
+        assert_eq!(first.status, TaskStatus::TaskQueued as i32);
+        for _ in 0..50 {
+            let status = service
+                .get_task_status(Request::new(TaskStatusRequest { task_id: first.task_id.clone() }))
+                .await
+                .unwrap()
+                .into_inner();
+            if status.task_info.unwrap().status == TaskStatus::TaskCompleted as i32 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+
+        // 2回目はキャッシュから即座に完了する
+        let second = service.execute_command(request()).await.unwrap().into_inner();
+        assert_eq!(second.status, TaskStatus::TaskCompleted as i32);
+        let status = service
+            .get_task_status(Request::new(TaskStatusRequest { task_id: second.task_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.task_info.unwrap().metadata[METADATA_RESULT_CACHE], "hit");
+        assert!(status.result.unwrap().stdout.contains("file.txt"));
+
+        let removed = service
+            .invalidate_result_cache(Request::new(InvalidateResultCacheRequest {
+                command: Some("ls".to_string()),
+                tenant_id: None,
+                path_prefix: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .removed;
+        assert_eq!(removed, 1);
+    }
+
+    // タスクストアに記録したタスクを別のサービスインスタンスから取得するテスト
+    #[tokio::test]
+    async fn test_task_store() {
+        let workspace = tempfile::tempdir().unwrap();
+        std::fs::write(workspace.path().join("file.txt"), "data").unwrap();
+        let database = tempfile::tempdir().unwrap();
+        let config = TaskStoreConfig {
+            url: Some(format!("sqlite://{}?mode=rwc", database.path().join("tasks.db").display())),
+            ..Default::default()
+        };
+        let task_store: Arc<dyn TaskStore> = Arc::new(SqlTaskStore::connect(&config).await.unwrap());
+        let service = create_service().with_task_store(task_store.clone());
+        // 再起動後や他のインスタンスを想定し、メモリにタスクを持たないサービス
+        let other = create_service().with_task_store(task_store);
+
+        let task_id = service
+            .execute_command(Request::new(CommandRequest {
+                command: "ls".to_string(),
+                args: vec![],
+                env: HashMap::new(),
+                cwd: Some(workspace.path().to_string_lossy().into_owned()),
+                timeout: 10,
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                dry_run: false,
+                diff_workspace: false,
+                priority: 0,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .task_id;
+
+        // 完了したタスクと結果がストアに書き込まれるまで待つ
+        let mut status = None;
+        for _ in 0..50 {
+            let response = other
+                .get_task_status(Request::new(TaskStatusRequest { task_id: task_id.clone() }))
+                .await;
+            if let Ok(response) = response {
+                let response = response.into_inner();
+                if response.task_info.as_ref().unwrap().status == TaskStatus::TaskCompleted as i32 {
+                    status = Some(response);
+                    break;
+                }
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        let status = status.expect("completed task was not found in the task store");
+        assert_eq!(status.task_info.unwrap().task_id, task_id);
+        let result = status.result.unwrap();
+        assert_eq!(result.exit_code, 0);
+        assert!(result.stdout.contains("file.txt"));
+        assert!(status.live_usage.is_none());
+
+        // ストアにもないタスクは見つからない
+        let missing = other
+            .get_task_status(Request::new(TaskStatusRequest { task_id: "task-missing".to_string() }))
+            .await
+            .unwrap_err();
+        assert_eq!(missing.code(), tonic::Code::NotFound);
+    }
+
+    // 実行環境フィンガープリントの付与のテスト
+    #[tokio::test]
+    async fn test_host_fingerprint() {
+        let service = create_service().with_host_fingerprint(HostFingerprint {
+            kernel_version: "6.1.0-test".to_string(),
+            ..Default::default()
+        });
+
+        let health = service.health(Request::new(HealthRequest {})).await.unwrap().into_inner();
+        assert_eq!(health.host["kernel_version"], "6.1.0-test");
+
+        let request = Request::new(CommandRequest {
+            command: "ls".to_string(),
+            args: vec![],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            metadata: HashMap::new(),
+            sandbox_config: None,
+            dry_run: false,
+            diff_workspace: false,
+            priority: 0,
+        });
+        let created = service.execute_command(request).await.unwrap().into_inner();
+        let status = service
+            .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.task_info.unwrap().metadata["host.kernel_version"], "6.1.0-test");
+    }
+
+    // 環境変数ポリシー（拒否と除去）のテスト
+    #[tokio::test]
+    async fn test_execute_command_env_policy() {
+        let env: HashMap<String, String> = [("GITHUB_TOKEN", "secret"), ("LANG", "C")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let request = || {
+            Request::new(CommandRequest {
+                command: "ls".to_string(),
+                args: vec![],
+                env: env.clone(),
+                cwd: None,
+                timeout: 10,
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                dry_run: false,
+                diff_workspace: false,
+                priority: 0,
+            })
+        };
+        let service_with = |action| {
+            let policy_engine =
+                PolicyEngine::new().with_env_policy(EnvPolicy::new(&["*_TOKEN"], action).unwrap());
+            McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now())
+        };
+
+        // 拒否: 該当する変数名がエラーに含まれる
+        let error = service_with(EnvAction::Deny).execute_command(request()).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::PermissionDenied);
+        assert!(error.message().contains("GITHUB_TOKEN"));
+
+        // 除去: 実行は継続し、除去した変数名をメタデータに記録
+        let service = service_with(EnvAction::Strip);
+        let created = service.execute_command(request()).await.unwrap().into_inner();
+        let status = service
+            .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.task_info.unwrap().metadata[METADATA_STRIPPED_ENV], "GITHUB_TOKEN");
+    }
+
+    // 要求リソース制限の上限チェックのテスト
+    #[tokio::test]
+    async fn test_execute_command_resource_limits() {
+        let policy_engine = PolicyEngine::new().with_resource_limit_policy(ResourceLimitPolicy::new(ResourceLimits {
+            memory_kb: Some(512 * 1024),
+            max_processes: Some(32),
+            ..Default::default()
+        }));
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now());
+
+        let request = |memory_limit: u64, pids_limit: u32| {
+            Request::new(CommandRequest {
+                command: "ls".to_string(),
+                args: vec![],
+                env: HashMap::new(),
+                cwd: None,
+                timeout: 10,
+                metadata: HashMap::new(),
+                sandbox_config: Some(proto::SandboxConfig {
+                    resource_limits: Some(proto::ResourceLimits {
+                        memory_limit,
+                        pids_limit,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                dry_run: false,
+                diff_workspace: false,
+                priority: 0,
+            })
+        };
+
+        assert!(service.execute_command(request(256 * 1024 * 1024, 16)).await.is_ok());
+
+        let error = service.execute_command(request(1024 * 1024 * 1024, 64)).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::PermissionDenied);
+        assert!(error.message().contains("memory_kb 1048576 exceeds the maximum 524288"));
+        assert!(error.message().contains("max_processes 64 exceeds the maximum 32"));
+    }
+
+    // ポリシー判定の説明のテスト
+    #[tokio::test]
+    async fn test_explain_policy() {
+        let service = create_service();
+        let request = |command: &str, args: &[&str]| {
+            Request::new(CommandRequest {
+                command: command.to_string(),
+                args: args.iter().map(|arg| arg.to_string()).collect(),
+                env: HashMap::new(),
+                cwd: None,
+                timeout: 10,
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                dry_run: false,
+                diff_workspace: false,
+                priority: 0,
+            })
+        };
+
+        // 拒否もエラーではなく説明として返す
+        let explanation = service.explain_policy(request("python", &["-c", "print(1)"])).await.unwrap().into_inner();
+        assert!(!explanation.allow);
+        assert_eq!(explanation.evaluator, "stub");
+        assert_eq!(explanation.rules.len(), 1);
+        assert_eq!(explanation.rules[0].rule, "commands.args.python.deny");
+        assert_eq!(explanation.rules[0].effect, "deny");
+        assert_eq!(explanation.rules[0].message, explanation.reasons[0]);
+
+        let explanation = service.explain_policy(request("ls", &["-la"])).await.unwrap().into_inner();
+        assert!(explanation.allow);
+        assert_eq!(explanation.rules[0].rule, "commands.allow");
+        assert_eq!(explanation.rules[0].effect, "allow");
+    }
+
+    // ポリシー事前評価のテスト
+    #[tokio::test]
+    async fn test_evaluate_policy() {
+        let service = create_service();
+        let evaluate = |action: evaluate_policy_request::Action| {
+            service.evaluate_policy(Request::new(EvaluatePolicyRequest { action: Some(action) }))
+        };
+        let command = |command: &str| {
+            evaluate_policy_request::Action::Command(CommandRequest {
+                command: command.to_string(),
+                args: vec![],
+                env: HashMap::new(),
+                cwd: None,
+                timeout: 10,
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                dry_run: false,
+                diff_workspace: false,
+                priority: 0,
+            })
+        };
+
+        let decision = evaluate(command("ls")).await.unwrap().into_inner();
+        assert!(decision.allow);
+
+        // 拒否もエラーではなく判定として返す
+        let decision = evaluate(command("rm")).await.unwrap().into_inner();
+        assert!(!decision.allow);
+        assert!(!decision.reasons.is_empty());
+
+        let read = |path: &str| evaluate_policy_request::Action::ReadFile(ReadFileRequest {
+            path: path.to_string(),
+            ..Default::default()
+        });
+        assert!(evaluate(read("/workspace/data.txt")).await.unwrap().into_inner().allow);
+        assert!(!evaluate(read("/etc/passwd")).await.unwrap().into_inner().allow);
+        let delete = evaluate_policy_request::Action::DeleteFile(DeleteFileRequest {
+            path: "/etc/passwd".to_string(),
+            recursive: false,
+        });
+        assert!(!evaluate(delete).await.unwrap().into_inner().allow);
+
+        // 操作の指定がなければ不正なリクエスト
+        let status = service
+            .evaluate_policy(Request::new(EvaluatePolicyRequest { action: None }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    // ファイル読み取りのテスト
+    #[tokio::test]
+    async fn test_read_file() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("tenant1/workspace")).unwrap();
+        std::fs::create_dir_all(root.path().join("tenant1/etc")).unwrap();
+        std::fs::write(root.path().join("tenant1/workspace/data.txt"), "hello world").unwrap();
+        std::fs::write(root.path().join("tenant1/etc/passwd"), "root:x:0:0").unwrap();
+        std::os::unix::fs::symlink("/etc/passwd", root.path().join("tenant1/workspace/passwd")).unwrap();
+        let service = create_service().with_tenant_files_config(TenantFilesConfig {
+            root: Some(root.path().to_path_buf()),
+            max_read_bytes: 1024,
+            ..Default::default()
+        });
+        let read = |path: &str, offset: u64, length: Option<u64>| {
+            service.read_file(Request::new(ReadFileRequest {
+                path: path.to_string(),
+                offset,
+                length,
+            }))
+        };
+
+        // 相対パスはワークスペースを基準に解決する
+        let response = read("data.txt", 0, None).await.unwrap().into_inner();
+        assert_eq!(response.path, "/workspace/data.txt");
+        assert_eq!(response.content, b"hello world");
+        assert_eq!(response.file_size, 11);
+        assert_eq!(response.mime_type, "text/plain; charset=utf-8");
+
+        let response = read("/workspace/data.txt", 6, Some(5)).await.unwrap().into_inner();
+        assert_eq!(response.content, b"world");
+        assert_eq!(response.file_size, 11);
+
+        // ワークスペース外へのパスやリンクは正規化した上でポリシーにより拒否される
+        let status = read("/workspace/../etc/passwd", 0, None).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        let status = read("/workspace/passwd", 0, None).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let status = read("/workspace/missing.txt", 0, None).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        // ルートが設定されていなければ読み取れない
+        let status = create_service()
+            .read_file(Request::new(ReadFileRequest {
+                path: "/workspace/data.txt".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    // ファイル書き込みのテスト
+    #[tokio::test]
+    async fn test_write_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("tenant1/workspace")).unwrap();
+        let service = create_service().with_tenant_files_config(TenantFilesConfig {
+            root: Some(root.path().to_path_buf()),
+            max_write_bytes: 16,
+            ..Default::default()
+        });
+        let write = |path: &str, content: &str, mode: u32, create_dirs: bool| {
+            service.write_file(Request::new(WriteFileRequest {
+                path: path.to_string(),
+                content: content.as_bytes().to_vec(),
+                create_dirs,
+                mode,
+            }))
+        };
+
+        let response = write("data.txt", "hello", 0, false).await.unwrap().into_inner();
+        assert_eq!(response.path, "/workspace/data.txt");
+        assert_eq!(response.bytes_written, 5);
+        let metadata = response.metadata.unwrap();
+        assert_eq!(metadata.size, 5);
+        assert_eq!(metadata.mode, 0o644);
+        assert!(!metadata.modified_at.is_empty());
+        let host_path = root.path().join("tenant1/workspace/data.txt");
+        assert_eq!(std::fs::read(&host_path).unwrap(), b"hello");
+
+        // 既存のファイルは置き換えられ、一時ファイルは残らない
+        let response = write("/workspace/data.txt", "replaced", 0o600, false).await.unwrap().into_inner();
+        assert_eq!(response.metadata.unwrap().mode, 0o600);
+        assert_eq!(std::fs::read(&host_path).unwrap(), b"replaced");
+        assert_eq!(std::fs::metadata(&host_path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(std::fs::read_dir(root.path().join("tenant1/workspace")).unwrap().count(), 1);
+
+        // 親ディレクトリはcreate_dirsの指定がある場合のみ作成する
+        let status = write("src/main.rs", "fn main() {}", 0, false).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        write("src/main.rs", "fn main() {}", 0, true).await.unwrap();
+        assert!(root.path().join("tenant1/workspace/src/main.rs").is_file());
+
+        // サイズ上限、特殊なモードビット、ポリシーで拒否されるパス
+        let status = write("large.txt", &"x".repeat(17), 0, false).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        let status = write("setuid", "x", 0o4755, false).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        let status = write("/workspace/../etc/passwd", "x", 0, true).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        assert!(!root.path().join("tenant1/etc").exists());
+    }
+
+    // ファイルの分割転送のテスト
+    #[tokio::test]
+    async fn test_file_stream() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("tenant1/workspace")).unwrap();
+        let service = create_service().with_tenant_files_config(TenantFilesConfig {
+            root: Some(root.path().to_path_buf()),
+            chunk_bytes: 4,
+            ..Default::default()
+        });
+
+        // クライアントストリーミングを使うため、gRPCサーバーを起動してクライアントから呼び出す
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        tokio::spawn(async move {
+            while tx.send(listener.accept().await.map(|(stream, _)| stream)).await.is_ok() {}
+        });
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(crate::create_server(service))
+                .serve_with_incoming(tokio_stream::wrappers::ReceiverStream::new(rx)),
+        );
+        let mut client = McpServiceClient::connect(format!("http://{}", addr)).await.unwrap();
+
+        let chunk = |offset: u64, data: &str| FileChunk {
+            offset,
+            data: data.as_bytes().to_vec(),
+            sha256: chunk_digest(data.as_bytes()),
+            file_size: 0,
+        };
+        let message = |path: &str, chunk: FileChunk| WriteFileStreamRequest {
+            path: path.to_string(),
+            create_dirs: true,
+            mode: 0o600,
+            chunk: Some(chunk),
+        };
+
+        // ファイルの指定は最初のメッセージのみで行う
+        let messages = vec![message("data/a.txt", chunk(0, "hello ")), message("", chunk(6, "world"))];
+        let response = client.write_file_stream(tokio_stream::iter(messages)).await.unwrap().into_inner();
+        assert_eq!(response.path, "/workspace/data/a.txt");
+        assert_eq!(response.bytes_written, 11);
+        assert_eq!(response.metadata.unwrap().mode, 0o600);
+        let host_path = root.path().join("tenant1/workspace/data/a.txt");
+        assert_eq!(std::fs::read(&host_path).unwrap(), b"hello world");
+
+        // チャンクサイズを指定しなければ設定値で分割される
+        let read = |offset: u64, length: Option<u64>, chunk_size: u32| ReadFileStreamRequest {
+            path: "/workspace/data/a.txt".to_string(),
+            offset,
+            length,
+            chunk_size,
+        };
+        let mut stream = client.read_file_stream(read(0, None, 0)).await.unwrap().into_inner();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.message().await.unwrap() {
+            assert_eq!(chunk.sha256, chunk_digest(&chunk.data));
+            assert_eq!(chunk.file_size, 11);
+            chunks.push((chunk.offset, String::from_utf8(chunk.data).unwrap()));
+        }
+        assert_eq!(chunks, [(0, "hell".to_string()), (4, "o wo".to_string()), (8, "rld".to_string())]);
+
+        let mut stream = client.read_file_stream(read(6, Some(3), 1024)).await.unwrap().into_inner();
+        assert_eq!(stream.message().await.unwrap().unwrap().data, b"wor");
+        assert!(stream.message().await.unwrap().is_none());
+
+        // チェックサムの不一致や連続しないチャンクは拒否され、ファイルは置き換えられない
+        let mut corrupted = chunk(0, "bad");
+        corrupted.sha256 = chunk_digest(b"good");
+        let status = client
+            .write_file_stream(tokio_stream::iter(vec![message("data/a.txt", corrupted)]))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        let messages = vec![message("data/a.txt", chunk(0, "new")), message("", chunk(4, "gap"))];
+        let status = client.write_file_stream(tokio_stream::iter(messages)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(std::fs::read(&host_path).unwrap(), b"hello world");
+        assert_eq!(std::fs::read_dir(root.path().join("tenant1/workspace/data")).unwrap().count(), 1);
+
+        let messages = vec![message("/workspace/../etc/passwd", chunk(0, "x"))];
+        let status = client.write_file_stream(tokio_stream::iter(messages)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        assert!(!root.path().join("tenant1/etc").exists());
+    }
+
+    // ポリシーデータ更新のテスト
+    #[tokio::test]
+    async fn test_update_policy_data() {
+        let policy_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            policy_dir.path().join("policy.rego"),
+            "package mcp\nimport future.keywords.if\nimport future.keywords.in\ndefault allow = false\nallow if { input.command.name in data.team.commands }\n",
+        )
+        .unwrap();
+        std::fs::write(policy_dir.path().join("data.json"), r#"{"team": {"commands": ["ls"]}}"#).unwrap();
+        let policy_engine = PolicyEngine::from_policy_dir(policy_dir.path()).unwrap();
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now());
+
+        let allowed = |command: &str| {
+            let request = EvaluatePolicyRequest {
+                action: Some(evaluate_policy_request::Action::Command(CommandRequest {
+                    command: command.to_string(),
+                    args: vec![],
+                    env: HashMap::new(),
+                    cwd: None,
+                    timeout: 10,
+                    metadata: HashMap::new(),
+                    sandbox_config: None,
+                    dry_run: false,
+                    diff_workspace: false,
+                    priority: 0,
+                })),
+            };
+            let service = &service;
+            async move { service.evaluate_policy(Request::new(request)).await.unwrap().into_inner().allow }
+        };
+        let update = |path: &str, document: &str| {
+            service.update_policy_data(Request::new(UpdatePolicyDataRequest {
+                path: path.to_string(),
+                document: document.to_string(),
+                tenant_id: None,
+            }))
+        };
+
+        assert!(!allowed("make").await);
+        update("team.commands", r#"["ls", "make"]"#).await.unwrap();
+        assert!(allowed("make").await);
+
+        // 不正なJSONや存在しないテナントはエラー
+        assert_eq!(update("team", "{").await.unwrap_err().code(), tonic::Code::InvalidArgument);
+        let status = service
+            .update_policy_data(Request::new(UpdatePolicyDataRequest {
+                path: "team".to_string(),
+                document: "{}".to_string(),
+                tenant_id: Some("unknown".to_string()),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        // データドキュメントを持たない評価器では更新できない
+        let status = create_service()
+            .update_policy_data(Request::new(UpdatePolicyDataRequest {
+                path: "team".to_string(),
+                document: "{}".to_string(),
+                tenant_id: None,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    // 判定メタデータにサンドボックス指定を返す評価器
+    struct SandboxDirectiveEvaluator(serde_json::Value);
+
+    impl PolicyEvaluator for SandboxDirectiveEvaluator {
+        fn evaluate(&self, _input: &PolicyInput) -> McpResult<PolicyDecision> {
+            Ok(PolicyDecision {
+                allow: true,
+                warnings: vec![],
+                reasons: vec![],
+                deny_reasons: vec![],
+                metadata: serde_json::from_value(self.0.clone()).unwrap(),
+            })
+        }
+    }
+
+    // ポリシーのサンドボックス指定のテスト
+    #[tokio::test]
+    async fn test_execute_command_sandbox_directives() {
+        let service_with = |metadata: serde_json::Value| {
+            let policy_engine = PolicyEngine::with_evaluator(SandboxDirectiveEvaluator(metadata));
+            McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now())
+        };
+        let request = || {
+            Request::new(CommandRequest {
+                command: "echo".to_string(),
+                args: vec!["hello".to_string()],
+                env: HashMap::new(),
+                cwd: None,
+                timeout: 10,
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                dry_run: false,
+                diff_workspace: false,
+                priority: 0,
+            })
+        };
+
+        // 適用した指定をタスクメタデータに記録
+        let service = service_with(serde_json::json!({ "network_access": "none", "memory_limit": "256M" }));
+        let created = service.execute_command(request()).await.unwrap().into_inner();
+        let status = service
+            .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.task_info.unwrap().metadata[METADATA_SANDBOX_DIRECTIVES], "network_access,memory_limit");
+
+        // 不正な指定の場合は実行しない
+        let service = service_with(serde_json::json!({ "network_access": "internet" }));
+        let error = service.execute_command(request()).await.unwrap_err();
+        assert!(error.message().contains("network_access"));
+    }
+
+    // タスクキャンセルで実行中のプロセスを終了させるテスト
+    #[tokio::test]
+    async fn test_cancel_task() {
+        let policy_engine = PolicyEngine::with_evaluator(SandboxDirectiveEvaluator(serde_json::json!({})));
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now());
+        let created = service
+            .execute_command(Request::new(CommandRequest {
+                command: "sleep".to_string(),
+                args: vec!["30".to_string()],
+                env: HashMap::new(),
+                cwd: None,
+                timeout: 60,
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                dry_run: false,
+                diff_workspace: false,
+                priority: 0,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let cancel = || Request::new(TaskStatusRequest { task_id: created.task_id.clone() });
+
+        // プロセスの終了を待ってからキャンセル状態を返す
+        let start = std::time::Instant::now();
+        let status = service.cancel_task(cancel()).await.unwrap().into_inner();
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+        let task_info = status.task_info.unwrap();
+        assert_eq!(task_info.status, proto::TaskStatus::TaskCancelled as i32);
+        assert!(task_info.completed_at.is_some());
+        assert!(status.result.unwrap().stderr.contains("cancelled"));
+
+        // 終了済みのタスクはそのまま
+        let status = service.cancel_task(cancel()).await.unwrap().into_inner();
+        assert_eq!(status.task_info.unwrap().status, proto::TaskStatus::TaskCancelled as i32);
+
+        let error = service
+            .cancel_task(Request::new(TaskStatusRequest { task_id: Uuid::new_v4().to_string() }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::NotFound);
+    }
+
+    // 他のテナントのタスクとテナント指定の操作を拒否するテスト
+    #[tokio::test]
+    async fn test_task_tenant_isolation() {
+        let policy_engine = PolicyEngine::with_evaluator(SandboxDirectiveEvaluator(serde_json::json!({})));
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now());
+        let user = |tenant_id: &str| {
+            Some(UserInfo {
+                id: "alice".to_string(),
+                tenant_id: tenant_id.to_string(),
+                roles: vec!["user".to_string()],
+                attributes: HashMap::new(),
+            })
+        };
+        let created = auth::scope(
+            user("tenant-a"),
+            service.execute_command(Request::new(CommandRequest {
+                command: "sleep".to_string(),
+                args: vec!["30".to_string()],
+                env: HashMap::new(),
+                cwd: None,
+                timeout: 60,
+                metadata: HashMap::from([(METADATA_TENANT_ID.to_string(), "tenant-b".to_string())]),
+                sandbox_config: None,
+                dry_run: false,
+                diff_workspace: false,
+                priority: 0,
+            })),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        let status = || Request::new(TaskStatusRequest { task_id: created.task_id.clone() });
+
+        // メタデータのテナントは呼び出し元のテナントで上書きされる
+        let response = auth::scope(user("tenant-a"), service.get_task_status(status())).await.unwrap().into_inner();
+        assert_eq!(response.task_info.unwrap().metadata[METADATA_TENANT_ID], "tenant-a");
+
+        // 他のテナントには存在しないタスクとして扱う
+        let error = auth::scope(user("tenant-b"), service.get_task_status(status())).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::NotFound);
+        let error = auth::scope(user("tenant-b"), service.cancel_task(status())).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::NotFound);
+
+        // 認証したユーザーは他のテナントのキャッシュを無効化できない
+        let invalidate = |tenant_id: &str| {
+            Request::new(InvalidateResultCacheRequest { tenant_id: Some(tenant_id.to_string()), ..Default::default() })
+        };
+        let error = auth::scope(user("tenant-a"), service.invalidate_result_cache(invalidate("tenant-b")))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::PermissionDenied);
+        auth::scope(user("tenant-a"), service.invalidate_result_cache(invalidate("tenant-a"))).await.unwrap();
+
+        let cancelled = auth::scope(user("tenant-a"), service.cancel_task(status())).await.unwrap().into_inner();
+        assert_eq!(cancelled.task_info.unwrap().status, TaskStatus::TaskCancelled as i32);
+    }
+
+    // タスクキューの上限と待機中のタスクのキャンセルのテスト
+    #[tokio::test]
+    async fn test_task_queue() {
+        let policy_engine = PolicyEngine::with_evaluator(SandboxDirectiveEvaluator(serde_json::json!({})));
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now())
+            .with_task_queue_config(TaskQueueConfig { workers: 1, max_depth: 1 });
+        let request = || Request::new(CommandRequest {
+            command: "sleep".to_string(),
+            args: vec!["30".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 60,
+            metadata: HashMap::new(),
+            sandbox_config: None,
+            dry_run: false,
+            diff_workspace: false,
+            priority: 0,
+        });
+        let status = |task_id: &str| Request::new(TaskStatusRequest { task_id: task_id.to_string() });
+
+        // 1つ目のタスクがワーカーで実行されるまで待つ
+        let running = service.execute_command(request()).await.unwrap().into_inner();
+        assert_eq!(running.status, TaskStatus::TaskQueued as i32);
+        for _ in 0..50 {
+            let response = service.get_task_status(status(&running.task_id)).await.unwrap().into_inner();
+            if response.task_info.unwrap().status == TaskStatus::TaskRunning as i32 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        // 2つ目のタスクはワーカーが空くまで待ち、3つ目はキューが満杯のため拒否される
+        let queued = service.execute_command(request()).await.unwrap().into_inner();
+        assert_eq!(queued.status, TaskStatus::TaskQueued as i32);
+        let error = service.execute_command(request()).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::ResourceExhausted);
+
+        // 待機中のタスクは実行されずにキャンセルされる
+        let cancelled = service.cancel_task(status(&queued.task_id)).await.unwrap().into_inner();
+        assert_eq!(cancelled.task_info.unwrap().status, TaskStatus::TaskCancelled as i32);
+        assert!(cancelled.result.unwrap().stderr.contains("before it started"));
+
+        // キャンセルで空いた場所に次のタスクが入る
+        let next = service.execute_command(request()).await.unwrap().into_inner();
+        assert_eq!(next.status, TaskStatus::TaskQueued as i32);
+
+        for task_id in [&next.task_id, &running.task_id] {
+            let cancelled = service.cancel_task(status(task_id)).await.unwrap().into_inner();
+            assert_eq!(cancelled.task_info.unwrap().status, TaskStatus::TaskCancelled as i32);
+        }
+    }
+
+    // シャットダウン時のタスクの引き上げのテスト
+    #[tokio::test]
+    async fn test_drain() {
+        let policy_engine = PolicyEngine::with_evaluator(SandboxDirectiveEvaluator(serde_json::json!({})));
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now())
+            .with_task_queue_config(TaskQueueConfig { workers: 2, max_depth: 4 });
+        let request = |seconds: &str| Request::new(CommandRequest {
+            command: "sleep".to_string(),
+            args: vec![seconds.to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 60,
+            metadata: HashMap::new(),
+            sandbox_config: None,
+            dry_run: false,
+            diff_workspace: false,
+            priority: 0,
+        });
+        let status = |task_id: &str| Request::new(TaskStatusRequest { task_id: task_id.to_string() });
+
+        // 2つのワーカーで短いタスクと長いタスクを実行し、3つ目は待ち行列に残す
+        let short = service.execute_command(request("0.2")).await.unwrap().into_inner();
+        let long = service.execute_command(request("30")).await.unwrap().into_inner();
+        for _ in 0..50 {
+            let response = service.get_task_status(status(&long.task_id)).await.unwrap().into_inner();
+            if response.task_info.unwrap().status == TaskStatus::TaskRunning as i32 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let queued = service.execute_command(request("30")).await.unwrap().into_inner();
+
+        // 短いタスクは期限内に完了し、長いタスクは期限を過ぎてキャンセルされる
+        let report = service.drain(std::time::Duration::from_secs(1)).await;
+        assert_eq!(report, DrainReport { finished: 1, unstarted: 1, interrupted: 1 });
+
+        let task_status = |task_id: String| {
+            let service = &service;
+            async move { service.get_task_status(status(&task_id)).await.unwrap().into_inner() }
+        };
+        let short = task_status(short.task_id).await;
+        assert_eq!(short.task_info.unwrap().status, TaskStatus::TaskCompleted as i32);
+        let long = task_status(long.task_id).await;
+        assert_eq!(long.task_info.unwrap().status, TaskStatus::TaskCancelled as i32);
+        let queued = task_status(queued.task_id).await;
+        assert_eq!(queued.task_info.unwrap().status, TaskStatus::TaskCancelled as i32);
+        assert!(queued.result.unwrap().stderr.contains("shutting down"));
+
+        // 新しいタスクは受け付けない
+        let error = service.execute_command(request("0")).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::Unavailable);
+    }
+
+    // タスクの一時停止と再開のテスト
+    #[tokio::test]
+    async fn test_pause_and_resume_task() {
+        let policy_engine = PolicyEngine::with_evaluator(SandboxDirectiveEvaluator(serde_json::json!({})));
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now());
+        let created = service
+            .execute_command(Request::new(CommandRequest {
+                command: "sleep".to_string(),
+                args: vec!["30".to_string()],
+                env: HashMap::new(),
+                cwd: None,
+                timeout: 60,
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                dry_run: false,
+                diff_workspace: false,
+                priority: 0,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let request = || Request::new(TaskStatusRequest { task_id: created.task_id.clone() });
+        let status_of = |response: TaskStatusResponse| response.task_info.unwrap().status;
+
+        // 一時停止中ではないタスクは再開できない
+        let error = service.resume_task(request()).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+
+        // コマンドが起動するまでは一時停止できない
+        let start = std::time::Instant::now();
+        let paused = loop {
+            match service.pause_task(request()).await {
+                Ok(response) => break response.into_inner(),
+                Err(error) if error.code() == tonic::Code::InvalidArgument => {
+                    assert!(start.elapsed() < std::time::Duration::from_secs(10), "{}", error.message());
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                }
+                Err(error) => panic!("unexpected error: {}", error),
+            }
+        };
+        assert_eq!(status_of(paused), proto::TaskStatus::TaskPaused as i32);
+        let error = service.pause_task(request()).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+
+        let resumed = service.resume_task(request()).await.unwrap().into_inner();
+        assert_eq!(status_of(resumed), proto::TaskStatus::TaskRunning as i32);
+
+        // 一時停止中のタスクもキャンセルできる
+        service.pause_task(request()).await.unwrap();
+        let cancelled = service.cancel_task(request()).await.unwrap().into_inner();
+        assert_eq!(status_of(cancelled), proto::TaskStatus::TaskCancelled as i32);
+
+        let error = service
+            .pause_task(Request::new(TaskStatusRequest { task_id: Uuid::new_v4().to_string() }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::NotFound);
+    }
+
+    // ポリシーのコマンド制限による要求値の丸めのテスト
+    #[tokio::test]
+    async fn test_execute_command_command_limits() {
+        let policy_engine = PolicyEngine::with_evaluator(SandboxDirectiveEvaluator(serde_json::json!({
+            "command_limits": { "max_timeout_secs": 600, "max_cpu_cores": 2.0, "max_memory": "2G" }
+        })));
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now());
+        let request = Request::new(CommandRequest {
+            command: "echo".to_string(),
+            args: vec!["install".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 3600,
+            metadata: HashMap::new(),
+            sandbox_config: Some(proto::SandboxConfig {
+                resource_limits: Some(proto::ResourceLimits {
+                    cpu_limit: 4.0,
+                    memory_limit: 1024 * 1024 * 1024,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            dry_run: false,
+            diff_workspace: false,
+            priority: 0,
+        });
+
+        // 上限を超える要求は拒否せず丸めて警告を記録する
+        let created = service.execute_command(request).await.unwrap().into_inner();
+        let status = service
+            .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        let metadata = status.task_info.unwrap().metadata;
+        assert_eq!(metadata[METADATA_EFFECTIVE_TIMEOUT], "600");
+        assert_eq!(metadata[METADATA_TIMEOUT_SOURCE], "policy");
+        assert_eq!(
+            metadata[METADATA_LIMIT_WARNINGS],
+            "timeout 3600s exceeds the policy maximum 600s and was clamped; \
+             cpu_limit 4 exceeds the policy maximum 2 and was clamped"
+        );
+    }
+
+    // リクエストごとのサンドボックス設定のテスト
+    #[tokio::test]
+    async fn test_execute_command_sandbox_config() {
+        let service = create_service();
+        let request = |rw_path: &str, network_access: i32| {
+            Request::new(CommandRequest {
+                command: "ls".to_string(),
+                args: vec![],
+                env: HashMap::new(),
+                cwd: None,
+                timeout: 10,
+                metadata: HashMap::new(),
+                sandbox_config: Some(proto::SandboxConfig {
+                    network_access,
+                    rw_paths: vec![rw_path.to_string()],
+                    ro_paths: vec!["/tmp/sdk".to_string()],
+                    resource_limits: Some(proto::ResourceLimits {
+                        pids_limit: 16,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                dry_run: false,
+                diff_workspace: false,
+                priority: 0,
+            })
+        };
+
+        // ポリシーの上限を超えるネットワークアクセスは拒否せず丸めて警告を記録する
+        let created = service
+            .execute_command(request("/workspace/cache", proto::NetworkAccess::NetworkHost as i32))
+            .await
+            .unwrap()
+            .into_inner();
+        let status = service
+            .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            status.task_info.unwrap().metadata[METADATA_LIMIT_WARNINGS],
+            "network_access host exceeds the policy maximum none and was clamped"
+        );
+
+        // ファイルアクセスポリシーで拒否されるパスやサンドボックスの拒否パスはマウントしない
+        for rw_path in ["/data/public/upload", "/etc/cron.d"] {
+            let error = service.execute_command(request(rw_path, 0)).await.unwrap_err();
+            assert_eq!(error.code(), tonic::Code::PermissionDenied, "{}: {}", rw_path, error.message());
+        }
+        let error = service.execute_command(request("/workspace/cache", 7)).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+    }
+
+    // ブレークグラストークンによる拒否の上書きのテスト
+    #[tokio::test]
+    async fn test_execute_command_break_glass() {
+        use jsonwebtoken::{Algorithm, EncodingKey, Header};
+
+        let key_pair = rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256).unwrap();
+        let break_glass = BreakGlass::new(Algorithm::ES256, key_pair.public_key_pem().as_bytes()).unwrap();
+        let policy_engine = PolicyEngine::new().with_break_glass(break_glass);
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now());
+
+        let now = mcp_common::utils::current_timestamp_ms() / 1000;
+        let token = |sub: &str| {
+            jsonwebtoken::encode(
+                &Header::new(Algorithm::ES256),
+                &serde_json::json!({ "sub": sub, "reason": "INC-42", "iat": now, "exp": now + 300 }),
+                &EncodingKey::from_ec_pem(key_pair.serialize_pem().as_bytes()).unwrap(),
+            )
+            .unwrap()
+        };
+        let request = |token: Option<String>| {
+            let mut request = Request::new(CommandRequest {
+                command: "rm".to_string(),
+                args: vec!["--version".to_string()],
+                env: HashMap::new(),
+                cwd: None,
+                timeout: 10,
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                dry_run: false,
+                diff_workspace: false,
+                priority: 0,
+            });
+            if let Some(token) = token {
+                request.metadata_mut().insert(BREAK_GLASS_HEADER, token.parse().unwrap());
+            }
+            request
+        };
+
+        // トークンがない場合と他のユーザーのトークンは拒否
+        assert!(service.execute_command(request(None)).await.is_err());
+        assert!(service.execute_command(request(Some(token("user2")))).await.is_err());
+
+        // 有効なトークンで拒否を上書きし、理由をタスクに記録
+        let created = service.execute_command(request(Some(token("user1")))).await.unwrap().into_inner();
+        let status = service
+            .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.task_info.unwrap().metadata[METADATA_BREAK_GLASS], "INC-42");
+    }
+
+    // 依存関係のあるコマンドを1つのタスクとして実行するプランのテスト
+    #[tokio::test]
+    async fn test_execute_plan() {
+        let policy_engine = PolicyEngine::with_evaluator(SandboxDirectiveEvaluator(serde_json::json!({})))
+            .with_env_policy(EnvPolicy::new(&["*_TOKEN"], EnvAction::Deny).unwrap());
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now());
+        let step = |id: &str, script: &str, depends_on: &[&str]| PlanStep {
+            id: id.to_string(),
+            command: Some(CommandRequest {
+                command: "sh".to_string(),
+                args: vec!["-c".to_string(), script.to_string()],
+                env: HashMap::new(),
+                cwd: None,
+                timeout: 10,
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                dry_run: false,
+                diff_workspace: false,
+                priority: 0,
+            }),
+            depends_on: depends_on.iter().map(|id| id.to_string()).collect(),
+        };
+        let request = |steps: Vec<PlanStep>| {
+            Request::new(PlanRequest {
+                steps,
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                priority: 0,
+            })
+        };
+
+        // 失敗したステップに依存するステップは実行せず、終了コードとステップごとの結果で報告する
+        let created = service
+            .execute_plan(request(vec![
+                step("test", "echo testing; exit 2", &["build"]),
+                step("build", "echo built", &[]),
+                step("publish", "echo published", &["test"]),
+            ]))
+            .await
+            .unwrap()
+            .into_inner();
+        let status = loop {
+            let status = service
+                .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id.clone() }))
+                .await
+                .unwrap()
+                .into_inner();
+            if status.task_info.as_ref().unwrap().status == TaskStatus::TaskCompleted as i32 {
+                break status;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        };
+        assert_eq!(status.task_info.unwrap().task_type, proto::TaskType::TaskPlan as i32);
+        let result = status.result.unwrap();
+        assert_eq!((result.exit_code, result.stdout.as_str()), (2, "built\ntesting\n"));
+        let steps: Vec<(&str, i32)> = result.steps.iter().map(|step| (step.step_id.as_str(), step.status)).collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("build", proto::StepStatus::StepSucceeded as i32),
+                ("test", proto::StepStatus::StepFailed as i32),
+                ("publish", proto::StepStatus::StepSkipped as i32),
+            ]
+        );
+        assert_eq!(result.steps[0].result.as_ref().unwrap().stdout, "built\n");
+
+        // 循環した依存関係は実行前に拒否する
+        let error = service
+            .execute_plan(request(vec![step("build", "true", &["test"]), step("test", "true", &["build"])]))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+
+        // ポリシーで拒否されたステップがあればプラン全体を実行しない
+        let mut denied = step("publish", "true", &["build"]);
+        denied.command.as_mut().unwrap().env.insert("GITHUB_TOKEN".to_string(), "secret".to_string());
+        let error = service.execute_plan(request(vec![step("build", "true", &[]), denied])).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::PermissionDenied);
+        assert!(error.message().contains("step 'publish'"));
+    }
+
+    // インラインに収まらない出力を出力ログの成果物で参照するテスト
+    #[tokio::test]
+    async fn test_execute_command_output_overflow() {
+        let log_dir = tempfile::tempdir().unwrap();
+        let policy_engine = PolicyEngine::with_evaluator(SandboxDirectiveEvaluator(serde_json::json!({})));
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now())
+            .with_output_log_config(OutputLogConfig {
+                base_dir: log_dir.path().to_path_buf(),
+                ..Default::default()
+            });
+
+        let request = Request::new(CommandRequest {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "head -c 2000000 /dev/zero | tr '\\0' x".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            metadata: HashMap::new(),
+            sandbox_config: None,
+            dry_run: false,
+            diff_workspace: false,
+            priority: 0,
+        });
+        let task_id = service.execute_command(request).await.unwrap().into_inner().task_id;
+        let status = loop {
+            let status = service
+                .get_task_status(Request::new(TaskStatusRequest { task_id: task_id.clone() }))
+                .await
+                .unwrap()
+                .into_inner();
+            if status.task_info.as_ref().unwrap().status == TaskStatus::TaskCompleted as i32 {
+                break status;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        };
+
+        // 結果には出力の先頭だけを含め、完全な出力は成果物から取得する
+        let result = status.result.unwrap();
+        assert_eq!(result.stdout.len() as u64, DEFAULT_INLINE_OUTPUT_BYTES);
+        let overflow = result.stdout_overflow.unwrap();
+        assert_eq!(overflow.size_bytes, 2_000_000);
+        assert_eq!(overflow.artifacts, vec!["stdout-000001.log"]);
+        assert!(result.stderr_overflow.is_none());
+
+        let mut download = service
+            .download_task_artifact(Request::new(TaskArtifactRequest {
+                task_id,
+                name: overflow.artifacts[0].clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut size = 0;
+        while let Some(chunk) = download.next().await {
+            size += chunk.unwrap().data.len();
+        }
+        assert_eq!(size, 2_000_000);
+    }
+
+    // ワークスペースの変更の報告のテスト
+    #[tokio::test]
+    async fn test_execute_command_workspace_diff() {
+        let workspace = tempfile::tempdir().unwrap();
+        std::fs::write(workspace.path().join("input.txt"), "input").unwrap();
+        let policy_engine = PolicyEngine::with_evaluator(SandboxDirectiveEvaluator(serde_json::json!({
+            "rw_paths": [workspace.path()],
+        })));
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now());
+        let command = CommandRequest {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "echo diff > out.txt && rm input.txt".to_string()],
+            env: HashMap::new(),
+            cwd: Some(workspace.path().display().to_string()),
+            timeout: 10,
+            metadata: HashMap::new(),
+            sandbox_config: None,
+            dry_run: false,
+            diff_workspace: true,
+            priority: 0,
+        };
+
+        // 追加・削除されたファイルをサイズとハッシュ付きで結果に含める
+        let created = service.execute_command(Request::new(command.clone())).await.unwrap().into_inner();
+        let status = loop {
+            let status = service
+                .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id.clone() }))
+                .await
+                .unwrap()
+                .into_inner();
+            if status.task_info.as_ref().unwrap().status == TaskStatus::TaskCompleted as i32 {
+                break status;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        };
+        let changes = status.result.unwrap().workspace_diff.unwrap().changes;
+        let summary: Vec<_> = changes.iter().map(|change| (change.path.clone(), change.kind.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (workspace.path().join("input.txt").display().to_string(), "deleted"),
+                (workspace.path().join("out.txt").display().to_string(), "added"),
+            ]
+        );
+        assert_eq!((changes[0].size, changes[0].previous_size), (None, Some(5)));
+        assert_eq!(changes[1].size, Some(5));
+        assert_eq!(changes[1].sha256.as_ref().map(String::len), Some(64));
+
+        // プランのステップでは報告できない
+        let error = service
+            .execute_plan(Request::new(PlanRequest {
+                steps: vec![PlanStep {
+                    id: "build".to_string(),
+                    command: Some(command),
+                    depends_on: Vec::new(),
+                }],
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                priority: 0,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+    }
+
+    // スクリプト実行のテスト
+    #[tokio::test]
+    async fn test_execute_script() {
+        let mut config = RuleConfig::default();
+        config.commands.allow.extend(["sh", "echo"].map(str::to_string));
+        let policy_engine = PolicyEngine::with_evaluator(RuleBasedEvaluator::new(config));
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now());
+        let request = |interpreter: &str, script: String| {
+            Request::new(ScriptRequest {
+                interpreter: interpreter.to_string(),
+                interpreter_args: Vec::new(),
+                script,
+                args: vec!["release".to_string()],
+                env: HashMap::new(),
+                cwd: None,
+                timeout: 10,
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                priority: 0,
+            })
+        };
+
+        // 許可されたスクリプトはタスクを作成し、スクリプトのハッシュを記録する
+        let created = service
+            .execute_script(request("sh", "echo building $1\n".to_string()))
+            .await
+            .unwrap()
+            .into_inner();
+        let status = service
+            .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        let metadata = status.task_info.unwrap().metadata;
+        assert_eq!(metadata.get(METADATA_SCRIPT_SHA256).map(String::len), Some(64));
+
+        // スクリプト内のコマンドもポリシーで評価する
+        let error = service
+            .execute_script(request("sh", "echo cleaning\nrm -rf /\n".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::PermissionDenied);
+        // インタプリタ自体もポリシーで評価する
+        let error = service.execute_script(request("perl", "print 1;\n".to_string())).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::PermissionDenied);
+
+        // 空のスクリプトと上限を超えるスクリプトは拒否する
+        for (interpreter, script) in [("sh", String::new()), ("", "echo\n".to_string()), ("sh", "#".repeat(2 << 20))] {
+            let error = service.execute_script(request(interpreter, script)).await.unwrap_err();
+            assert_eq!(error.code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    // ドライランのテスト
+    #[tokio::test]
+    async fn test_dry_run() {
+        let mut config = RuleConfig::default();
+        config.commands.allow.push("echo".to_string());
+        // プリセットのマウントもファイルアクセスポリシーで確認する
+        config.files.read.push(PathPattern::parse("/usr/").unwrap());
+        let policy_engine = PolicyEngine::with_evaluator(RuleBasedEvaluator::new(config));
+        let service = McpServiceImpl::new(policy_engine, CommandExecutor::new(), SystemTime::now());
+        let request = |command: &str| CommandRequest {
+            command: command.to_string(),
+            args: vec!["hello".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            metadata: HashMap::new(),
+            sandbox_config: None,
+            dry_run: true,
+            diff_workspace: false,
+            priority: 0,
+        };
+
+        // コマンドは実行せず、完了済みのタスクの結果として起動方法を返す
+        let created = service.execute_command(Request::new(request("echo"))).await.unwrap().into_inner();
+        assert_eq!(created.status, TaskStatus::TaskCompleted as i32);
+        let status = service
+            .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.task_info.unwrap().metadata.get(METADATA_DRY_RUN).map(String::as_str), Some("true"));
+        let result = status.result.unwrap();
+        assert!(result.stdout.is_empty());
+        let dry_run = result.dry_run.unwrap();
+        assert!(!dry_run.backend.is_empty());
+        assert_eq!(dry_run.argv.last().map(String::as_str), Some("hello"));
+        assert_eq!(dry_run.timeout, 10);
+        // コアダンプは既定で無効
+        assert_eq!(dry_run.rlimits.get("core"), Some(&0));
+
+        // プリセットは指定されていないリソース制限を補う
+        let preset = |name: &str| proto::SandboxConfig {
+            preset: name.to_string(),
+            resource_limits: Some(proto::ResourceLimits {
+                pids_limit: 16,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let created = service
+            .execute_command(Request::new(CommandRequest {
+                sandbox_config: Some(preset("python")),
+                ..request("echo")
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let status = service
+            .get_task_status(Request::new(TaskStatusRequest { task_id: created.task_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        let limits = status.result.unwrap().dry_run.unwrap().resource_limits.unwrap();
+        assert_eq!(limits.memory_limit, 1 << 30);
+        assert_eq!(limits.pids_limit, 16);
+        let error = service
+            .execute_command(Request::new(CommandRequest {
+                sandbox_config: Some(preset("ruby")),
+                ..request("echo")
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+        // seccompプロファイルは名前でも要求できる
+        let seccomp_profile = |name: &str| proto::SandboxConfig {
+            seccomp_profile: name.to_string(),
+            ..preset("python")
+        };
+        let created = service
+            .execute_command(Request::new(CommandRequest {
+                sandbox_config: Some(seccomp_profile("network")),
+                ..request("echo")
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(created.status, TaskStatus::TaskCompleted as i32);
+        let error = service
+            .execute_command(Request::new(CommandRequest {
+                sandbox_config: Some(seccomp_profile("../basic")),
+                ..request("echo")
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+
+        // ドライランでもポリシーで拒否されたコマンドは拒否する
+        let error = service.execute_command(Request::new(request("rm"))).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::PermissionDenied);
+
+        // プランのステップはドライランできない
+        let error = service
+            .execute_plan(Request::new(PlanRequest {
+                steps: vec![PlanStep {
+                    id: "build".to_string(),
+                    command: Some(request("echo")),
+                    depends_on: Vec::new(),
+                }],
+                metadata: HashMap::new(),
+                sandbox_config: None,
+                priority: 0,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,71 @@
+//! Graceful shutdown
+//!
+//! On SIGTERM or SIGINT the gateway stops accepting requests and new tasks, cancels the tasks
+//! still waiting in the task queue and waits for the running tasks to finish (see
+//! `McpServiceImpl::drain`). Tasks still running when the drain timeout expires are cancelled
+//! like with `CancelTask` (SIGTERM, then SIGKILL after the grace period), so that every task
+//! ends with a recorded status and result instead of disappearing with the process. The task
+//! store is flushed and the remaining traces are exported before the process exits.
+
+use mcp_common::error::{McpError, McpResult};
+use std::time::Duration;
+use tracing::info;
+
+/// Shutdown settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownConfig {
+    /// Time running tasks have to finish before they are cancelled
+    pub drain_timeout: Duration,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            drain_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ShutdownConfig {
+    /// Build the settings from environment variables
+    ///
+    /// * `MCP_SHUTDOWN_DRAIN_SECS` - seconds running tasks have to finish (0 cancels them at once)
+    pub fn from_env() -> McpResult<Self> {
+        let mut config = Self::default();
+        if let Ok(value) = std::env::var("MCP_SHUTDOWN_DRAIN_SECS") {
+            let seconds = value.trim().parse::<u64>().map_err(|_| {
+                McpError::InvalidRequest(format!("MCP_SHUTDOWN_DRAIN_SECS must be a number of seconds: '{}'", value))
+            })?;
+            config.drain_timeout = Duration::from_secs(seconds);
+        }
+        Ok(config)
+    }
+}
+
+/// What happened to the unfinished tasks on shutdown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrainReport {
+    /// Running tasks that finished within the drain timeout
+    pub finished: usize,
+    /// Queued tasks cancelled before they started
+    pub unstarted: usize,
+    /// Running tasks cancelled when the drain timeout expired
+    pub interrupted: usize,
+}
+
+/// Wait for SIGTERM or SIGINT
+pub async fn signal() {
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            // Without a SIGTERM handler only SIGINT shuts the gateway down
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    tokio::select! {
+        _ = terminate => info!("Received SIGTERM"),
+        _ = tokio::signal::ctrl_c() => info!("Received SIGINT"),
+    }
+}
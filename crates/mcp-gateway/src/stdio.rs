@@ -0,0 +1,132 @@
+//! MCP stdio transport
+//!
+//! With `mcp-gateway --stdio` the gateway serves a single MCP client (a desktop or IDE
+//! assistant launching it as a subprocess) on stdin and stdout instead of listening for gRPC.
+//! Messages are JSON-RPC 2.0, one per line, handled by [`McpToolServer`].
+//!
+//! Requests are handled concurrently and each response is written as soon as it is ready. As
+//! stdout carries the protocol, logs have to be written to stderr.
+
+use crate::mcp_proxy::McpProxy;
+use crate::mcp_tools::McpToolServer;
+use crate::service::McpServiceImpl;
+use mcp_common::error::McpResult;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tracing::info;
+
+/// MCP server on a pair of byte streams
+#[derive(Debug)]
+pub struct StdioServer {
+    tools: McpToolServer,
+}
+
+impl StdioServer {
+    pub fn new(service: Arc<McpServiceImpl>) -> Self {
+        Self {
+            tools: McpToolServer::new(service),
+        }
+    }
+
+    /// Also offer the tools of upstream MCP servers
+    pub fn with_proxy(mut self, proxy: Arc<McpProxy>) -> Self {
+        self.tools = self.tools.with_proxy(proxy);
+        self
+    }
+
+    /// Serve the client on stdin and stdout until stdin is closed
+    pub async fn serve_stdio(self) -> McpResult<()> {
+        self.serve(tokio::io::stdin(), tokio::io::stdout()).await
+    }
+
+    /// Serve the client until `input` is closed. Requests still running at that point are
+    /// answered before returning
+    pub async fn serve<R, W>(self, input: R, mut output: W) -> McpResult<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let (responses, mut pending) = mpsc::unbounded_channel::<Value>();
+        let mut lines = BufReader::new(input).lines();
+
+        loop {
+            tokio::select! {
+                line = lines.next_line() => {
+                    let Some(line) = line? else {
+                        break;
+                    };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let tools = self.tools.clone();
+                    let responses = responses.clone();
+                    tokio::spawn(async move {
+                        if let Some(response) = tools.handle_message(&line).await {
+                            let _ = responses.send(response);
+                        }
+                    });
+                }
+                Some(response) = pending.recv() => write_message(&mut output, &response).await?,
+            }
+        }
+
+        info!("MCP client closed stdin");
+        drop(responses);
+        while let Some(response) = pending.recv().await {
+            write_message(&mut output, &response).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(output: &mut W, message: &Value) -> McpResult<()> {
+    let mut line = message.to_string();
+    line.push('\n');
+    output.write_all(line.as_bytes()).await?;
+    output.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp_tools::{METHOD_NOT_FOUND, PARSE_ERROR, PROTOCOL_VERSION};
+    use mcp_policy::PolicyEngine;
+    use mcp_sandbox::CommandExecutor;
+    use std::collections::HashMap;
+    use std::time::SystemTime;
+
+    // Test for serving messages line by line
+    #[tokio::test]
+    async fn test_serve() {
+        let service = McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now());
+        let server = StdioServer::new(Arc::new(service));
+
+        let input = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-06-18"}}"#,
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "",
+            r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"resources/list"}"#,
+            "not json",
+        ]
+        .join("\n");
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).await.unwrap();
+
+        let responses: HashMap<String, Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .map(|response| (response["id"].to_string(), response))
+            .collect();
+        // No response to the notification
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses["1"]["result"]["protocolVersion"], PROTOCOL_VERSION);
+        assert_eq!(responses["2"]["result"], serde_json::json!({}));
+        assert_eq!(responses["3"]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses["null"]["error"]["code"], PARSE_ERROR);
+    }
+}
@@ -0,0 +1,351 @@
+//! WebSocket endpoint for task output
+//!
+//! `GET /ws/tasks/{id}/output` upgrades to a WebSocket that streams the output of a task, for
+//! clients that cannot consume the gRPC `StreamTaskOutput` stream (web UIs, notebooks). The
+//! output is read like `StreamTaskOutput` reads it, and every message is a JSON text message:
+//!
+//! * `{"type": "status", "status": "TASK_RUNNING", "timestamp_ms": ...}` - the task status
+//!   changed (the current status is sent first)
+//! * `{"type": "stdout" | "stderr", "data": "...", "timestamp_ms": ...}` - output, with invalid
+//!   UTF-8 replaced
+//! * `{"type": "telemetry", "resource_sample": {...}, "timestamp_ms": ...}` - resource usage
+//! * `{"type": "exit_code", "exit_code": 0, "timestamp_ms": ...}` - the command exited
+//! * `{"type": "error", "message": "..."}` - reading the output failed
+//!
+//! Once the output is complete and the final status has been sent, the server closes the socket.
+//! Unknown tasks are answered with `404 Not Found` instead of an upgrade.
+//!
+//! When authentication is enabled, the upgrade request needs the credentials of a
+//! `StreamTaskOutput` call (see [`crate::auth`]), and only tasks of the caller's tenant are
+//! streamed. RBAC rules (see [`crate::rbac`]) for `StreamTaskOutput` apply to the upgrade as well.
+
+use crate::auth::{self, Authenticator};
+use crate::proto::{McpService, OutputChunkType, TaskOutputChunk, TaskStatus, TaskStatusRequest};
+use crate::rbac::RbacConfig;
+use crate::service::{is_terminal_status, McpServiceImpl};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use mcp_common::utils::current_timestamp_ms;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::{Code, Request, Status};
+use tracing::debug;
+
+/// Interval at which the task status is checked for transitions
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// RPC whose credentials the endpoint requires
+const STREAM_TASK_OUTPUT_RPC: &str = "/mcp.McpService/StreamTaskOutput";
+
+/// State shared by the upgrade requests
+#[derive(Debug)]
+struct Endpoint {
+    service: Arc<McpServiceImpl>,
+    authenticator: Authenticator,
+    rbac: Option<Arc<RbacConfig>>,
+}
+
+/// Route of the endpoint
+pub fn router(service: Arc<McpServiceImpl>, authenticator: Authenticator, rbac: Option<Arc<RbacConfig>>) -> Router {
+    Router::new()
+        .route("/ws/tasks/:id/output", get(handle_upgrade))
+        .with_state(Arc::new(Endpoint {
+            service,
+            authenticator,
+            rbac,
+        }))
+}
+
+async fn handle_upgrade(
+    State(endpoint): State<Arc<Endpoint>>,
+    Path(task_id): Path<String>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let caller = match endpoint.authenticator.authenticate_http(STREAM_TASK_OUTPUT_RPC, &headers).await {
+        Ok(caller) => caller,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if let Some(rbac) = &endpoint.rbac {
+        if let Err(e) = rbac.authorize(caller.as_ref(), STREAM_TASK_OUTPUT_RPC) {
+            return (StatusCode::FORBIDDEN, e.to_string()).into_response();
+        }
+    }
+    let service = endpoint.service.clone();
+    let request = Request::new(TaskStatusRequest { task_id: task_id.clone() });
+    let output = match auth::scope(caller.clone(), service.stream_task_output(request)).await {
+        Ok(output) => output.into_inner(),
+        Err(status) => return (http_status(&status), status.message().to_string()).into_response(),
+    };
+    // The status is read as the caller as well
+    upgrade.on_upgrade(move |socket| auth::scope(caller, stream_output(socket, service, task_id, output)))
+}
+
+fn http_status(status: &Status) -> StatusCode {
+    match status.code() {
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::InvalidArgument => StatusCode::BAD_REQUEST,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+async fn stream_output(
+    mut socket: WebSocket,
+    service: Arc<McpServiceImpl>,
+    task_id: String,
+    mut output: ReceiverStream<Result<TaskOutputChunk, Status>>,
+) {
+    debug!("Streaming the output of task {} over WebSocket", task_id);
+    let mut last_status = None;
+    let mut status_poll = tokio::time::interval(STATUS_POLL_INTERVAL);
+
+    loop {
+        let message = tokio::select! {
+            chunk = output.next() => match chunk {
+                Some(Ok(chunk)) => chunk_message(&chunk),
+                Some(Err(status)) => json!({ "type": "error", "message": status.message() }),
+                None => break,
+            },
+            _ = status_poll.tick() => match status_transition(&service, &task_id, &mut last_status).await {
+                Some(message) => message,
+                None => continue,
+            },
+            // Clients only close the socket (pings are answered by the socket itself)
+            received = socket.recv() => match received {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+        };
+        if socket.send(Message::Text(message.to_string())).await.is_err() {
+            return;
+        }
+    }
+
+    // The output can be complete shortly before the task status is final
+    loop {
+        if let Some(message) = status_transition(&service, &task_id, &mut last_status).await {
+            if socket.send(Message::Text(message.to_string())).await.is_err() {
+                return;
+            }
+        }
+        if last_status.is_none_or(is_terminal_status) {
+            break;
+        }
+        tokio::time::sleep(STATUS_POLL_INTERVAL).await;
+    }
+
+    let close = CloseFrame {
+        code: close_code::NORMAL,
+        reason: "output complete".into(),
+    };
+    let _ = socket.send(Message::Close(Some(close))).await;
+}
+
+/// Status message if the status of the task changed since `last_status`
+async fn status_transition(service: &McpServiceImpl, task_id: &str, last_status: &mut Option<i32>) -> Option<Value> {
+    let request = Request::new(TaskStatusRequest { task_id: task_id.to_string() });
+    let status = service.get_task_status(request).await.ok()?.into_inner().task_info?.status;
+    if *last_status == Some(status) {
+        return None;
+    }
+    *last_status = Some(status);
+    Some(json!({
+        "type": "status",
+        "status": TaskStatus::try_from(status).map_or("UNKNOWN", |status| status.as_str_name()),
+        "timestamp_ms": current_timestamp_ms(),
+    }))
+}
+
+fn chunk_message(chunk: &TaskOutputChunk) -> Value {
+    let data = String::from_utf8_lossy(&chunk.data);
+    match OutputChunkType::try_from(chunk.r#type) {
+        Ok(OutputChunkType::ChunkStdout) => {
+            json!({ "type": "stdout", "data": data, "timestamp_ms": chunk.timestamp_ms })
+        }
+        Ok(OutputChunkType::ChunkStderr) => {
+            json!({ "type": "stderr", "data": data, "timestamp_ms": chunk.timestamp_ms })
+        }
+        Ok(OutputChunkType::ChunkExitCode) => json!({
+            "type": "exit_code",
+            "exit_code": data.trim().parse::<i32>().ok(),
+            "timestamp_ms": chunk.timestamp_ms,
+        }),
+        Ok(OutputChunkType::ChunkTelemetry) => {
+            let sample = chunk.resource_sample.clone().unwrap_or_default();
+            json!({
+                "type": "telemetry",
+                "resource_sample": {
+                    "cpu_time_ms": sample.cpu_time_ms,
+                    "memory_kb": sample.memory_kb,
+                    "max_memory_kb": sample.max_memory_kb,
+                    "io_read_bytes": sample.io_read_bytes,
+                    "io_write_bytes": sample.io_write_bytes,
+                },
+                "timestamp_ms": chunk.timestamp_ms,
+            })
+        }
+        Ok(OutputChunkType::ChunkEvent) | Err(_) => {
+            json!({ "type": "event", "data": data, "timestamp_ms": chunk.timestamp_ms })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_keys::{ApiKeyStore, API_KEY_HEADER};
+    use crate::proto::CommandRequest;
+    use mcp_policy::models::UserInfo;
+    use mcp_policy::PolicyEngine;
+    use mcp_sandbox::CommandExecutor;
+    use std::collections::HashMap;
+    use std::time::SystemTime;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::{self, Error};
+
+    async fn serve(
+        service: Arc<McpServiceImpl>,
+        authenticator: Authenticator,
+        rbac: Option<RbacConfig>,
+    ) -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = router(service, authenticator, rbac.map(Arc::new));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        addr
+    }
+
+    async fn upgrade_status(url: &str, api_key: Option<&str>) -> u16 {
+        let mut request = url.into_client_request().unwrap();
+        if let Some(api_key) = api_key {
+            request.headers_mut().insert(API_KEY_HEADER, api_key.parse().unwrap());
+        }
+        match tokio_tungstenite::connect_async(request).await {
+            Ok((_, response)) => response.status().as_u16(),
+            Err(Error::Http(response)) => response.status().as_u16(),
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    // Test for streaming the output and status transitions of a task
+    #[tokio::test]
+    async fn test_stream_output() {
+        let service = Arc::new(McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now()));
+        let addr = serve(service.clone(), Authenticator::new(), None).await;
+        let request = CommandRequest {
+            command: "echo".to_string(),
+            args: vec!["hello".to_string()],
+            timeout: 10,
+            ..Default::default()
+        };
+        let task_id = service.execute_command(Request::new(request)).await.unwrap().into_inner().task_id;
+
+        let url = format!("ws://{}/ws/tasks/{}/output", addr, task_id);
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        let mut messages = Vec::new();
+        while let Some(message) = socket.next().await {
+            match message.unwrap() {
+                tungstenite::Message::Text(text) => messages.push(serde_json::from_str::<Value>(&text).unwrap()),
+                tungstenite::Message::Close(frame) => {
+                    assert_eq!(frame.unwrap().code, tungstenite::protocol::frame::coding::CloseCode::Normal);
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        let stdout: String = messages
+            .iter()
+            .filter(|message| message["type"] == "stdout")
+            .map(|message| message["data"].as_str().unwrap())
+            .collect();
+        assert_eq!(stdout, "hello\n");
+        assert!(messages.iter().any(|message| message["type"] == "exit_code" && message["exit_code"] == 0));
+        let statuses: Vec<_> = messages.iter().filter(|message| message["type"] == "status").collect();
+        assert_eq!(statuses.last().unwrap()["status"], "TASK_COMPLETED");
+        assert!(statuses.windows(2).all(|pair| pair[0]["status"] != pair[1]["status"]));
+    }
+
+    // Unknown tasks are not upgraded
+    #[tokio::test]
+    async fn test_unknown_task() {
+        let service = Arc::new(McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now()));
+        let addr = serve(service, Authenticator::new(), None).await;
+
+        let url = format!("ws://{}/ws/tasks/task-missing/output", addr);
+        match tokio_tungstenite::connect_async(url).await {
+            Err(Error::Http(response)) => assert_eq!(response.status(), 404),
+            other => panic!("unexpected result: {:?}", other.map(|(_, response)| response.status())),
+        }
+    }
+
+    // Test for requiring credentials and streaming only tasks of the caller's tenant
+    #[tokio::test]
+    async fn test_authentication() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ApiKeyStore::open(dir.path().join("api_keys.json")).unwrap());
+        let user = |tenant_id: &str| UserInfo {
+            id: "alice".to_string(),
+            tenant_id: tenant_id.to_string(),
+            roles: Vec::new(),
+            attributes: HashMap::new(),
+        };
+        let (_, owner) = store.create(&user("tenant-a"), vec![], "").unwrap();
+        let (_, other) = store.create(&user("tenant-b"), vec![], "").unwrap();
+        let service = Arc::new(McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now()));
+        let addr = serve(service.clone(), Authenticator::new().with_api_keys(store), None).await;
+
+        let request = CommandRequest {
+            command: "echo".to_string(),
+            timeout: 10,
+            ..Default::default()
+        };
+        let created = auth::scope(Some(user("tenant-a")), service.execute_command(Request::new(request))).await;
+        let url = format!("ws://{}/ws/tasks/{}/output", addr, created.unwrap().into_inner().task_id);
+
+        assert_eq!(upgrade_status(&url, None).await, 401);
+        assert_eq!(upgrade_status(&url, Some("mcp_wrong")).await, 401);
+        assert_eq!(upgrade_status(&url, Some(&other)).await, 404);
+        assert_eq!(upgrade_status(&url, Some(&owner)).await, 101);
+    }
+
+    // Test for authorizing the upgrade with the roles of the caller
+    #[tokio::test]
+    async fn test_authorization() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ApiKeyStore::open(dir.path().join("api_keys.json")).unwrap());
+        let user = |role: &str| UserInfo {
+            id: "alice".to_string(),
+            tenant_id: "tenant-a".to_string(),
+            roles: vec![role.to_string()],
+            attributes: HashMap::new(),
+        };
+        let (_, operator) = store.create(&user("operator"), vec![], "").unwrap();
+        let (_, developer) = store.create(&user("developer"), vec![], "").unwrap();
+        let rbac = serde_json::from_value(json!({
+            "rules": [{"methods": ["StreamTaskOutput"], "roles": ["operator"]}],
+        }))
+        .unwrap();
+        let service = Arc::new(McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now()));
+        let addr = serve(service.clone(), Authenticator::new().with_api_keys(store), Some(rbac)).await;
+
+        let request = CommandRequest {
+            command: "echo".to_string(),
+            timeout: 10,
+            ..Default::default()
+        };
+        let created = auth::scope(Some(user("operator")), service.execute_command(Request::new(request))).await;
+        let url = format!("ws://{}/ws/tasks/{}/output", addr, created.unwrap().into_inner().task_id);
+
+        assert_eq!(upgrade_status(&url, Some(&developer)).await, 403);
+        assert_eq!(upgrade_status(&url, Some(&operator)).await, 101);
+    }
+}
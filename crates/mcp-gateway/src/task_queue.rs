@@ -0,0 +1,471 @@
+//! Task queue with a bounded pool of workers
+//!
+//! Tasks are not started as soon as they are created: they wait in a queue of bounded depth
+//! until one of a fixed number of workers is free, so that a burst of requests cannot start
+//! an unbounded number of tasks. When the queue is full, new tasks are rejected.
+//!
+//! Each tenant has its own queue ordered by the priority of the request (higher first, then
+//! in order of arrival). A free worker takes the task with the highest priority over all
+//! tenants; among tenants whose next task has that priority it takes turns, so that a
+//! tenant with many queued tasks does not hold up the others.
+//!
+//! On shutdown the queue is closed: it takes no new tasks, and the tasks still waiting are
+//! removed without running.
+
+use crate::metrics;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::parse_positive;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::cmp::Ordering;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Once};
+use tokio::sync::Semaphore;
+use tracing::debug;
+
+/// Work of a queued task
+pub type Job = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Task queue settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskQueueConfig {
+    /// Number of tasks that run at the same time
+    pub workers: usize,
+    /// Maximum number of tasks waiting for a worker
+    pub max_depth: usize,
+}
+
+impl Default for TaskQueueConfig {
+    fn default() -> Self {
+        Self {
+            workers: 64,
+            max_depth: 1024,
+        }
+    }
+}
+
+impl TaskQueueConfig {
+    /// Build the settings from environment variables
+    ///
+    /// * `MCP_TASK_QUEUE_WORKERS` - number of tasks that run at the same time
+    /// * `MCP_TASK_QUEUE_MAX_DEPTH` - maximum number of tasks waiting for a worker
+    pub fn from_env() -> McpResult<Self> {
+        let mut config = Self::default();
+        if let Ok(value) = std::env::var("MCP_TASK_QUEUE_WORKERS") {
+            config.workers = parse_positive("MCP_TASK_QUEUE_WORKERS", &value)?;
+        }
+        if let Ok(value) = std::env::var("MCP_TASK_QUEUE_MAX_DEPTH") {
+            config.max_depth = parse_positive("MCP_TASK_QUEUE_MAX_DEPTH", &value)?;
+        }
+        Ok(config)
+    }
+}
+
+struct QueuedJob {
+    task_id: String,
+    priority: i32,
+    /// Order of arrival
+    sequence: u64,
+    job: Job,
+}
+
+impl PartialEq for QueuedJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedJob {}
+
+impl PartialOrd for QueuedJob {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedJob {
+    /// Higher priority first, then earlier arrival first
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    /// Queued tasks per tenant
+    tenants: HashMap<String, BinaryHeap<QueuedJob>>,
+    /// Tenants with queued tasks, the next to take a turn first
+    turns: VecDeque<String>,
+    depth: usize,
+    sequence: u64,
+    /// No new tasks are accepted
+    closed: bool,
+}
+
+impl QueueState {
+    /// Take the next task: the highest priority, taking turns between tenants
+    fn pop(&mut self) -> Option<QueuedJob> {
+        let priority = self.tenants.values().filter_map(|jobs| jobs.peek()).map(|job| job.priority).max()?;
+        let turn = self
+            .turns
+            .iter()
+            .position(|tenant_id| self.tenants[tenant_id].peek().is_some_and(|job| job.priority == priority))?;
+        let tenant_id = self.turns.remove(turn)?;
+        let jobs = self.tenants.get_mut(&tenant_id)?;
+        let job = jobs.pop()?;
+        if jobs.is_empty() {
+            self.tenants.remove(&tenant_id);
+        } else {
+            self.turns.push_back(tenant_id);
+        }
+        self.depth -= 1;
+        Some(job)
+    }
+
+    /// Remove a queued task
+    fn remove(&mut self, task_id: &str) -> Option<QueuedJob> {
+        let tenant_id = self
+            .tenants
+            .iter()
+            .find(|(_, jobs)| jobs.iter().any(|job| job.task_id == task_id))
+            .map(|(tenant_id, _)| tenant_id.clone())?;
+        let jobs = self.tenants.remove(&tenant_id)?;
+        let (mut removed, remaining): (Vec<_>, Vec<_>) = jobs.into_iter().partition(|job| job.task_id == task_id);
+        if remaining.is_empty() {
+            self.turns.retain(|turn| turn != &tenant_id);
+        } else {
+            self.tenants.insert(tenant_id, BinaryHeap::from(remaining));
+        }
+        self.depth -= 1;
+        removed.pop()
+    }
+}
+
+struct Inner {
+    config: TaskQueueConfig,
+    state: Mutex<QueueState>,
+    /// One permit per queued task
+    queued: Semaphore,
+    workers: Once,
+}
+
+impl Inner {
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Stops the workers when the last handle of the queue is dropped
+struct Shutdown(Arc<Inner>);
+
+impl Drop for Shutdown {
+    fn drop(&mut self) {
+        self.0.queued.close();
+    }
+}
+
+/// Queue of tasks waiting for a worker
+#[derive(Clone)]
+pub struct TaskQueue {
+    inner: Arc<Inner>,
+    _shutdown: Arc<Shutdown>,
+}
+
+impl fmt::Debug for TaskQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskQueue")
+            .field("config", &self.inner.config)
+            .field("depth", &self.depth())
+            .finish()
+    }
+}
+
+impl Default for TaskQueue {
+    fn default() -> Self {
+        Self::new(TaskQueueConfig::default())
+    }
+}
+
+impl TaskQueue {
+    /// Create a queue; its workers start with the first task
+    pub fn new(config: TaskQueueConfig) -> Self {
+        let inner = Arc::new(Inner {
+            config,
+            state: Mutex::new(QueueState::default()),
+            queued: Semaphore::new(0),
+            workers: Once::new(),
+        });
+        Self {
+            _shutdown: Arc::new(Shutdown(inner.clone())),
+            inner,
+        }
+    }
+
+    /// Number of tasks waiting for a worker
+    pub fn depth(&self) -> usize {
+        self.lock().depth
+    }
+
+    /// Queue a task of a tenant (must be called within a Tokio runtime)
+    ///
+    /// Fails with [`McpError::ResourceExhausted`] when the queue is full.
+    pub fn submit(&self, task_id: &str, tenant_id: &str, priority: i32, job: Job) -> McpResult<()> {
+        self.reserve()?.submit(task_id, tenant_id, priority, job);
+        Ok(())
+    }
+
+    /// Reserve a place in the queue for a task that is about to be created
+    ///
+    /// Fails with [`McpError::ResourceExhausted`] when the queue is full. The place is
+    /// released if the reservation is dropped without submitting a task.
+    pub fn reserve(&self) -> McpResult<Reservation> {
+        let mut state = self.lock();
+        if state.closed {
+            return Err(McpError::Temporary("Task queue is closed for shutdown".to_string()));
+        }
+        if state.depth >= self.inner.config.max_depth {
+            metrics::increment_task_queue_rejections();
+            return Err(McpError::ResourceExhausted(format!(
+                "Task queue is full ({} tasks waiting)",
+                state.depth
+            )));
+        }
+        state.depth += 1;
+        metrics::set_task_queue_depth(state.depth as i64);
+        Ok(Reservation {
+            queue: Some(self.clone()),
+        })
+    }
+
+    /// Remove a task that is still waiting for a worker, dropping its work
+    ///
+    /// Returns false if the task is not queued (it has started or is unknown).
+    pub fn cancel(&self, task_id: &str) -> bool {
+        let removed = {
+            let mut state = self.lock();
+            let removed = state.remove(task_id);
+            metrics::set_task_queue_depth(state.depth as i64);
+            removed
+        };
+        let Some(removed) = removed else {
+            return false;
+        };
+        // A worker that already took the permit finds the queue without this task
+        if let Ok(permit) = self.inner.queued.try_acquire() {
+            permit.forget();
+        }
+        drop(removed);
+        debug!("Removed task {} from the queue", task_id);
+        true
+    }
+
+    /// Stop accepting tasks and remove the tasks still waiting for a worker, dropping their work
+    ///
+    /// Returns the IDs of the removed tasks. Later reservations fail with
+    /// [`McpError::Temporary`]; tasks of reservations made before are still queued and run.
+    pub fn close(&self) -> Vec<String> {
+        let removed: Vec<QueuedJob> = {
+            let mut state = self.lock();
+            state.closed = true;
+            state.turns.clear();
+            let removed: Vec<_> = state.tenants.drain().flat_map(|(_, jobs)| jobs.into_sorted_vec()).collect();
+            state.depth -= removed.len();
+            metrics::set_task_queue_depth(state.depth as i64);
+            removed
+        };
+        // Workers taking the permits of removed tasks find the queue without them
+        let task_ids: Vec<String> = removed.iter().map(|job| job.task_id.clone()).collect();
+        drop(removed);
+        debug!("Closed the task queue, removing {} tasks", task_ids.len());
+        task_ids
+    }
+
+    fn start_workers(&self) {
+        for _ in 0..self.inner.config.workers {
+            let inner = self.inner.clone();
+            tokio::spawn(async move {
+                loop {
+                    match inner.queued.acquire().await {
+                        Ok(permit) => permit.forget(),
+                        Err(_) => break,
+                    }
+                    let next = {
+                        let mut state = inner.lock();
+                        let next = state.pop();
+                        metrics::set_task_queue_depth(state.depth as i64);
+                        next
+                    };
+                    if let Some(next) = next {
+                        next.job.await;
+                    }
+                }
+            });
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.inner.lock()
+    }
+}
+
+/// Place in the queue reserved for a task
+#[derive(Debug)]
+pub struct Reservation {
+    queue: Option<TaskQueue>,
+}
+
+impl Reservation {
+    /// Queue the task in the reserved place (must be called within a Tokio runtime)
+    pub fn submit(mut self, task_id: &str, tenant_id: &str, priority: i32, job: Job) {
+        let Some(queue) = self.queue.take() else {
+            return;
+        };
+        queue.inner.workers.call_once(|| queue.start_workers());
+        {
+            let mut state = queue.lock();
+            state.sequence += 1;
+            let queued = QueuedJob {
+                task_id: task_id.to_string(),
+                priority,
+                sequence: state.sequence,
+                job,
+            };
+            let jobs = state.tenants.entry(tenant_id.to_string()).or_default();
+            let first = jobs.is_empty();
+            jobs.push(queued);
+            if first {
+                state.turns.push_back(tenant_id.to_string());
+            }
+        }
+        debug!("Queued task {} of tenant {} with priority {}", task_id, tenant_id, priority);
+        queue.inner.queued.add_permits(1);
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.take() {
+            let mut state = queue.lock();
+            state.depth -= 1;
+            metrics::set_task_queue_depth(state.depth as i64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::{mpsc, oneshot};
+
+    fn job(name: &'static str, started: &mpsc::UnboundedSender<&'static str>) -> Job {
+        let started = started.clone();
+        Box::pin(async move {
+            let _ = started.send(name);
+        })
+    }
+
+    /// Job occupying the only worker until released
+    fn blocker(queue: &TaskQueue) -> oneshot::Sender<()> {
+        let (release, released) = oneshot::channel();
+        queue
+            .submit("blocker", "tenant0", 0, Box::pin(async move {
+                let _ = released.await;
+            }))
+            .unwrap();
+        release
+    }
+
+    async fn wait_for_worker(queue: &TaskQueue) {
+        while queue.depth() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_priority_and_fairness() {
+        let queue = TaskQueue::new(TaskQueueConfig { workers: 1, max_depth: 10 });
+        let release = blocker(&queue);
+        wait_for_worker(&queue).await;
+
+        let (started, mut order) = mpsc::unbounded_channel();
+        queue.submit("a1", "tenant1", 0, job("a1", &started)).unwrap();
+        queue.submit("a2", "tenant1", 0, job("a2", &started)).unwrap();
+        queue.submit("a3", "tenant1", 0, job("a3", &started)).unwrap();
+        queue.submit("b1", "tenant2", 0, job("b1", &started)).unwrap();
+        queue.submit("b2", "tenant2", 5, job("b2", &started)).unwrap();
+        queue.submit("c1", "tenant3", 0, job("c1", &started)).unwrap();
+        assert_eq!(queue.depth(), 6);
+        drop(started);
+
+        release.send(()).unwrap();
+        let mut started = Vec::new();
+        while let Some(name) = order.recv().await {
+            started.push(name);
+        }
+        // The highest priority first, then the tenants take turns (tenant2 just had one)
+        assert_eq!(started, ["b2", "a1", "c1", "b1", "a2", "a3"]);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_and_cancel() {
+        let queue = TaskQueue::new(TaskQueueConfig { workers: 1, max_depth: 2 });
+        let release = blocker(&queue);
+        wait_for_worker(&queue).await;
+
+        let (started, mut order) = mpsc::unbounded_channel();
+        queue.submit("a1", "tenant1", 0, job("a1", &started)).unwrap();
+        queue.submit("a2", "tenant1", 0, job("a2", &started)).unwrap();
+        let error = queue.submit("a3", "tenant1", 0, job("a3", &started)).unwrap_err();
+        assert!(matches!(error, McpError::ResourceExhausted(_)));
+
+        // A cancelled task makes room and never runs
+        assert!(queue.cancel("a1"));
+        assert!(!queue.cancel("a1"));
+        // A reservation holds a place until it is dropped
+        let reservation = queue.reserve().unwrap();
+        assert!(queue.reserve().is_err());
+        drop(reservation);
+        queue.submit("a3", "tenant1", 0, job("a3", &started)).unwrap();
+        drop(started);
+
+        release.send(()).unwrap();
+        let mut started = Vec::new();
+        while let Some(name) = order.recv().await {
+            started.push(name);
+        }
+        assert_eq!(started, ["a2", "a3"]);
+        assert_eq!(queue.depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_close() {
+        let queue = TaskQueue::new(TaskQueueConfig { workers: 1, max_depth: 10 });
+        let release = blocker(&queue);
+        wait_for_worker(&queue).await;
+
+        let (started, mut order) = mpsc::unbounded_channel();
+        queue.submit("a1", "tenant1", 0, job("a1", &started)).unwrap();
+        queue.submit("b1", "tenant2", 0, job("b1", &started)).unwrap();
+        let reservation = queue.reserve().unwrap();
+
+        let mut removed = queue.close();
+        removed.sort();
+        assert_eq!(removed, ["a1", "b1"]);
+        assert!(matches!(queue.reserve().unwrap_err(), McpError::Temporary(_)));
+        // A place reserved before closing is still honoured
+        reservation.submit("c1", "tenant1", 0, job("c1", &started));
+        drop(started);
+
+        release.send(()).unwrap();
+        let mut started = Vec::new();
+        while let Some(name) = order.recv().await {
+            started.push(name);
+        }
+        assert_eq!(started, ["c1"]);
+        assert_eq!(queue.depth(), 0);
+    }
+}
@@ -0,0 +1,288 @@
+//! Retention of finished tasks
+//!
+//! The gateway holds every task and its result in memory. A background reaper periodically
+//! removes finished tasks (completed, failed, cancelled or timed out) together with their
+//! results and output logs once their completion is older than the retention period of their
+//! tenant.
+//! Tasks that have not finished are never removed. Tasks written to a task store are only
+//! removed from memory; the store keeps them for its own retention.
+
+use crate::metrics;
+use crate::proto;
+use crate::service::is_terminal_status;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::parse_key_value_pairs;
+use mcp_sandbox::OutputLogConfig;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Task retention settings
+#[derive(Debug, Clone)]
+pub struct TaskRetentionConfig {
+    /// How long finished tasks are kept
+    pub retention: Duration,
+    /// Per-tenant retention periods, replacing the default
+    pub tenant_retention: HashMap<String, Duration>,
+    /// How often expired tasks are removed
+    pub interval: Duration,
+}
+
+impl Default for TaskRetentionConfig {
+    fn default() -> Self {
+        Self {
+            retention: Duration::from_secs(24 * 60 * 60),
+            tenant_retention: HashMap::new(),
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl TaskRetentionConfig {
+    /// Build the settings from environment variables
+    ///
+    /// * `MCP_TASK_RETENTION_SECS` - how long finished tasks are kept
+    /// * `MCP_TENANT_TASK_RETENTION` - per-tenant periods in seconds (`tenant1=3600,tenant2=600`)
+    /// * `MCP_TASK_RETENTION_INTERVAL_SECS` - how often expired tasks are removed
+    pub fn from_env() -> McpResult<Self> {
+        let mut config = Self::default();
+
+        if let Ok(value) = std::env::var("MCP_TASK_RETENTION_SECS") {
+            config.retention = parse_secs("MCP_TASK_RETENTION_SECS", &value)?;
+        }
+        if let Ok(value) = std::env::var("MCP_TENANT_TASK_RETENTION") {
+            config.tenant_retention = parse_key_value_pairs(&value)?
+                .into_iter()
+                .map(|(tenant_id, secs)| Ok((tenant_id, parse_secs("MCP_TENANT_TASK_RETENTION", &secs)?)))
+                .collect::<McpResult<_>>()?;
+        }
+        if let Ok(value) = std::env::var("MCP_TASK_RETENTION_INTERVAL_SECS") {
+            config.interval = parse_secs("MCP_TASK_RETENTION_INTERVAL_SECS", &value)?;
+        }
+
+        Ok(config)
+    }
+
+    /// Retention period of the tasks of a tenant
+    pub fn retention(&self, tenant_id: &str) -> Duration {
+        self.tenant_retention.get(tenant_id).copied().unwrap_or(self.retention)
+    }
+}
+
+/// Number of entries removed by one pass of the reaper
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Reclaimed {
+    /// Removed tasks
+    pub tasks: usize,
+    /// Removed results
+    pub results: usize,
+    /// Removed output log directories
+    pub output_logs: usize,
+}
+
+/// Removes finished tasks and their results after the retention period
+#[derive(Debug, Default)]
+pub struct TaskReaper {
+    config: TaskRetentionConfig,
+    /// Tenant of every task held in memory
+    tenants: DashMap<String, String>,
+    /// Output logs removed with their tasks
+    output_logs: Option<OutputLogConfig>,
+}
+
+impl TaskReaper {
+    /// Create a reaper with the given settings
+    pub fn new(config: TaskRetentionConfig) -> Self {
+        Self {
+            config,
+            tenants: DashMap::new(),
+            output_logs: None,
+        }
+    }
+
+    /// Remove the output log of a task together with the task
+    pub fn with_output_logs(mut self, config: OutputLogConfig) -> Self {
+        self.output_logs = Some(config);
+        self
+    }
+
+    /// Remember the tenant of a new task, which selects its retention period
+    pub fn track(&self, task_id: &str, tenant_id: &str) {
+        self.tenants.insert(task_id.to_string(), tenant_id.to_string());
+    }
+
+    /// Remove the finished tasks that are past their retention period at `now`
+    ///
+    /// A task expires by the time it completed, or by its creation time if it was never run.
+    pub fn reap(
+        &self,
+        tasks: &DashMap<String, proto::TaskInfo>,
+        results: &DashMap<String, proto::TaskResult>,
+        now: DateTime<Utc>,
+    ) -> Reclaimed {
+        let expired: Vec<String> = tasks
+            .iter()
+            .filter(|task| is_terminal_status(task.status))
+            .filter(|task| {
+                let finished_at = task.completed_at.as_deref().unwrap_or(&task.created_at);
+                let Ok(finished_at) = DateTime::parse_from_rfc3339(finished_at) else {
+                    return false;
+                };
+                let retention = self.tenants.get(task.key()).map_or(self.config.retention, |tenant_id| {
+                    self.config.retention(&tenant_id)
+                });
+                (now - finished_at.with_timezone(&Utc)).to_std().is_ok_and(|age| age >= retention)
+            })
+            .map(|task| task.key().clone())
+            .collect();
+
+        let mut reclaimed = Reclaimed::default();
+        for task_id in expired {
+            // The status is checked again in case the task was changed meanwhile
+            if tasks.remove_if(&task_id, |_, task| is_terminal_status(task.status)).is_none() {
+                continue;
+            }
+            reclaimed.tasks += 1;
+            if results.remove(&task_id).is_some() {
+                reclaimed.results += 1;
+            }
+            self.tenants.remove(&task_id);
+            if self.remove_output_log(&task_id) {
+                reclaimed.output_logs += 1;
+            }
+        }
+        reclaimed
+    }
+
+    /// Remove the output log directory of a task, returning whether there was one
+    fn remove_output_log(&self, task_id: &str) -> bool {
+        let Some(dir) = self.output_logs.as_ref().and_then(|config| config.task_dir(task_id).ok()) else {
+            return false;
+        };
+        match std::fs::remove_dir_all(&dir) {
+            Ok(()) => true,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+            Err(e) => {
+                warn!("Failed to remove the output log {:?} of task {}: {}", dir, task_id, e);
+                false
+            }
+        }
+    }
+
+    /// Start removing expired tasks in the background (must be called within a Tokio runtime)
+    ///
+    /// The reaper stops when the maps have been dropped.
+    pub fn start(
+        self: Arc<Self>,
+        tasks: Weak<DashMap<String, proto::TaskInfo>>,
+        results: Weak<DashMap<String, proto::TaskResult>>,
+    ) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.config.interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let (Some(tasks), Some(results)) = (tasks.upgrade(), results.upgrade()) else {
+                    break;
+                };
+                let reclaimed = self.reap(&tasks, &results, Utc::now());
+                metrics::increment_reclaimed_entries("task", reclaimed.tasks);
+                metrics::increment_reclaimed_entries("result", reclaimed.results);
+                metrics::increment_reclaimed_entries("output_log", reclaimed.output_logs);
+                if reclaimed.tasks > 0 {
+                    info!("Removed {} expired tasks and {} results", reclaimed.tasks, reclaimed.results);
+                }
+            }
+        });
+    }
+}
+
+fn parse_secs(name: &str, value: &str) -> McpResult<Duration> {
+    value.trim().parse::<u64>().map(Duration::from_secs).map_err(|_| {
+        McpError::InvalidRequest(format!("{} must be a number of seconds: '{}'", name, value))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(task_id: &str, status: proto::TaskStatus, completed_at: Option<DateTime<Utc>>) -> proto::TaskInfo {
+        proto::TaskInfo {
+            task_id: task_id.to_string(),
+            task_type: proto::TaskType::TaskCommand as i32,
+            status: status as i32,
+            created_at: (Utc::now() - chrono::Duration::days(2)).to_rfc3339(),
+            started_at: None,
+            completed_at: completed_at.map(|time| time.to_rfc3339()),
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn result() -> proto::TaskResult {
+        proto::TaskResult {
+            exit_code: 0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_retention_per_tenant() {
+        let config = TaskRetentionConfig {
+            retention: Duration::from_secs(3600),
+            tenant_retention: HashMap::from([("tenant1".to_string(), Duration::from_secs(60))]),
+            ..Default::default()
+        };
+        assert_eq!(config.retention("tenant1"), Duration::from_secs(60));
+        assert_eq!(config.retention("tenant2"), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_reap_expired_tasks() {
+        let log_dir = tempfile::tempdir().unwrap();
+        let output_logs = OutputLogConfig {
+            base_dir: log_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let reaper = TaskReaper::new(TaskRetentionConfig {
+            retention: Duration::from_secs(3600),
+            tenant_retention: HashMap::from([("tenant1".to_string(), Duration::from_secs(60))]),
+            ..Default::default()
+        })
+        .with_output_logs(output_logs.clone());
+        let now = Utc::now();
+        let ten_minutes_ago = now - chrono::Duration::minutes(10);
+        let tasks = DashMap::new();
+        let results = DashMap::new();
+        let add = |task_id: &str, tenant_id: &str, status, completed_at| {
+            reaper.track(task_id, tenant_id);
+            tasks.insert(task_id.to_string(), task(task_id, status, completed_at));
+            results.insert(task_id.to_string(), result());
+            std::fs::create_dir_all(output_logs.task_dir(task_id).unwrap()).unwrap();
+        };
+        // Expired by the retention of its tenant
+        add("expired", "tenant1", proto::TaskStatus::TaskFailed, Some(ten_minutes_ago));
+        // Within the default retention
+        add("retained", "tenant2", proto::TaskStatus::TaskCompleted, Some(ten_minutes_ago));
+        // Not finished, even though created long ago
+        add("running", "tenant1", proto::TaskStatus::TaskRunning, None);
+        // Cancelled before it was run expires by its creation time
+        add("cancelled", "tenant2", proto::TaskStatus::TaskCancelled, None);
+
+        let reclaimed = reaper.reap(&tasks, &results, now);
+        assert_eq!(reclaimed, Reclaimed { tasks: 2, results: 2, output_logs: 2 });
+        let mut remaining: Vec<_> = tasks.iter().map(|task| task.key().clone()).collect();
+        remaining.sort();
+        assert_eq!(remaining, ["retained", "running"]);
+        assert!(!results.contains_key("expired"));
+        assert!(!reaper.tenants.contains_key("expired"));
+        assert!(!output_logs.task_dir("expired").unwrap().exists());
+        assert!(output_logs.task_dir("retained").unwrap().exists());
+
+        // Nothing is left to remove
+        assert_eq!(reaper.reap(&tasks, &results, now), Reclaimed::default());
+    }
+}
@@ -0,0 +1,389 @@
+//! Persistence of tasks and their results
+//!
+//! The gateway keeps the state of its tasks in memory, which is lost on restart and not
+//! visible to other instances. With a [`TaskStore`], every change of a task and its result is
+//! also written to the store, and tasks that are not held in memory (created before a restart
+//! or by another instance) are looked up there. Cancelling, pausing and streaming the output
+//! of a task still require the instance that runs it.
+//!
+//! [`SqlTaskStore`] keeps tasks in PostgreSQL or SQLite (`MCP_TASK_STORE_URL`, for example
+//! `postgres://mcp@db/mcp` or `sqlite:///var/lib/mcp/tasks.db?mode=rwc`) and creates its
+//! schema with the migrations in `migrations/`. Besides the encoded task and result, the
+//! tables hold the status, timestamps, resource usage and audit references (tenant, user and
+//! break-glass reason) of every task as columns, for retention and reporting queries.
+//!
+//! Writes are applied in order by a background task, so request handling never waits for the
+//! database; failed writes are logged.
+
+use crate::proto;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::get_env_var_or;
+use mcp_policy::break_glass::METADATA_BREAK_GLASS;
+use mcp_policy::models::UserInfo;
+use prost::Message;
+use sqlx::any::AnyPoolOptions;
+use sqlx::migrate::Migrator;
+use sqlx::{AnyPool, Row};
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, OnceCell};
+use tracing::{debug, warn};
+
+/// Schema of the SQL task store
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+const UPSERT_TASK: &str = "INSERT INTO tasks (task_id, task_type, status, tenant_id, user_id, break_glass, \
+    created_at, started_at, completed_at, metadata, task) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) \
+    ON CONFLICT (task_id) DO UPDATE SET status = excluded.status, \
+    tenant_id = COALESCE(excluded.tenant_id, tasks.tenant_id), user_id = COALESCE(excluded.user_id, tasks.user_id), \
+    break_glass = excluded.break_glass, started_at = excluded.started_at, completed_at = excluded.completed_at, \
+    metadata = excluded.metadata, task = excluded.task";
+
+const UPSERT_RESULT: &str = "INSERT INTO task_results (task_id, exit_code, execution_time_ms, cpu_time_ms, \
+    max_memory_kb, io_read_bytes, io_write_bytes, result) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+    ON CONFLICT (task_id) DO UPDATE SET exit_code = excluded.exit_code, \
+    execution_time_ms = excluded.execution_time_ms, cpu_time_ms = excluded.cpu_time_ms, \
+    max_memory_kb = excluded.max_memory_kb, io_read_bytes = excluded.io_read_bytes, \
+    io_write_bytes = excluded.io_write_bytes, result = excluded.result";
+
+/// Task store settings
+#[derive(Debug, Clone)]
+pub struct TaskStoreConfig {
+    /// Database URL; tasks are kept in memory only if unset
+    pub url: Option<String>,
+    /// Maximum number of database connections
+    pub max_connections: u32,
+}
+
+impl Default for TaskStoreConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            max_connections: 5,
+        }
+    }
+}
+
+impl TaskStoreConfig {
+    /// Build the settings from environment variables
+    ///
+    /// * `MCP_TASK_STORE_URL` - `postgres://` or `sqlite://` URL of the database
+    /// * `MCP_TASK_STORE_MAX_CONNECTIONS` - maximum number of database connections
+    pub fn from_env() -> McpResult<Self> {
+        let default = Self::default();
+        let value = get_env_var_or("MCP_TASK_STORE_MAX_CONNECTIONS", &default.max_connections.to_string());
+        let max_connections = match value.trim().parse::<u32>() {
+            Ok(number) if number > 0 => number,
+            _ => {
+                return Err(McpError::InvalidRequest(format!(
+                    "MCP_TASK_STORE_MAX_CONNECTIONS must be a positive number: '{}'",
+                    value
+                )))
+            }
+        };
+        Ok(Self {
+            url: std::env::var("MCP_TASK_STORE_URL").ok().filter(|url| !url.is_empty()),
+            max_connections,
+        })
+    }
+}
+
+/// Storage of tasks and their results
+#[tonic::async_trait]
+pub trait TaskStore: Send + Sync {
+    /// Insert or update a task; the owner is given when the task is created
+    async fn save_task(&self, task: &proto::TaskInfo, owner: Option<&UserInfo>) -> McpResult<()>;
+
+    /// Insert or update the result of a task
+    async fn save_result(&self, task_id: &str, result: &proto::TaskResult) -> McpResult<()>;
+
+    /// Stored task with the ID
+    async fn load_task(&self, task_id: &str) -> McpResult<Option<proto::TaskInfo>>;
+
+    /// Stored result of the task
+    async fn load_result(&self, task_id: &str) -> McpResult<Option<proto::TaskResult>>;
+}
+
+/// Task store in a PostgreSQL or SQLite database
+#[derive(Debug)]
+pub struct SqlTaskStore {
+    pool: AnyPool,
+    migrated: OnceCell<()>,
+}
+
+impl SqlTaskStore {
+    /// Store connecting to the database when first used, which also migrates the schema
+    pub fn connect_lazy(config: &TaskStoreConfig) -> McpResult<Self> {
+        let Some(url) = &config.url else {
+            return Err(McpError::InvalidRequest("MCP_TASK_STORE_URL is not set".to_string()));
+        };
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(config.max_connections)
+            .connect_lazy(url)
+            .map_err(|e| McpError::InvalidRequest(format!("Invalid task store URL: {}", e)))?;
+        Ok(Self {
+            pool,
+            migrated: OnceCell::new(),
+        })
+    }
+
+    /// Connect to the database and migrate the schema
+    pub async fn connect(config: &TaskStoreConfig) -> McpResult<Self> {
+        let store = Self::connect_lazy(config)?;
+        store.migrate().await?;
+        Ok(store)
+    }
+
+    async fn migrate(&self) -> McpResult<&AnyPool> {
+        self.migrated
+            .get_or_try_init(|| async {
+                MIGRATOR
+                    .run(&self.pool)
+                    .await
+                    .map_err(|e| McpError::ExternalService(format!("Failed to migrate the task store: {}", e)))?;
+                debug!("Task store schema is up to date");
+                Ok::<_, McpError>(())
+            })
+            .await?;
+        Ok(&self.pool)
+    }
+}
+
+#[tonic::async_trait]
+impl TaskStore for SqlTaskStore {
+    async fn save_task(&self, task: &proto::TaskInfo, owner: Option<&UserInfo>) -> McpResult<()> {
+        let task_type = proto::TaskType::try_from(task.task_type).map_or("UNKNOWN", |t| t.as_str_name());
+        let status = proto::TaskStatus::try_from(task.status).map_or("UNKNOWN", |status| status.as_str_name());
+        sqlx::query(UPSERT_TASK)
+            .bind(task.task_id.clone())
+            .bind(task_type)
+            .bind(status)
+            .bind(owner.map(|owner| owner.tenant_id.clone()))
+            .bind(owner.map(|owner| owner.id.clone()))
+            .bind(task.metadata.get(METADATA_BREAK_GLASS).cloned())
+            .bind(task.created_at.clone())
+            .bind(task.started_at.clone())
+            .bind(task.completed_at.clone())
+            .bind(serde_json::to_string(&task.metadata)?)
+            .bind(task.encode_to_vec())
+            .execute(self.migrate().await?)
+            .await
+            .map_err(store_error)?;
+        Ok(())
+    }
+
+    async fn save_result(&self, task_id: &str, result: &proto::TaskResult) -> McpResult<()> {
+        let usage = result.resource_usage.as_ref();
+        let usage_value = |value: fn(&proto::ResourceUsage) -> u64| usage.map(|usage| value(usage) as i64);
+        sqlx::query(UPSERT_RESULT)
+            .bind(task_id.to_string())
+            .bind(result.exit_code)
+            .bind(result.execution_time_ms as i64)
+            .bind(usage_value(|usage| usage.cpu_time_ms))
+            .bind(usage_value(|usage| usage.max_memory_kb))
+            .bind(usage_value(|usage| usage.io_read_bytes))
+            .bind(usage_value(|usage| usage.io_write_bytes))
+            .bind(result.encode_to_vec())
+            .execute(self.migrate().await?)
+            .await
+            .map_err(store_error)?;
+        Ok(())
+    }
+
+    async fn load_task(&self, task_id: &str) -> McpResult<Option<proto::TaskInfo>> {
+        let row = sqlx::query("SELECT task FROM tasks WHERE task_id = $1")
+            .bind(task_id.to_string())
+            .fetch_optional(self.migrate().await?)
+            .await
+            .map_err(store_error)?;
+        row.map(|row| decode(row.try_get("task").map_err(store_error)?)).transpose()
+    }
+
+    async fn load_result(&self, task_id: &str) -> McpResult<Option<proto::TaskResult>> {
+        let row = sqlx::query("SELECT result FROM task_results WHERE task_id = $1")
+            .bind(task_id.to_string())
+            .fetch_optional(self.migrate().await?)
+            .await
+            .map_err(store_error)?;
+        row.map(|row| decode(row.try_get("result").map_err(store_error)?)).transpose()
+    }
+}
+
+fn store_error(e: sqlx::Error) -> McpError {
+    McpError::ExternalService(format!("Task store error: {}", e))
+}
+
+fn decode<T: Message + Default>(bytes: Vec<u8>) -> McpResult<T> {
+    T::decode(bytes.as_slice()).map_err(|e| McpError::Internal(format!("Invalid task in the task store: {}", e)))
+}
+
+/// A change to write to the store
+enum TaskWrite {
+    Task(Box<proto::TaskInfo>, Option<UserInfo>),
+    Result(String, Box<proto::TaskResult>),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Writes task changes to a store in the background, in the order they were recorded
+///
+/// Without a store (the default), nothing is recorded.
+#[derive(Clone, Default)]
+pub struct TaskRecorder {
+    store: Option<Arc<dyn TaskStore>>,
+    writes: Option<mpsc::UnboundedSender<TaskWrite>>,
+}
+
+impl fmt::Debug for TaskRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskRecorder").field("enabled", &self.store.is_some()).finish()
+    }
+}
+
+impl TaskRecorder {
+    /// Start writing to the store (must be called within a Tokio runtime)
+    pub fn start(store: Arc<dyn TaskStore>) -> Self {
+        let (writes, mut pending) = mpsc::unbounded_channel();
+        let writer = store.clone();
+        tokio::spawn(async move {
+            while let Some(write) = pending.recv().await {
+                let (task_id, written) = match write {
+                    TaskWrite::Task(task, owner) => {
+                        let written = writer.save_task(&task, owner.as_ref()).await;
+                        (task.task_id.clone(), written)
+                    }
+                    TaskWrite::Result(task_id, result) => {
+                        let written = writer.save_result(&task_id, &result).await;
+                        (task_id, written)
+                    }
+                    TaskWrite::Flush(done) => {
+                        let _ = done.send(());
+                        continue;
+                    }
+                };
+                if let Err(e) = written {
+                    warn!("Failed to write task {} to the task store: {}", task_id, e);
+                }
+            }
+        });
+        Self {
+            store: Some(store),
+            writes: Some(writes),
+        }
+    }
+
+    /// Record a new task and its owner
+    pub fn created(&self, task: &proto::TaskInfo, owner: &UserInfo) {
+        self.send(|| TaskWrite::Task(Box::new(task.clone()), Some(owner.clone())));
+    }
+
+    /// Record a change of a task
+    pub fn updated(&self, task: &proto::TaskInfo) {
+        self.send(|| TaskWrite::Task(Box::new(task.clone()), None));
+    }
+
+    /// Record the result of a task
+    pub fn result(&self, task_id: &str, result: &proto::TaskResult) {
+        self.send(|| TaskWrite::Result(task_id.to_string(), Box::new(result.clone())));
+    }
+
+    /// Wait until everything recorded so far has been written
+    pub async fn flush(&self) {
+        let (done, written) = oneshot::channel();
+        self.send(|| TaskWrite::Flush(done));
+        let _ = written.await;
+    }
+
+    /// Task and result from the store, for tasks that are not held in memory
+    pub async fn load(&self, task_id: &str) -> McpResult<Option<(proto::TaskInfo, Option<proto::TaskResult>)>> {
+        let Some(store) = &self.store else {
+            return Ok(None);
+        };
+        let Some(task) = store.load_task(task_id).await? else {
+            return Ok(None);
+        };
+        Ok(Some((task, store.load_result(task_id).await?)))
+    }
+
+    fn send(&self, write: impl FnOnce() -> TaskWrite) {
+        if let Some(writes) = &self.writes {
+            if writes.send(write()).is_err() {
+                warn!("The task store writer has stopped");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn sqlite_config(dir: &tempfile::TempDir) -> TaskStoreConfig {
+        TaskStoreConfig {
+            url: Some(format!("sqlite://{}?mode=rwc", dir.path().join("tasks.db").display())),
+            ..Default::default()
+        }
+    }
+
+    // Test for storing tasks and results in SQLite
+    #[tokio::test]
+    async fn test_sql_task_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SqlTaskStore::connect(&sqlite_config(&dir)).await.unwrap();
+        let owner = UserInfo {
+            id: "user1".to_string(),
+            tenant_id: "tenant1".to_string(),
+            roles: vec![],
+            attributes: HashMap::new(),
+        };
+        let mut task = proto::TaskInfo {
+            task_id: "task-1".to_string(),
+            task_type: proto::TaskType::TaskCommand as i32,
+            status: proto::TaskStatus::TaskCreated as i32,
+            created_at: "2026-10-15T00:00:00+00:00".to_string(),
+            started_at: None,
+            completed_at: None,
+            metadata: HashMap::from([(METADATA_BREAK_GLASS.to_string(), "incident".to_string())]),
+        };
+        store.save_task(&task, Some(&owner)).await.unwrap();
+        task.status = proto::TaskStatus::TaskCompleted as i32;
+        task.completed_at = Some("2026-10-15T00:00:01+00:00".to_string());
+        store.save_task(&task, None).await.unwrap();
+        let result = proto::TaskResult {
+            exit_code: 3,
+            stdout: "out".to_string(),
+            resource_usage: Some(proto::ResourceUsage {
+                cpu_time_ms: 12,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        store.save_result("task-1", &result).await.unwrap();
+
+        assert_eq!(store.load_task("task-1").await.unwrap(), Some(task));
+        assert_eq!(store.load_result("task-1").await.unwrap(), Some(result));
+        assert_eq!(store.load_task("task-2").await.unwrap(), None);
+        assert_eq!(store.load_result("task-2").await.unwrap(), None);
+
+        // The owner recorded at creation is kept, the reporting columns follow the task
+        let row = sqlx::query(
+            "SELECT t.status, t.tenant_id, t.user_id, t.break_glass, r.exit_code, r.cpu_time_ms, r.max_memory_kb \
+             FROM tasks t JOIN task_results r ON r.task_id = t.task_id",
+        )
+        .fetch_one(&store.pool)
+        .await
+        .unwrap();
+        assert_eq!(row.get::<String, _>("status"), "TASK_COMPLETED");
+        assert_eq!(row.get::<String, _>("tenant_id"), "tenant1");
+        assert_eq!(row.get::<String, _>("user_id"), "user1");
+        assert_eq!(row.get::<String, _>("break_glass"), "incident");
+        assert_eq!(row.get::<i32, _>("exit_code"), 3);
+        assert_eq!(row.get::<i64, _>("cpu_time_ms"), 12);
+        assert_eq!(row.get::<i64, _>("max_memory_kb"), 0);
+
+        // The schema is migrated only once
+        let store = SqlTaskStore::connect(&sqlite_config(&dir)).await.unwrap();
+        assert!(store.load_task("task-1").await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,559 @@
+//! Tenant file roots of the file RPCs
+//!
+//! File requests never open the requested path on the host. Every tenant has a root directory
+//! `<MCP_TENANT_FILES_ROOT>/<tenant_id>` holding its files as a sandbox would see them: the
+//! path `/workspace/src/main.rs` is the file `<root>/<tenant_id>/workspace/src/main.rs`, and
+//! relative paths are resolved against the workspace mount point. Paths are canonicalized
+//! within the tenant root with a [`PathCanonicalizer`], so neither `..` nor symbolic links
+//! (absolute targets included) lead outside of it, and the policy is evaluated on the
+//! canonical path.
+//!
+//! Files beyond the gRPC message size limit are transferred in chunks by the streaming RPCs,
+//! each chunk carrying the SHA-256 digest of its content. Writes of either kind go to a
+//! temporary file first (see [`FileUpload`]).
+//!
+//! Without a configured root, file requests are rejected.
+
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{hex, parse_positive};
+use mcp_policy::PathCanonicalizer;
+use mcp_sandbox::workspace::WORKSPACE_MOUNT_POINT;
+use std::fs::{self, File, Metadata, OpenOptions, Permissions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use sha2::{Digest, Sha256};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::{FileExt, OpenOptionsExt, PermissionsExt};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Bytes returned by one read unless configured
+pub const DEFAULT_MAX_READ_BYTES: u64 = 4 * 1024 * 1024;
+/// Bytes accepted by one write unless configured
+pub const DEFAULT_MAX_WRITE_BYTES: u64 = 4 * 1024 * 1024;
+/// Bytes accepted by one streamed write unless configured
+pub const DEFAULT_MAX_STREAM_WRITE_BYTES: u64 = 1024 * 1024 * 1024;
+/// Chunk size of streamed reads unless configured or requested
+pub const DEFAULT_CHUNK_BYTES: u64 = 1024 * 1024;
+/// Largest chunk of a streamed read (below the default gRPC message size limit of 4 MiB)
+pub const MAX_CHUNK_BYTES: u64 = 3 * 1024 * 1024;
+/// Permission bits of written files unless requested
+pub const DEFAULT_FILE_MODE: u32 = 0o644;
+
+/// Sequence number making the names of temporary files unique within the process
+static TEMP_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Tenant file root settings
+#[derive(Debug, Clone)]
+pub struct TenantFilesConfig {
+    /// Directory holding the root of every tenant (`<root>/<tenant_id>`)
+    pub root: Option<PathBuf>,
+    /// Maximum number of bytes returned by one read
+    pub max_read_bytes: u64,
+    /// Maximum size of the content of one write
+    pub max_write_bytes: u64,
+    /// Maximum size of the content of one streamed write
+    pub max_stream_write_bytes: u64,
+    /// Chunk size of streamed reads
+    pub chunk_bytes: u64,
+}
+
+impl Default for TenantFilesConfig {
+    fn default() -> Self {
+        Self {
+            root: None,
+            max_read_bytes: DEFAULT_MAX_READ_BYTES,
+            max_write_bytes: DEFAULT_MAX_WRITE_BYTES,
+            max_stream_write_bytes: DEFAULT_MAX_STREAM_WRITE_BYTES,
+            chunk_bytes: DEFAULT_CHUNK_BYTES,
+        }
+    }
+}
+
+impl TenantFilesConfig {
+    /// Build the settings from environment variables
+    ///
+    /// * `MCP_TENANT_FILES_ROOT` - directory holding the tenant roots
+    /// * `MCP_FILE_READ_MAX_BYTES` - maximum number of bytes returned by one read
+    /// * `MCP_FILE_WRITE_MAX_BYTES` - maximum size of the content of one write
+    /// * `MCP_FILE_STREAM_WRITE_MAX_BYTES` - maximum size of the content of one streamed write
+    /// * `MCP_FILE_CHUNK_BYTES` - chunk size of streamed reads
+    pub fn from_env() -> McpResult<Self> {
+        let mut config = Self {
+            root: std::env::var("MCP_TENANT_FILES_ROOT").ok().map(PathBuf::from),
+            ..Self::default()
+        };
+
+        if let Ok(value) = std::env::var("MCP_FILE_READ_MAX_BYTES") {
+            config.max_read_bytes = parse_positive("MCP_FILE_READ_MAX_BYTES", &value)?;
+        }
+        if let Ok(value) = std::env::var("MCP_FILE_WRITE_MAX_BYTES") {
+            config.max_write_bytes = parse_positive("MCP_FILE_WRITE_MAX_BYTES", &value)?;
+        }
+        if let Ok(value) = std::env::var("MCP_FILE_STREAM_WRITE_MAX_BYTES") {
+            config.max_stream_write_bytes = parse_positive("MCP_FILE_STREAM_WRITE_MAX_BYTES", &value)?;
+        }
+        if let Ok(value) = std::env::var("MCP_FILE_CHUNK_BYTES") {
+            config.chunk_bytes = parse_positive("MCP_FILE_CHUNK_BYTES", &value)?;
+        }
+
+        Ok(config)
+    }
+}
+
+/// A request path resolved within the root of a tenant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantPath {
+    /// Canonical path as the tenant sees it
+    pub path: String,
+    /// Root of the tenant on the host
+    pub tenant_root: PathBuf,
+    /// The file on the host
+    pub host_path: PathBuf,
+}
+
+/// Part of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRange {
+    /// Bytes read
+    pub content: Vec<u8>,
+    /// Size of the whole file (bytes)
+    pub file_size: u64,
+}
+
+/// Entry of a directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryEntry {
+    /// File name
+    pub name: String,
+    /// Whether the entry is a directory
+    pub is_dir: bool,
+    /// Size (bytes; 0 unless a regular file)
+    pub size: u64,
+}
+
+/// Access to the files of tenants
+#[derive(Debug, Clone, Default)]
+pub struct TenantFiles {
+    config: TenantFilesConfig,
+}
+
+impl TenantFiles {
+    pub fn new(config: TenantFilesConfig) -> Self {
+        Self { config }
+    }
+
+    /// Resolve a request path within the root of a tenant
+    pub fn resolve(&self, tenant_id: &str, path: &str) -> McpResult<TenantPath> {
+        let Some(root) = &self.config.root else {
+            return Err(McpError::InvalidRequest(
+                "File access is not available: MCP_TENANT_FILES_ROOT is not set".to_string(),
+            ));
+        };
+        if tenant_id.is_empty() || tenant_id == "." || tenant_id == ".." || tenant_id.contains('/') {
+            return Err(McpError::InvalidRequest(format!("Invalid tenant ID: '{}'", tenant_id)));
+        }
+        if path.is_empty() {
+            return Err(McpError::InvalidRequest("File path is empty".to_string()));
+        }
+
+        let tenant_root = root.join(tenant_id);
+        let path = PathCanonicalizer::new()
+            .with_sandbox_root(&tenant_root)
+            .canonicalize(path, WORKSPACE_MOUNT_POINT)?;
+        let host_path = tenant_root.join(path.trim_start_matches('/'));
+        Ok(TenantPath { path, tenant_root, host_path })
+    }
+
+    /// Open a regular file for reading, with its metadata
+    pub fn open(&self, file: &TenantPath) -> McpResult<(File, Metadata)> {
+        let handle = open_within_root(file, "File")?;
+        let metadata = handle.metadata()?;
+        if !metadata.is_file() {
+            return Err(McpError::InvalidRequest(format!("'{}' is not a regular file", file.path)));
+        }
+        Ok((handle, metadata))
+    }
+
+    /// List the entries of a directory, sorted by name. Symbolic links are listed as they are,
+    /// without following them
+    pub fn list(&self, dir: &TenantPath) -> McpResult<Vec<DirectoryEntry>> {
+        let handle = open_within_root(dir, "Directory")?;
+        if !handle.metadata()?.is_dir() {
+            return Err(McpError::InvalidRequest(format!("'{}' is not a directory", dir.path)));
+        }
+
+        // Read the opened directory, not whatever the path names by now
+        let mut entries = fs::read_dir(fd_path(&handle))?
+            .map(|entry| {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                Ok(DirectoryEntry {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    is_dir: metadata.is_dir(),
+                    size: if metadata.is_file() { metadata.len() } else { 0 },
+                })
+            })
+            .collect::<McpResult<Vec<_>>>()?;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    /// Read at most `length` bytes from `offset` (to the end of the file if unset), capped by
+    /// the configured maximum
+    pub fn read(&self, file: &TenantPath, offset: u64, length: Option<u64>) -> McpResult<FileRange> {
+        let (mut handle, metadata) = self.open(file)?;
+        let length = length.unwrap_or(u64::MAX).min(self.config.max_read_bytes);
+        let mut content = Vec::new();
+        handle.seek(SeekFrom::Start(offset))?;
+        handle.take(length).read_to_end(&mut content)?;
+        Ok(FileRange {
+            content,
+            file_size: metadata.len(),
+        })
+    }
+
+    /// Chunk size of a streamed read: the requested size (the configured size if 0), capped at
+    /// [`MAX_CHUNK_BYTES`]
+    pub fn chunk_size(&self, requested: u32) -> u64 {
+        let size = if requested == 0 { self.config.chunk_bytes } else { u64::from(requested) };
+        size.min(MAX_CHUNK_BYTES)
+    }
+
+    /// Check the size of content to write and the requested permission bits (0 for the default)
+    pub fn validate_write(&self, size: u64, mode: u32) -> McpResult<()> {
+        if size > self.config.max_write_bytes {
+            return Err(McpError::InvalidRequest(format!(
+                "Content of {} bytes exceeds the maximum of {} bytes",
+                size, self.config.max_write_bytes
+            )));
+        }
+        // setuid, setgid and sticky bits cannot be requested
+        if mode & !0o777 != 0 {
+            return Err(McpError::InvalidRequest(format!("Invalid file mode: {:o}", mode)));
+        }
+        Ok(())
+    }
+
+    /// Write a file atomically (see [`FileUpload`]). Returns the metadata of the written file
+    pub fn write(&self, file: &TenantPath, content: &[u8], mode: u32, create_dirs: bool) -> McpResult<Metadata> {
+        self.validate_write(content.len() as u64, mode)?;
+        let mut upload = self.begin_upload(file, mode, create_dirs)?;
+        upload.append(0, content)?;
+        upload.commit()
+    }
+
+    /// Start writing a file in chunks. Missing parent directories are created only with
+    /// `create_dirs`; `mode` holds the permission bits (0 for the default)
+    pub fn begin_upload(&self, file: &TenantPath, mode: u32, create_dirs: bool) -> McpResult<FileUpload> {
+        self.validate_write(0, mode)?;
+        let mode = if mode == 0 { DEFAULT_FILE_MODE } else { mode };
+
+        let (Some(parent), Some(name)) = (file.host_path.parent(), file.host_path.file_name()) else {
+            return Err(McpError::InvalidRequest(format!("'{}' is not a regular file", file.path)));
+        };
+        if create_dirs {
+            fs::create_dir_all(parent)?;
+        } else if !parent.is_dir() {
+            return Err(McpError::NotFound(format!("Parent directory of '{}' does not exist", file.path)));
+        }
+
+        // A directory may have been replaced by a link after the path was resolved
+        if !parent.canonicalize()?.starts_with(file.tenant_root.canonicalize()?) {
+            return Err(McpError::PolicyViolation(format!(
+                "'{}' resolves outside of the tenant root",
+                file.path
+            )));
+        }
+        if file.host_path.is_dir() {
+            return Err(McpError::InvalidRequest(format!("'{}' is a directory", file.path)));
+        }
+
+        let temp_path = parent.join(format!(
+            ".{}.{}-{}.tmp",
+            name.to_string_lossy(),
+            std::process::id(),
+            TEMP_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temp = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&temp_path)?;
+        Ok(FileUpload {
+            path: file.path.clone(),
+            host_path: file.host_path.clone(),
+            temp_path,
+            temp,
+            mode,
+            written: 0,
+            max_bytes: self.config.max_stream_write_bytes,
+            head_bytes: self.config.max_write_bytes,
+            committed: false,
+        })
+    }
+}
+
+/// File being written: the content goes to a temporary file in the same directory, which
+/// replaces the file on commit, so readers never see a partially written file. The temporary
+/// file is removed if the upload is dropped without being committed.
+#[derive(Debug)]
+pub struct FileUpload {
+    path: String,
+    host_path: PathBuf,
+    temp_path: PathBuf,
+    temp: File,
+    mode: u32,
+    written: u64,
+    max_bytes: u64,
+    head_bytes: u64,
+    committed: bool,
+}
+
+impl FileUpload {
+    /// Number of bytes written so far
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Append a chunk, which must start at the end of the content written so far
+    pub fn append(&mut self, offset: u64, data: &[u8]) -> McpResult<()> {
+        if offset != self.written {
+            return Err(McpError::InvalidRequest(format!(
+                "Chunk at offset {} of '{}' does not follow the {} bytes written",
+                offset, self.path, self.written
+            )));
+        }
+        if self.written + data.len() as u64 > self.max_bytes {
+            return Err(McpError::InvalidRequest(format!(
+                "Content of '{}' exceeds the maximum of {} bytes",
+                self.path, self.max_bytes
+            )));
+        }
+        self.temp.write_all(data)?;
+        self.written += data.len() as u64;
+        Ok(())
+    }
+
+    /// Beginning of the content written so far, as much as a single write may hold (for
+    /// content scanning)
+    pub fn head(&self) -> McpResult<Vec<u8>> {
+        let mut head = vec![0; self.written.min(self.head_bytes) as usize];
+        self.temp.read_exact_at(&mut head, 0)?;
+        Ok(head)
+    }
+
+    /// Replace the file with the content written, returning the metadata of the file
+    pub fn commit(mut self) -> McpResult<Metadata> {
+        // The permission bits are set regardless of the umask
+        self.temp.set_permissions(Permissions::from_mode(self.mode))?;
+        self.temp.sync_all()?;
+        fs::rename(&self.temp_path, &self.host_path)?;
+        self.committed = true;
+        Ok(fs::metadata(&self.host_path)?)
+    }
+}
+
+impl Drop for FileUpload {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// Open `file` and check that what was opened lies within the tenant root
+///
+/// The path may have been replaced by a link after it was resolved, and again between opening
+/// and checking it, so the opened file is checked rather than the path.
+fn open_within_root(file: &TenantPath, kind: &str) -> McpResult<File> {
+    let handle = File::open(&file.host_path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => McpError::NotFound(format!("{} not found: '{}'", kind, file.path)),
+        _ => McpError::from(e),
+    })?;
+    let opened = fs::read_link(fd_path(&handle))?;
+    if !opened.starts_with(file.tenant_root.canonicalize()?) {
+        return Err(McpError::PolicyViolation(format!("'{}' resolves outside of the tenant root", file.path)));
+    }
+    Ok(handle)
+}
+
+/// Path naming an open file through procfs
+fn fd_path(handle: &File) -> PathBuf {
+    PathBuf::from(format!("/proc/self/fd/{}", handle.as_raw_fd()))
+}
+
+/// SHA-256 digest of a chunk (hex)
+pub fn chunk_digest(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+/// MIME type of file content: text if it is UTF-8 (possibly cut within the last character)
+pub fn mime_type(content: &[u8]) -> &'static str {
+    match std::str::from_utf8(content) {
+        Ok(_) => "text/plain; charset=utf-8",
+        Err(e) if e.error_len().is_none() => "text/plain; charset=utf-8",
+        Err(_) => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    fn tenant_files(root: &tempfile::TempDir, max_bytes: u64) -> TenantFiles {
+        TenantFiles::new(TenantFilesConfig {
+            root: Some(root.path().to_path_buf()),
+            max_read_bytes: max_bytes,
+            max_write_bytes: max_bytes,
+            ..Default::default()
+        })
+    }
+
+    // Test for resolving request paths within the tenant root
+    #[test]
+    fn test_resolve() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("tenant1/workspace")).unwrap();
+        symlink("/etc/passwd", root.path().join("tenant1/workspace/passwd")).unwrap();
+        let files = tenant_files(&root, 1024);
+
+        let file = files.resolve("tenant1", "src/main.rs").unwrap();
+        assert_eq!(file.path, "/workspace/src/main.rs");
+        assert_eq!(file.host_path, root.path().join("tenant1/workspace/src/main.rs"));
+        assert_eq!(files.resolve("tenant1", "../../../etc/hosts").unwrap().path, "/etc/hosts");
+        // Links are resolved within the tenant root
+        let file = files.resolve("tenant1", "/workspace/passwd").unwrap();
+        assert_eq!(file.path, "/etc/passwd");
+        assert_eq!(file.host_path, root.path().join("tenant1/etc/passwd"));
+
+        assert!(files.resolve("..", "/etc/passwd").is_err());
+        assert!(files.resolve("tenant1", "").is_err());
+        assert!(TenantFiles::default().resolve("tenant1", "/workspace/a.txt").is_err());
+    }
+
+    // Test for partial reads
+    #[test]
+    fn test_read() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("tenant1/workspace/dir")).unwrap();
+        std::fs::write(root.path().join("tenant1/workspace/a.txt"), "hello world").unwrap();
+        let files = tenant_files(&root, 8);
+        let file = files.resolve("tenant1", "a.txt").unwrap();
+
+        let range = files.read(&file, 0, None).unwrap();
+        assert_eq!(range.content, b"hello wo");
+        assert_eq!(range.file_size, 11);
+        assert_eq!(files.read(&file, 6, Some(3)).unwrap().content, b"wor");
+        assert!(files.read(&file, 20, Some(3)).unwrap().content.is_empty());
+
+        let missing = files.resolve("tenant1", "b.txt").unwrap();
+        assert!(matches!(files.read(&missing, 0, None), Err(McpError::NotFound(_))));
+        let dir = files.resolve("tenant1", "dir").unwrap();
+        assert!(matches!(files.read(&dir, 0, None), Err(McpError::InvalidRequest(_))));
+
+        // A file replaced by a link to outside of the root after resolving is not read
+        std::fs::write(root.path().join("secret.txt"), "secret").unwrap();
+        std::fs::remove_file(root.path().join("tenant1/workspace/a.txt")).unwrap();
+        symlink(root.path().join("secret.txt"), root.path().join("tenant1/workspace/a.txt")).unwrap();
+        assert!(matches!(files.read(&file, 0, None), Err(McpError::PolicyViolation(_))));
+
+        assert_eq!(mime_type(b"hello"), "text/plain; charset=utf-8");
+        assert_eq!(mime_type(&"\u{3042}".as_bytes()[..2]), "text/plain; charset=utf-8");
+        assert_eq!(mime_type(&[0xff, 0x00]), "application/octet-stream");
+    }
+
+    // Test for listing directories
+    #[test]
+    fn test_list() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("tenant1/workspace/dir")).unwrap();
+        std::fs::write(root.path().join("tenant1/workspace/b.txt"), "hello").unwrap();
+        symlink("/etc", root.path().join("tenant1/workspace/link")).unwrap();
+        let files = tenant_files(&root, 1024);
+
+        let entry = |name: &str, is_dir, size| DirectoryEntry { name: name.to_string(), is_dir, size };
+        let workspace = files.resolve("tenant1", ".").unwrap();
+        assert_eq!(
+            files.list(&workspace).unwrap(),
+            [entry("b.txt", false, 5), entry("dir", true, 0), entry("link", false, 0)]
+        );
+        assert!(files.list(&files.resolve("tenant1", "dir").unwrap()).unwrap().is_empty());
+
+        let missing = files.resolve("tenant1", "missing").unwrap();
+        assert!(matches!(files.list(&missing), Err(McpError::NotFound(_))));
+        let file = files.resolve("tenant1", "b.txt").unwrap();
+        assert!(matches!(files.list(&file), Err(McpError::InvalidRequest(_))));
+    }
+
+    // Test for atomic writes
+    #[test]
+    fn test_write() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("tenant1/workspace/dir")).unwrap();
+        std::fs::create_dir_all(root.path().join("outside")).unwrap();
+        symlink(root.path().join("outside"), root.path().join("tenant1/workspace/link")).unwrap();
+        let files = tenant_files(&root, 8);
+
+        let file = files.resolve("tenant1", "a.txt").unwrap();
+        let metadata = files.write(&file, b"hello", 0o640, false).unwrap();
+        assert_eq!(metadata.len(), 5);
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+        assert_eq!(files.read(&file, 0, None).unwrap().content, b"hello");
+
+        // The absolute link target is interpreted within the tenant root
+        let file = files.resolve("tenant1", "link/a.txt").unwrap();
+        assert_eq!(file.path, format!("{}/outside/a.txt", root.path().display()));
+        assert!(files.write(&file, b"x", 0, false).is_err());
+        assert!(!root.path().join("outside/a.txt").exists());
+
+        let dir = files.resolve("tenant1", "dir").unwrap();
+        assert!(matches!(files.write(&dir, b"x", 0, false), Err(McpError::InvalidRequest(_))));
+        assert!(matches!(files.write(&file, b"too large", 0, true), Err(McpError::InvalidRequest(_))));
+        assert!(matches!(files.write(&file, b"x", 0o1777, true), Err(McpError::InvalidRequest(_))));
+    }
+
+    // Test for writing a file in chunks
+    #[test]
+    fn test_upload() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("tenant1/workspace")).unwrap();
+        let files = TenantFiles::new(TenantFilesConfig {
+            root: Some(root.path().to_path_buf()),
+            max_write_bytes: 4,
+            max_stream_write_bytes: 16,
+            ..Default::default()
+        });
+        let file = files.resolve("tenant1", "a.txt").unwrap();
+        let workspace_entries = || std::fs::read_dir(root.path().join("tenant1/workspace")).unwrap().count();
+
+        let mut upload = files.begin_upload(&file, 0, false).unwrap();
+        upload.append(0, b"hello ").unwrap();
+        // Chunks must follow each other
+        assert!(upload.append(0, b"hello ").is_err());
+        assert!(upload.append(7, b"world").is_err());
+        upload.append(6, b"world").unwrap();
+        assert_eq!(upload.written(), 11);
+        // The head is limited to the size of a single write
+        assert_eq!(upload.head().unwrap(), b"hell");
+        assert!(!file.host_path.exists());
+        let metadata = upload.commit().unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, DEFAULT_FILE_MODE);
+        assert_eq!(std::fs::read(&file.host_path).unwrap(), b"hello world");
+        assert_eq!(workspace_entries(), 1);
+
+        // The temporary file is removed when the upload is abandoned
+        let mut upload = files.begin_upload(&file, 0, false).unwrap();
+        assert!(upload.append(0, &[b'x'; 17]).is_err());
+        assert_eq!(workspace_entries(), 2);
+        drop(upload);
+        assert_eq!(workspace_entries(), 1);
+        assert_eq!(std::fs::read(&file.host_path).unwrap(), b"hello world");
+
+        assert_eq!(files.chunk_size(0), DEFAULT_CHUNK_BYTES);
+        assert_eq!(files.chunk_size(10), 10);
+        assert_eq!(files.chunk_size(u32::MAX), MAX_CHUNK_BYTES);
+        assert_eq!(chunk_digest(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+}
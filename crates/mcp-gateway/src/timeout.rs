@@ -0,0 +1,273 @@
+//! Timeout hierarchy for command tasks
+//!
+//! The effective timeout of a task is resolved at creation time with the precedence
+//! `request timeout ≤ policy limit ≤ tool limit ≤ tenant limit ≤ global max`, where the
+//! policy limit is the maximum of the command's policy limits. The requested value is
+//! never trusted directly: it is clamped by every applicable limit, and the limit that
+//! actually bounded the value is recorded as the clamping source.
+
+use mcp_common::error::McpResult;
+use mcp_common::utils::{parse_key_value_pairs, parse_positive};
+use mcp_policy::CommandLimits;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Task metadata key holding the effective timeout (seconds)
+pub const METADATA_EFFECTIVE_TIMEOUT: &str = "effective_timeout_secs";
+/// Task metadata key holding the timeout clamping source
+pub const METADATA_TIMEOUT_SOURCE: &str = "timeout_source";
+/// Task metadata key holding the originally requested timeout (seconds)
+pub const METADATA_REQUESTED_TIMEOUT: &str = "requested_timeout_secs";
+
+/// Where the effective timeout came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutSource {
+    /// The requested value was within all limits
+    Request,
+    /// No timeout was requested, the default was used
+    Default,
+    /// Clamped by the maximum of the command's policy limits
+    Policy,
+    /// Clamped by the per-tool limit
+    Tool,
+    /// Clamped by the per-tenant limit
+    Tenant,
+    /// Clamped by the global maximum
+    Global,
+}
+
+impl TimeoutSource {
+    /// Label used in task metadata
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeoutSource::Request => "request",
+            TimeoutSource::Default => "default",
+            TimeoutSource::Policy => "policy",
+            TimeoutSource::Tool => "tool",
+            TimeoutSource::Tenant => "tenant",
+            TimeoutSource::Global => "global",
+        }
+    }
+}
+
+impl fmt::Display for TimeoutSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Resolved timeout for a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EffectiveTimeout {
+    /// Effective timeout (seconds)
+    pub secs: u32,
+    /// Clamping source
+    pub source: TimeoutSource,
+}
+
+impl EffectiveTimeout {
+    /// Record the effective timeout in task metadata
+    pub fn record(&self, requested: u32, metadata: &mut HashMap<String, String>) {
+        metadata.insert(METADATA_EFFECTIVE_TIMEOUT.to_string(), self.secs.to_string());
+        metadata.insert(METADATA_TIMEOUT_SOURCE.to_string(), self.source.to_string());
+        metadata.insert(METADATA_REQUESTED_TIMEOUT.to_string(), requested.to_string());
+    }
+}
+
+/// Timeout limits applied at task creation
+#[derive(Debug, Clone)]
+pub struct TimeoutPolicy {
+    /// Timeout used when the request does not specify one (seconds)
+    pub default_secs: u32,
+    /// Upper bound for every task (seconds)
+    pub global_max_secs: u32,
+    /// Per-tenant upper bounds (seconds)
+    pub tenant_limits: HashMap<String, u32>,
+    /// Per-tool (command name without directory) upper bounds (seconds)
+    pub tool_limits: HashMap<String, u32>,
+}
+
+impl Default for TimeoutPolicy {
+    fn default() -> Self {
+        Self {
+            default_secs: 30,
+            global_max_secs: 3600,
+            tenant_limits: HashMap::new(),
+            tool_limits: HashMap::new(),
+        }
+    }
+}
+
+impl TimeoutPolicy {
+    /// Build the policy from environment variables
+    ///
+    /// * `MCP_DEFAULT_TIMEOUT_SECS` - default timeout
+    /// * `MCP_MAX_TIMEOUT_SECS` - global maximum
+    /// * `MCP_TENANT_TIMEOUTS` - per-tenant limits (`tenant1=300,tenant2=60`)
+    /// * `MCP_TOOL_TIMEOUTS` - per-tool limits (`npm=600,python=120`)
+    pub fn from_env() -> McpResult<Self> {
+        let mut policy = Self::default();
+
+        if let Ok(value) = std::env::var("MCP_DEFAULT_TIMEOUT_SECS") {
+            policy.default_secs = parse_positive("MCP_DEFAULT_TIMEOUT_SECS", &value)?;
+        }
+        if let Ok(value) = std::env::var("MCP_MAX_TIMEOUT_SECS") {
+            policy.global_max_secs = parse_positive("MCP_MAX_TIMEOUT_SECS", &value)?;
+        }
+        if let Ok(value) = std::env::var("MCP_TENANT_TIMEOUTS") {
+            policy.tenant_limits = parse_limits("MCP_TENANT_TIMEOUTS", &value)?;
+        }
+        if let Ok(value) = std::env::var("MCP_TOOL_TIMEOUTS") {
+            policy.tool_limits = parse_limits("MCP_TOOL_TIMEOUTS", &value)?;
+        }
+
+        Ok(policy)
+    }
+
+    /// Resolve the effective timeout for a task
+    ///
+    /// `command` is the command that actually runs (see
+    /// [`PolicyEngine::effective_command_name`](mcp_policy::PolicyEngine::effective_command_name));
+    /// its tool limit applies whatever directory it is invoked from. A requested value of `0`
+    /// means "not specified" and falls back to the default, which is subject to the same limits.
+    pub fn resolve(&self, tenant_id: &str, command: &str, requested: u32) -> EffectiveTimeout {
+        self.resolve_with_limits(tenant_id, command, requested, None)
+    }
+
+    /// Resolve the effective timeout for a task within the limits the policy set for the command
+    ///
+    /// The policy default replaces the configured default; the policy maximum is the most
+    /// specific limit.
+    pub fn resolve_with_limits(
+        &self,
+        tenant_id: &str,
+        command: &str,
+        requested: u32,
+        command_limits: Option<&CommandLimits>,
+    ) -> EffectiveTimeout {
+        let command_limits = command_limits.cloned().unwrap_or_default();
+        let (mut secs, mut source) = if requested > 0 {
+            (requested, TimeoutSource::Request)
+        } else {
+            let default_secs = command_limits.default_timeout_secs.filter(|secs| *secs > 0);
+            (default_secs.unwrap_or(self.default_secs), TimeoutSource::Default)
+        };
+
+        // Apply the most specific limit first so that ties are attributed to it
+        let tool = command.rsplit('/').next().unwrap_or_default();
+        let limits = [
+            (command_limits.max_timeout_secs, TimeoutSource::Policy),
+            (self.tool_limits.get(tool).copied(), TimeoutSource::Tool),
+            (self.tenant_limits.get(tenant_id).copied(), TimeoutSource::Tenant),
+            (Some(self.global_max_secs), TimeoutSource::Global),
+        ];
+
+        for (limit, limit_source) in limits {
+            if let Some(limit) = limit {
+                if secs > limit {
+                    secs = limit;
+                    source = limit_source;
+                }
+            }
+        }
+
+        EffectiveTimeout { secs, source }
+    }
+}
+
+fn parse_limits(name: &str, value: &str) -> McpResult<HashMap<String, u32>> {
+    parse_key_value_pairs(value)?
+        .into_iter()
+        .map(|(key, secs)| Ok((key, parse_positive(name, &secs)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> TimeoutPolicy {
+        TimeoutPolicy {
+            default_secs: 30,
+            global_max_secs: 600,
+            tenant_limits: HashMap::from([("tenant1".to_string(), 300)]),
+            tool_limits: HashMap::from([("npm".to_string(), 120), ("make".to_string(), 900)]),
+        }
+    }
+
+    #[test]
+    fn test_request_within_limits() {
+        let timeout = policy().resolve("tenant1", "ls", 10);
+        assert_eq!(timeout, EffectiveTimeout { secs: 10, source: TimeoutSource::Request });
+    }
+
+    #[test]
+    fn test_default_when_not_requested() {
+        let timeout = policy().resolve("tenant1", "ls", 0);
+        assert_eq!(timeout, EffectiveTimeout { secs: 30, source: TimeoutSource::Default });
+    }
+
+    #[test]
+    fn test_clamp_order() {
+        let policy = policy();
+
+        // Tool limit is the tightest
+        let timeout = policy.resolve("tenant1", "npm", 1000);
+        assert_eq!(timeout, EffectiveTimeout { secs: 120, source: TimeoutSource::Tool });
+
+        // The tool limit applies to the command in any directory
+        let timeout = policy.resolve("tenant1", "/usr/bin/npm", 1000);
+        assert_eq!(timeout, EffectiveTimeout { secs: 120, source: TimeoutSource::Tool });
+
+        // Tool limit above the tenant limit is clamped by the tenant
+        let timeout = policy.resolve("tenant1", "make", 1000);
+        assert_eq!(timeout, EffectiveTimeout { secs: 300, source: TimeoutSource::Tenant });
+
+        // Unknown tenant falls back to the global maximum
+        let timeout = policy.resolve("tenant2", "ls", 1000);
+        assert_eq!(timeout, EffectiveTimeout { secs: 600, source: TimeoutSource::Global });
+    }
+
+    #[test]
+    fn test_policy_limits() {
+        let policy = policy();
+        let limits = CommandLimits {
+            default_timeout_secs: Some(90),
+            max_timeout_secs: Some(100),
+            ..Default::default()
+        };
+
+        let timeout = policy.resolve_with_limits("tenant1", "npm", 0, Some(&limits));
+        assert_eq!(timeout, EffectiveTimeout { secs: 90, source: TimeoutSource::Default });
+        let timeout = policy.resolve_with_limits("tenant1", "npm", 1000, Some(&limits));
+        assert_eq!(timeout, EffectiveTimeout { secs: 100, source: TimeoutSource::Policy });
+
+        // The policy cannot raise the configured limits
+        let limits = CommandLimits {
+            default_timeout_secs: Some(900),
+            ..Default::default()
+        };
+        let timeout = policy.resolve_with_limits("tenant1", "ls", 0, Some(&limits));
+        assert_eq!(timeout, EffectiveTimeout { secs: 300, source: TimeoutSource::Tenant });
+    }
+
+    #[test]
+    fn test_record_metadata() {
+        let mut metadata = HashMap::new();
+        policy().resolve("tenant1", "npm", 1000).record(1000, &mut metadata);
+
+        assert_eq!(metadata[METADATA_EFFECTIVE_TIMEOUT], "120");
+        assert_eq!(metadata[METADATA_TIMEOUT_SOURCE], "tool");
+        assert_eq!(metadata[METADATA_REQUESTED_TIMEOUT], "1000");
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let limits = parse_limits("MCP_TOOL_TIMEOUTS", "npm=600,python=120").unwrap();
+        assert_eq!(limits["npm"], 600);
+        assert_eq!(limits["python"], 120);
+
+        assert!(parse_limits("MCP_TOOL_TIMEOUTS", "npm=0").is_err());
+        assert!(parse_limits("MCP_TOOL_TIMEOUTS", "npm=abc").is_err());
+    }
+}
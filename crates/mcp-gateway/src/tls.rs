@@ -0,0 +1,414 @@
+//! TLS for the gRPC and metrics servers
+//!
+//! Both servers serve plaintext unless a certificate and key are configured. They are given as
+//! PEM files (`MCP_TLS_CERT_FILE`, `MCP_TLS_KEY_FILE`) or as PEM text (`MCP_TLS_CERT_PEM`,
+//! `MCP_TLS_KEY_PEM`, e.g. from a Kubernetes secret). The certificate is resolved on every
+//! handshake, so when `MCP_TLS_RELOAD_SECS` is set the files are checked for changes at that
+//! interval and renewed certificates are served to new connections without a restart. A
+//! certificate that fails to load is logged and the previous one stays in use.
+
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
+use rustls_pemfile::Item;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::parse_positive;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::{self, CertifiedKey};
+use tokio_rustls::rustls::{Certificate, PrivateKey, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tracing::{debug, info, warn};
+
+/// Time a client has to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause before accepting again after a failed accept (e.g. out of file descriptors)
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Where a PEM document comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PemSource {
+    /// PEM file, read again on reload
+    File(PathBuf),
+    /// PEM text
+    Inline(String),
+}
+
+impl PemSource {
+    fn read(&self) -> McpResult<Vec<u8>> {
+        match self {
+            Self::File(path) => std::fs::read(path)
+                .map_err(|e| McpError::Internal(format!("Failed to read {}: {}", path.display(), e))),
+            Self::Inline(pem) => Ok(pem.clone().into_bytes()),
+        }
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        match self {
+            Self::File(path) => std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok(),
+            Self::Inline(_) => None,
+        }
+    }
+}
+
+/// TLS settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// Certificate chain, leaf first
+    pub cert: PemSource,
+    /// Private key of the leaf certificate (PKCS#8, PKCS#1 or SEC1)
+    pub key: PemSource,
+    /// Interval at which certificate files are checked for changes (no reload if `None`)
+    pub reload_interval: Option<Duration>,
+}
+
+impl TlsConfig {
+    /// Settings for a certificate and key in PEM files
+    pub fn from_files(cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        Self {
+            cert: PemSource::File(cert.into()),
+            key: PemSource::File(key.into()),
+            reload_interval: None,
+        }
+    }
+
+    /// Settings for a certificate and key given as PEM text
+    pub fn from_pem(cert: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            cert: PemSource::Inline(cert.into()),
+            key: PemSource::Inline(key.into()),
+            reload_interval: None,
+        }
+    }
+
+    /// Check the certificate files for changes at `interval`
+    pub fn with_reload_interval(mut self, interval: Duration) -> Self {
+        self.reload_interval = Some(interval);
+        self
+    }
+
+    /// Build the settings from environment variables (`None` serves plaintext)
+    ///
+    /// * `MCP_TLS_CERT_FILE` / `MCP_TLS_KEY_FILE` - PEM files of the certificate chain and key
+    /// * `MCP_TLS_CERT_PEM` / `MCP_TLS_KEY_PEM` - the same as PEM text, used when no file is set
+    /// * `MCP_TLS_RELOAD_SECS` - interval at which the files are checked for changes
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let cert = pem_source_from_env("MCP_TLS_CERT_FILE", "MCP_TLS_CERT_PEM");
+        let key = pem_source_from_env("MCP_TLS_KEY_FILE", "MCP_TLS_KEY_PEM");
+        let mut config = match (cert, key) {
+            (Some(cert), Some(key)) => Self {
+                cert,
+                key,
+                reload_interval: None,
+            },
+            (None, None) => return Ok(None),
+            _ => {
+                return Err(McpError::InvalidRequest(
+                    "TLS needs both a certificate and a key (MCP_TLS_CERT_* and MCP_TLS_KEY_*)".to_string(),
+                ))
+            }
+        };
+        if let Ok(value) = std::env::var("MCP_TLS_RELOAD_SECS") {
+            config.reload_interval = Some(Duration::from_secs(parse_positive("MCP_TLS_RELOAD_SECS", &value)?));
+        }
+        Ok(Some(config))
+    }
+}
+
+fn pem_source_from_env(file_var: &str, pem_var: &str) -> Option<PemSource> {
+    let non_empty = |name: &str| std::env::var(name).ok().filter(|value| !value.trim().is_empty());
+    non_empty(file_var)
+        .map(|path| PemSource::File(PathBuf::from(path.trim())))
+        .or_else(|| non_empty(pem_var).map(PemSource::Inline))
+}
+
+/// Certificate served by both servers, replaced when the files change
+pub struct TlsCertificate {
+    config: TlsConfig,
+    current: RwLock<Arc<CertifiedKey>>,
+    loaded_from: RwLock<(Option<SystemTime>, Option<SystemTime>)>,
+}
+
+impl TlsCertificate {
+    /// Load the certificate and key, and watch the files if a reload interval is set
+    pub fn load(config: TlsConfig) -> McpResult<Arc<Self>> {
+        let loaded_from = (config.cert.modified(), config.key.modified());
+        let certificate = Arc::new(Self {
+            current: RwLock::new(Arc::new(load_certified_key(&config)?)),
+            loaded_from: RwLock::new(loaded_from),
+            config,
+        });
+        if let Some(interval) = certificate.config.reload_interval {
+            let watched = Arc::downgrade(&certificate);
+            tokio::spawn(async move {
+                let mut ticks = tokio::time::interval(interval);
+                ticks.tick().await;
+                loop {
+                    ticks.tick().await;
+                    let Some(certificate) = watched.upgrade() else {
+                        return;
+                    };
+                    if let Err(e) = certificate.reload_if_changed() {
+                        warn!("Keeping the current TLS certificate: {}", e);
+                    }
+                }
+            });
+        }
+        Ok(certificate)
+    }
+
+    /// Load the certificate again if one of the files changed, returning whether it was replaced
+    pub fn reload_if_changed(&self) -> McpResult<bool> {
+        let modified = (self.config.cert.modified(), self.config.key.modified());
+        if *self.loaded_from.read().unwrap() == modified {
+            return Ok(false);
+        }
+        // Remember the change even if loading fails, so a broken file is reported once
+        *self.loaded_from.write().unwrap() = modified;
+        let certified_key = load_certified_key(&self.config)?;
+        *self.current.write().unwrap() = Arc::new(certified_key);
+        info!("Reloaded the TLS certificate");
+        Ok(true)
+    }
+
+    /// Certificate chain currently served, in DER
+    pub fn chain(&self) -> Vec<Vec<u8>> {
+        self.current.read().unwrap().cert.iter().map(|cert| cert.0.clone()).collect()
+    }
+
+    /// rustls configuration serving this certificate with the given ALPN protocols
+    pub fn server_config(self: &Arc<Self>, alpn_protocols: &[&[u8]]) -> Arc<ServerConfig> {
+        let mut config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        config.alpn_protocols = alpn_protocols.iter().map(|protocol| protocol.to_vec()).collect();
+        Arc::new(config)
+    }
+}
+
+impl ResolvesServerCert for TlsCertificate {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().unwrap().clone())
+    }
+}
+
+fn load_certified_key(config: &TlsConfig) -> McpResult<CertifiedKey> {
+    let cert_pem = config.cert.read()?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(cert_pem.as_slice()))
+        .map_err(|e| McpError::InvalidRequest(format!("Invalid TLS certificate: {}", e)))?;
+    if certs.is_empty() {
+        return Err(McpError::InvalidRequest("No certificate found in the TLS certificate PEM".to_string()));
+    }
+
+    let key_pem = config.key.read()?;
+    let mut reader = BufReader::new(key_pem.as_slice());
+    let key = loop {
+        match rustls_pemfile::read_one(&mut reader) {
+            Ok(Some(Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key))) => break key,
+            Ok(Some(_)) => continue,
+            Ok(None) => {
+                return Err(McpError::InvalidRequest("No private key found in the TLS key PEM".to_string()))
+            }
+            Err(e) => return Err(McpError::InvalidRequest(format!("Invalid TLS key: {}", e))),
+        }
+    };
+    let signing_key = sign::any_supported_type(&PrivateKey(key))
+        .map_err(|e| McpError::InvalidRequest(format!("Unsupported TLS key: {}", e)))?;
+
+    Ok(CertifiedKey::new(certs.into_iter().map(Certificate).collect(), signing_key))
+}
+
+/// TLS connections accepted on `listener`, for `Server::serve_with_incoming_shutdown`
+///
+/// Handshakes run concurrently so that a slow client does not hold up the others. Failed
+/// handshakes are dropped, failed accepts are retried after a short pause, and
+/// accepting stops when the stream is dropped.
+pub fn incoming(
+    listener: TcpListener,
+    config: Arc<ServerConfig>,
+) -> ReceiverStream<std::io::Result<TlsStream<TcpStream>>> {
+    let (sender, receiver) = tokio::sync::mpsc::channel(32);
+    let acceptor = TlsAcceptor::from(config);
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        // Errors such as EMFILE persist for a while, so do not retry at once
+                        warn!("Failed to accept a connection: {}", e);
+                        tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                        continue;
+                    }
+                },
+                _ = sender.closed() => return,
+            };
+            let acceptor = acceptor.clone();
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => {
+                        let _ = sender.send(Ok(stream)).await;
+                    }
+                    Ok(Err(e)) => debug!("TLS handshake with {} failed: {}", peer, e),
+                    Err(_) => debug!("TLS handshake with {} timed out", peer),
+                }
+            });
+        }
+    });
+    ReceiverStream::new(receiver)
+}
+
+/// Serve `app` over HTTPS (HTTP/1.1 and HTTP/2, with WebSocket upgrades) on `listener`
+pub async fn serve_router(listener: TcpListener, config: Arc<ServerConfig>, app: Router) {
+    let mut connections = incoming(listener, config);
+    while let Some(Ok(stream)) = connections.next().await {
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            let builder = auto::Builder::new(TokioExecutor::new());
+            if let Err(e) = builder.serve_connection_with_upgrades(TokioIo::new(stream), service).await {
+                debug!("HTTPS connection failed: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::mcp_service_server::McpServiceServer;
+    use crate::proto::{McpServiceClient, TaskStatusRequest};
+    use crate::McpServiceImpl;
+    use mcp_policy::PolicyEngine;
+    use mcp_sandbox::CommandExecutor;
+    use tonic::transport::{Certificate as CaCertificate, Channel, ClientTlsConfig, Server};
+
+    // Certificate PEM, key PEM and certificate DER (signing again would give another DER)
+    fn self_signed() -> (String, String, Vec<u8>) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let pem = cert.serialize_pem().unwrap();
+        let der = rustls_pemfile::certs(&mut pem.as_bytes()).unwrap().remove(0);
+        (pem, cert.serialize_private_key_pem(), der)
+    }
+
+    // Test for loading and reloading the certificate from files
+    #[tokio::test]
+    async fn test_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert_path, key_path) = (dir.path().join("tls.crt"), dir.path().join("tls.key"));
+        let (first_cert, first_key, first_der) = self_signed();
+        std::fs::write(&cert_path, &first_cert).unwrap();
+        std::fs::write(&key_path, &first_key).unwrap();
+
+        let certificate = TlsCertificate::load(TlsConfig::from_files(&cert_path, &key_path)).unwrap();
+        assert_eq!(certificate.chain(), vec![first_der.clone()]);
+        assert!(!certificate.reload_if_changed().unwrap());
+
+        // A broken certificate keeps the current one
+        std::fs::write(&cert_path, "not a certificate").unwrap();
+        bump_modified(&cert_path);
+        assert!(certificate.reload_if_changed().is_err());
+        assert_eq!(certificate.chain(), vec![first_der]);
+
+        let (second_cert, second_key, second_der) = self_signed();
+        std::fs::write(&cert_path, &second_cert).unwrap();
+        std::fs::write(&key_path, &second_key).unwrap();
+        bump_modified(&cert_path);
+        assert!(certificate.reload_if_changed().unwrap());
+        assert_eq!(certificate.chain(), vec![second_der]);
+
+        // A missing key is rejected up front
+        assert!(TlsCertificate::load(TlsConfig::from_pem(second_cert, "no key")).is_err());
+    }
+
+    // The modification time has a coarse resolution on some file systems
+    fn bump_modified(path: &std::path::Path) {
+        let file = std::fs::File::options().append(true).open(path).unwrap();
+        let modified = file.metadata().unwrap().modified().unwrap() + Duration::from_secs(1);
+        file.set_modified(modified).unwrap();
+    }
+
+    // Test for serving gRPC over TLS
+    #[tokio::test]
+    async fn test_grpc_over_tls() {
+        let (cert, key, _) = self_signed();
+        let certificate = TlsCertificate::load(TlsConfig::from_pem(cert.clone(), key)).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = McpServiceImpl::new(PolicyEngine::new(), CommandExecutor::new(), SystemTime::now());
+        tokio::spawn(
+            Server::builder()
+                .add_service(McpServiceServer::new(service))
+                .serve_with_incoming(incoming(listener, certificate.server_config(&[b"h2"]))),
+        );
+
+        let tls = ClientTlsConfig::new()
+            .ca_certificate(CaCertificate::from_pem(cert))
+            .domain_name("localhost");
+        let channel = Channel::from_shared(format!("https://{}", addr))
+            .unwrap()
+            .tls_config(tls)
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let status = McpServiceClient::new(channel)
+            .get_task_status(TaskStatusRequest { task_id: "task-missing".to_string() })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        // Plaintext clients cannot talk to the server
+        let plaintext = Channel::from_shared(format!("http://{}", addr)).unwrap().connect().await;
+        if let Ok(channel) = plaintext {
+            let result = McpServiceClient::new(channel)
+                .get_task_status(TaskStatusRequest { task_id: "task-missing".to_string() })
+                .await;
+            assert!(result.is_err_and(|status| status.code() != tonic::Code::NotFound));
+        }
+    }
+
+    // Test for serving an axum router over HTTPS
+    #[tokio::test]
+    async fn test_https_router() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerName};
+
+        let (cert, key, der) = self_signed();
+        let certificate = TlsCertificate::load(TlsConfig::from_pem(cert, key)).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/health", axum::routing::get(|| async { "healthy" }));
+        tokio::spawn(serve_router(listener, certificate.server_config(&[b"h2", b"http/1.1"]), app));
+
+        let mut roots = RootCertStore::empty();
+        roots.add(&Certificate(der)).unwrap();
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut stream = tokio_rustls::TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .unwrap();
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response).await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("healthy"), "{}", response);
+    }
+}
@@ -9,6 +9,7 @@ use opentelemetry_otlp::WithExportConfig;
 use tracing::info;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::{EnvFilter, fmt};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_opentelemetry::OpenTelemetryLayer;
 
 /// トレーシングモジュール
@@ -53,6 +54,8 @@ pub struct TracingConfig {
     pub parent_base_trace_id_ratio: f64,
     /// ログレベル
     pub log_level: String,
+    /// ログを標準エラー出力に書き込む（標準出力をプロトコルに使うstdioモード用）
+    pub log_to_stderr: bool,
 }
 
 impl Default for TracingConfig {
@@ -65,6 +68,7 @@ impl Default for TracingConfig {
             batch_interval_secs: 5,
             parent_base_trace_id_ratio: 1.0,
             log_level: "info".to_string(),
+            log_to_stderr: false,
         }
     }
 }
@@ -92,8 +96,14 @@ pub fn init_tracing(config: TracingConfig) -> Result<()> {
         .unwrap_or_else(|_| EnvFilter::new("info"));
 
     // JSONフォーマットのレイヤーを設定
+    let writer = if config.log_to_stderr {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
     let fmt_layer = fmt::layer()
         .json()
+        .with_writer(writer)
         .with_ansi(true)
         .with_timer(fmt::time::UtcTime::rfc_3339());
 
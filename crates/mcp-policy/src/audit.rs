@@ -0,0 +1,420 @@
+//! Policy decision audit log
+//!
+//! `PolicyEngine` emits an [`AuditRecord`] for every evaluation to the configured
+//! [`AuditSink`]s. Sinks must not fail the evaluation, so write errors are logged and
+//! otherwise ignored.
+//!
+//! The stdout and file sinks write either the native record or, with
+//! [`AuditFormat::OpaDecisionLog`], an entry in OPA's decision log format (`decision_id`,
+//! `bundles`, `input`, `result`, `metrics`, ...) that existing OPA decision log pipelines
+//! ingest as is.
+
+use crate::break_glass::BreakGlassClaims;
+use crate::canary::CanaryOutcome;
+use crate::models::{PolicyDecision, PolicyInput};
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::get_env_var_or;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{error, info};
+
+/// Tracing target of the events emitted by [`TracingAuditSink`]
+pub const AUDIT_TARGET: &str = "mcp_policy::audit";
+
+/// Query path reported in OPA decision log entries
+pub const DECISION_LOG_PATH: &str = "mcp/allow";
+
+/// Name of the policy bundle in OPA decision log entries
+pub const DECISION_LOG_BUNDLE: &str = "mcp";
+
+/// Serialization of audit records
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuditFormat {
+    /// [`AuditRecord`] as is
+    #[default]
+    Native,
+    /// OPA decision log entry (see [`AuditRecord::to_decision_log`])
+    OpaDecisionLog,
+}
+
+impl AuditFormat {
+    /// Parse a format name (`native` or `opa`)
+    pub fn parse(name: &str) -> McpResult<Self> {
+        match name.trim() {
+            "native" => Ok(Self::Native),
+            "opa" => Ok(Self::OpaDecisionLog),
+            other => Err(McpError::InvalidRequest(format!(
+                "Unknown audit format '{}', expected native or opa",
+                other
+            ))),
+        }
+    }
+}
+
+/// Audit record of a single policy evaluation
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    /// Unique ID of the decision
+    pub decision_id: String,
+    /// Evaluation time (milliseconds since the Unix epoch)
+    pub timestamp_ms: u64,
+    /// Name of the evaluator that produced the decision
+    pub evaluator: String,
+    /// Complete evaluation input
+    pub input: PolicyInput,
+    /// Decision (absent if the evaluation failed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<PolicyDecision>,
+    /// Evaluation error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Evaluation latency in microseconds
+    pub latency_us: u64,
+    /// Whether the decision came from the decision cache
+    pub cached: bool,
+    /// Comparison with the stable version if the evaluation was routed to a canary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canary: Option<CanaryOutcome>,
+    /// Claims of the break-glass token if the record is a break-glass override
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_glass: Option<BreakGlassClaims>,
+    /// Revision of the active policy bundle, if policies are loaded from a bundle
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle_revision: Option<String>,
+}
+
+impl AuditRecord {
+    /// Serialize the record as a single JSON line
+    pub fn to_json(&self) -> McpResult<String> {
+        serde_json::to_string(self)
+            .map_err(|e| McpError::Internal(format!("Failed to serialize audit record: {}", e)))
+    }
+
+    /// Serialize the record as a single JSON line in the given format
+    pub fn to_json_as(&self, format: AuditFormat) -> McpResult<String> {
+        match format {
+            AuditFormat::Native => self.to_json(),
+            AuditFormat::OpaDecisionLog => serde_json::to_string(&self.to_decision_log()).map_err(|e| {
+                McpError::Internal(format!("Failed to serialize decision log entry: {}", e))
+            }),
+        }
+    }
+
+    /// The record as an OPA decision log entry
+    ///
+    /// The decision is the `result`; an evaluation error is reported as `error` without a
+    /// result. The evaluator, cache hits and the justification of break-glass overrides are
+    /// reported as labels.
+    pub fn to_decision_log(&self) -> Value {
+        let timestamp = chrono::DateTime::from_timestamp_millis(self.timestamp_ms as i64)
+            .unwrap_or_default()
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+        let mut labels = json!({
+            "evaluator": self.evaluator,
+            "cached": self.cached.to_string(),
+        });
+        if let Some(claims) = &self.break_glass {
+            labels["break_glass"] = json!(claims.reason);
+        }
+
+        let mut entry = json!({
+            "decision_id": self.decision_id,
+            "labels": labels,
+            "path": DECISION_LOG_PATH,
+            "input": self.input,
+            "timestamp": timestamp,
+            "metrics": { "timer_rego_query_eval_ns": self.latency_us.saturating_mul(1000) },
+        });
+        if let Some(revision) = &self.bundle_revision {
+            entry["bundles"] = json!({ DECISION_LOG_BUNDLE: { "revision": revision } });
+        }
+        match (&self.decision, &self.error) {
+            (Some(decision), _) => entry["result"] = json!(decision),
+            (None, Some(error)) => entry["error"] = json!({ "code": "eval_error", "message": error }),
+            (None, None) => {}
+        }
+        entry
+    }
+}
+
+/// Destination of audit records
+pub trait AuditSink: Send + Sync {
+    /// Write one record
+    fn record(&self, record: &AuditRecord);
+}
+
+/// Writes records as JSON lines to standard output
+#[derive(Debug, Default)]
+pub struct StdoutAuditSink {
+    format: AuditFormat,
+}
+
+impl StdoutAuditSink {
+    /// Write records in the given format
+    pub fn with_format(mut self, format: AuditFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+impl AuditSink for StdoutAuditSink {
+    fn record(&self, record: &AuditRecord) {
+        match record.to_json_as(self.format) {
+            Ok(line) => {
+                let mut stdout = std::io::stdout().lock();
+                if let Err(e) = writeln!(stdout, "{}", line) {
+                    error!("Failed to write audit record to stdout: {}", e);
+                }
+            }
+            Err(e) => error!("{}", e),
+        }
+    }
+}
+
+/// Appends records as JSON lines to a file
+#[derive(Debug)]
+pub struct FileAuditSink {
+    path: PathBuf,
+    file: Mutex<File>,
+    format: AuditFormat,
+}
+
+impl FileAuditSink {
+    /// Open (or create) the audit file for appending
+    pub fn open(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                McpError::Internal(format!("Failed to create audit directory {}: {}", parent.display(), e))
+            })?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| McpError::Internal(format!("Failed to open audit file {}: {}", path.display(), e)))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            format: AuditFormat::Native,
+        })
+    }
+
+    /// Write records in the given format
+    pub fn with_format(mut self, format: AuditFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Path of the audit file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn record(&self, record: &AuditRecord) {
+        let line = match record.to_json_as(self.format) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        };
+
+        // One write per record so that concurrent records are never interleaved
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = file.write_all(format!("{}\n", line).as_bytes()) {
+            error!("Failed to write audit record to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Emits records as `tracing` events with the target [`AUDIT_TARGET`]
+///
+/// With the OpenTelemetry layer of the gateway enabled, the events are exported over
+/// OTLP as events of the current span.
+#[derive(Debug, Default)]
+pub struct TracingAuditSink;
+
+impl AuditSink for TracingAuditSink {
+    fn record(&self, record: &AuditRecord) {
+        let input = serde_json::to_string(&record.input).unwrap_or_default();
+        let decision = record
+            .decision
+            .as_ref()
+            .and_then(|decision| serde_json::to_string(decision).ok())
+            .unwrap_or_default();
+
+        info!(
+            target: AUDIT_TARGET,
+            evaluator = %record.evaluator,
+            allow = record.decision.as_ref().map(|decision| decision.allow).unwrap_or(false),
+            latency_us = record.latency_us,
+            cached = record.cached,
+            error = record.error.as_deref().unwrap_or(""),
+            input = %input,
+            decision = %decision,
+            "policy decision"
+        );
+    }
+}
+
+/// Build audit sinks from environment variables
+///
+/// * `MCP_POLICY_AUDIT` - comma separated sinks: `stdout`, `file`, `otlp`
+/// * `MCP_POLICY_AUDIT_FILE` - file of the `file` sink
+/// * `MCP_POLICY_AUDIT_FORMAT` - format of the `stdout` and `file` sinks: `native` (default)
+///   or `opa` (OPA decision log)
+pub fn sinks_from_env() -> McpResult<Vec<Arc<dyn AuditSink>>> {
+    let mut sinks: Vec<Arc<dyn AuditSink>> = Vec::new();
+    let format = AuditFormat::parse(&get_env_var_or("MCP_POLICY_AUDIT_FORMAT", "native"))?;
+
+    for name in get_env_var_or("MCP_POLICY_AUDIT", "")
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        match name {
+            "stdout" => sinks.push(Arc::new(StdoutAuditSink::default().with_format(format))),
+            "file" => {
+                let path = get_env_var_or("MCP_POLICY_AUDIT_FILE", "/var/log/mcp/policy-audit.jsonl");
+                sinks.push(Arc::new(FileAuditSink::open(path)?.with_format(format)));
+            }
+            "otlp" => sinks.push(Arc::new(TracingAuditSink)),
+            other => {
+                return Err(McpError::InvalidRequest(format!(
+                    "Unknown audit sink '{}', expected stdout, file or otlp",
+                    other
+                )))
+            }
+        }
+    }
+
+    Ok(sinks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{PolicyEngine, StubPolicyEvaluator};
+    use crate::test_support::input;
+
+    /// Keeps records in memory
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<AuditRecord>>);
+
+    impl AuditSink for MemorySink {
+        fn record(&self, record: &AuditRecord) {
+            self.0.lock().unwrap().push(record.clone());
+        }
+    }
+
+    // Test for recording allowed and denied evaluations
+    #[tokio::test]
+    async fn test_engine_audit() {
+        let sink = Arc::new(MemorySink::default());
+        let engine = PolicyEngine::with_evaluator(StubPolicyEvaluator::default()).with_audit_sink(sink.clone());
+
+        engine.check_command_execution(&input("ls")).await.unwrap();
+        assert!(engine.check_command_execution(&input("rm")).await.is_err());
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].evaluator, "stub");
+        assert_eq!(records[0].input.command.name, "ls");
+        assert!(records[0].decision.as_ref().unwrap().allow);
+        assert!(!records[0].cached);
+        assert!(!records[1].decision.as_ref().unwrap().allow);
+        assert_ne!(records[0].decision_id, records[1].decision_id);
+    }
+
+    // Test for the JSON lines file sink
+    #[test]
+    fn test_file_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let sink = FileAuditSink::open(dir.path().join("audit").join("policy.jsonl")).unwrap();
+
+        let record = AuditRecord {
+            decision_id: "d-1".to_string(),
+            timestamp_ms: 1,
+            evaluator: "stub".to_string(),
+            input: input("ls"),
+            decision: None,
+            error: Some("failed".to_string()),
+            latency_us: 10,
+            cached: false,
+            canary: None,
+            break_glass: None,
+            bundle_revision: None,
+        };
+        sink.record(&record);
+        sink.record(&record);
+
+        let content = std::fs::read_to_string(sink.path()).unwrap();
+        let lines: Vec<serde_json::Value> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["input"]["command"]["name"], "ls");
+        assert_eq!(lines[0]["error"], "failed");
+        assert!(lines[0].get("decision").is_none());
+    }
+
+    // Test for OPA decision log entries
+    #[test]
+    fn test_decision_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let sink = FileAuditSink::open(dir.path().join("decisions.jsonl"))
+            .unwrap()
+            .with_format(AuditFormat::OpaDecisionLog);
+
+        let mut record = AuditRecord {
+            decision_id: "d-1".to_string(),
+            timestamp_ms: 1_700_000_000_123,
+            evaluator: "rego".to_string(),
+            input: input("ls"),
+            decision: Some(PolicyDecision {
+                allow: true,
+                warnings: vec![],
+                reasons: vec![],
+                deny_reasons: vec![],
+                metadata: Default::default(),
+            }),
+            error: None,
+            latency_us: 42,
+            cached: false,
+            canary: None,
+            break_glass: None,
+            bundle_revision: Some("rev-1".to_string()),
+        };
+        sink.record(&record);
+        record.decision = None;
+        record.error = Some("failed".to_string());
+        record.bundle_revision = None;
+        sink.record(&record);
+
+        let content = std::fs::read_to_string(sink.path()).unwrap();
+        let lines: Vec<Value> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines[0]["decision_id"], "d-1");
+        assert_eq!(lines[0]["path"], DECISION_LOG_PATH);
+        assert_eq!(lines[0]["timestamp"], "2023-11-14T22:13:20.123Z");
+        assert_eq!(lines[0]["bundles"], json!({ "mcp": { "revision": "rev-1" } }));
+        assert_eq!(lines[0]["input"]["command"]["name"], "ls");
+        assert_eq!(lines[0]["result"]["allow"], true);
+        assert_eq!(lines[0]["metrics"]["timer_rego_query_eval_ns"], 42_000);
+        assert_eq!(lines[0]["labels"], json!({ "evaluator": "rego", "cached": "false" }));
+
+        assert!(lines[1].get("result").is_none());
+        assert!(lines[1].get("bundles").is_none());
+        assert_eq!(lines[1]["error"]["message"], "failed");
+
+        assert_eq!(AuditFormat::parse("opa").unwrap(), AuditFormat::OpaDecisionLog);
+        assert!(AuditFormat::parse("xml").is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! Policy benchmark harness
+//!
+//! Replays a corpus of recorded inputs against an evaluator and reports latency
+//! percentiles, so that the performance of policy changes can be validated before rollout.
+//! A corpus is a JSON Lines file; each line is either a `PolicyInput` or an audit record
+//! with an `input` field (native or OPA decision log format), so that audit logs of real
+//! traffic can be replayed as they are:
+//!
+//! ```text
+//! {"command": {"name": "ls", "args": ["-la"]}}
+//! {"decision_id": "...", "input": {"command": {"name": "rm"}}, "result": {...}}
+//! ```
+//!
+//! Blank lines and lines starting with `#` are ignored.
+
+use crate::engine::AsyncPolicyEvaluator;
+use crate::models::PolicyInput;
+use mcp_common::error::{McpError, McpResult};
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Load a corpus of recorded inputs from a JSON Lines file
+pub fn load_corpus(path: impl AsRef<Path>) -> McpResult<Vec<PolicyInput>> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| McpError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+    parse_corpus(&content)
+        .map_err(|e| McpError::InvalidRequest(format!("Invalid benchmark corpus {}: {}", path.display(), e)))
+}
+
+/// Parse a corpus of recorded inputs (see the module documentation)
+pub fn parse_corpus(content: &str) -> McpResult<Vec<PolicyInput>> {
+    let mut inputs = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = |e: serde_json::Error| McpError::InvalidRequest(format!("line {}: {}", index + 1, e));
+        let mut value: serde_json::Value = serde_json::from_str(line).map_err(invalid)?;
+        // Audit records carry the evaluated input in the `input` field
+        if let Some(input) = value.get_mut("input").filter(|input| input.is_object()) {
+            value = input.take();
+        }
+        inputs.push(serde_json::from_value(value).map_err(invalid)?);
+    }
+    Ok(inputs)
+}
+
+/// Benchmark settings
+#[derive(Debug, Clone)]
+pub struct PolicyBenchmark {
+    /// Number of measured passes over the corpus
+    iterations: usize,
+    /// Number of passes before measuring (e.g. to warm up caches of the evaluator)
+    warmup: usize,
+}
+
+impl Default for PolicyBenchmark {
+    fn default() -> Self {
+        Self {
+            iterations: 10,
+            warmup: 1,
+        }
+    }
+}
+
+impl PolicyBenchmark {
+    /// Benchmark with the default settings (1 warm-up pass and 10 measured passes)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of measured passes over the corpus
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations.max(1);
+        self
+    }
+
+    /// Set the number of passes before measuring
+    pub fn with_warmup(mut self, warmup: usize) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Replay a corpus against an evaluator
+    ///
+    /// Evaluations are run one at a time so that the latencies are not skewed by
+    /// contention. Failed evaluations are timed as well and counted in the report.
+    pub async fn run(
+        &self,
+        inputs: &[PolicyInput],
+        evaluator: &dyn AsyncPolicyEvaluator,
+    ) -> McpResult<PolicyBenchReport> {
+        if inputs.is_empty() {
+            return Err(McpError::InvalidRequest("Benchmark corpus is empty".to_string()));
+        }
+
+        for _ in 0..self.warmup {
+            for input in inputs {
+                let _ = evaluator.evaluate(input).await;
+            }
+        }
+
+        let mut report = PolicyBenchReport {
+            evaluator: evaluator.name().to_string(),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        for _ in 0..self.iterations {
+            for input in inputs {
+                let start = Instant::now();
+                let result = evaluator.evaluate(input).await;
+                report.latencies.push(start.elapsed());
+                match result {
+                    Ok(decision) if decision.allow => report.allowed += 1,
+                    Ok(_) => report.denied += 1,
+                    Err(_) => report.errors += 1,
+                }
+            }
+        }
+        report.elapsed = started.elapsed();
+        report.latencies.sort();
+        Ok(report)
+    }
+}
+
+/// Result of a benchmark
+#[derive(Debug, Clone, Default)]
+pub struct PolicyBenchReport {
+    /// Name of the benchmarked evaluator
+    pub evaluator: String,
+    /// Latency of each evaluation, in ascending order
+    pub latencies: Vec<Duration>,
+    /// Number of allowing decisions
+    pub allowed: usize,
+    /// Number of denying decisions
+    pub denied: usize,
+    /// Number of failed evaluations
+    pub errors: usize,
+    /// Wall-clock time of the measured passes
+    pub elapsed: Duration,
+}
+
+impl PolicyBenchReport {
+    /// Number of measured evaluations
+    pub fn evaluations(&self) -> usize {
+        self.latencies.len()
+    }
+
+    /// Latency at a percentile (0-100, nearest rank)
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        self.latencies[rank.clamp(1, self.latencies.len()) - 1]
+    }
+
+    /// Mean latency
+    pub fn mean(&self) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        self.latencies.iter().sum::<Duration>() / self.latencies.len() as u32
+    }
+
+    /// Evaluations per second
+    pub fn throughput(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        self.evaluations() as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl fmt::Display for PolicyBenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} evaluations ({} allowed, {} denied, {} errors) in {:.2?} ({:.0}/s)",
+            self.evaluator,
+            self.evaluations(),
+            self.allowed,
+            self.denied,
+            self.errors,
+            self.elapsed,
+            self.throughput()
+        )?;
+        write!(
+            f,
+            "mean {:.2?}  p50 {:.2?}  p95 {:.2?}  p99 {:.2?}  max {:.2?}",
+            self.mean(),
+            self.percentile(50.0),
+            self.percentile(95.0),
+            self.percentile(99.0),
+            self.percentile(100.0)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::StubPolicyEvaluator;
+
+    // Test for replaying a corpus of inputs and audit records
+    #[tokio::test]
+    async fn test_benchmark() {
+        let corpus = r#"
+# recorded inputs
+{"command": {"name": "ls", "args": ["-la"]}}
+{"decision_id": "1", "timestamp_ms": 0, "evaluator": "rego", "input": {"command": {"name": "rm"}}, "latency_us": 5}
+
+{"decision_id": "2", "path": "mcp/allow", "input": {"command": {"name": "cat"}}, "result": {"allow": true}}
+"#;
+        let inputs = parse_corpus(corpus).unwrap();
+        assert_eq!(
+            inputs.iter().map(|input| input.command.name.as_str()).collect::<Vec<_>>(),
+            ["ls", "rm", "cat"]
+        );
+        let err = parse_corpus("{\"command\": {\"name\": \"ls\"}}\nnot json").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{}", err);
+
+        let evaluator = StubPolicyEvaluator::default();
+        let report = PolicyBenchmark::new().with_iterations(4).run(&inputs, &evaluator).await.unwrap();
+        assert_eq!(report.evaluations(), 12);
+        assert_eq!(report.allowed + report.denied + report.errors, 12);
+        assert!(report.percentile(50.0) <= report.percentile(95.0));
+        assert!(report.percentile(99.0) <= report.percentile(100.0));
+        assert_eq!(report.percentile(100.0), *report.latencies.last().unwrap());
+        assert!(report.to_string().contains("12 evaluations"));
+
+        assert!(PolicyBenchmark::new().run(&[], &evaluator).await.is_err());
+    }
+
+    // Test for nearest-rank percentiles
+    #[test]
+    fn test_percentile() {
+        let report = PolicyBenchReport {
+            latencies: (1..=100).map(Duration::from_micros).collect(),
+            ..Default::default()
+        };
+        assert_eq!(report.percentile(50.0), Duration::from_micros(50));
+        assert_eq!(report.percentile(99.0), Duration::from_micros(99));
+        assert_eq!(report.percentile(0.0), Duration::from_micros(1));
+        assert_eq!(report.mean(), Duration::from_nanos(50_500));
+        assert_eq!(PolicyBenchReport::default().percentile(99.0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,284 @@
+//! Break-glass override of policy denials
+//!
+//! During an incident, responders may need to run a command the policies deny. A request
+//! can carry a break-glass token: a short-lived JWT issued to the requesting user and
+//! signed with a private key whose public key the gateway trusts. Shared secrets (HMAC) are
+//! not accepted, as anything able to read the gateway's configuration could mint tokens.
+//!
+//! When the policy denies the command and the token verifies,
+//! `PolicyEngine::check_command_execution_with_break_glass` allows it anyway, logs the
+//! override at error level, writes an audit record carrying the token claims and reports
+//! the outcome to the observer ("granted" or "rejected").
+//!
+//! Only denials of the policy evaluator can be overridden; evaluation errors are not.
+
+use crate::bundle::decoding_key;
+use crate::decision_cache::CacheObserver;
+use crate::models::PolicyInput;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{current_timestamp_ms, get_env_var_or};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Decision metadata key set on decisions allowed by a break-glass override
+pub const METADATA_BREAK_GLASS: &str = "break_glass";
+
+/// Clock skew tolerated between the token issuer and the gateway
+const CLOCK_SKEW_SECS: u64 = 60;
+
+/// Claims of a break-glass token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakGlassClaims {
+    /// User the token was issued to
+    pub sub: String,
+    /// Justification (e.g. the incident ID)
+    pub reason: String,
+    /// Issue time (seconds since the Unix epoch)
+    pub iat: u64,
+    /// Expiration time (seconds since the Unix epoch)
+    pub exp: u64,
+    /// Issuer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    /// Token ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+}
+
+/// Verifier of break-glass tokens
+#[derive(Clone)]
+pub struct BreakGlass {
+    algorithm: Algorithm,
+    key: DecodingKey,
+    max_ttl: Duration,
+    issuer: Option<String>,
+    observer: Option<CacheObserver>,
+}
+
+impl fmt::Debug for BreakGlass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the key material
+        f.debug_struct("BreakGlass")
+            .field("algorithm", &self.algorithm)
+            .field("max_ttl", &self.max_ttl)
+            .field("issuer", &self.issuer)
+            .finish()
+    }
+}
+
+impl BreakGlass {
+    /// Verify tokens with a PEM encoded public key
+    ///
+    /// Only asymmetric algorithms are accepted. Tokens may be valid for at most one hour by
+    /// default.
+    pub fn new(algorithm: Algorithm, key: &[u8]) -> McpResult<Self> {
+        if matches!(algorithm, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            return Err(McpError::InvalidRequest(format!(
+                "Break-glass tokens must be signed with an asymmetric algorithm, not {:?}",
+                algorithm
+            )));
+        }
+        let key = decoding_key(algorithm, key)
+            .map_err(|e| McpError::Internal(format!("Invalid break-glass verification key: {}", e)))?;
+
+        Ok(Self {
+            algorithm,
+            key,
+            max_ttl: Duration::from_secs(3600),
+            issuer: None,
+            observer: None,
+        })
+    }
+
+    /// Longest lifetime accepted for a token (both `exp - iat` and the time left until `exp`)
+    pub fn with_max_ttl(mut self, max_ttl: Duration) -> Self {
+        self.max_ttl = max_ttl;
+        self
+    }
+
+    /// Require tokens of an issuer
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.issuer = Some(issuer.to_string());
+        self
+    }
+
+    /// Report every verification ("granted" or "rejected")
+    pub fn with_observer(mut self, observer: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Build the verifier from environment variables (`None` when no key is configured)
+    ///
+    /// * `MCP_BREAK_GLASS_KEY` - path to a PEM public key
+    /// * `MCP_BREAK_GLASS_KEY_ALG` - signing algorithm (default `RS256`)
+    /// * `MCP_BREAK_GLASS_ISSUER` - required issuer
+    /// * `MCP_BREAK_GLASS_MAX_TTL_SECS` - longest accepted token lifetime (default 3600)
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let Ok(key) = std::env::var("MCP_BREAK_GLASS_KEY") else {
+            return Ok(None);
+        };
+
+        let algorithm_name = get_env_var_or("MCP_BREAK_GLASS_KEY_ALG", "RS256");
+        let algorithm = Algorithm::from_str(&algorithm_name).map_err(|_| {
+            McpError::InvalidRequest(format!("Unsupported break-glass signing algorithm: '{}'", algorithm_name))
+        })?;
+
+        let key = std::fs::read(&key)
+            .map_err(|e| McpError::Internal(format!("Failed to read break-glass verification key {}: {}", key, e)))?;
+        let mut break_glass = Self::new(algorithm, &key)?;
+
+        if let Ok(issuer) = std::env::var("MCP_BREAK_GLASS_ISSUER") {
+            break_glass = break_glass.with_issuer(&issuer);
+        }
+        if let Ok(value) = std::env::var("MCP_BREAK_GLASS_MAX_TTL_SECS") {
+            let secs = value.trim().parse::<u64>().ok().filter(|secs| *secs > 0).ok_or_else(|| {
+                McpError::InvalidRequest(format!(
+                    "MCP_BREAK_GLASS_MAX_TTL_SECS must be a positive number of seconds: '{}'",
+                    value
+                ))
+            })?;
+            break_glass = break_glass.with_max_ttl(Duration::from_secs(secs));
+        }
+
+        Ok(Some(break_glass))
+    }
+
+    /// Verify a token presented for an input and report the outcome to the observer
+    ///
+    /// The token must be signed with the configured key, issued no later than now, unexpired,
+    /// no longer lived than the maximum lifetime and issued to the requesting user.
+    pub fn verify(&self, token: &str, input: &PolicyInput) -> McpResult<BreakGlassClaims> {
+        let result = self.verify_claims(token, input);
+        if let Some(observer) = &self.observer {
+            observer(if result.is_ok() { "granted" } else { "rejected" });
+        }
+        result
+    }
+
+    fn verify_claims(&self, token: &str, input: &PolicyInput) -> McpResult<BreakGlassClaims> {
+        let mut validation = Validation::new(self.algorithm);
+        validation.set_required_spec_claims(&["exp", "sub"]);
+        validation.leeway = 0;
+        if let Some(issuer) = &self.issuer {
+            validation.set_required_spec_claims(&["exp", "sub", "iss"]);
+            validation.set_issuer(&[issuer]);
+        }
+
+        let claims = jsonwebtoken::decode::<BreakGlassClaims>(token, &self.key, &validation)
+            .map_err(|e| McpError::PolicyViolation(format!("Invalid break-glass token: {}", e)))?
+            .claims;
+
+        let now = current_timestamp_ms() / 1000;
+        if claims.iat > now + CLOCK_SKEW_SECS {
+            return Err(McpError::PolicyViolation("Break-glass token is issued in the future".to_string()));
+        }
+        // A token issued in the future could otherwise stay valid for longer than the maximum lifetime
+        if claims.exp.saturating_sub(claims.iat) > self.max_ttl.as_secs()
+            || claims.exp.saturating_sub(now) > self.max_ttl.as_secs() + CLOCK_SKEW_SECS
+        {
+            return Err(McpError::PolicyViolation(format!(
+                "Break-glass token is valid for longer than {} seconds",
+                self.max_ttl.as_secs()
+            )));
+        }
+        if claims.sub != input.user.id {
+            return Err(McpError::PolicyViolation(format!(
+                "Break-glass token was issued to '{}', not to '{}'",
+                claims.sub, input.user.id
+            )));
+        }
+
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_support::input;
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::json;
+    use std::sync::{Mutex, OnceLock};
+
+    /// Key pair signing the test tokens
+    fn key_pair() -> &'static rcgen::KeyPair {
+        static KEY_PAIR: OnceLock<rcgen::KeyPair> = OnceLock::new();
+        KEY_PAIR.get_or_init(|| rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256).unwrap())
+    }
+
+    /// Verifier trusting the test key pair
+    pub(crate) fn break_glass() -> BreakGlass {
+        BreakGlass::new(Algorithm::ES256, key_pair().public_key_pem().as_bytes()).unwrap()
+    }
+
+    pub(crate) fn token(sub: &str, ttl_secs: i64) -> String {
+        token_at(sub, 0, ttl_secs)
+    }
+
+    /// Token issued `issued_in_secs` from now, expiring `ttl_secs` after that
+    fn token_at(sub: &str, issued_in_secs: i64, ttl_secs: i64) -> String {
+        let iat = (current_timestamp_ms() / 1000) as i64 + issued_in_secs;
+        jsonwebtoken::encode(
+            &Header::new(Algorithm::ES256),
+            &json!({ "sub": sub, "reason": "INC-42", "iat": iat, "exp": iat + ttl_secs }),
+            &EncodingKey::from_ec_pem(key_pair().serialize_pem().as_bytes()).unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn as_user(user: &str) -> PolicyInput {
+        let mut input = input("ls");
+        input.user.id = user.to_string();
+        input
+    }
+
+    // Test for verifying break-glass tokens
+    #[test]
+    fn test_verify() {
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+        let observed = outcomes.clone();
+        let break_glass = break_glass()
+            .with_max_ttl(Duration::from_secs(900))
+            .with_observer(move |outcome| observed.lock().unwrap().push(outcome.to_string()));
+
+        let claims = break_glass.verify(&token("alice", 600), &as_user("alice")).unwrap();
+        assert_eq!(claims.reason, "INC-42");
+
+        // Other users, expired or long-lived tokens and other keys are rejected
+        assert!(break_glass.verify(&token("alice", 600), &as_user("bob")).is_err());
+        assert!(break_glass.verify(&token("alice", -10), &as_user("alice")).is_err());
+        assert!(break_glass.verify(&token("alice", 3600), &as_user("alice")).is_err());
+        assert!(break_glass.verify("not-a-token", &as_user("alice")).is_err());
+        let other_key = rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256).unwrap();
+        let other = BreakGlass::new(Algorithm::ES256, other_key.public_key_pem().as_bytes()).unwrap();
+        assert!(other.verify(&token("alice", 600), &as_user("alice")).is_err());
+
+        assert_eq!(*outcomes.lock().unwrap(), vec!["granted", "rejected", "rejected", "rejected", "rejected"]);
+
+        let issuer = self::break_glass().with_issuer("incident-bot");
+        assert!(issuer.verify(&token("alice", 600), &as_user("alice")).is_err());
+    }
+
+    // Test for tokens issued in the future
+    #[test]
+    fn test_verify_issue_time() {
+        let break_glass = break_glass().with_max_ttl(Duration::from_secs(900));
+
+        // exp - iat is within the maximum, but the token would stay valid for a day
+        assert!(break_glass.verify(&token_at("alice", 86400, 600), &as_user("alice")).is_err());
+        // Small clock skew is tolerated
+        assert!(break_glass.verify(&token_at("alice", 30, 600), &as_user("alice")).is_ok());
+    }
+
+    // Shared secrets are not accepted
+    #[test]
+    fn test_symmetric_algorithm() {
+        let error = BreakGlass::new(Algorithm::HS256, b"break-glass-secret").unwrap_err();
+        assert!(matches!(error, McpError::InvalidRequest(_)));
+    }
+}
@@ -0,0 +1,867 @@
+//! OPA bundle polling
+//!
+//! Periodically downloads a policy bundle (`.tar.gz` with `.rego` modules, `data.json`
+//! documents, an optional `.manifest` and a `.signatures.json`) from a bundle server, as
+//! OPA's bundle API does. The `ETag` of the active bundle is sent as `If-None-Match` so an
+//! unchanged bundle is not downloaded again. A new bundle is activated only after its
+//! signature has been verified and all of its modules have compiled; otherwise the
+//! previous policies stay active.
+//!
+//! Besides OPA's embedded `.signatures.json`, a bundle can be signed with a detached Ed25519
+//! signature of the whole archive (base64, as written by `cosign sign-blob`), published next
+//! to it as `<url>.sig`. The signature must verify against one of the configured trust keys.
+//! The bundle URL may also be a local path (or `file://` URL); the bundle file is then re-read
+//! when it changes and verified the same way. The result of the verification is kept in
+//! [`BundleState::signature`] for the health output.
+
+use crate::engine::PolicyEngine;
+use crate::rego::RegoEvaluator;
+use base64::Engine as _;
+use ed25519_dalek::pkcs8::DecodePublicKey;
+use ed25519_dalek::{Signature, VerifyingKey};
+use flate2::read::GzDecoder;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{current_timestamp_ms, get_env_var_or, hex};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+/// Name of the signatures file inside a bundle
+pub const SIGNATURES_FILE: &str = ".signatures.json";
+/// Name of the manifest file inside a bundle
+pub const MANIFEST_FILE: &str = ".manifest";
+
+/// Key used to verify bundle signatures
+#[derive(Clone)]
+pub struct BundleVerification {
+    /// Signing algorithm (e.g. HS256, RS256)
+    pub algorithm: Algorithm,
+    /// Shared secret (HMAC) or PEM encoded public key
+    pub key: Vec<u8>,
+    /// Expected `keyid` claim, if any
+    pub key_id: Option<String>,
+}
+
+impl std::fmt::Debug for BundleVerification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the key material
+        f.debug_struct("BundleVerification")
+            .field("algorithm", &self.algorithm)
+            .field("key_id", &self.key_id)
+            .finish()
+    }
+}
+
+impl BundleVerification {
+    fn decoding_key(&self) -> McpResult<DecodingKey> {
+        decoding_key(self.algorithm, &self.key)
+            .map_err(|e| McpError::Internal(format!("Invalid bundle verification key: {}", e)))
+    }
+}
+
+/// Verification key for an algorithm from a shared secret (HMAC) or a PEM encoded public key
+pub(crate) fn decoding_key(algorithm: Algorithm, key: &[u8]) -> jsonwebtoken::errors::Result<DecodingKey> {
+    match algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => Ok(DecodingKey::from_secret(key)),
+        Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512
+        | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512 => DecodingKey::from_rsa_pem(key),
+        Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(key),
+        Algorithm::EdDSA => DecodingKey::from_ed_pem(key),
+    }
+}
+
+/// Ed25519 public key trusted to sign bundles (detached signatures)
+#[derive(Debug, Clone)]
+pub struct BundleTrustKey {
+    /// Key ID reported when a bundle was verified with this key
+    pub key_id: String,
+    /// Public key
+    pub key: VerifyingKey,
+}
+
+impl BundleTrustKey {
+    /// Read a PEM encoded (SPKI) Ed25519 public key; the key ID is the file name without extension
+    pub fn from_pem_file(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        let pem = std::fs::read_to_string(path).map_err(|e| {
+            McpError::Internal(format!("Failed to read bundle trust key {}: {}", path.display(), e))
+        })?;
+        let key = VerifyingKey::from_public_key_pem(&pem).map_err(|e| {
+            McpError::InvalidRequest(format!("Invalid Ed25519 bundle trust key {}: {}", path.display(), e))
+        })?;
+        let key_id = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        Ok(Self { key_id, key })
+    }
+}
+
+/// Verify a detached signature (base64 Ed25519) of a bundle archive; returns the ID of the matching key
+pub fn verify_detached_signature(archive: &[u8], signature: &[u8], trust_keys: &[BundleTrustKey]) -> McpResult<String> {
+    let signature = std::str::from_utf8(signature)
+        .ok()
+        .and_then(|signature| base64::engine::general_purpose::STANDARD.decode(signature.trim()).ok())
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| McpError::PolicyViolation("Invalid detached bundle signature".to_string()))?;
+
+    trust_keys
+        .iter()
+        .find(|trust_key| trust_key.key.verify_strict(archive, &signature).is_ok())
+        .map(|trust_key| trust_key.key_id.clone())
+        .ok_or_else(|| McpError::PolicyViolation("Bundle signature does not match any trust key".to_string()))
+}
+
+/// Bundle polling settings
+#[derive(Debug, Clone)]
+pub struct BundleConfig {
+    /// Bundle URL
+    pub url: String,
+    /// Interval between polls
+    pub poll_interval: Duration,
+    /// Timeout of a single download
+    pub timeout: Duration,
+    /// Bearer token sent to the bundle server
+    pub auth_token: Option<String>,
+    /// Signature verification key
+    pub verification: Option<BundleVerification>,
+    /// Keys trusted to sign the bundle archive (detached signature)
+    pub trust_keys: Vec<BundleTrustKey>,
+    /// Location of the detached signature (default `<url>.sig`)
+    pub signature_url: Option<String>,
+    /// Accept bundles without signatures (only when no verification key is set)
+    pub allow_unsigned: bool,
+    /// Maximum size of the uncompressed bundle
+    pub max_size_bytes: u64,
+}
+
+impl BundleConfig {
+    /// Create settings for a bundle URL with default values
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            poll_interval: Duration::from_secs(60),
+            timeout: Duration::from_secs(30),
+            auth_token: None,
+            verification: None,
+            trust_keys: Vec::new(),
+            signature_url: None,
+            allow_unsigned: false,
+            max_size_bytes: 64 * 1024 * 1024,
+        }
+    }
+
+    /// Build the settings from environment variables (`None` when no URL is configured)
+    ///
+    /// * `MCP_POLICY_BUNDLE_URL` - bundle URL or local path
+    /// * `MCP_POLICY_BUNDLE_POLL_SECS` - polling interval
+    /// * `MCP_POLICY_BUNDLE_TOKEN` - bearer token
+    /// * `MCP_POLICY_BUNDLE_KEY_ALG` - signing algorithm (default `HS256`)
+    /// * `MCP_POLICY_BUNDLE_KEY` - shared secret (HMAC) or path to a PEM public key
+    /// * `MCP_POLICY_BUNDLE_KEY_ID` - expected key ID
+    /// * `MCP_POLICY_BUNDLE_TRUST_KEYS` - comma separated paths to Ed25519 PEM public keys
+    /// * `MCP_POLICY_BUNDLE_SIGNATURE_URL` - detached signature location (default `<url>.sig`)
+    /// * `MCP_POLICY_BUNDLE_ALLOW_UNSIGNED` - `true` to accept unsigned bundles
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let url = match std::env::var("MCP_POLICY_BUNDLE_URL") {
+            Ok(url) if !url.trim().is_empty() => url,
+            _ => return Ok(None),
+        };
+        let mut config = Self::new(url.trim());
+
+        if let Ok(value) = std::env::var("MCP_POLICY_BUNDLE_POLL_SECS") {
+            let secs = value.trim().parse::<u64>().ok().filter(|secs| *secs > 0).ok_or_else(|| {
+                McpError::InvalidRequest(format!(
+                    "MCP_POLICY_BUNDLE_POLL_SECS must be a positive number of seconds: '{}'",
+                    value
+                ))
+            })?;
+            config.poll_interval = Duration::from_secs(secs);
+        }
+
+        config.auth_token = std::env::var("MCP_POLICY_BUNDLE_TOKEN").ok();
+        config.allow_unsigned = get_env_var_or("MCP_POLICY_BUNDLE_ALLOW_UNSIGNED", "false") == "true";
+
+        if let Ok(key) = std::env::var("MCP_POLICY_BUNDLE_KEY") {
+            let algorithm_name = get_env_var_or("MCP_POLICY_BUNDLE_KEY_ALG", "HS256");
+            let algorithm = Algorithm::from_str(&algorithm_name).map_err(|_| {
+                McpError::InvalidRequest(format!("Unsupported bundle signing algorithm: '{}'", algorithm_name))
+            })?;
+
+            // HMAC keys are given inline, public keys as a path to a PEM file
+            let key = match algorithm {
+                Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => key.into_bytes(),
+                _ => std::fs::read(&key).map_err(|e| {
+                    McpError::Internal(format!("Failed to read bundle verification key {}: {}", key, e))
+                })?,
+            };
+
+            config.verification = Some(BundleVerification {
+                algorithm,
+                key,
+                key_id: std::env::var("MCP_POLICY_BUNDLE_KEY_ID").ok(),
+            });
+        }
+
+        if let Ok(paths) = std::env::var("MCP_POLICY_BUNDLE_TRUST_KEYS") {
+            config.trust_keys = paths
+                .split(',')
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .map(BundleTrustKey::from_pem_file)
+                .collect::<McpResult<_>>()?;
+        }
+        config.signature_url = std::env::var("MCP_POLICY_BUNDLE_SIGNATURE_URL").ok();
+
+        Ok(Some(config))
+    }
+
+    /// Location of the detached signature
+    pub fn signature_location(&self) -> String {
+        self.signature_url.clone().unwrap_or_else(|| format!("{}.sig", self.url))
+    }
+}
+
+/// Local file of a bundle location (a path or a `file://` URL), `None` for HTTP(S) URLs
+fn local_path(location: &str) -> Option<PathBuf> {
+    if let Some(path) = location.strip_prefix("file://") {
+        return Some(PathBuf::from(path));
+    }
+    (!location.starts_with("http://") && !location.starts_with("https://")).then(|| PathBuf::from(location))
+}
+
+/// Downloaded policy bundle
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    /// Revision from the manifest (empty if the bundle has no manifest)
+    pub revision: String,
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Signatures {
+    signatures: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignedFiles {
+    files: Vec<SignedFile>,
+    #[serde(default)]
+    keyid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignedFile {
+    name: String,
+    hash: String,
+    algorithm: String,
+}
+
+impl Bundle {
+    /// Read a gzip compressed tarball
+    pub fn from_tar_gz(bytes: &[u8], max_size_bytes: u64) -> McpResult<Self> {
+        let mut archive = tar::Archive::new(GzDecoder::new(bytes));
+        let mut files = BTreeMap::new();
+        let mut total = 0u64;
+
+        let entries = archive
+            .entries()
+            .map_err(|e| McpError::InvalidRequest(format!("Invalid bundle archive: {}", e)))?;
+        for entry in entries {
+            let mut entry =
+                entry.map_err(|e| McpError::InvalidRequest(format!("Invalid bundle entry: {}", e)))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let path = entry
+                .path()
+                .map_err(|e| McpError::InvalidRequest(format!("Invalid bundle entry path: {}", e)))?
+                .into_owned();
+            let name = normalize_name(&path)?;
+
+            total += entry.size();
+            if total > max_size_bytes {
+                return Err(McpError::InvalidRequest(format!(
+                    "Bundle exceeds the maximum size of {} bytes",
+                    max_size_bytes
+                )));
+            }
+
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .map_err(|e| McpError::InvalidRequest(format!("Failed to read bundle entry {}: {}", name, e)))?;
+            files.insert(name, content);
+        }
+
+        let revision = match files.get(MANIFEST_FILE) {
+            Some(manifest) => serde_json::from_slice::<serde_json::Value>(manifest)
+                .map_err(|e| McpError::InvalidRequest(format!("Invalid bundle manifest: {}", e)))?
+                .get("revision")
+                .and_then(|revision| revision.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            None => String::new(),
+        };
+
+        Ok(Self { revision, files })
+    }
+
+    /// Names of the files in the bundle
+    pub fn file_names(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(String::as_str)
+    }
+
+    /// Whether the bundle contains a signatures file
+    pub fn is_signed(&self) -> bool {
+        self.files.contains_key(SIGNATURES_FILE)
+    }
+
+    /// Verify the bundle signature and the hash of every file
+    pub fn verify(&self, verification: &BundleVerification) -> McpResult<()> {
+        let signatures = self
+            .files
+            .get(SIGNATURES_FILE)
+            .ok_or_else(|| McpError::PolicyViolation("Bundle is not signed".to_string()))?;
+        let signatures: Signatures = serde_json::from_slice(signatures)
+            .map_err(|e| McpError::InvalidRequest(format!("Invalid bundle signatures: {}", e)))?;
+        let token = signatures
+            .signatures
+            .first()
+            .ok_or_else(|| McpError::PolicyViolation("Bundle has no signatures".to_string()))?;
+
+        let mut validation = Validation::new(verification.algorithm);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+        let claims = jsonwebtoken::decode::<SignedFiles>(token, &verification.decoding_key()?, &validation)
+            .map_err(|e| McpError::PolicyViolation(format!("Invalid bundle signature: {}", e)))?
+            .claims;
+
+        if let Some(expected) = &verification.key_id {
+            if claims.keyid.as_deref() != Some(expected.as_str()) {
+                return Err(McpError::PolicyViolation(format!(
+                    "Bundle was signed with an unexpected key: {:?}",
+                    claims.keyid
+                )));
+            }
+        }
+
+        let mut signed: BTreeMap<String, SignedFile> = BTreeMap::new();
+        for file in claims.files {
+            let name = normalize_name(Path::new(&file.name))?;
+            signed.insert(name, file);
+        }
+
+        for (name, content) in &self.files {
+            if name == SIGNATURES_FILE {
+                continue;
+            }
+            let file = signed
+                .remove(name)
+                .ok_or_else(|| McpError::PolicyViolation(format!("Bundle file {} is not signed", name)))?;
+            if !file.algorithm.eq_ignore_ascii_case("SHA-256") {
+                return Err(McpError::PolicyViolation(format!(
+                    "Unsupported hash algorithm for {}: {}",
+                    name, file.algorithm
+                )));
+            }
+            if !file.hash.eq_ignore_ascii_case(&file_hash(name, content)?) {
+                return Err(McpError::PolicyViolation(format!("Hash mismatch for bundle file {}", name)));
+            }
+        }
+
+        if let Some(name) = signed.keys().next() {
+            return Err(McpError::PolicyViolation(format!("Signed file {} is missing from the bundle", name)));
+        }
+
+        Ok(())
+    }
+
+    /// Compile the bundle into an evaluator
+    pub fn compile(&self, origin: &str) -> McpResult<RegoEvaluator> {
+        let mut modules = Vec::new();
+        let mut data = Vec::new();
+
+        for (name, content) in &self.files {
+            let is_module = name.ends_with(".rego");
+            let is_data = Path::new(name).file_name().is_some_and(|file| file == "data.json");
+            if !is_module && !is_data {
+                continue;
+            }
+
+            let content = String::from_utf8(content.clone())
+                .map_err(|_| McpError::InvalidRequest(format!("Bundle file {} is not valid UTF-8", name)))?;
+            if is_module {
+                modules.push((name.clone(), content));
+            } else {
+                data.push((name.clone(), content));
+            }
+        }
+
+        RegoEvaluator::from_sources(origin, modules, data)
+    }
+}
+
+/// Normalize an archive path ("/a/b.rego", "./a/b.rego" -> "a/b.rego")
+fn normalize_name(path: &Path) -> McpResult<String> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::RootDir | Component::CurDir => {}
+            _ => {
+                return Err(McpError::InvalidRequest(format!(
+                    "Invalid path in bundle: {}",
+                    path.display()
+                )))
+            }
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+/// SHA-256 of a bundle file; JSON files are hashed in their canonical (sorted, compact) form
+pub(crate) fn file_hash(name: &str, content: &[u8]) -> McpResult<String> {
+    let canonical;
+    let is_manifest = Path::new(name).file_name().is_some_and(|file| file == MANIFEST_FILE);
+    let bytes = if name.ends_with(".json") || is_manifest {
+        let value: serde_json::Value = serde_json::from_slice(content)
+            .map_err(|e| McpError::InvalidRequest(format!("Invalid JSON in bundle file {}: {}", name, e)))?;
+        canonical = serde_json::to_vec(&sort_keys(value))
+            .map_err(|e| McpError::Internal(format!("Failed to encode {}: {}", name, e)))?;
+        &canonical[..]
+    } else {
+        content
+    };
+
+    Ok(hex(&Sha256::digest(bytes)))
+}
+
+/// Recursively sort object keys (independent of serde_json's `preserve_order` feature)
+pub(crate) fn sort_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let sorted: BTreeMap<_, _> = map.into_iter().map(|(key, value)| (key, sort_keys(value))).collect();
+            serde_json::Value::Object(sorted.into_iter().collect())
+        }
+        serde_json::Value::Array(values) => serde_json::Value::Array(values.into_iter().map(sort_keys).collect()),
+        other => other,
+    }
+}
+
+/// State of the bundle poller
+#[derive(Debug, Clone, Default)]
+pub struct BundleState {
+    /// Revision of the active bundle
+    pub revision: Option<String>,
+    /// ETag of the active bundle
+    pub etag: Option<String>,
+    /// Activation time of the active bundle (milliseconds)
+    pub activated_at_ms: Option<u64>,
+    /// Error of the last poll, if it failed
+    pub last_error: Option<String>,
+    /// Signature verification of the last downloaded bundle
+    pub signature: Option<SignatureStatus>,
+}
+
+/// Result of the signature verification of a bundle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// Signature verified (with the ID of the key, if known)
+    Verified { key_id: Option<String> },
+    /// Activated without verification (unsigned bundles allowed)
+    Unverified,
+    /// Verification failed and the bundle was not activated
+    Rejected { reason: String },
+}
+
+impl std::fmt::Display for SignatureStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Verified { key_id: Some(key_id) } => write!(f, "verified ({})", key_id),
+            Self::Verified { key_id: None } => write!(f, "verified"),
+            Self::Unverified => write!(f, "unverified"),
+            Self::Rejected { reason } => write!(f, "rejected: {}", reason),
+        }
+    }
+}
+
+/// Handle of a running bundle poller
+///
+/// Dropping the handle stops polling.
+#[derive(Debug)]
+pub struct BundlePoller {
+    state: Arc<RwLock<BundleState>>,
+    _stop: mpsc::Sender<()>,
+}
+
+impl BundlePoller {
+    pub(crate) fn start(
+        engine: PolicyEngine,
+        config: BundleConfig,
+        on_activate: impl Fn(&str) + Send + 'static,
+    ) -> McpResult<Self> {
+        if config.verification.is_none() && config.trust_keys.is_empty() && !config.allow_unsigned {
+            return Err(McpError::InvalidRequest(
+                "A bundle verification or trust key is required unless unsigned bundles are allowed".to_string(),
+            ));
+        }
+
+        let state = Arc::new(RwLock::new(BundleState::default()));
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        {
+            let state = state.clone();
+            std::thread::Builder::new()
+                .name("policy-bundle".to_string())
+                .spawn(move || {
+                    let agent = ureq::AgentBuilder::new().timeout(config.timeout).build();
+                    loop {
+                        match poll_once(&agent, &config, &state, &engine) {
+                            Ok(Some(revision)) => {
+                                engine.observe_reload("bundle", true);
+                                on_activate(&revision);
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                engine.observe_reload("bundle", false);
+                                error!("Policy bundle update failed, keeping the previous policies: {}", e);
+                                state.write().unwrap_or_else(|e| e.into_inner()).last_error = Some(e.to_string());
+                            }
+                        }
+
+                        // The channel is closed when the poller is dropped
+                        match stopped.recv_timeout(config.poll_interval) {
+                            Err(RecvTimeoutError::Timeout) => continue,
+                            _ => break,
+                        }
+                    }
+                    debug!("Policy bundle poller for {} stopped", config.url);
+                })
+                .map_err(|e| McpError::Internal(format!("Failed to start bundle poller: {}", e)))?;
+        }
+
+        Ok(Self { state, _stop: stop })
+    }
+
+    /// Revision of the active bundle
+    pub fn revision(&self) -> Option<String> {
+        self.state().revision
+    }
+
+    /// Current poller state
+    pub fn state(&self) -> BundleState {
+        self.state.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Download and activate the bundle if it changed; returns the new revision
+fn poll_once(
+    agent: &ureq::Agent,
+    config: &BundleConfig,
+    state: &RwLock<BundleState>,
+    engine: &PolicyEngine,
+) -> McpResult<Option<String>> {
+    let etag = state.read().unwrap_or_else(|e| e.into_inner()).etag.clone();
+
+    let Some((body, new_etag)) = fetch(agent, config, &config.url, etag.as_deref())? else {
+        debug!("Policy bundle not modified: {}", config.url);
+        state.write().unwrap_or_else(|e| e.into_inner()).last_error = None;
+        return Ok(None);
+    };
+
+    let bundle = Bundle::from_tar_gz(&body, config.max_size_bytes)?;
+    let signature = verify_bundle(agent, config, &bundle, &body).inspect_err(|e| {
+        state.write().unwrap_or_else(|e| e.into_inner()).signature = Some(SignatureStatus::Rejected {
+            reason: e.to_string(),
+        });
+    })?;
+
+    // Activate only after every module has compiled
+    let evaluator = bundle.compile(&config.url)?;
+    engine.activate_bundle(evaluator, &bundle.revision);
+
+    let mut state = state.write().unwrap_or_else(|e| e.into_inner());
+    state.revision = Some(bundle.revision.clone());
+    state.etag = new_etag;
+    state.activated_at_ms = Some(current_timestamp_ms());
+    state.last_error = None;
+    state.signature = Some(signature);
+
+    info!("Activated policy bundle revision '{}' from {}", bundle.revision, config.url);
+    Ok(Some(bundle.revision))
+}
+
+/// Verify the embedded and/or detached signature required by the settings
+fn verify_bundle(
+    agent: &ureq::Agent,
+    config: &BundleConfig,
+    bundle: &Bundle,
+    archive: &[u8],
+) -> McpResult<SignatureStatus> {
+    if config.verification.is_none() && config.trust_keys.is_empty() {
+        warn!("Activating policy bundle without signature verification: {}", config.url);
+        return Ok(SignatureStatus::Unverified);
+    }
+
+    let mut key_id = None;
+    if let Some(verification) = &config.verification {
+        bundle.verify(verification)?;
+        key_id = verification.key_id.clone();
+    }
+    if !config.trust_keys.is_empty() {
+        let location = config.signature_location();
+        let (signature, _) = fetch(agent, config, &location, None)?
+            .ok_or_else(|| McpError::PolicyViolation(format!("Bundle signature {} is missing", location)))?;
+        key_id = Some(verify_detached_signature(archive, &signature, &config.trust_keys)?);
+    }
+    Ok(SignatureStatus::Verified { key_id })
+}
+
+/// Read a bundle (or signature) location and its ETag; `None` if it still matches `etag`
+///
+/// Local files use their size and modification time as ETag.
+fn fetch(
+    agent: &ureq::Agent,
+    config: &BundleConfig,
+    location: &str,
+    etag: Option<&str>,
+) -> McpResult<Option<(Vec<u8>, Option<String>)>> {
+    let too_large = || {
+        McpError::InvalidRequest(format!(
+            "Bundle exceeds the maximum size of {} bytes",
+            config.max_size_bytes
+        ))
+    };
+
+    if let Some(path) = local_path(location) {
+        let read_error = |e: std::io::Error| {
+            McpError::ExternalService(format!("Failed to read bundle file {}: {}", path.display(), e))
+        };
+        let metadata = std::fs::metadata(&path).map_err(read_error)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        let file_etag = format!("{}-{}", metadata.len(), modified.as_nanos());
+        if etag == Some(file_etag.as_str()) {
+            return Ok(None);
+        }
+        if metadata.len() > config.max_size_bytes {
+            return Err(too_large());
+        }
+        return Ok(Some((std::fs::read(&path).map_err(read_error)?, Some(file_etag))));
+    }
+
+    let mut request = agent.get(location);
+    if let Some(etag) = etag {
+        request = request.set("If-None-Match", etag);
+    }
+    if let Some(token) = &config.auth_token {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, _)) => {
+            return Err(McpError::ExternalService(format!("Bundle server returned HTTP {} for {}", code, location)))
+        }
+        Err(e) => return Err(McpError::ExternalService(format!("Failed to download {}: {}", location, e))),
+    };
+
+    if response.status() == 304 {
+        return Ok(None);
+    }
+
+    let new_etag = response.header("ETag").map(str::to_string);
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(config.max_size_bytes + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| McpError::ExternalService(format!("Failed to read {}: {}", location, e)))?;
+    if body.len() as u64 > config.max_size_bytes {
+        return Err(too_large());
+    }
+    Ok(Some((body, new_etag)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::json;
+
+    const POLICY: &str = "package mcp\nimport future.keywords.if\ndefault allow = false\nallow if { input.command.name == \"make\" }\n";
+
+    fn tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            // Archive entries are relative; signatures may still use OPA's "/name" form
+            builder.append_data(&mut header, name.trim_start_matches('/'), *content).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn signatures(files: &[(&str, &[u8])], secret: &[u8]) -> Vec<u8> {
+        let files: Vec<_> = files
+            .iter()
+            .map(|(name, content)| {
+                json!({"name": name, "hash": file_hash(name, content).unwrap(), "algorithm": "SHA-256"})
+            })
+            .collect();
+        let token = jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &json!({"files": files, "keyid": "test"}),
+            &EncodingKey::from_secret(secret),
+        )
+        .unwrap();
+        serde_json::to_vec(&json!({"signatures": [token]})).unwrap()
+    }
+
+    fn verification(secret: &[u8]) -> BundleVerification {
+        BundleVerification {
+            algorithm: Algorithm::HS256,
+            key: secret.to_vec(),
+            key_id: Some("test".to_string()),
+        }
+    }
+
+    // Test for reading, verifying and compiling a signed bundle
+    #[tokio::test]
+    async fn test_signed_bundle() {
+        let manifest = br#"{"revision": "rev-1"}"#;
+        let files: [(&str, &[u8]); 2] = [("/policy.rego", POLICY.as_bytes()), ("/.manifest", manifest)];
+        let signatures = signatures(&files, b"secret");
+
+        let archive = tar_gz(&[files[0], files[1], ("/.signatures.json", &signatures)]);
+        let bundle = Bundle::from_tar_gz(&archive, 1024 * 1024).unwrap();
+        assert_eq!(bundle.revision, "rev-1");
+        assert!(bundle.is_signed());
+
+        bundle.verify(&verification(b"secret")).unwrap();
+        assert!(bundle.verify(&verification(b"other")).is_err());
+
+        let engine = PolicyEngine::with_evaluator(bundle.compile("test").unwrap());
+        let mut input = crate::models::PolicyInput {
+            user: Default::default(),
+            command: Default::default(),
+            file: None,
+            network: None,
+            resources: Default::default(),
+            context: Default::default(),
+        };
+        input.command.name = "make".to_string();
+        assert!(engine.check_command_execution(&input).await.is_ok());
+    }
+
+    // Test for rejecting tampered and unsigned bundles
+    #[test]
+    fn test_tampered_bundle() {
+        let files: [(&str, &[u8]); 1] = [("policy.rego", POLICY.as_bytes())];
+        let signatures = signatures(&files, b"secret");
+
+        // Modified file
+        let archive = tar_gz(&[("policy.rego", b"package mcp\n"), (".signatures.json", &signatures)]);
+        let bundle = Bundle::from_tar_gz(&archive, 1024 * 1024).unwrap();
+        assert!(bundle.verify(&verification(b"secret")).is_err());
+
+        // Unsigned extra file
+        let archive = tar_gz(&[files[0], ("extra.rego", b"package extra\n"), (".signatures.json", &signatures)]);
+        let bundle = Bundle::from_tar_gz(&archive, 1024 * 1024).unwrap();
+        assert!(bundle.verify(&verification(b"secret")).is_err());
+
+        // No signatures at all
+        let bundle = Bundle::from_tar_gz(&tar_gz(&files), 1024 * 1024).unwrap();
+        assert!(!bundle.is_signed());
+        assert!(bundle.verify(&verification(b"secret")).is_err());
+
+        // Size limit
+        assert!(Bundle::from_tar_gz(&tar_gz(&files), 8).is_err());
+    }
+
+    // Test for detached signatures of local bundles and the reported signature status
+    #[test]
+    fn test_detached_signature() {
+        use ed25519_dalek::pkcs8::{spki::der::pem::LineEnding, EncodePublicKey};
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let dir = tempfile::tempdir().unwrap();
+        let trust_key = |name: &str, signing_key: &SigningKey| {
+            let path = dir.path().join(format!("{}.pem", name));
+            let pem = signing_key.verifying_key().to_public_key_pem(LineEnding::LF).unwrap();
+            std::fs::write(&path, pem).unwrap();
+            BundleTrustKey::from_pem_file(&path).unwrap()
+        };
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let prod = trust_key("prod", &signing_key);
+        let other = trust_key("other", &SigningKey::from_bytes(&[8; 32]));
+        assert_eq!(prod.key_id, "prod");
+
+        let archive = tar_gz(&[("policy.rego", POLICY.as_bytes()), (".manifest", br#"{"revision": "rev-2"}"#)]);
+        let signature = base64::engine::general_purpose::STANDARD.encode(signing_key.sign(&archive).to_bytes());
+        let keys = [other.clone(), prod.clone()];
+        assert_eq!(verify_detached_signature(&archive, signature.as_bytes(), &keys).unwrap(), "prod");
+        assert!(verify_detached_signature(&archive, signature.as_bytes(), std::slice::from_ref(&other)).is_err());
+        assert!(verify_detached_signature(&archive[1..], signature.as_bytes(), &keys).is_err());
+        assert!(verify_detached_signature(&archive, b"not a signature", &keys).is_err());
+
+        let bundle_path = dir.path().join("bundle.tar.gz");
+        std::fs::write(&bundle_path, &archive).unwrap();
+        std::fs::write(dir.path().join("bundle.tar.gz.sig"), format!("{}\n", signature)).unwrap();
+
+        let poll = |trust_key: BundleTrustKey| {
+            let mut config = BundleConfig::new(&format!("file://{}", bundle_path.display()));
+            config.trust_keys = vec![trust_key];
+            let engine = PolicyEngine::with_evaluator(crate::engine::StubPolicyEvaluator::default());
+            let poller = engine.poll_bundle(config, |_| {}).unwrap();
+            for _ in 0..100 {
+                if poller.state().signature.is_some() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            poller.state()
+        };
+
+        let state = poll(prod);
+        assert_eq!(state.revision.as_deref(), Some("rev-2"));
+        assert_eq!(
+            state.signature,
+            Some(SignatureStatus::Verified {
+                key_id: Some("prod".to_string())
+            })
+        );
+        assert_eq!(state.signature.unwrap().to_string(), "verified (prod)");
+
+        // A bundle signed by an untrusted key is never activated
+        let state = poll(other);
+        assert_eq!(state.revision, None);
+        assert!(matches!(state.signature, Some(SignatureStatus::Rejected { .. })));
+    }
+
+    // Test for canonical JSON hashing
+    #[test]
+    fn test_file_hash_canonical_json() {
+        let compact = file_hash("data.json", br#"{"a":1,"b":2}"#).unwrap();
+        let formatted = file_hash("data.json", b"{\n  \"b\": 2,\n  \"a\": 1\n}").unwrap();
+        assert_eq!(compact, formatted);
+        assert_ne!(file_hash("x.rego", b"a").unwrap(), file_hash("x.rego", b"b").unwrap());
+    }
+}
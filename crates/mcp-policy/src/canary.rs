@@ -0,0 +1,253 @@
+//! Canary rollout of a new policy version
+//!
+//! While a [`PolicyCanary`] is active, `PolicyEngine` routes a percentage of the
+//! evaluations of the default evaluator to the candidate version. Routed evaluations are
+//! decided by the candidate and also evaluated with the stable version; the comparison is
+//! recorded as a [`CanaryOutcome`] in the audit record and reported to the canary observer
+//! ("match" or "divergence") so that the candidate can be checked before it is promoted.
+//!
+//! Routing is derived from a hash of the complete input, so the same request is always
+//! decided by the same version and cached decisions stay consistent. Tenants with their
+//! own evaluator are not part of the rollout.
+
+use crate::decision_cache::DecisionCache;
+use crate::engine::{load_policy_dir, AsyncPolicyEvaluator};
+use crate::models::{PolicyDecision, PolicyInput};
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::get_env_var_or;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Candidate policy version and the percentage of evaluations routed to it
+#[derive(Clone)]
+pub struct PolicyCanary {
+    candidate: Arc<dyn AsyncPolicyEvaluator>,
+    percent: u8,
+}
+
+impl fmt::Debug for PolicyCanary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolicyCanary")
+            .field("candidate", &self.candidate.name())
+            .field("percent", &self.percent)
+            .finish()
+    }
+}
+
+impl PolicyCanary {
+    /// Route `percent` (0 to 100) percent of the evaluations to a candidate evaluator
+    pub fn new(candidate: impl AsyncPolicyEvaluator + 'static, percent: u8) -> McpResult<Self> {
+        if percent > 100 {
+            return Err(McpError::InvalidRequest(format!(
+                "Canary percentage must be between 0 and 100: {}",
+                percent
+            )));
+        }
+
+        Ok(Self {
+            candidate: Arc::new(candidate),
+            percent,
+        })
+    }
+
+    /// Load the candidate version from a policy directory
+    ///
+    /// See [`load_policy_dir`] for how the policy language is chosen.
+    pub fn from_policy_dir(path: impl AsRef<Path>, percent: u8) -> McpResult<Self> {
+        Self::new(load_policy_dir(path.as_ref())?, percent)
+    }
+
+    /// Build the canary from environment variables
+    ///
+    /// * `MCP_POLICY_CANARY_DIR` - policy directory of the candidate version
+    /// * `MCP_POLICY_CANARY_PERCENT` - percentage of evaluations routed to it (default 10)
+    ///
+    /// Returns `None` if `MCP_POLICY_CANARY_DIR` is not set.
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let Ok(dir) = std::env::var("MCP_POLICY_CANARY_DIR") else {
+            return Ok(None);
+        };
+
+        let percent = get_env_var_or("MCP_POLICY_CANARY_PERCENT", "10");
+        let percent = percent.trim().parse().map_err(|_| {
+            McpError::InvalidRequest(format!("MCP_POLICY_CANARY_PERCENT must be a number: '{}'", percent))
+        })?;
+
+        Self::from_policy_dir(dir, percent).map(Some)
+    }
+
+    /// Candidate evaluator
+    pub fn candidate(&self) -> Arc<dyn AsyncPolicyEvaluator> {
+        self.candidate.clone()
+    }
+
+    /// Percentage of evaluations routed to the candidate
+    pub fn percent(&self) -> u8 {
+        self.percent
+    }
+
+    /// Whether an input is decided by the candidate
+    pub fn routes(&self, input: &PolicyInput) -> McpResult<bool> {
+        match self.percent {
+            0 => Ok(false),
+            100 => Ok(true),
+            percent => Ok(bucket(input)? < percent),
+        }
+    }
+}
+
+/// Bucket (0 to 99) of an input
+fn bucket(input: &PolicyInput) -> McpResult<u8> {
+    let key = DecisionCache::key(input)?;
+    let prefix = u32::from_str_radix(&key[..8], 16)
+        .map_err(|e| McpError::Internal(format!("Failed to hash policy input: {}", e)))?;
+    Ok((prefix % 100) as u8)
+}
+
+/// Comparison of the candidate decision with the stable version
+#[derive(Debug, Clone, Serialize)]
+pub struct CanaryOutcome {
+    /// Name of the stable evaluator
+    pub stable_evaluator: String,
+    /// Decision of the stable version (absent if the evaluation failed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stable_decision: Option<PolicyDecision>,
+    /// Evaluation error of the stable version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stable_error: Option<String>,
+    /// Whether the versions reached different outcomes (allow, deny or error)
+    pub diverged: bool,
+}
+
+impl CanaryOutcome {
+    /// Compare the results of the stable version and the candidate
+    pub fn compare(
+        stable_evaluator: &str,
+        stable: McpResult<PolicyDecision>,
+        candidate: &McpResult<PolicyDecision>,
+    ) -> Self {
+        let outcome = |result: &McpResult<PolicyDecision>| result.as_ref().ok().map(|decision| decision.allow);
+        let diverged = outcome(&stable) != outcome(candidate);
+
+        let (stable_decision, stable_error) = match stable {
+            Ok(decision) => (Some(decision), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        Self {
+            stable_evaluator: stable_evaluator.to_string(),
+            stable_decision,
+            stable_error,
+            diverged,
+        }
+    }
+
+    /// Observer label of the outcome
+    pub fn label(&self) -> &'static str {
+        if self.diverged {
+            "divergence"
+        } else {
+            "match"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditRecord, AuditSink};
+    use crate::engine::{PolicyEngine, PolicyEvaluator, StubPolicyEvaluator};
+    use crate::test_support::input;
+    use std::sync::Mutex;
+
+    struct Fixed {
+        name: &'static str,
+        allow: bool,
+    }
+
+    impl PolicyEvaluator for Fixed {
+        fn evaluate(&self, _input: &PolicyInput) -> McpResult<PolicyDecision> {
+            Ok(PolicyDecision {
+                allow: self.allow,
+                warnings: vec![],
+                reasons: vec![],
+                deny_reasons: vec![],
+                metadata: Default::default(),
+            })
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<AuditRecord>>);
+
+    impl AuditSink for MemorySink {
+        fn record(&self, record: &AuditRecord) {
+            self.0.lock().unwrap().push(record.clone());
+        }
+    }
+
+    // Test for routing a stable share of the inputs
+    #[test]
+    fn test_routing() {
+        let inputs: Vec<PolicyInput> = (0..1000).map(|i| input(&format!("command-{}", i))).collect();
+        let routed = |percent| {
+            let canary = PolicyCanary::new(StubPolicyEvaluator::default(), percent).unwrap();
+            inputs.iter().filter(|input| canary.routes(input).unwrap()).count()
+        };
+
+        assert_eq!(routed(0), 0);
+        assert_eq!(routed(100), 1000);
+        let share = routed(30);
+        assert!((200..400).contains(&share), "unexpected share {}", share);
+
+        // The same input is always routed the same way
+        let canary = PolicyCanary::new(StubPolicyEvaluator::default(), 50).unwrap();
+        assert_eq!(canary.routes(&inputs[0]).unwrap(), canary.routes(&inputs[0]).unwrap());
+
+        assert!(PolicyCanary::new(StubPolicyEvaluator::default(), 101).is_err());
+    }
+
+    // Test for reporting divergences and promoting the candidate
+    #[tokio::test]
+    async fn test_engine_canary() {
+        let sink = Arc::new(MemorySink::default());
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let labels = observed.clone();
+        let engine = PolicyEngine::with_evaluator(Fixed { name: "v1", allow: true })
+            .with_audit_sink(sink.clone())
+            .with_canary_observer(move |label| labels.lock().unwrap().push(label.to_string()));
+
+        engine.start_canary(PolicyCanary::new(Fixed { name: "v2", allow: false }, 100).unwrap());
+        assert_eq!(engine.canary().unwrap().percent(), 100);
+        assert!(engine.check_command_execution(&input("ls")).await.is_err());
+
+        {
+            let records = sink.0.lock().unwrap();
+            assert_eq!(records[0].evaluator, "v2");
+            let canary = records[0].canary.as_ref().unwrap();
+            assert_eq!(canary.stable_evaluator, "v1");
+            assert!(canary.stable_decision.as_ref().unwrap().allow);
+            assert!(canary.diverged);
+        }
+        assert_eq!(*observed.lock().unwrap(), vec!["divergence"]);
+
+        // Nothing is routed at 0%
+        engine.start_canary(PolicyCanary::new(Fixed { name: "v2", allow: false }, 0).unwrap());
+        engine.check_command_execution(&input("ls")).await.unwrap();
+        assert!(sink.0.lock().unwrap()[1].canary.is_none());
+
+        // Promotion makes the candidate the default evaluator
+        assert!(engine.promote_canary());
+        assert!(engine.canary().is_none());
+        assert!(engine.check_command_execution(&input("ls")).await.is_err());
+        assert_eq!(sink.0.lock().unwrap()[2].evaluator, "v2");
+        assert!(!engine.promote_canary());
+        assert_eq!(observed.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,180 @@
+//! Canonicalization of file paths before policy evaluation
+//!
+//! Policies match file paths as strings, so `/workspace/../etc/passwd` would pass a rule
+//! allowing `/workspace/` unless the path is normalized first. `PolicyEngine` therefore
+//! rewrites `PolicyInput.file.path` with a [`PathCanonicalizer`] before every evaluation:
+//!
+//! * relative paths are resolved against the working directory of the command (if it is
+//!   absolute),
+//! * the path is normalized lexically (see [`normalize_path`]),
+//! * with a sandbox root, symlinks are resolved instead as the sandbox would see them: the
+//!   path `/workspace/link` is looked up as `<root>/workspace/link`, absolute link targets
+//!   are interpreted relative to the root so that a link cannot lead outside of it, and
+//!   `..` steps out of the directory a link resolved to, as the kernel does.
+//!
+//! Evaluators, the decision cache and the audit records all see the canonical path.
+
+use crate::path_pattern::normalize_path;
+use mcp_common::error::{McpError, McpResult};
+use std::path::{Path, PathBuf};
+
+/// Maximum number of symlinks followed while resolving one path
+const MAX_SYMLINKS: usize = 40;
+
+/// Rewrites file paths into their canonical form
+#[derive(Debug, Clone, Default)]
+pub struct PathCanonicalizer {
+    sandbox_root: Option<PathBuf>,
+}
+
+impl PathCanonicalizer {
+    /// Canonicalizer normalizing paths lexically, without consulting the file system
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve symlinks within the file system of a sandbox rooted at `root`
+    pub fn with_sandbox_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.sandbox_root = Some(root.into());
+        self
+    }
+
+    /// Build the canonicalizer from environment variables
+    ///
+    /// * `MCP_POLICY_SANDBOX_ROOT` - directory holding the file system of the sandbox; symlinks
+    ///   are resolved only if it is set
+    pub fn from_env() -> McpResult<Self> {
+        let Ok(root) = std::env::var("MCP_POLICY_SANDBOX_ROOT") else {
+            return Ok(Self::new());
+        };
+        if !Path::new(&root).is_dir() {
+            return Err(McpError::InvalidRequest(format!(
+                "MCP_POLICY_SANDBOX_ROOT must be an existing directory: '{}'",
+                root
+            )));
+        }
+        Ok(Self::new().with_sandbox_root(root))
+    }
+
+    /// Root of the sandbox file system, if symlinks are resolved
+    pub fn sandbox_root(&self) -> Option<&Path> {
+        self.sandbox_root.as_deref()
+    }
+
+    /// Canonical form of a path; relative paths are resolved against `cwd` if it is absolute
+    pub fn canonicalize(&self, path: &str, cwd: &str) -> McpResult<String> {
+        let path = if !path.starts_with('/') && cwd.starts_with('/') {
+            format!("{}/{}", cwd, path)
+        } else {
+            path.to_string()
+        };
+
+        match &self.sandbox_root {
+            Some(root) if path.starts_with('/') => resolve_symlinks(root, &path),
+            _ => Ok(normalize_path(&path)),
+        }
+    }
+}
+
+// Resolve the symlinks, `.` and `..` of an absolute path within the sandbox root
+fn resolve_symlinks(root: &Path, path: &str) -> McpResult<String> {
+    // Segments still to resolve, in reverse order
+    let mut pending: Vec<String> = path.split('/').rev().map(str::to_string).collect();
+    let mut resolved: Vec<String> = Vec::new();
+    let mut links = 0;
+    // Below a missing directory nothing can be a symlink
+    let mut exists = true;
+
+    while let Some(segment) = pending.pop() {
+        match segment.as_str() {
+            "" | "." => continue,
+            ".." => {
+                resolved.pop();
+                continue;
+            }
+            _ => resolved.push(segment),
+        }
+        if !exists {
+            continue;
+        }
+
+        let host_path = root.join(resolved.join("/"));
+        let metadata = match std::fs::symlink_metadata(&host_path) {
+            Ok(metadata) => metadata,
+            Err(_) => {
+                exists = false;
+                continue;
+            }
+        };
+        if !metadata.file_type().is_symlink() {
+            continue;
+        }
+
+        links += 1;
+        if links > MAX_SYMLINKS {
+            return Err(McpError::PolicyViolation(format!(
+                "Too many levels of symbolic links in path '{}'",
+                path
+            )));
+        }
+        let target = std::fs::read_link(&host_path).map_err(|e| {
+            McpError::Internal(format!("Failed to read symbolic link {}: {}", host_path.display(), e))
+        })?;
+        let target = target.to_string_lossy();
+
+        resolved.pop();
+        if target.starts_with('/') {
+            resolved.clear();
+        }
+        pending.extend(target.split('/').rev().map(str::to_string));
+    }
+
+    Ok(format!("/{}", resolved.join("/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    // Test for lexical canonicalization
+    #[test]
+    fn test_canonicalize_lexically() {
+        let canonicalizer = PathCanonicalizer::new();
+        assert_eq!(canonicalizer.canonicalize("/workspace/../etc/passwd", "").unwrap(), "/etc/passwd");
+        assert_eq!(canonicalizer.canonicalize("src/./main.rs", "/workspace").unwrap(), "/workspace/src/main.rs");
+        assert_eq!(canonicalizer.canonicalize("../../etc/passwd", "/workspace").unwrap(), "/etc/passwd");
+        assert_eq!(canonicalizer.canonicalize("src//main.rs", "").unwrap(), "src/main.rs");
+
+    }
+
+    // Test for resolving symlinks within the sandbox root
+    #[test]
+    fn test_resolve_symlinks() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("workspace/src")).unwrap();
+        std::fs::create_dir_all(root.path().join("etc")).unwrap();
+        symlink("/etc/passwd", root.path().join("workspace/passwd")).unwrap();
+        symlink("../etc", root.path().join("workspace/config")).unwrap();
+        symlink("src", root.path().join("workspace/code")).unwrap();
+        symlink("../../../../etc", root.path().join("workspace/escape")).unwrap();
+        symlink("loop", root.path().join("workspace/loop")).unwrap();
+
+        let canonicalizer = PathCanonicalizer::new().with_sandbox_root(root.path());
+        let canonicalize = |path: &str| canonicalizer.canonicalize(path, "/workspace");
+
+        assert_eq!(canonicalize("/workspace/passwd").unwrap(), "/etc/passwd");
+        assert_eq!(canonicalize("config/hosts").unwrap(), "/etc/hosts");
+        assert_eq!(canonicalize("/workspace/code/main.rs").unwrap(), "/workspace/src/main.rs");
+        assert_eq!(canonicalize("/workspace/code/../code/lib.rs").unwrap(), "/workspace/src/lib.rs");
+        // `..` leaves the directory the link resolved to
+        assert_eq!(canonicalize("/workspace/config/../passwd").unwrap(), "/passwd");
+        assert_eq!(canonicalize("/../workspace/./src//x").unwrap(), "/workspace/src/x");
+        // Links cannot climb above the sandbox root
+        assert_eq!(canonicalize("/workspace/escape/shadow").unwrap(), "/etc/shadow");
+        // Paths that do not exist yet are kept
+        assert_eq!(canonicalize("/workspace/new/file.txt").unwrap(), "/workspace/new/file.txt");
+
+        assert!(canonicalize("/workspace/loop").is_err());
+    }
+}
@@ -0,0 +1,478 @@
+//! Cedar policy evaluator
+//!
+//! Evaluates `.cedar` policies with the Cedar authorizer. A `PolicyInput` is mapped to a
+//! Cedar request as follows (all types live in the `Mcp` namespace):
+//!
+//! | Input                | Principal               | Action                          | Resource               |
+//! |----------------------|-------------------------|---------------------------------|------------------------|
+//! | `file` is set        | `Mcp::User::"<id>"`     | `Mcp::Action::"file.<mode>"`    | `Mcp::File::"<path>"`  |
+//! | `network` is set     | `Mcp::User::"<id>"`     | `Mcp::Action::"network.connect"`| `Mcp::Host::"<host>"`  |
+//! | otherwise            | `Mcp::User::"<id>"`     | `Mcp::Action::"command.execute"`| `Mcp::Command::"<name>"` |
+//!
+//! The user entity has the attributes `tenant_id`, `roles` and `attributes` and is a member
+//! of `Mcp::Role::"<role>"` for each role and of `Mcp::Tenant::"<tenant_id>"`. The resource
+//! entity carries the fields of the corresponding input section, and the request context
+//! holds `input.context` plus a `resources` record. Entities from `entities.json` files are
+//! added to every request.
+//!
+//! Policy annotations are used for the decision details: `@reason("...")` on a `forbid`
+//! policy becomes the denial reason, `@warning("...")` on a determining `permit` policy
+//! becomes a warning and `@cacheable("true")` marks the command as cacheable.
+//!
+//! Explanations list the determining policies, identified by their `@id` annotation (or
+//! the generated policy ID) and the file that contains them.
+
+use crate::engine::{self, PolicyEvaluator};
+use crate::models::{PolicyDecision, PolicyExplanation, PolicyInput, RuleEffect, RuleMatch, METADATA_CACHEABLE};
+use cedar_policy::{
+    Authorizer, Context, Decision, Effect, Entities, EntityId, EntityTypeName, EntityUid, PolicyId, PolicySet,
+    Request,
+};
+use mcp_common::error::{McpError, McpResult};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::{debug, info};
+
+/// Namespace of the entity types and actions
+pub const NAMESPACE: &str = "Mcp";
+
+/// Decision metadata key listing the policies that determined the decision
+pub const METADATA_CEDAR_POLICIES: &str = "cedar_policies";
+
+/// Policy evaluator backed by Cedar policies
+pub struct CedarEvaluator {
+    policies: PolicySet,
+    entities: Entities,
+    authorizer: Authorizer,
+    origin: String,
+    // File of every policy
+    sources: HashMap<PolicyId, String>,
+}
+
+impl CedarEvaluator {
+    /// Load all policy files below a directory
+    ///
+    /// Files ending in `.cedar` are added as policies and files named `entities.json` are
+    /// loaded as static entities. Directories are searched recursively.
+    pub fn from_dir(dir: impl AsRef<Path>) -> McpResult<Self> {
+        let dir = dir.as_ref();
+        let mut policies = Vec::new();
+        let mut entities = Vec::new();
+
+        for path in engine::policy_files(dir, "cedar", "entities.json")? {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| McpError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+            if path.extension().is_some_and(|ext| ext == "cedar") {
+                policies.push((path.to_string_lossy().into_owned(), content));
+            } else {
+                entities.push((path.to_string_lossy().into_owned(), content));
+            }
+        }
+
+        Self::from_sources(&dir.display().to_string(), policies, entities)
+    }
+
+    /// Compile policies and entity documents held in memory
+    ///
+    /// `policies` and `entities` are `(name, content)` pairs; names are only used in messages.
+    pub fn from_sources(
+        origin: &str,
+        policies: Vec<(String, String)>,
+        entities: Vec<(String, String)>,
+    ) -> McpResult<Self> {
+        if policies.is_empty() {
+            return Err(McpError::Internal(format!("No .cedar policy files found in {}", origin)));
+        }
+
+        // Parse each file separately for error messages, then together so policy IDs are unique
+        let mut file_of_policy = Vec::new();
+        for (name, source) in &policies {
+            let file_policies = PolicySet::from_str(source)
+                .map_err(|e| McpError::Internal(format!("Failed to compile policy {}: {}", name, e)))?;
+            file_of_policy.extend(std::iter::repeat_n(name.clone(), file_policies.policies().count()));
+        }
+        let combined = policies
+            .iter()
+            .map(|(_, source)| source.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let policy_set = PolicySet::from_str(&combined)
+            .map_err(|e| McpError::Internal(format!("Failed to compile policies from {}: {}", origin, e)))?;
+
+        let mut static_entities = Entities::empty();
+        for (name, content) in entities {
+            let value: Value = serde_json::from_str(&content)
+                .map_err(|e| McpError::Internal(format!("Failed to parse entities {}: {}", name, e)))?;
+            static_entities = static_entities
+                .add_entities_from_json_value(value, None)
+                .map_err(|e| McpError::Internal(format!("Failed to load entities {}: {}", name, e)))?;
+        }
+
+        // Policies of the combined set are numbered in source order
+        let sources = file_of_policy
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| (PolicyId::new(format!("policy{}", index)), name))
+            .collect();
+
+        info!("Loaded {} Cedar policies from {}", policy_set.policies().count(), origin);
+
+        Ok(Self {
+            policies: policy_set,
+            entities: static_entities,
+            authorizer: Authorizer::new(),
+            origin: origin.to_string(),
+            sources,
+        })
+    }
+
+    /// Where the policies were loaded from
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    fn request(&self, input: &PolicyInput) -> McpResult<(Request, Entities)> {
+        let principal = entity_uid("User", &input.user.id)?;
+
+        let mut parents: Vec<Value> = input
+            .user
+            .roles
+            .iter()
+            .map(|role| uid_json("Role", role))
+            .collect();
+        if !input.user.tenant_id.is_empty() {
+            parents.push(uid_json("Tenant", &input.user.tenant_id));
+        }
+
+        let user = json!({
+            "uid": uid_json("User", &input.user.id),
+            "attrs": {
+                "tenant_id": input.user.tenant_id,
+                "roles": input.user.roles,
+                "attributes": input.user.attributes,
+            },
+            "parents": parents,
+        });
+
+        let (action, resource_type, resource_id, attrs) = match (&input.file, &input.network) {
+            (Some(file), _) => (
+                format!("file.{}", file.mode),
+                "File",
+                file.path.clone(),
+                json!({ "path": file.path, "mode": file.mode }),
+            ),
+            (None, Some(network)) => (
+                "network.connect".to_string(),
+                "Host",
+                network.host.clone(),
+                json!({ "host": network.host, "port": network.port, "protocol": network.protocol }),
+            ),
+            (None, None) => (
+                "command.execute".to_string(),
+                "Command",
+                input.command.name.clone(),
+                json!({
+                    "name": input.command.name,
+                    "args": input.command.args,
+                    "cwd": input.command.cwd,
+                    "env": input.command.env,
+                }),
+            ),
+        };
+
+        let resource = json!({
+            "uid": uid_json(resource_type, &resource_id),
+            "attrs": to_cedar_value(attrs),
+            "parents": [],
+        });
+
+        let mut context = input
+            .context
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<serde_json::Map<_, _>>();
+        context.insert(
+            "resources".to_string(),
+            serde_json::to_value(&input.resources)
+                .map_err(|e| McpError::Internal(format!("Failed to serialize resources: {}", e)))?,
+        );
+        let context = Context::from_json_value(to_cedar_value(Value::Object(context)), None)
+            .map_err(|e| McpError::InvalidRequest(format!("Invalid Cedar context: {}", e)))?;
+
+        let entities = self
+            .entities
+            .clone()
+            .add_entities_from_json_value(json!([to_cedar_value(user), resource]), None)
+            .map_err(|e| McpError::InvalidRequest(format!("Invalid Cedar entities: {}", e)))?;
+
+        let request = Request::new(
+            principal,
+            entity_uid("Action", &action)?,
+            entity_uid(resource_type, &resource_id)?,
+            context,
+            None,
+        )
+        .map_err(|e| McpError::InvalidRequest(format!("Invalid Cedar request: {}", e)))?;
+
+        Ok((request, entities))
+    }
+}
+
+impl PolicyEvaluator for CedarEvaluator {
+    fn evaluate(&self, input: &PolicyInput) -> McpResult<PolicyDecision> {
+        Ok(PolicyEvaluator::explain(self, input)?.decision)
+    }
+
+    fn name(&self) -> &str {
+        "cedar"
+    }
+
+    fn explain(&self, input: &PolicyInput) -> McpResult<PolicyExplanation> {
+        let (request, entities) = self.request(input)?;
+        let response = self.authorizer.is_authorized(&request, &self.policies, &entities);
+        let diagnostics = response.diagnostics();
+
+        let determining: Vec<_> = diagnostics.reason().collect();
+        let mut decision = PolicyDecision {
+            allow: response.decision() == Decision::Allow,
+            warnings: diagnostics
+                .errors()
+                .map(|e| format!("Cedar policy evaluation error: {}", e))
+                .collect(),
+            reasons: Vec::new(),
+            deny_reasons: vec![],
+            metadata: HashMap::new(),
+        };
+
+        let mut rules = Vec::new();
+        for id in &determining {
+            let Some(policy) = self.policies.policy(id) else {
+                continue;
+            };
+            let message = if decision.allow {
+                let warning = policy.annotation("warning").map(str::to_string);
+                decision.warnings.extend(warning.clone());
+                warning.unwrap_or_default()
+            } else {
+                let reason = policy
+                    .annotation("reason")
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("Denied by policy {}", id));
+                decision.reasons.push(reason.clone());
+                reason
+            };
+            rules.push(RuleMatch {
+                rule: policy.annotation("id").map_or_else(|| id.to_string(), str::to_string),
+                source: self.sources.get(*id).cloned().unwrap_or_else(|| self.origin.clone()),
+                effect: match policy.effect() {
+                    Effect::Permit => RuleEffect::Allow,
+                    Effect::Forbid => RuleEffect::Deny,
+                },
+                message,
+            });
+        }
+
+        // Cedar denies by default when no permit policy matches
+        if !decision.allow && decision.reasons.is_empty() {
+            decision.reasons.push(format!(
+                "No policy permits {} on {}",
+                request.action().map(|a| a.to_string()).unwrap_or_default(),
+                request.resource().map(|r| r.to_string()).unwrap_or_default()
+            ));
+        }
+
+        if decision.allow
+            && determining
+                .iter()
+                .any(|id| self.policies.annotation(id, "cacheable") == Some("true"))
+        {
+            decision.metadata.insert(METADATA_CACHEABLE.to_string(), Value::Bool(true));
+        }
+        decision.metadata.insert(
+            METADATA_CEDAR_POLICIES.to_string(),
+            json!(determining.iter().map(|id| id.to_string()).collect::<Vec<_>>()),
+        );
+
+        debug!("Cedar decision: allow={} policies={:?}", decision.allow, determining);
+        Ok(PolicyExplanation {
+            evaluator: PolicyEvaluator::name(self).to_string(),
+            decision,
+            rules,
+        })
+    }
+}
+
+fn uid_json(entity_type: &str, id: &str) -> Value {
+    json!({ "type": format!("{}::{}", NAMESPACE, entity_type), "id": id })
+}
+
+fn entity_uid(entity_type: &str, id: &str) -> McpResult<EntityUid> {
+    let type_name = EntityTypeName::from_str(&format!("{}::{}", NAMESPACE, entity_type))
+        .map_err(|e| McpError::Internal(format!("Invalid Cedar entity type {}: {}", entity_type, e)))?;
+    Ok(EntityUid::from_type_name_and_id(type_name, EntityId::new(id)))
+}
+
+/// Convert JSON into values Cedar accepts (no nulls, no floating point numbers)
+fn to_cedar_value(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, to_cedar_value(value)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .filter(|value| !value.is_null())
+                .map(to_cedar_value)
+                .collect(),
+        ),
+        Value::Number(number) if !number.is_i64() => Value::String(number.to_string()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{FileInfo, NetworkInfo};
+    use crate::test_support::input_with_roles as input;
+
+    fn repo_policies() -> CedarEvaluator {
+        CedarEvaluator::from_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../../policies/cedar")).unwrap()
+    }
+
+    // Test for command policies shipped with the repository
+    #[test]
+    fn test_repo_command_policies() {
+        let evaluator = repo_policies();
+
+        let decision = evaluator.evaluate(&input("ls", &["user"])).unwrap();
+        assert!(decision.allow);
+        assert!(decision.is_cacheable());
+
+        let decision = evaluator.evaluate(&input("python", &["user"])).unwrap();
+        assert!(decision.allow);
+        assert!(!decision.is_cacheable());
+
+        // Forbidden for everyone, with the annotated reason
+        let decision = evaluator.evaluate(&input("rm", &["admin"])).unwrap();
+        assert!(!decision.allow);
+        assert_eq!(decision.reasons.len(), 1);
+        assert!(!decision.reasons[0].starts_with("Denied by policy"));
+
+        // Not permitted at all
+        let decision = evaluator.evaluate(&input("nc", &["user"])).unwrap();
+        assert!(!decision.allow);
+        assert!(!decision.reasons.is_empty());
+    }
+
+    // Test for file and network policies shipped with the repository
+    #[test]
+    fn test_repo_file_and_network_policies() {
+        let evaluator = repo_policies();
+
+        let mut file_input = input("", &["user"]);
+        file_input.file = Some(FileInfo {
+            path: "/workspace/data.txt".to_string(),
+            mode: "read".to_string(),
+        });
+        assert!(evaluator.evaluate(&file_input).unwrap().allow);
+
+        file_input.file = Some(FileInfo {
+            path: "/etc/passwd".to_string(),
+            mode: "read".to_string(),
+        });
+        assert!(!evaluator.evaluate(&file_input).unwrap().allow);
+
+        let mut network_input = input("", &["user"]);
+        network_input.network = Some(NetworkInfo {
+            host: "api.example.com".to_string(),
+            port: 443,
+            protocol: "tcp".to_string(),
+        });
+        let decision = evaluator.evaluate(&network_input).unwrap();
+        assert!(decision.allow);
+        assert_eq!(decision.warnings.len(), 1);
+
+        network_input.network.as_mut().unwrap().host = "example.com".to_string();
+        assert!(!evaluator.evaluate(&network_input).unwrap().allow);
+    }
+
+    // Test for static entities, context values and load errors
+    #[test]
+    fn test_from_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        assert!(CedarEvaluator::from_dir(dir).is_err());
+
+        std::fs::write(
+            dir.join("policy.cedar"),
+            r#"permit(principal in Mcp::Role::"ops", action == Mcp::Action::"command.execute", resource)
+               when { context.ticket != "" && resource.args.contains("-v") };"#,
+        )
+        .unwrap();
+        // Role hierarchy: "oncall" is part of "ops"
+        std::fs::write(
+            dir.join("entities.json"),
+            r#"[{"uid": {"type": "Mcp::Role", "id": "oncall"}, "attrs": {}, "parents": [{"type": "Mcp::Role", "id": "ops"}]}]"#,
+        )
+        .unwrap();
+
+        let evaluator = CedarEvaluator::from_dir(dir).unwrap();
+        let mut request = input("make", &["oncall"]);
+        request.command.args = vec!["-v".to_string()];
+        request.context.insert("ticket".to_string(), json!("INC-1"));
+        request.context.insert("ratio".to_string(), json!(0.5));
+        assert!(evaluator.evaluate(&request).unwrap().allow);
+
+        request.context.insert("ticket".to_string(), json!(""));
+        assert!(!evaluator.evaluate(&request).unwrap().allow);
+
+        // Syntax errors are reported at load time
+        std::fs::write(dir.join("broken.cedar"), "permit(principal").unwrap();
+        assert!(CedarEvaluator::from_dir(dir).is_err());
+    }
+
+    // Test for explaining decisions with the determining policies
+    #[test]
+    fn test_explain() {
+        let evaluator = repo_policies();
+
+        let explanation = evaluator.explain(&input("rm", &["admin"])).unwrap();
+        assert!(!explanation.decision.allow);
+        assert_eq!(explanation.evaluator, "cedar");
+        assert_eq!(explanation.rules.len(), 1);
+        assert!(explanation.rules[0].source.ends_with("command.cedar"));
+        assert_eq!(explanation.rules[0].effect, RuleEffect::Deny);
+        assert_eq!(explanation.rules[0].message, explanation.decision.reasons[0]);
+
+        let explanation = evaluator.explain(&input("ls", &["user"])).unwrap();
+        assert!(explanation.decision.allow);
+        assert!(!explanation.rules.is_empty());
+        assert!(explanation.rules.iter().all(|rule| rule.effect == RuleEffect::Allow));
+
+        // The @id annotation names the policy
+        let evaluator = CedarEvaluator::from_sources(
+            "memory",
+            vec![
+                ("a.cedar".to_string(), "permit(principal, action, resource);".to_string()),
+                (
+                    "b.cedar".to_string(),
+                    "@id(\"no-rm\") forbid(principal, action, resource == Mcp::Command::\"rm\");".to_string(),
+                ),
+            ],
+            vec![],
+        )
+        .unwrap();
+        let explanation = evaluator.explain(&input("rm", &["user"])).unwrap();
+        assert_eq!(explanation.rules[0].rule, "no-rm");
+        assert_eq!(explanation.rules[0].source, "b.cedar");
+        let explanation = evaluator.explain(&input("ls", &["user"])).unwrap();
+        assert_eq!(explanation.rules[0].rule, "policy0");
+        assert_eq!(explanation.rules[0].source, "a.cedar");
+    }
+}
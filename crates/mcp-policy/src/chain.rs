@@ -0,0 +1,387 @@
+//! Composition of several policy evaluators
+//!
+//! A [`ChainedEvaluator`] runs its evaluators in order (e.g. the local rule engine before a
+//! remote OPA server) and combines their decisions according to a [`CombineMode`]. Warnings
+//! and denial reasons of every evaluator that ran are aggregated in the combined decision,
+//! and its metadata is merged with earlier evaluators taking precedence. An evaluation
+//! error of any evaluator that runs fails the whole chain.
+
+use crate::engine::{load_policy_dir, load_policy_plugin, load_policy_script, AsyncPolicyEvaluator};
+use crate::models::{PolicyDecision, PolicyExplanation, PolicyInput};
+use crate::opa_http::{OpaHttpConfig, OpaHttpEvaluator};
+use crate::rules::RuleBasedEvaluator;
+use async_trait::async_trait;
+use mcp_common::error::{McpError, McpResult};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Decision metadata key with which an evaluator abstains in [`CombineMode::FirstMatch`]
+pub const METADATA_NO_MATCH: &str = "no_match";
+
+/// How the decisions of a chain are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CombineMode {
+    /// Stop at the first denial; allowed if every evaluator allows
+    #[default]
+    FirstDenyWins,
+    /// Run every evaluator; allowed only if all of them allow
+    AllMustAllow,
+    /// The first evaluator that does not abstain (see [`METADATA_NO_MATCH`]) decides;
+    /// denied if all of them abstain
+    FirstMatch,
+}
+
+impl CombineMode {
+    /// Name of the mode as accepted by `from_str`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FirstDenyWins => "first-deny-wins",
+            Self::AllMustAllow => "all-must-allow",
+            Self::FirstMatch => "first-match",
+        }
+    }
+}
+
+impl FromStr for CombineMode {
+    type Err = McpError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "first-deny-wins" => Ok(Self::FirstDenyWins),
+            "all-must-allow" => Ok(Self::AllMustAllow),
+            "first-match" => Ok(Self::FirstMatch),
+            _ => Err(McpError::InvalidRequest(format!(
+                "Invalid policy chain mode '{}', expected 'first-deny-wins', 'all-must-allow' or 'first-match'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Evaluator running several evaluators in order
+#[derive(Clone)]
+pub struct ChainedEvaluator {
+    evaluators: Vec<Arc<dyn AsyncPolicyEvaluator>>,
+    mode: CombineMode,
+    name: String,
+}
+
+impl fmt::Debug for ChainedEvaluator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChainedEvaluator")
+            .field("evaluators", &self.evaluators.iter().map(|evaluator| evaluator.name()).collect::<Vec<_>>())
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+impl Default for ChainedEvaluator {
+    fn default() -> Self {
+        Self::new(CombineMode::default())
+    }
+}
+
+impl ChainedEvaluator {
+    /// Create an empty chain
+    ///
+    /// An empty chain denies every request.
+    pub fn new(mode: CombineMode) -> Self {
+        Self {
+            evaluators: Vec::new(),
+            mode,
+            name: "chain()".to_string(),
+        }
+    }
+
+    /// Build the chain from environment variables
+    ///
+    /// `MCP_POLICY_CHAIN` selects the combination mode (`first-deny-wins`, `all-must-allow`
+    /// or `first-match`). The chain runs every configured policy source in the order
+    /// `MCP_POLICY_DIR`, `MCP_POLICY_RULES`, `MCP_POLICY_SCRIPT`, `MCP_POLICY_WASM`,
+    /// `MCP_OPA_URL`. Returns `None` if `MCP_POLICY_CHAIN` is not set.
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let Ok(mode) = std::env::var("MCP_POLICY_CHAIN") else {
+            return Ok(None);
+        };
+
+        let mut chain = Self::new(mode.parse()?);
+        if let Ok(dir) = std::env::var("MCP_POLICY_DIR") {
+            chain = chain.with_evaluator(load_policy_dir(dir.as_ref())?);
+        }
+        if let Ok(path) = std::env::var("MCP_POLICY_RULES") {
+            chain = chain.with_evaluator(RuleBasedEvaluator::from_file(path)?);
+        }
+        if let Ok(path) = std::env::var("MCP_POLICY_SCRIPT") {
+            chain = chain.with_evaluator(load_policy_script(&path)?);
+        }
+        if let Ok(path) = std::env::var("MCP_POLICY_WASM") {
+            chain = chain.with_evaluator(load_policy_plugin(&path)?);
+        }
+        if let Some(config) = OpaHttpConfig::from_env()? {
+            chain = chain.with_evaluator(OpaHttpEvaluator::new(config));
+        }
+
+        if chain.is_empty() {
+            return Err(McpError::InvalidRequest(
+                "MCP_POLICY_CHAIN is set but none of MCP_POLICY_DIR, MCP_POLICY_RULES, MCP_POLICY_SCRIPT, \
+                 MCP_POLICY_WASM and MCP_OPA_URL is"
+                    .to_string(),
+            ));
+        }
+        Ok(Some(chain))
+    }
+
+    /// Append an evaluator to the chain
+    pub fn with_evaluator(mut self, evaluator: impl AsyncPolicyEvaluator + 'static) -> Self {
+        self.evaluators.push(Arc::new(evaluator));
+        self.name = format!(
+            "chain({})",
+            self.evaluators.iter().map(|evaluator| evaluator.name()).collect::<Vec<_>>().join(",")
+        );
+        self
+    }
+
+    /// Combination mode
+    pub fn mode(&self) -> CombineMode {
+        self.mode
+    }
+
+    /// Number of evaluators in the chain
+    pub fn len(&self) -> usize {
+        self.evaluators.len()
+    }
+
+    /// Whether the chain has no evaluators
+    pub fn is_empty(&self) -> bool {
+        self.evaluators.is_empty()
+    }
+
+    // Run the evaluators and combine their results; with `explain` the matched rules of
+    // every evaluator that ran are collected as well
+    async fn run(&self, input: &PolicyInput, explain: bool) -> McpResult<PolicyExplanation> {
+        let mut combined = PolicyExplanation {
+            evaluator: self.name.clone(),
+            decision: PolicyDecision {
+                allow: !self.evaluators.is_empty() && self.mode != CombineMode::FirstMatch,
+                warnings: Vec::new(),
+                reasons: Vec::new(),
+                deny_reasons: vec![],
+                metadata: Default::default(),
+            },
+            rules: Vec::new(),
+        };
+
+        for evaluator in &self.evaluators {
+            let PolicyExplanation { decision, rules, .. } = if explain {
+                evaluator.explain(input).await?
+            } else {
+                PolicyExplanation {
+                    evaluator: evaluator.name().to_string(),
+                    decision: evaluator.evaluate(input).await?,
+                    rules: Vec::new(),
+                }
+            };
+
+            let abstained = self.mode == CombineMode::FirstMatch
+                && decision.metadata.get(METADATA_NO_MATCH).and_then(|value| value.as_bool()) == Some(true);
+            let allow = decision.allow;
+
+            combined.decision.warnings.extend(decision.warnings);
+            combined.rules.extend(rules);
+            for (key, value) in decision.metadata {
+                combined.decision.metadata.entry(key).or_insert(value);
+            }
+            if abstained {
+                continue;
+            }
+            if !allow {
+                combined.decision.reasons.extend(decision.reasons);
+            }
+
+            match self.mode {
+                CombineMode::FirstDenyWins if !allow => {
+                    combined.decision.allow = false;
+                    break;
+                }
+                CombineMode::FirstDenyWins => {}
+                CombineMode::AllMustAllow => combined.decision.allow &= allow,
+                CombineMode::FirstMatch => {
+                    combined.decision.allow = allow;
+                    combined.decision.metadata.remove(METADATA_NO_MATCH);
+                    return Ok(combined);
+                }
+            }
+        }
+
+        if self.mode == CombineMode::FirstMatch {
+            combined.decision.metadata.remove(METADATA_NO_MATCH);
+            combined.decision.reasons.push("No policy in the chain matched the request".to_string());
+        } else if self.evaluators.is_empty() {
+            combined.decision.reasons.push("No policy evaluators are configured".to_string());
+        }
+
+        Ok(combined)
+    }
+}
+
+#[async_trait]
+impl AsyncPolicyEvaluator for ChainedEvaluator {
+    async fn evaluate(&self, input: &PolicyInput) -> McpResult<PolicyDecision> {
+        Ok(self.run(input, false).await?.decision)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn explain(&self, input: &PolicyInput) -> McpResult<PolicyExplanation> {
+        self.run(input, true).await
+    }
+
+    // Replaced in every member that has data documents
+    fn with_data_document(
+        &self,
+        path: &str,
+        document: serde_json::Value,
+    ) -> McpResult<Arc<dyn AsyncPolicyEvaluator>> {
+        let mut chain = self.clone();
+        let mut replaced = false;
+        for evaluator in &mut chain.evaluators {
+            if let Ok(updated) = evaluator.with_data_document(path, document.clone()) {
+                *evaluator = updated;
+                replaced = true;
+            }
+        }
+        if !replaced {
+            return Err(McpError::InvalidRequest(format!(
+                "No evaluator of policy chain '{}' supports data documents",
+                self.name
+            )));
+        }
+        Ok(Arc::new(chain))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::PolicyEvaluator;
+    use crate::test_support::input;
+    use crate::rules::{RuleBasedEvaluator, RuleConfig};
+    use serde_json::json;
+
+    struct Fixed {
+        name: &'static str,
+        decision: serde_json::Value,
+    }
+
+    impl Fixed {
+        fn new(name: &'static str, decision: serde_json::Value) -> Self {
+            Self { name, decision }
+        }
+    }
+
+    impl PolicyEvaluator for Fixed {
+        fn evaluate(&self, _input: &PolicyInput) -> McpResult<PolicyDecision> {
+            if self.decision.is_null() {
+                return Err(McpError::ExternalService(format!("{} is unavailable", self.name)));
+            }
+            Ok(serde_json::from_value(self.decision.clone()).unwrap())
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+
+    fn chain(mode: CombineMode) -> ChainedEvaluator {
+        ChainedEvaluator::new(mode)
+            .with_evaluator(Fixed::new("local", json!({ "allow": true, "warnings": ["local warning"] })))
+            .with_evaluator(Fixed::new("remote", json!({ "allow": false, "reasons": ["remote denial"] })))
+            .with_evaluator(Fixed::new("audit", json!({ "allow": false, "reasons": ["audit denial"] })))
+    }
+
+    // Test for the combination modes
+    #[tokio::test]
+    async fn test_combine_modes() {
+        let decision = chain(CombineMode::FirstDenyWins).evaluate(&input("ls")).await.unwrap();
+        assert!(!decision.allow);
+        assert_eq!(decision.warnings, vec!["local warning"]);
+        assert_eq!(decision.reasons, vec!["remote denial"]);
+
+        let decision = chain(CombineMode::AllMustAllow).evaluate(&input("ls")).await.unwrap();
+        assert!(!decision.allow);
+        assert_eq!(decision.reasons, vec!["remote denial", "audit denial"]);
+
+        let decision = chain(CombineMode::FirstMatch).evaluate(&input("ls")).await.unwrap();
+        assert!(decision.allow);
+        assert_eq!(decision.warnings, vec!["local warning"]);
+
+        // Abstaining evaluators are skipped in first-match mode
+        let abstaining = ChainedEvaluator::new(CombineMode::FirstMatch)
+            .with_evaluator(Fixed::new("local", json!({ "allow": true, "metadata": { "no_match": true } })))
+            .with_evaluator(Fixed::new("remote", json!({ "allow": false, "reasons": ["remote denial"] })));
+        let decision = abstaining.evaluate(&input("ls")).await.unwrap();
+        assert!(!decision.allow);
+        assert_eq!(decision.reasons, vec!["remote denial"]);
+        assert!(decision.metadata.is_empty());
+
+        let nobody = ChainedEvaluator::new(CombineMode::FirstMatch)
+            .with_evaluator(Fixed::new("local", json!({ "allow": true, "metadata": { "no_match": true } })));
+        assert!(!nobody.evaluate(&input("ls")).await.unwrap().allow);
+
+        // Empty chains deny
+        assert!(!ChainedEvaluator::default().evaluate(&input("ls")).await.unwrap().allow);
+
+        // Metadata is merged, earlier evaluators take precedence
+        let merged = ChainedEvaluator::new(CombineMode::AllMustAllow)
+            .with_evaluator(Fixed::new("local", json!({ "allow": true, "metadata": { "cacheable": true } })))
+            .with_evaluator(Fixed::new("remote", json!({ "allow": true, "metadata": { "cacheable": false, "x": 1 } })));
+        let decision = merged.evaluate(&input("ls")).await.unwrap();
+        assert!(decision.allow);
+        assert_eq!(decision.metadata["cacheable"], json!(true));
+        assert_eq!(decision.metadata["x"], json!(1));
+
+        assert_eq!("ALL-MUST-ALLOW".parse::<CombineMode>().unwrap(), CombineMode::AllMustAllow);
+        assert!("any".parse::<CombineMode>().is_err());
+    }
+
+    // Test for errors of evaluators that run
+    #[tokio::test]
+    async fn test_evaluator_errors() {
+        let evaluator = ChainedEvaluator::new(CombineMode::FirstDenyWins)
+            .with_evaluator(Fixed::new("local", json!({ "allow": false })))
+            .with_evaluator(Fixed::new("remote", serde_json::Value::Null));
+        assert_eq!(evaluator.name(), "chain(local,remote)");
+
+        // Evaluators after the first denial do not run
+        assert!(!evaluator.evaluate(&input("ls")).await.unwrap().allow);
+
+        let evaluator = ChainedEvaluator::new(CombineMode::AllMustAllow)
+            .with_evaluator(Fixed::new("local", json!({ "allow": false })))
+            .with_evaluator(Fixed::new("remote", serde_json::Value::Null));
+        assert!(evaluator.evaluate(&input("ls")).await.is_err());
+    }
+
+    // Test for collecting the matched rules of the chain
+    #[tokio::test]
+    async fn test_explain() {
+        let rules = RuleBasedEvaluator::new(RuleConfig {
+            commands: crate::rules::CommandRules {
+                deny: vec!["rm".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let evaluator = ChainedEvaluator::new(CombineMode::AllMustAllow)
+            .with_evaluator(Fixed::new("remote", json!({ "allow": true })))
+            .with_evaluator(rules);
+
+        let explanation = evaluator.explain(&input("rm")).await.unwrap();
+        assert_eq!(explanation.evaluator, "chain(remote,rules)");
+        assert!(!explanation.decision.allow);
+        assert_eq!(explanation.rules.len(), 1);
+        assert_eq!(explanation.rules[0].rule, "commands.deny");
+    }
+}
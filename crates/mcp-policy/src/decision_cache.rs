@@ -0,0 +1,338 @@
+//! Policy decision cache
+//!
+//! Caches decisions of the active evaluator under a hash of the normalized `PolicyInput`
+//! (object keys sorted, so the iteration order of maps does not matter). Entries expire
+//! after a TTL and the least recently used entry is evicted when the cache is full. The
+//! whole cache is invalidated whenever the evaluator is replaced, e.g. by a policy reload
+//! or a new bundle; a generation counter keeps evaluations that were running during the
+//! swap from storing decisions of the previous policies.
+//!
+//! The cache is opt-in and disabled by default.
+
+use crate::bundle::sort_keys;
+use crate::models::{PolicyDecision, PolicyInput};
+use crate::opa_http::METADATA_OPA_FALLBACK;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{get_env_var_or, hex, parse_positive};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Decision cache settings
+#[derive(Debug, Clone)]
+pub struct DecisionCacheConfig {
+    /// Whether the cache is used at all
+    pub enabled: bool,
+    /// Lifetime of an entry
+    pub ttl: Duration,
+    /// Maximum number of entries (the least recently used entry is evicted first)
+    pub max_entries: usize,
+}
+
+impl Default for DecisionCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl: Duration::from_secs(30),
+            max_entries: 10_000,
+        }
+    }
+}
+
+impl DecisionCacheConfig {
+    /// Build the settings from environment variables
+    ///
+    /// * `MCP_POLICY_CACHE_ENABLED` - `true` to enable the cache
+    /// * `MCP_POLICY_CACHE_TTL_SECS` - entry lifetime
+    /// * `MCP_POLICY_CACHE_MAX_ENTRIES` - maximum number of entries
+    pub fn from_env() -> McpResult<Self> {
+        let mut config = Self {
+            enabled: get_env_var_or("MCP_POLICY_CACHE_ENABLED", "false") == "true",
+            ..Self::default()
+        };
+
+        if let Ok(value) = std::env::var("MCP_POLICY_CACHE_TTL_SECS") {
+            config.ttl = Duration::from_secs(parse_positive("MCP_POLICY_CACHE_TTL_SECS", &value)?);
+        }
+        if let Ok(value) = std::env::var("MCP_POLICY_CACHE_MAX_ENTRIES") {
+            config.max_entries = parse_positive("MCP_POLICY_CACHE_MAX_ENTRIES", &value)?;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Callback invoked with "hit" or "miss" for every lookup
+pub type CacheObserver = Arc<dyn Fn(&str) + Send + Sync>;
+
+struct CacheEntry {
+    decision: PolicyDecision,
+    inserted_at: Instant,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    // Monotonic counter used as the recency of an entry
+    clock: u64,
+}
+
+/// LRU cache of policy decisions with a TTL
+pub struct DecisionCache {
+    config: DecisionCacheConfig,
+    state: Mutex<CacheState>,
+    generation: AtomicU64,
+    observer: Option<CacheObserver>,
+}
+
+impl fmt::Debug for DecisionCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecisionCache")
+            .field("config", &self.config)
+            .field("entries", &self.len())
+            .finish()
+    }
+}
+
+impl Default for DecisionCache {
+    fn default() -> Self {
+        Self::new(DecisionCacheConfig::default())
+    }
+}
+
+impl DecisionCache {
+    /// Create a cache with the given settings
+    pub fn new(config: DecisionCacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CacheState::default()),
+            generation: AtomicU64::new(0),
+            observer: None,
+        }
+    }
+
+    /// Report every lookup ("hit" or "miss") to a callback, e.g. for metrics
+    pub fn with_observer(mut self, observer: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Whether the cache is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Number of stored entries (including expired ones not yet removed)
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Whether the cache has no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Current generation; pass it to [`DecisionCache::insert`]
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Compute the cache key of an input
+    pub fn key(input: &PolicyInput) -> McpResult<String> {
+        let value = serde_json::to_value(input)
+            .map_err(|e| McpError::Internal(format!("Failed to serialize input: {}", e)))?;
+        let normalized = serde_json::to_vec(&sort_keys(value))
+            .map_err(|e| McpError::Internal(format!("Failed to serialize input: {}", e)))?;
+
+        Ok(hex(&Sha256::digest(&normalized)))
+    }
+
+    /// Look up a cached decision
+    pub fn get(&self, key: &str) -> Option<PolicyDecision> {
+        let decision = {
+            let mut state = self.lock();
+            state.clock += 1;
+            let clock = state.clock;
+
+            match state.entries.get_mut(key) {
+                Some(entry) if entry.inserted_at.elapsed() < self.config.ttl => {
+                    entry.last_used = clock;
+                    Some(entry.decision.clone())
+                }
+                Some(_) => {
+                    state.entries.remove(key);
+                    None
+                }
+                None => None,
+            }
+        };
+
+        self.observe(if decision.is_some() { "hit" } else { "miss" });
+        decision
+    }
+
+    /// Store a decision evaluated during `generation`
+    ///
+    /// Decisions from an older generation (the evaluator was replaced meanwhile) and
+    /// fail-open fallback decisions are not stored.
+    pub fn insert(&self, key: String, decision: &PolicyDecision, generation: u64) {
+        if decision.metadata.contains_key(METADATA_OPA_FALLBACK) {
+            return;
+        }
+
+        let mut state = self.lock();
+        // Checked under the lock so that a concurrent invalidation cannot be missed
+        if generation != self.generation() {
+            return;
+        }
+
+        if state.entries.len() >= self.config.max_entries && !state.entries.contains_key(&key) {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+
+        state.clock += 1;
+        let last_used = state.clock;
+        state.entries.insert(
+            key,
+            CacheEntry {
+                decision: decision.clone(),
+                inserted_at: Instant::now(),
+                last_used,
+            },
+        );
+    }
+
+    /// Remove all entries
+    pub fn invalidate(&self) {
+        let mut state = self.lock();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        state.entries.clear();
+    }
+
+    fn observe(&self, result: &str) {
+        if let Some(observer) = &self.observer {
+            observer(result);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{PolicyEngine, PolicyEvaluator};
+    use crate::test_support::input;
+    use std::sync::atomic::AtomicUsize;
+
+    fn decision(allow: bool) -> PolicyDecision {
+        PolicyDecision {
+            allow,
+            warnings: Vec::new(),
+            reasons: Vec::new(),
+            deny_reasons: vec![],
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn cache(ttl: Duration, max_entries: usize) -> DecisionCache {
+        DecisionCache::new(DecisionCacheConfig {
+            enabled: true,
+            ttl,
+            max_entries,
+        })
+    }
+
+    /// Evaluator that allows everything and counts its evaluations
+    struct CountingEvaluator(Arc<AtomicUsize>);
+
+    impl PolicyEvaluator for CountingEvaluator {
+        fn evaluate(&self, _input: &PolicyInput) -> McpResult<PolicyDecision> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(decision(true))
+        }
+    }
+
+    // Test for key normalization
+    #[test]
+    fn test_key_is_normalized() {
+        let mut a = input("ls");
+        let mut b = input("ls");
+        for i in 0..20 {
+            a.command.env.insert(format!("VAR{}", i), i.to_string());
+        }
+        for i in (0..20).rev() {
+            b.command.env.insert(format!("VAR{}", i), i.to_string());
+        }
+        assert_eq!(DecisionCache::key(&a).unwrap(), DecisionCache::key(&b).unwrap());
+        assert_ne!(DecisionCache::key(&a).unwrap(), DecisionCache::key(&input("ls")).unwrap());
+    }
+
+    // Test for TTL expiry and LRU eviction
+    #[test]
+    fn test_ttl_and_lru() {
+        let cache = cache(Duration::from_millis(10), 2);
+        cache.insert("a".to_string(), &decision(true), cache.generation());
+        assert!(cache.get("a").is_some());
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(cache.get("a").is_none());
+        assert!(cache.is_empty());
+
+        let cache = self::cache(Duration::from_secs(60), 2);
+        cache.insert("a".to_string(), &decision(true), 0);
+        cache.insert("b".to_string(), &decision(true), 0);
+        // "a" was used more recently than "b"
+        assert!(cache.get("a").is_some());
+        cache.insert("c".to_string(), &decision(true), 0);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+
+        // Decisions from before an invalidation are dropped
+        cache.invalidate();
+        cache.insert("d".to_string(), &decision(true), 0);
+        assert!(cache.is_empty());
+    }
+
+    // Test for caching in the engine, hit/miss reporting and invalidation on reload
+    #[tokio::test]
+    async fn test_engine_cache() {
+        let evaluations = Arc::new(AtomicUsize::new(0));
+        let lookups = Arc::new(Mutex::new(Vec::new()));
+        let observed = lookups.clone();
+
+        let engine = PolicyEngine::with_evaluator(CountingEvaluator(evaluations.clone())).with_decision_cache(
+            cache(Duration::from_secs(60), 10)
+                .with_observer(move |result| observed.lock().unwrap().push(result.to_string())),
+        );
+
+        engine.check_command_execution(&input("ls")).await.unwrap();
+        engine.check_command_execution(&input("ls")).await.unwrap();
+        assert_eq!(evaluations.load(Ordering::SeqCst), 1);
+        assert_eq!(*lookups.lock().unwrap(), vec!["miss", "hit"]);
+
+        // Replacing the evaluator invalidates the cache
+        engine.replace_evaluator(CountingEvaluator(evaluations.clone()));
+        engine.check_command_execution(&input("ls")).await.unwrap();
+        assert_eq!(evaluations.load(Ordering::SeqCst), 2);
+
+        // A disabled cache is bypassed
+        let engine = PolicyEngine::with_evaluator(CountingEvaluator(evaluations.clone()));
+        engine.check_command_execution(&input("ls")).await.unwrap();
+        engine.check_command_execution(&input("ls")).await.unwrap();
+        assert_eq!(evaluations.load(Ordering::SeqCst), 4);
+    }
+}
@@ -0,0 +1,433 @@
+//! Policy input enrichment
+//!
+//! `PolicyEngine` runs its [`InputEnricher`]s in order before every evaluation, so that
+//! policies can decide on facts the request does not carry: user groups from a directory,
+//! repository metadata, whether the request falls into working hours, and so on. Enrichers
+//! add their facts to `PolicyInput.context`. The enriched input is what the evaluator, the
+//! decision cache and the audit records see. An enricher error fails the evaluation.
+//!
+//! [`ExecutableHashEnricher`] computes the SHA-256 digest of the executable a command
+//! resolves to, so that policies can pin commands to known binaries (see the
+//! `commands.sha256` rules of the rule-based evaluator) and a tampered binary inside an
+//! allowed directory is not run.
+
+use crate::models::PolicyInput;
+use crate::path_pattern::normalize_path;
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, FixedOffset, Timelike, Utc, Weekday};
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{get_env_var_or, hex};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Context key set by [`WorkingHoursEnricher`]
+pub const CONTEXT_WORKING_HOURS: &str = "working_hours";
+
+/// Context key set by [`ExecutableHashEnricher`]
+pub const CONTEXT_EXECUTABLE: &str = "executable";
+
+/// Directories searched for command names without a `/` by default
+pub const DEFAULT_EXECUTABLE_SEARCH_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// Step run on the policy input before evaluation
+#[async_trait]
+pub trait InputEnricher: Send + Sync {
+    /// Add facts to the input (usually to `input.context`)
+    async fn enrich(&self, input: &mut PolicyInput) -> McpResult<()>;
+
+    /// Enricher name used in logs
+    fn name(&self) -> &str {
+        "custom"
+    }
+}
+
+/// Adds fixed values to the context (e.g. the deployment environment or repository metadata)
+///
+/// Values already present in the context are kept.
+#[derive(Debug, Clone, Default)]
+pub struct StaticContextEnricher {
+    values: HashMap<String, Value>,
+}
+
+impl StaticContextEnricher {
+    /// Create an enricher adding the given values
+    pub fn new(values: HashMap<String, Value>) -> Self {
+        Self { values }
+    }
+
+    /// Add a value
+    pub fn with_value(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.values.insert(key.to_string(), value.into());
+        self
+    }
+}
+
+#[async_trait]
+impl InputEnricher for StaticContextEnricher {
+    async fn enrich(&self, input: &mut PolicyInput) -> McpResult<()> {
+        for (key, value) in &self.values {
+            input.context.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "static-context"
+    }
+}
+
+/// Sets `working_hours` in the context to whether the evaluation falls into working hours
+#[derive(Debug, Clone)]
+pub struct WorkingHoursEnricher {
+    start_hour: u32,
+    end_hour: u32,
+    days: Vec<Weekday>,
+    offset: FixedOffset,
+}
+
+impl WorkingHoursEnricher {
+    /// Working hours from `start_hour` (inclusive) to `end_hour` (exclusive), Monday to
+    /// Friday in UTC
+    pub fn new(start_hour: u32, end_hour: u32) -> McpResult<Self> {
+        if start_hour >= end_hour || end_hour > 24 {
+            return Err(McpError::InvalidRequest(format!(
+                "Invalid working hours {}-{}, expected 0 <= start < end <= 24",
+                start_hour, end_hour
+            )));
+        }
+
+        Ok(Self {
+            start_hour,
+            end_hour,
+            days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+            offset: FixedOffset::east_opt(0).expect("zero offset is valid"),
+        })
+    }
+
+    /// Working days
+    pub fn with_days(mut self, days: Vec<Weekday>) -> Self {
+        self.days = days;
+        self
+    }
+
+    /// Time zone of the working hours, as an offset from UTC in hours
+    pub fn with_utc_offset_hours(mut self, hours: i32) -> McpResult<Self> {
+        self.offset = FixedOffset::east_opt(hours * 3600)
+            .ok_or_else(|| McpError::InvalidRequest(format!("Invalid UTC offset: {} hours", hours)))?;
+        Ok(self)
+    }
+
+    /// Build the enricher from environment variables
+    ///
+    /// * `MCP_POLICY_WORKING_HOURS` - working hours such as `9-18`
+    /// * `MCP_POLICY_WORKING_HOURS_UTC_OFFSET` - offset of their time zone in hours (default 0)
+    ///
+    /// Returns `None` if `MCP_POLICY_WORKING_HOURS` is not set.
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let Ok(hours) = std::env::var("MCP_POLICY_WORKING_HOURS") else {
+            return Ok(None);
+        };
+        let invalid = || {
+            McpError::InvalidRequest(format!("MCP_POLICY_WORKING_HOURS must look like '9-18': '{}'", hours))
+        };
+
+        let (start, end) = hours.trim().split_once('-').ok_or_else(invalid)?;
+        let start = start.trim().parse().map_err(|_| invalid())?;
+        let end = end.trim().parse().map_err(|_| invalid())?;
+        let enricher = Self::new(start, end)?;
+
+        match std::env::var("MCP_POLICY_WORKING_HOURS_UTC_OFFSET") {
+            Ok(offset) => {
+                let offset = offset.trim().parse().map_err(|_| {
+                    McpError::InvalidRequest(format!(
+                        "MCP_POLICY_WORKING_HOURS_UTC_OFFSET must be a number of hours: '{}'",
+                        offset
+                    ))
+                })?;
+                enricher.with_utc_offset_hours(offset).map(Some)
+            }
+            Err(_) => Ok(Some(enricher)),
+        }
+    }
+
+    /// Whether a point in time falls into the working hours
+    pub fn is_working_time(&self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&self.offset);
+        self.days.contains(&local.weekday()) && (self.start_hour..self.end_hour).contains(&local.hour())
+    }
+}
+
+#[async_trait]
+impl InputEnricher for WorkingHoursEnricher {
+    async fn enrich(&self, input: &mut PolicyInput) -> McpResult<()> {
+        input
+            .context
+            .insert(CONTEXT_WORKING_HOURS.to_string(), Value::Bool(self.is_working_time(Utc::now())));
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "working-hours"
+    }
+}
+
+/// Sets `executable` in the context to the path and SHA-256 digest of the command's executable
+///
+/// The executable resolved by the gateway (`command.executable`) is used when set. Otherwise
+/// command names without a `/` are looked up in the search path like a shell does, relative
+/// names are resolved against the working directory of the command. The context value is
+/// `{"path": "/usr/bin/ls", "sha256": "<hex digest>"}`; it is not set when no executable is
+/// found. With a sandbox root, paths are looked up below it (the context keeps the path as
+/// the sandbox sees it). Digests are cached until the size or modification time of the
+/// file changes.
+#[derive(Debug)]
+pub struct ExecutableHashEnricher {
+    search_path: Vec<String>,
+    sandbox_root: Option<PathBuf>,
+    digests: Mutex<HashMap<PathBuf, CachedDigest>>,
+}
+
+// Digest of a file, valid while its size and modification time are unchanged
+#[derive(Debug)]
+struct CachedDigest {
+    len: u64,
+    modified: Option<SystemTime>,
+    digest: String,
+}
+
+impl Default for ExecutableHashEnricher {
+    fn default() -> Self {
+        Self::new(DEFAULT_EXECUTABLE_SEARCH_PATH.split(':').map(str::to_string).collect())
+    }
+}
+
+impl ExecutableHashEnricher {
+    /// Create an enricher looking up command names in the given directories
+    pub fn new(search_path: Vec<String>) -> Self {
+        Self {
+            search_path,
+            sandbox_root: None,
+            digests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Look up executables in the file system of a sandbox rooted at `root`
+    pub fn with_sandbox_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.sandbox_root = Some(root.into());
+        self
+    }
+
+    /// Build the enricher from environment variables
+    ///
+    /// * `MCP_POLICY_EXECUTABLE_HASH` - `true` to enable the enricher
+    /// * `MCP_POLICY_EXECUTABLE_PATH` - colon separated search path (default
+    ///   `/usr/local/bin:/usr/bin:/bin`)
+    /// * `MCP_POLICY_SANDBOX_ROOT` - root of the sandbox file system
+    ///
+    /// Returns `None` unless `MCP_POLICY_EXECUTABLE_HASH` is `true`.
+    pub fn from_env() -> McpResult<Option<Self>> {
+        if get_env_var_or("MCP_POLICY_EXECUTABLE_HASH", "false") != "true" {
+            return Ok(None);
+        }
+
+        let search_path = get_env_var_or("MCP_POLICY_EXECUTABLE_PATH", DEFAULT_EXECUTABLE_SEARCH_PATH)
+            .split(':')
+            .map(|dir| dir.trim().to_string())
+            .filter(|dir| !dir.is_empty())
+            .collect();
+        let mut enricher = Self::new(search_path);
+        if let Ok(root) = std::env::var("MCP_POLICY_SANDBOX_ROOT") {
+            enricher = enricher.with_sandbox_root(root);
+        }
+        Ok(Some(enricher))
+    }
+
+    /// Path (as the sandbox sees it) and digest of the executable of a command
+    pub fn resolve(&self, command: &str, cwd: &str) -> McpResult<Option<(String, String)>> {
+        if command.is_empty() {
+            return Ok(None);
+        }
+
+        let candidates: Vec<String> = if command.starts_with('/') {
+            vec![normalize_path(command)]
+        } else if command.contains('/') {
+            vec![normalize_path(&format!("{}/{}", cwd, command))]
+        } else {
+            self.search_path.iter().map(|dir| format!("{}/{}", dir.trim_end_matches('/'), command)).collect()
+        };
+
+        for path in candidates {
+            let host_path = self.host_path(&path);
+            let Ok(metadata) = std::fs::metadata(&host_path) else {
+                continue;
+            };
+            if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0 {
+                let digest = self.digest(&host_path, &metadata)?;
+                return Ok(Some((path, digest)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn host_path(&self, path: &str) -> PathBuf {
+        match &self.sandbox_root {
+            Some(root) => root.join(path.trim_start_matches('/')),
+            None => PathBuf::from(path),
+        }
+    }
+
+    fn digest(&self, host_path: &Path, metadata: &std::fs::Metadata) -> McpResult<String> {
+        let modified = metadata.modified().ok();
+        let mut digests = self.digests.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = digests.get(host_path) {
+            if cached.len == metadata.len() && cached.modified == modified {
+                return Ok(cached.digest.clone());
+            }
+        }
+
+        let read_error =
+            |e: std::io::Error| McpError::Internal(format!("Failed to hash executable {}: {}", host_path.display(), e));
+        let mut file = std::fs::File::open(host_path).map_err(read_error)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer).map_err(read_error)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        let digest = hex(&hasher.finalize());
+
+        digests.insert(
+            host_path.to_path_buf(),
+            CachedDigest {
+                len: metadata.len(),
+                modified,
+                digest: digest.clone(),
+            },
+        );
+        Ok(digest)
+    }
+}
+
+#[async_trait]
+impl InputEnricher for ExecutableHashEnricher {
+    async fn enrich(&self, input: &mut PolicyInput) -> McpResult<()> {
+        // The executable the gateway resolved the command to, if any, is the one that runs
+        let command = input.command.executable.as_deref().unwrap_or(&input.command.name);
+        if let Some((path, digest)) = self.resolve(command, &input.command.cwd)? {
+            input
+                .context
+                .insert(CONTEXT_EXECUTABLE.to_string(), json!({ "path": path, "sha256": digest }));
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "executable-hash"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    // Test for the working hours calculation
+    #[test]
+    fn test_working_hours() {
+        let enricher = WorkingHoursEnricher::new(9, 18).unwrap();
+        // 2024-01-08 is a Monday
+        assert!(enricher.is_working_time(Utc.with_ymd_and_hms(2024, 1, 8, 9, 0, 0).unwrap()));
+        assert!(!enricher.is_working_time(Utc.with_ymd_and_hms(2024, 1, 8, 18, 0, 0).unwrap()));
+        assert!(!enricher.is_working_time(Utc.with_ymd_and_hms(2024, 1, 7, 12, 0, 0).unwrap()));
+
+        // 01:00 UTC on Monday is 10:00 in UTC+9
+        let tokyo = WorkingHoursEnricher::new(9, 18).unwrap().with_utc_offset_hours(9).unwrap();
+        assert!(tokyo.is_working_time(Utc.with_ymd_and_hms(2024, 1, 8, 1, 0, 0).unwrap()));
+        assert!(!tokyo.is_working_time(Utc.with_ymd_and_hms(2024, 1, 8, 12, 0, 0).unwrap()));
+
+        let weekend = WorkingHoursEnricher::new(0, 24).unwrap().with_days(vec![Weekday::Sat, Weekday::Sun]);
+        assert!(weekend.is_working_time(Utc.with_ymd_and_hms(2024, 1, 7, 23, 0, 0).unwrap()));
+
+        assert!(WorkingHoursEnricher::new(18, 9).is_err());
+        assert!(WorkingHoursEnricher::new(9, 25).is_err());
+    }
+
+    // Test for adding static values without overwriting the request context
+    #[tokio::test]
+    async fn test_static_context() {
+        let enricher = StaticContextEnricher::default()
+            .with_value("environment", "production")
+            .with_value("repository", json!({ "name": "gateway", "protected": true }));
+
+        let mut input = PolicyInput {
+            user: Default::default(),
+            command: Default::default(),
+            file: None,
+            network: None,
+            resources: Default::default(),
+            context: HashMap::from([("environment".to_string(), json!("staging"))]),
+        };
+        enricher.enrich(&mut input).await.unwrap();
+
+        assert_eq!(input.context["environment"], json!("staging"));
+        assert_eq!(input.context["repository"]["protected"], json!(true));
+    }
+
+    // Test for hashing the executable of a command
+    #[tokio::test]
+    async fn test_executable_hash() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("usr/bin")).unwrap();
+        std::fs::create_dir_all(root.path().join("workspace")).unwrap();
+        let tool = root.path().join("usr/bin/tool");
+        std::fs::write(&tool, "#!/bin/sh\necho tool\n").unwrap();
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(root.path().join("workspace/data.txt"), "not executable").unwrap();
+
+        let enricher = ExecutableHashEnricher::new(vec!["/bin".to_string(), "/usr/bin".to_string()])
+            .with_sandbox_root(root.path());
+        let expected = hex(&Sha256::digest(b"#!/bin/sh\necho tool\n"));
+
+        let mut input = PolicyInput {
+            user: Default::default(),
+            command: crate::models::CommandInfo {
+                name: "tool".to_string(),
+                cwd: "/workspace".to_string(),
+                ..Default::default()
+            },
+            file: None,
+            network: None,
+            resources: Default::default(),
+            context: HashMap::new(),
+        };
+        enricher.enrich(&mut input).await.unwrap();
+        assert_eq!(input.context[CONTEXT_EXECUTABLE], json!({ "path": "/usr/bin/tool", "sha256": expected }));
+
+        // The executable resolved by the gateway takes precedence over the search path
+        std::fs::create_dir_all(root.path().join("opt")).unwrap();
+        std::fs::copy(&tool, root.path().join("opt/tool")).unwrap();
+        input.command.executable = Some("/opt/tool".to_string());
+        enricher.enrich(&mut input).await.unwrap();
+        assert_eq!(input.context[CONTEXT_EXECUTABLE]["path"], "/opt/tool");
+
+        assert_eq!(enricher.resolve("../usr/bin/tool", "/workspace").unwrap().unwrap().0, "/usr/bin/tool");
+        assert!(enricher.resolve("data.txt", "/workspace").unwrap().is_none());
+        assert!(enricher.resolve("./data.txt", "/workspace").unwrap().is_none());
+        assert!(enricher.resolve("missing", "/workspace").unwrap().is_none());
+
+        // A modified binary gets a new digest
+        std::fs::write(&tool, "#!/bin/sh\nrm -rf /\n").unwrap();
+        let (_, digest) = enricher.resolve("/usr/bin/tool", "").unwrap().unwrap();
+        assert_ne!(digest, expected);
+    }
+}
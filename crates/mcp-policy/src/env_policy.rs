@@ -0,0 +1,214 @@
+//! Environment variable policy
+//!
+//! Keeps secrets from leaking into sandboxed commands through `CommandInfo.env`.
+//! Variables whose names match one of the configured globs (case-insensitively, e.g.
+//! `AWS_*`, `*_TOKEN`, `*_SECRET`) either fail the request with a policy violation or are
+//! stripped before the command is evaluated and executed. The check is independent of
+//! the active evaluator.
+//!
+//! The policy is opt-in: without patterns every variable is passed through.
+
+use crate::engine::policy_violation;
+use crate::models::PolicyInput;
+use globset::{GlobBuilder, GlobMatcher};
+use mcp_common::error::{error_code, McpError, McpResult};
+use mcp_common::utils::get_env_var_or;
+use serde_json::json;
+use std::str::FromStr;
+use tracing::{error, info};
+
+/// What to do with matching variables
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EnvAction {
+    /// Reject the request
+    #[default]
+    Deny,
+    /// Remove the variables and continue
+    Strip,
+}
+
+impl FromStr for EnvAction {
+    type Err = McpError;
+
+    fn from_str(value: &str) -> McpResult<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "deny" => Ok(Self::Deny),
+            "strip" => Ok(Self::Strip),
+            other => Err(McpError::InvalidRequest(format!(
+                "Unknown environment variable policy action '{}', expected deny or strip",
+                other
+            ))),
+        }
+    }
+}
+
+/// Policy on the environment variables of commands
+#[derive(Debug, Clone, Default)]
+pub struct EnvPolicy {
+    patterns: Vec<(String, GlobMatcher)>,
+    action: EnvAction,
+}
+
+impl EnvPolicy {
+    /// Create a policy for the given name globs
+    pub fn new<S: AsRef<str>>(patterns: &[S], action: EnvAction) -> McpResult<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                let pattern = pattern.as_ref();
+                GlobBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map(|glob| (pattern.to_string(), glob.compile_matcher()))
+                    .map_err(|e| {
+                        McpError::InvalidRequest(format!("Invalid environment variable pattern '{}': {}", pattern, e))
+                    })
+            })
+            .collect::<McpResult<_>>()?;
+
+        Ok(Self { patterns, action })
+    }
+
+    /// Build the policy from environment variables
+    ///
+    /// * `MCP_POLICY_ENV_PATTERNS` - comma separated name globs, e.g. `AWS_*,*_TOKEN,*_SECRET`
+    /// * `MCP_POLICY_ENV_ACTION` - `deny` (default) or `strip`
+    pub fn from_env() -> McpResult<Self> {
+        let patterns: Vec<String> = get_env_var_or("MCP_POLICY_ENV_PATTERNS", "")
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect();
+        let action = get_env_var_or("MCP_POLICY_ENV_ACTION", "deny").parse()?;
+
+        Self::new(&patterns, action)
+    }
+
+    /// Whether any pattern is configured
+    pub fn is_enabled(&self) -> bool {
+        !self.patterns.is_empty()
+    }
+
+    /// Action applied to matching variables
+    pub fn action(&self) -> EnvAction {
+        self.action
+    }
+
+    /// Whether a variable name matches a pattern
+    pub fn matches(&self, name: &str) -> bool {
+        self.patterns.iter().any(|(_, glob)| glob.is_match(name))
+    }
+
+    /// Apply the policy to the command environment of an input
+    ///
+    /// Returns the stripped variable names (sorted), or a `PolicyViolation` listing the
+    /// offending names when the action is [`EnvAction::Deny`].
+    pub fn apply(&self, input: &mut PolicyInput) -> McpResult<Vec<String>> {
+        let mut offending: Vec<String> = input
+            .command
+            .env
+            .keys()
+            .filter(|name| self.matches(name))
+            .cloned()
+            .collect();
+        if offending.is_empty() {
+            return Ok(offending);
+        }
+        offending.sort();
+
+        match self.action {
+            EnvAction::Deny => {
+                let reasons: Vec<String> = offending
+                    .iter()
+                    .map(|name| format!("Environment variable '{}' is not allowed", name))
+                    .collect();
+                let message = format!(
+                    "Command '{}' execution was denied by policy: {}",
+                    input.command.name,
+                    reasons.join(", ")
+                );
+                error!("Policy violation: {}", message);
+
+                let details = json!({
+                    "command": input.command.name,
+                    "env_keys": offending,
+                    "reasons": reasons,
+                    "user_id": input.user.id,
+                    "tenant_id": input.user.tenant_id
+                });
+                Err(policy_violation(error_code::POLICY_COMMAND_NOT_ALLOWED, message, Some(details)))
+            }
+            EnvAction::Strip => {
+                for name in &offending {
+                    input.command.env.remove(name);
+                }
+                info!(
+                    "Stripped environment variables from command '{}': {}",
+                    input.command.name,
+                    offending.join(", ")
+                );
+                Ok(offending)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::input;
+
+    fn with_env(env: &[(&str, &str)]) -> PolicyInput {
+        let mut input = input("python");
+        input.command.env = env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        input
+    }
+
+    const PATTERNS: [&str; 3] = ["AWS_*", "*_TOKEN", "*_SECRET"];
+
+    // Test for denying matching variables
+    #[test]
+    fn test_deny() {
+        let policy = EnvPolicy::new(&PATTERNS, EnvAction::Deny).unwrap();
+        assert!(policy.matches("github_token"));
+        assert!(!policy.matches("PATH"));
+
+        let mut allowed = with_env(&[("PATH", "/usr/bin"), ("LANG", "C")]);
+        assert!(policy.apply(&mut allowed).unwrap().is_empty());
+        assert_eq!(allowed.command.env.len(), 2);
+
+        let mut denied = with_env(&[("PATH", "/usr/bin"), ("GITHUB_TOKEN", "x"), ("AWS_REGION", "y")]);
+        match policy.apply(&mut denied) {
+            Err(McpError::DetailedPolicyViolation { message, .. }) => {
+                assert!(message.contains("'AWS_REGION'"));
+                assert!(message.contains("'GITHUB_TOKEN'"));
+                assert!(!message.contains("PATH"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    // Test for stripping matching variables
+    #[test]
+    fn test_strip() {
+        let policy = EnvPolicy::new(&PATTERNS, EnvAction::Strip).unwrap();
+        let mut input = with_env(&[("PATH", "/usr/bin"), ("DB_SECRET", "x"), ("AWS_ACCESS_KEY_ID", "y")]);
+
+        let stripped = policy.apply(&mut input).unwrap();
+        assert_eq!(stripped, vec!["AWS_ACCESS_KEY_ID", "DB_SECRET"]);
+        assert_eq!(input.command.env.keys().collect::<Vec<_>>(), vec!["PATH"]);
+    }
+
+    // Test for the disabled default and invalid settings
+    #[test]
+    fn test_disabled_and_invalid() {
+        let policy = EnvPolicy::default();
+        assert!(!policy.is_enabled());
+        assert!(policy.apply(&mut with_env(&[("AWS_SECRET_ACCESS_KEY", "x")])).unwrap().is_empty());
+
+        assert!(EnvPolicy::new(&["AWS_[*"], EnvAction::Deny).is_err());
+        assert!("Strip".parse::<EnvAction>().unwrap() == EnvAction::Strip);
+        assert!("drop".parse::<EnvAction>().is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! GeoIP enrichment of network requests
+//!
+//! [`GeoIpEnricher`] resolves `NetworkInfo.host` to its IP addresses and looks them up in
+//! MaxMind databases (GeoLite2/GeoIP2 Country and ASN), so that policies can deny egress
+//! to countries or autonomous systems (see the `network.deny_countries` and
+//! `network.deny_asns` rules of the rule-based evaluator). The result is added to
+//! `PolicyInput.context` as `geoip`:
+//!
+//! ```json
+//! {"addresses": ["93.184.216.34"], "countries": ["US"], "asns": [15133]}
+//! ```
+//!
+//! A host may resolve to several addresses; the countries and ASNs of all of them are
+//! listed, so a policy denying any of them denies the request. Lookups are cached per host.
+
+use crate::enrich::InputEnricher;
+use crate::models::PolicyInput;
+use async_trait::async_trait;
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+use mcp_common::error::{McpError, McpResult};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Context key set by [`GeoIpEnricher`]
+pub const CONTEXT_GEOIP: &str = "geoip";
+
+/// Maximum number of cached hosts; the cache is emptied when it is full
+const CACHE_CAPACITY: usize = 10_000;
+
+/// Country and autonomous system of an IP address
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeoIpRecord {
+    /// ISO 3166-1 alpha-2 country code
+    pub country: Option<String>,
+    /// Autonomous system number
+    pub asn: Option<u32>,
+}
+
+/// Source of GeoIP records
+pub trait GeoIpLookup: Send + Sync {
+    /// Record of an address (empty if the address is not in the database)
+    fn lookup(&self, ip: IpAddr) -> McpResult<GeoIpRecord>;
+}
+
+/// GeoIP records from MaxMind databases in the MMDB format
+pub struct MaxMindLookup {
+    country: Option<Reader<Vec<u8>>>,
+    asn: Option<Reader<Vec<u8>>>,
+}
+
+impl MaxMindLookup {
+    /// Open a Country (or City) database and/or an ASN database
+    pub fn open(country_db: Option<&Path>, asn_db: Option<&Path>) -> McpResult<Self> {
+        let open = |path: &Path| {
+            Reader::open_readfile(path).map_err(|e| {
+                McpError::InvalidRequest(format!("Failed to open GeoIP database {}: {}", path.display(), e))
+            })
+        };
+        Ok(Self {
+            country: country_db.map(open).transpose()?,
+            asn: asn_db.map(open).transpose()?,
+        })
+    }
+}
+
+impl GeoIpLookup for MaxMindLookup {
+    fn lookup(&self, ip: IpAddr) -> McpResult<GeoIpRecord> {
+        let mut record = GeoIpRecord::default();
+        if let Some(reader) = &self.country {
+            if let Some(country) = not_found_as_none(reader.lookup::<geoip2::Country>(ip))? {
+                record.country = country.country.and_then(|country| country.iso_code).map(str::to_string);
+            }
+        }
+        if let Some(reader) = &self.asn {
+            if let Some(asn) = not_found_as_none(reader.lookup::<geoip2::Asn>(ip))? {
+                record.asn = asn.autonomous_system_number;
+            }
+        }
+        Ok(record)
+    }
+}
+
+fn not_found_as_none<T>(result: Result<T, MaxMindDBError>) -> McpResult<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(MaxMindDBError::AddressNotFoundError(_)) => Ok(None),
+        Err(e) => Err(McpError::Internal(format!("GeoIP lookup failed: {}", e))),
+    }
+}
+
+/// Sets `geoip` in the context to the addresses, countries and ASNs of the network destination
+///
+/// The context is not set for inputs without a network destination or when the host cannot
+/// be resolved. Results are cached per host for the cache TTL (5 minutes by default).
+pub struct GeoIpEnricher {
+    lookup: Arc<dyn GeoIpLookup>,
+    ttl: Duration,
+    cache: Mutex<HashMap<String, (Instant, Value)>>,
+}
+
+impl GeoIpEnricher {
+    /// Create an enricher using a lookup
+    pub fn new(lookup: impl GeoIpLookup + 'static) -> Self {
+        Self {
+            lookup: Arc::new(lookup),
+            ttl: Duration::from_secs(300),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set how long the result for a host is cached
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Build the enricher from environment variables
+    ///
+    /// * `MCP_POLICY_GEOIP_COUNTRY_DB` - path of a GeoLite2/GeoIP2 Country or City database
+    /// * `MCP_POLICY_GEOIP_ASN_DB` - path of a GeoLite2/GeoIP2 ASN database
+    /// * `MCP_POLICY_GEOIP_CACHE_TTL_SECS` - cache TTL of a host (default 300)
+    ///
+    /// Returns `None` when no database is configured.
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let country_db = std::env::var("MCP_POLICY_GEOIP_COUNTRY_DB").ok();
+        let asn_db = std::env::var("MCP_POLICY_GEOIP_ASN_DB").ok();
+        if country_db.is_none() && asn_db.is_none() {
+            return Ok(None);
+        }
+
+        let lookup = MaxMindLookup::open(country_db.as_deref().map(Path::new), asn_db.as_deref().map(Path::new))?;
+        let mut enricher = Self::new(lookup);
+        if let Ok(value) = std::env::var("MCP_POLICY_GEOIP_CACHE_TTL_SECS") {
+            let secs = value.trim().parse().map_err(|_| {
+                McpError::InvalidRequest(format!("MCP_POLICY_GEOIP_CACHE_TTL_SECS must be a number: '{}'", value))
+            })?;
+            enricher = enricher.with_cache_ttl(Duration::from_secs(secs));
+        }
+        Ok(Some(enricher))
+    }
+
+    /// GeoIP context value of a host, `None` if it cannot be resolved
+    pub async fn locate(&self, host: &str, port: u16) -> McpResult<Option<Value>> {
+        if let Some((at, value)) = self.cache.lock().unwrap().get(host) {
+            if at.elapsed() < self.ttl {
+                return Ok(Some(value.clone()));
+            }
+        }
+
+        let addresses = match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+            Ok(ip) => vec![ip],
+            Err(_) => match tokio::net::lookup_host((host, port)).await {
+                Ok(addresses) => {
+                    let mut ips: Vec<IpAddr> = addresses.map(|address| address.ip()).collect();
+                    ips.sort();
+                    ips.dedup();
+                    ips
+                }
+                Err(e) => {
+                    debug!("Failed to resolve host '{}' for the GeoIP lookup: {}", host, e);
+                    return Ok(None);
+                }
+            },
+        };
+
+        let mut countries: Vec<String> = Vec::new();
+        let mut asns: Vec<u32> = Vec::new();
+        for ip in &addresses {
+            let record = self.lookup.lookup(*ip)?;
+            if let Some(country) = record.country {
+                if !countries.contains(&country) {
+                    countries.push(country);
+                }
+            }
+            if let Some(asn) = record.asn {
+                if !asns.contains(&asn) {
+                    asns.push(asn);
+                }
+            }
+        }
+        let addresses: Vec<String> = addresses.iter().map(IpAddr::to_string).collect();
+        let value = json!({ "addresses": addresses, "countries": countries, "asns": asns });
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(host.to_string(), (Instant::now(), value.clone()));
+        Ok(Some(value))
+    }
+}
+
+#[async_trait]
+impl InputEnricher for GeoIpEnricher {
+    async fn enrich(&self, input: &mut PolicyInput) -> McpResult<()> {
+        let Some(network) = &input.network else {
+            return Ok(());
+        };
+        if let Some(value) = self.locate(&network.host, network.port).await? {
+            input.context.insert(CONTEXT_GEOIP.to_string(), value);
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "geoip"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NetworkInfo;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Lookup assigning 10.0.0.0/8 to Japan and everything else to AS 64512 in the US
+    #[derive(Default)]
+    struct StubLookup {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl GeoIpLookup for StubLookup {
+        fn lookup(&self, ip: IpAddr) -> McpResult<GeoIpRecord> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(match ip {
+                IpAddr::V4(v4) if v4.octets()[0] == 10 => GeoIpRecord {
+                    country: Some("JP".to_string()),
+                    asn: None,
+                },
+                _ => GeoIpRecord {
+                    country: Some("US".to_string()),
+                    asn: Some(64512),
+                },
+            })
+        }
+    }
+
+    fn policy_input(network: Option<NetworkInfo>) -> PolicyInput {
+        PolicyInput {
+            user: Default::default(),
+            command: Default::default(),
+            file: None,
+            network,
+            resources: Default::default(),
+            context: HashMap::new(),
+        }
+    }
+
+    fn network_input(host: &str) -> PolicyInput {
+        policy_input(Some(NetworkInfo {
+            host: host.to_string(),
+            port: 443,
+            protocol: "tcp".to_string(),
+        }))
+    }
+
+    // Test for adding the GeoIP context and caching lookups
+    #[tokio::test]
+    async fn test_geoip_enricher() {
+        let lookup = StubLookup::default();
+        let calls = lookup.calls.clone();
+        let enricher = GeoIpEnricher::new(lookup);
+
+        let mut input = network_input("10.1.2.3");
+        enricher.enrich(&mut input).await.unwrap();
+        assert_eq!(
+            input.context[CONTEXT_GEOIP],
+            json!({ "addresses": ["10.1.2.3"], "countries": ["JP"], "asns": [] })
+        );
+
+        let mut input = network_input("[2001:db8::1]");
+        enricher.enrich(&mut input).await.unwrap();
+        assert_eq!(input.context[CONTEXT_GEOIP]["countries"], json!(["US"]));
+        assert_eq!(input.context[CONTEXT_GEOIP]["asns"], json!([64512]));
+
+        // The second lookup of a host is served from the cache
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        enricher.enrich(&mut network_input("10.1.2.3")).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Unresolvable hosts and inputs without a network destination are left alone
+        let mut input = network_input("unresolvable.invalid");
+        enricher.enrich(&mut input).await.unwrap();
+        assert!(!input.context.contains_key(CONTEXT_GEOIP));
+        let mut input = policy_input(None);
+        enricher.enrich(&mut input).await.unwrap();
+        assert!(input.context.is_empty());
+    }
+}
@@ -1,13 +1,81 @@
 //! MCPセキュリティゲートウェイのポリシーエンジン
 //!
 //! OPA (Open Policy Agent) Regoポリシーを評価するためのエンジンを提供します。
+//!
+//! Cedarのポリシー、Rhaiのスクリプト、WASMのプラグインによる評価は、それぞれ`cedar`、`script`、`wasm`機能で有効になります。
 
+pub mod audit;
+pub mod bench;
+pub mod break_glass;
+pub mod bundle;
+pub mod canary;
+pub mod chain;
+pub mod canonicalize;
+#[cfg(feature = "cedar")]
+pub mod cedar;
+pub mod content_scan;
+pub mod decision_cache;
 pub mod engine;
+pub mod enrich;
+pub mod env_policy;
+pub mod geoip;
+pub mod metrics;
 pub mod models;
+pub mod normalize;
+pub mod opa_http;
+pub mod path_pattern;
+pub mod rego;
+pub mod resource_limits;
+pub mod rules;
+#[cfg(feature = "script")]
+pub mod script;
+pub mod shell;
+pub mod testing;
+#[cfg(feature = "wasm")]
+pub mod wasm_plugin;
+pub mod watcher;
+pub mod webhook;
+
+#[cfg(test)]
+mod test_support;
 
 /// Re-export the main components
-pub use engine::{PolicyEngine, PolicyEvaluator, StubPolicyEvaluator};
-pub use models::{PolicyDecision, PolicyInput, CommandInfo, UserInfo, FileInfo, NetworkInfo, ResourceLimits};
+pub use audit::{AuditFormat, AuditRecord, AuditSink, FileAuditSink, StdoutAuditSink, TracingAuditSink};
+pub use bench::{PolicyBenchReport, PolicyBenchmark};
+pub use break_glass::{BreakGlass, BreakGlassClaims};
+pub use bundle::{BundleConfig, BundlePoller, BundleTrustKey, SignatureStatus};
+pub use canary::{CanaryOutcome, PolicyCanary};
+pub use chain::{ChainedEvaluator, CombineMode};
+pub use canonicalize::PathCanonicalizer;
+#[cfg(feature = "cedar")]
+pub use cedar::CedarEvaluator;
+pub use content_scan::{ContentScan, ContentScanner, RegexScanner, ScanFinding};
+pub use decision_cache::{DecisionCache, DecisionCacheConfig};
+pub use engine::{
+    AsyncPolicyEvaluator, EnforcementMode, FailClosedEvaluator, PolicyDiagnostic, PolicyEngine, PolicyEvaluator,
+    PolicyReload, StubPolicyEvaluator,
+};
+pub use enrich::{ExecutableHashEnricher, InputEnricher, StaticContextEnricher, WorkingHoursEnricher};
+pub use env_policy::{EnvAction, EnvPolicy};
+pub use geoip::{GeoIpEnricher, GeoIpLookup, GeoIpRecord, MaxMindLookup};
+pub use metrics::PolicyMetrics;
+pub use normalize::CommandNormalizer;
+pub use opa_http::{FailureMode, OpaHttpConfig, OpaHttpEvaluator};
+pub use path_pattern::PathPattern;
+pub use rego::RegoEvaluator;
+pub use resource_limits::ResourceLimitPolicy;
+pub use rules::{ArgPattern, RuleBasedEvaluator, RuleConfig, ToolRules};
+#[cfg(feature = "script")]
+pub use script::ScriptEvaluator;
+pub use testing::{PolicyTestReport, PolicyTestSuite};
+#[cfg(feature = "wasm")]
+pub use wasm_plugin::{WasmPluginConfig, WasmPluginEvaluator};
+pub use watcher::PolicyWatcher;
+pub use webhook::{ViolationEvent, WebhookConfig, WebhookNotifier};
+pub use models::{
+    PolicyDecision, PolicyExplanation, PolicyInput, CommandInfo, UserInfo, FileInfo, NetworkInfo, ResourceLimits, RuleEffect,
+    RuleMatch, DenyReason, CommandLimits,
+};
 
 /// Provide version information
 pub const VERSION: &str = env!("CARGO_PKG_VERSION"); 
\ No newline at end of file
@@ -0,0 +1,45 @@
+//! Policy engine metrics
+//!
+//! `PolicyEngine` reports what it does to a [`PolicyMetrics`] implementation, so that the
+//! embedding application can export the numbers with its own metrics library (the gateway
+//! registers them with its Prometheus registry). The policy crate itself does not depend
+//! on a metrics library.
+
+use crate::models::PolicyInput;
+use std::time::Duration;
+
+/// Check type of inputs with a file (`PolicyInput.file`)
+pub const CHECK_FILE_ACCESS: &str = "file_access";
+/// Check type of inputs with a network destination (`PolicyInput.network`)
+pub const CHECK_NETWORK_ACCESS: &str = "network_access";
+/// Check type of all other inputs
+pub const CHECK_COMMAND_EXECUTION: &str = "command_execution";
+
+/// Receiver of policy engine measurements
+pub trait PolicyMetrics: Send + Sync {
+    /// Record an evaluation
+    ///
+    /// `check` is the check type of the input (see [`check_type`]) and `outcome` one of
+    /// "allow", "deny" or "error". `latency` includes enrichment and the decision cache.
+    fn observe_evaluation(&self, check: &str, outcome: &str, latency: Duration);
+
+    /// Record a decision cache lookup ("hit" or "miss")
+    fn observe_cache_lookup(&self, result: &str);
+
+    /// Record a reload of the policies
+    ///
+    /// `source` is "admin" (see `PolicyEngine::reload_from_env`), "watcher" or "bundle";
+    /// `result` is "success" or "failure".
+    fn observe_reload(&self, source: &str, result: &str);
+}
+
+/// Check type of an input: `file_access`, `network_access` or `command_execution`
+pub fn check_type(input: &PolicyInput) -> &'static str {
+    if input.file.is_some() {
+        CHECK_FILE_ACCESS
+    } else if input.network.is_some() {
+        CHECK_NETWORK_ACCESS
+    } else {
+        CHECK_COMMAND_EXECUTION
+    }
+}
@@ -1,4 +1,8 @@
-use serde::{Serialize, Deserialize};
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::hex;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 
 /// Policy evaluation input data
@@ -24,6 +28,55 @@ pub struct PolicyInput {
     pub context: HashMap<String, serde_json::Value>,
 }
 
+/// Context key of the script a command runs (see [`PolicyInput::set_script`])
+pub const CONTEXT_SCRIPT: &str = "script";
+
+/// Context key of the MCP tool call being evaluated (see [`PolicyInput::set_tool_call`])
+pub const CONTEXT_TOOL_CALL: &str = "tool_call";
+
+impl PolicyInput {
+    /// Record a script the command runs in the context as `script`
+    ///
+    /// The context holds the path and content of the script with its SHA-256 digest and size,
+    /// so that policies can allow scripts by digest or deny them by content. When the command
+    /// is a shell interpreter, `PolicyEngine` evaluates the commands of the script as well
+    /// (see [`crate::shell::script_commands`]).
+    pub fn set_script(&mut self, path: &str, content: &str) {
+        let sha256 = hex(&Sha256::digest(content.as_bytes()));
+        self.context.insert(
+            CONTEXT_SCRIPT.to_string(),
+            json!({ "path": path, "content": content, "sha256": sha256, "bytes": content.len() }),
+        );
+    }
+
+    /// Content of the script recorded with [`Self::set_script`]
+    pub fn script(&self) -> Option<&str> {
+        self.context.get(CONTEXT_SCRIPT)?.get("content")?.as_str()
+    }
+
+    /// Hex SHA-256 digest of the script recorded with [`Self::set_script`]
+    pub fn script_sha256(&self) -> Option<&str> {
+        self.context.get(CONTEXT_SCRIPT)?.get("sha256")?.as_str()
+    }
+
+    /// Record a call of a tool of an upstream MCP server in the context as `tool_call`
+    ///
+    /// The context holds the server and tool names with the arguments of the call, so that
+    /// policies can allow tools by name or deny calls by argument.
+    pub fn set_tool_call(&mut self, server: &str, tool: &str, arguments: &serde_json::Value) {
+        self.context.insert(
+            CONTEXT_TOOL_CALL.to_string(),
+            json!({ "server": server, "tool": tool, "arguments": arguments }),
+        );
+    }
+
+    /// Server and tool name of the call recorded with [`Self::set_tool_call`]
+    pub fn tool_call(&self) -> Option<(&str, &str)> {
+        let tool_call = self.context.get(CONTEXT_TOOL_CALL)?;
+        Some((tool_call.get("server")?.as_str()?, tool_call.get("tool")?.as_str()?))
+    }
+}
+
 /// User information
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct UserInfo {
@@ -53,6 +106,9 @@ pub struct CommandInfo {
     /// Environment variables
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Absolute path of the executable the command resolves to, if the gateway resolved it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executable: Option<String>,
 }
 
 /// File access information
@@ -81,6 +137,9 @@ pub struct ResourceLimits {
     /// CPU time limit (milliseconds)
     #[serde(default)]
     pub cpu_time_ms: Option<u64>,
+    /// CPU limit (cores)
+    #[serde(default)]
+    pub cpu_cores: Option<f64>,
     /// Memory usage limit (kilobytes)
     #[serde(default)]
     pub memory_kb: Option<u64>,
@@ -92,6 +151,120 @@ pub struct ResourceLimits {
     pub max_processes: Option<u32>,
 }
 
+/// Decision metadata key marking a command as cacheable (read-only and idempotent)
+pub const METADATA_CACHEABLE: &str = "cacheable";
+
+/// Decision metadata key with the [`CommandLimits`] of an allowed command
+pub const METADATA_COMMAND_LIMITS: &str = "command_limits";
+
+/// Default and maximum timeout and resources of a command
+///
+/// Requested values above a maximum are clamped to it rather than denied; values that are
+/// not requested fall back to the default. Memory sizes are bytes, or strings with a binary
+/// `K`, `M` or `G` suffix (e.g. `"2G"`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandLimits {
+    /// Timeout when none is requested (seconds)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_timeout_secs: Option<u32>,
+    /// Maximum timeout (seconds)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_timeout_secs: Option<u32>,
+    /// CPU limit when none is requested (cores)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_cpu_cores: Option<f64>,
+    /// Maximum CPU limit (cores)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cpu_cores: Option<f64>,
+    /// Memory limit when none is requested (bytes)
+    #[serde(deserialize_with = "memory_size", skip_serializing_if = "Option::is_none")]
+    pub default_memory: Option<u64>,
+    /// Maximum memory limit (bytes)
+    #[serde(deserialize_with = "memory_size", skip_serializing_if = "Option::is_none")]
+    pub max_memory: Option<u64>,
+}
+
+/// Parse a memory size: bytes, or a number with a binary `K`, `M` or `G` suffix
+pub fn parse_memory_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (number, multiplier) = match size.char_indices().last() {
+        Some((index, 'K' | 'k')) => (&size[..index], 1 << 10),
+        Some((index, 'M' | 'm')) => (&size[..index], 1 << 20),
+        Some((index, 'G' | 'g')) => (&size[..index], 1 << 30),
+        _ => (size, 1),
+    };
+    number.trim().parse::<u64>().ok().and_then(|number| number.checked_mul(multiplier))
+}
+
+fn memory_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+
+    match Option::<Size>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Size::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(Size::Text(text)) => parse_memory_size(&text).map(Some).ok_or_else(|| {
+            serde::de::Error::custom(format!("invalid memory size '{}', expected bytes or a size such as \"2G\"", text))
+        }),
+    }
+}
+
+/// Codes of [`DenyReason`]s
+pub mod reason_code {
+    /// The command is on a deny list
+    pub const COMMAND_DENIED: &str = "command_denied";
+    /// The command is not on an allow list
+    pub const COMMAND_NOT_ALLOWED: &str = "command_not_allowed";
+    /// An argument of the command is not allowed
+    pub const ARGUMENT_NOT_ALLOWED: &str = "argument_not_allowed";
+    /// The executable of the command does not have a pinned digest
+    pub const EXECUTABLE_NOT_VERIFIED: &str = "executable_not_verified";
+    /// The path is on a deny list
+    pub const PATH_DENIED: &str = "path_denied";
+    /// The path is not on an allow list for the access mode
+    pub const PATH_NOT_ALLOWED: &str = "path_not_allowed";
+    /// The written content has a denied content scan finding
+    pub const CONTENT_DENIED: &str = "content_denied";
+    /// The host is on a deny list
+    pub const HOST_DENIED: &str = "host_denied";
+    /// The host is not on an allow list
+    pub const HOST_NOT_ALLOWED: &str = "host_not_allowed";
+    /// The port is not allowed
+    pub const PORT_NOT_ALLOWED: &str = "port_not_allowed";
+    /// The protocol is not allowed
+    pub const PROTOCOL_NOT_ALLOWED: &str = "protocol_not_allowed";
+    /// The host is located in a denied country or autonomous system
+    pub const LOCATION_DENIED: &str = "location_denied";
+    /// The MCP tool is on a deny list
+    pub const TOOL_DENIED: &str = "tool_denied";
+    /// The MCP tool is not on an allow list
+    pub const TOOL_NOT_ALLOWED: &str = "tool_not_allowed";
+    /// The command string of a shell wrapper could not be parsed
+    pub const SHELL_COMMAND_REJECTED: &str = "shell_command_rejected";
+    /// Denial of an evaluator that does not report structured reasons
+    pub const POLICY_DENIED: &str = "policy_denied";
+}
+
+/// Machine-readable denial reason
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DenyReason {
+    /// Reason code (see [`reason_code`])
+    pub code: String,
+    /// Rule that denied the action (e.g. `commands.deny`), if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_id: Option<String>,
+    /// Human-readable reason, as in `PolicyDecision.reasons`
+    pub message: String,
+    /// What was denied: a command, argument, path, host, ...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+}
+
 /// Policy evaluation decision result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PolicyDecision {
@@ -103,7 +276,102 @@ pub struct PolicyDecision {
     /// Denial reasons (if rejected)
     #[serde(default)]
     pub reasons: Vec<String>,
+    /// Machine-readable denial reasons (empty if the evaluator reports plain `reasons` only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny_reasons: Vec<DenyReason>,
     /// Additional metadata
     #[serde(default)]
     pub metadata: HashMap<String, serde_json::Value>,
-} 
\ No newline at end of file
+}
+
+impl PolicyDecision {
+    /// Whether the policy marked the action as cacheable
+    pub fn is_cacheable(&self) -> bool {
+        self.metadata
+            .get(METADATA_CACHEABLE)
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Limits the policy set for the command, if any
+    ///
+    /// Malformed limits are an error, so that a mistake in a policy never goes unnoticed.
+    pub fn command_limits(&self) -> McpResult<Option<CommandLimits>> {
+        self.metadata
+            .get(METADATA_COMMAND_LIMITS)
+            .map(|value| {
+                serde_json::from_value(value.clone()).map_err(|e| {
+                    McpError::InvalidRequest(format!("Invalid '{}' in policy decision: {}", METADATA_COMMAND_LIMITS, e))
+                })
+            })
+            .transpose()
+    }
+
+    /// Machine-readable denial reasons
+    ///
+    /// Plain `reasons` of evaluators that do not report structured ones become
+    /// `policy_denied` reasons about `subject`.
+    pub fn structured_reasons(&self, subject: &str) -> Vec<DenyReason> {
+        if !self.deny_reasons.is_empty() {
+            return self.deny_reasons.clone();
+        }
+        self.reasons
+            .iter()
+            .map(|reason| DenyReason {
+                code: reason_code::POLICY_DENIED.to_string(),
+                rule_id: None,
+                message: reason.clone(),
+                subject: Some(subject.to_string()),
+            })
+            .collect()
+    }
+}
+
+/// Effect of a matched rule on the decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleEffect {
+    /// The rule permits the action
+    Allow,
+    /// The rule denies the action
+    Deny,
+    /// The rule adds a warning
+    Warn,
+}
+
+impl RuleEffect {
+    /// Lowercase name of the effect
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+            Self::Warn => "warn",
+        }
+    }
+}
+
+/// Rule that contributed to a decision
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleMatch {
+    /// Rule identifier (e.g. `commands.deny`, `data.mcp.command.deny_reasons` or a Cedar policy ID)
+    pub rule: String,
+    /// Where the rule is defined (policy file, rule file or origin of the evaluator)
+    pub source: String,
+    /// Effect of the rule
+    pub effect: RuleEffect,
+    /// Reason, warning or description of the match
+    #[serde(default)]
+    pub message: String,
+}
+
+/// Decision together with the rules that produced it, in evaluation order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyExplanation {
+    /// Name of the evaluator that made the decision
+    pub evaluator: String,
+    /// Decision
+    pub decision: PolicyDecision,
+    /// Matched rules (empty if the evaluator cannot trace its rules)
+    #[serde(default)]
+    pub rules: Vec<RuleMatch>,
+}
@@ -0,0 +1,372 @@
+//! Normalization of commands before policy evaluation
+//!
+//! Policies decide on the command name, so `busybox rm -rf /` or `/usr/bin/env python3 -c ...`
+//! would be checked as `busybox` and `env`. `PolicyEngine` therefore rewrites
+//! `PolicyInput.command` with a [`CommandNormalizer`] into the command that actually runs:
+//!
+//! * configured aliases are expanded (`ll` -> `ls -l`),
+//! * wrappers that run their arguments as a command (`env`, `nice`, `nohup`, `timeout`,
+//!   `stdbuf`, `setsid`, `ionice`, `time`, `xargs`) are skipped together with their options,
+//!   environment assignments and operands,
+//! * multi-call binaries (`busybox`, `toybox` and configured ones) are replaced by their applet.
+//!
+//! The steps repeat until the command no longer changes, so `env busybox nice rm` becomes
+//! `rm`. Wrappers and multi-call binaries are recognized with or without a directory. The
+//! original command is kept in `PolicyInput.context` as `original_command`:
+//!
+//! ```json
+//! {"name": "busybox", "args": ["rm", "-rf", "/"], "via": ["busybox"]}
+//! ```
+//!
+//! Only the policy input is rewritten; the gateway still executes the original command.
+
+use crate::models::PolicyInput;
+use crate::shell::split_command_line;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::parse_key_value_pairs;
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Context key set when a command was normalized
+pub const CONTEXT_ORIGINAL_COMMAND: &str = "original_command";
+
+/// Multi-call binaries recognized by default
+pub const DEFAULT_MULTI_CALL_BINARIES: &[&str] = &["busybox", "toybox"];
+
+/// Maximum number of normalization steps for one command
+const MAX_STEPS: usize = 16;
+
+/// Command that runs its arguments as a command
+struct Wrapper {
+    name: &'static str,
+    /// Options followed by a separate value (`-u NAME`)
+    options_with_value: &'static [&'static str],
+    /// Operands before the command (the duration of `timeout`)
+    operands: usize,
+}
+
+const WRAPPERS: &[Wrapper] = &[
+    Wrapper {
+        name: "env",
+        options_with_value: &["-u", "--unset", "-C", "--chdir", "-S", "--split-string"],
+        operands: 0,
+    },
+    Wrapper {
+        name: "nice",
+        options_with_value: &["-n", "--adjustment"],
+        operands: 0,
+    },
+    Wrapper {
+        name: "nohup",
+        options_with_value: &[],
+        operands: 0,
+    },
+    Wrapper {
+        name: "timeout",
+        options_with_value: &["-s", "--signal", "-k", "--kill-after"],
+        operands: 1,
+    },
+    Wrapper {
+        name: "stdbuf",
+        options_with_value: &["-i", "--input", "-o", "--output", "-e", "--error"],
+        operands: 0,
+    },
+    Wrapper {
+        name: "setsid",
+        options_with_value: &[],
+        operands: 0,
+    },
+    Wrapper {
+        name: "ionice",
+        options_with_value: &["-c", "--class", "-n", "--classdata"],
+        operands: 0,
+    },
+    Wrapper {
+        name: "time",
+        options_with_value: &["-f", "--format", "-o", "--output"],
+        operands: 0,
+    },
+    Wrapper {
+        name: "xargs",
+        options_with_value: &[
+            "-a", "--arg-file", "-d", "--delimiter", "-E", "-I", "-L", "-n", "--max-args", "-P", "--max-procs", "-s",
+            "--max-chars",
+        ],
+        operands: 0,
+    },
+];
+
+/// Rewrites commands into the command that actually runs
+#[derive(Debug, Clone)]
+pub struct CommandNormalizer {
+    aliases: HashMap<String, Vec<String>>,
+    multi_call: Vec<String>,
+}
+
+impl Default for CommandNormalizer {
+    fn default() -> Self {
+        Self {
+            aliases: HashMap::new(),
+            multi_call: DEFAULT_MULTI_CALL_BINARIES.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+}
+
+impl CommandNormalizer {
+    /// Normalizer with the built-in wrappers and multi-call binaries
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expand `name` to `target` (a command with optional leading arguments, e.g. `ls -l`)
+    pub fn with_alias(mut self, name: &str, target: &str) -> McpResult<Self> {
+        let target: Vec<String> = target.split_whitespace().map(str::to_string).collect();
+        if target.is_empty() {
+            return Err(McpError::InvalidRequest(format!("Alias '{}' has no target command", name)));
+        }
+        self.aliases.insert(name.to_string(), target);
+        Ok(self)
+    }
+
+    /// Treat a binary as a multi-call binary whose first argument is the applet
+    pub fn with_multi_call_binary(mut self, name: &str) -> Self {
+        self.multi_call.push(name.to_string());
+        self
+    }
+
+    /// Build the normalizer from environment variables
+    ///
+    /// * `MCP_POLICY_COMMAND_ALIASES` - aliases (`ll=ls -l,la=ls -a`)
+    /// * `MCP_POLICY_MULTI_CALL_BINARIES` - comma separated multi-call binaries in addition to
+    ///   `busybox` and `toybox`
+    pub fn from_env() -> McpResult<Self> {
+        let mut normalizer = Self::new();
+        if let Ok(value) = std::env::var("MCP_POLICY_COMMAND_ALIASES") {
+            for (name, target) in parse_key_value_pairs(&value)? {
+                normalizer = normalizer.with_alias(&name, &target)?;
+            }
+        }
+        if let Ok(value) = std::env::var("MCP_POLICY_MULTI_CALL_BINARIES") {
+            for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                normalizer = normalizer.with_multi_call_binary(name);
+            }
+        }
+        Ok(normalizer)
+    }
+
+    /// Effective command of `name` and `args`, with the wrappers and multi-call binaries it went through
+    ///
+    /// Returns `None` if the command is already in its effective form.
+    pub fn normalize(&self, name: &str, args: &[String]) -> McpResult<Option<(Vec<String>, Vec<String>)>> {
+        let mut argv: Vec<String> = std::iter::once(name.to_string()).chain(args.iter().cloned()).collect();
+        let mut via = Vec::new();
+
+        for _ in 0..MAX_STEPS {
+            let base = argv[0].rsplit('/').next().unwrap_or(&argv[0]).to_string();
+            let next = if let Some(target) = self.aliases.get(&argv[0]) {
+                Some(target.iter().cloned().chain(argv[1..].iter().cloned()).collect())
+            } else if let Some(wrapper) = WRAPPERS.iter().find(|wrapper| wrapper.name == base) {
+                unwrap(wrapper, &argv[1..])?
+            } else if self.multi_call.contains(&base) {
+                argv.get(1).filter(|applet| !applet.starts_with('-')).map(|_| argv[1..].to_vec())
+            } else {
+                None
+            };
+
+            match next {
+                Some(next) => {
+                    via.push(argv[0].clone());
+                    argv = next;
+                }
+                None if via.is_empty() => return Ok(None),
+                None => return Ok(Some((argv, via))),
+            }
+        }
+
+        Err(McpError::InvalidRequest(format!(
+            "Command '{}' is wrapped more than {} times",
+            name, MAX_STEPS
+        )))
+    }
+
+    /// Name of the command that actually runs, without its directory (`env /usr/bin/npm ci` is `npm`)
+    pub fn effective_name(&self, name: &str, args: &[String]) -> McpResult<String> {
+        let effective = match self.normalize(name, args)? {
+            Some((argv, _)) => argv.into_iter().next().unwrap_or_default(),
+            None => name.to_string(),
+        };
+        Ok(effective.rsplit('/').next().unwrap_or_default().to_string())
+    }
+
+    /// Rewrite the command of an input into its effective form
+    ///
+    /// Returns whether the input was changed.
+    pub fn apply(&self, input: &mut PolicyInput) -> McpResult<bool> {
+        if input.command.name.is_empty() {
+            return Ok(false);
+        }
+        let Some((argv, via)) = self.normalize(&input.command.name, &input.command.args)? else {
+            return Ok(false);
+        };
+
+        let original = json!({ "name": input.command.name, "args": input.command.args, "via": via });
+        input.context.insert(CONTEXT_ORIGINAL_COMMAND.to_string(), original);
+        input.command.name = argv[0].clone();
+        input.command.args = argv[1..].to_vec();
+        Ok(true)
+    }
+}
+
+// Command run by a wrapper (`None` if it runs none, e.g. a bare `env`)
+fn unwrap(wrapper: &Wrapper, args: &[String]) -> McpResult<Option<Vec<String>>> {
+    let mut index = 0;
+    let mut split_string = None;
+
+    while let Some(arg) = args.get(index) {
+        if arg == "--" {
+            index += 1;
+            break;
+        }
+        if wrapper.name == "env" && !arg.starts_with('-') && arg.contains('=') {
+            index += 1;
+            continue;
+        }
+        if !arg.starts_with('-') || arg == "-" {
+            break;
+        }
+
+        // Option taking a value, with its value (`--unset=NAME`, `-u NAME`, `-iuNAME`)
+        let with_value = match arg.strip_prefix("--") {
+            Some(long) => match long.split_once('=') {
+                Some((option, value)) => Some((format!("--{}", option), Some(value.to_string()))),
+                None if wrapper.options_with_value.contains(&arg.as_str()) => {
+                    index += 1;
+                    Some((arg.clone(), args.get(index).cloned()))
+                }
+                None => None,
+            },
+            None => {
+                let cluster = &arg[1..];
+                cluster.char_indices().find_map(|(position, flag)| {
+                    let option = format!("-{}", flag);
+                    if !wrapper.options_with_value.contains(&option.as_str()) {
+                        return None;
+                    }
+                    let rest = &cluster[position + flag.len_utf8()..];
+                    if rest.is_empty() {
+                        index += 1;
+                        Some((option, args.get(index).cloned()))
+                    } else {
+                        Some((option, Some(rest.to_string())))
+                    }
+                })
+            }
+        };
+        if let Some((option, value)) = with_value {
+            if wrapper.name == "env" && matches!(option.as_str(), "-S" | "--split-string") {
+                split_string = value;
+            }
+        }
+        index += 1;
+    }
+
+    let mut command: Vec<String> = args.iter().skip(index + wrapper.operands).cloned().collect();
+    if let Some(line) = split_string {
+        // `env -S` splits its argument into the command and its first arguments
+        let mut commands = split_command_line(&line)?;
+        if commands.len() != 1 {
+            return Err(McpError::InvalidRequest(format!(
+                "Split string of '{}' must contain a single command: '{}'",
+                wrapper.name, line
+            )));
+        }
+        let mut split = commands.remove(0);
+        split.append(&mut command);
+        command = split;
+    }
+
+    Ok((!command.is_empty()).then_some(command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(normalizer: &CommandNormalizer, line: &[&str]) -> Option<Vec<String>> {
+        let args: Vec<String> = line[1..].iter().map(|arg| arg.to_string()).collect();
+        normalizer.normalize(line[0], &args).unwrap().map(|(argv, _)| argv)
+    }
+
+    fn argv(line: &[&str]) -> Option<Vec<String>> {
+        Some(line.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    // Test for resolving wrappers, multi-call binaries and aliases
+    #[test]
+    fn test_normalize_commands() {
+        let normalizer = CommandNormalizer::new().with_alias("ll", "ls -l").unwrap();
+
+        assert_eq!(normalize(&normalizer, &["busybox", "rm", "-rf", "/"]), argv(&["rm", "-rf", "/"]));
+        assert_eq!(normalize(&normalizer, &["/usr/bin/env", "python3", "x.py"]), argv(&["python3", "x.py"]));
+        assert_eq!(
+            normalize(&normalizer, &["env", "-i", "-u", "HOME", "PATH=/tmp", "--", "rm", "x"]),
+            argv(&["rm", "x"])
+        );
+        assert_eq!(normalize(&normalizer, &["env", "-S", "rm -rf", "/"]), argv(&["rm", "-rf", "/"]));
+        assert_eq!(normalize(&normalizer, &["timeout", "-s", "KILL", "10", "dd"]), argv(&["dd"]));
+        assert_eq!(
+            normalize(&normalizer, &["nice", "-n", "5", "toybox", "nohup", "ll", "/etc"]),
+            argv(&["ls", "-l", "/etc"])
+        );
+        assert_eq!(normalize(&normalizer, &["stdbuf", "-oL", "xargs", "-n", "1", "rm"]), argv(&["rm"]));
+        // Option clusters take the value of their last option
+        assert_eq!(normalize(&normalizer, &["env", "-iu", "ls", "rm", "-rf"]), argv(&["rm", "-rf"]));
+        assert_eq!(normalize(&normalizer, &["env", "--split-string=rm -rf", "/"]), argv(&["rm", "-rf", "/"]));
+
+        // Commands without indirection and wrappers without a command stay as they are
+        assert_eq!(normalize(&normalizer, &["ls", "-l"]), None);
+        assert_eq!(normalize(&normalizer, &["env"]), None);
+        assert_eq!(normalize(&normalizer, &["busybox", "--list"]), None);
+        assert_eq!(normalize(&normalizer, &["env", "-i", "FOO=1"]), None);
+
+        let (_, via) = normalizer.normalize("env", &["busybox".to_string(), "rm".to_string()]).unwrap().unwrap();
+        assert_eq!(via, vec!["env", "busybox"]);
+
+        // Alias loops are cut off
+        let looping = CommandNormalizer::new().with_alias("a", "b").unwrap().with_alias("b", "a").unwrap();
+        assert!(looping.normalize("a", &[]).is_err());
+        assert!(normalizer.normalize("env", &["-S".to_string(), "ls; rm x".to_string()]).is_err());
+
+        let effective_name = |line: &[&str]| {
+            let args: Vec<String> = line[1..].iter().map(|arg| arg.to_string()).collect();
+            normalizer.effective_name(line[0], &args).unwrap()
+        };
+        assert_eq!(effective_name(&["/usr/bin/npm", "ci"]), "npm");
+        assert_eq!(effective_name(&["env", "/usr/local/bin/npm", "ci"]), "npm");
+        assert_eq!(effective_name(&["ll"]), "ls");
+    }
+
+    // Test for rewriting a policy input
+    #[test]
+    fn test_apply() {
+        let mut input = PolicyInput {
+            user: Default::default(),
+            command: Default::default(),
+            file: None,
+            network: None,
+            resources: Default::default(),
+            context: HashMap::new(),
+        };
+        input.command.name = "busybox".to_string();
+        input.command.args = vec!["rm".to_string(), "-rf".to_string()];
+
+        assert!(CommandNormalizer::new().apply(&mut input).unwrap());
+        assert_eq!(input.command.name, "rm");
+        assert_eq!(input.command.args, vec!["-rf"]);
+        assert_eq!(
+            input.context[CONTEXT_ORIGINAL_COMMAND],
+            json!({ "name": "busybox", "args": ["rm", "-rf"], "via": ["busybox"] })
+        );
+        assert!(!CommandNormalizer::new().apply(&mut input).unwrap());
+    }
+}
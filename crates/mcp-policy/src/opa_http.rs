@@ -0,0 +1,317 @@
+//! Remote OPA evaluator
+//!
+//! Sends the policy input to an OPA server through its Data API
+//! (`POST /v1/data/<path>` with `{"input": ...}`) and converts the returned document with
+//! the same rules as the embedded Rego evaluator. Connections are kept alive and reused
+//! between evaluations, and requests run on the blocking thread pool so that they do not
+//! stall the async runtime. When OPA cannot be reached the configured failure mode decides
+//! whether requests are denied (fail-closed, the default) or allowed with a warning
+//! (fail-open).
+
+use crate::engine::{parse_opa_result, AsyncPolicyEvaluator};
+use crate::models::{PolicyDecision, PolicyInput};
+use async_trait::async_trait;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{get_env_var_or, parse_positive};
+use serde_json::json;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Metadata key set on decisions produced by the fail-open fallback
+pub const METADATA_OPA_FALLBACK: &str = "opa_fallback";
+
+/// Behavior when the OPA server cannot be reached or returns an invalid response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FailureMode {
+    /// Deny the request
+    #[default]
+    Closed,
+    /// Allow the request with a warning
+    Open,
+}
+
+impl FromStr for FailureMode {
+    type Err = McpError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "closed" | "fail-closed" => Ok(Self::Closed),
+            "open" | "fail-open" => Ok(Self::Open),
+            _ => Err(McpError::InvalidRequest(format!(
+                "Invalid OPA failure mode '{}', expected 'open' or 'closed'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Settings of the remote OPA evaluator
+#[derive(Debug, Clone)]
+pub struct OpaHttpConfig {
+    /// Base URL of the OPA server (e.g. `http://opa:8181`)
+    pub url: String,
+    /// Path of the decision document below `/v1/data` (e.g. `mcp`)
+    pub decision_path: String,
+    /// Timeout for establishing a connection
+    pub connect_timeout: Duration,
+    /// Timeout of a whole evaluation request
+    pub timeout: Duration,
+    /// Maximum number of idle connections kept per host
+    pub max_idle_connections: usize,
+    /// Bearer token sent to OPA
+    pub auth_token: Option<String>,
+    /// Behavior when OPA is unavailable
+    pub failure_mode: FailureMode,
+}
+
+impl OpaHttpConfig {
+    /// Create settings for an OPA server with default values
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            decision_path: "mcp".to_string(),
+            connect_timeout: Duration::from_millis(500),
+            timeout: Duration::from_secs(2),
+            max_idle_connections: 16,
+            auth_token: None,
+            failure_mode: FailureMode::Closed,
+        }
+    }
+
+    /// Build the settings from environment variables (`None` when no URL is configured)
+    ///
+    /// * `MCP_OPA_URL` - base URL of the OPA server
+    /// * `MCP_OPA_DECISION_PATH` - decision path below `/v1/data` (default `mcp`)
+    /// * `MCP_OPA_TIMEOUT_MS` - request timeout in milliseconds
+    /// * `MCP_OPA_CONNECT_TIMEOUT_MS` - connect timeout in milliseconds
+    /// * `MCP_OPA_MAX_IDLE_CONNECTIONS` - size of the connection pool
+    /// * `MCP_OPA_TOKEN` - bearer token
+    /// * `MCP_OPA_FAILURE_MODE` - `closed` (default) or `open`
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let url = match std::env::var("MCP_OPA_URL") {
+            Ok(url) if !url.trim().is_empty() => url,
+            _ => return Ok(None),
+        };
+
+        let mut config = Self::new(url.trim());
+        config.decision_path = get_env_var_or("MCP_OPA_DECISION_PATH", "mcp")
+            .trim_matches('/')
+            .to_string();
+        let number = |name: &str, default: &str| parse_positive(name, &get_env_var_or(name, default));
+        config.timeout = Duration::from_millis(number("MCP_OPA_TIMEOUT_MS", "2000")?);
+        config.connect_timeout = Duration::from_millis(number("MCP_OPA_CONNECT_TIMEOUT_MS", "500")?);
+        config.max_idle_connections = number("MCP_OPA_MAX_IDLE_CONNECTIONS", "16")? as usize;
+        config.auth_token = std::env::var("MCP_OPA_TOKEN").ok();
+        config.failure_mode = get_env_var_or("MCP_OPA_FAILURE_MODE", "closed").parse()?;
+
+        Ok(Some(config))
+    }
+
+    /// Data API endpoint of the decision document
+    pub fn endpoint(&self) -> String {
+        format!("{}/v1/data/{}", self.url, self.decision_path.trim_matches('/'))
+    }
+}
+
+/// Policy evaluator that queries a remote OPA server
+pub struct OpaHttpEvaluator {
+    // ureq agents pool and reuse connections
+    agent: ureq::Agent,
+    endpoint: String,
+    config: OpaHttpConfig,
+}
+
+impl OpaHttpEvaluator {
+    /// Create an evaluator for the configured OPA server
+    pub fn new(config: OpaHttpConfig) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(config.connect_timeout)
+            .timeout(config.timeout)
+            .max_idle_connections_per_host(config.max_idle_connections)
+            .max_idle_connections(config.max_idle_connections)
+            .build();
+
+        Self {
+            agent,
+            endpoint: config.endpoint(),
+            config,
+        }
+    }
+
+    /// Settings of this evaluator
+    pub fn config(&self) -> &OpaHttpConfig {
+        &self.config
+    }
+
+    async fn query(&self, input: &PolicyInput) -> McpResult<serde_json::Value> {
+        let agent = self.agent.clone();
+        let endpoint = self.endpoint.clone();
+        let auth_token = self.config.auth_token.clone();
+        let body = json!({ "input": input });
+
+        tokio::task::spawn_blocking(move || query(&agent, &endpoint, auth_token.as_deref(), body))
+            .await
+            .map_err(|e| McpError::Internal(format!("OPA request task failed: {}", e)))?
+    }
+}
+
+fn query(
+    agent: &ureq::Agent,
+    endpoint: &str,
+    auth_token: Option<&str>,
+    body: serde_json::Value,
+) -> McpResult<serde_json::Value> {
+    let mut request = agent.post(endpoint);
+    if let Some(token) = auth_token {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+
+    let response = match request.send_json(body) {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, _)) => {
+            return Err(McpError::ExternalService(format!("OPA returned HTTP {}", code)))
+        }
+        Err(e) => return Err(McpError::ExternalService(format!("OPA request failed: {}", e))),
+    };
+
+    let body: serde_json::Value = response
+        .into_json()
+        .map_err(|e| McpError::ExternalService(format!("Invalid OPA response: {}", e)))?;
+
+    // An undefined document has no "result" and is treated as a denial
+    Ok(body.get("result").cloned().unwrap_or(serde_json::Value::Null))
+}
+
+#[async_trait]
+impl AsyncPolicyEvaluator for OpaHttpEvaluator {
+    async fn evaluate(&self, input: &PolicyInput) -> McpResult<PolicyDecision> {
+        match self.query(input).await {
+            Ok(document) => {
+                debug!("OPA decision document: {}", document);
+                parse_opa_result(document)
+            }
+            Err(e) => match self.config.failure_mode {
+                FailureMode::Closed => Err(e),
+                FailureMode::Open => {
+                    warn!("OPA is unavailable, allowing the request (fail-open): {}", e);
+                    let mut decision = PolicyDecision {
+                        allow: true,
+                        warnings: vec![format!("Policy was not evaluated: {}", e)],
+                        reasons: Vec::new(),
+                        deny_reasons: vec![],
+                        metadata: std::collections::HashMap::new(),
+                    };
+                    decision
+                        .metadata
+                        .insert(METADATA_OPA_FALLBACK.to_string(), serde_json::Value::Bool(true));
+                    Ok(decision)
+                }
+            },
+        }
+    }
+
+    fn name(&self) -> &str {
+        "opa-http"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::input;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    /// Minimal OPA stand-in: allows "ls" and reports each request line and body
+    fn start_opa() -> (String, mpsc::Receiver<(String, serde_json::Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+                let allow = body["input"]["command"]["name"] == "ls";
+                let response = json!({"result": {"allow": allow, "deny_reasons": if allow { vec![] } else { vec!["not allowed"] }}}).to_string();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.len(),
+                    response
+                )
+                .unwrap();
+                let _ = tx.send((request_line.trim().to_string(), body));
+            }
+        });
+
+        (url, rx)
+    }
+
+    fn unreachable_url() -> String {
+        // Bind and drop a listener to get a port nothing listens on
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    // Test for evaluating through the Data API
+    #[tokio::test]
+    async fn test_remote_evaluation() {
+        let (url, requests) = start_opa();
+        let mut config = OpaHttpConfig::new(&format!("{}/", url));
+        config.decision_path = "mcp/authz".to_string();
+        let evaluator = OpaHttpEvaluator::new(config);
+
+        let decision = evaluator.evaluate(&input("ls")).await.unwrap();
+        assert!(decision.allow);
+        let (request_line, body) = requests.recv().unwrap();
+        assert_eq!(request_line, "POST /v1/data/mcp/authz HTTP/1.1");
+        assert_eq!(body["input"]["command"]["name"], "ls");
+
+        let decision = evaluator.evaluate(&input("rm")).await.unwrap();
+        assert!(!decision.allow);
+        assert_eq!(decision.reasons, vec!["not allowed".to_string()]);
+    }
+
+    // Test for fail-closed and fail-open behavior
+    #[tokio::test]
+    async fn test_failure_modes() {
+        let url = unreachable_url();
+
+        let evaluator = OpaHttpEvaluator::new(OpaHttpConfig::new(&url));
+        assert!(matches!(evaluator.evaluate(&input("ls")).await, Err(McpError::ExternalService(_))));
+
+        let mut config = OpaHttpConfig::new(&url);
+        config.failure_mode = FailureMode::Open;
+        let decision = OpaHttpEvaluator::new(config).evaluate(&input("ls")).await.unwrap();
+        assert!(decision.allow);
+        assert_eq!(decision.warnings.len(), 1);
+        assert_eq!(decision.metadata.get(METADATA_OPA_FALLBACK), Some(&serde_json::Value::Bool(true)));
+
+        assert_eq!("open".parse::<FailureMode>().unwrap(), FailureMode::Open);
+        assert_eq!("Fail-Closed".parse::<FailureMode>().unwrap(), FailureMode::Closed);
+        assert!("maybe".parse::<FailureMode>().is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! File path patterns of the rule-based evaluator
+//!
+//! A pattern is one of
+//!
+//! * a directory prefix such as `/workspace/` or `/workspace`, matching the directory
+//!   itself and everything below it (but not `/workspace2`),
+//! * a glob such as `/workspace/**/*.py`, where `*` and `?` do not cross `/` and `**`
+//!   matches any number of directories,
+//! * an anchored regex prefixed with `re:`, such as `re:/home/[a-z]+/\.ssh/.*`.
+//!
+//! Paths are normalized lexically before matching (see [`normalize_path`]) so that
+//! `/workspace/../etc/passwd` cannot pass as a path below `/workspace`.
+
+use globset::{GlobBuilder, GlobMatcher};
+use mcp_common::error::{McpError, McpResult};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Prefix of regex patterns
+pub const REGEX_PREFIX: &str = "re:";
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Prefix(String),
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+/// Compiled file path pattern
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PathPattern {
+    source: String,
+    matcher: Matcher,
+}
+
+impl PathPattern {
+    /// Compile a pattern
+    pub fn parse(pattern: &str) -> McpResult<Self> {
+        let invalid = |e: String| McpError::InvalidRequest(format!("Invalid path pattern '{}': {}", pattern, e));
+
+        let matcher = if let Some(regex) = pattern.strip_prefix(REGEX_PREFIX) {
+            Matcher::Regex(Regex::new(&format!("^(?:{})$", regex)).map_err(|e| invalid(e.to_string()))?)
+        } else if pattern.contains(['*', '?', '[', '{']) {
+            let glob = GlobBuilder::new(&normalize_path(pattern))
+                .literal_separator(true)
+                .build()
+                .map_err(|e| invalid(e.to_string()))?;
+            Matcher::Glob(glob.compile_matcher())
+        } else if pattern.is_empty() {
+            return Err(invalid("empty pattern".to_string()));
+        } else {
+            Matcher::Prefix(normalize_path(pattern))
+        };
+
+        Ok(Self {
+            source: pattern.to_string(),
+            matcher,
+        })
+    }
+
+    /// Pattern as written in the configuration
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether a path matches the pattern
+    ///
+    /// The path is normalized first.
+    pub fn matches(&self, path: &str) -> bool {
+        self.matches_normalized(&normalize_path(path))
+    }
+
+    /// Whether an already normalized path matches the pattern
+    pub fn matches_normalized(&self, path: &str) -> bool {
+        match &self.matcher {
+            Matcher::Prefix(prefix) if prefix == "/" => path.starts_with('/'),
+            Matcher::Prefix(prefix) => {
+                path == prefix || path.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.starts_with('/'))
+            }
+            Matcher::Glob(glob) => glob.is_match(path),
+            Matcher::Regex(regex) => regex.is_match(path),
+        }
+    }
+}
+
+impl PartialEq for PathPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl TryFrom<String> for PathPattern {
+    type Error = McpError;
+
+    fn try_from(pattern: String) -> McpResult<Self> {
+        Self::parse(&pattern)
+    }
+}
+
+impl From<PathPattern> for String {
+    fn from(pattern: PathPattern) -> Self {
+        pattern.source
+    }
+}
+
+/// Normalize a path lexically
+///
+/// Collapses repeated separators, removes `.` segments and trailing slashes, and resolves
+/// `..` against the preceding segment (`..` never climbs above the root of an absolute
+/// path). The file system is not consulted, so symlinks are not resolved.
+pub fn normalize_path(path: &str) -> String {
+    let absolute = path.starts_with('/');
+    let mut segments: Vec<&str> = Vec::new();
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => match segments.last() {
+                Some(last) if *last != ".." => {
+                    segments.pop();
+                }
+                _ if absolute => {}
+                _ => segments.push(".."),
+            },
+            segment => segments.push(segment),
+        }
+    }
+
+    let joined = segments.join("/");
+    if absolute {
+        format!("/{}", joined)
+    } else if joined.is_empty() {
+        ".".to_string()
+    } else {
+        joined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        PathPattern::parse(pattern).unwrap().matches(path)
+    }
+
+    // Test for lexical normalization
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/workspace//src/./main.rs"), "/workspace/src/main.rs");
+        assert_eq!(normalize_path("/workspace/../etc/passwd"), "/etc/passwd");
+        assert_eq!(normalize_path("/../../etc/"), "/etc");
+        assert_eq!(normalize_path("/"), "/");
+        assert_eq!(normalize_path("a/../../b"), "../b");
+        assert_eq!(normalize_path("./"), ".");
+    }
+
+    // Test for directory prefixes with and without trailing slashes
+    #[test]
+    fn test_prefix_patterns() {
+        for pattern in ["/workspace", "/workspace/"] {
+            assert!(matches(pattern, "/workspace"));
+            assert!(matches(pattern, "/workspace/"));
+            assert!(matches(pattern, "/workspace/src/main.rs"));
+            assert!(!matches(pattern, "/workspace2/file"));
+            assert!(!matches(pattern, "/workspace/../etc/passwd"));
+        }
+        assert!(matches("/", "/etc/passwd"));
+    }
+
+    // Test for glob patterns
+    #[test]
+    fn test_glob_patterns() {
+        assert!(matches("/workspace/**/*.py", "/workspace/main.py"));
+        assert!(matches("/workspace/**/*.py", "/workspace/src/pkg/main.py"));
+        assert!(!matches("/workspace/**/*.py", "/workspace/src/main.rs"));
+        assert!(matches("/workspace/*.py", "/workspace/main.py"));
+        assert!(!matches("/workspace/*.py", "/workspace/src/main.py"));
+        assert!(matches("/home/*/.ssh/**", "/home/user/.ssh/id_rsa"));
+        assert!(matches("/tmp/*/", "/tmp/dir"));
+    }
+
+    // Test for anchored regex patterns
+    #[test]
+    fn test_regex_patterns() {
+        assert!(matches(r"re:/data/[0-9]+\.csv", "/data/42.csv"));
+        assert!(!matches(r"re:/data/[0-9]+\.csv", "/data/42.csv.bak"));
+        assert!(!matches(r"re:/data/[0-9]+\.csv", "/old/data/42.csv"));
+        assert!(PathPattern::parse("re:(").is_err());
+        assert!(PathPattern::parse("").is_err());
+    }
+}
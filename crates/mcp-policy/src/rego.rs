@@ -0,0 +1,436 @@
+//! Rego policy evaluator
+//!
+//! Loads `.rego` modules (and optional `data.json` documents, as found in an OPA bundle
+//! directory) from disk and evaluates them with the regorus interpreter.
+//!
+//! Explanations report, for every package below the query, the `allow` rule if it holds
+//! and the entries of `deny_reasons`, `reasons` and `warnings` that made it into the
+//! decision, identified by their rule path (e.g. `data.mcp.command.deny_reasons`) and the
+//! module that declares the package. Messages aggregated by a parent package are
+//! attributed to the most specific package that produces them.
+
+use crate::engine::{self, parse_opa_result, PolicyEvaluator};
+use crate::models::{PolicyDecision, PolicyExplanation, PolicyInput, RuleEffect, RuleMatch};
+use mcp_common::error::{McpError, McpResult};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// Default query whose result document holds `allow`, `deny_reasons`, `warnings` and `metadata`
+pub const DEFAULT_QUERY: &str = "data.mcp";
+
+/// Policy evaluator backed by Rego files
+#[derive(Clone)]
+pub struct RegoEvaluator {
+    // Engine with all modules loaded; cloned for each evaluation
+    engine: regorus::Engine,
+    query: String,
+    origin: String,
+    // Packages and the module that declares them
+    packages: Vec<(String, String)>,
+}
+
+impl RegoEvaluator {
+    /// Load all policy files below a directory
+    ///
+    /// Files ending in `.rego` are added as policy modules and files named `data.json`
+    /// are merged into the base data document. Directories are searched recursively.
+    pub fn from_dir(dir: impl AsRef<Path>) -> McpResult<Self> {
+        let dir = dir.as_ref();
+        let mut modules = Vec::new();
+        let mut data = Vec::new();
+
+        for path in engine::policy_files(dir, "rego", "data.json")? {
+            let content = read_file(&path)?;
+            if path.extension().is_some_and(|ext| ext == "rego") {
+                modules.push((path.to_string_lossy().into_owned(), content));
+            } else {
+                data.push((path.to_string_lossy().into_owned(), content));
+            }
+        }
+
+        Self::from_sources(&dir.display().to_string(), modules, data)
+    }
+
+    /// Compile policy modules and data documents held in memory
+    ///
+    /// `modules` and `data` are `(name, content)` pairs; names are only used in messages.
+    pub fn from_sources(
+        origin: &str,
+        modules: Vec<(String, String)>,
+        data: Vec<(String, String)>,
+    ) -> McpResult<Self> {
+        if modules.is_empty() {
+            return Err(McpError::Internal(format!("No .rego policy files found in {}", origin)));
+        }
+
+        let mut engine = regorus::Engine::new();
+        let module_count = modules.len();
+
+        let mut packages: Vec<(String, String)> = Vec::new();
+        for (name, source) in &modules {
+            if let Some(package) = package_name(source) {
+                if !packages.iter().any(|(existing, _)| *existing == package) {
+                    packages.push((package, name.clone()));
+                }
+            }
+        }
+
+        for (name, source) in modules {
+            engine.add_policy(name.clone(), source).map_err(|e| {
+                McpError::Internal(format!("Failed to compile policy {}: {}", name, e))
+            })?;
+        }
+
+        for (name, content) in data {
+            let value = regorus::Value::from_json_str(&content).map_err(|e| {
+                McpError::Internal(format!("Failed to parse policy data {}: {}", name, e))
+            })?;
+            engine.add_data(value).map_err(|e| {
+                McpError::Internal(format!("Failed to load policy data {}: {}", name, e))
+            })?;
+        }
+
+        info!("Loaded {} Rego policy modules from {}", module_count, origin);
+
+        Ok(Self {
+            engine,
+            query: DEFAULT_QUERY.to_string(),
+            origin: origin.to_string(),
+            packages,
+        })
+    }
+
+    /// Change the query used to obtain the decision document
+    pub fn with_query(mut self, query: &str) -> Self {
+        self.query = query.to_string();
+        self
+    }
+
+    /// Copy of the evaluator with the data document at `path` replaced
+    ///
+    /// `path` is a dot separated path below `data` (e.g. `organization.allowed_hosts` for
+    /// `data.organization.allowed_hosts`); missing parent objects are created. An empty path
+    /// replaces the whole base document, which must then be an object.
+    pub fn with_data(&self, path: &str, document: serde_json::Value) -> McpResult<Self> {
+        let mut data = serde_json::to_value(self.engine.get_data())
+            .map_err(|e| McpError::Internal(format!("Failed to read policy data: {}", e)))?;
+
+        let mut target = &mut data;
+        if !path.is_empty() {
+            for segment in path.split('.') {
+                if segment.is_empty() {
+                    return Err(McpError::InvalidRequest(format!("Invalid policy data path '{}'", path)));
+                }
+                if !target.is_object() {
+                    *target = serde_json::Value::Object(Default::default());
+                }
+                target = target
+                    .as_object_mut()
+                    .expect("target is an object")
+                    .entry(segment)
+                    .or_insert(serde_json::Value::Null);
+            }
+        } else if !document.is_object() {
+            return Err(McpError::InvalidRequest("The base policy data document must be an object".to_string()));
+        }
+        *target = document;
+
+        let value = regorus::Value::from_json_str(&data.to_string())
+            .map_err(|e| McpError::Internal(format!("Failed to convert policy data: {}", e)))?;
+        let mut evaluator = self.clone();
+        evaluator.engine.clear_data();
+        evaluator
+            .engine
+            .add_data(value)
+            .map_err(|e| McpError::Internal(format!("Failed to load policy data: {}", e)))?;
+
+        info!("Replaced policy data document data.{} of {}", path, self.origin);
+        Ok(evaluator)
+    }
+
+    /// Where the policies were loaded from (directory or bundle URL)
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    // Engine with the input set
+    fn engine_for(&self, input: &PolicyInput) -> McpResult<regorus::Engine> {
+        let input_json = serde_json::to_string(input)
+            .map_err(|e| McpError::Internal(format!("Failed to serialize input: {}", e)))?;
+        let input_value = regorus::Value::from_json_str(&input_json)
+            .map_err(|e| McpError::Internal(format!("Failed to convert input: {}", e)))?;
+
+        let mut engine = self.engine.clone();
+        engine.set_input(input_value);
+        Ok(engine)
+    }
+
+    fn decide(&self, engine: &mut regorus::Engine) -> McpResult<PolicyDecision> {
+        // An undefined result is treated as a denial by parse_opa_result
+        let document = query_document(engine, &self.query)?;
+        debug!("Rego decision document: {}", document);
+
+        parse_opa_result(document)
+    }
+}
+
+impl PolicyEvaluator for RegoEvaluator {
+    fn evaluate(&self, input: &PolicyInput) -> McpResult<PolicyDecision> {
+        self.decide(&mut self.engine_for(input)?)
+    }
+
+    fn name(&self) -> &str {
+        "rego"
+    }
+
+    fn with_data_document(
+        &self,
+        path: &str,
+        document: serde_json::Value,
+    ) -> McpResult<Arc<dyn crate::engine::AsyncPolicyEvaluator>> {
+        Ok(Arc::new(self.with_data(path, document)?))
+    }
+
+    fn explain(&self, input: &PolicyInput) -> McpResult<PolicyExplanation> {
+        let mut engine = self.engine_for(input)?;
+        let decision = self.decide(&mut engine)?;
+
+        let query_prefix = format!("{}.", self.query);
+        let mut documents = Vec::new();
+        for (package, source) in &self.packages {
+            let path = format!("data.{}", package);
+            if path == self.query || path.starts_with(&query_prefix) {
+                let document = query_document(&mut engine, &path)?;
+                documents.push((path, source, document));
+            }
+        }
+        // Outer packages first, as they are entered during evaluation
+        documents.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+
+        // Messages of a package, most specific packages (longest paths) win
+        let owner = |key: &str, message: &str| {
+            documents
+                .iter()
+                .filter(|(_, _, document)| strings(&document[key]).contains(&message))
+                .map(|(path, _, _)| path.as_str())
+                .max_by_key(|path| path.len())
+        };
+
+        let mut rules = Vec::new();
+        for (path, source, document) in &documents {
+            let mut matched = |rule: &str, effect: RuleEffect, message: &str| {
+                rules.push(RuleMatch {
+                    rule: format!("{}.{}", path, rule),
+                    source: source.to_string(),
+                    effect,
+                    message: message.to_string(),
+                });
+            };
+
+            if decision.allow && document["allow"] == serde_json::Value::Bool(true) {
+                matched("allow", RuleEffect::Allow, "");
+            }
+            for key in ["deny_reasons", "reasons"] {
+                for message in strings(&document[key]) {
+                    if decision.reasons.iter().any(|reason| reason == message)
+                        && owner(key, message) == Some(path.as_str())
+                    {
+                        matched(key, RuleEffect::Deny, message);
+                    }
+                }
+            }
+            for message in strings(&document["warnings"]) {
+                if decision.warnings.iter().any(|warning| warning == message)
+                    && owner("warnings", message) == Some(path.as_str())
+                {
+                    matched("warnings", RuleEffect::Warn, message);
+                }
+            }
+        }
+
+        Ok(PolicyExplanation {
+            evaluator: PolicyEvaluator::name(self).to_string(),
+            decision,
+            rules,
+        })
+    }
+}
+
+// Evaluate a query to a JSON document (null if undefined)
+fn query_document(engine: &mut regorus::Engine, query: &str) -> McpResult<serde_json::Value> {
+    let results = engine
+        .eval_query(query.to_string(), false)
+        .map_err(|e| McpError::Internal(format!("Rego evaluation failed: {}", e)))?;
+
+    match results.result.first().and_then(|r| r.expressions.first()) {
+        Some(expression) => serde_json::to_value(&expression.value)
+            .map_err(|e| McpError::Internal(format!("Failed to convert Rego result: {}", e))),
+        None => Ok(serde_json::Value::Null),
+    }
+}
+
+// String entries of an array or set document
+fn strings(value: &serde_json::Value) -> Vec<&str> {
+    value
+        .as_array()
+        .map(|values| values.iter().filter_map(|value| value.as_str()).collect())
+        .unwrap_or_default()
+}
+
+// Package declared by a module
+fn package_name(source: &str) -> Option<String> {
+    source.lines().find_map(|line| {
+        let package = line.trim().strip_prefix("package ")?;
+        Some(package.split_whitespace().next()?.to_string())
+    })
+}
+
+fn read_file(path: &Path) -> McpResult<String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| McpError::Internal(format!("Failed to read {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CommandInfo, FileInfo, UserInfo};
+    use std::collections::HashMap;
+
+    fn repo_policies() -> RegoEvaluator {
+        RegoEvaluator::from_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../../policies/rego")).unwrap()
+    }
+
+    fn command_input(name: &str) -> PolicyInput {
+        PolicyInput {
+            user: UserInfo {
+                id: "user1".to_string(),
+                tenant_id: "tenant1".to_string(),
+                roles: vec!["user".to_string()],
+                attributes: HashMap::new(),
+            },
+            command: CommandInfo {
+                name: name.to_string(),
+                ..Default::default()
+            },
+            file: None,
+            network: None,
+            resources: Default::default(),
+            context: HashMap::new(),
+        }
+    }
+
+    // Test for evaluating the policies shipped with the repository
+    #[test]
+    fn test_repo_command_policies() {
+        let evaluator = repo_policies();
+
+        let decision = evaluator.evaluate(&command_input("ls")).unwrap();
+        assert!(decision.allow);
+        assert!(decision.is_cacheable());
+
+        let decision = evaluator.evaluate(&command_input("python")).unwrap();
+        assert!(decision.allow);
+        assert!(!decision.is_cacheable());
+
+        // find can write files (-fprint), so its results are not cached
+        let decision = evaluator.evaluate(&command_input("find")).unwrap();
+        assert!(decision.allow);
+        assert!(!decision.is_cacheable());
+
+        let decision = evaluator.evaluate(&command_input("rm")).unwrap();
+        assert!(!decision.allow);
+        assert!(!decision.reasons.is_empty());
+    }
+
+    // Test for file policies
+    #[test]
+    fn test_repo_file_policies() {
+        let evaluator = repo_policies();
+
+        let mut input = command_input("");
+        input.file = Some(FileInfo {
+            path: "/workspace/data.txt".to_string(),
+            mode: "read".to_string(),
+        });
+        assert!(evaluator.evaluate(&input).unwrap().allow);
+
+        input.file = Some(FileInfo {
+            path: "/etc/passwd".to_string(),
+            mode: "read".to_string(),
+        });
+        assert!(!evaluator.evaluate(&input).unwrap().allow);
+    }
+
+    // Test for bundle data and load errors
+    #[test]
+    fn test_from_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::create_dir(dir.join("data")).unwrap();
+
+        // No policies
+        assert!(RegoEvaluator::from_dir(dir).is_err());
+
+        std::fs::write(
+            dir.join("policy.rego"),
+            "package mcp\nimport future.keywords.if\nimport future.keywords.in\ndefault allow = false\nallow if { input.command.name in data.allowed }\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("data").join("data.json"), r#"{"allowed": ["make"]}"#).unwrap();
+
+        let evaluator = RegoEvaluator::from_dir(dir).unwrap();
+        assert!(evaluator.evaluate(&command_input("make")).unwrap().allow);
+        assert!(!evaluator.evaluate(&command_input("ls")).unwrap().allow);
+
+        // Syntax errors are reported at load time
+        std::fs::write(dir.join("broken.rego"), "package mcp\nallow if {").unwrap();
+        assert!(RegoEvaluator::from_dir(dir).is_err());
+    }
+
+    // Test for replacing data documents at runtime
+    #[test]
+    fn test_with_data() {
+        let evaluator = repo_policies();
+        let mut input = command_input("");
+        input.network = Some(crate::models::NetworkInfo {
+            host: "git.example.com".to_string(),
+            port: 443,
+            protocol: "https".to_string(),
+        });
+        assert!(!evaluator.evaluate(&input).unwrap().allow);
+
+        let updated = evaluator
+            .with_data("organization.allowed_hosts", serde_json::json!(["git.example.com"]))
+            .unwrap();
+        assert!(updated.evaluate(&input).unwrap().allow);
+        // The original evaluator is unchanged and other documents are kept
+        assert!(!evaluator.evaluate(&input).unwrap().allow);
+        assert!(updated.evaluate(&command_input("ls")).unwrap().allow);
+
+        assert!(evaluator.with_data("organization..hosts", serde_json::json!([])).is_err());
+        assert!(evaluator.with_data("", serde_json::json!([])).is_err());
+    }
+
+    // Test for explaining decisions with the matched rules
+    #[test]
+    fn test_explain() {
+        let evaluator = repo_policies();
+
+        let explanation = evaluator.explain(&command_input("rm")).unwrap();
+        assert!(!explanation.decision.allow);
+        assert_eq!(explanation.rules.len(), explanation.decision.reasons.len());
+        for rule in &explanation.rules {
+            // Reasons are attributed to the command package, not to the aggregating main package
+            assert_eq!(rule.rule, "data.mcp.command.deny_reasons");
+            assert!(rule.source.ends_with("command.rego"));
+            assert_eq!(rule.effect, RuleEffect::Deny);
+        }
+
+        let explanation = evaluator.explain(&command_input("ls")).unwrap();
+        assert!(explanation.decision.allow);
+        let rules: Vec<_> = explanation.rules.iter().map(|rule| rule.rule.as_str()).collect();
+        assert_eq!(rules, vec!["data.mcp.allow", "data.mcp.command.allow"]);
+        assert!(explanation.rules[0].source.ends_with("main.rego"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Resource limit policy
+//!
+//! Compares the resource limits requested in `PolicyInput.resources` with configured
+//! maximums. A limit that is not requested falls back to the sandbox default and always
+//! passes; a maximum that is not configured is unbounded. The check is independent of
+//! the active evaluator.
+
+use crate::engine::policy_violation;
+use crate::models::{PolicyInput, ResourceLimits};
+use mcp_common::error::{error_code, McpError, McpResult};
+use serde_json::json;
+use std::fmt::Display;
+use std::str::FromStr;
+use tracing::error;
+
+/// Maximum resource limits a request may ask for
+#[derive(Debug, Clone, Default)]
+pub struct ResourceLimitPolicy {
+    maximums: ResourceLimits,
+}
+
+impl ResourceLimitPolicy {
+    /// Create a policy with the given maximums
+    pub fn new(maximums: ResourceLimits) -> Self {
+        Self { maximums }
+    }
+
+    /// Build the policy from environment variables
+    ///
+    /// * `MCP_POLICY_MAX_CPU_TIME_MS` - CPU time (milliseconds)
+    /// * `MCP_POLICY_MAX_CPU_CORES` - CPU cores
+    /// * `MCP_POLICY_MAX_MEMORY_KB` - memory (kilobytes)
+    /// * `MCP_POLICY_MAX_FILES` - number of files
+    /// * `MCP_POLICY_MAX_PROCESSES` - number of processes
+    pub fn from_env() -> McpResult<Self> {
+        Ok(Self::new(ResourceLimits {
+            cpu_time_ms: parse_env("MCP_POLICY_MAX_CPU_TIME_MS")?,
+            cpu_cores: parse_env("MCP_POLICY_MAX_CPU_CORES")?,
+            memory_kb: parse_env("MCP_POLICY_MAX_MEMORY_KB")?,
+            max_files: parse_env("MCP_POLICY_MAX_FILES")?,
+            max_processes: parse_env("MCP_POLICY_MAX_PROCESSES")?,
+        }))
+    }
+
+    /// Configured maximums
+    pub fn maximums(&self) -> &ResourceLimits {
+        &self.maximums
+    }
+
+    /// Check the requested limits of an input
+    ///
+    /// Returns a `PolicyViolation` with the `POLICY_RESOURCE_LIMIT_EXCEEDED` code listing
+    /// every limit above its maximum.
+    pub fn check(&self, input: &PolicyInput) -> McpResult<()> {
+        let requested = &input.resources;
+        let maximums = &self.maximums;
+
+        let mut reasons = Vec::new();
+        exceeds(&mut reasons, "cpu_time_ms", requested.cpu_time_ms, maximums.cpu_time_ms);
+        exceeds(&mut reasons, "cpu_cores", requested.cpu_cores, maximums.cpu_cores);
+        exceeds(&mut reasons, "memory_kb", requested.memory_kb, maximums.memory_kb);
+        exceeds(&mut reasons, "max_files", requested.max_files, maximums.max_files);
+        exceeds(&mut reasons, "max_processes", requested.max_processes, maximums.max_processes);
+
+        if reasons.is_empty() {
+            return Ok(());
+        }
+
+        let message = format!(
+            "Requested resource limits for command '{}' exceed the policy maximums: {}",
+            input.command.name,
+            reasons.join(", ")
+        );
+        error!("Policy violation: {}", message);
+
+        let details = json!({
+            "command": input.command.name,
+            "reasons": reasons,
+            "requested": requested,
+            "maximums": maximums,
+            "user_id": input.user.id,
+            "tenant_id": input.user.tenant_id
+        });
+        Err(policy_violation(error_code::POLICY_RESOURCE_LIMIT_EXCEEDED, message, Some(details)))
+    }
+}
+
+fn exceeds<T: PartialOrd + Display + Copy>(
+    reasons: &mut Vec<String>,
+    name: &str,
+    requested: Option<T>,
+    maximum: Option<T>,
+) {
+    if let (Some(requested), Some(maximum)) = (requested, maximum) {
+        if requested > maximum {
+            reasons.push(format!("{} {} exceeds the maximum {}", name, requested, maximum));
+        }
+    }
+}
+
+fn parse_env<T: FromStr>(name: &str) -> McpResult<Option<T>> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| McpError::InvalidRequest(format!("{} must be a number: '{}'", name, value))),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::input;
+
+    fn with_resources(resources: ResourceLimits) -> PolicyInput {
+        PolicyInput { resources, ..input("python") }
+    }
+
+    // Test for comparing requested limits with the maximums
+    #[test]
+    fn test_check_resource_limits() {
+        let policy = ResourceLimitPolicy::new(ResourceLimits {
+            cpu_cores: Some(2.0),
+            memory_kb: Some(1024 * 1024),
+            max_processes: Some(64),
+            ..Default::default()
+        });
+
+        // Unrequested limits and unset maximums pass
+        assert!(policy.check(&with_resources(ResourceLimits::default())).is_ok());
+        assert!(policy
+            .check(&with_resources(ResourceLimits {
+                cpu_cores: Some(2.0),
+                memory_kb: Some(512 * 1024),
+                max_files: Some(100_000),
+                ..Default::default()
+            }))
+            .is_ok());
+
+        let result = policy.check(&with_resources(ResourceLimits {
+            cpu_cores: Some(4.0),
+            memory_kb: Some(512 * 1024),
+            max_processes: Some(1000),
+            ..Default::default()
+        }));
+        match result {
+            Err(error @ McpError::DetailedPolicyViolation { .. }) => {
+                let message = error.policy_violation_message().unwrap();
+                assert!(message.contains("cpu_cores 4 exceeds the maximum 2"));
+                assert!(message.contains("max_processes 1000 exceeds the maximum 64"));
+                assert!(!message.contains("memory_kb"));
+                assert_eq!(error.code(), error_code::POLICY_RESOURCE_LIMIT_EXCEEDED);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // Without maximums everything passes
+        assert!(ResourceLimitPolicy::default()
+            .check(&with_resources(ResourceLimits {
+                memory_kb: Some(u64::MAX),
+                ..Default::default()
+            }))
+            .is_ok());
+    }
+}
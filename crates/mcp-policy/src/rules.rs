@@ -0,0 +1,1290 @@
+//! Rule-based policy evaluator
+//!
+//! Evaluates requests against allow/deny lists loaded from a TOML or YAML file, so that
+//! operators can change the lists without rebuilding:
+//!
+//! ```toml
+//! [commands]
+//! allow = ["ls", "cat"]
+//! deny = ["rm", "sudo"]
+//! cacheable = ["ls", "cat"]
+//! admin_roles = ["admin"]
+//! deny_args = ["--privileged"]
+//!
+//! [commands.args.python]
+//! allow = ["*.py", "-u"]
+//! deny = ["-c"]
+//!
+//! [commands.sha256]
+//! python3 = ["<hex digest>"]
+//!
+//! [commands.groups]
+//! build-tools = ["make", "cargo"]
+//! vcs = ["git"]
+//!
+//! [commands.roles]
+//! developer = ["build-tools", "vcs"]
+//!
+//! [commands.limits."npm install"]
+//! default_timeout_secs = 300
+//! max_timeout_secs = 600
+//! max_memory = "2G"
+//!
+//! [files]
+//! read = ["/workspace/", "/tmp/"]
+//! write = ["/workspace/"]
+//! execute = ["/usr/bin/"]
+//! deny = ["/etc/"]
+//!
+//! [files.deny_content]
+//! private_key = ["/workspace/"]
+//!
+//! [network]
+//! allow_hosts = ["api.example.com"]
+//! deny_hosts = []
+//! ports = [443]
+//! protocols = ["https"]
+//! deny_countries = ["KP"]
+//! deny_asns = [64512]
+//!
+//! [tools]
+//! allow = ["github/*", "filesystem/read_file"]
+//! deny = ["github/delete_*"]
+//! ```
+//!
+//! Omitted sections and lists keep the defaults, which are the lists of the stub
+//! evaluator. File path rules accept directory prefixes, globs and `re:` regexes (see
+//! [`PathPattern`]) and are matched against the normalized path. Deny lists take
+//! precedence over allow lists, and users with an admin role may run any command that is
+//! not denied. Argument rules (see [`ArgPattern`]) apply to every user, administrators
+//! included, and so do digest rules: a command listed in `commands.sha256` runs only if
+//! the SHA-256 digest of its executable, as reported in the `executable` context by
+//! [`ExecutableHashEnricher`](crate::enrich::ExecutableHashEnricher), is one of the listed
+//! digests.
+//!
+//! Besides the commands anyone may run, users may run the commands of the command groups
+//! (`commands.groups`) their roles are mapped to (`commands.roles`). Every tenant with its
+//! own policy directory (see `PolicyEngine::load_tenant_policy_dirs`) maps its roles in its
+//! own rule file.
+//!
+//! Limit rules set the default and maximum timeout, CPU and memory of allowed commands
+//! (see [`CommandLimits`]). A limit key is a command name optionally followed by leading
+//! arguments (`"npm install"`); the key with the most arguments matching the request wins.
+//!
+//! Content rules deny writes whose content scan (see [`crate::content_scan`]) reported the
+//! finding to the listed paths.
+//!
+//! Country and ASN rules use the `geoip` context set by
+//! [`GeoIpEnricher`](crate::geoip::GeoIpEnricher); requests whose host could not be
+//! located pass them.
+//!
+//! Tool rules apply to the tools of upstream MCP servers called through the gateway's
+//! proxy (see `PolicyEngine::check_tool_call`). They are matched against `server/tool`
+//! with the [`ArgPattern`] syntax, and no tool may be called unless it is allowed.
+
+use crate::engine::PolicyEvaluator;
+use crate::content_scan::CONTEXT_CONTENT_SCAN;
+use crate::enrich::CONTEXT_EXECUTABLE;
+use crate::geoip::CONTEXT_GEOIP;
+use crate::models::{
+    reason_code, CommandLimits, DenyReason, FileInfo, NetworkInfo, PolicyDecision, PolicyExplanation, PolicyInput,
+    RuleEffect, RuleMatch, METADATA_CACHEABLE, METADATA_COMMAND_LIMITS,
+};
+use crate::path_pattern::{normalize_path, PathPattern, REGEX_PREFIX};
+use globset::{Glob, GlobMatcher};
+use mcp_common::error::{McpError, McpResult};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Command rules
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandRules {
+    /// Commands anyone may run
+    pub allow: Vec<String>,
+    /// Commands nobody may run
+    pub deny: Vec<String>,
+    /// Read-only commands whose results may be cached
+    pub cacheable: Vec<String>,
+    /// Roles that may run any command that is not denied
+    pub admin_roles: Vec<String>,
+    /// Arguments no command may be given
+    pub deny_args: Vec<ArgPattern>,
+    /// Argument rules per command
+    pub args: HashMap<String, ArgRules>,
+    /// Allowed SHA-256 digests (hex) of the executable per command
+    pub sha256: HashMap<String, Vec<String>>,
+    /// Named command groups (e.g. `build-tools`)
+    pub groups: HashMap<String, Vec<String>>,
+    /// Command groups the users of each role may run
+    pub roles: HashMap<String, Vec<String>>,
+    /// Timeout and resource limits per command (optionally with leading arguments)
+    pub limits: HashMap<String, CommandLimits>,
+}
+
+impl Default for CommandRules {
+    fn default() -> Self {
+        let mut args = HashMap::new();
+        for interpreter in ["python", "python3"] {
+            args.insert(interpreter.to_string(), ArgRules::deny(&["-c"]));
+        }
+        args.insert("node".to_string(), ArgRules::deny(&["-e", "--eval", "-p", "--print"]));
+        // -fprint and the like write files, so find is only read-only without them
+        let find_denied = ["-exec", "-execdir", "-ok", "-okdir", "-delete", "-fprint", "-fprint0", "-fprintf", "-fls"];
+        args.insert("find".to_string(), ArgRules::deny(&find_denied));
+
+        Self {
+            allow: strings(&["ls", "echo", "cat", "grep", "find", "python", "python3", "node", "npm"]),
+            deny: strings(&["rm", "dd", "wget", "curl", "chmod", "chown", "sudo", "su"]),
+            cacheable: strings(&["ls", "cat", "grep"]),
+            admin_roles: strings(&["admin"]),
+            deny_args: arg_patterns(&["--privileged"]),
+            args,
+            sha256: HashMap::new(),
+            groups: HashMap::new(),
+            roles: HashMap::new(),
+            limits: HashMap::new(),
+        }
+    }
+}
+
+/// Argument rules of one command
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArgRules {
+    /// If not empty, every argument must match one of these patterns
+    pub allow: Vec<ArgPattern>,
+    /// Arguments that may not be given
+    pub deny: Vec<ArgPattern>,
+}
+
+impl ArgRules {
+    fn deny(patterns: &[&str]) -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: arg_patterns(patterns),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum ArgMatcher {
+    Exact(String),
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+/// Command argument pattern
+///
+/// An exact argument (`-c`), a glob (`*.py`, where `*` also matches `/`) or an anchored
+/// regex prefixed with `re:`. An exact flag also matches its `--flag=value` form.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ArgPattern {
+    source: String,
+    matcher: ArgMatcher,
+}
+
+impl ArgPattern {
+    /// Compile a pattern
+    pub fn parse(pattern: &str) -> McpResult<Self> {
+        let invalid = |e: String| McpError::InvalidRequest(format!("Invalid argument pattern '{}': {}", pattern, e));
+
+        let matcher = if let Some(regex) = pattern.strip_prefix(REGEX_PREFIX) {
+            ArgMatcher::Regex(Regex::new(&format!("^(?:{})$", regex)).map_err(|e| invalid(e.to_string()))?)
+        } else if pattern.contains(['*', '?', '[', '{']) {
+            let glob = Glob::new(pattern).map_err(|e| invalid(e.to_string()))?;
+            ArgMatcher::Glob(glob.compile_matcher())
+        } else {
+            ArgMatcher::Exact(pattern.to_string())
+        };
+
+        Ok(Self {
+            source: pattern.to_string(),
+            matcher,
+        })
+    }
+
+    /// Pattern as written in the configuration
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether an argument matches the pattern
+    pub fn matches(&self, arg: &str) -> bool {
+        match &self.matcher {
+            ArgMatcher::Exact(exact) => {
+                arg == exact
+                    || (exact.starts_with('-')
+                        && arg.strip_prefix(exact.as_str()).is_some_and(|rest| rest.starts_with('=')))
+            }
+            ArgMatcher::Glob(glob) => glob.is_match(arg),
+            ArgMatcher::Regex(regex) => regex.is_match(arg),
+        }
+    }
+}
+
+impl PartialEq for ArgPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl TryFrom<String> for ArgPattern {
+    type Error = McpError;
+
+    fn try_from(pattern: String) -> McpResult<Self> {
+        Self::parse(&pattern)
+    }
+}
+
+/// File path rules (see [`PathPattern`] for the pattern syntax)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileRules {
+    /// Readable paths
+    pub read: Vec<PathPattern>,
+    /// Writable paths
+    pub write: Vec<PathPattern>,
+    /// Executable paths
+    pub execute: Vec<PathPattern>,
+    /// Paths that may not be accessed in any mode
+    pub deny: Vec<PathPattern>,
+    /// Paths that may not be written with content having a content scan finding, per finding
+    pub deny_content: HashMap<String, Vec<PathPattern>>,
+}
+
+impl Default for FileRules {
+    fn default() -> Self {
+        Self {
+            read: patterns(&["/workspace/", "/tmp/", "/data/public/"]),
+            write: patterns(&["/workspace/", "/tmp/"]),
+            execute: patterns(&["/workspace/bin/", "/usr/bin/", "/bin/"]),
+            deny: patterns(&["/etc/", "/var/", "/root/", "/home/"]),
+            deny_content: HashMap::new(),
+        }
+    }
+}
+
+/// Network rules
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkRules {
+    /// Allowed hosts
+    pub allow_hosts: Vec<String>,
+    /// Hosts that may never be accessed
+    pub deny_hosts: Vec<String>,
+    /// Allowed ports
+    pub ports: Vec<u16>,
+    /// Allowed protocols
+    pub protocols: Vec<String>,
+    /// ISO 3166-1 alpha-2 codes of countries that may never be accessed
+    pub deny_countries: Vec<String>,
+    /// Autonomous system numbers that may never be accessed
+    pub deny_asns: Vec<u32>,
+}
+
+impl Default for NetworkRules {
+    fn default() -> Self {
+        Self {
+            allow_hosts: strings(&["api.example.com", "cdn.example.com", "data.example.com"]),
+            deny_hosts: Vec::new(),
+            ports: vec![80, 443, 8080],
+            protocols: strings(&["tcp", "https"]),
+            deny_countries: Vec::new(),
+            deny_asns: Vec::new(),
+        }
+    }
+}
+
+/// Rules for the tools of upstream MCP servers, matched against `server/tool`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolRules {
+    /// Tools that may be called
+    pub allow: Vec<ArgPattern>,
+    /// Tools that may never be called
+    pub deny: Vec<ArgPattern>,
+}
+
+/// Allow/deny lists of the rule-based evaluator
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleConfig {
+    /// Command rules
+    pub commands: CommandRules,
+    /// File path rules
+    pub files: FileRules,
+    /// Network rules
+    pub network: NetworkRules,
+    /// Rules for the tools of upstream MCP servers
+    pub tools: ToolRules,
+}
+
+impl RuleConfig {
+    /// Load the rules from a `.toml`, `.yaml` or `.yml` file
+    pub fn from_file(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| McpError::Internal(format!("Failed to read rule file {}: {}", path.display(), e)))?;
+
+        let invalid = |e: String| McpError::InvalidRequest(format!("Invalid rule file {}: {}", path.display(), e));
+        let config: Self = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+            _ => {
+                return Err(McpError::InvalidRequest(format!(
+                    "Unsupported rule file {}, expected .toml, .yaml or .yml",
+                    path.display()
+                )))
+            }
+        };
+        config.validate().map_err(invalid)?;
+        Ok(config)
+    }
+
+    // Reject references to undefined command groups
+    fn validate(&self) -> Result<(), String> {
+        let commands = &self.commands;
+        let mut roles: Vec<_> = commands.roles.iter().collect();
+        roles.sort();
+        for (role, groups) in roles {
+            if let Some(group) = groups.iter().find(|group| !commands.groups.contains_key(*group)) {
+                return Err(format!("role '{}' refers to undefined command group '{}'", role, group));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+fn patterns(values: &[&str]) -> Vec<PathPattern> {
+    values
+        .iter()
+        .map(|value| PathPattern::parse(value).expect("default path patterns are valid"))
+        .collect()
+}
+
+fn arg_patterns(values: &[&str]) -> Vec<ArgPattern> {
+    values
+        .iter()
+        .map(|value| ArgPattern::parse(value).expect("default argument patterns are valid"))
+        .collect()
+}
+
+fn contains(list: &[String], value: &str) -> bool {
+    list.iter().any(|item| item == value)
+}
+
+fn first_match<'a>(patterns: &'a [PathPattern], normalized_path: &str) -> Option<&'a PathPattern> {
+    patterns.iter().find(|pattern| pattern.matches_normalized(normalized_path))
+}
+
+fn allowed(warnings: Vec<String>, metadata: HashMap<String, serde_json::Value>) -> PolicyDecision {
+    PolicyDecision {
+        allow: true,
+        warnings,
+        reasons: vec![],
+        deny_reasons: vec![],
+        metadata,
+    }
+}
+
+fn denied(reasons: Vec<DenyReason>) -> PolicyDecision {
+    PolicyDecision {
+        allow: false,
+        warnings: vec![],
+        reasons: reasons.iter().map(|reason| reason.message.clone()).collect(),
+        deny_reasons: reasons,
+        metadata: Default::default(),
+    }
+}
+
+fn deny_reason(code: &str, rule: impl Into<String>, message: String, subject: impl Into<String>) -> DenyReason {
+    DenyReason {
+        code: code.to_string(),
+        rule_id: Some(rule.into()),
+        message,
+        subject: Some(subject.into()),
+    }
+}
+
+/// Source of the rules of evaluators created from a [`RuleConfig`] in memory
+pub const BUILTIN_SOURCE: &str = "built-in";
+
+/// Policy evaluator driven by configurable allow/deny lists
+///
+/// Explanations identify rules by their configuration key, e.g. `commands.deny` or
+/// `commands.args.python.deny`, and the rule file as their source.
+#[derive(Debug, Clone)]
+pub struct RuleBasedEvaluator {
+    config: RuleConfig,
+    source: String,
+}
+
+impl Default for RuleBasedEvaluator {
+    fn default() -> Self {
+        Self::new(RuleConfig::default())
+    }
+}
+
+impl RuleBasedEvaluator {
+    /// Create an evaluator with the given rules
+    pub fn new(config: RuleConfig) -> Self {
+        Self {
+            config,
+            source: BUILTIN_SOURCE.to_string(),
+        }
+    }
+
+    /// Create an evaluator with rules loaded from a TOML or YAML file
+    pub fn from_file(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        Ok(Self {
+            config: RuleConfig::from_file(path)?,
+            source: path.display().to_string(),
+        })
+    }
+
+    /// Active rules
+    pub fn config(&self) -> &RuleConfig {
+        &self.config
+    }
+
+    /// Where the rules were loaded from
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn matched(&self, matches: &mut Vec<RuleMatch>, rule: impl Into<String>, effect: RuleEffect, message: String) {
+        matches.push(RuleMatch {
+            rule: rule.into(),
+            source: self.source.clone(),
+            effect,
+            message,
+        });
+    }
+
+    fn evaluate_command(&self, input: &PolicyInput, matches: &mut Vec<RuleMatch>) -> PolicyDecision {
+        let rules = &self.config.commands;
+        let cmd = input.command.name.as_str();
+
+        if contains(&rules.deny, cmd) {
+            let reason = format!("Command '{}' is forbidden as it is dangerous", cmd);
+            self.matched(matches, "commands.deny", RuleEffect::Deny, reason.clone());
+            return denied(vec![deny_reason(reason_code::COMMAND_DENIED, "commands.deny", reason, cmd)]);
+        }
+
+        let arg_reasons = self.check_args(cmd, &input.command.args, matches);
+        if !arg_reasons.is_empty() {
+            return denied(arg_reasons);
+        }
+
+        if let Some(digests) = rules.sha256.get(cmd) {
+            let rule = format!("commands.sha256.{}", cmd);
+            let digest = input.context.get(CONTEXT_EXECUTABLE).and_then(|executable| executable["sha256"].as_str());
+            match digest {
+                Some(digest) if digests.iter().any(|allowed| allowed.eq_ignore_ascii_case(digest)) => {
+                    self.matched(
+                        matches,
+                        rule,
+                        RuleEffect::Allow,
+                        format!("Executable of command '{}' has an allowed digest", cmd),
+                    );
+                }
+                Some(digest) => {
+                    let reason = format!("Executable of command '{}' has an unknown SHA-256 digest {}", cmd, digest);
+                    self.matched(matches, rule.clone(), RuleEffect::Deny, reason.clone());
+                    return denied(vec![deny_reason(reason_code::EXECUTABLE_NOT_VERIFIED, rule, reason, cmd)]);
+                }
+                None => {
+                    let reason = format!("Executable of command '{}' could not be verified", cmd);
+                    self.matched(matches, rule.clone(), RuleEffect::Deny, reason.clone());
+                    return denied(vec![deny_reason(reason_code::EXECUTABLE_NOT_VERIFIED, rule, reason, cmd)]);
+                }
+            }
+        }
+
+        let is_admin = input.user.roles.iter().any(|role| contains(&rules.admin_roles, role));
+        if contains(&rules.allow, cmd) {
+            self.matched(matches, "commands.allow", RuleEffect::Allow, format!("Command '{}' is allowed", cmd));
+        } else if let Some((role, group)) = self.granting_group(&input.user.roles, cmd) {
+            self.matched(
+                matches,
+                format!("commands.roles.{}", role),
+                RuleEffect::Allow,
+                format!("Role '{}' may run command '{}' (command group '{}')", role, cmd, group),
+            );
+        } else if is_admin {
+            self.matched(
+                matches,
+                "commands.admin_roles",
+                RuleEffect::Allow,
+                format!("Administrators may run command '{}'", cmd),
+            );
+        } else {
+            let reason = format!("Command '{}' is not in the allowed list", cmd);
+            self.matched(matches, "commands.allow", RuleEffect::Deny, reason.clone());
+            return denied(vec![deny_reason(reason_code::COMMAND_NOT_ALLOWED, "commands.allow", reason, cmd)]);
+        }
+
+        let mut warnings = vec![];
+        if is_admin {
+            let warning = "Executing as administrator. All operations are audited.".to_string();
+            self.matched(matches, "commands.admin_roles", RuleEffect::Warn, warning.clone());
+            warnings.push(warning);
+        }
+
+        let mut metadata = HashMap::new();
+        if contains(&rules.cacheable, cmd) {
+            metadata.insert(METADATA_CACHEABLE.to_string(), json!(true));
+        }
+        if let Some((key, limits)) = self.command_limits(cmd, &input.command.args) {
+            self.matched(
+                matches,
+                format!("commands.limits.{}", key),
+                RuleEffect::Allow,
+                format!("Command '{}' runs with the limits of '{}'", cmd, key),
+            );
+            metadata.insert(METADATA_COMMAND_LIMITS.to_string(), json!(limits));
+        }
+
+        allowed(warnings, metadata)
+    }
+
+    // Most specific limits whose key is the command followed by leading arguments of the request
+    fn command_limits<'a>(&'a self, cmd: &str, args: &[String]) -> Option<(&'a str, &'a CommandLimits)> {
+        self.config
+            .commands
+            .limits
+            .iter()
+            .filter_map(|(key, limits)| {
+                let mut words = key.split_whitespace();
+                if words.next() != Some(cmd) {
+                    return None;
+                }
+                let prefix: Vec<&str> = words.collect();
+                let matches = prefix.len() <= args.len() && prefix.iter().zip(args).all(|(word, arg)| word == arg);
+                matches.then_some((prefix.len(), key.as_str(), limits))
+            })
+            .max_by_key(|(specificity, _, _)| *specificity)
+            .map(|(_, key, limits)| (key, limits))
+    }
+
+    // First role of the user mapped to a command group containing the command
+    fn granting_group<'a>(&'a self, roles: &'a [String], cmd: &str) -> Option<(&'a str, &'a str)> {
+        let rules = &self.config.commands;
+        roles.iter().find_map(|role| {
+            rules.roles.get(role)?.iter().find_map(|group| {
+                let commands = rules.groups.get(group)?;
+                contains(commands, cmd).then_some((role.as_str(), group.as_str()))
+            })
+        })
+    }
+
+    // Denial reasons for the arguments of a command (empty if they are all permitted)
+    fn check_args(&self, cmd: &str, args: &[String], matches: &mut Vec<RuleMatch>) -> Vec<DenyReason> {
+        let rules = &self.config.commands;
+        let command_rules = rules.args.get(cmd);
+
+        // Rule that denies an argument
+        let denying_rule = |arg: &str| {
+            if rules.deny_args.iter().any(|pattern| pattern.matches(arg)) {
+                return Some("commands.deny_args".to_string());
+            }
+            let command_rules = command_rules?;
+            if command_rules.deny.iter().any(|pattern| pattern.matches(arg)) {
+                Some(format!("commands.args.{}.deny", cmd))
+            } else if !command_rules.allow.is_empty()
+                && !command_rules.allow.iter().any(|pattern| pattern.matches(arg))
+            {
+                Some(format!("commands.args.{}.allow", cmd))
+            } else {
+                None
+            }
+        };
+
+        let mut reasons = Vec::new();
+        for arg in args {
+            if let Some(rule) = denying_rule(arg) {
+                let reason = format!("Argument '{}' is not allowed for command '{}'", arg, cmd);
+                self.matched(matches, rule.clone(), RuleEffect::Deny, reason.clone());
+                reasons.push(deny_reason(reason_code::ARGUMENT_NOT_ALLOWED, rule, reason, arg.as_str()));
+            }
+        }
+        reasons
+    }
+
+    fn evaluate_file_access(
+        &self,
+        input: &PolicyInput,
+        file_info: &FileInfo,
+        matches: &mut Vec<RuleMatch>,
+    ) -> PolicyDecision {
+        let rules = &self.config.files;
+        let path = file_info.path.as_str();
+        let normalized = normalize_path(path);
+
+        if let Some(pattern) = first_match(&rules.deny, &normalized) {
+            let reason = format!("Access to path '{}' is forbidden", path);
+            self.matched(
+                matches,
+                "files.deny",
+                RuleEffect::Deny,
+                format!("{} (matched '{}')", reason, pattern),
+            );
+            return denied(vec![deny_reason(reason_code::PATH_DENIED, "files.deny", reason, path)]);
+        }
+
+        let findings = input.context.get(CONTEXT_CONTENT_SCAN).and_then(|scan| scan["findings"].as_array());
+        let mut content_reasons = Vec::new();
+        for finding in findings.into_iter().flatten().filter_map(|finding| finding["rule"].as_str()) {
+            let Some(pattern) = rules.deny_content.get(finding).and_then(|patterns| first_match(patterns, &normalized))
+            else {
+                continue;
+            };
+            let rule = format!("files.deny_content.{}", finding);
+            let reason = format!("Writing content with finding '{}' to path '{}' is forbidden", finding, path);
+            self.matched(matches, rule.clone(), RuleEffect::Deny, format!("{} (matched '{}')", reason, pattern));
+            content_reasons.push(deny_reason(reason_code::CONTENT_DENIED, rule, reason, path));
+        }
+        if !content_reasons.is_empty() {
+            return denied(content_reasons);
+        }
+
+        let (rule, patterns) = match file_info.mode.as_str() {
+            "read" => ("files.read", Some(&rules.read)),
+            "write" => ("files.write", Some(&rules.write)),
+            "execute" => ("files.execute", Some(&rules.execute)),
+            _ => ("files", None),
+        };
+        match patterns.and_then(|patterns| first_match(patterns, &normalized)) {
+            Some(pattern) => self.matched(
+                matches,
+                rule,
+                RuleEffect::Allow,
+                format!("'{}' access to path '{}' is allowed (matched '{}')", file_info.mode, path, pattern),
+            ),
+            None => {
+                let reason = format!("'{}' access to path '{}' is not allowed", file_info.mode, path);
+                self.matched(matches, rule, RuleEffect::Deny, reason.clone());
+                return denied(vec![deny_reason(reason_code::PATH_NOT_ALLOWED, rule, reason, path)]);
+            }
+        }
+
+        let mut warnings = vec![];
+        if file_info.mode == "write" {
+            let warning = "File write operations are audited".to_string();
+            self.matched(matches, "files.write", RuleEffect::Warn, warning.clone());
+            warnings.push(warning);
+        }
+        allowed(warnings, Default::default())
+    }
+
+    fn evaluate_network_access(
+        &self,
+        input: &PolicyInput,
+        network_info: &NetworkInfo,
+        matches: &mut Vec<RuleMatch>,
+    ) -> PolicyDecision {
+        let rules = &self.config.network;
+        let host = network_info.host.as_str();
+
+        let mut reasons = vec![];
+        let mut deny = |rule: &str, code: &str, reason: String, subject: String| {
+            self.matched(matches, rule, RuleEffect::Deny, reason.clone());
+            reasons.push(deny_reason(code, rule, reason, subject));
+        };
+        if contains(&rules.deny_hosts, host) {
+            let reason = format!("Access to host '{}' is forbidden", host);
+            deny("network.deny_hosts", reason_code::HOST_DENIED, reason, host.to_string());
+        } else if !contains(&rules.allow_hosts, host) {
+            let reason = format!("Access to host '{}' is not allowed", host);
+            deny("network.allow_hosts", reason_code::HOST_NOT_ALLOWED, reason, host.to_string());
+        }
+        if !rules.ports.contains(&network_info.port) {
+            let reason = format!("Access to port {} is not allowed", network_info.port);
+            deny("network.ports", reason_code::PORT_NOT_ALLOWED, reason, network_info.port.to_string());
+        }
+        if !contains(&rules.protocols, &network_info.protocol) {
+            let reason = format!("Use of protocol '{}' is not allowed", network_info.protocol);
+            deny("network.protocols", reason_code::PROTOCOL_NOT_ALLOWED, reason, network_info.protocol.clone());
+        }
+        if let Some(geoip) = input.context.get(CONTEXT_GEOIP) {
+            let countries = geoip["countries"].as_array().into_iter().flatten().filter_map(|c| c.as_str());
+            for country in countries {
+                if rules.deny_countries.iter().any(|denied| denied.eq_ignore_ascii_case(country)) {
+                    let reason = format!("Access to host '{}' in country {} is forbidden", host, country);
+                    deny("network.deny_countries", reason_code::LOCATION_DENIED, reason, country.to_string());
+                }
+            }
+            for asn in geoip["asns"].as_array().into_iter().flatten().filter_map(|asn| asn.as_u64()) {
+                if rules.deny_asns.iter().any(|denied| u64::from(*denied) == asn) {
+                    let reason = format!("Access to host '{}' in AS{} is forbidden", host, asn);
+                    deny("network.deny_asns", reason_code::LOCATION_DENIED, reason, format!("AS{}", asn));
+                }
+            }
+        }
+
+        if reasons.is_empty() {
+            self.matched(
+                matches,
+                "network.allow_hosts",
+                RuleEffect::Allow,
+                format!("{} access to host '{}:{}' is allowed", network_info.protocol, host, network_info.port),
+            );
+            let warning = "Network requests are audited".to_string();
+            self.matched(matches, "network", RuleEffect::Warn, warning.clone());
+            allowed(vec![warning], Default::default())
+        } else {
+            denied(reasons)
+        }
+    }
+
+    fn evaluate_tool_call(&self, server: &str, tool: &str, matches: &mut Vec<RuleMatch>) -> PolicyDecision {
+        let rules = &self.config.tools;
+        let name = format!("{}/{}", server, tool);
+
+        if let Some(pattern) = rules.deny.iter().find(|pattern| pattern.matches(&name)) {
+            let reason = format!("Tool '{}' is forbidden", name);
+            self.matched(
+                matches,
+                "tools.deny",
+                RuleEffect::Deny,
+                format!("{} (matched '{}')", reason, pattern.as_str()),
+            );
+            return denied(vec![deny_reason(reason_code::TOOL_DENIED, "tools.deny", reason, name)]);
+        }
+        match rules.allow.iter().find(|pattern| pattern.matches(&name)) {
+            Some(pattern) => {
+                self.matched(
+                    matches,
+                    "tools.allow",
+                    RuleEffect::Allow,
+                    format!("Tool '{}' is allowed (matched '{}')", name, pattern.as_str()),
+                );
+                let warning = "Tool calls are audited".to_string();
+                self.matched(matches, "tools", RuleEffect::Warn, warning.clone());
+                allowed(vec![warning], Default::default())
+            }
+            None => {
+                let reason = format!("Tool '{}' is not in the allowed list", name);
+                self.matched(matches, "tools.allow", RuleEffect::Deny, reason.clone());
+                denied(vec![deny_reason(reason_code::TOOL_NOT_ALLOWED, "tools.allow", reason, name)])
+            }
+        }
+    }
+}
+
+impl PolicyEvaluator for RuleBasedEvaluator {
+    fn evaluate(&self, input: &PolicyInput) -> McpResult<PolicyDecision> {
+        Ok(PolicyEvaluator::explain(self, input)?.decision)
+    }
+
+    fn name(&self) -> &str {
+        "rules"
+    }
+
+    fn explain(&self, input: &PolicyInput) -> McpResult<PolicyExplanation> {
+        let mut rules = Vec::new();
+        let decision = if !input.command.name.is_empty() {
+            self.evaluate_command(input, &mut rules)
+        } else if let Some(file_info) = &input.file {
+            self.evaluate_file_access(input, file_info, &mut rules)
+        } else if let Some(network_info) = &input.network {
+            self.evaluate_network_access(input, network_info, &mut rules)
+        } else if let Some((server, tool)) = input.tool_call() {
+            self.evaluate_tool_call(server, tool, &mut rules)
+        } else {
+            denied(vec![DenyReason {
+                code: reason_code::POLICY_DENIED.to_string(),
+                rule_id: None,
+                message: "Unknown request type".to_string(),
+                subject: None,
+            }])
+        };
+
+        Ok(PolicyExplanation {
+            evaluator: PolicyEvaluator::name(self).to_string(),
+            decision,
+            rules,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CommandInfo;
+
+    fn command(name: &str, roles: &[&str]) -> PolicyInput {
+        let mut input = PolicyInput {
+            user: Default::default(),
+            command: CommandInfo {
+                name: name.to_string(),
+                ..Default::default()
+            },
+            file: None,
+            network: None,
+            resources: Default::default(),
+            context: Default::default(),
+        };
+        input.user.roles = strings(roles);
+        input
+    }
+
+    fn network(host: &str, port: u16) -> PolicyInput {
+        let mut input = command("", &[]);
+        input.network = Some(NetworkInfo {
+            host: host.to_string(),
+            port,
+            protocol: "https".to_string(),
+        });
+        input
+    }
+
+    // Test for rules loaded from TOML, with omitted lists keeping their defaults
+    #[test]
+    fn test_toml_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        std::fs::write(
+            &path,
+            r#"
+[commands]
+allow = ["ls", "make"]
+deny = ["rm"]
+
+[network]
+allow_hosts = ["internal.example.com"]
+deny_hosts = ["api.example.com"]
+ports = [443]
+"#,
+        )
+        .unwrap();
+        let evaluator = RuleBasedEvaluator::from_file(&path).unwrap();
+
+        assert!(evaluator.evaluate(&command("make", &["user"])).unwrap().allow);
+        assert!(!evaluator.evaluate(&command("echo", &["user"])).unwrap().allow);
+        assert!(!evaluator.evaluate(&command("rm", &["admin"])).unwrap().allow);
+        // The defaults still apply to omitted lists
+        assert!(evaluator.evaluate(&command("ls", &["user"])).unwrap().is_cacheable());
+        assert_eq!(evaluator.config().files.deny, FileRules::default().deny);
+
+        assert!(evaluator.evaluate(&network("internal.example.com", 443)).unwrap().allow);
+        let decision = evaluator.evaluate(&network("api.example.com", 80)).unwrap();
+        assert!(!decision.allow);
+        assert_eq!(
+            decision.reasons,
+            vec!["Access to host 'api.example.com' is forbidden", "Access to port 80 is not allowed"]
+        );
+    }
+
+    // Test for country and ASN rules on the GeoIP context
+    #[test]
+    fn test_geoip_rules() {
+        let mut config = RuleConfig::default();
+        config.network.deny_countries = strings(&["kp"]);
+        config.network.deny_asns = vec![64512];
+        let evaluator = RuleBasedEvaluator::new(config);
+        let located = |countries: &[&str], asns: &[u32]| {
+            let mut input = network("api.example.com", 443);
+            input.context.insert(
+                CONTEXT_GEOIP.to_string(),
+                json!({ "addresses": ["192.0.2.1"], "countries": countries, "asns": asns }),
+            );
+            evaluator.evaluate(&input).unwrap()
+        };
+
+        assert!(located(&["US"], &[15133]).allow);
+        let decision = located(&["US", "KP"], &[64512]);
+        assert!(!decision.allow);
+        assert_eq!(
+            decision.reasons,
+            vec![
+                "Access to host 'api.example.com' in country KP is forbidden",
+                "Access to host 'api.example.com' in AS64512 is forbidden"
+            ]
+        );
+        // Hosts that could not be located pass the GeoIP rules
+        assert!(evaluator.evaluate(&network("api.example.com", 443)).unwrap().allow);
+    }
+
+    // Test for per-command timeout and resource limits
+    #[test]
+    fn test_command_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        std::fs::write(
+            &path,
+            r#"
+[commands.limits.npm]
+max_timeout_secs = 120
+
+[commands.limits."npm install"]
+default_timeout_secs = 300
+max_timeout_secs = 600
+max_cpu_cores = 2.0
+max_memory = "2G"
+"#,
+        )
+        .unwrap();
+        let evaluator = RuleBasedEvaluator::from_file(&path).unwrap();
+        let limits = |args: &[&str]| {
+            let mut input = command("npm", &["user"]);
+            input.command.args = strings(args);
+            evaluator.evaluate(&input).unwrap().command_limits().unwrap()
+        };
+
+        let install = limits(&["install", "--save-dev", "jest"]).unwrap();
+        assert_eq!(install.default_timeout_secs, Some(300));
+        assert_eq!(install.max_timeout_secs, Some(600));
+        assert_eq!(install.max_cpu_cores, Some(2.0));
+        assert_eq!(install.max_memory, Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(install.default_memory, None);
+
+        assert_eq!(limits(&["test"]).unwrap().max_timeout_secs, Some(120));
+        assert!(limits(&[]).unwrap().default_timeout_secs.is_none());
+        // Commands without limits report none
+        assert_eq!(evaluator.evaluate(&command("ls", &["user"])).unwrap().command_limits().unwrap(), None);
+
+        std::fs::write(&path, "[commands.limits.npm]\nmax_memory = \"lots\"\n").unwrap();
+        assert!(RuleBasedEvaluator::from_file(&path).is_err());
+    }
+
+    // Test for per-command and global argument rules
+    #[test]
+    fn test_argument_rules() {
+        let evaluator = RuleBasedEvaluator::default();
+        let run = |name: &str, args: &[&str], roles: &[&str]| {
+            let mut input = command(name, roles);
+            input.command.args = strings(args);
+            evaluator.evaluate(&input).unwrap()
+        };
+
+        assert!(run("python", &["script.py"], &["user"]).allow);
+        let decision = run("python", &["-c", "print(1)"], &["user"]);
+        assert!(!decision.allow);
+        assert_eq!(decision.reasons, vec!["Argument '-c' is not allowed for command 'python'"]);
+        assert!(run("find", &[".", "-name", "*.rs"], &["user"]).allow);
+        assert!(!run("find", &[".", "-exec", "cat", "{}", ";"], &["user"]).allow);
+        assert!(!run("find", &[".", "-fprintf", "/home/user/.bashrc", "%p"], &["user"]).allow);
+        // Administrators are bound by argument rules too
+        assert!(!run("node", &["--eval", "1"], &["admin"]).allow);
+        assert!(!run("docker", &["run", "--privileged=true"], &["admin"]).allow);
+        assert!(run("docker", &["run", "--privileged-mode"], &["admin"]).allow);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        std::fs::write(
+            &path,
+            r#"
+[commands.args.python]
+allow = ["*.py", "re:-[uB]"]
+"#,
+        )
+        .unwrap();
+        let evaluator = RuleBasedEvaluator::from_file(&path).unwrap();
+        let run = |args: &[&str]| {
+            let mut input = command("python", &["user"]);
+            input.command.args = strings(args);
+            evaluator.evaluate(&input).unwrap().allow
+        };
+        assert!(run(&["-u", "tools/build.py"]));
+        assert!(!run(&["-m", "http.server"]));
+        // The global deny list keeps its default
+        assert!(!run(&["--privileged"]));
+    }
+
+    // Test for pinning commands to executable digests
+    #[test]
+    fn test_digest_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        std::fs::write(
+            &path,
+            r#"
+[commands.sha256]
+python3 = ["ABC123", "def456"]
+"#,
+        )
+        .unwrap();
+        let evaluator = RuleBasedEvaluator::from_file(&path).unwrap();
+        let run = |name: &str, digest: Option<&str>, roles: &[&str]| {
+            let mut input = command(name, roles);
+            if let Some(digest) = digest {
+                input.context.insert(
+                    CONTEXT_EXECUTABLE.to_string(),
+                    json!({ "path": format!("/usr/bin/{}", name), "sha256": digest }),
+                );
+            }
+            evaluator.evaluate(&input).unwrap()
+        };
+
+        assert!(run("python3", Some("abc123"), &["user"]).allow);
+        assert!(run("python3", Some("DEF456"), &["user"]).allow);
+        let decision = run("python3", Some("0000"), &["admin"]);
+        assert!(!decision.allow);
+        assert_eq!(decision.reasons, vec!["Executable of command 'python3' has an unknown SHA-256 digest 0000"]);
+        assert!(!run("python3", None, &["user"]).allow);
+        // Commands without digest rules are not affected
+        assert!(run("ls", None, &["user"]).allow);
+    }
+
+    // Test for mapping roles to command groups
+    #[test]
+    fn test_command_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        std::fs::write(
+            &path,
+            r#"
+[commands]
+deny = ["sudo"]
+
+[commands.groups]
+build-tools = ["make", "cargo"]
+vcs = ["git"]
+privileged = ["sudo"]
+
+[commands.roles]
+developer = ["build-tools", "vcs"]
+releaser = ["vcs", "privileged"]
+"#,
+        )
+        .unwrap();
+        let evaluator = RuleBasedEvaluator::from_file(&path).unwrap();
+        let allowed = |name: &str, roles: &[&str]| evaluator.evaluate(&command(name, roles)).unwrap().allow;
+
+        assert!(allowed("make", &["developer"]));
+        assert!(allowed("git", &["user", "releaser"]));
+        assert!(!allowed("make", &["releaser"]));
+        assert!(!allowed("git", &["user"]));
+        // Deny lists take precedence over command groups
+        assert!(!allowed("sudo", &["releaser"]));
+        // Commands anyone may run are not affected
+        assert!(allowed("ls", &["user"]));
+
+        let explanation = evaluator.explain(&command("cargo", &["developer"])).unwrap();
+        assert_eq!(explanation.rules[0].rule, "commands.roles.developer");
+        assert_eq!(
+            explanation.rules[0].message,
+            "Role 'developer' may run command 'cargo' (command group 'build-tools')"
+        );
+
+        std::fs::write(&path, "[commands.roles]\ndeveloper = [\"build\"]\n").unwrap();
+        let err = RuleBasedEvaluator::from_file(&path).unwrap_err();
+        assert!(err.to_string().contains("undefined command group 'build'"), "{}", err);
+    }
+
+    // Test for rules loaded from YAML
+    #[test]
+    fn test_yaml_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.yaml");
+        std::fs::write(
+            &path,
+            "files:\n  read: [/srv/, '/data/**/*.csv']\n  deny: [/srv/secret/, 're:/srv/.*\\.key']\n",
+        )
+        .unwrap();
+        let evaluator = RuleBasedEvaluator::from_file(&path).unwrap();
+
+        let mut input = command("", &[]);
+        input.file = Some(FileInfo {
+            path: "/srv/data.txt".to_string(),
+            mode: "read".to_string(),
+        });
+        assert!(evaluator.evaluate(&input).unwrap().allow);
+
+        input.file = Some(FileInfo {
+            path: "/srv/secret/key".to_string(),
+            mode: "read".to_string(),
+        });
+        assert!(!evaluator.evaluate(&input).unwrap().allow);
+
+        let read = |path: &str| {
+            let mut input = command("", &[]);
+            input.file = Some(FileInfo {
+                path: path.to_string(),
+                mode: "read".to_string(),
+            });
+            evaluator.evaluate(&input).unwrap().allow
+        };
+        assert!(read("/data/2024/sales.csv"));
+        assert!(!read("/data/2024/sales.json"));
+        assert!(!read("/srv/tls/server.key"));
+        // Traversal out of an allowed directory and into a denied one
+        assert!(!read("/srv/../etc/passwd"));
+        assert!(!read("/srv/public/../secret/token"));
+
+        // Unknown request types are denied
+        assert!(!evaluator.evaluate(&command("", &[])).unwrap().allow);
+    }
+
+    // Test for denying writes by content scan finding
+    #[test]
+    fn test_content_rules() {
+        let mut config = RuleConfig::default();
+        config.files.deny_content.insert("private_key".to_string(), patterns(&["/workspace/"]));
+        let evaluator = RuleBasedEvaluator::new(config);
+        let write = |path: &str, findings: &[&str]| {
+            let mut input = command("", &[]);
+            input.file = Some(FileInfo {
+                path: path.to_string(),
+                mode: "write".to_string(),
+            });
+            let findings: Vec<_> = findings.iter().map(|rule| json!({ "scanner": "secrets", "rule": rule })).collect();
+            input.context.insert(CONTEXT_CONTENT_SCAN.to_string(), json!({ "findings": findings }));
+            evaluator.evaluate(&input).unwrap()
+        };
+
+        assert!(write("/workspace/notes.md", &[]).allow);
+        assert!(write("/workspace/notes.md", &["aws_access_key_id"]).allow);
+        assert!(write("/tmp/id_rsa", &["private_key"]).allow);
+        let decision = write("/workspace/id_rsa", &["private_key"]);
+        assert!(!decision.allow);
+        assert_eq!(
+            decision.reasons,
+            vec!["Writing content with finding 'private_key' to path '/workspace/id_rsa' is forbidden"]
+        );
+        assert_eq!(decision.deny_reasons[0].rule_id.as_deref(), Some("files.deny_content.private_key"));
+    }
+
+    // Test for rules on the tools of upstream MCP servers
+    #[test]
+    fn test_tool_rules() {
+        let config: RuleConfig = toml::from_str(
+            r#"
+[tools]
+allow = ["github/*", "filesystem/read_file"]
+deny = ["github/delete_*"]
+"#,
+        )
+        .unwrap();
+        let evaluator = RuleBasedEvaluator::new(config);
+        let tool_call = |server: &str, tool: &str| {
+            let mut input = command("", &["user"]);
+            input.set_tool_call(server, tool, &json!({}));
+            evaluator.evaluate(&input).unwrap()
+        };
+
+        assert!(tool_call("github", "create_issue").allow);
+        assert!(tool_call("filesystem", "read_file").allow);
+        let decision = tool_call("github", "delete_repository");
+        assert!(!decision.allow);
+        assert_eq!(decision.deny_reasons[0].code, reason_code::TOOL_DENIED);
+        assert_eq!(decision.deny_reasons[0].subject.as_deref(), Some("github/delete_repository"));
+        let decision = tool_call("filesystem", "write_file");
+        assert_eq!(decision.deny_reasons[0].code, reason_code::TOOL_NOT_ALLOWED);
+        // No tool may be called by default
+        assert!(!RuleBasedEvaluator::default().evaluate(&command("", &[])).unwrap().allow);
+        let mut input = command("", &["admin"]);
+        input.set_tool_call("github", "create_issue", &json!({}));
+        assert!(!RuleBasedEvaluator::default().evaluate(&input).unwrap().allow);
+    }
+
+    // The shipped example matches the defaults
+    #[test]
+    fn test_example_rules() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../policies/rules/rules.toml");
+        let config = RuleConfig::from_file(path).unwrap();
+        let defaults = RuleConfig::default();
+        assert_eq!(config.commands.allow, defaults.commands.allow);
+        assert_eq!(config.commands.deny, defaults.commands.deny);
+        assert_eq!(config.commands.deny_args, defaults.commands.deny_args);
+        assert_eq!(config.commands.args, defaults.commands.args);
+        assert_eq!(config.files.read, defaults.files.read);
+        assert_eq!(config.network.ports, defaults.network.ports);
+    }
+
+    // Test for rejecting invalid rule files
+    #[test]
+    fn test_invalid_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let typo = dir.path().join("rules.toml");
+        std::fs::write(&typo, "[commands]\nallowed = [\"ls\"]\n").unwrap();
+        assert!(RuleBasedEvaluator::from_file(&typo).is_err());
+
+        let json = dir.path().join("rules.json");
+        std::fs::write(&json, "{}").unwrap();
+        assert!(RuleBasedEvaluator::from_file(&json).is_err());
+    }
+
+    // Test for machine-readable deny reasons
+    #[test]
+    fn test_deny_reasons() {
+        let evaluator = RuleBasedEvaluator::default();
+        let reasons = |input: &PolicyInput| {
+            let decision = evaluator.evaluate(input).unwrap();
+            assert_eq!(decision.reasons.len(), decision.deny_reasons.len());
+            decision
+                .deny_reasons
+                .into_iter()
+                .map(|reason| (reason.code, reason.rule_id.unwrap(), reason.subject.unwrap()))
+                .collect::<Vec<_>>()
+        };
+        let triple = |code: &str, rule: &str, subject: &str| (code.to_string(), rule.to_string(), subject.to_string());
+
+        assert_eq!(reasons(&command("sudo", &["user"])), vec![triple("command_denied", "commands.deny", "sudo")]);
+        assert_eq!(
+            reasons(&command("make", &["user"])),
+            vec![triple("command_not_allowed", "commands.allow", "make")]
+        );
+        assert_eq!(
+            reasons(&network("evil.example.com", 22)),
+            vec![
+                triple("host_not_allowed", "network.allow_hosts", "evil.example.com"),
+                triple("port_not_allowed", "network.ports", "22")
+            ]
+        );
+        assert!(evaluator.evaluate(&command("ls", &["user"])).unwrap().deny_reasons.is_empty());
+    }
+
+    // Test for explaining decisions with the matched rules
+    #[test]
+    fn test_explain() {
+        let evaluator = RuleBasedEvaluator::default();
+
+        let explanation = evaluator.explain(&command("rm", &["admin"])).unwrap();
+        assert!(!explanation.decision.allow);
+        assert_eq!(explanation.evaluator, "rules");
+        assert_eq!(explanation.rules.len(), 1);
+        assert_eq!(explanation.rules[0].rule, "commands.deny");
+        assert_eq!(explanation.rules[0].source, BUILTIN_SOURCE);
+        assert_eq!(explanation.rules[0].effect, RuleEffect::Deny);
+
+        let mut input = command("python", &["user"]);
+        input.command.args = strings(&["-c", "print(1)", "--privileged"]);
+        let rules: Vec<String> = evaluator.explain(&input).unwrap().rules.into_iter().map(|rule| rule.rule).collect();
+        assert_eq!(rules, vec!["commands.args.python.deny", "commands.deny_args"]);
+
+        // Allowed administrators match the allow rule and the warning
+        let explanation = evaluator.explain(&command("make", &["admin"])).unwrap();
+        let rules: Vec<_> = explanation.rules.iter().map(|rule| (rule.rule.as_str(), rule.effect)).collect();
+        assert_eq!(
+            rules,
+            vec![("commands.admin_roles", RuleEffect::Allow), ("commands.admin_roles", RuleEffect::Warn)]
+        );
+
+        // Rules loaded from a file name it as their source
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.yaml");
+        std::fs::write(&path, "files:\n  read: [\"/srv/**/*.txt\"]\n").unwrap();
+        let mut input = command("", &[]);
+        input.file = Some(FileInfo {
+            path: "/srv/docs/a.txt".to_string(),
+            mode: "read".to_string(),
+        });
+        let explanation = RuleBasedEvaluator::from_file(&path).unwrap().explain(&input).unwrap();
+        assert!(explanation.decision.allow);
+        assert_eq!(explanation.rules[0].rule, "files.read");
+        assert_eq!(explanation.rules[0].source, path.display().to_string());
+        assert!(explanation.rules[0].message.contains("/srv/**/*.txt"));
+    }
+}
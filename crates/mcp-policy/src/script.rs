@@ -0,0 +1,166 @@
+//! Rhai script evaluator
+//!
+//! Small site-specific rules can be written as a [Rhai](https://rhai.rs) script instead of
+//! a full Rego policy. The script sees the `PolicyInput` as the constant `input` and its
+//! last expression is the decision: either a boolean, or a map with the fields of
+//! `PolicyDecision` (only `allow` is required).
+//!
+//! ```rhai
+//! if input.command.name == "curl" && !input.user.roles.contains("network") {
+//!     #{ allow: false, reasons: ["curl requires the network role"] }
+//! } else {
+//!     true
+//! }
+//! ```
+//!
+//! Scripts run with limits on the number of operations and the size of the values they
+//! build, so that a faulty script fails the evaluation instead of stalling the gateway.
+
+use crate::engine::PolicyEvaluator;
+use crate::models::{PolicyDecision, PolicyInput};
+use mcp_common::error::{McpError, McpResult};
+use rhai::{Dynamic, Engine, Scope, AST};
+use std::fmt;
+use std::path::Path;
+
+/// Maximum number of operations of one evaluation
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Evaluator running a Rhai script
+pub struct ScriptEvaluator {
+    engine: Engine,
+    ast: AST,
+    source: String,
+}
+
+impl fmt::Debug for ScriptEvaluator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptEvaluator").field("source", &self.source).finish()
+    }
+}
+
+impl ScriptEvaluator {
+    /// Compile a script; `source` names it in errors
+    pub fn new(script: &str, source: &str) -> McpResult<Self> {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(64 * 1024)
+            .set_max_array_size(10_000)
+            .set_max_map_size(10_000);
+
+        let ast = engine
+            .compile(script)
+            .map_err(|e| McpError::Internal(format!("Failed to compile policy script {}: {}", source, e)))?;
+
+        Ok(Self {
+            engine,
+            ast,
+            source: source.to_string(),
+        })
+    }
+
+    /// Load a script from a `.rhai` file
+    pub fn from_file(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        let script = std::fs::read_to_string(path)
+            .map_err(|e| McpError::Internal(format!("Failed to read policy script {}: {}", path.display(), e)))?;
+        Self::new(&script, &path.display().to_string())
+    }
+
+    /// Where the script was loaded from
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+impl PolicyEvaluator for ScriptEvaluator {
+    fn evaluate(&self, input: &PolicyInput) -> McpResult<PolicyDecision> {
+        let input = rhai::serde::to_dynamic(input)
+            .map_err(|e| McpError::Internal(format!("Failed to convert input for policy script: {}", e)))?;
+        let mut scope = Scope::new();
+        scope.push_constant("input", input);
+
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast)
+            .map_err(|e| McpError::Internal(format!("Policy script {} failed: {}", self.source, e)))?;
+
+        if let Some(allow) = result.clone().try_cast::<bool>() {
+            return Ok(PolicyDecision {
+                allow,
+                warnings: vec![],
+                reasons: vec![],
+                deny_reasons: vec![],
+                metadata: Default::default(),
+            });
+        }
+        rhai::serde::from_dynamic(&result).map_err(|e| {
+            McpError::Internal(format!(
+                "Policy script {} must return a boolean or a decision map: {}",
+                self.source, e
+            ))
+        })
+    }
+
+    fn name(&self) -> &str {
+        "script"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::input_with_roles as input;
+
+    // Test for evaluating a script
+    #[test]
+    fn test_evaluate() {
+        let script = r#"
+            if input.command.name == "curl" && !input.user.roles.contains("network") {
+                #{ allow: false, reasons: ["curl requires the network role"] }
+            } else if input.command.name == "sudo" {
+                false
+            } else {
+                #{ allow: true, warnings: ["checked by script"], metadata: #{ cacheable: true } }
+            }
+        "#;
+        let evaluator = ScriptEvaluator::new(script, "test.rhai").unwrap();
+
+        let decision = evaluator.evaluate(&input("curl", &[])).unwrap();
+        assert!(!decision.allow);
+        assert_eq!(decision.reasons, vec!["curl requires the network role"]);
+
+        let decision = evaluator.evaluate(&input("curl", &["network"])).unwrap();
+        assert!(decision.allow);
+        assert_eq!(decision.warnings, vec!["checked by script"]);
+        assert!(decision.is_cacheable());
+
+        assert!(!evaluator.evaluate(&input("sudo", &[])).unwrap().allow);
+    }
+
+    // Test for rejecting faulty scripts
+    #[test]
+    fn test_invalid_scripts() {
+        assert!(ScriptEvaluator::new("if {", "test.rhai").is_err());
+
+        for script in [r#""allow""#, "#{ reasons: [] }", "loop {}", "undefined_function()"] {
+            let evaluator = ScriptEvaluator::new(script, "test.rhai").unwrap();
+            assert!(evaluator.evaluate(&input("ls", &[])).is_err(), "{}", script);
+        }
+    }
+
+    // Test for the bundled example script
+    #[test]
+    fn test_example_script() {
+        let evaluator =
+            ScriptEvaluator::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/../../policies/script/site.rhai")).unwrap();
+
+        assert!(evaluator.evaluate(&input("ls", &[])).unwrap().allow);
+        assert!(!evaluator.evaluate(&input("rm", &["admin"])).unwrap().allow);
+        assert!(!evaluator.evaluate(&input("curl", &[])).unwrap().allow);
+        assert!(evaluator.evaluate(&input("curl", &["network"])).unwrap().allow);
+    }
+}
@@ -0,0 +1,465 @@
+//! Shell wrapper expansion
+//!
+//! Policies decide on the name and arguments of a command, so `sh -c "ls; rm -rf /"` would
+//! only be checked as `sh`. When a shell interpreter is invoked with a command string
+//! (`-c`, alone or in an option cluster such as `-ec`), `PolicyEngine` splits the string
+//! into its simple commands with [`embedded_commands`] and evaluates each of them as well.
+//!
+//! The splitter understands quoting, escapes, the control operators (`;`, `&`, `&&`, `|`,
+//! `||`, newlines, subshells), command and process substitution, redirections, here
+//! documents and leading variable assignments. It does not expand variables: a command
+//! whose name is only known at run time (e.g. `$CMD`) is evaluated under its literal name,
+//! which policies do not allow. Command strings the splitter cannot parse are rejected.
+
+use mcp_common::error::{McpError, McpResult};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Shell interpreters whose command strings are expanded
+pub const SHELL_INTERPRETERS: &[&str] = &["sh", "bash", "dash", "zsh", "ksh", "mksh", "ash", "fish"];
+
+/// Maximum nesting of shells within command strings
+const MAX_DEPTH: usize = 8;
+
+/// Words skipped at the start of a command
+const RESERVED_WORDS: &[&str] = &[
+    "!", "{", "}", "if", "then", "else", "elif", "fi", "do", "done", "while", "until", "time",
+];
+
+/// Whether a command name is a shell interpreter (with or without a directory)
+pub fn is_shell_interpreter(name: &str) -> bool {
+    let base = name.rsplit('/').next().unwrap_or(name);
+    SHELL_INTERPRETERS.contains(&base)
+}
+
+/// Command string passed to a shell with `-c` (`None` for other commands and invocations)
+pub fn command_string<'a>(name: &str, args: &'a [String]) -> McpResult<Option<&'a str>> {
+    if !is_shell_interpreter(name) {
+        return Ok(None);
+    }
+
+    let mut has_command_string = false;
+    let mut index = 0;
+    while let Some(arg) = args.get(index) {
+        if arg == "--" || arg == "-" {
+            index += 1;
+            break;
+        }
+        if arg.starts_with("--") {
+            index += 1;
+            continue;
+        }
+        let Some(flags) = arg.strip_prefix('-').or_else(|| arg.strip_prefix('+')) else {
+            break;
+        };
+        if flags.contains('c') {
+            has_command_string = true;
+        }
+        // `-o option` and `-O option` take an argument
+        if flags.ends_with('o') || flags.ends_with('O') {
+            index += 1;
+        }
+        index += 1;
+    }
+
+    if !has_command_string {
+        return Ok(None);
+    }
+    args.get(index).map(|command| Some(command.as_str())).ok_or_else(|| {
+        McpError::InvalidRequest(format!("Shell '{}' was invoked with -c but without a command string", name))
+    })
+}
+
+/// Commands a shell invocation runs through its command string, as argument vectors
+///
+/// Shells nested within the command string are expanded as well; the nested shell itself
+/// is part of the result. Returns an empty list for commands that are not shell wrappers.
+pub fn embedded_commands(name: &str, args: &[String]) -> McpResult<Vec<Vec<String>>> {
+    let mut commands = Vec::new();
+    expand(name, args, 0, &mut commands)?;
+    Ok(commands)
+}
+
+/// Commands a shell script runs, as argument vectors
+///
+/// The script is split like a command string; shells it runs with `-c` are expanded as well
+/// (see [`embedded_commands`]).
+pub fn script_commands(script: &str) -> McpResult<Vec<Vec<String>>> {
+    let mut commands = Vec::new();
+    for argv in split_command_line(script)? {
+        expand(&argv[0], &argv[1..], 1, &mut commands)?;
+        commands.push(argv);
+    }
+    Ok(commands)
+}
+
+fn expand(name: &str, args: &[String], depth: usize, commands: &mut Vec<Vec<String>>) -> McpResult<()> {
+    let Some(line) = command_string(name, args)? else {
+        return Ok(());
+    };
+    if depth >= MAX_DEPTH {
+        return Err(McpError::InvalidRequest(format!(
+            "Shell commands are nested more than {} levels deep",
+            MAX_DEPTH
+        )));
+    }
+
+    for argv in split_command_line(line)? {
+        expand(&argv[0], &argv[1..], depth + 1, commands)?;
+        commands.push(argv);
+    }
+    Ok(())
+}
+
+/// Split a shell command line into its simple commands, as argument vectors
+///
+/// Commands of command and process substitutions follow the commands of the line.
+pub fn split_command_line(line: &str) -> McpResult<Vec<Vec<String>>> {
+    let mut splitter = Splitter::default();
+    splitter.run(&mut line.chars().peekable())?;
+
+    let mut commands = splitter.commands;
+    for substitution in splitter.substitutions {
+        commands.extend(split_command_line(&substitution)?);
+    }
+    Ok(commands)
+}
+
+#[derive(Default)]
+struct Splitter {
+    commands: Vec<Vec<String>>,
+    substitutions: Vec<String>,
+    argv: Vec<String>,
+    word: Option<String>,
+    /// Drop the next word (the target of a redirection)
+    redirect_target: bool,
+    /// Delimiters of here documents whose bodies start after the current line
+    heredocs: Vec<(String, bool)>,
+    /// The next word is the delimiter of a here document (`true` for `<<-`)
+    heredoc_delimiter: Option<bool>,
+}
+
+impl Splitter {
+    fn run(&mut self, chars: &mut Peekable<Chars>) -> McpResult<()> {
+        while let Some(c) = chars.next() {
+            match c {
+                ' ' | '\t' => self.end_word(),
+                '\n' => {
+                    self.end_command();
+                    self.skip_heredocs(chars)?;
+                }
+                ';' | '&' | '|' | '(' | ')' => self.end_command(),
+                '#' if self.word.is_none() => {
+                    while chars.next_if(|c| *c != '\n').is_some() {}
+                }
+                '\\' => match chars.next() {
+                    Some('\n') => {}
+                    Some(c) => self.push(c),
+                    None => self.push('\\'),
+                },
+                '\'' => {
+                    self.word.get_or_insert_with(String::new);
+                    loop {
+                        match chars.next() {
+                            Some('\'') => break,
+                            Some(c) => self.push(c),
+                            None => return Err(unterminated("single quote")),
+                        }
+                    }
+                }
+                '"' => self.double_quoted(chars)?,
+                '`' => self.backquoted(chars)?,
+                '$' if chars.next_if_eq(&'(').is_some() => self.dollar_parenthesis(chars)?,
+                '<' | '>' if chars.peek() == Some(&'(') => {
+                    chars.next();
+                    let command = balanced(chars)?;
+                    self.substitutions.push(command);
+                    self.push_str("<(...)");
+                }
+                '<' | '>' => self.redirection(c, chars),
+                c => self.push(c),
+            }
+        }
+
+        if self.heredoc_delimiter.is_some() || !self.heredocs.is_empty() {
+            return Err(unterminated("here document"));
+        }
+        self.end_command();
+        Ok(())
+    }
+
+    fn push(&mut self, c: char) {
+        self.word.get_or_insert_with(String::new).push(c);
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.word.get_or_insert_with(String::new).push_str(s);
+    }
+
+    fn end_word(&mut self) {
+        let Some(word) = self.word.take() else {
+            return;
+        };
+        if let Some(strip_tabs) = self.heredoc_delimiter.take() {
+            self.heredocs.push((word, strip_tabs));
+        } else if self.redirect_target {
+            self.redirect_target = false;
+        } else if self.argv.is_empty() && (RESERVED_WORDS.contains(&word.as_str()) || is_assignment(&word)) {
+            // Not the command name
+        } else {
+            self.argv.push(word);
+        }
+    }
+
+    fn end_command(&mut self) {
+        self.end_word();
+        if !self.argv.is_empty() {
+            self.commands.push(std::mem::take(&mut self.argv));
+        }
+    }
+
+    fn double_quoted(&mut self, chars: &mut Peekable<Chars>) -> McpResult<()> {
+        self.word.get_or_insert_with(String::new);
+        loop {
+            match chars.next() {
+                Some('"') => return Ok(()),
+                Some('\\') => match chars.next() {
+                    Some(c @ ('$' | '`' | '"' | '\\')) => self.push(c),
+                    Some('\n') => {}
+                    Some(c) => {
+                        self.push('\\');
+                        self.push(c);
+                    }
+                    None => return Err(unterminated("double quote")),
+                },
+                Some('`') => self.backquoted(chars)?,
+                Some('$') if chars.next_if_eq(&'(').is_some() => self.dollar_parenthesis(chars)?,
+                Some(c) => self.push(c),
+                None => return Err(unterminated("double quote")),
+            }
+        }
+    }
+
+    /// Command substitution or arithmetic expansion after `$(`
+    fn dollar_parenthesis(&mut self, chars: &mut Peekable<Chars>) -> McpResult<()> {
+        if chars.next_if_eq(&'(').is_some() {
+            // Arithmetic expansion runs no commands
+            let expression = balanced(chars)?;
+            if chars.next() != Some(')') {
+                return Err(unterminated("arithmetic expansion"));
+            }
+            self.push_str(&format!("$(({}))", expression));
+        } else {
+            let command = balanced(chars)?;
+            self.substitutions.push(command);
+            self.push_str("$(...)");
+        }
+        Ok(())
+    }
+
+    fn backquoted(&mut self, chars: &mut Peekable<Chars>) -> McpResult<()> {
+        let mut command = String::new();
+        loop {
+            match chars.next() {
+                Some('`') => break,
+                Some('\\') => match chars.next() {
+                    Some(c @ ('$' | '`' | '\\')) => command.push(c),
+                    Some(c) => {
+                        command.push('\\');
+                        command.push(c);
+                    }
+                    None => return Err(unterminated("command substitution")),
+                },
+                Some(c) => command.push(c),
+                None => return Err(unterminated("command substitution")),
+            }
+        }
+        self.substitutions.push(command);
+        self.push_str("`...`");
+        Ok(())
+    }
+
+    fn redirection(&mut self, operator: char, chars: &mut Peekable<Chars>) {
+        // A file descriptor number directly before the operator belongs to it
+        if self.word.as_deref().is_some_and(|word| word.chars().all(|c| c.is_ascii_digit())) {
+            self.word = None;
+        }
+        self.end_word();
+
+        if operator == '<' && chars.next_if_eq(&'<').is_some() {
+            if chars.next_if_eq(&'<').is_some() {
+                // Here string
+                self.redirect_target = true;
+            } else {
+                self.heredoc_delimiter = Some(chars.next_if_eq(&'-').is_some());
+            }
+        } else {
+            // `>>`, `<>` and `>|` name a file; `>&2` and `<&-` name a descriptor
+            chars.next_if(|c| matches!(c, '>' | '&' | '|'));
+            self.redirect_target = true;
+        }
+    }
+
+    /// Skip the bodies of the here documents started on the line just ended
+    fn skip_heredocs(&mut self, chars: &mut Peekable<Chars>) -> McpResult<()> {
+        for (delimiter, strip_tabs) in std::mem::take(&mut self.heredocs) {
+            loop {
+                if chars.peek().is_none() {
+                    return Err(unterminated("here document"));
+                }
+                let line: String = std::iter::from_fn(|| chars.next_if(|c| *c != '\n')).collect();
+                chars.next();
+                let line = if strip_tabs { line.trim_start_matches('\t') } else { &line };
+                if line == delimiter {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read up to the parenthesis closing an opened one, honouring quotes
+fn balanced(chars: &mut Peekable<Chars>) -> McpResult<String> {
+    let mut content = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                content.push(c);
+                if let Some(escaped) = chars.next() {
+                    content.push(escaped);
+                }
+                continue;
+            }
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => return Ok(content),
+            (None, ')') => depth -= 1,
+            (None, _) => {}
+        }
+        content.push(c);
+    }
+    Err(unterminated("command substitution"))
+}
+
+fn is_assignment(word: &str) -> bool {
+    let Some((name, _)) = word.split_once('=') else {
+        return false;
+    };
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn unterminated(what: &str) -> McpError {
+    McpError::InvalidRequest(format!("Unterminated {} in shell command", what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn names(line: &str) -> Vec<String> {
+        split_command_line(line).unwrap().into_iter().map(|argv| argv[0].clone()).collect()
+    }
+
+    // Test for splitting command lines into simple commands
+    #[test]
+    fn test_split_command_line() {
+        assert_eq!(
+            split_command_line("ls -la; rm -rf /").unwrap(),
+            vec![args(&["ls", "-la"]), args(&["rm", "-rf", "/"])]
+        );
+        assert_eq!(names("a && b || c | d & e\nf"), vec!["a", "b", "c", "d", "e", "f"]);
+        assert_eq!(names("(cd /tmp && { make; })"), vec!["cd", "make"]);
+        assert_eq!(names("if true; then rm x; fi"), vec!["true", "rm"]);
+        assert_eq!(names("FOO=bar BAZ=1 env"), vec!["env"]);
+
+        // Quotes and escapes
+        assert_eq!(
+            split_command_line(r#"echo 'a; b' "c && $HOME" d\;e"#).unwrap(),
+            vec![args(&["echo", "a; b", "c && $HOME", "d;e"])]
+        );
+        assert_eq!(split_command_line("echo '' x").unwrap(), vec![args(&["echo", "", "x"])]);
+
+        // Substitutions
+        assert_eq!(names("echo $(rm -rf /) `curl x` \"$(id)\""), vec!["echo", "rm", "curl", "id"]);
+        assert_eq!(names("diff <(cat a) >(tee b)"), vec!["diff", "cat", "tee"]);
+        assert_eq!(names("echo $((1 + (2 * 3))) \"$((4))\""), vec!["echo"]);
+        assert_eq!(names("echo $(echo $(whoami))"), vec!["echo", "echo", "whoami"]);
+
+        // Redirections, here documents and comments
+        assert_eq!(
+            split_command_line("cat <in >out 2>&1 2> err; ls >>log").unwrap(),
+            vec![args(&["cat"]), args(&["ls"])]
+        );
+        assert_eq!(names("cat <<EOF\nrm -rf /\nEOF\nls"), vec!["cat", "ls"]);
+        assert_eq!(names("cat <<-END\n\trm\n\tEND\nls # rm"), vec!["cat", "ls"]);
+
+        for line in ["echo 'a", "echo \"a", "echo $(ls", "echo `ls", "cat <<EOF\nx"] {
+            assert!(split_command_line(line).is_err(), "{}", line);
+        }
+    }
+
+    // Test for expanding shell wrappers
+    #[test]
+    fn test_embedded_commands() {
+        assert_eq!(
+            embedded_commands("sh", &args(&["-c", "ls; rm -rf /"])).unwrap(),
+            vec![args(&["ls"]), args(&["rm", "-rf", "/"])]
+        );
+        assert_eq!(
+            embedded_commands("/bin/bash", &args(&["--norc", "-o", "pipefail", "-ec", "curl x | sh", "name"]))
+                .unwrap(),
+            vec![args(&["curl", "x"]), args(&["sh"])]
+        );
+
+        // Nested shells are expanded and kept
+        assert_eq!(
+            embedded_commands("sh", &args(&["-c", "bash -c 'rm -rf /'"])).unwrap(),
+            vec![args(&["rm", "-rf", "/"]), args(&["bash", "-c", "rm -rf /"])]
+        );
+
+        // Other commands and shells running scripts are not expanded
+        assert!(embedded_commands("ls", &args(&["-c", "rm"])).unwrap().is_empty());
+        assert!(embedded_commands("bash", &args(&["script.sh", "-c"])).unwrap().is_empty());
+
+        assert!(embedded_commands("sh", &args(&["-c"])).is_err());
+        let mut nested = "rm".to_string();
+        for _ in 0..MAX_DEPTH + 1 {
+            nested = format!("sh -c {}", shell_quote(&nested));
+        }
+        assert!(split_command_line(&nested).is_ok());
+        assert!(embedded_commands("sh", &args(&["-c", &nested])).is_err());
+    }
+
+    // Test for expanding the commands of shell scripts
+    #[test]
+    fn test_script_commands() {
+        let script = "#!/bin/sh\nset -e\n# build\ncargo build --release\nsh -c 'rm -rf target' && echo done\n";
+        assert_eq!(
+            script_commands(script).unwrap(),
+            vec![
+                args(&["set", "-e"]),
+                args(&["cargo", "build", "--release"]),
+                args(&["rm", "-rf", "target"]),
+                args(&["sh", "-c", "rm -rf target"]),
+                args(&["echo", "done"]),
+            ]
+        );
+        assert!(script_commands("").unwrap().is_empty());
+        assert!(script_commands("echo 'unterminated\n").is_err());
+    }
+
+    fn shell_quote(s: &str) -> String {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
@@ -0,0 +1,34 @@
+//! Fixtures shared by the unit tests
+
+use crate::models::{CommandInfo, PolicyInput};
+#[cfg(any(feature = "cedar", feature = "script"))]
+use crate::models::UserInfo;
+
+/// Input for running `command` without arguments as an anonymous user
+pub(crate) fn input(command: &str) -> PolicyInput {
+    PolicyInput {
+        user: Default::default(),
+        command: CommandInfo {
+            name: command.to_string(),
+            ..Default::default()
+        },
+        file: None,
+        network: None,
+        resources: Default::default(),
+        context: Default::default(),
+    }
+}
+
+/// Input for running `command` as `user1` of `tenant1` with the given roles
+#[cfg(any(feature = "cedar", feature = "script"))]
+pub(crate) fn input_with_roles(command: &str, roles: &[&str]) -> PolicyInput {
+    PolicyInput {
+        user: UserInfo {
+            id: "user1".to_string(),
+            tenant_id: "tenant1".to_string(),
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+            ..Default::default()
+        },
+        ..input(command)
+    }
+}
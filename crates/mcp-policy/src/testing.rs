@@ -0,0 +1,326 @@
+//! Policy test harness
+//!
+//! Runs YAML test cases against an evaluator so that policy changes can be gated in CI.
+//! Each `.yaml`/`.yml` file in a directory is a suite:
+//!
+//! ```yaml
+//! name: command policies
+//! cases:
+//!   - name: ls is allowed and cacheable
+//!     input:
+//!       user: { id: user1, tenant_id: tenant1, roles: [user] }
+//!       command: { name: ls }
+//!     expect:
+//!       allow: true
+//!       metadata: { cacheable: true }
+//!   - name: rm is denied
+//!     input:
+//!       command: { name: rm }
+//!     expect:
+//!       allow: false
+//!       reasons: ["禁止されています"]
+//! ```
+//!
+//! `input` is a `PolicyInput`. Expected `warnings` and `reasons` must each be contained in
+//! (a substring of) at least one actual warning or reason; `no_warnings: true` requires
+//! that there are none. Expected `metadata` entries must be equal to the actual ones.
+
+use crate::engine::AsyncPolicyEvaluator;
+use crate::models::{PolicyDecision, PolicyInput};
+use mcp_common::error::{McpError, McpResult};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Expected outcome of a test case
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyExpectation {
+    /// Expected allow/deny
+    pub allow: bool,
+    /// Fragments that must appear in the warnings
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Require that there are no warnings
+    #[serde(default)]
+    pub no_warnings: bool,
+    /// Fragments that must appear in the denial reasons
+    #[serde(default)]
+    pub reasons: Vec<String>,
+    /// Metadata entries that must be present with these values
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+/// Single test case
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyTestCase {
+    /// Case name
+    pub name: String,
+    /// Evaluation input
+    pub input: PolicyInput,
+    /// Expected outcome
+    pub expect: PolicyExpectation,
+}
+
+impl PolicyTestCase {
+    /// Compare a decision with the expectation and describe all mismatches
+    pub fn check(&self, decision: &PolicyDecision) -> Vec<String> {
+        let expect = &self.expect;
+        let mut problems = Vec::new();
+
+        if decision.allow != expect.allow {
+            problems.push(format!(
+                "expected {} but was {} (reasons: {:?})",
+                verdict(expect.allow),
+                verdict(decision.allow),
+                decision.reasons
+            ));
+        }
+
+        for fragment in &expect.warnings {
+            if !decision.warnings.iter().any(|warning| warning.contains(fragment.as_str())) {
+                problems.push(format!("missing warning '{}' (warnings: {:?})", fragment, decision.warnings));
+            }
+        }
+        if expect.no_warnings && !decision.warnings.is_empty() {
+            problems.push(format!("expected no warnings but got {:?}", decision.warnings));
+        }
+
+        for fragment in &expect.reasons {
+            if !decision.reasons.iter().any(|reason| reason.contains(fragment.as_str())) {
+                problems.push(format!("missing reason '{}' (reasons: {:?})", fragment, decision.reasons));
+            }
+        }
+
+        let mut keys: Vec<&String> = expect.metadata.keys().collect();
+        keys.sort();
+        for key in keys {
+            let expected = &expect.metadata[key];
+            match decision.metadata.get(key) {
+                Some(actual) if actual == expected => {}
+                actual => problems.push(format!("metadata '{}': expected {} but was {:?}", key, expected, actual)),
+            }
+        }
+
+        problems
+    }
+}
+
+fn verdict(allow: bool) -> &'static str {
+    if allow {
+        "allow"
+    } else {
+        "deny"
+    }
+}
+
+/// Test cases loaded from one YAML file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyTestSuite {
+    /// Suite name (defaults to the file name)
+    #[serde(default)]
+    pub name: String,
+    /// Test cases
+    pub cases: Vec<PolicyTestCase>,
+    /// File the suite was loaded from
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+/// Failed test case
+#[derive(Debug, Clone)]
+pub struct PolicyTestFailure {
+    /// Suite name
+    pub suite: String,
+    /// Case name
+    pub case: String,
+    /// Mismatches or the evaluation error
+    pub problems: Vec<String>,
+}
+
+/// Result of running test suites
+#[derive(Debug, Clone, Default)]
+pub struct PolicyTestReport {
+    /// Number of cases run
+    pub total: usize,
+    /// Number of passed cases
+    pub passed: usize,
+    /// Failed cases
+    pub failures: Vec<PolicyTestFailure>,
+}
+
+impl PolicyTestReport {
+    /// Whether every case passed
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl fmt::Display for PolicyTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for failure in &self.failures {
+            writeln!(f, "FAILED {} :: {}", failure.suite, failure.case)?;
+            for problem in &failure.problems {
+                writeln!(f, "    {}", problem)?;
+            }
+        }
+        write!(
+            f,
+            "{} passed, {} failed, {} total",
+            self.passed,
+            self.failures.len(),
+            self.total
+        )
+    }
+}
+
+impl PolicyTestSuite {
+    /// Load a suite from a YAML file
+    pub fn load(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| McpError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+        let mut suite: Self = serde_yaml::from_str(&content)
+            .map_err(|e| McpError::InvalidRequest(format!("Invalid policy test file {}: {}", path.display(), e)))?;
+
+        if suite.name.is_empty() {
+            suite.name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+        suite.path = path.to_path_buf();
+        Ok(suite)
+    }
+
+    /// Load all suites in a directory (recursively, in file name order)
+    pub fn load_dir(dir: impl AsRef<Path>) -> McpResult<Vec<Self>> {
+        let mut files = Vec::new();
+        let mut pending = vec![dir.as_ref().to_path_buf()];
+
+        while let Some(current) = pending.pop() {
+            let entries = std::fs::read_dir(&current).map_err(|e| {
+                McpError::Internal(format!("Failed to read test directory {}: {}", current.display(), e))
+            })?;
+            for entry in entries {
+                let path = entry
+                    .map_err(|e| McpError::Internal(format!("Failed to read test directory entry: {}", e)))?
+                    .path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "yaml" || ext == "yml") {
+                    files.push(path);
+                }
+            }
+        }
+
+        files.sort();
+        files.iter().map(Self::load).collect()
+    }
+
+    /// Run every suite in a directory against an evaluator
+    ///
+    /// Fails only if the suites cannot be loaded; failing cases are listed in the report.
+    pub async fn run(dir: impl AsRef<Path>, evaluator: &dyn AsyncPolicyEvaluator) -> McpResult<PolicyTestReport> {
+        let dir = dir.as_ref();
+        let suites = Self::load_dir(dir)?;
+        if suites.is_empty() {
+            return Err(McpError::NotFound(format!("No policy test files found in {}", dir.display())));
+        }
+
+        let mut report = PolicyTestReport::default();
+        for suite in &suites {
+            suite.run_cases(evaluator, &mut report).await;
+        }
+        Ok(report)
+    }
+
+    /// Run the cases of this suite and add the results to a report
+    pub async fn run_cases(&self, evaluator: &dyn AsyncPolicyEvaluator, report: &mut PolicyTestReport) {
+        for case in &self.cases {
+            report.total += 1;
+
+            let problems = match evaluator.evaluate(&case.input).await {
+                Ok(decision) => case.check(&decision),
+                Err(e) => vec![format!("evaluation failed: {}", e)],
+            };
+
+            if problems.is_empty() {
+                report.passed += 1;
+            } else {
+                report.failures.push(PolicyTestFailure {
+                    suite: self.name.clone(),
+                    case: case.name.clone(),
+                    problems,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{load_policy_dir, StubPolicyEvaluator};
+
+    fn repo_path(path: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../..").join(path)
+    }
+
+    // The shipped test cases gate both policy sets
+    #[tokio::test]
+    async fn test_repo_policy_suites() {
+        let mut policy_sets = vec!["policies/rego"];
+        if cfg!(feature = "cedar") {
+            policy_sets.push("policies/cedar");
+        }
+        for policies in policy_sets {
+            let evaluator = load_policy_dir(&repo_path(policies)).unwrap();
+            let report = PolicyTestSuite::run(repo_path("policies/tests"), &evaluator).await.unwrap();
+            assert!(report.total > 0);
+            assert!(report.is_success(), "{}:\n{}", policies, report);
+        }
+    }
+
+    // Test for reporting mismatches
+    #[tokio::test]
+    async fn test_failures_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("stub.yaml"),
+            r#"
+cases:
+  - name: ls is allowed
+    input: { command: { name: ls } }
+    expect: { allow: true, warnings: [stub policy engine] }
+  - name: wrong expectation
+    input: { command: { name: rm } }
+    expect:
+      allow: true
+      warnings: [missing]
+      metadata: { cacheable: true }
+"#,
+        )
+        .unwrap();
+
+        let report = PolicyTestSuite::run(dir.path(), &StubPolicyEvaluator::default()).await.unwrap();
+        assert_eq!(report.total, 2);
+        assert_eq!(report.passed, 1);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].suite, "stub");
+        assert_eq!(report.failures[0].case, "wrong expectation");
+        assert_eq!(report.failures[0].problems.len(), 3);
+        assert!(report.to_string().contains("1 passed, 1 failed, 2 total"));
+
+        // Unknown fields are rejected so that typos do not silently pass
+        std::fs::write(dir.path().join("typo.yaml"), "cases:\n  - name: x\n    input: {}\n    expect: { alow: true }\n").unwrap();
+        assert!(PolicyTestSuite::run(dir.path(), &StubPolicyEvaluator::default()).await.is_err());
+
+        let empty = tempfile::tempdir().unwrap();
+        assert!(PolicyTestSuite::run(empty.path(), &StubPolicyEvaluator::default()).await.is_err());
+    }
+}
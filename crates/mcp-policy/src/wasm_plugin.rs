@@ -0,0 +1,254 @@
+//! Policy plugins compiled to WebAssembly
+//!
+//! Besides Rego and Cedar, policies can be written in any language that compiles to
+//! WebAssembly (e.g. Rust or Go). A plugin is a module without imports that exports:
+//!
+//! * `memory` - the linear memory shared with the gateway
+//! * `alloc(len: i32) -> i32` - reserves `len` bytes for the input and returns their address
+//! * `evaluate(ptr: i32, len: i32) -> i64` - evaluates the `PolicyInput` JSON at `ptr` and
+//!   returns the address (upper 32 bits) and length (lower 32 bits) of the `PolicyDecision`
+//!   JSON it produced
+//!
+//! Every evaluation runs in a fresh instance, so plugins cannot keep state between
+//! requests. Plugins are limited in the fuel (roughly the number of executed instructions)
+//! and the memory they may use; exceeding a limit fails the evaluation.
+
+use crate::engine::PolicyEvaluator;
+use crate::models::{PolicyDecision, PolicyInput};
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{get_env_var_or, parse_positive};
+use std::fmt;
+use std::path::Path;
+use wasmtime::{Config, Engine, InstancePre, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+
+/// Execution limits of a policy plugin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmPluginConfig {
+    /// Fuel available to one evaluation
+    pub fuel: u64,
+    /// Maximum size of the linear memory in bytes
+    pub memory_limit: usize,
+}
+
+impl Default for WasmPluginConfig {
+    fn default() -> Self {
+        Self {
+            fuel: 10_000_000,
+            memory_limit: 16 * 1024 * 1024,
+        }
+    }
+}
+
+impl WasmPluginConfig {
+    /// Load the limits from environment variables
+    ///
+    /// * `MCP_POLICY_WASM_FUEL` - fuel available to one evaluation (default 10000000)
+    /// * `MCP_POLICY_WASM_MEMORY_LIMIT` - maximum memory in bytes (default 16 MiB)
+    pub fn from_env() -> McpResult<Self> {
+        let defaults = Self::default();
+        Ok(Self {
+            fuel: parse_env("MCP_POLICY_WASM_FUEL", defaults.fuel)?,
+            memory_limit: parse_env("MCP_POLICY_WASM_MEMORY_LIMIT", defaults.memory_limit)?,
+        })
+    }
+}
+
+fn parse_env<T>(name: &str, default: T) -> McpResult<T>
+where
+    T: std::str::FromStr + Default + PartialOrd + ToString,
+{
+    parse_positive(name, &get_env_var_or(name, &default.to_string()))
+}
+
+/// Evaluator running a WebAssembly policy plugin
+pub struct WasmPluginEvaluator {
+    engine: Engine,
+    instance_pre: InstancePre<StoreLimits>,
+    config: WasmPluginConfig,
+}
+
+impl fmt::Debug for WasmPluginEvaluator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasmPluginEvaluator").field("config", &self.config).finish()
+    }
+}
+
+impl WasmPluginEvaluator {
+    /// Compile a plugin from its binary (or text) format
+    pub fn new(module: &[u8], config: WasmPluginConfig) -> McpResult<Self> {
+        let mut engine_config = Config::new();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config)
+            .map_err(|e| McpError::Internal(format!("Failed to create WASM engine: {}", e)))?;
+
+        let module = Module::new(&engine, module)
+            .map_err(|e| McpError::Internal(format!("Failed to compile WASM policy plugin: {}", e)))?;
+        for export in ["memory", "alloc", "evaluate"] {
+            if module.get_export(export).is_none() {
+                return Err(McpError::Internal(format!(
+                    "WASM policy plugin does not export '{}'",
+                    export
+                )));
+            }
+        }
+
+        // Plugins get no host functions, so any import fails here
+        let instance_pre = Linker::new(&engine)
+            .instantiate_pre(&module)
+            .map_err(|e| McpError::Internal(format!("WASM policy plugins must not have imports: {}", e)))?;
+
+        Ok(Self {
+            engine,
+            instance_pre,
+            config,
+        })
+    }
+
+    /// Load a plugin from a `.wasm` (or `.wat`) file
+    pub fn from_file(path: impl AsRef<Path>, config: WasmPluginConfig) -> McpResult<Self> {
+        let path = path.as_ref();
+        let module = std::fs::read(path).map_err(|e| {
+            McpError::Internal(format!("Failed to read WASM policy plugin {}: {}", path.display(), e))
+        })?;
+        Self::new(&module, config)
+    }
+
+    /// Execution limits
+    pub fn config(&self) -> WasmPluginConfig {
+        self.config
+    }
+
+    // Run the plugin in a fresh instance and return the decision JSON
+    fn run(&self, input: &[u8]) -> McpResult<Vec<u8>> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.config.memory_limit)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.config.fuel).map_err(plugin_error)?;
+
+        let instance = self.instance_pre.instantiate(&mut store).map_err(plugin_error)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| McpError::Internal("WASM policy plugin export 'memory' is not a memory".to_string()))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "alloc")
+            .map_err(plugin_error)?;
+        let evaluate = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, "evaluate")
+            .map_err(plugin_error)?;
+
+        let len = i32::try_from(input.len())
+            .map_err(|_| McpError::Internal("Policy input is too large for the WASM policy plugin".to_string()))?;
+        let ptr = alloc.call(&mut store, len).map_err(plugin_error)?;
+        memory.write(&mut store, ptr as u32 as usize, input).map_err(plugin_error)?;
+
+        let result = evaluate.call(&mut store, (ptr, len)).map_err(plugin_error)? as u64;
+        let mut output = vec![0; (result & 0xffff_ffff) as usize];
+        memory.read(&store, (result >> 32) as usize, &mut output).map_err(plugin_error)?;
+        Ok(output)
+    }
+}
+
+fn plugin_error(e: impl Into<wasmtime::Error>) -> McpError {
+    let e = e.into();
+    if e.downcast_ref::<Trap>() == Some(&Trap::OutOfFuel) {
+        return McpError::Internal("WASM policy plugin exceeded its fuel limit".to_string());
+    }
+    McpError::Internal(format!("WASM policy plugin failed: {:#}", e))
+}
+
+impl PolicyEvaluator for WasmPluginEvaluator {
+    fn evaluate(&self, input: &PolicyInput) -> McpResult<PolicyDecision> {
+        let input = serde_json::to_vec(input)
+            .map_err(|e| McpError::Internal(format!("Failed to serialize input: {}", e)))?;
+        let output = self.run(&input)?;
+        serde_json::from_slice(&output)
+            .map_err(|e| McpError::Internal(format!("Invalid decision from WASM policy plugin: {}", e)))
+    }
+
+    fn name(&self) -> &str {
+        "wasm-plugin"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::input;
+
+    // Plugin returning a fixed decision; `body` is inserted into `evaluate`
+    fn plugin(decision: &str, body: &str) -> String {
+        format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (data (i32.const 0) "{decision}")
+                (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+                (func (export "evaluate") (param i32 i32) (result i64)
+                    {body}
+                    (i64.const {len})))"#,
+            decision = decision.replace('"', "\\\""),
+            body = body,
+            len = decision.len(),
+        )
+    }
+
+    // Test for evaluating a plugin
+    #[test]
+    fn test_evaluate() {
+        let decision = r#"{"allow":false,"reasons":["denied by plugin"]}"#;
+        let evaluator = WasmPluginEvaluator::new(plugin(decision, "").as_bytes(), WasmPluginConfig::default()).unwrap();
+
+        let decision = evaluator.evaluate(&input("ls")).unwrap();
+        assert!(!decision.allow);
+        assert_eq!(decision.reasons, vec!["denied by plugin"]);
+        assert_eq!(PolicyEvaluator::name(&evaluator), "wasm-plugin");
+
+        // Invalid decisions fail the evaluation
+        let evaluator = WasmPluginEvaluator::new(plugin("allow", "").as_bytes(), WasmPluginConfig::default()).unwrap();
+        assert!(evaluator.evaluate(&input("ls")).is_err());
+    }
+
+    // Test for the fuel and memory limits of plugins
+    #[test]
+    fn test_limits() {
+        let endless = plugin(r#"{"allow":true}"#, "(loop $again (br $again))");
+        let evaluator = WasmPluginEvaluator::new(endless.as_bytes(), WasmPluginConfig::default()).unwrap();
+        let error = evaluator.evaluate(&input("ls")).unwrap_err();
+        assert!(error.to_string().contains("fuel"), "{}", error);
+
+        // The plugin traps when it cannot grow its memory by four pages
+        let growing = plugin(
+            r#"{"allow":true}"#,
+            "(if (i32.eq (memory.grow (i32.const 4)) (i32.const -1)) (then unreachable))",
+        );
+        let config = WasmPluginConfig {
+            memory_limit: 1024 * 1024,
+            ..Default::default()
+        };
+        let evaluator = WasmPluginEvaluator::new(growing.as_bytes(), config).unwrap();
+        assert!(evaluator.evaluate(&input("ls")).unwrap().allow);
+
+        let config = WasmPluginConfig {
+            memory_limit: 128 * 1024,
+            ..Default::default()
+        };
+        let evaluator = WasmPluginEvaluator::new(growing.as_bytes(), config).unwrap();
+        assert!(evaluator.evaluate(&input("ls")).is_err());
+    }
+
+    // Test for rejecting plugins that do not follow the ABI
+    #[test]
+    fn test_invalid_plugins() {
+        let config = WasmPluginConfig::default();
+        assert!(WasmPluginEvaluator::new(b"not wasm", config).is_err());
+        assert!(WasmPluginEvaluator::new(b"(module (memory (export \"memory\") 1))", config).is_err());
+
+        let importing = plugin(r#"{"allow":true}"#, "").replace(
+            "(module",
+            "(module (import \"env\" \"log\" (func $log (param i32)))",
+        );
+        assert!(WasmPluginEvaluator::new(importing.as_bytes(), config).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! Policy hot reload
+//!
+//! Watches a policy directory and swaps the evaluator of a `PolicyEngine` when policy
+//! files change. Changes are debounced so that editors writing several files (or writing
+//! through a temporary file and a rename) trigger a single reload. A policy set that fails
+//! to load is rejected and the previous policies stay active.
+
+use crate::engine::{load_policy_dir, PolicyEngine};
+use mcp_common::error::{McpError, McpResult};
+use notify::{Event, RecursiveMode, Watcher};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// Quiet period after the last change before policies are reloaded
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Handle of a running policy directory watcher
+///
+/// Dropping the handle stops watching.
+pub struct PolicyWatcher {
+    _watcher: notify::RecommendedWatcher,
+    policy_dir: PathBuf,
+    reloads: Arc<AtomicU64>,
+}
+
+impl fmt::Debug for PolicyWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolicyWatcher")
+            .field("policy_dir", &self.policy_dir)
+            .field("reloads", &self.reload_count())
+            .finish()
+    }
+}
+
+impl PolicyWatcher {
+    pub(crate) fn start(engine: PolicyEngine, dir: &Path) -> McpResult<Self> {
+        Self::spawn(engine, dir, None)
+    }
+
+    pub(crate) fn start_for_tenant(engine: PolicyEngine, dir: &Path, tenant_id: String) -> McpResult<Self> {
+        Self::spawn(engine, dir, Some(tenant_id))
+    }
+
+    // Reload into the evaluator of the tenant, or into the default evaluator
+    fn spawn(engine: PolicyEngine, dir: &Path, tenant_id: Option<String>) -> McpResult<Self> {
+        let (tx, rx) = mpsc::channel::<Event>();
+
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            match result {
+                Ok(event) => {
+                    let _ = tx.send(event);
+                }
+                Err(e) => error!("Policy watcher error: {}", e),
+            }
+        })
+        .map_err(|e| McpError::Internal(format!("Failed to create policy watcher: {}", e)))?;
+
+        watcher
+            .watch(dir, RecursiveMode::Recursive)
+            .map_err(|e| McpError::Internal(format!("Failed to watch {}: {}", dir.display(), e)))?;
+
+        let reloads = Arc::new(AtomicU64::new(0));
+        let policy_dir = dir.to_path_buf();
+        {
+            let reloads = reloads.clone();
+            let policy_dir = policy_dir.clone();
+            std::thread::Builder::new()
+                .name("policy-watcher".to_string())
+                .spawn(move || reload_loop(engine, tenant_id, policy_dir, rx, reloads))
+                .map_err(|e| McpError::Internal(format!("Failed to start policy watcher: {}", e)))?;
+        }
+
+        info!("Watching {} for policy changes", dir.display());
+
+        Ok(Self {
+            _watcher: watcher,
+            policy_dir,
+            reloads,
+        })
+    }
+
+    /// Directory being watched
+    pub fn policy_dir(&self) -> &Path {
+        &self.policy_dir
+    }
+
+    /// Number of successful reloads
+    pub fn reload_count(&self) -> u64 {
+        self.reloads.load(Ordering::SeqCst)
+    }
+}
+
+fn reload_loop(
+    engine: PolicyEngine,
+    tenant_id: Option<String>,
+    dir: PathBuf,
+    rx: mpsc::Receiver<Event>,
+    reloads: Arc<AtomicU64>,
+) {
+    // The channel is closed when the watcher is dropped
+    while let Ok(event) = rx.recv() {
+        if !is_policy_event(&event) {
+            continue;
+        }
+
+        // Wait until the directory has been quiet for the debounce period
+        loop {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        match load_policy_dir(&dir) {
+            Ok(evaluator) => {
+                match &tenant_id {
+                    Some(tenant_id) => engine.replace_tenant_evaluator(tenant_id.clone(), evaluator),
+                    None => engine.replace_evaluator(evaluator),
+                }
+                reloads.fetch_add(1, Ordering::SeqCst);
+                engine.observe_reload("watcher", true);
+                info!("Reloaded policies from {}", dir.display());
+            }
+            Err(e) => {
+                engine.observe_reload("watcher", false);
+                error!("Policy reload failed, keeping the previous policies: {}", e);
+            }
+        }
+    }
+
+    debug!("Policy watcher for {} stopped", dir.display());
+}
+
+fn is_policy_event(event: &Event) -> bool {
+    if event.kind.is_access() {
+        return false;
+    }
+
+    event.paths.iter().any(|path| {
+        path.extension().is_some_and(|ext| ext == "rego" || ext == "cedar")
+            || path
+                .file_name()
+                .is_some_and(|name| name == "data.json" || name == "entities.json")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::PolicyEngine;
+    use crate::test_support::input;
+    use std::time::{Duration, Instant};
+
+    const POLICY: &str = "package mcp\nimport future.keywords.if\nimport future.keywords.in\ndefault allow = false\nallow if { input.command.name in {\"ls\"} }\n";
+
+    // Test for reloading on change and keeping policies on a broken update
+    #[tokio::test]
+    async fn test_hot_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("policy.rego"), POLICY).unwrap();
+
+        let engine = PolicyEngine::from_policy_dir(dir.path()).unwrap();
+        // Clones share the active evaluator
+        let clone = engine.clone();
+        let watcher = engine.watch_policy_dir(dir.path()).unwrap();
+        assert!(clone.check_command_execution(&input("make")).await.is_err());
+
+        std::fs::write(dir.path().join("policy.rego"), POLICY.replace("{\"ls\"}", "{\"ls\", \"make\"}")).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while watcher.reload_count() == 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(watcher.reload_count(), 1);
+        assert!(clone.check_command_execution(&input("make")).await.is_ok());
+
+        // A broken policy is rejected and the previous one stays active
+        std::fs::write(dir.path().join("policy.rego"), "package mcp\nallow if {").unwrap();
+        std::thread::sleep(Duration::from_secs(1));
+        assert_eq!(watcher.reload_count(), 1);
+        assert!(clone.check_command_execution(&input("make")).await.is_ok());
+    }
+}
@@ -0,0 +1,266 @@
+//! Webhook notifications of policy violations
+//!
+//! `PolicyEngine` hands every policy violation to the configured [`WebhookNotifier`],
+//! which POSTs a [`ViolationEvent`] as JSON to each webhook URL (e.g. a Slack workflow or
+//! a SIEM collector). Delivery happens on a background thread so that evaluations never
+//! wait for a webhook; failed deliveries are retried with exponential backoff and then
+//! dropped with an error log.
+
+use crate::models::PolicyInput;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{current_timestamp_ms, get_env_var_or};
+use serde::Serialize;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+/// Maximum number of events waiting for delivery
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Payload sent to the webhooks
+#[derive(Debug, Clone, Serialize)]
+pub struct ViolationEvent {
+    /// Time of the violation (milliseconds since the Unix epoch)
+    pub timestamp_ms: u64,
+    /// User ID
+    pub user_id: String,
+    /// Tenant ID
+    pub tenant_id: String,
+    /// Checked action (`command_execution`, `file_access`, `network_access`,
+    /// `environment` or `resource_limits`)
+    pub action: String,
+    /// Command name
+    pub command: String,
+    /// Denial reasons
+    pub reasons: Vec<String>,
+}
+
+impl ViolationEvent {
+    /// Event for a violation of an input
+    pub fn new(input: &PolicyInput, action: &str, reasons: Vec<String>) -> Self {
+        Self {
+            timestamp_ms: current_timestamp_ms(),
+            user_id: input.user.id.clone(),
+            tenant_id: input.user.tenant_id.clone(),
+            action: action.to_string(),
+            command: input.command.name.clone(),
+            reasons,
+        }
+    }
+}
+
+/// Webhook settings
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// Webhook URLs
+    pub urls: Vec<String>,
+    /// Retries after a failed delivery
+    pub max_retries: u32,
+    /// Wait before the first retry (doubled for every further retry)
+    pub initial_backoff: Duration,
+    /// Timeout of a single delivery
+    pub timeout: Duration,
+}
+
+impl WebhookConfig {
+    /// Create settings for webhook URLs with default values
+    pub fn new(urls: Vec<String>) -> Self {
+        Self {
+            urls,
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Build the settings from environment variables (`None` when no URL is configured)
+    ///
+    /// * `MCP_POLICY_WEBHOOK_URLS` - comma separated webhook URLs
+    /// * `MCP_POLICY_WEBHOOK_RETRIES` - retries after a failed delivery (default 3)
+    /// * `MCP_POLICY_WEBHOOK_TIMEOUT_MS` - timeout of a single delivery (default 5000)
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let urls: Vec<String> = get_env_var_or("MCP_POLICY_WEBHOOK_URLS", "")
+            .split(',')
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect();
+        if urls.is_empty() {
+            return Ok(None);
+        }
+        let mut config = Self::new(urls);
+
+        if let Ok(value) = std::env::var("MCP_POLICY_WEBHOOK_RETRIES") {
+            config.max_retries = value.trim().parse().map_err(|_| {
+                McpError::InvalidRequest(format!("MCP_POLICY_WEBHOOK_RETRIES must be a number: '{}'", value))
+            })?;
+        }
+        if let Ok(value) = std::env::var("MCP_POLICY_WEBHOOK_TIMEOUT_MS") {
+            let millis = value.trim().parse::<u64>().ok().filter(|millis| *millis > 0).ok_or_else(|| {
+                McpError::InvalidRequest(format!(
+                    "MCP_POLICY_WEBHOOK_TIMEOUT_MS must be a positive number of milliseconds: '{}'",
+                    value
+                ))
+            })?;
+            config.timeout = Duration::from_millis(millis);
+        }
+
+        Ok(Some(config))
+    }
+}
+
+/// Sends violation events to webhooks in the background
+///
+/// The delivery thread stops when the notifier is dropped.
+#[derive(Debug)]
+pub struct WebhookNotifier {
+    sender: SyncSender<ViolationEvent>,
+}
+
+impl WebhookNotifier {
+    /// Start the delivery thread
+    pub fn start(config: WebhookConfig) -> McpResult<Self> {
+        let (sender, receiver) = mpsc::sync_channel::<ViolationEvent>(QUEUE_CAPACITY);
+        let agent = ureq::AgentBuilder::new().timeout(config.timeout).build();
+
+        std::thread::Builder::new()
+            .name("policy-webhook".to_string())
+            .spawn(move || {
+                for event in receiver {
+                    for url in &config.urls {
+                        deliver(&agent, &config, url, &event);
+                    }
+                }
+            })
+            .map_err(|e| McpError::Internal(format!("Failed to start the policy webhook thread: {}", e)))?;
+
+        Ok(Self { sender })
+    }
+
+    /// Queue an event for delivery
+    ///
+    /// Events are dropped with a warning when the queue is full.
+    pub fn notify(&self, event: ViolationEvent) {
+        match self.sender.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(event)) => {
+                warn!("Policy webhook queue is full, dropping the violation of '{}'", event.command);
+            }
+            Err(TrySendError::Disconnected(_)) => error!("Policy webhook thread has stopped"),
+        }
+    }
+}
+
+fn deliver(agent: &ureq::Agent, config: &WebhookConfig, url: &str, event: &ViolationEvent) {
+    let mut backoff = config.initial_backoff;
+    for attempt in 0..=config.max_retries {
+        match agent.post(url).send_json(event) {
+            Ok(_) => {
+                debug!("Delivered policy violation to webhook {}", url);
+                return;
+            }
+            Err(e) if attempt < config.max_retries => {
+                warn!("Policy webhook {} failed (attempt {}), retrying in {:?}: {}", url, attempt + 1, backoff, e);
+                std::thread::sleep(backoff);
+                backoff *= 2;
+            }
+            Err(e) => error!(
+                "Giving up delivering a policy violation to webhook {} after {} attempt(s): {}",
+                url,
+                attempt + 1,
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Webhook stand-in answering the first `failures` requests with 503; reports each body
+    pub(crate) fn start_webhook(failures: usize) -> (String, mpsc::Receiver<serde_json::Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            for (index, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let status = if index < failures { "503 Service Unavailable" } else { "200 OK" };
+                write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).unwrap();
+                let _ = tx.send(serde_json::from_slice(&body).unwrap());
+            }
+        });
+
+        (url, rx)
+    }
+
+    fn event(command: &str) -> ViolationEvent {
+        ViolationEvent {
+            timestamp_ms: 1,
+            user_id: "alice".to_string(),
+            tenant_id: "tenant1".to_string(),
+            action: "command_execution".to_string(),
+            command: command.to_string(),
+            reasons: vec!["denied".to_string()],
+        }
+    }
+
+    // Test for retrying failed deliveries
+    #[test]
+    fn test_delivery_with_retries() {
+        let (url, requests) = start_webhook(2);
+        let mut config = WebhookConfig::new(vec![url]);
+        config.initial_backoff = Duration::from_millis(10);
+        let notifier = WebhookNotifier::start(config).unwrap();
+
+        notifier.notify(event("rm"));
+        // Two failed attempts and the successful retry
+        for _ in 0..3 {
+            let body = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(body["command"], "rm");
+            assert_eq!(body["user_id"], "alice");
+            assert_eq!(body["reasons"][0], "denied");
+        }
+
+        notifier.notify(event("dd"));
+        assert_eq!(requests.recv_timeout(Duration::from_secs(5)).unwrap()["command"], "dd");
+    }
+
+    // Test for giving up after the retries
+    #[test]
+    fn test_delivery_gives_up() {
+        let (url, requests) = start_webhook(usize::MAX);
+        let mut config = WebhookConfig::new(vec![url]);
+        config.max_retries = 1;
+        config.initial_backoff = Duration::from_millis(10);
+        let notifier = WebhookNotifier::start(config).unwrap();
+
+        notifier.notify(event("rm"));
+        notifier.notify(event("dd"));
+        let commands: Vec<_> = (0..4)
+            .map(|_| requests.recv_timeout(Duration::from_secs(5)).unwrap()["command"].clone())
+            .collect();
+        assert_eq!(commands, vec!["rm", "rm", "dd", "dd"]);
+    }
+}
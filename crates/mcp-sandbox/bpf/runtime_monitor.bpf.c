@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// eBPF probes of the runtime monitor (crates/mcp-sandbox/src/monitor.rs)
+//
+// Records exec, connect and openat calls of the processes in the cgroups listed in
+// MONITORED_CGROUPS into the EVENTS ring buffer. Build with:
+//
+//   clang -O2 -g -target bpf -c runtime_monitor.bpf.c -o runtime_monitor.bpf.o
+//
+// and set MCP_EBPF_MONITOR_OBJECT to the object file.
+
+#include <linux/bpf.h>
+#include <linux/types.h>
+#include <bpf/bpf_helpers.h>
+
+#define EVENT_EXEC 1
+#define EVENT_CONNECT 2
+#define EVENT_OPEN 3
+
+#define AF_UNIX 1
+#define AF_INET 2
+#define AF_INET6 10
+
+// Layout parsed by parse_event() in monitor.rs (308 bytes without trailing padding)
+struct runtime_event {
+    __u64 cgroup_id;
+    __u32 pid;
+    __u32 kind;
+    __u16 family;
+    __u16 port;
+    __u8 addr[16];
+    char comm[16];
+    char path[256];
+};
+
+struct {
+    __uint(type, BPF_MAP_TYPE_HASH);
+    __uint(max_entries, 4096);
+    __type(key, __u64);
+    __type(value, __u8);
+} MONITORED_CGROUPS SEC(".maps");
+
+struct {
+    __uint(type, BPF_MAP_TYPE_RINGBUF);
+    __uint(max_entries, 1 << 20);
+} EVENTS SEC(".maps");
+
+// Tracepoint records (see /sys/kernel/tracing/events/<category>/<name>/format)
+struct sched_process_exec_args {
+    __u64 common;
+    __u32 filename_loc;
+    __s32 pid;
+    __s32 old_pid;
+};
+
+struct sys_enter_openat_args {
+    __u64 common;
+    __s32 syscall_nr;
+    __u32 pad;
+    __u64 dfd;
+    const char *filename;
+    __u64 flags;
+    __u64 mode;
+};
+
+struct sys_enter_connect_args {
+    __u64 common;
+    __s32 syscall_nr;
+    __u32 pad;
+    __u64 fd;
+    const char *uservaddr;
+    __u64 addrlen;
+};
+
+// Event of the current process, NULL if its cgroup is not monitored or the buffer is full
+static __always_inline struct runtime_event *reserve_event(__u32 kind)
+{
+    __u64 cgroup_id = bpf_get_current_cgroup_id();
+    if (!bpf_map_lookup_elem(&MONITORED_CGROUPS, &cgroup_id))
+        return NULL;
+    struct runtime_event *event = bpf_ringbuf_reserve(&EVENTS, sizeof(*event), 0);
+    if (!event)
+        return NULL;
+    __builtin_memset(event, 0, sizeof(*event));
+    event->cgroup_id = cgroup_id;
+    event->pid = bpf_get_current_pid_tgid() >> 32;
+    event->kind = kind;
+    bpf_get_current_comm(event->comm, sizeof(event->comm));
+    return event;
+}
+
+SEC("tracepoint/sched/sched_process_exec")
+int runtime_monitor_exec(struct sched_process_exec_args *ctx)
+{
+    struct runtime_event *event = reserve_event(EVENT_EXEC);
+    if (!event)
+        return 0;
+    bpf_probe_read_kernel_str(event->path, sizeof(event->path), (void *)ctx + (ctx->filename_loc & 0xFFFF));
+    bpf_ringbuf_submit(event, 0);
+    return 0;
+}
+
+SEC("tracepoint/syscalls/sys_enter_openat")
+int runtime_monitor_openat(struct sys_enter_openat_args *ctx)
+{
+    struct runtime_event *event = reserve_event(EVENT_OPEN);
+    if (!event)
+        return 0;
+    bpf_probe_read_user_str(event->path, sizeof(event->path), ctx->filename);
+    bpf_ringbuf_submit(event, 0);
+    return 0;
+}
+
+SEC("tracepoint/syscalls/sys_enter_connect")
+int runtime_monitor_connect(struct sys_enter_connect_args *ctx)
+{
+    struct runtime_event *event = reserve_event(EVENT_CONNECT);
+    if (!event)
+        return 0;
+    if (bpf_probe_read_user(&event->family, sizeof(event->family), ctx->uservaddr)) {
+        bpf_ringbuf_discard(event, 0);
+        return 0;
+    }
+    // sockaddr_in: port at 2, address at 4; sockaddr_in6: port at 2, address at 8
+    if (event->family == AF_INET) {
+        bpf_probe_read_user(&event->port, sizeof(event->port), ctx->uservaddr + 2);
+        bpf_probe_read_user(event->addr, 4, ctx->uservaddr + 4);
+    } else if (event->family == AF_INET6) {
+        bpf_probe_read_user(&event->port, sizeof(event->port), ctx->uservaddr + 2);
+        bpf_probe_read_user(event->addr, 16, ctx->uservaddr + 8);
+    } else if (event->family == AF_UNIX) {
+        bpf_probe_read_user_str(event->path, sizeof(event->path), ctx->uservaddr + 2);
+    }
+    bpf_ringbuf_submit(event, 0);
+    return 0;
+}
+
+char LICENSE[] SEC("license") = "GPL";
@@ -1,8 +1,27 @@
-use std::path::PathBuf;
+use std::fs::File;
+use std::os::fd::AsRawFd;
+use std::path::{Component, Path, PathBuf};
 use std::process::Stdio;
 use tokio::process::Command;
 use tracing::{debug, warn};
-use crate::models::{NetworkAccess, SandboxConfig};
+use mcp_common::error::{McpError, McpResult};
+use crate::capabilities::canonical_names;
+use crate::dns::sandbox_resolv_conf;
+use crate::models::{MacProfile, NetworkAccess, SandboxConfig};
+use crate::overlay::WorkspaceOverlay;
+
+/// サンドボックスにマウントしてはならないホストのパス（これらの配下と、これらを含む上位のディレクトリも含む）
+pub const SENSITIVE_HOST_PATHS: [&str; 3] = ["/proc", "/sys", "/etc"];
+
+/// `SandboxConfig::extra_bwrap_args`に指定できるbwrapのフラグ（引数を取らず、分離を弱めないものに限る）
+///
+/// * `--new-session`: 新しいセッションで実行し、TIOCSTIで端末に入力を注入できないようにする
+/// * `--clearenv`: 環境変数を全て消す（リクエストの環境変数も渡されなくなる）
+/// * `--as-pid-1`: コマンドをPID名前空間のPID 1として実行する
+/// * `--unshare-cgroup`: cgroup名前空間を必ず分離する（作成できない場合は失敗する）
+/// * `--disable-userns`: サンドボックス内で更にユーザー名前空間を作成できないようにする
+pub const ALLOWED_EXTRA_BWRAP_ARGS: [&str; 5] =
+    ["--new-session", "--clearenv", "--as-pid-1", "--unshare-cgroup", "--disable-userns"];
 
 /// bubblewrapのラッパー
 #[derive(Debug)]
@@ -28,19 +47,87 @@ impl BubblewrapWrapper {
         })
     }
     
+    /// 指定したパスのbwrapを使うBubblewrapWrapperを作成
+    pub fn with_path(bwrap_path: impl Into<PathBuf>) -> Self {
+        Self {
+            bwrap_path: bwrap_path.into(),
+        }
+    }
+    
     /// bubblewrapが使用可能かどうか
     pub fn is_available(&self) -> bool {
         true
     }
     
     /// bubblewrapコマンドを構築
-    pub fn build_command(&self, config: &SandboxConfig, command: &str, args: &[String]) -> Command {
+    ///
+    /// `overlay`が指定された場合、読み書き可能なディレクトリはその上位レイヤーを使ったoverlayfsとしてマウントする
+    ///
+    /// 制限付きネットワークの場合、呼び出し側が[`EgressProxy::attach`](crate::egress::EgressProxy::attach)で
+    /// ネットワーク名前空間を用意する必要がある
+    pub fn build_command(
+        &self,
+        config: &SandboxConfig,
+        overlay: Option<&WorkspaceOverlay>,
+        command: &str,
+        args: &[String],
+    ) -> McpResult<Command> {
+        validate_mounts(config)?;
+        validate_extra_args(&config.extra_bwrap_args)?;
         let mut cmd = Command::new(&self.bwrap_path);
         
-        // 基本的な分離設定
-        cmd.arg("--unshare-all");
+        // 基本的な分離設定（IPC・UTS名前空間を共有する場合は、それ以外の名前空間を個別に分離する）
+        if config.share_ipc || config.share_uts {
+            cmd.args(["--unshare-pid", "--unshare-net", "--unshare-cgroup-try"]);
+            if !config.share_ipc {
+                cmd.arg("--unshare-ipc");
+            }
+            if !config.share_uts {
+                cmd.arg("--unshare-uts");
+            }
+        } else {
+            cmd.arg("--unshare-all");
+        }
         cmd.arg("--die-with-parent");
         
+        // ホスト名が解決できないと動作しないツールがあるため、分離したUTS名前空間にはホスト名を設定する
+        if !config.share_uts {
+            validate_hostname(&config.hostname)?;
+            cmd.arg("--hostname");
+            cmd.arg(&config.hostname);
+        }
+        
+        // ユーザー名前空間内のUID/GID（ゲートウェイのIDは引き継がない）
+        cmd.arg("--unshare-user");
+        cmd.arg("--uid");
+        cmd.arg(config.uid.to_string());
+        cmd.arg("--gid");
+        cmd.arg(config.gid.to_string());
+        
+        // 信頼されたプロファイルが保持するもの以外のケーパビリティを落とす（no-new-privsはbwrapが常に設定する）
+        cmd.arg("--cap-drop");
+        cmd.arg("ALL");
+        for capability in canonical_names(&config.retained_capabilities)? {
+            cmd.arg("--cap-add");
+            cmd.arg(capability);
+        }
+        
+        // ルートは空のtmpfsで、最小限の/proc、/dev、/tmpだけを用意する
+        if config.mount_proc {
+            cmd.arg("--proc");
+            cmd.arg("/proc");
+        }
+        if config.mount_dev {
+            cmd.arg("--dev");
+            cmd.arg("/dev");
+        }
+        if let Some(disk_limit) = config.resource_limits.disk_limit {
+            cmd.arg("--size");
+            cmd.arg(disk_limit.to_string());
+        }
+        cmd.arg("--tmpfs");
+        cmd.arg("/tmp");
+        
         // ネットワーク設定
         match &config.network_access {
             NetworkAccess::None => {
@@ -51,18 +138,29 @@ impl BubblewrapWrapper {
                 // ネットワークを共有
                 // デフォルトではunshare-allに含まれるので、何もしない
             },
-            NetworkAccess::Restricted(hosts) => {
-                // 制限付きネットワークは現在サポートしていないので、警告を出して無効化
-                warn!("制限付きネットワークアクセスは現在サポートされていません: {:?}", hosts);
-                cmd.arg("--unshare-net");
+            NetworkAccess::Restricted(_) => {
+                // ランナーが用意したネットワーク名前空間をそのまま使う（外部へはegressプロキシ経由でのみ接続できる）
+                cmd.arg("--share-net");
             }
         }
         
         // 読み書き可能なディレクトリをマウント
         for path in &config.rw_paths {
-            cmd.arg("--bind");
-            cmd.arg(path);
-            cmd.arg(path);
+            // タスク専用のワークスペースはホスト側のディレクトリが異なる
+            let host_path = config.host_path(path);
+            if let Some(layer) = overlay.and_then(|overlay| overlay.layer(host_path)) {
+                // 変更は上位レイヤーにのみ書き込まれる
+                cmd.arg("--overlay-src");
+                cmd.arg(&layer.lower);
+                cmd.arg("--overlay");
+                cmd.arg(&layer.upper);
+                cmd.arg(&layer.work);
+                cmd.arg(path);
+            } else {
+                cmd.arg("--bind");
+                cmd.arg(host_path);
+                cmd.arg(path);
+            }
         }
         
         // 読み取り専用ディレクトリをマウント
@@ -72,18 +170,61 @@ impl BubblewrapWrapper {
             cmd.arg(path);
         }
         
-        // 拒否するパスを空のディレクトリでマウント
+        // 拒否するパスを空のディレクトリでマウント（配下へのマウントを全て終えてから読み取り専用にする）
         for path in &config.denied_paths {
             cmd.arg("--tmpfs");
             cmd.arg(path);
         }
+
+        // 制限付きネットワークではゲートウェイのDNSリゾルバーを参照させる（/etcは拒否するパスに含まれることが多い）
+        if matches!(config.network_access, NetworkAccess::Restricted(_)) {
+            if let Some(resolv_conf) = sandbox_resolv_conf()? {
+                cmd.arg("--ro-bind");
+                cmd.arg(resolv_conf);
+                cmd.arg("/etc/resolv.conf");
+            }
+        }
+
+        // 拒否するパスを読み取り専用にする（書き込めないため、ディスククォータは/tmpの1回分だけになる）
+        for path in &config.denied_paths {
+            cmd.arg("--remount-ro");
+            cmd.arg(path);
+        }
+        
+        // 全てのマウントの後でルートを読み取り専用にする（マウントしたパス以外には書き込めない）
+        cmd.arg("--remount-ro");
+        cmd.arg("/");
         
         // seccompプロファイルの適用
+        // bwrapはコンパイル済みのBPFプログラムをファイルディスクリプタから読み込む
         if let Some(seccomp_profile) = &config.seccomp_profile {
+            let file = File::open(seccomp_profile).map_err(|e| {
+                McpError::Sandbox(format!("seccompプロファイルを開けません {}: {}", seccomp_profile.display(), e))
+            })?;
+            let fd = file.as_raw_fd();
             cmd.arg("--seccomp");
-            cmd.arg(seccomp_profile);
+            cmd.arg(fd.to_string());
+            // SAFETY: fcntlはasync-signal-safeで、クロージャはメモリを確保しない
+            unsafe {
+                cmd.pre_exec(move || {
+                    // bwrapに引き継ぐためにclose-on-execを解除する（fileはコマンドと共に保持される）
+                    if libc::fcntl(file.as_raw_fd(), libc::F_SETFD, 0) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
         }
         
+        // SELinuxコンテキストはbwrapがコマンドのexec時に設定する（AppArmorプロファイルはランナーがaa-execで適用する）
+        if let Some(MacProfile::SeLinux(context)) = &config.mac_profile {
+            cmd.arg("--exec-label");
+            cmd.arg(context);
+        }
+        
+        // 運用者が有効にした追加のフラグ
+        cmd.args(&config.extra_bwrap_args);
+        
         // 実行するコマンドとその引数を指定
         cmd.arg("--");
         cmd.arg(command);
@@ -96,6 +237,72 @@ impl BubblewrapWrapper {
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
         
-        cmd
+        Ok(cmd)
+    }
+}
+
+/// サンドボックスのホスト名を検証する
+///
+/// RFC 1123のホスト名（英数字とハイフンのラベルを`.`で区切ったもの、64文字以内）でなければならない。
+pub fn validate_hostname(hostname: &str) -> McpResult<()> {
+    let valid_label = |label: &str| {
+        !label.is_empty()
+            && label.len() <= 63
+            && label.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+    };
+    if hostname.is_empty() || hostname.len() > 64 || !hostname.split('.').all(valid_label) {
+        return Err(McpError::InvalidRequest(format!("Invalid sandbox hostname: {}", hostname)));
     }
-} 
\ No newline at end of file
+    Ok(())
+}
+
+/// 追加のbwrapのフラグを検証する
+///
+/// [`ALLOWED_EXTRA_BWRAP_ARGS`]のフラグだけを許可する。
+pub fn validate_extra_args(args: &[String]) -> McpResult<()> {
+    if let Some(arg) = args.iter().find(|arg| !ALLOWED_EXTRA_BWRAP_ARGS.contains(&arg.as_str())) {
+        return Err(McpError::InvalidRequest(format!("Bubblewrap argument not allowed: {}", arg)));
+    }
+    Ok(())
+}
+
+/// 読み書き可能・読み取り専用のマウントを検証する
+///
+/// マウント先は絶対パスで`..`を含まず、拒否するパスの配下であってはならない。マウント元のホストのパスは
+/// シンボリックリンクを解決した上で[`SENSITIVE_HOST_PATHS`]と重なってはならない。
+pub fn validate_mounts(config: &SandboxConfig) -> McpResult<()> {
+    for path in config.rw_paths.iter().chain(&config.ro_paths) {
+        let normalized = path.is_absolute() && path.components().all(|component| component != Component::ParentDir);
+        if !normalized {
+            return Err(McpError::InvalidRequest(format!(
+                "Sandbox mount {} must be an absolute path without '..'",
+                path.display()
+            )));
+        }
+        if let Some(denied) = config.denied_paths.iter().find(|denied| path.starts_with(denied)) {
+            return Err(McpError::PolicyViolation(format!(
+                "Sandbox mount {} is under the denied path {}",
+                path.display(),
+                denied.display()
+            )));
+        }
+
+        // シンボリックリンクで機密パスを指すマウント元も拒否する（存在しないパスはbwrapがエラーにする）
+        let host_path = config.host_path(path);
+        let resolved = host_path.canonicalize().unwrap_or_else(|_| host_path.to_path_buf());
+        let sensitive = SENSITIVE_HOST_PATHS
+            .iter()
+            .map(Path::new)
+            .find(|sensitive| resolved.starts_with(sensitive) || sensitive.starts_with(&resolved));
+        if let Some(sensitive) = sensitive {
+            return Err(McpError::PolicyViolation(format!(
+                "Sandbox mount {} exposes the sensitive host path {}",
+                path.display(),
+                sensitive.display()
+            )));
+        }
+    }
+    Ok(())
+}
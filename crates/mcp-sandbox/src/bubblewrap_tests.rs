@@ -0,0 +1,213 @@
+#[cfg(test)]
+mod tests {
+    use crate::bubblewrap::BubblewrapWrapper;
+    use crate::dns::sandbox_resolv_conf;
+    use crate::models::{
+        NetworkAccess, ResourceLimits, SandboxConfig, DEFAULT_SANDBOX_GID, DEFAULT_SANDBOX_HOSTNAME, DEFAULT_SANDBOX_UID,
+    };
+    use crate::test_support::{args_of, contains};
+    use mcp_common::error::McpError;
+    use std::path::PathBuf;
+
+    // Test for the identity of the command inside the sandbox
+    #[test]
+    fn test_uid_gid_mapping() {
+        let bubblewrap = BubblewrapWrapper::with_path("/usr/bin/bwrap");
+
+        // The gateway's identity is not inherited by default
+        let config = SandboxConfig::default();
+        assert_eq!((config.uid, config.gid), (DEFAULT_SANDBOX_UID, DEFAULT_SANDBOX_GID));
+        let args = args_of(&bubblewrap.build_command(&config, None, "id", &[]).unwrap());
+        assert!(args.contains(&"--unshare-user".to_string()));
+        assert!(contains(&args, &["--uid", "65534", "--gid", "65534"]));
+        assert!(contains(&args, &["--cap-drop", "ALL"]));
+        assert!(!args.contains(&"--cap-add".to_string()));
+
+        let config = SandboxConfig {
+            uid: 1000,
+            gid: 100,
+            ..Default::default()
+        };
+        let args = args_of(&bubblewrap.build_command(&config, None, "id", &[]).unwrap());
+        assert!(contains(&args, &["--uid", "1000", "--gid", "100"]));
+        assert!(args.ends_with(&["--".to_string(), "id".to_string()]));
+    }
+
+    // Test for the read-only root with minimal /proc, /dev and /tmp
+    #[test]
+    fn test_minimal_root() {
+        let bubblewrap = BubblewrapWrapper::with_path("/usr/bin/bwrap");
+
+        let config = SandboxConfig::default();
+        let args = args_of(&bubblewrap.build_command(&config, None, "ls", &[]).unwrap());
+        assert!(contains(&args, &["--proc", "/proc"]));
+        assert!(contains(&args, &["--dev", "/dev"]));
+        assert!(contains(&args, &["--tmpfs", "/tmp"]));
+        // The root is remounted read-only after every mount
+        let remount = args.windows(2).position(|window| window == ["--remount-ro", "/"]).unwrap();
+        let last_mount = args.iter().rposition(|arg| arg.ends_with("bind") || arg == "--tmpfs").unwrap();
+        assert!(last_mount < remount);
+        assert!(remount < args.iter().position(|arg| arg == "--").unwrap());
+
+        let config = SandboxConfig {
+            mount_proc: false,
+            mount_dev: false,
+            resource_limits: ResourceLimits {
+                disk_limit: Some(1 << 20),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let args = args_of(&bubblewrap.build_command(&config, None, "ls", &[]).unwrap());
+        assert!(!args.contains(&"--proc".to_string()));
+        assert!(!args.contains(&"--dev".to_string()));
+        assert!(contains(&args, &["--size", "1048576", "--tmpfs", "/tmp"]));
+
+        // The quota applies once; denied paths are empty and read-only
+        let config = SandboxConfig {
+            denied_paths: vec![PathBuf::from("/etc"), PathBuf::from("/root")],
+            ..config
+        };
+        let args = args_of(&bubblewrap.build_command(&config, None, "ls", &[]).unwrap());
+        assert_eq!(args.iter().filter(|arg| *arg == "--size").count(), 1);
+        assert!(contains(&args, &["--tmpfs", "/etc", "--tmpfs", "/root"]));
+        assert!(contains(&args, &["--remount-ro", "/etc", "--remount-ro", "/root"]));
+    }
+
+    // Denied paths are made read-only only after every mount below them
+    #[test]
+    fn test_denied_path_remount_order() {
+        let bubblewrap = BubblewrapWrapper::with_path("/usr/bin/bwrap");
+        let config = SandboxConfig {
+            network_access: NetworkAccess::Restricted(vec!["api.example.com".to_string()]),
+            denied_paths: vec![PathBuf::from("/etc")],
+            ..Default::default()
+        };
+        let args = args_of(&bubblewrap.build_command(&config, None, "ls", &[]).unwrap());
+
+        let tmpfs = args.windows(2).position(|window| window == ["--tmpfs", "/etc"]).unwrap();
+        let remount = args.windows(2).position(|window| window == ["--remount-ro", "/etc"]).unwrap();
+        let last_mount = args.iter().rposition(|arg| arg.ends_with("bind") || arg == "--tmpfs").unwrap();
+        assert!(tmpfs < last_mount);
+        assert!(last_mount < remount);
+        // The resolver configuration is mounted into the empty /etc while it is still writable
+        if sandbox_resolv_conf().unwrap().is_some() {
+            let resolv_conf = args.iter().position(|arg| arg == "/etc/resolv.conf").unwrap();
+            assert!(tmpfs < resolv_conf && resolv_conf < remount);
+        }
+    }
+
+    // Test for isolating or sharing the IPC and UTS namespaces
+    #[test]
+    fn test_ipc_uts_namespaces() {
+        let bubblewrap = BubblewrapWrapper::with_path("/usr/bin/bwrap");
+
+        // Both are isolated by default and the sandbox gets its own hostname
+        let config = SandboxConfig::default();
+        assert_eq!(config.hostname, DEFAULT_SANDBOX_HOSTNAME);
+        let args = args_of(&bubblewrap.build_command(&config, None, "hostname", &[]).unwrap());
+        assert!(args.contains(&"--unshare-all".to_string()));
+        assert!(contains(&args, &["--hostname", "mcp-sandbox"]));
+
+        let config = SandboxConfig {
+            share_ipc: true,
+            hostname: "build.local".to_string(),
+            ..Default::default()
+        };
+        let args = args_of(&bubblewrap.build_command(&config, None, "hostname", &[]).unwrap());
+        assert!(!args.contains(&"--unshare-all".to_string()));
+        assert!(!args.contains(&"--unshare-ipc".to_string()));
+        for namespace in ["--unshare-pid", "--unshare-net", "--unshare-uts", "--unshare-user"] {
+            assert!(args.contains(&namespace.to_string()), "{}", namespace);
+        }
+        assert!(contains(&args, &["--hostname", "build.local"]));
+
+        let config = SandboxConfig {
+            share_uts: true,
+            ..Default::default()
+        };
+        let args = args_of(&bubblewrap.build_command(&config, None, "hostname", &[]).unwrap());
+        assert!(args.contains(&"--unshare-ipc".to_string()));
+        assert!(!args.contains(&"--unshare-uts".to_string()));
+        assert!(!args.contains(&"--hostname".to_string()));
+
+        for hostname in ["", "-sandbox", "sand_box", "a..b", &"a".repeat(65)] {
+            let config = SandboxConfig {
+                hostname: hostname.to_string(),
+                ..Default::default()
+            };
+            match bubblewrap.build_command(&config, None, "hostname", &[]) {
+                Err(McpError::InvalidRequest(_)) => {}
+                other => panic!("unexpected result for {:?}: {:?}", hostname, other),
+            }
+        }
+    }
+
+    // Test for passing allowlisted extra flags to bubblewrap
+    #[test]
+    fn test_extra_bwrap_args() {
+        let bubblewrap = BubblewrapWrapper::with_path("/usr/bin/bwrap");
+
+        let config = SandboxConfig {
+            extra_bwrap_args: vec!["--new-session".to_string(), "--clearenv".to_string()],
+            ..Default::default()
+        };
+        let args = args_of(&bubblewrap.build_command(&config, None, "echo", &["hi".to_string()]).unwrap());
+        assert!(contains(&args, &["--new-session", "--clearenv", "--", "echo", "hi"]));
+
+        // Flags outside the allowlist, including ones that take a value, are rejected
+        for arg in ["--bind", "--share-net", "--cap-add", "--new-session=1", ""] {
+            let config = SandboxConfig {
+                extra_bwrap_args: vec!["--new-session".to_string(), arg.to_string()],
+                ..Default::default()
+            };
+            match bubblewrap.build_command(&config, None, "echo", &[]) {
+                Err(McpError::InvalidRequest(_)) => {}
+                other => panic!("unexpected result for {:?}: {:?}", arg, other),
+            }
+        }
+    }
+
+    // Test for rejecting mounts of denied and sensitive host paths
+    #[test]
+    fn test_mount_validation() {
+        let bubblewrap = BubblewrapWrapper::with_path("/usr/bin/bwrap");
+        let dir = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink("/etc", dir.path().join("config")).unwrap();
+        let build = |rw_paths: Vec<PathBuf>, ro_paths: Vec<PathBuf>, workspace_dir: Option<PathBuf>| {
+            let config = SandboxConfig {
+                rw_paths,
+                ro_paths,
+                workspace_dir,
+                ..Default::default()
+            };
+            bubblewrap.build_command(&config, None, "ls", &[])
+        };
+
+        build(vec![dir.path().to_path_buf()], vec![PathBuf::from("/usr")], None).unwrap();
+        build(vec![PathBuf::from("/workspace")], Vec::new(), Some(dir.path().to_path_buf())).unwrap();
+
+        for (rw_paths, ro_paths, workspace_dir) in [
+            // Under a denied path of the configuration
+            (Vec::new(), vec![PathBuf::from("/var/lib")], None),
+            // Sensitive host paths, their children and their parents
+            (Vec::new(), vec![PathBuf::from("/proc")], None),
+            (vec![PathBuf::from("/sys/fs/cgroup")], Vec::new(), None),
+            (Vec::new(), vec![PathBuf::from("/")], None),
+            // Symbolic links to sensitive host paths
+            (Vec::new(), vec![dir.path().join("config")], None),
+            (vec![PathBuf::from("/workspace")], Vec::new(), Some(dir.path().join("config"))),
+        ] {
+            match build(rw_paths.clone(), ro_paths.clone(), workspace_dir) {
+                Err(McpError::PolicyViolation(_)) => {}
+                other => panic!("unexpected result for {:?} {:?}: {:?}", rw_paths, ro_paths, other),
+            }
+        }
+        for path in ["relative", "/usr/../etc"] {
+            match build(Vec::new(), vec![PathBuf::from(path)], None) {
+                Err(McpError::InvalidRequest(_)) => {}
+                other => panic!("unexpected result for {}: {:?}", path, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Privilege restrictions of sandboxed commands
+//!
+//! Every sandboxed command runs with `PR_SET_NO_NEW_PRIVS` and an empty capability bounding
+//! set, so that neither setuid/setgid binaries nor file capabilities can give it privileges.
+//! Trusted profiles may retain specific capabilities (`retained_capabilities` of the
+//! sandbox configuration, e.g. `CAP_NET_BIND_SERVICE`):
+//!
+//! * bubblewrap: `--cap-drop ALL` and `--cap-add` for every retained capability;
+//!   bubblewrap always sets no-new-privs
+//! * containers: `--cap-drop ALL`, `--cap-add` and `--security-opt no-new-privileges`
+//! * commands without sandbox: no-new-privs, ambient capabilities and the bounding set are
+//!   set before `exec` (see [`restrict_privileges`])
+//!
+//! Dropping capabilities from the bounding set needs `CAP_SETPCAP`. A gateway running
+//! without it has no capabilities to pass on, and no-new-privs alone keeps its commands
+//! from gaining any.
+
+use mcp_common::error::{McpError, McpResult};
+
+/// Capability names by number
+pub const CAPABILITIES: [&str; 41] = [
+    "CAP_CHOWN",
+    "CAP_DAC_OVERRIDE",
+    "CAP_DAC_READ_SEARCH",
+    "CAP_FOWNER",
+    "CAP_FSETID",
+    "CAP_KILL",
+    "CAP_SETGID",
+    "CAP_SETUID",
+    "CAP_SETPCAP",
+    "CAP_LINUX_IMMUTABLE",
+    "CAP_NET_BIND_SERVICE",
+    "CAP_NET_BROADCAST",
+    "CAP_NET_ADMIN",
+    "CAP_NET_RAW",
+    "CAP_IPC_LOCK",
+    "CAP_IPC_OWNER",
+    "CAP_SYS_MODULE",
+    "CAP_SYS_RAWIO",
+    "CAP_SYS_CHROOT",
+    "CAP_SYS_PTRACE",
+    "CAP_SYS_PACCT",
+    "CAP_SYS_ADMIN",
+    "CAP_SYS_BOOT",
+    "CAP_SYS_NICE",
+    "CAP_SYS_RESOURCE",
+    "CAP_SYS_TIME",
+    "CAP_SYS_TTY_CONFIG",
+    "CAP_MKNOD",
+    "CAP_LEASE",
+    "CAP_AUDIT_WRITE",
+    "CAP_AUDIT_CONTROL",
+    "CAP_SETFCAP",
+    "CAP_MAC_OVERRIDE",
+    "CAP_MAC_ADMIN",
+    "CAP_SYSLOG",
+    "CAP_WAKE_ALARM",
+    "CAP_BLOCK_SUSPEND",
+    "CAP_AUDIT_READ",
+    "CAP_PERFMON",
+    "CAP_BPF",
+    "CAP_CHECKPOINT_RESTORE",
+];
+
+/// Number of a capability, by name with or without the `CAP_` prefix in any case
+pub fn capability_number(name: &str) -> Option<u32> {
+    let name = name.trim().to_ascii_uppercase();
+    let name = name.strip_prefix("CAP_").unwrap_or(&name);
+    CAPABILITIES
+        .iter()
+        .position(|capability| capability[4..] == *name)
+        .map(|number| number as u32)
+}
+
+/// Canonical names (`CAP_...`) of capabilities, failing on unknown ones
+pub fn canonical_names(names: &[String]) -> McpResult<Vec<&'static str>> {
+    names
+        .iter()
+        .map(|name| {
+            capability_number(name)
+                .map(|number| CAPABILITIES[number as usize])
+                .ok_or_else(|| McpError::Sandbox(format!("Unknown capability '{}'", name)))
+        })
+        .collect()
+}
+
+/// Run a command with no-new-privs and only the retained capabilities in its bounding set
+pub(crate) fn restrict_privileges(cmd: &mut tokio::process::Command, retained: &[String]) -> McpResult<()> {
+    let mut retained_mask = 0u64;
+    for name in canonical_names(retained)? {
+        retained_mask |= 1 << capability_number(name).unwrap_or_default();
+    }
+    // SAFETY: prctl is async-signal-safe and the closure does not allocate
+    unsafe {
+        cmd.pre_exec(move || {
+            for capability in 0..64 {
+                if retained_mask & (1 << capability) != 0 {
+                    continue;
+                }
+                if libc::prctl(libc::PR_CAPBSET_DROP, capability as libc::c_ulong, 0, 0, 0) == -1 {
+                    // EINVAL past the last capability of the kernel, EPERM without CAP_SETPCAP
+                    break;
+                }
+            }
+            // Fails on kernels without ambient capabilities, which then have none to clear
+            libc::prctl(libc::PR_CAP_AMBIENT, libc::PR_CAP_AMBIENT_CLEAR_ALL as libc::c_ulong, 0, 0, 0);
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1 as libc::c_ulong, 0, 0, 0) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod tests {
+    use crate::bubblewrap::BubblewrapWrapper;
+    use crate::capabilities::{canonical_names, capability_number};
+    use crate::container::{ContainerRunner, ContainerRuntime};
+    use crate::models::{ExecutionRequest, SandboxConfig};
+    use crate::runner::SandboxRunner;
+    use crate::test_support::args_of;
+    use mcp_common::error::McpError;
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    fn unsandboxed(command: &Path, args: &[&str], retained_capabilities: &[&str]) -> ExecutionRequest {
+        ExecutionRequest {
+            command: command.to_string_lossy().to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config: SandboxConfig {
+                enabled: false,
+                retained_capabilities: retained_capabilities.iter().map(|name| name.to_string()).collect(),
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        }
+    }
+
+    // Test for capability names
+    #[test]
+    fn test_capability_names() {
+        assert_eq!(capability_number("CAP_CHOWN"), Some(0));
+        assert_eq!(capability_number("net_bind_service"), Some(10));
+        assert_eq!(capability_number("CAP_CHECKPOINT_RESTORE"), Some(40));
+        assert_eq!(capability_number("CAP_EVERYTHING"), None);
+        assert_eq!(
+            canonical_names(&["sys_ptrace".to_string(), "CAP_KILL".to_string()]).unwrap(),
+            vec!["CAP_SYS_PTRACE", "CAP_KILL"]
+        );
+        assert!(matches!(canonical_names(&["ALL".to_string()]), Err(McpError::Sandbox(_))));
+
+        // Every capability is dropped except the retained ones
+        let config = SandboxConfig {
+            retained_capabilities: vec!["net_bind_service".to_string()],
+            ..Default::default()
+        };
+        let args = args_of(&BubblewrapWrapper::with_path("bwrap").build_command(&config, None, "id", &[]).unwrap());
+        assert!(args.windows(4).any(|window| window == ["--cap-drop", "ALL", "--cap-add", "CAP_NET_BIND_SERVICE"]));
+        let runner = ContainerRunner::new(ContainerRuntime::Docker, "docker", "example.com/sandbox:1");
+        let args = args_of(&runner.build_command("mcp-test", &config, "id", &[], &HashMap::new(), None).unwrap());
+        assert!(args.windows(2).any(|window| window == ["--cap-add", "CAP_NET_BIND_SERVICE"]));
+        assert!(args.windows(2).any(|window| window == ["--security-opt", "no-new-privileges"]));
+    }
+
+    // Test for the privileges of commands without sandbox
+    #[tokio::test]
+    async fn test_no_new_privs() {
+        let runner = SandboxRunner::new();
+        let request = unsandboxed(Path::new("grep"), &["-E", "^(NoNewPrivs|CapBnd|CapEff)", "/proc/self/status"], &[]);
+        let status = runner.run(request).await.unwrap().stdout;
+        assert!(status.contains("NoNewPrivs:\t1"), "{}", status);
+        assert!(status.contains("CapEff:\t0000000000000000"), "{}", status);
+        // SAFETY: geteuid cannot fail
+        if unsafe { libc::geteuid() } == 0 {
+            // Only a privileged gateway has capabilities to drop
+            assert!(status.contains("CapBnd:\t0000000000000000"), "{}", status);
+            let request =
+                unsandboxed(Path::new("grep"), &["^CapBnd", "/proc/self/status"], &["CAP_NET_BIND_SERVICE"]);
+            let status = runner.run(request).await.unwrap().stdout;
+            assert_eq!(status.trim(), "CapBnd:\t0000000000000400");
+        }
+
+        let request = unsandboxed(Path::new("true"), &[], &["CAP_EVERYTHING"]);
+        assert!(matches!(runner.run(request).await, Err(McpError::Sandbox(_))));
+    }
+
+    // Test for setuid binaries not changing the identity of the command
+    #[tokio::test]
+    async fn test_setuid_cannot_escalate() {
+        // SAFETY: geteuid cannot fail
+        if unsafe { libc::geteuid() } != 0 {
+            // Only root can create a setuid binary owned by another user
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+        let id = dir.path().join("id");
+        std::fs::copy(which::which("id").unwrap(), &id).unwrap();
+        let path = CString::new(id.as_os_str().as_bytes()).unwrap();
+        // SAFETY: the path is NUL-terminated
+        assert_eq!(unsafe { libc::chown(path.as_ptr(), 65534, 65534) }, 0);
+        std::fs::set_permissions(&id, std::fs::Permissions::from_mode(0o6755)).unwrap();
+
+        // The binary does change the identity without no-new-privs
+        let output = std::process::Command::new(&id).output().unwrap();
+        assert!(String::from_utf8_lossy(&output.stdout).contains("euid=65534"));
+
+        let result = SandboxRunner::new().run(unsandboxed(&id, &[], &[])).await.unwrap();
+        assert_eq!(result.exit_code, Some(0));
+        assert!(!result.stdout.contains("euid="), "{}", result.stdout);
+        assert!(!result.stdout.contains("egid="), "{}", result.stdout);
+    }
+}
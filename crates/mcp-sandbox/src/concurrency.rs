@@ -0,0 +1,213 @@
+//! Concurrency limits of sandbox executions
+//!
+//! A [`ConcurrencyLimiter`] bounds the number of commands running at the same time, overall
+//! and per tenant, so that a burst of requests queues up instead of starting hundreds of
+//! sandboxes at once. A command waits for a permit of its tenant first and then for one of
+//! the overall limit, so that a tenant at its limit does not hold up the others.
+//!
+//! The number of queued commands and the time each command waited are reported to a
+//! [`ConcurrencyMetrics`] implementation.
+
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::parse_positive;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError};
+use tracing::debug;
+
+/// Maximum numbers of concurrently running commands (unlimited if `None`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConcurrencyLimits {
+    /// Limit over all tenants
+    pub max_tasks: Option<usize>,
+    /// Limit per tenant
+    pub max_tasks_per_tenant: Option<usize>,
+}
+
+impl ConcurrencyLimits {
+    /// Build the limits from environment variables
+    ///
+    /// * `MCP_SANDBOX_MAX_CONCURRENT_TASKS` - limit over all tenants
+    /// * `MCP_SANDBOX_MAX_CONCURRENT_TASKS_PER_TENANT` - limit per tenant
+    pub fn from_env() -> McpResult<Self> {
+        Ok(Self {
+            max_tasks: limit_from_env("MCP_SANDBOX_MAX_CONCURRENT_TASKS")?,
+            max_tasks_per_tenant: limit_from_env("MCP_SANDBOX_MAX_CONCURRENT_TASKS_PER_TENANT")?,
+        })
+    }
+}
+
+fn limit_from_env(name: &str) -> McpResult<Option<usize>> {
+    std::env::var(name).ok().map(|value| parse_positive(name, &value)).transpose()
+}
+
+/// Receiver of the measurements of a [`ConcurrencyLimiter`]
+pub trait ConcurrencyMetrics: Send + Sync {
+    /// Record the number of commands waiting for a permit
+    fn observe_queue_depth(&self, depth: usize);
+
+    /// Record the time a command waited for its permit (zero if it did not wait)
+    fn observe_queue_wait(&self, wait: Duration);
+}
+
+/// Limiter of concurrently running commands
+pub struct ConcurrencyLimiter {
+    limits: ConcurrencyLimits,
+    global: Option<Arc<Semaphore>>,
+    tenants: Mutex<HashMap<String, Arc<Semaphore>>>,
+    queued: AtomicUsize,
+    metrics: Option<Arc<dyn ConcurrencyMetrics>>,
+}
+
+impl fmt::Debug for ConcurrencyLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConcurrencyLimiter")
+            .field("limits", &self.limits)
+            .field("queued", &self.queued)
+            .finish()
+    }
+}
+
+impl Default for ConcurrencyLimiter {
+    fn default() -> Self {
+        Self::new(ConcurrencyLimits::default())
+    }
+}
+
+impl ConcurrencyLimiter {
+    /// Create a limiter
+    pub fn new(limits: ConcurrencyLimits) -> Self {
+        Self {
+            limits,
+            global: limits.max_tasks.map(|max_tasks| Arc::new(Semaphore::new(max_tasks))),
+            tenants: Mutex::new(HashMap::new()),
+            queued: AtomicUsize::new(0),
+            metrics: None,
+        }
+    }
+
+    /// Report the queue depth and wait times
+    pub fn with_metrics(mut self, metrics: impl ConcurrencyMetrics + 'static) -> Self {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    /// Configured limits
+    pub fn limits(&self) -> ConcurrencyLimits {
+        self.limits
+    }
+
+    /// Number of commands waiting for a permit
+    pub fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Wait until a command of a tenant may run
+    ///
+    /// The command may run as long as the returned permit is held. Commands without a
+    /// tenant are only subject to the overall limit.
+    pub async fn acquire(&self, tenant_id: Option<&str>) -> McpResult<ExecutionPermit> {
+        let start = Instant::now();
+        let mut queued = None;
+        let tenant = match (self.limits.max_tasks_per_tenant, tenant_id) {
+            (Some(max_tasks), Some(tenant_id)) => {
+                Some(self.permit(self.tenant_semaphore(tenant_id, max_tasks), &mut queued).await?)
+            }
+            _ => None,
+        };
+        let global = match &self.global {
+            Some(global) => Some(self.permit(global.clone(), &mut queued).await?),
+            None => None,
+        };
+
+        let wait = match queued.take() {
+            Some(_) => start.elapsed(),
+            None => Duration::ZERO,
+        };
+        if wait > Duration::ZERO {
+            debug!("Command of tenant {:?} waited {:?} for a permit", tenant_id, wait);
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_queue_wait(wait);
+        }
+        Ok(ExecutionPermit {
+            _tenant: tenant,
+            _global: global,
+            wait,
+        })
+    }
+
+    /// Semaphore of a tenant, forgetting those of tenants without running commands
+    fn tenant_semaphore(&self, tenant_id: &str, max_tasks: usize) -> Arc<Semaphore> {
+        let mut tenants = self.tenants.lock().unwrap_or_else(|e| e.into_inner());
+        // Permits and waiters hold a reference as well
+        tenants.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+        tenants
+            .entry(tenant_id.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(max_tasks)))
+            .clone()
+    }
+
+    /// Take a permit, joining the queue if none is available
+    async fn permit<'a>(
+        &'a self,
+        semaphore: Arc<Semaphore>,
+        queued: &mut Option<QueueEntry<'a>>,
+    ) -> McpResult<OwnedSemaphorePermit> {
+        match semaphore.clone().try_acquire_owned() {
+            Ok(permit) => return Ok(permit),
+            Err(TryAcquireError::NoPermits) => {}
+            Err(TryAcquireError::Closed) => return Err(closed()),
+        }
+        queued.get_or_insert_with(|| QueueEntry::new(self));
+        semaphore.acquire_owned().await.map_err(|_| closed())
+    }
+
+    fn observe_queue_depth(&self, depth: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_queue_depth(depth);
+        }
+    }
+}
+
+fn closed() -> McpError {
+    McpError::Internal("Concurrency limiter is closed".to_string())
+}
+
+/// Command counted in the queue depth until dropped (also when the wait is abandoned)
+struct QueueEntry<'a> {
+    limiter: &'a ConcurrencyLimiter,
+}
+
+impl<'a> QueueEntry<'a> {
+    fn new(limiter: &'a ConcurrencyLimiter) -> Self {
+        let depth = limiter.queued.fetch_add(1, Ordering::Relaxed) + 1;
+        limiter.observe_queue_depth(depth);
+        Self { limiter }
+    }
+}
+
+impl Drop for QueueEntry<'_> {
+    fn drop(&mut self) {
+        let depth = self.limiter.queued.fetch_sub(1, Ordering::Relaxed) - 1;
+        self.limiter.observe_queue_depth(depth);
+    }
+}
+
+/// Permission to run a command, released when dropped
+#[derive(Debug)]
+pub struct ExecutionPermit {
+    _tenant: Option<OwnedSemaphorePermit>,
+    _global: Option<OwnedSemaphorePermit>,
+    wait: Duration,
+}
+
+impl ExecutionPermit {
+    /// Time the command waited for the permit
+    pub fn wait_time(&self) -> Duration {
+        self.wait
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod tests {
+    use crate::concurrency::{ConcurrencyLimiter, ConcurrencyLimits, ConcurrencyMetrics};
+    use crate::executor::CommandExecutor;
+    use crate::models::SandboxConfig;
+    use mcp_common::error::McpError;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[derive(Clone, Default)]
+    struct RecordingMetrics {
+        depths: Arc<Mutex<Vec<usize>>>,
+        waits: Arc<Mutex<Vec<Duration>>>,
+    }
+
+    impl ConcurrencyMetrics for RecordingMetrics {
+        fn observe_queue_depth(&self, depth: usize) {
+            self.depths.lock().unwrap().push(depth);
+        }
+
+        fn observe_queue_wait(&self, wait: Duration) {
+            self.waits.lock().unwrap().push(wait);
+        }
+    }
+
+    fn limiter(max_tasks: Option<usize>, max_tasks_per_tenant: Option<usize>) -> ConcurrencyLimiter {
+        ConcurrencyLimiter::new(ConcurrencyLimits {
+            max_tasks,
+            max_tasks_per_tenant,
+        })
+    }
+
+    // Test for queueing commands beyond the overall limit
+    #[tokio::test]
+    async fn test_global_limit() {
+        let metrics = RecordingMetrics::default();
+        let limiter = Arc::new(limiter(Some(1), None).with_metrics(metrics.clone()));
+
+        let permit = limiter.acquire(Some("tenant1")).await.unwrap();
+        assert_eq!(permit.wait_time(), Duration::ZERO);
+        let waiting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire(Some("tenant2")).await.unwrap().wait_time() }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(limiter.queue_depth(), 1);
+        assert!(!waiting.is_finished());
+
+        drop(permit);
+        let wait = waiting.await.unwrap();
+        assert!(wait >= Duration::from_millis(100));
+        assert_eq!(limiter.queue_depth(), 0);
+        assert_eq!(*metrics.depths.lock().unwrap(), vec![1, 0]);
+        assert_eq!(metrics.waits.lock().unwrap().len(), 2);
+
+        // Abandoned waits leave the queue
+        let _permit = limiter.acquire(None).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(50), limiter.acquire(None)).await.is_err());
+        assert_eq!(limiter.queue_depth(), 0);
+    }
+
+    // Test for limiting each tenant separately
+    #[tokio::test]
+    async fn test_per_tenant_limit() {
+        let limiter = limiter(Some(3), Some(1));
+        assert_eq!(limiter.limits().max_tasks_per_tenant, Some(1));
+
+        let _tenant1 = limiter.acquire(Some("tenant1")).await.unwrap();
+        let tenant2 = limiter.acquire(Some("tenant2")).await.unwrap();
+        let _untenanted = limiter.acquire(None).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(50), limiter.acquire(Some("tenant1"))).await.is_err());
+
+        // The tenant at its limit does not hold a permit of the overall limit while waiting
+        drop(tenant2);
+        let _tenant3 = tokio::time::timeout(Duration::from_millis(50), limiter.acquire(Some("tenant3")))
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    // Test for commands of an executor waiting for each other
+    #[tokio::test]
+    async fn test_executor_queue() {
+        let config = SandboxConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let executor = CommandExecutor::with_config(10, config).with_concurrency_limiter(limiter(Some(1), None));
+        let run = |executor: CommandExecutor| async move {
+            let args = vec!["-c".to_string(), "sleep 0.3".to_string()];
+            executor.execute("sh", args, HashMap::new(), None, None).await
+        };
+
+        let start = Instant::now();
+        let (first, second) = tokio::join!(
+            run(executor.with_tenant_id("tenant1")),
+            run(executor.with_tenant_id("tenant2"))
+        );
+        assert_eq!(first.unwrap().exit_code, Some(0));
+        assert_eq!(second.unwrap().exit_code, Some(0));
+        assert!(start.elapsed() >= Duration::from_millis(600));
+    }
+
+    // Test for cancelling a task while it waits for a permit
+    #[tokio::test]
+    async fn test_cancel_queued_task() {
+        let config = SandboxConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let executor = CommandExecutor::with_config(10, config).with_concurrency_limiter(limiter(Some(1), None));
+        let _permit = executor.concurrency_limiter().acquire(None).await.unwrap();
+
+        let guard = executor.register_task("queued").unwrap();
+        let queued = tokio::spawn({
+            let executor = executor.with_task_id("queued");
+            async move { executor.execute("true", Vec::new(), HashMap::new(), None, None).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(executor.concurrency_limiter().queue_depth(), 1);
+
+        let cancel = executor.with_cancel_grace_period(Duration::from_secs(5));
+        let cancelled = tokio::spawn(async move { cancel.cancel_task("queued").await });
+        let result = tokio::time::timeout(Duration::from_secs(1), queued).await.unwrap().unwrap();
+        assert!(matches!(result, Err(McpError::Execution(_))));
+        assert_eq!(executor.concurrency_limiter().queue_depth(), 0);
+        drop(guard);
+        assert!(cancelled.await.unwrap().unwrap());
+    }
+}
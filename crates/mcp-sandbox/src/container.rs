@@ -0,0 +1,272 @@
+//! Container runtime backend
+//!
+//! Runs commands in a rootless Podman (or Docker) container, for deployments that have a
+//! container runtime but no bubblewrap. The sandbox configuration is translated to the
+//! options of `run`:
+//!
+//! * read-write and read-only paths become bind mounts at the same path (paths that do not
+//!   exist on the host are left out), denied paths become empty tmpfs mounts; a per-task
+//!   workspace is mounted at `/workspace`
+//! * the network access becomes `--network none` or `--network host` (restricted access is
+//!   not supported and runs without network)
+//! * the IPC namespace is private unless shared (`--ipc host`), the UTS namespace gets the
+//!   configured hostname unless shared (`--uts host`)
+//! * the resource limits become `--cpus`, `--memory`, `--pids-limit` and `--blkio-weight`,
+//!   the disk quota limits the tmpfs mounts (see [`crate::quota`]), the CPU time limit
+//!   becomes `--ulimit cpu=` and the rlimits of [`crate::rlimit`] become `--ulimit` options
+//!   (per process)
+//! * the seccomp profile (Docker format) is applied with `--security-opt seccomp=`
+//! * an AppArmor profile or SELinux context is applied with `--security-opt apparmor=` or
+//!   `--security-opt label=` (see [`crate::mac`])
+//!
+//! The container runs as the user of the gateway with every capability dropped (except the
+//! retained capabilities of trusted profiles), `no-new-privileges` and a read-only root
+//! filesystem. Environment variables are passed by
+//! name only, so that their values do not appear in the arguments of the runtime.
+
+use crate::bubblewrap::{validate_hostname, validate_mounts};
+use crate::capabilities::canonical_names;
+use crate::mac::container_security_opts;
+use crate::models::{NetworkAccess, SandboxConfig};
+use crate::rlimit::process_rlimits;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::get_env_var_or;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+/// Sequence number for unique container names
+static NEXT_CONTAINER: AtomicU64 = AtomicU64::new(0);
+
+/// Image used when `MCP_CONTAINER_IMAGE` is not set
+pub const DEFAULT_CONTAINER_IMAGE: &str = "docker.io/library/debian:bookworm-slim";
+
+/// Container runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerRuntime {
+    Podman,
+    Docker,
+}
+
+impl ContainerRuntime {
+    /// Name of the runtime binary
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContainerRuntime::Podman => "podman",
+            ContainerRuntime::Docker => "docker",
+        }
+    }
+}
+
+/// Backend running commands in containers
+#[derive(Debug, Clone)]
+pub struct ContainerRunner {
+    runtime: ContainerRuntime,
+    runtime_path: PathBuf,
+    image: String,
+}
+
+impl ContainerRunner {
+    /// Backend using a runtime binary and an image
+    pub fn new(runtime: ContainerRuntime, runtime_path: impl Into<PathBuf>, image: impl Into<String>) -> Self {
+        Self {
+            runtime,
+            runtime_path: runtime_path.into(),
+            image: image.into(),
+        }
+    }
+
+    /// Build the backend from environment variables
+    ///
+    /// * `MCP_CONTAINER_RUNTIME` - `podman`, `docker` or `auto` (Podman if it is installed,
+    ///   otherwise Docker); the backend is disabled when it is not set
+    /// * `MCP_CONTAINER_IMAGE` - image the commands run in
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let runtimes = match std::env::var("MCP_CONTAINER_RUNTIME").ok().as_deref() {
+            None => return Ok(None),
+            Some("auto") => vec![ContainerRuntime::Podman, ContainerRuntime::Docker],
+            Some("podman") => vec![ContainerRuntime::Podman],
+            Some("docker") => vec![ContainerRuntime::Docker],
+            Some(other) => {
+                return Err(McpError::InvalidRequest(format!(
+                    "MCP_CONTAINER_RUNTIME must be \"podman\", \"docker\" or \"auto\": '{}'",
+                    other
+                )))
+            }
+        };
+        let image = get_env_var_or("MCP_CONTAINER_IMAGE", DEFAULT_CONTAINER_IMAGE);
+
+        for runtime in runtimes {
+            if let Ok(path) = which::which(runtime.as_str()) {
+                debug!("{} found at: {:?}", runtime.as_str(), path);
+                return Ok(Some(Self::new(runtime, path, image)));
+            }
+        }
+        Err(McpError::Sandbox("No container runtime of MCP_CONTAINER_RUNTIME is installed".to_string()))
+    }
+
+    /// Container runtime
+    pub fn runtime(&self) -> ContainerRuntime {
+        self.runtime
+    }
+
+    /// Image the commands run in
+    pub fn image(&self) -> &str {
+        &self.image
+    }
+
+    /// Unique name for a new container
+    pub fn container_name() -> String {
+        format!("mcp-{}-{}", std::process::id(), NEXT_CONTAINER.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Build the `run` command of a container
+    ///
+    /// The container is named `name`, so that it can be removed with [`Self::remove`] if the
+    /// runtime client is killed.
+    pub fn build_command(
+        &self,
+        name: &str,
+        config: &SandboxConfig,
+        command: &str,
+        args: &[String],
+        env: &HashMap<String, String>,
+        cwd: Option<&Path>,
+    ) -> McpResult<Command> {
+        // The same host paths are kept out of containers as out of bubblewrap
+        validate_mounts(config)?;
+        let mut cmd = Command::new(&self.runtime_path);
+        cmd.args(["run", "--rm", "--init", "--name", name]);
+        cmd.args(["--cap-drop", "ALL", "--security-opt", "no-new-privileges", "--read-only"]);
+        for capability in canonical_names(&config.retained_capabilities)? {
+            cmd.arg("--cap-add").arg(capability);
+        }
+        for security_opt in config.mac_profile.iter().flat_map(container_security_opts) {
+            cmd.arg("--security-opt").arg(security_opt);
+        }
+        // SAFETY: getuid and getgid cannot fail
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        cmd.arg("--user").arg(format!("{}:{}", uid, gid));
+        if self.runtime == ContainerRuntime::Podman {
+            // Keep the files written to the mounts owned by the user of the gateway
+            cmd.arg("--userns=keep-id");
+        }
+
+        // Network settings
+        let network = match &config.network_access {
+            NetworkAccess::None => "none",
+            NetworkAccess::Host => "host",
+            NetworkAccess::Restricted(hosts) => {
+                warn!("Restricted network access is not supported by the container backend: {:?}", hosts);
+                "none"
+            }
+        };
+        cmd.args(["--network", network]);
+
+        // IPC and UTS namespaces
+        cmd.args(["--ipc", if config.share_ipc { "host" } else { "private" }]);
+        if config.share_uts {
+            cmd.args(["--uts", "host"]);
+        } else {
+            validate_hostname(&config.hostname)?;
+            cmd.arg("--hostname").arg(&config.hostname);
+        }
+
+        // Mounts
+        for (paths, readonly) in [(&config.rw_paths, false), (&config.ro_paths, true)] {
+            for path in paths {
+                let source = config.host_path(path);
+                if !source.exists() {
+                    debug!("Not mounting {} into the container, it does not exist", source.display());
+                    continue;
+                }
+                let readonly = if readonly { ",readonly" } else { "" };
+                cmd.arg("--mount").arg(format!(
+                    "type=bind,source={},target={}{}",
+                    mount_path(source)?,
+                    mount_path(path)?,
+                    readonly
+                ));
+            }
+        }
+        let tmpfs_size = match config.resource_limits.disk_limit {
+            Some(disk_limit) => format!(",tmpfs-size={}", disk_limit),
+            None => String::new(),
+        };
+        for path in &config.denied_paths {
+            cmd.arg("--mount").arg(format!("type=tmpfs,target={}{}", mount_path(path)?, tmpfs_size));
+        }
+
+        // Resource limits
+        let limits = &config.resource_limits;
+        if let Some(cpus) = limits.cpu_limit {
+            cmd.arg("--cpus").arg(cpus.to_string());
+        }
+        if let Some(memory) = limits.memory_limit {
+            cmd.arg("--memory").arg(memory.to_string());
+        }
+        if let Some(pids) = limits.pids_limit {
+            cmd.arg("--pids-limit").arg(pids.to_string());
+        }
+        // The file size is also bounded just over the disk quota, as for commands outside containers
+        for (rlimit, value) in process_rlimits(limits) {
+            cmd.arg("--ulimit").arg(format!("{}={}:{}", rlimit.name(), value, value));
+        }
+        if let Some(cpu_time_limit) = limits.cpu_time_limit {
+            // SIGXCPU at the limit, SIGKILL a second later
+            cmd.arg("--ulimit").arg(format!("cpu={}:{}", cpu_time_limit, cpu_time_limit.saturating_add(1)));
+        }
+        if let Some(weight) = limits.io_weight {
+            // The runtimes accept weights from 10 to 1000
+            cmd.arg("--blkio-weight").arg(weight.clamp(10, 1000).to_string());
+        }
+
+        if let Some(seccomp_profile) = &config.seccomp_profile {
+            cmd.arg("--security-opt").arg(format!("seccomp={}", seccomp_profile.display()));
+        }
+
+        // Environment variables are taken from the environment of the runtime client
+        for (key, value) in env {
+            cmd.arg("--env").arg(key);
+            cmd.env(key, value);
+        }
+        if let Some(cwd) = cwd {
+            cmd.arg("--workdir").arg(cwd);
+        }
+
+        cmd.arg("--entrypoint").arg(command);
+        cmd.arg(&self.image);
+        cmd.args(args);
+
+        cmd.stdin(Stdio::null());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+
+        Ok(cmd)
+    }
+
+    /// Remove a container, stopping it if it is still running
+    pub async fn remove(&self, name: &str) {
+        let status = Command::new(&self.runtime_path)
+            .args(["rm", "--force", name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await;
+        if let Err(e) = status {
+            warn!("Failed to remove container {}: {}", name, e);
+        }
+    }
+}
+
+/// Path as a value of `--mount`, which cannot contain commas
+fn mount_path(path: &Path) -> McpResult<&str> {
+    match path.to_str() {
+        Some(path) if !path.contains(',') => Ok(path),
+        _ => Err(McpError::Sandbox(format!("Path cannot be mounted into a container: {}", path.display()))),
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests {
+    use crate::container::{ContainerRunner, ContainerRuntime};
+    use crate::models::{ExecutionRequest, NetworkAccess, ResourceLimits, SandboxBackend, SandboxConfig};
+    use crate::runner::SandboxRunner;
+    use crate::test_support::{args_of, contains};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    // Test for translating the sandbox configuration to container options
+    #[test]
+    fn test_build_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().to_string_lossy().to_string();
+        let config = SandboxConfig {
+            rw_paths: vec![dir.path().to_path_buf(), PathBuf::from("/nonexistent/workspace")],
+            ro_paths: vec![PathBuf::from("/usr")],
+            denied_paths: vec![PathBuf::from("/etc")],
+            network_access: NetworkAccess::Restricted(vec!["api.example.com".to_string()]),
+            resource_limits: ResourceLimits {
+                cpu_limit: Some(0.5),
+                memory_limit: Some(268435456),
+                pids_limit: Some(64),
+                io_weight: Some(1),
+                disk_limit: None,
+                cpu_time_limit: None,
+                open_files_limit: Some(256),
+                file_size_limit: None,
+                core_dump_limit: None,
+                stack_limit: None,
+            },
+            ..Default::default()
+        };
+        let env = HashMap::from([("TOKEN".to_string(), "secret".to_string())]);
+
+        let runner = ContainerRunner::new(ContainerRuntime::Podman, "podman", "example.com/sandbox:1");
+        let cmd = runner
+            .build_command("mcp-test", &config, "ls", &["-la".to_string()], &env, Some(dir.path()))
+            .unwrap();
+        let args = args_of(&cmd);
+
+        assert!(contains(&args, &["run", "--rm", "--init", "--name", "mcp-test"]));
+        assert!(contains(&args, &["--cap-drop", "ALL"]));
+        assert!(args.contains(&"--userns=keep-id".to_string()));
+        // Restricted network access is not supported
+        assert!(contains(&args, &["--network", "none"]));
+        assert!(contains(&args, &["--ipc", "private"]));
+        assert!(contains(&args, &["--hostname", "mcp-sandbox"]));
+        assert!(contains(&args, &["--mount", &format!("type=bind,source={},target={}", workspace, workspace)]));
+        assert!(contains(&args, &["--mount", "type=bind,source=/usr,target=/usr,readonly"]));
+        assert!(contains(&args, &["--mount", "type=tmpfs,target=/etc"]));
+        assert!(!args.iter().any(|arg| arg.contains("/nonexistent")));
+        assert!(contains(&args, &["--cpus", "0.5"]));
+        assert!(contains(&args, &["--memory", "268435456"]));
+        assert!(contains(&args, &["--pids-limit", "64"]));
+        assert!(contains(&args, &["--blkio-weight", "10"]));
+        assert!(contains(&args, &["--ulimit", "nofile=256:256"]));
+        assert!(contains(&args, &["--ulimit", "core=0:0"]));
+        // Values of environment variables are not part of the arguments
+        assert!(contains(&args, &["--env", "TOKEN"]));
+        assert!(!args.iter().any(|arg| arg.contains("secret")));
+        assert!(args.ends_with(&[
+            "--entrypoint".to_string(),
+            "ls".to_string(),
+            "example.com/sandbox:1".to_string(),
+            "-la".to_string()
+        ]));
+
+        let docker = ContainerRunner::new(ContainerRuntime::Docker, "docker", "example.com/sandbox:1");
+        let config = SandboxConfig {
+            network_access: NetworkAccess::Host,
+            rw_paths: vec![],
+            share_ipc: true,
+            share_uts: true,
+            ..Default::default()
+        };
+        let args = args_of(&docker.build_command("mcp-test", &config, "ls", &[], &HashMap::new(), None).unwrap());
+        assert!(contains(&args, &["--network", "host"]));
+        assert!(contains(&args, &["--ipc", "host"]));
+        assert!(contains(&args, &["--uts", "host"]));
+        assert!(!args.contains(&"--hostname".to_string()));
+        assert!(!args.contains(&"--userns=keep-id".to_string()));
+
+        // Paths that cannot be expressed as mount options are rejected
+        let config = SandboxConfig {
+            denied_paths: vec![PathBuf::from("/data/a,b")],
+            ..Default::default()
+        };
+        assert!(docker.build_command("mcp-test", &config, "ls", &[], &HashMap::new(), None).is_err());
+    }
+
+    // Test for running a command selecting the container backend
+    #[tokio::test]
+    async fn test_run_in_container() {
+        let request = || ExecutionRequest {
+            command: "ls".to_string(),
+            args: vec!["-la".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config: SandboxConfig {
+                backend: SandboxBackend::Container,
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+
+        // Without a runtime the command is not run in another sandbox
+        if std::env::var("MCP_CONTAINER_RUNTIME").is_err() {
+            let err = SandboxRunner::new().run(request()).await.unwrap_err();
+            assert!(err.to_string().contains("No container runtime"), "{}", err);
+        }
+
+        // A runtime that prints its arguments
+        let runner = SandboxRunner::new().with_container_runner(ContainerRunner::new(
+            ContainerRuntime::Docker,
+            "echo",
+            "example.com/sandbox:1",
+        ));
+        let result = runner.run(request()).await.unwrap();
+        assert_eq!(result.exit_code, Some(0));
+        assert!(result.stdout.starts_with("run --rm --init --name mcp-"), "{}", result.stdout);
+        assert!(result.stdout.contains("--security-opt seccomp="), "{}", result.stdout);
+        assert!(result.stdout.trim_end().ends_with("--entrypoint ls example.com/sandbox:1 -la"), "{}", result.stdout);
+    }
+}
@@ -0,0 +1,231 @@
+//! DNS filtering for restricted network access
+//!
+//! The network namespace of a sandbox with restricted network access (see [`crate::egress`])
+//! has a resolver on port 53 of its loopback addresses, and bubblewrap replaces
+//! `/etc/resolv.conf` with one pointing to it (see [`sandbox_resolv_conf`]). The resolver
+//! only answers queries for domains of the allowlist, which it resolves on the host, and
+//! refuses all other queries, so that lookups cannot carry data out of the sandbox. Every
+//! query is logged and recorded.
+//!
+//! Only A and AAAA records are resolved; other record types of allowed domains get an
+//! empty answer. When the `/etc/resolv.conf` of the host is a symbolic link (e.g. to the
+//! stub resolver of systemd-resolved) it is left alone: such links usually point to a
+//! loopback address, which reaches the resolver as well.
+
+use crate::egress::EgressPolicy;
+use mcp_common::error::{McpError, McpResult};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+
+/// Port of the resolver in the network namespace of the sandbox
+pub const DNS_PORT: u16 = 53;
+
+/// A record (IPv4 address)
+pub const RECORD_A: u16 = 1;
+/// AAAA record (IPv6 address)
+pub const RECORD_AAAA: u16 = 28;
+
+/// Time to live of the answers (seconds)
+const ANSWER_TTL: u32 = 60;
+/// Maximum size of a DNS message over UDP without EDNS
+const MAX_MESSAGE: usize = 512;
+/// Answers that fit a message with the longest question (28 bytes per AAAA record)
+const MAX_ANSWERS: usize = 8;
+/// Timeout for resolving a name on the host
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+const RCODE_NO_ERROR: u8 = 0;
+const RCODE_SERVER_FAILURE: u8 = 2;
+const RCODE_NAME_ERROR: u8 = 3;
+const RCODE_REFUSED: u8 = 5;
+
+/// DNS query of a sandbox
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsQuery {
+    /// Queried name (lower case, without the trailing dot)
+    pub name: String,
+    /// Queried record type (e.g. [`RECORD_A`])
+    pub record_type: u16,
+    /// Whether the policy allowed the name
+    pub allowed: bool,
+}
+
+/// Queries recorded by a resolver
+pub(crate) type Queries = Arc<Mutex<Vec<DnsQuery>>>;
+
+/// `/etc/resolv.conf` for sandboxes with restricted network access
+///
+/// Returns `None` when the host file is a symbolic link, which cannot be replaced by a mount.
+pub fn sandbox_resolv_conf() -> McpResult<Option<&'static Path>> {
+    static RESOLV_CONF: OnceLock<PathBuf> = OnceLock::new();
+
+    if std::fs::symlink_metadata("/etc/resolv.conf").is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        return Ok(None);
+    }
+    if let Some(path) = RESOLV_CONF.get() {
+        return Ok(Some(path));
+    }
+    let path = std::env::temp_dir().join(format!("mcp-resolv-{}.conf", std::process::id()));
+    std::fs::write(&path, "nameserver 127.0.0.1\n")
+        .map_err(|e| McpError::Sandbox(format!("Failed to write {}: {}", path.display(), e)))?;
+    Ok(Some(RESOLV_CONF.get_or_init(|| path)))
+}
+
+/// Question of a DNS query message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DnsQuestion {
+    pub(crate) id: u16,
+    pub(crate) recursion_desired: bool,
+    pub(crate) name: String,
+    pub(crate) record_type: u16,
+    /// Question section as received
+    pub(crate) question: Vec<u8>,
+}
+
+impl DnsQuestion {
+    /// Parse a standard query with a single question
+    pub(crate) fn parse(message: &[u8]) -> Option<Self> {
+        if message.len() < 12 {
+            return None;
+        }
+        let flags = u16::from_be_bytes([message[2], message[3]]);
+        let is_query = flags & 0x8000 == 0 && (flags >> 11) & 0xf == 0;
+        let question_count = u16::from_be_bytes([message[4], message[5]]);
+        if !is_query || question_count != 1 {
+            return None;
+        }
+
+        let mut labels = Vec::new();
+        let mut offset = 12;
+        loop {
+            let len = *message.get(offset)? as usize;
+            offset += 1;
+            if len == 0 {
+                break;
+            }
+            // Compression pointers have no place in a question
+            if len > 63 {
+                return None;
+            }
+            let label = message.get(offset..offset + len)?;
+            labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+            offset += len;
+        }
+        let record_type = u16::from_be_bytes([*message.get(offset)?, *message.get(offset + 1)?]);
+        let end = offset + 4;
+        if message.len() < end || offset - 12 > 255 {
+            return None;
+        }
+        Some(Self {
+            id: u16::from_be_bytes([message[0], message[1]]),
+            recursion_desired: flags & 0x0100 != 0,
+            name: labels.join("."),
+            record_type,
+            question: message[12..end].to_vec(),
+        })
+    }
+
+    /// Response with a result code and address records
+    pub(crate) fn response(&self, rcode: u8, addresses: &[IpAddr]) -> Vec<u8> {
+        let answers = &addresses[..addresses.len().min(MAX_ANSWERS)];
+
+        // Response with recursion available
+        let mut flags = 0x8080 | rcode as u16;
+        if self.recursion_desired {
+            flags |= 0x0100;
+        }
+        let mut message = Vec::with_capacity(MAX_MESSAGE);
+        message.extend_from_slice(&self.id.to_be_bytes());
+        message.extend_from_slice(&flags.to_be_bytes());
+        for count in [1, answers.len() as u16, 0, 0] {
+            message.extend_from_slice(&count.to_be_bytes());
+        }
+        message.extend_from_slice(&self.question);
+
+        for address in answers {
+            let (record_type, data) = match address {
+                IpAddr::V4(address) => (RECORD_A, address.octets().to_vec()),
+                IpAddr::V6(address) => (RECORD_AAAA, address.octets().to_vec()),
+            };
+            // Pointer to the name of the question
+            message.extend_from_slice(&0xc00cu16.to_be_bytes());
+            message.extend_from_slice(&record_type.to_be_bytes());
+            message.extend_from_slice(&1u16.to_be_bytes());
+            message.extend_from_slice(&ANSWER_TTL.to_be_bytes());
+            message.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            message.extend_from_slice(&data);
+        }
+        message
+    }
+}
+
+/// Answer the queries received on a socket
+pub(crate) async fn serve_dns(socket: UdpSocket, policy: Arc<EgressPolicy>, queries: Queries) {
+    let socket = Arc::new(socket);
+    let mut lookups = JoinSet::new();
+    let mut buffer = [0u8; MAX_MESSAGE];
+    loop {
+        tokio::select! {
+            received = socket.recv_from(&mut buffer) => match received {
+                Ok((len, peer)) => {
+                    let Some(question) = DnsQuestion::parse(&buffer[..len]) else {
+                        debug!("Ignoring malformed DNS message from the sandbox");
+                        continue;
+                    };
+                    let (socket, policy, queries) = (socket.clone(), policy.clone(), queries.clone());
+                    lookups.spawn(async move {
+                        let response = answer(&question, &policy, &queries).await;
+                        if let Err(e) = socket.send_to(&response, peer).await {
+                            debug!("Failed to send DNS response: {}", e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    warn!("DNS resolver stopped receiving queries: {}", e);
+                    return;
+                }
+            },
+            Some(_) = lookups.join_next() => {}
+        }
+    }
+}
+
+/// Answer one query according to the policy
+async fn answer(question: &DnsQuestion, policy: &EgressPolicy, queries: &Queries) -> Vec<u8> {
+    let allowed = policy.allows_host(&question.name);
+    queries.lock().unwrap().push(DnsQuery {
+        name: question.name.clone(),
+        record_type: question.record_type,
+        allowed,
+    });
+    if !allowed {
+        warn!("DNS query for {} (type {}) denied", question.name, question.record_type);
+        return question.response(RCODE_REFUSED, &[]);
+    }
+    info!("DNS query for {} (type {}) allowed", question.name, question.record_type);
+
+    if question.record_type != RECORD_A && question.record_type != RECORD_AAAA {
+        return question.response(RCODE_NO_ERROR, &[]);
+    }
+    let lookup = tokio::net::lookup_host((question.name.as_str(), 0));
+    match tokio::time::timeout(LOOKUP_TIMEOUT, lookup).await {
+        Ok(Ok(addresses)) => {
+            let mut addresses: Vec<IpAddr> = addresses
+                .map(|address| address.ip())
+                .filter(|address| address.is_ipv4() == (question.record_type == RECORD_A))
+                .collect();
+            addresses.dedup();
+            question.response(RCODE_NO_ERROR, &addresses)
+        }
+        Ok(Err(e)) => {
+            debug!("Failed to resolve {}: {}", question.name, e);
+            question.response(RCODE_NAME_ERROR, &[])
+        }
+        Err(_) => question.response(RCODE_SERVER_FAILURE, &[]),
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod tests {
+    use crate::dns::{DnsQuery, DnsQuestion, RECORD_A};
+    use crate::egress::{EgressPolicy, EgressProxy};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::time::Duration;
+    use tokio::net::UdpSocket;
+
+    /// Standard query with recursion desired
+    fn query(id: u16, name: &str, record_type: u16) -> Vec<u8> {
+        let mut message = id.to_be_bytes().to_vec();
+        message.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+        for label in name.split('.') {
+            message.push(label.len() as u8);
+            message.extend_from_slice(label.as_bytes());
+        }
+        message.push(0);
+        message.extend_from_slice(&record_type.to_be_bytes());
+        message.extend_from_slice(&1u16.to_be_bytes());
+        message
+    }
+
+    async fn resolve(client: &UdpSocket, id: u16, name: &str, record_type: u16) -> Vec<u8> {
+        client.send(&query(id, name, record_type)).await.unwrap();
+        let mut buffer = [0u8; 512];
+        let len = tokio::time::timeout(Duration::from_secs(10), client.recv(&mut buffer)).await.unwrap().unwrap();
+        buffer[..len].to_vec()
+    }
+
+    // Test for parsing queries and encoding responses
+    #[test]
+    fn test_dns_messages() {
+        let message = query(0x1234, "Api.Example.com", RECORD_A);
+        let question = DnsQuestion::parse(&message).unwrap();
+        assert_eq!(question.id, 0x1234);
+        assert!(question.recursion_desired);
+        assert_eq!(question.name, "api.example.com");
+        assert_eq!(question.record_type, RECORD_A);
+        assert_eq!(question.question, message[12..]);
+
+        let addresses = [IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)), IpAddr::V6(Ipv6Addr::LOCALHOST)];
+        let response = question.response(0, &addresses);
+        // Response, recursion desired and available, no error, one question and two answers
+        assert_eq!(response[..12], [0x12, 0x34, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0]);
+        assert_eq!(response[12..message.len()], message[12..]);
+        let first = &response[message.len()..message.len() + 16];
+        assert_eq!(first, [0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 192, 0, 2, 1]);
+        assert_eq!(response.len(), message.len() + 16 + 28);
+
+        // Refused without answers
+        let response = question.response(5, &[]);
+        assert_eq!(response[..12], [0x12, 0x34, 0x81, 0x85, 0, 1, 0, 0, 0, 0, 0, 0]);
+        // Responses never exceed a UDP message
+        let many = vec![IpAddr::V6(Ipv6Addr::LOCALHOST); 100];
+        assert!(question.response(0, &many).len() <= 512);
+
+        let mut response_message = message.clone();
+        response_message[2] |= 0x80;
+        let mut two_questions = message.clone();
+        two_questions[5] = 2;
+        let mut pointer = message.clone();
+        pointer[12] = 0xc0;
+        for message in [&message[..11], &response_message, &two_questions, &pointer, &message[..message.len() - 1]] {
+            assert_eq!(DnsQuestion::parse(message), None, "{:?}", message);
+        }
+    }
+
+    // Test for answering only allowed domains
+    #[tokio::test]
+    async fn test_resolver() {
+        let policy = EgressPolicy::new(&["localhost:8080".to_string()]).unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let dns_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = dns_socket.local_addr().unwrap();
+        let proxy = EgressProxy::serve(policy, listener, dns_socket).unwrap();
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.connect(resolver).await.unwrap();
+
+        let response = resolve(&client, 1, "localhost", RECORD_A).await;
+        assert_eq!(response[3] & 0x0f, 0);
+        assert!(u16::from_be_bytes([response[6], response[7]]) >= 1);
+        assert_eq!(response[response.len() - 4..], [127, 0, 0, 1]);
+
+        let response = resolve(&client, 2, "c2VjcmV0.attacker.example", RECORD_A).await;
+        assert_eq!(response[..2], [0, 2]);
+        assert_eq!(response[3] & 0x0f, 5);
+        assert_eq!(response[6..8], [0, 0]);
+        // Other record types of allowed domains get an empty answer
+        let response = resolve(&client, 3, "localhost", 16).await;
+        assert_eq!(response[3] & 0x0f, 0);
+        assert_eq!(response[6..8], [0, 0]);
+
+        assert_eq!(
+            proxy.queries(),
+            vec![
+                DnsQuery {
+                    name: "localhost".to_string(),
+                    record_type: RECORD_A,
+                    allowed: true
+                },
+                DnsQuery {
+                    name: "c2vjcmv0.attacker.example".to_string(),
+                    record_type: RECORD_A,
+                    allowed: false
+                },
+                DnsQuery {
+                    name: "localhost".to_string(),
+                    record_type: 16,
+                    allowed: true
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Dry runs of commands
+//!
+//! A dry run prepares a command the way an execution does (the executable is resolved, the
+//! default MAC label applied, the backend selected, the seccomp profile generated and the
+//! command line of the backend built) but does not start it. The report tells how the
+//! command would be started, e.g. to debug a sandbox configuration.
+//!
+//! Nothing of the task is created: the per-task workspace is not provisioned, so the
+//! configured read-write paths are reported as they are, and a command with restricted
+//! network access is reported without the egress proxy it would be attached to. Dry runs are
+//! not supported by the firecracker backend, whose preparation creates the microVM.
+
+use crate::models::{NetworkAccess, ResourceLimits, SandboxConfig};
+use crate::overlay::WorkspaceOverlay;
+use std::path::PathBuf;
+use tokio::process::Command;
+
+/// Backend that would run a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DryRunBackend {
+    /// bubblewrap sandbox
+    Bubblewrap,
+    /// Podman or Docker container
+    Container,
+    /// The host, without sandbox
+    Host,
+}
+
+impl DryRunBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DryRunBackend::Bubblewrap => "bubblewrap",
+            DryRunBackend::Container => "container",
+            DryRunBackend::Host => "host",
+        }
+    }
+}
+
+/// Kind of a mount of the sandbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MountKind {
+    /// Read-write bind mount
+    ReadWrite,
+    /// Read-only bind mount
+    ReadOnly,
+    /// Overlay with a throwaway upper layer (see [`crate::overlay`])
+    Overlay,
+    /// Empty tmpfs hiding a denied path
+    Tmpfs,
+}
+
+impl MountKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MountKind::ReadWrite => "rw",
+            MountKind::ReadOnly => "ro",
+            MountKind::Overlay => "overlay",
+            MountKind::Tmpfs => "tmpfs",
+        }
+    }
+}
+
+/// Mount of the sandbox
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxMount {
+    /// Host path (`None` for tmpfs mounts)
+    pub source: Option<PathBuf>,
+    /// Path in the sandbox
+    pub target: PathBuf,
+    pub kind: MountKind,
+}
+
+/// How a command would be started
+#[derive(Debug, Clone)]
+pub struct DryRunReport {
+    pub backend: DryRunBackend,
+    /// Command line that would be started, the program first
+    pub argv: Vec<String>,
+    /// Mounts of the sandbox (none on the host)
+    pub mounts: Vec<SandboxMount>,
+    /// Name of the seccomp profile, if one would be applied
+    pub seccomp_profile: Option<String>,
+    /// Profile file the backend would load
+    pub seccomp_profile_path: Option<PathBuf>,
+    pub resource_limits: ResourceLimits,
+    /// Timeout in seconds
+    pub timeout: u32,
+    pub network_access: NetworkAccess,
+}
+
+impl DryRunReport {
+    pub(crate) fn new(
+        backend: DryRunBackend,
+        cmd: &Command,
+        config: &SandboxConfig,
+        overlay: Option<&WorkspaceOverlay>,
+        seccomp_profile: Option<String>,
+        timeout: u32,
+    ) -> Self {
+        let argv = std::iter::once(cmd.as_std().get_program())
+            .chain(cmd.as_std().get_args())
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        let (mounts, seccomp_profile_path) = match backend {
+            DryRunBackend::Host => (Vec::new(), None),
+            _ => (mounts(config, overlay), config.seccomp_profile.clone()),
+        };
+        Self {
+            backend,
+            argv,
+            mounts,
+            seccomp_profile,
+            seccomp_profile_path,
+            resource_limits: config.resource_limits.clone(),
+            timeout,
+            network_access: config.network_access.clone(),
+        }
+    }
+}
+
+/// Mounts of a sandbox configuration, in the order they are made
+fn mounts(config: &SandboxConfig, overlay: Option<&WorkspaceOverlay>) -> Vec<SandboxMount> {
+    let read_write = config.rw_paths.iter().map(|path| {
+        let host_path = config.host_path(path);
+        let kind = match overlay.and_then(|overlay| overlay.layer(host_path)) {
+            Some(_) => MountKind::Overlay,
+            None => MountKind::ReadWrite,
+        };
+        SandboxMount {
+            source: Some(host_path.to_path_buf()),
+            target: path.clone(),
+            kind,
+        }
+    });
+    let read_only = config.ro_paths.iter().map(|path| SandboxMount {
+        source: Some(path.clone()),
+        target: path.clone(),
+        kind: MountKind::ReadOnly,
+    });
+    let denied = config.denied_paths.iter().map(|path| SandboxMount {
+        source: None,
+        target: path.clone(),
+        kind: MountKind::Tmpfs,
+    });
+    read_write.chain(read_only).chain(denied).collect()
+}
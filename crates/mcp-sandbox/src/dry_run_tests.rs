@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod tests {
+    use crate::bubblewrap::BubblewrapWrapper;
+    use crate::dry_run::{DryRunBackend, MountKind, SandboxMount};
+    use crate::models::{ExecutionRequest, NetworkAccess, ResourceLimits, SandboxBackend, SandboxConfig};
+    use crate::runner::SandboxRunner;
+    use mcp_common::error::McpError;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    fn request(sandbox_config: SandboxConfig) -> ExecutionRequest {
+        ExecutionRequest {
+            command: "echo".to_string(),
+            args: vec!["hello".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 30,
+            sandbox_config,
+            input_files: Vec::new(),
+            diff_workspace: false,
+        }
+    }
+
+    // Test for reporting the bubblewrap command line and mounts of a sandboxed command
+    #[test]
+    fn test_dry_run_bubblewrap() {
+        let runner = SandboxRunner::new().with_bubblewrap(BubblewrapWrapper::with_path("/usr/bin/bwrap"));
+        let config = SandboxConfig {
+            resource_limits: ResourceLimits {
+                memory_limit: Some(256 * 1024 * 1024),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let report = runner.dry_run(&request(config)).unwrap();
+
+        assert_eq!(report.backend, DryRunBackend::Bubblewrap);
+        assert_eq!(report.argv[0], "/usr/bin/bwrap");
+        assert!(report.argv.contains(&"--unshare-all".to_string()), "{:?}", report.argv);
+        assert_eq!(report.argv.last().map(String::as_str), Some("hello"));
+        assert!(report.mounts.contains(&SandboxMount {
+            source: Some(PathBuf::from("/usr/bin")),
+            target: PathBuf::from("/usr/bin"),
+            kind: MountKind::ReadOnly,
+        }));
+        assert!(report.mounts.contains(&SandboxMount {
+            source: None,
+            target: PathBuf::from("/etc"),
+            kind: MountKind::Tmpfs,
+        }));
+        assert_eq!(report.seccomp_profile.is_some(), report.seccomp_profile_path.is_some());
+        assert_eq!(report.resource_limits.memory_limit, Some(256 * 1024 * 1024));
+        assert_eq!(report.network_access, NetworkAccess::None);
+        assert_eq!(report.timeout, 30);
+    }
+
+    // Test for reporting a command that runs on the host
+    #[test]
+    fn test_dry_run_host() {
+        let runner = SandboxRunner::new();
+        let config = SandboxConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let report = runner.dry_run(&request(config)).unwrap();
+
+        assert_eq!(report.backend, DryRunBackend::Host);
+        assert_eq!(report.argv.len(), 2);
+        assert!(report.argv[0].ends_with("/echo"), "{:?}", report.argv);
+        assert!(report.mounts.is_empty());
+        assert_eq!(report.seccomp_profile, None);
+        assert_eq!(report.seccomp_profile_path, None);
+    }
+
+    // Test for rejecting dry runs with the firecracker backend
+    #[test]
+    fn test_dry_run_firecracker() {
+        let config = SandboxConfig {
+            backend: SandboxBackend::Firecracker,
+            ..Default::default()
+        };
+        let result = SandboxRunner::new().dry_run(&request(config));
+        assert!(matches!(result, Err(McpError::InvalidRequest(_))), "{:?}", result);
+    }
+}
@@ -0,0 +1,605 @@
+//! Restricted network egress
+//!
+//! With [`NetworkAccess::Restricted`], the sandbox runs in a user and network namespace of
+//! its own whose only interface is the loopback device, so it cannot reach any host
+//! directly. Before the sandbox starts, a listening socket is created at
+//! `127.0.0.1:3128` in that namespace and handed to the gateway, which serves it with a
+//! built-in filtering proxy. The proxy is announced to the command with the usual
+//! `http_proxy`/`https_proxy`/`all_proxy` variables and is the only way out:
+//!
+//! * `CONNECT host:port` opens a tunnel (TLS and other TCP protocols)
+//! * HTTP requests in absolute form (`GET http://host/path`) are forwarded
+//!
+//! The proxy connects from the host network to destinations of the allowlist and answers
+//! `403 Forbidden` otherwise. Every attempt is logged and recorded. A connection stays with
+//! its first destination.
+//!
+//! The namespace also has a resolver that only answers for the allowlisted domains (see
+//! [`crate::dns`]).
+//!
+//! Allowlist entries are `host` or `host:port`, where the host may start with `*.` to
+//! match its subdomains (`*.example.com`) and IPv6 addresses are written in brackets.
+//! Entries without a port allow ports 80 and 443.
+//!
+//! No privileges are needed: the namespaces are created by the sandbox process itself
+//! before `exec`, and bubblewrap keeps the network namespace (`--share-net`).
+//!
+//! [`NetworkAccess::Restricted`]: crate::models::NetworkAccess::Restricted
+
+use crate::dns::{serve_dns, DnsQuery, Queries, DNS_PORT};
+use mcp_common::error::{McpError, McpResult};
+use std::ffi::CString;
+use std::net::Ipv4Addr;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{debug, info, warn};
+
+/// Port of the proxy in the network namespace of the sandbox
+pub const PROXY_PORT: u16 = 3128;
+
+/// Ports allowed for entries without a port
+pub const DEFAULT_PORTS: [u16; 2] = [80, 443];
+
+/// Maximum size of the head of a proxy request
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+/// Timeout for connecting to a destination
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Environment variables pointing commands to the proxy
+const PROXY_VARIABLES: [&str; 6] = ["http_proxy", "https_proxy", "all_proxy", "HTTP_PROXY", "HTTPS_PROXY", "ALL_PROXY"];
+
+/// Allowlist entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EgressRule {
+    /// Lower-case host name or address, `*.domain` for subdomains
+    host: String,
+    /// Allowed port (`None` for [`DEFAULT_PORTS`])
+    port: Option<u16>,
+}
+
+impl EgressRule {
+    /// Parse an allowlist entry (`host`, `host:port`, `*.domain`, `[::1]:443`)
+    pub fn parse(entry: &str) -> McpResult<Self> {
+        let invalid = || McpError::Sandbox(format!("Invalid network host '{}'", entry));
+
+        let (host, port) = split_host_port(entry.trim()).ok_or_else(invalid)?;
+        let port = match port {
+            Some(port) => Some(port.parse::<u16>().ok().filter(|port| *port > 0).ok_or_else(invalid)?),
+            None => None,
+        };
+        let name = host.strip_prefix("*.").unwrap_or(&host);
+        let name = name.strip_prefix('[').and_then(|name| name.strip_suffix(']')).unwrap_or(name);
+        let valid = !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':'))
+            && !name.starts_with('.')
+            && !name.ends_with('.');
+        if !valid {
+            return Err(invalid());
+        }
+        Ok(Self { host, port })
+    }
+
+    /// Whether a destination matches the entry
+    pub fn matches(&self, host: &str, port: u16) -> bool {
+        let port_matches = match self.port {
+            Some(allowed) => allowed == port,
+            None => DEFAULT_PORTS.contains(&port),
+        };
+        port_matches && self.matches_host(host)
+    }
+
+    /// Whether a host matches the entry on any port
+    pub fn matches_host(&self, host: &str) -> bool {
+        let host = normalize_host(host);
+        match self.host.strip_prefix("*.") {
+            Some(domain) => host.strip_suffix(domain).is_some_and(|prefix| prefix.ends_with('.')),
+            None => host == self.host,
+        }
+    }
+}
+
+/// Destinations a sandbox may connect to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EgressPolicy {
+    rules: Vec<EgressRule>,
+}
+
+impl EgressPolicy {
+    /// Policy allowing the given allowlist entries
+    pub fn new(hosts: &[String]) -> McpResult<Self> {
+        Ok(Self {
+            rules: hosts.iter().map(|entry| EgressRule::parse(entry)).collect::<McpResult<_>>()?,
+        })
+    }
+
+    /// Allowlist entries
+    pub fn rules(&self) -> &[EgressRule] {
+        &self.rules
+    }
+
+    /// Whether a destination is allowed
+    pub fn allows(&self, host: &str, port: u16) -> bool {
+        self.rules.iter().any(|rule| rule.matches(host, port))
+    }
+
+    /// Whether a host is allowed on some port (e.g. for resolving its name)
+    pub fn allows_host(&self, host: &str) -> bool {
+        self.rules.iter().any(|rule| rule.matches_host(host))
+    }
+}
+
+/// Connection attempt through the proxy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionAttempt {
+    /// Requested host
+    pub host: String,
+    /// Requested port
+    pub port: u16,
+    /// Whether the policy allowed the destination
+    pub allowed: bool,
+}
+
+/// Attempts recorded by a proxy
+type Attempts = Arc<Mutex<Vec<ConnectionAttempt>>>;
+
+/// Filtering proxy and resolver of one sandbox, stopped when dropped
+#[derive(Debug)]
+pub struct EgressProxy {
+    attempts: Attempts,
+    queries: Queries,
+    task: JoinHandle<()>,
+}
+
+impl EgressProxy {
+    /// Serve a listening socket with the proxy and a datagram socket with the resolver
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn serve(
+        policy: EgressPolicy,
+        listener: std::net::TcpListener,
+        dns_socket: std::net::UdpSocket,
+    ) -> McpResult<Self> {
+        let sockets = listen(listener, dns_socket).map_err(proxy_error)?;
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let queries = Arc::new(Mutex::new(Vec::new()));
+        let task = tokio::spawn(serve_sockets(sockets, Arc::new(policy), attempts.clone(), queries.clone()));
+        Ok(Self {
+            attempts,
+            queries,
+            task,
+        })
+    }
+
+    /// Run a command in a network namespace of its own that can only reach the proxy
+    ///
+    /// The namespace is created when the command is spawned, which must happen within a
+    /// Tokio runtime while the proxy is alive. The proxy variables of the command are
+    /// replaced.
+    pub fn attach(policy: EgressPolicy, cmd: &mut tokio::process::Command) -> McpResult<Self> {
+        let (receiver, sender) = UnixStream::pair().map_err(proxy_error)?;
+        let namespace = NamespaceSetup::new().map_err(proxy_error)?;
+        // SAFETY: the closure only makes system calls on memory prepared before the fork
+        unsafe {
+            cmd.pre_exec(move || namespace.enter(&sender));
+        }
+        let proxy_url = format!("http://{}:{}", Ipv4Addr::LOCALHOST, PROXY_PORT);
+        for name in PROXY_VARIABLES {
+            cmd.env(name, &proxy_url);
+        }
+        cmd.env_remove("no_proxy");
+        cmd.env_remove("NO_PROXY");
+
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let queries = Arc::new(Mutex::new(Vec::new()));
+        let policy = Arc::new(policy);
+        let (task_attempts, task_queries) = (attempts.clone(), queries.clone());
+        let task = tokio::spawn(async move {
+            // The sockets arrive once the command has been spawned, or never if that failed
+            let (listener, dns_socket) = match tokio::task::spawn_blocking(move || receive_fds(&receiver)).await {
+                Ok(Ok([listener, dns_socket])) => (listener, dns_socket),
+                Ok(Err(e)) => {
+                    debug!("No egress proxy sockets received: {}", e);
+                    return;
+                }
+                Err(_) => return,
+            };
+            match listen(listener.into(), dns_socket.into()) {
+                Ok(sockets) => serve_sockets(sockets, policy, task_attempts, task_queries).await,
+                Err(e) => warn!("Failed to serve egress proxy: {}", e),
+            }
+        });
+        Ok(Self {
+            attempts,
+            queries,
+            task,
+        })
+    }
+
+    /// Connection attempts so far
+    pub fn attempts(&self) -> Vec<ConnectionAttempt> {
+        self.attempts.lock().unwrap().clone()
+    }
+
+    /// DNS queries so far
+    pub fn queries(&self) -> Vec<DnsQuery> {
+        self.queries.lock().unwrap().clone()
+    }
+}
+
+impl Drop for EgressProxy {
+    fn drop(&mut self) {
+        // Dropping the task also drops the open connections
+        self.task.abort();
+    }
+}
+
+/// Register sockets with the runtime
+fn listen(
+    listener: std::net::TcpListener,
+    dns_socket: std::net::UdpSocket,
+) -> std::io::Result<(TcpListener, UdpSocket)> {
+    listener.set_nonblocking(true)?;
+    dns_socket.set_nonblocking(true)?;
+    Ok((TcpListener::from_std(listener)?, UdpSocket::from_std(dns_socket)?))
+}
+
+async fn serve_sockets(
+    (listener, dns_socket): (TcpListener, UdpSocket),
+    policy: Arc<EgressPolicy>,
+    attempts: Attempts,
+    queries: Queries,
+) {
+    tokio::join!(accept_loop(listener, policy.clone(), attempts), serve_dns(dns_socket, policy, queries));
+}
+
+async fn accept_loop(listener: TcpListener, policy: Arc<EgressPolicy>, attempts: Attempts) {
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((client, _)) => {
+                    connections.spawn(proxy_connection(client, policy.clone(), attempts.clone()));
+                }
+                Err(e) => {
+                    warn!("Egress proxy stopped accepting connections: {}", e);
+                    return;
+                }
+            },
+            Some(_) = connections.join_next() => {}
+        }
+    }
+}
+
+/// Handle one client connection
+async fn proxy_connection(mut client: TcpStream, policy: Arc<EgressPolicy>, attempts: Attempts) {
+    let Some((head, rest)) = read_request_head(&mut client).await else {
+        let _ = client.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n").await;
+        return;
+    };
+    let Some(request) = ProxyRequest::parse(&head) else {
+        debug!("Egress proxy received an unsupported request");
+        let _ = client.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n").await;
+        return;
+    };
+
+    let allowed = policy.allows(&request.host, request.port);
+    attempts.lock().unwrap().push(ConnectionAttempt {
+        host: request.host.clone(),
+        port: request.port,
+        allowed,
+    });
+    if !allowed {
+        warn!("Egress to {}:{} denied", request.host, request.port);
+        let _ = client.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n").await;
+        return;
+    }
+    info!("Egress to {}:{} allowed", request.host, request.port);
+
+    let destination = (request.host.trim_start_matches('[').trim_end_matches(']'), request.port);
+    let mut upstream = match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(destination)).await {
+        Ok(Ok(upstream)) => upstream,
+        Ok(Err(e)) => {
+            warn!("Egress to {}:{} failed: {}", request.host, request.port, e);
+            let _ = client.write_all(b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\n\r\n").await;
+            return;
+        }
+        Err(_) => {
+            warn!("Egress to {}:{} timed out", request.host, request.port);
+            let _ = client.write_all(b"HTTP/1.1 504 Gateway Timeout\r\nContent-Length: 0\r\n\r\n").await;
+            return;
+        }
+    };
+
+    let forwarded = match &request.forward_head {
+        // Tunnel: the client talks to the destination from now on
+        None => client.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").await,
+        Some(forward_head) => upstream.write_all(forward_head).await,
+    };
+    if forwarded.is_err() || upstream.write_all(&rest).await.is_err() {
+        return;
+    }
+    if let Err(e) = tokio::io::copy_bidirectional(&mut client, &mut upstream).await {
+        debug!("Egress connection to {}:{} closed: {}", request.host, request.port, e);
+    }
+}
+
+/// Read the request head, returning it and the bytes read beyond it
+async fn read_request_head(client: &mut TcpStream) -> Option<(Vec<u8>, Vec<u8>)> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            let rest = buffer.split_off(end + 4);
+            return Some((buffer, rest));
+        }
+        if buffer.len() > MAX_REQUEST_HEAD {
+            return None;
+        }
+        let read = client.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+}
+
+/// Destination of a proxy request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ProxyRequest {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    /// Head to send to the destination (`None` for a tunnel)
+    pub(crate) forward_head: Option<Vec<u8>>,
+}
+
+impl ProxyRequest {
+    /// Parse the head of a `CONNECT` or absolute-form HTTP request
+    pub(crate) fn parse(head: &[u8]) -> Option<Self> {
+        let head = std::str::from_utf8(head).ok()?;
+        let (request_line, fields) = head.split_once("\r\n")?;
+        let mut parts = request_line.split(' ');
+        let (method, target, version) = (parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() || !version.starts_with("HTTP/") {
+            return None;
+        }
+
+        if method.eq_ignore_ascii_case("CONNECT") {
+            let (host, port) = split_host_port(target)?;
+            return Some(Self {
+                host: normalize_host(&host),
+                port: port?.parse().ok()?,
+                forward_head: None,
+            });
+        }
+
+        let rest = target.strip_prefix("http://")?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        if authority.contains('@') {
+            return None;
+        }
+        let (host, port) = split_host_port(authority)?;
+        let port = match port {
+            Some(port) => port.parse().ok()?,
+            None => 80,
+        };
+        Some(Self {
+            host: normalize_host(&host),
+            port,
+            forward_head: Some(format!("{} {} {}\r\n{}", method, path, version, fields).into_bytes()),
+        })
+    }
+}
+
+/// Split `host[:port]` into a lower-case host and the port, keeping brackets of IPv6 addresses
+fn split_host_port(value: &str) -> Option<(String, Option<&str>)> {
+    let (host, port) = if value.starts_with('[') {
+        let end = value.find(']')?;
+        let port = match &value[end + 1..] {
+            "" => None,
+            rest => Some(rest.strip_prefix(':')?),
+        };
+        (&value[..=end], port)
+    } else {
+        match value.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (value, None),
+        }
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_ascii_lowercase(), port))
+}
+
+/// Lower-case host without the trailing dot of a fully qualified name
+fn normalize_host(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Namespace setup run by the sandbox process before `exec`
+#[derive(Debug)]
+struct NamespaceSetup {
+    setgroups: CString,
+    uid_map_path: CString,
+    gid_map_path: CString,
+    uid_map: Vec<u8>,
+    gid_map: Vec<u8>,
+}
+
+impl NamespaceSetup {
+    fn new() -> std::io::Result<Self> {
+        // SAFETY: getuid and getgid cannot fail
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        Ok(Self {
+            setgroups: CString::new("/proc/self/setgroups")?,
+            uid_map_path: CString::new("/proc/self/uid_map")?,
+            gid_map_path: CString::new("/proc/self/gid_map")?,
+            // Keep the IDs, so that files in the sandbox keep their owners
+            uid_map: format!("{} {} 1\n", uid, uid).into_bytes(),
+            gid_map: format!("{} {} 1\n", gid, gid).into_bytes(),
+        })
+    }
+
+    /// Enter new user and network namespaces and send the proxy and resolver sockets
+    ///
+    /// Only async-signal-safe system calls are made, as this runs between fork and exec.
+    fn enter(&self, sender: &UnixStream) -> std::io::Result<()> {
+        // SAFETY: all pointers refer to memory owned by self or the stack
+        unsafe {
+            check(libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET))?;
+            // setgroups must be denied before an unprivileged process can write the gid map
+            write_file(&self.setgroups, b"deny")?;
+            write_file(&self.uid_map_path, &self.uid_map)?;
+            write_file(&self.gid_map_path, &self.gid_map)?;
+            loopback_up()?;
+            let listener = bind_socket(libc::SOCK_STREAM, Ipv4Addr::LOCALHOST, PROXY_PORT)?;
+            // Any address, so that resolvers configured at other loopback addresses reach it as well
+            let dns_socket = match bind_socket(libc::SOCK_DGRAM, Ipv4Addr::UNSPECIFIED, DNS_PORT) {
+                Ok(dns_socket) => dns_socket,
+                Err(e) => {
+                    libc::close(listener);
+                    return Err(e);
+                }
+            };
+            let mut result = check(libc::listen(listener, 128)).map(|_| ());
+            if result.is_ok() {
+                result = send_fds(sender.as_raw_fd(), [listener, dns_socket]);
+            }
+            libc::close(listener);
+            libc::close(dns_socket);
+            result
+        }
+    }
+}
+
+fn check(result: libc::c_int) -> std::io::Result<libc::c_int> {
+    if result == -1 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+unsafe fn write_file(path: &CString, contents: &[u8]) -> std::io::Result<()> {
+    let fd = check(libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC))?;
+    let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+    libc::close(fd);
+    if written != contents.len() as isize {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Bring up the loopback device of the current network namespace
+unsafe fn loopback_up() -> std::io::Result<()> {
+    let fd = check(libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0))?;
+    let mut request: libc::ifreq = std::mem::zeroed();
+    for (target, source) in request.ifr_name.iter_mut().zip(b"lo\0") {
+        *target = *source as libc::c_char;
+    }
+    let mut result = check(libc::ioctl(fd, libc::SIOCGIFFLAGS as _, &mut request));
+    if result.is_ok() {
+        request.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+        result = check(libc::ioctl(fd, libc::SIOCSIFFLAGS as _, &request));
+    }
+    libc::close(fd);
+    result.map(|_| ())
+}
+
+/// IPv4 socket bound to an address
+unsafe fn bind_socket(socket_type: libc::c_int, address: Ipv4Addr, port: u16) -> std::io::Result<RawFd> {
+    let fd = check(libc::socket(libc::AF_INET, socket_type | libc::SOCK_CLOEXEC, 0))?;
+    let address = libc::sockaddr_in {
+        sin_family: libc::AF_INET as libc::sa_family_t,
+        sin_port: port.to_be(),
+        sin_addr: libc::in_addr {
+            s_addr: u32::from(address).to_be(),
+        },
+        sin_zero: [0; 8],
+    };
+    let bound = check(libc::bind(
+        fd,
+        (&address as *const libc::sockaddr_in).cast(),
+        std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+    ));
+    if let Err(e) = bound {
+        libc::close(fd);
+        return Err(e);
+    }
+    Ok(fd)
+}
+
+/// Buffer for one control message carrying the sockets
+#[repr(C, align(8))]
+struct ControlBuffer([u8; 64]);
+
+/// Size of the data of the control message
+const SOCKETS_LEN: u32 = (2 * std::mem::size_of::<RawFd>()) as u32;
+
+/// Send the proxy and resolver sockets over a Unix socket
+unsafe fn send_fds(socket: RawFd, fds: [RawFd; 2]) -> std::io::Result<()> {
+    let mut data = [0u8; 1];
+    let mut iov = libc::iovec {
+        iov_base: data.as_mut_ptr().cast(),
+        iov_len: data.len(),
+    };
+    let mut control = ControlBuffer([0; 64]);
+    let mut message: libc::msghdr = std::mem::zeroed();
+    message.msg_iov = &mut iov;
+    message.msg_iovlen = 1;
+    message.msg_control = control.0.as_mut_ptr().cast();
+    message.msg_controllen = libc::CMSG_SPACE(SOCKETS_LEN) as _;
+    let header = libc::CMSG_FIRSTHDR(&message);
+    (*header).cmsg_level = libc::SOL_SOCKET;
+    (*header).cmsg_type = libc::SCM_RIGHTS;
+    (*header).cmsg_len = libc::CMSG_LEN(SOCKETS_LEN) as _;
+    std::ptr::write_unaligned(libc::CMSG_DATA(header).cast::<[RawFd; 2]>(), fds);
+    if libc::sendmsg(socket, &message, libc::MSG_NOSIGNAL) == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Receive the sockets sent with [`send_fds`]
+fn receive_fds(socket: &UnixStream) -> std::io::Result<[OwnedFd; 2]> {
+    let mut data = [0u8; 1];
+    let mut iov = libc::iovec {
+        iov_base: data.as_mut_ptr().cast(),
+        iov_len: data.len(),
+    };
+    let mut control = ControlBuffer([0; 64]);
+    // SAFETY: the message points to live buffers of the given lengths
+    unsafe {
+        let mut message: libc::msghdr = std::mem::zeroed();
+        message.msg_iov = &mut iov;
+        message.msg_iovlen = 1;
+        message.msg_control = control.0.as_mut_ptr().cast();
+        message.msg_controllen = control.0.len() as _;
+        if libc::recvmsg(socket.as_raw_fd(), &mut message, libc::MSG_CMSG_CLOEXEC) == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let header = libc::CMSG_FIRSTHDR(&message);
+        if header.is_null()
+            || (*header).cmsg_level != libc::SOL_SOCKET
+            || (*header).cmsg_type != libc::SCM_RIGHTS
+            || (*header).cmsg_len as usize != libc::CMSG_LEN(SOCKETS_LEN) as usize
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "no sockets received"));
+        }
+        let [listener, dns_socket] = std::ptr::read_unaligned(libc::CMSG_DATA(header).cast::<[RawFd; 2]>());
+        Ok([OwnedFd::from_raw_fd(listener), OwnedFd::from_raw_fd(dns_socket)])
+    }
+}
+
+fn proxy_error(e: std::io::Error) -> McpError {
+    McpError::Sandbox(format!("Egress proxy error: {}", e))
+}
@@ -0,0 +1,163 @@
+#[cfg(test)]
+mod tests {
+    use crate::egress::{ConnectionAttempt, EgressPolicy, EgressProxy, EgressRule, ProxyRequest};
+    use mcp_common::error::McpError;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Destination answering every connection with the bytes of its request head
+    async fn echo_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buffer = [0u8; 1024];
+                let read = stream.read(&mut buffer).await.unwrap_or(0);
+                let _ = stream.write_all(b"HTTP/1.0 200 OK\r\n\r\n").await;
+                let _ = stream.write_all(&buffer[..read]).await;
+            }
+        });
+        port
+    }
+
+    async fn request(proxy_port: u16, request: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", proxy_port)).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    // Test for matching destinations against the allowlist
+    #[test]
+    fn test_egress_policy() {
+        let policy = EgressPolicy::new(&[
+            "API.example.com".to_string(),
+            "*.internal.example:8443".to_string(),
+            "[::1]:9000".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(policy.rules().len(), 3);
+
+        // Entries without a port allow HTTP and HTTPS
+        assert!(policy.allows("api.example.com", 443));
+        assert!(policy.allows("api.example.com.", 80));
+        assert!(!policy.allows("api.example.com", 22));
+        assert!(!policy.allows("evil-api.example.com", 443));
+        // Wildcards match subdomains only
+        assert!(policy.allows("build.internal.example", 8443));
+        assert!(policy.allows("a.b.internal.example", 8443));
+        assert!(!policy.allows("internal.example", 8443));
+        assert!(!policy.allows("build.internal.example", 443));
+        assert!(policy.allows("[::1]", 9000));
+        assert!(!EgressPolicy::default().allows("api.example.com", 443));
+
+        for entry in ["", ":443", "example.com:0", "example.com:https", "exa mple.com", "*.", "[::1", "a/b"] {
+            match EgressRule::parse(entry) {
+                Err(McpError::Sandbox(_)) => {}
+                other => panic!("unexpected result for '{}': {:?}", entry, other),
+            }
+        }
+    }
+
+    // Test for parsing proxy requests
+    #[test]
+    fn test_proxy_request() {
+        let request = ProxyRequest::parse(b"CONNECT Api.Example.com:443 HTTP/1.1\r\nHost: api\r\n\r\n").unwrap();
+        assert_eq!(request.host, "api.example.com");
+        assert_eq!(request.port, 443);
+        assert_eq!(request.forward_head, None);
+
+        let request =
+            ProxyRequest::parse(b"GET http://example.com/path?q=1 HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+        assert_eq!(request.host, "example.com");
+        assert_eq!(request.port, 80);
+        assert_eq!(
+            request.forward_head.as_deref(),
+            Some(&b"GET /path?q=1 HTTP/1.1\r\nHost: example.com\r\n\r\n"[..])
+        );
+        let request = ProxyRequest::parse(b"GET http://example.com:8080 HTTP/1.0\r\n\r\n").unwrap();
+        assert_eq!(request.port, 8080);
+        assert_eq!(request.forward_head.as_deref(), Some(&b"GET / HTTP/1.0\r\n\r\n"[..]));
+
+        for head in [
+            &b"CONNECT example.com HTTP/1.1\r\n\r\n"[..],
+            b"GET /path HTTP/1.1\r\n\r\n",
+            b"GET https://example.com/ HTTP/1.1\r\n\r\n",
+            b"GET http://user@example.com/ HTTP/1.1\r\n\r\n",
+            b"GET http://example.com/ SMTP\r\n\r\n",
+        ] {
+            assert_eq!(ProxyRequest::parse(head), None, "{}", String::from_utf8_lossy(head));
+        }
+    }
+
+    // Test for forwarding allowed connections and refusing the others
+    #[tokio::test]
+    async fn test_proxy_connections() {
+        let destination = echo_server().await;
+        let policy = EgressPolicy::new(&[format!("127.0.0.1:{}", destination)]).unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy_port = listener.local_addr().unwrap().port();
+        let proxy = EgressProxy::serve(policy, listener, std::net::UdpSocket::bind("127.0.0.1:0").unwrap()).unwrap();
+
+        let response = request(proxy_port, &format!("CONNECT 127.0.0.1:{} HTTP/1.1\r\n\r\nhello", destination)).await;
+        assert!(response.starts_with("HTTP/1.1 200 Connection established\r\n\r\n"));
+        assert!(response.ends_with("hello"));
+
+        let response = request(
+            proxy_port,
+            &format!("GET http://127.0.0.1:{}/status HTTP/1.0\r\nHost: example\r\n\r\n", destination),
+        )
+        .await;
+        assert!(response.ends_with("GET /status HTTP/1.0\r\nHost: example\r\n\r\n"));
+
+        let response = request(proxy_port, "CONNECT 127.0.0.1:22 HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden"));
+
+        assert_eq!(
+            proxy.attempts(),
+            vec![
+                ConnectionAttempt {
+                    host: "127.0.0.1".to_string(),
+                    port: destination,
+                    allowed: true
+                },
+                ConnectionAttempt {
+                    host: "127.0.0.1".to_string(),
+                    port: destination,
+                    allowed: true
+                },
+                ConnectionAttempt {
+                    host: "127.0.0.1".to_string(),
+                    port: 22,
+                    allowed: false
+                },
+            ]
+        );
+    }
+
+    // Test for running a command that can only reach the network through the proxy
+    #[tokio::test]
+    async fn test_attach_network_namespace() {
+        let destination = echo_server().await;
+        let policy = EgressPolicy::new(&[format!("127.0.0.1:{}", destination)]).unwrap();
+        let script = format!(
+            "exec 3<>/dev/tcp/127.0.0.1/3128 && printf 'GET http://127.0.0.1:{port}/ HTTP/1.0\\r\\n\\r\\n' >&3; \
+             cat <&3; (exec 4<>/dev/tcp/127.0.0.1/{port}) 2>/dev/null && echo direct || echo isolated; \
+             echo $http_proxy",
+            port = destination
+        );
+        let mut cmd = tokio::process::Command::new("bash");
+        cmd.arg("-c").arg(script).env("no_proxy", "*");
+        let proxy = EgressProxy::attach(policy, &mut cmd).unwrap();
+
+        let output = cmd.output().await.unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("GET / HTTP/1.0"), "{}", stdout);
+        // The host is not reachable from the namespace
+        assert!(stdout.contains("isolated"), "{}", stdout);
+        assert!(stdout.contains("http://127.0.0.1:3128"), "{}", stdout);
+        assert_eq!(proxy.attempts().len(), 1);
+        assert!(proxy.queries().is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+//! Resolution of the executable of a command
+//!
+//! Before a command is started, its name is resolved to the absolute path of an executable
+//! file, so that a missing or relative executable is rejected with a clear error instead of
+//! failing inside the sandbox, and so that policies can check the file that will actually
+//! run. Names without a `/` are looked up in the `PATH` of the command's environment
+//! ([`DEFAULT_SANDBOX_PATH`] if it has none); relative paths such as `./build.sh` and paths
+//! with `..` components are rejected.
+//!
+//! For bubblewrap, only paths the sandbox mounts are considered: a candidate must be below
+//! one of the read-write or read-only paths and not below a denied path. It is looked up at
+//! its host path (the task workspace for [`WORKSPACE_MOUNT_POINT`](crate::workspace::WORKSPACE_MOUNT_POINT)),
+//! and the resolved path is the one the sandbox sees. Containers and microVMs run the command
+//! in their own file system, which cannot be inspected beforehand.
+
+use crate::models::SandboxConfig;
+use mcp_common::error::{McpError, McpResult};
+use std::collections::HashMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+
+/// Search path of commands whose environment has no `PATH`
+pub const DEFAULT_SANDBOX_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// Absolute path of the executable a command runs
+///
+/// With a sandbox configuration, only paths mounted into the sandbox are considered.
+pub fn resolve_executable(
+    command: &str,
+    env: &HashMap<String, String>,
+    sandbox: Option<&SandboxConfig>,
+) -> McpResult<PathBuf> {
+    if command.is_empty() {
+        return Err(McpError::InvalidRequest("Command is not specified".to_string()));
+    }
+    let candidates: Vec<PathBuf> = if command.contains('/') {
+        let path = Path::new(command);
+        if !path.is_absolute() {
+            return Err(McpError::InvalidRequest(format!(
+                "Relative executable paths are not allowed: {}",
+                command
+            )));
+        }
+        if path.components().any(|component| matches!(component, Component::ParentDir | Component::CurDir)) {
+            return Err(McpError::InvalidRequest(format!("Executable path must be normalized: {}", command)));
+        }
+        vec![path.to_path_buf()]
+    } else {
+        let search_path = env.get("PATH").map(String::as_str).unwrap_or(DEFAULT_SANDBOX_PATH);
+        search_path
+            .split(':')
+            // Empty and relative entries would depend on the working directory
+            .filter(|dir| dir.starts_with('/'))
+            .map(|dir| Path::new(dir).join(command))
+            .collect()
+    };
+
+    for path in candidates {
+        let host_path = match sandbox {
+            Some(config) => match sandbox_host_path(config, &path) {
+                Some(host_path) => host_path,
+                None => continue,
+            },
+            None => path.clone(),
+        };
+        let Ok(metadata) = std::fs::metadata(&host_path) else {
+            continue;
+        };
+        if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0 {
+            return Ok(path);
+        }
+    }
+    Err(McpError::InvalidRequest(match sandbox {
+        Some(_) => format!("Executable not found in the sandbox: {}", command),
+        None => format!("Executable not found: {}", command),
+    }))
+}
+
+/// Host path of a path the sandbox mounts, `None` if the sandbox does not see it
+fn sandbox_host_path(config: &SandboxConfig, path: &Path) -> Option<PathBuf> {
+    // Denied paths are mounted last and hide everything below them
+    if config.denied_paths.iter().any(|denied| path.starts_with(denied)) {
+        return None;
+    }
+    // The innermost mount wins; read-only paths are mounted after the read-write ones
+    let mount = config
+        .rw_paths
+        .iter()
+        .chain(&config.ro_paths)
+        .filter(|mount| path.starts_with(mount))
+        .max_by_key(|mount| mount.components().count())?;
+    let relative = path.strip_prefix(mount).ok()?;
+    Some(config.host_path(mount).join(relative))
+}
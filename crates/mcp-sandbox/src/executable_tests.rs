@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use crate::executable::resolve_executable;
+    use crate::models::{ExecutionRequest, SandboxConfig};
+    use crate::runner::SandboxRunner;
+    use mcp_common::error::McpError;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+
+    fn write_file(path: &Path, mode: u32) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    // Test for looking up commands on the host
+    #[test]
+    fn test_resolve_on_host() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("bin");
+        write_file(&bin.join("tool"), 0o755);
+        write_file(&dir.path().join("other/tool"), 0o755);
+        write_file(&bin.join("data"), 0o644);
+        let env = HashMap::from([(
+            "PATH".to_string(),
+            format!("relative:{}:{}", bin.display(), dir.path().join("other").display()),
+        )]);
+
+        // The first directory of the search path with an executable file wins
+        assert_eq!(resolve_executable("tool", &env, None).unwrap(), bin.join("tool"));
+        let absolute = dir.path().join("other/tool");
+        assert_eq!(resolve_executable(absolute.to_str().unwrap(), &env, None).unwrap(), absolute);
+        // Without a PATH, the default search path is used
+        let sh = resolve_executable("sh", &HashMap::new(), None).unwrap();
+        assert!(sh.is_absolute() && sh.ends_with("sh"), "{}", sh.display());
+
+        for command in ["data", "missing", "./tool", "bin/tool", "/usr/bin/../bin/sh", ""] {
+            match resolve_executable(command, &env, None) {
+                Err(McpError::InvalidRequest(_)) => {}
+                other => panic!("unexpected result for {:?}: {:?}", command, other),
+            }
+        }
+    }
+
+    // Test for looking up commands in the paths a sandbox mounts
+    #[test]
+    fn test_resolve_in_sandbox() {
+        let dir = tempfile::tempdir().unwrap();
+        let tools = dir.path().join("tools");
+        write_file(&tools.join("tool"), 0o755);
+        write_file(&tools.join("secret/tool"), 0o755);
+        let workspace = dir.path().join("workspace");
+        write_file(&workspace.join("build.sh"), 0o755);
+        let config = SandboxConfig {
+            rw_paths: vec![PathBuf::from("/workspace")],
+            ro_paths: vec![tools.clone()],
+            denied_paths: vec![tools.join("secret")],
+            workspace_dir: Some(workspace),
+            ..Default::default()
+        };
+        let env = HashMap::from([("PATH".to_string(), format!("/usr/bin:{}", tools.display()))]);
+
+        // Directories the sandbox does not mount are skipped
+        assert_eq!(resolve_executable("tool", &env, Some(&config)).unwrap(), tools.join("tool"));
+        // The task workspace is looked up on the host, the path is the one of the sandbox
+        assert_eq!(
+            resolve_executable("/workspace/build.sh", &env, Some(&config)).unwrap(),
+            PathBuf::from("/workspace/build.sh")
+        );
+        let hidden = tools.join("secret/tool");
+        assert!(resolve_executable(hidden.to_str().unwrap(), &env, Some(&config)).is_err());
+        assert!(resolve_executable("sh", &env, Some(&config)).is_err());
+    }
+
+    // Test for rejecting commands without an executable before they are started
+    #[tokio::test]
+    async fn test_run_unresolvable_command() {
+        let runner = SandboxRunner::new();
+        for command in ["mcp-no-such-command", "./script.sh"] {
+            let request = ExecutionRequest {
+                command: command.to_string(),
+                args: Vec::new(),
+                env: HashMap::new(),
+                cwd: None,
+                timeout: 10,
+                sandbox_config: SandboxConfig {
+                    enabled: false,
+                    ..Default::default()
+                },
+                input_files: Vec::new(),
+                diff_workspace: false,
+            };
+            match runner.run(request).await {
+                Err(McpError::InvalidRequest(_)) => {}
+                other => panic!("unexpected result for {}: {:?}", command, other),
+            }
+        }
+    }
+}
@@ -1,10 +1,18 @@
-use crate::models::{ExecutionRequest, ExecutionResult, SandboxConfig};
+use crate::concurrency::{ConcurrencyLimiter, ExecutionPermit};
+use crate::dry_run::DryRunReport;
+use crate::models::{ExecutionRequest, ExecutionResult, OutputChunk, ResourceSample, SandboxConfig};
+use crate::plan::{ExecutionPlan, PlanResult};
+use crate::process::TaskGuard;
+use crate::retry::RetryPolicy;
 use crate::runner::SandboxRunner;
+use crate::staging::{ContentStore, StagedFile};
 use mcp_common::error::{McpError, McpResult};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tracing::info;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
 use std::fmt;
 
 /// Executor for running commands in a sandbox
@@ -13,13 +21,36 @@ pub struct CommandExecutor {
     runner: Arc<SandboxRunner>,
     default_timeout: u32,
     default_sandbox_config: SandboxConfig,
+    /// Task the executed commands belong to (see [`Self::register_task`])
+    task_id: Option<String>,
+    /// Time between SIGTERM and SIGKILL when a task is cancelled
+    cancel_grace_period: Duration,
+    /// Limiter of concurrently running commands, shared by all derived executors
+    concurrency_limiter: Arc<ConcurrencyLimiter>,
+    /// Tenant the executed commands count against (see [`Self::with_tenant_id`])
+    tenant_id: Option<String>,
+    /// Files staged into the task workspace before each command (see [`Self::with_input_files`])
+    input_files: Vec<StagedFile>,
+    /// Whether the changed files are reported in the results (see [`Self::with_workspace_diff`])
+    diff_workspace: bool,
+    /// Retries of transient failures (see [`crate::retry`])
+    retry_policy: RetryPolicy,
 }
 
+/// Default time a cancelled task has to exit after SIGTERM
+const DEFAULT_CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
 impl fmt::Debug for CommandExecutor {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("CommandExecutor")
             .field("default_timeout", &self.default_timeout)
             .field("default_sandbox_config", &self.default_sandbox_config)
+            .field("task_id", &self.task_id)
+            .field("cancel_grace_period", &self.cancel_grace_period)
+            .field("concurrency_limiter", &self.concurrency_limiter)
+            .field("tenant_id", &self.tenant_id)
+            .field("input_files", &self.input_files.len())
+            .field("retry_policy", &self.retry_policy)
             .finish()
     }
 }
@@ -31,6 +62,13 @@ impl CommandExecutor {
             runner: Arc::new(SandboxRunner::new()),
             default_timeout: 30, // 30 seconds
             default_sandbox_config: SandboxConfig::default(),
+            task_id: None,
+            cancel_grace_period: DEFAULT_CANCEL_GRACE_PERIOD,
+            concurrency_limiter: Arc::new(ConcurrencyLimiter::default()),
+            tenant_id: None,
+            input_files: Vec::new(),
+            diff_workspace: false,
+            retry_policy: RetryPolicy::default(),
         }
     }
 
@@ -40,6 +78,13 @@ impl CommandExecutor {
             runner: Arc::new(SandboxRunner::new()),
             default_timeout: timeout,
             default_sandbox_config: sandbox_config,
+            task_id: None,
+            cancel_grace_period: DEFAULT_CANCEL_GRACE_PERIOD,
+            concurrency_limiter: Arc::new(ConcurrencyLimiter::default()),
+            tenant_id: None,
+            input_files: Vec::new(),
+            diff_workspace: false,
+            retry_policy: RetryPolicy::default(),
         }
     }
 
@@ -51,6 +96,34 @@ impl CommandExecutor {
         env: HashMap<String, String>,
         cwd: Option<String>,
         timeout: Option<u32>,
+    ) -> McpResult<ExecutionResult> {
+        self.execute_with_output(command, args, env, cwd, timeout, None).await
+    }
+
+    /// Execute a command, sending its stdout/stderr chunks to `output` while it runs
+    ///
+    /// The receiving half of the channel is an async stream of the live output; see
+    /// [`SandboxRunner::run_streaming`].
+    pub async fn execute_streaming(
+        &self,
+        command: &str,
+        args: Vec<String>,
+        env: HashMap<String, String>,
+        cwd: Option<String>,
+        timeout: Option<u32>,
+        output: mpsc::Sender<OutputChunk>,
+    ) -> McpResult<ExecutionResult> {
+        self.execute_with_output(command, args, env, cwd, timeout, Some(output)).await
+    }
+
+    async fn execute_with_output(
+        &self,
+        command: &str,
+        args: Vec<String>,
+        env: HashMap<String, String>,
+        cwd: Option<String>,
+        timeout: Option<u32>,
+        output: Option<mpsc::Sender<OutputChunk>>,
     ) -> McpResult<ExecutionResult> {
         let timeout = timeout.unwrap_or(self.default_timeout);
         
@@ -75,28 +148,292 @@ impl CommandExecutor {
             cwd,
             timeout,
             sandbox_config: self.default_sandbox_config.clone(),
+            input_files: self.input_files.clone(),
+            diff_workspace: self.diff_workspace,
         };
         
-        self.runner.run(request).await
+        let mut attempt = 1;
+        loop {
+            let result = self.run_attempt(request.clone(), output.clone()).await;
+            match result {
+                Err(e) if self.retry_policy.should_retry(&e, attempt) && !self.is_cancelled() => {
+                    let backoff = self.retry_policy.backoff(attempt);
+                    warn!(
+                        "Transient failure of {} (attempt {} of {}), retrying in {:?}: {}",
+                        command, attempt, self.retry_policy.max_attempts, backoff, e
+                    );
+                    if let Some(task_id) = &self.task_id {
+                        self.runner.processes().record_retry(task_id);
+                    }
+                    if !self.back_off(backoff).await {
+                        return Err(e);
+                    }
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Run one attempt of a command, holding a permit while it runs
+    async fn run_attempt(
+        &self,
+        request: ExecutionRequest,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<ExecutionResult> {
+        let _permit = self.acquire_permit().await?;
+        self.runner.run_task(request, self.task_id.as_deref(), output).await
+    }
+
+    /// Wait before a retry; returns false if the task is cancelled in the meantime
+    async fn back_off(&self, backoff: Duration) -> bool {
+        let cancellation = self.task_id.as_deref().and_then(|task_id| self.runner.processes().cancellation(task_id));
+        match cancellation {
+            Some(mut cancellation) => tokio::select! {
+                _ = tokio::time::sleep(backoff) => true,
+                _ = cancellation.wait_for(|cancelled| *cancelled) => false,
+            },
+            None => {
+                tokio::time::sleep(backoff).await;
+                true
+            }
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.task_id.as_deref().is_some_and(|task_id| self.is_task_cancelled(task_id))
     }
     
+    /// Execute the steps of a plan (see [`crate::plan`]), sending their output to `output`
+    ///
+    /// Steps without a timeout get the default timeout. The plan holds one concurrency
+    /// permit while it runs, since its steps run one at a time.
+    pub async fn execute_plan(
+        &self,
+        plan: &ExecutionPlan,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<PlanResult> {
+        info!("Executing plan with {} steps", plan.steps.len());
+        if let Some(step) = plan.steps.iter().find(|step| step.command.is_empty()) {
+            return Err(McpError::InvalidRequest(format!("Command of plan step '{}' is not specified", step.id)));
+        }
+        let mut plan = plan.clone();
+        for step in &mut plan.steps {
+            if step.timeout == 0 {
+                step.timeout = self.default_timeout;
+            }
+        }
+        if plan.steps.iter().any(|step| step.timeout == 0) {
+            return Err(McpError::InvalidRequest("Timeout must be at least 1 second".to_string()));
+        }
+
+        let _permit = self.acquire_permit().await?;
+        self.runner
+            .run_plan(
+                &plan,
+                self.default_sandbox_config.clone(),
+                self.input_files.clone(),
+                self.task_id.as_deref(),
+                output,
+            )
+            .await
+    }
+
+    /// Report how a command would be started with the default sandbox configuration, without
+    /// starting it (see [`crate::dry_run`])
+    pub fn dry_run(
+        &self,
+        command: &str,
+        args: Vec<String>,
+        env: HashMap<String, String>,
+        cwd: Option<String>,
+        timeout: Option<u32>,
+    ) -> McpResult<DryRunReport> {
+        let timeout = timeout.unwrap_or(self.default_timeout);
+        if command.is_empty() {
+            return Err(McpError::InvalidRequest("Command is not specified".to_string()));
+        }
+        if timeout == 0 {
+            return Err(McpError::InvalidRequest("Timeout must be at least 1 second".to_string()));
+        }
+        let request = ExecutionRequest {
+            command: command.to_string(),
+            args,
+            env,
+            cwd: cwd.map(PathBuf::from),
+            timeout,
+            sandbox_config: self.default_sandbox_config.clone(),
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+        self.runner.dry_run(&request)
+    }
+
+    /// Wait for a permit unless the task is cancelled while queued
+    async fn acquire_permit(&self) -> McpResult<ExecutionPermit> {
+        let acquire = self.concurrency_limiter.acquire(self.tenant_id.as_deref());
+        let cancellation = self.task_id.as_deref().and_then(|task_id| self.runner.processes().cancellation(task_id));
+        match cancellation {
+            Some(mut cancellation) => tokio::select! {
+                permit = acquire => permit,
+                _ = cancellation.wait_for(|cancelled| *cancelled) => {
+                    Err(McpError::Execution("Execution cancelled while queued".to_string()))
+                }
+            },
+            None => acquire.await,
+        }
+    }
+
+    /// Absolute path of the executable a command runs with the default sandbox configuration
+    ///
+    /// Returns `None` for backends that resolve the command in their own file system.
+    pub fn resolve_executable(&self, command: &str, env: &HashMap<String, String>) -> McpResult<Option<PathBuf>> {
+        self.runner.resolve_executable(command, env, &self.default_sandbox_config)
+    }
+
+    /// Default sandbox configuration
+    pub fn sandbox_config(&self) -> &SandboxConfig {
+        &self.default_sandbox_config
+    }
+
     /// Create an Executor with updated sandbox configuration
     pub fn with_sandbox_config(&self, config: SandboxConfig) -> Self {
         Self {
-            runner: self.runner.clone(),
-            default_timeout: self.default_timeout,
             default_sandbox_config: config,
+            ..self.clone()
         }
     }
     
     /// Create an Executor with updated timeout setting
     pub fn with_timeout(&self, timeout: u32) -> Self {
         Self {
-            runner: self.runner.clone(),
             default_timeout: timeout,
-            default_sandbox_config: self.default_sandbox_config.clone(),
+            ..self.clone()
         }
     }
+
+    /// Create an Executor whose commands belong to a task registered with [`Self::register_task`]
+    pub fn with_task_id(&self, task_id: &str) -> Self {
+        Self {
+            task_id: Some(task_id.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// Create an Executor whose commands count against the concurrency limit of a tenant
+    pub fn with_tenant_id(&self, tenant_id: &str) -> Self {
+        Self {
+            tenant_id: Some(tenant_id.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// Create an Executor that stages files into the task workspace before each command
+    ///
+    /// The files need a per-task workspace (see [`crate::workspace`]) and a task ID; blobs are
+    /// read from the content store of the runner (see [`crate::staging`]).
+    pub fn with_input_files(&self, input_files: Vec<StagedFile>) -> Self {
+        Self {
+            input_files,
+            ..self.clone()
+        }
+    }
+
+    /// Create an Executor that reports the files each command changes in its result (see
+    /// [`crate::workspace_diff`])
+    pub fn with_workspace_diff(&self, diff_workspace: bool) -> Self {
+        Self {
+            diff_workspace,
+            ..self.clone()
+        }
+    }
+
+    /// Content store the blobs of staged files are read from, if configured
+    pub fn content_store(&self) -> Option<&ContentStore> {
+        self.runner.content_store()
+    }
+
+    /// Create an Executor that retries commands after transient failures by a policy
+    pub fn with_retry_policy(&self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy,
+            ..self.clone()
+        }
+    }
+
+    /// Create an Executor that runs commands only when the limiter permits
+    ///
+    /// The limiter is shared by all executors derived from the returned one.
+    pub fn with_concurrency_limiter(&self, limiter: ConcurrencyLimiter) -> Self {
+        Self {
+            concurrency_limiter: Arc::new(limiter),
+            ..self.clone()
+        }
+    }
+
+    /// Limiter of concurrently running commands
+    pub fn concurrency_limiter(&self) -> &ConcurrencyLimiter {
+        &self.concurrency_limiter
+    }
+
+    /// Create an Executor with updated time between SIGTERM and SIGKILL on cancellation
+    pub fn with_cancel_grace_period(&self, grace_period: Duration) -> Self {
+        Self {
+            cancel_grace_period: grace_period,
+            ..self.clone()
+        }
+    }
+
+    /// Register a task so that its commands can be cancelled
+    ///
+    /// The task stays registered (and [`Self::cancel_task`] waits) until the guard is dropped.
+    pub fn register_task(&self, task_id: &str) -> McpResult<TaskGuard> {
+        self.runner.processes().register(task_id)
+    }
+
+    /// Terminate the running command of a task and wait until the task has finished
+    ///
+    /// The process tree receives SIGTERM, and SIGKILL after the grace period. Returns
+    /// false if the task is not registered (e.g. it has already finished).
+    pub async fn cancel_task(&self, task_id: &str) -> McpResult<bool> {
+        self.runner.processes().cancel(task_id, self.cancel_grace_period).await
+    }
+
+    /// Pause the running command of a task (see [`crate::process`])
+    ///
+    /// Returns false if the task is not registered.
+    pub fn pause_task(&self, task_id: &str) -> McpResult<bool> {
+        self.runner.processes().pause(task_id)
+    }
+
+    /// Resume the paused command of a task
+    ///
+    /// Returns false if the task is not registered.
+    pub fn resume_task(&self, task_id: &str) -> McpResult<bool> {
+        self.runner.processes().resume(task_id)
+    }
+
+    /// Whether the command of a registered task is paused
+    pub fn is_task_paused(&self, task_id: &str) -> bool {
+        self.runner.processes().is_paused(task_id)
+    }
+
+    /// Current resource usage of the running command of a task (see [`crate::usage`])
+    ///
+    /// Returns `None` unless the command runs in a task cgroup.
+    pub fn sample_task_usage(&self, task_id: &str) -> Option<ResourceSample> {
+        self.runner.processes().usage_sample(task_id)
+    }
+
+    /// Number of times a command of a registered task was retried (see [`crate::retry`])
+    pub fn task_retries(&self, task_id: &str) -> u32 {
+        self.runner.processes().retries(task_id)
+    }
+
+    /// Whether cancellation of a registered task has been requested
+    pub fn is_task_cancelled(&self, task_id: &str) -> bool {
+        self.runner.processes().is_cancelled(task_id)
+    }
 }
 
 impl Default for CommandExecutor {
@@ -0,0 +1,543 @@
+//! Firecracker microVM backend
+//!
+//! Commands of tasks whose sandbox configuration selects
+//! [`SandboxBackend::Firecracker`](crate::models::SandboxBackend::Firecracker)
+//! run in a microVM booted from a pre-built kernel and root filesystem image, which gives
+//! VM-level isolation for untrusted tenants. Each command gets a fresh VM:
+//!
+//! 1. `firecracker --no-api --config-file` boots the VM with the root filesystem attached
+//!    read-only and a vsock device whose host side is a Unix socket.
+//! 2. The gateway connects to the guest agent on [`FirecrackerConfig::agent_port`] through
+//!    that socket (`CONNECT <port>` handshake), retrying until the agent is up.
+//! 3. The workspace (the files below the read-write paths of the sandbox configuration) and
+//!    the command are sent to the agent. Firecracker has no virtio-fs, so the workspace is
+//!    copied into the guest; changes made in the guest are not copied back.
+//! 4. The agent runs the command and sends its output while it runs, followed by the exit
+//!    code and resource usage measured in the guest.
+//! 5. The VM is killed.
+//!
+//! The image must run an agent listening on the vsock port that speaks the following
+//! protocol. Every message is a frame of a kind byte, a big-endian `u32` payload length and
+//! the payload (at most [`MAX_FRAME_BYTES`]):
+//!
+//! | Kind            | Direction     | Payload                                               |
+//! |-----------------|---------------|-------------------------------------------------------|
+//! | `FRAME_FILE`    | host -> guest | JSON `{"path": ..., "mode": ...}` of a workspace file |
+//! | `FRAME_DATA`    | host -> guest | contents of the last file (any number of frames)      |
+//! | `FRAME_REQUEST` | host -> guest | JSON `{"command", "args", "env", "cwd", "timeout_secs"}` |
+//! | `FRAME_STDOUT`  | guest -> host | stdout bytes                                          |
+//! | `FRAME_STDERR`  | guest -> host | stderr bytes                                          |
+//! | `FRAME_EXIT`    | guest -> host | JSON `{"exit_code", "resource_usage", "error"}`       |
+//!
+//! The VM has no network interface: commands run without network access whatever the
+//! sandbox configuration requests.
+
+use crate::models::{ExecutionRequest, NetworkAccess, OutputChunk, ResourceUsage, SandboxConfig};
+use crate::output_log::OutputStream;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{current_timestamp_ms, get_env_var_or, parse_positive};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// Workspace file header (JSON)
+pub const FRAME_FILE: u8 = 1;
+/// Contents of the last workspace file
+pub const FRAME_DATA: u8 = 2;
+/// Command to run (JSON); the last frame sent by the host
+pub const FRAME_REQUEST: u8 = 3;
+/// Stdout of the command
+pub const FRAME_STDOUT: u8 = 16;
+/// Stderr of the command
+pub const FRAME_STDERR: u8 = 17;
+/// Exit status of the command (JSON); the last frame sent by the guest
+pub const FRAME_EXIT: u8 = 18;
+
+/// Maximum payload size of a frame
+pub const MAX_FRAME_BYTES: usize = 1024 * 1024;
+
+/// Context identifier of the guest (the same for every VM, as each has its own socket)
+const GUEST_CID: u32 = 3;
+/// Smallest memory size a VM is booted with
+const MIN_MEM_SIZE_MIB: u64 = 128;
+/// Interval between connection attempts while the VM boots
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Sequence number for unique VM directories
+static NEXT_VM: AtomicU64 = AtomicU64::new(0);
+
+/// Firecracker backend settings
+#[derive(Debug, Clone)]
+pub struct FirecrackerConfig {
+    /// Firecracker binary
+    pub firecracker_path: PathBuf,
+    /// Uncompressed guest kernel
+    pub kernel_image: PathBuf,
+    /// Root filesystem image running the guest agent (attached read-only)
+    pub rootfs_image: PathBuf,
+    /// Kernel command line
+    pub boot_args: String,
+    /// Maximum number of vCPUs of a VM
+    pub max_vcpu_count: u32,
+    /// Maximum memory size of a VM (MiB)
+    pub max_mem_size_mib: u64,
+    /// Vsock port the guest agent listens on
+    pub agent_port: u32,
+    /// Time the VM has to boot and accept the connection to the agent
+    pub boot_timeout: Duration,
+    /// Maximum total size of the workspace files copied into a VM
+    pub max_workspace_bytes: u64,
+    /// Directory for the configuration and socket of each VM
+    pub work_dir: PathBuf,
+}
+
+impl FirecrackerConfig {
+    /// Settings for a kernel and root filesystem image, with defaults for everything else
+    pub fn new(kernel_image: impl Into<PathBuf>, rootfs_image: impl Into<PathBuf>) -> Self {
+        Self {
+            firecracker_path: PathBuf::from("firecracker"),
+            kernel_image: kernel_image.into(),
+            rootfs_image: rootfs_image.into(),
+            boot_args: "console=ttyS0 reboot=k panic=1 pci=off".to_string(),
+            max_vcpu_count: 2,
+            max_mem_size_mib: 1024,
+            agent_port: 52,
+            boot_timeout: Duration::from_secs(10),
+            max_workspace_bytes: 64 * 1024 * 1024, // 64 MiB
+            work_dir: std::env::temp_dir().join("mcp-firecracker"),
+        }
+    }
+
+    /// Use a different Firecracker binary
+    pub fn with_firecracker_path(mut self, firecracker_path: impl Into<PathBuf>) -> Self {
+        self.firecracker_path = firecracker_path.into();
+        self
+    }
+
+    /// Set the maximum size of a VM
+    pub fn with_max_size(mut self, vcpu_count: u32, mem_size_mib: u64) -> Self {
+        self.max_vcpu_count = vcpu_count.max(1);
+        self.max_mem_size_mib = mem_size_mib.max(MIN_MEM_SIZE_MIB);
+        self
+    }
+
+    /// Set the time the VM has to boot
+    pub fn with_boot_timeout(mut self, boot_timeout: Duration) -> Self {
+        self.boot_timeout = boot_timeout;
+        self
+    }
+
+    /// Set the maximum total size of the workspace files copied into a VM
+    pub fn with_max_workspace_bytes(mut self, max_workspace_bytes: u64) -> Self {
+        self.max_workspace_bytes = max_workspace_bytes;
+        self
+    }
+
+    /// Use a different directory for the configuration and socket of each VM
+    pub fn with_work_dir(mut self, work_dir: impl Into<PathBuf>) -> Self {
+        self.work_dir = work_dir.into();
+        self
+    }
+
+    /// Build the settings from environment variables
+    ///
+    /// The backend is enabled when `MCP_FIRECRACKER_KERNEL` and `MCP_FIRECRACKER_ROOTFS`
+    /// are set.
+    ///
+    /// * `MCP_FIRECRACKER_BIN` - Firecracker binary
+    /// * `MCP_FIRECRACKER_VCPUS` - maximum number of vCPUs of a VM
+    /// * `MCP_FIRECRACKER_MEM_MIB` - maximum memory size of a VM
+    /// * `MCP_FIRECRACKER_BOOT_TIMEOUT_SECS` - time the VM has to boot
+    /// * `MCP_FIRECRACKER_WORKSPACE_BYTES` - maximum size of the copied workspace
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let (Ok(kernel_image), Ok(rootfs_image)) =
+            (std::env::var("MCP_FIRECRACKER_KERNEL"), std::env::var("MCP_FIRECRACKER_ROOTFS"))
+        else {
+            return Ok(None);
+        };
+        let default = Self::new(kernel_image, rootfs_image);
+
+        let number = |name: &str, default: u64| parse_positive(name, &get_env_var_or(name, &default.to_string()));
+        let vcpu_count = number("MCP_FIRECRACKER_VCPUS", default.max_vcpu_count as u64)?;
+        let mem_size_mib = number("MCP_FIRECRACKER_MEM_MIB", default.max_mem_size_mib)?;
+        let boot_timeout_secs = number("MCP_FIRECRACKER_BOOT_TIMEOUT_SECS", default.boot_timeout.as_secs())?;
+        let max_workspace_bytes = number("MCP_FIRECRACKER_WORKSPACE_BYTES", default.max_workspace_bytes)?;
+        let firecracker_path = get_env_var_or("MCP_FIRECRACKER_BIN", &default.firecracker_path.to_string_lossy());
+
+        Ok(Some(
+            default
+                .with_firecracker_path(firecracker_path)
+                .with_max_size(u32::try_from(vcpu_count).unwrap_or(u32::MAX), mem_size_mib)
+                .with_boot_timeout(Duration::from_secs(boot_timeout_secs))
+                .with_max_workspace_bytes(max_workspace_bytes),
+        ))
+    }
+}
+
+/// Backend running commands in Firecracker microVMs
+#[derive(Debug)]
+pub struct FirecrackerBackend {
+    config: FirecrackerConfig,
+}
+
+impl FirecrackerBackend {
+    /// Create the backend, checking that the images exist
+    pub fn new(config: FirecrackerConfig) -> McpResult<Self> {
+        for (name, path) in [("kernel", &config.kernel_image), ("root filesystem", &config.rootfs_image)] {
+            if !path.is_file() {
+                return Err(McpError::Sandbox(format!("Firecracker {} image not found: {}", name, path.display())));
+            }
+        }
+        Ok(Self { config })
+    }
+
+    /// Backend settings
+    pub fn config(&self) -> &FirecrackerConfig {
+        &self.config
+    }
+
+    /// VM configuration (Firecracker `--config-file` format) for a sandbox configuration
+    pub fn vm_config(&self, sandbox_config: &SandboxConfig, vsock_path: &Path) -> serde_json::Value {
+        let limits = &sandbox_config.resource_limits;
+        let vcpu_count = limits
+            .cpu_limit
+            .map(|cores| cores.ceil().clamp(1.0, self.config.max_vcpu_count as f64) as u32)
+            .unwrap_or(self.config.max_vcpu_count);
+        // The memory of the VM also holds the guest kernel, so it has a lower bound
+        let mem_size_mib = limits
+            .memory_limit
+            .map(|bytes| bytes.div_ceil(1024 * 1024).clamp(MIN_MEM_SIZE_MIB, self.config.max_mem_size_mib))
+            .unwrap_or(self.config.max_mem_size_mib);
+
+        serde_json::json!({
+            "boot-source": {
+                "kernel_image_path": self.config.kernel_image,
+                "boot_args": self.config.boot_args,
+            },
+            "drives": [{
+                "drive_id": "rootfs",
+                "path_on_host": self.config.rootfs_image,
+                "is_root_device": true,
+                "is_read_only": true,
+            }],
+            "machine-config": {
+                "vcpu_count": vcpu_count,
+                "mem_size_mib": mem_size_mib,
+            },
+            "vsock": {
+                "guest_cid": GUEST_CID,
+                "uds_path": vsock_path,
+            },
+        })
+    }
+
+    /// Prepare a VM for a request
+    pub fn prepare(&self, request: &ExecutionRequest) -> McpResult<MicroVm> {
+        if request.sandbox_config.network_access != NetworkAccess::None {
+            warn!(
+                "The firecracker backend has no network, running without network access: {:?}",
+                request.sandbox_config.network_access
+            );
+        }
+
+        let name = format!("vm-{}-{}", std::process::id(), NEXT_VM.fetch_add(1, Ordering::Relaxed));
+        let dir = self.config.work_dir.join(name);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| McpError::Sandbox(format!("Failed to create VM directory {}: {}", dir.display(), e)))?;
+        let vm = MicroVm {
+            vsock_path: dir.join("vsock.sock"),
+            config_path: dir.join("config.json"),
+            dir,
+        };
+
+        let config = self.vm_config(&request.sandbox_config, &vm.vsock_path);
+        std::fs::write(&vm.config_path, config.to_string())
+            .map_err(|e| McpError::Sandbox(format!("Failed to write VM configuration: {}", e)))?;
+        Ok(vm)
+    }
+
+    /// Command booting a prepared VM
+    pub fn command(&self, vm: &MicroVm) -> Command {
+        let mut cmd = Command::new(&self.config.firecracker_path);
+        cmd.arg("--no-api").arg("--config-file").arg(&vm.config_path);
+        // The serial console is only useful for debugging the image
+        cmd.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+        cmd
+    }
+
+    /// Connect to the agent of a booting VM and run a request
+    pub async fn run(
+        &self,
+        vm: &MicroVm,
+        request: &ExecutionRequest,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<GuestOutcome> {
+        let files = collect_workspace(&request.sandbox_config, self.config.max_workspace_bytes)?;
+        let stream = connect_agent(&vm.vsock_path, self.config.agent_port, self.config.boot_timeout).await?;
+        let guest_request = GuestRequest {
+            command: request.command.clone(),
+            args: request.args.clone(),
+            env: request.env.clone(),
+            cwd: request.cwd.clone(),
+            timeout_secs: request.timeout,
+        };
+        run_guest(stream, &files, &guest_request, output).await
+    }
+}
+
+/// Files of a VM, removed when dropped
+#[derive(Debug)]
+pub struct MicroVm {
+    dir: PathBuf,
+    config_path: PathBuf,
+    vsock_path: PathBuf,
+}
+
+impl Drop for MicroVm {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            debug!("Failed to remove VM directory {}: {}", self.dir.display(), e);
+        }
+    }
+}
+
+/// Command sent to the guest agent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GuestRequest {
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub cwd: Option<PathBuf>,
+    pub timeout_secs: u32,
+}
+
+/// Header of a workspace file sent to the guest agent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GuestFile {
+    /// Path in the guest (the same as on the host)
+    pub path: PathBuf,
+    /// Permission bits
+    pub mode: u32,
+}
+
+/// Exit status reported by the guest agent
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuestExit {
+    /// Exit code (`None` if the command was killed by a signal)
+    pub exit_code: Option<i32>,
+    /// Resource usage measured in the guest
+    #[serde(default)]
+    pub resource_usage: ResourceUsage,
+    /// Error of the agent (e.g. the command could not be started)
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Result of a command run by the guest agent
+#[derive(Debug, Clone, Default)]
+pub struct GuestOutcome {
+    pub exit: GuestExit,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// Workspace file to copy into the guest
+#[derive(Debug, Clone)]
+pub(crate) struct WorkspaceFile {
+    pub(crate) host_path: PathBuf,
+    pub(crate) header: GuestFile,
+}
+
+/// List the regular files below the read-write paths, skipping denied paths
+///
+/// Symbolic links are not followed, so that nothing outside the workspace is copied.
+pub(crate) fn collect_workspace(config: &SandboxConfig, max_bytes: u64) -> McpResult<Vec<WorkspaceFile>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut files = Vec::new();
+    let mut total_bytes = 0u64;
+    // Paths in the guest with their host paths (different for a per-task workspace)
+    let mut pending: Vec<(PathBuf, PathBuf)> = config
+        .rw_paths
+        .iter()
+        .map(|path| (path.clone(), config.host_path(path).to_path_buf()))
+        .collect();
+    while let Some((path, host_path)) = pending.pop() {
+        if config.denied_paths.iter().any(|denied| path.starts_with(denied)) {
+            continue;
+        }
+        let Ok(metadata) = std::fs::symlink_metadata(&host_path) else {
+            // Read-write paths that do not exist on the host are left out
+            continue;
+        };
+        if metadata.is_dir() {
+            let entries = std::fs::read_dir(&host_path)
+                .map_err(|e| McpError::Sandbox(format!("Failed to read workspace {}: {}", host_path.display(), e)))?;
+            for entry in entries.flatten() {
+                pending.push((path.join(entry.file_name()), entry.path()));
+            }
+        } else if metadata.is_file() {
+            total_bytes += metadata.len();
+            if total_bytes > max_bytes {
+                return Err(McpError::Sandbox(format!(
+                    "Workspace exceeds the maximum of {} bytes copied into a microVM",
+                    max_bytes
+                )));
+            }
+            files.push(WorkspaceFile {
+                header: GuestFile {
+                    path,
+                    mode: metadata.permissions().mode() & 0o7777,
+                },
+                host_path,
+            });
+        }
+    }
+    files.sort_by(|a, b| a.header.path.cmp(&b.header.path));
+    Ok(files)
+}
+
+/// Connect to the guest agent through the host side of the vsock device
+///
+/// The socket only accepts the connection once the agent listens, so connection attempts
+/// are repeated until `boot_timeout`.
+pub(crate) async fn connect_agent(vsock_path: &Path, port: u32, boot_timeout: Duration) -> McpResult<UnixStream> {
+    let deadline = Instant::now() + boot_timeout;
+    loop {
+        let error = match try_connect(vsock_path, port).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => e,
+        };
+        if Instant::now() >= deadline {
+            return Err(McpError::Sandbox(format!(
+                "MicroVM agent did not accept the connection within {:?}: {}",
+                boot_timeout, error
+            )));
+        }
+        tokio::time::sleep(CONNECT_RETRY_INTERVAL).await;
+    }
+}
+
+async fn try_connect(vsock_path: &Path, port: u32) -> std::io::Result<UnixStream> {
+    let mut stream = UnixStream::connect(vsock_path).await?;
+    stream.write_all(format!("CONNECT {}\n", port).as_bytes()).await?;
+
+    // Read the reply byte by byte, so that nothing after it is consumed
+    let mut reply = Vec::new();
+    loop {
+        let byte = stream.read_u8().await?;
+        if byte == b'\n' {
+            break;
+        }
+        reply.push(byte);
+        if reply.len() > 64 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid vsock handshake reply"));
+        }
+    }
+    if reply.starts_with(b"OK ") {
+        Ok(stream)
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            format!("vsock handshake failed: {}", String::from_utf8_lossy(&reply)),
+        ))
+    }
+}
+
+/// Send the workspace and the command to the guest agent and collect the output
+pub(crate) async fn run_guest<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    files: &[WorkspaceFile],
+    request: &GuestRequest,
+    mut output: Option<mpsc::Sender<OutputChunk>>,
+) -> McpResult<GuestOutcome> {
+    let io_error = |e: std::io::Error| McpError::Sandbox(format!("MicroVM agent connection failed: {}", e));
+
+    for file in files {
+        write_frame(&mut stream, FRAME_FILE, &to_json(&file.header)?).await.map_err(io_error)?;
+        let mut content = tokio::fs::File::open(&file.host_path).await.map_err(|e| {
+            McpError::Sandbox(format!("Failed to read workspace file {}: {}", file.host_path.display(), e))
+        })?;
+        let mut buffer = vec![0; MAX_FRAME_BYTES];
+        loop {
+            let read = content.read(&mut buffer).await.map_err(io_error)?;
+            if read == 0 {
+                break;
+            }
+            write_frame(&mut stream, FRAME_DATA, &buffer[..read]).await.map_err(io_error)?;
+        }
+    }
+    write_frame(&mut stream, FRAME_REQUEST, &to_json(request)?).await.map_err(io_error)?;
+    stream.flush().await.map_err(io_error)?;
+
+    let mut outcome = GuestOutcome::default();
+    loop {
+        let Some((kind, payload)) = read_frame(&mut stream).await.map_err(io_error)? else {
+            return Err(McpError::Sandbox("MicroVM agent closed the connection before the command exited".to_string()));
+        };
+        let stream = match kind {
+            FRAME_STDOUT => OutputStream::Stdout,
+            FRAME_STDERR => OutputStream::Stderr,
+            FRAME_EXIT => {
+                outcome.exit = serde_json::from_slice(&payload)
+                    .map_err(|e| McpError::Sandbox(format!("Invalid exit status from microVM agent: {}", e)))?;
+                return Ok(outcome);
+            }
+            _ => return Err(McpError::Sandbox(format!("Unexpected frame from microVM agent: {}", kind))),
+        };
+
+        match stream {
+            OutputStream::Stdout => outcome.stdout.extend_from_slice(&payload),
+            OutputStream::Stderr => outcome.stderr.extend_from_slice(&payload),
+        }
+        if let Some(sender) = &output {
+            let chunk = OutputChunk {
+                stream,
+                data: payload,
+                timestamp_ms: current_timestamp_ms(),
+            };
+            if sender.send(chunk).await.is_err() {
+                // The receiver is gone; keep collecting the output for the result
+                output = None;
+            }
+        }
+    }
+}
+
+fn to_json(value: &impl Serialize) -> McpResult<Vec<u8>> {
+    serde_json::to_vec(value).map_err(|e| McpError::Internal(format!("Failed to encode microVM message: {}", e)))
+}
+
+/// Write one frame
+pub async fn write_frame(writer: &mut (impl AsyncWrite + Unpin), kind: u8, payload: &[u8]) -> std::io::Result<()> {
+    if payload.len() > MAX_FRAME_BYTES {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "frame too large"));
+    }
+    writer.write_u8(kind).await?;
+    writer.write_u32(payload.len() as u32).await?;
+    writer.write_all(payload).await
+}
+
+/// Read one frame; `None` at the end of the stream
+pub async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> std::io::Result<Option<(u8, Vec<u8>)>> {
+    let kind = match reader.read_u8().await {
+        Ok(kind) => kind,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let len = reader.read_u32().await? as usize;
+    if len > MAX_FRAME_BYTES {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "frame too large"));
+    }
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload).await?;
+    Ok(Some((kind, payload)))
+}
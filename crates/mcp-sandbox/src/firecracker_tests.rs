@@ -0,0 +1,204 @@
+#[cfg(test)]
+mod tests {
+    use crate::firecracker::{
+        collect_workspace, connect_agent, read_frame, run_guest, write_frame, FirecrackerBackend,
+        FirecrackerConfig, GuestFile, GuestRequest, FRAME_DATA, FRAME_EXIT, FRAME_FILE, FRAME_REQUEST,
+        FRAME_STDERR, FRAME_STDOUT,
+    };
+    use crate::models::{ExecutionRequest, SandboxBackend, SandboxConfig};
+    use crate::output_log::OutputStream;
+    use crate::runner::SandboxRunner;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn backend(dir: &std::path::Path) -> FirecrackerBackend {
+        std::fs::write(dir.join("vmlinux"), b"").unwrap();
+        std::fs::write(dir.join("rootfs.ext4"), b"").unwrap();
+        let config = FirecrackerConfig::new(dir.join("vmlinux"), dir.join("rootfs.ext4"))
+            .with_max_size(4, 2048)
+            .with_work_dir(dir.join("vms"));
+        FirecrackerBackend::new(config).unwrap()
+    }
+
+    // Test for the VM configuration derived from the sandbox configuration
+    #[test]
+    fn test_vm_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = backend(dir.path());
+
+        let mut sandbox_config = SandboxConfig::default();
+        let config = backend.vm_config(&sandbox_config, std::path::Path::new("/run/vm/vsock.sock"));
+        assert_eq!(config["machine-config"]["vcpu_count"], 4);
+        assert_eq!(config["machine-config"]["mem_size_mib"], 2048);
+        assert_eq!(config["drives"][0]["is_read_only"], true);
+        assert_eq!(config["vsock"]["uds_path"], "/run/vm/vsock.sock");
+
+        // Limits are rounded up to whole vCPUs and MiB, within the bounds of the backend
+        sandbox_config.resource_limits.cpu_limit = Some(1.5);
+        sandbox_config.resource_limits.memory_limit = Some(300 * 1024 * 1024 + 1);
+        let config = backend.vm_config(&sandbox_config, std::path::Path::new("/run/vm/vsock.sock"));
+        assert_eq!(config["machine-config"]["vcpu_count"], 2);
+        assert_eq!(config["machine-config"]["mem_size_mib"], 301);
+        sandbox_config.resource_limits.cpu_limit = Some(16.0);
+        sandbox_config.resource_limits.memory_limit = Some(1024);
+        let config = backend.vm_config(&sandbox_config, std::path::Path::new("/run/vm/vsock.sock"));
+        assert_eq!(config["machine-config"]["vcpu_count"], 4);
+        assert_eq!(config["machine-config"]["mem_size_mib"], 128);
+
+        // Missing images are reported when the backend is created
+        let missing = FirecrackerConfig::new("/nonexistent/vmlinux", "/nonexistent/rootfs");
+        assert!(FirecrackerBackend::new(missing).is_err());
+    }
+
+    // Test for listing the workspace files copied into a VM
+    #[test]
+    fn test_collect_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("workspace");
+        std::fs::create_dir_all(workspace.join("src")).unwrap();
+        std::fs::create_dir_all(workspace.join("secret")).unwrap();
+        std::fs::write(workspace.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(workspace.join("README"), "hello").unwrap();
+        std::fs::write(workspace.join("secret/key"), "key").unwrap();
+        std::os::unix::fs::symlink("/etc/passwd", workspace.join("passwd")).unwrap();
+
+        let config = SandboxConfig {
+            rw_paths: vec![workspace.clone(), dir.path().join("missing")],
+            denied_paths: vec![workspace.join("secret")],
+            ..Default::default()
+        };
+        let files = collect_workspace(&config, 1024).unwrap();
+        assert_eq!(
+            files.iter().map(|file| file.header.path.clone()).collect::<Vec<_>>(),
+            vec![workspace.join("README"), workspace.join("src/main.rs")]
+        );
+
+        let err = collect_workspace(&config, 8).unwrap_err();
+        assert!(err.to_string().contains("maximum of 8 bytes"), "{}", err);
+    }
+
+    // Test for the exchange with the guest agent
+    #[tokio::test]
+    async fn test_run_guest() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("input.txt"), "workspace data").unwrap();
+        let config = SandboxConfig {
+            rw_paths: vec![dir.path().to_path_buf()],
+            ..Default::default()
+        };
+        let files = collect_workspace(&config, 1024).unwrap();
+        let request = GuestRequest {
+            command: "cat".to_string(),
+            args: vec!["input.txt".to_string()],
+            env: HashMap::new(),
+            cwd: Some(dir.path().to_path_buf()),
+            timeout_secs: 10,
+        };
+
+        let (host, mut guest) = tokio::io::duplex(64 * 1024);
+        let expected_request = request.clone();
+        let agent = tokio::spawn(async move {
+            let (kind, header) = read_frame(&mut guest).await.unwrap().unwrap();
+            assert_eq!(kind, FRAME_FILE);
+            let header: GuestFile = serde_json::from_slice(&header).unwrap();
+            let (kind, data) = read_frame(&mut guest).await.unwrap().unwrap();
+            assert_eq!(kind, FRAME_DATA);
+            let (kind, request) = read_frame(&mut guest).await.unwrap().unwrap();
+            assert_eq!(kind, FRAME_REQUEST);
+            assert_eq!(serde_json::from_slice::<GuestRequest>(&request).unwrap(), expected_request);
+
+            write_frame(&mut guest, FRAME_STDOUT, &data).await.unwrap();
+            write_frame(&mut guest, FRAME_STDERR, b"warning").await.unwrap();
+            let exit = serde_json::json!({ "exit_code": 3, "resource_usage": {
+                "cpu_time_ms": 7, "max_memory_kb": 1024, "io_read_bytes": 14, "io_write_bytes": 0
+            } });
+            write_frame(&mut guest, FRAME_EXIT, exit.to_string().as_bytes()).await.unwrap();
+            header.path
+        });
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let outcome = run_guest(host, &files, &request, Some(tx)).await.unwrap();
+        assert_eq!(agent.await.unwrap(), dir.path().join("input.txt"));
+        assert_eq!(outcome.stdout, b"workspace data");
+        assert_eq!(outcome.stderr, b"warning");
+        assert_eq!(outcome.exit.exit_code, Some(3));
+        assert_eq!(outcome.exit.resource_usage.max_memory_kb, 1024);
+        assert_eq!(rx.recv().await.unwrap().stream, OutputStream::Stdout);
+        assert_eq!(rx.recv().await.unwrap().stream, OutputStream::Stderr);
+
+        // A guest that goes away before the exit status is an error
+        let (host, mut guest) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let _ = read_frame(&mut guest).await;
+        });
+        assert!(run_guest(host, &[], &request, None).await.is_err());
+    }
+
+    // Test for the vsock handshake while the VM boots
+    #[tokio::test]
+    async fn test_connect_agent() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("vsock.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        tokio::spawn(async move {
+            // The first connection is refused as the agent is not listening yet
+            let (mut stream, _) = listener.accept().await.unwrap();
+            drop(stream.read(&mut [0; 16]).await);
+            drop(stream);
+
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut line = [0; 11];
+            stream.read_exact(&mut line).await.unwrap();
+            assert_eq!(&line, b"CONNECT 52\n");
+            stream.write_all(b"OK 1073741824\n").await.unwrap();
+            stream.write_all(b"x").await.unwrap();
+        });
+
+        let mut stream = connect_agent(&socket, 52, Duration::from_secs(5)).await.unwrap();
+        // Nothing after the reply is consumed by the handshake
+        assert_eq!(stream.read_u8().await.unwrap(), b'x');
+
+        let err = connect_agent(&dir.path().join("missing.sock"), 52, Duration::from_millis(100)).await.unwrap_err();
+        assert!(err.to_string().contains("did not accept"), "{}", err);
+    }
+
+    // Test for running a command selecting the firecracker backend
+    #[tokio::test]
+    async fn test_run_in_vm() {
+        let request = || ExecutionRequest {
+            command: "echo".to_string(),
+            args: vec!["hello".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config: SandboxConfig {
+                backend: SandboxBackend::Firecracker,
+                rw_paths: vec![PathBuf::from("/nonexistent")],
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+
+        // Without the backend the command is not run in a weaker sandbox
+        if std::env::var("MCP_FIRECRACKER_KERNEL").is_err() {
+            let err = SandboxRunner::new().run(request()).await.unwrap_err();
+            assert!(err.to_string().contains("not configured"), "{}", err);
+        }
+
+        // A VM that exits while booting fails the command
+        let dir = tempfile::tempdir().unwrap();
+        let config = backend(dir.path())
+            .config()
+            .clone()
+            .with_firecracker_path("false")
+            .with_boot_timeout(Duration::from_secs(5));
+        let runner = SandboxRunner::new().with_firecracker(FirecrackerBackend::new(config).unwrap());
+        let err = runner.run(request()).await.unwrap_err();
+        assert!(err.to_string().contains("MicroVM exited"), "{}", err);
+        // The files of the VM are removed
+        assert_eq!(std::fs::read_dir(dir.path().join("vms")).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,170 @@
+//! Host environment fingerprint
+//!
+//! Describes the execution environment (kernel, sandbox tooling, isolation features and
+//! capacity) so that task results can be correlated with the host that produced them.
+//! The fingerprint is collected once per process and cached.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::debug;
+
+/// Value used when a property could not be determined
+pub const UNKNOWN: &str = "unknown";
+
+/// Prefix of the host fingerprint keys in task metadata
+pub const METADATA_PREFIX: &str = "host.";
+
+/// Host environment fingerprint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostFingerprint {
+    /// Kernel release (e.g. "6.1.0-18-amd64")
+    pub kernel_version: String,
+    /// bubblewrap version, if bwrap is installed
+    pub bwrap_version: Option<String>,
+    /// Whether the kernel supports seccomp filters
+    pub seccomp_supported: bool,
+    /// Whether the landlock LSM is enabled
+    pub landlock_supported: bool,
+    /// cgroup hierarchy ("v1", "v2", "hybrid" or "unknown")
+    pub cgroup_mode: String,
+    /// Number of CPUs available to the process
+    pub cpu_count: usize,
+    /// Total memory (kilobytes)
+    pub memory_total_kb: u64,
+}
+
+impl Default for HostFingerprint {
+    fn default() -> Self {
+        Self {
+            kernel_version: UNKNOWN.to_string(),
+            bwrap_version: None,
+            seccomp_supported: false,
+            landlock_supported: false,
+            cgroup_mode: UNKNOWN.to_string(),
+            cpu_count: 0,
+            memory_total_kb: 0,
+        }
+    }
+}
+
+static CURRENT: OnceLock<HostFingerprint> = OnceLock::new();
+
+impl HostFingerprint {
+    /// Fingerprint of the current host (collected on first use)
+    pub fn current() -> &'static HostFingerprint {
+        CURRENT.get_or_init(Self::collect)
+    }
+
+    /// Collect the fingerprint of the current host
+    pub fn collect() -> Self {
+        let fingerprint = Self {
+            kernel_version: read_trimmed("/proc/sys/kernel/osrelease")
+                .unwrap_or_else(|| UNKNOWN.to_string()),
+            bwrap_version: bwrap_version(),
+            seccomp_supported: std::fs::read_to_string("/proc/self/status")
+                .map(|status| parse_seccomp_support(&status))
+                .unwrap_or(false),
+            landlock_supported: read_trimmed("/sys/kernel/security/lsm")
+                .map(|lsm| parse_landlock_support(&lsm))
+                .unwrap_or(false),
+            cgroup_mode: detect_cgroup_mode(Path::new("/sys/fs/cgroup")).to_string(),
+            cpu_count: std::thread::available_parallelism()
+                .map(|count| count.get())
+                .unwrap_or(0),
+            memory_total_kb: std::fs::read_to_string("/proc/meminfo")
+                .ok()
+                .and_then(|meminfo| parse_mem_total_kb(&meminfo))
+                .unwrap_or(0),
+        };
+
+        debug!("Host fingerprint: {:?}", fingerprint);
+        fingerprint
+    }
+
+    /// Flatten the fingerprint into string key/value pairs
+    pub fn to_map(&self) -> HashMap<String, String> {
+        let entries = [
+            ("kernel_version", self.kernel_version.clone()),
+            (
+                "bwrap_version",
+                self.bwrap_version.clone().unwrap_or_else(|| UNKNOWN.to_string()),
+            ),
+            ("seccomp", self.seccomp_supported.to_string()),
+            ("landlock", self.landlock_supported.to_string()),
+            ("cgroup_mode", self.cgroup_mode.clone()),
+            ("cpu_count", self.cpu_count.to_string()),
+            ("memory_total_kb", self.memory_total_kb.to_string()),
+        ];
+
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect()
+    }
+
+    /// Key/value pairs for task metadata (keys prefixed with `host.`)
+    pub fn to_metadata(&self) -> HashMap<String, String> {
+        self.to_map()
+            .into_iter()
+            .map(|(key, value)| (format!("{}{}", METADATA_PREFIX, key), value))
+            .collect()
+    }
+}
+
+fn read_trimmed(path: &str) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn bwrap_version() -> Option<String> {
+    let path = which::which("bwrap").ok()?;
+    let output = std::process::Command::new(path).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_bwrap_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the output of `bwrap --version` ("bubblewrap 0.8.0")
+pub(crate) fn parse_bwrap_version(output: &str) -> Option<String> {
+    output
+        .split_whitespace()
+        .last()
+        .map(str::to_string)
+}
+
+/// Whether `/proc/self/status` reports seccomp support (the `Seccomp:` field exists)
+pub(crate) fn parse_seccomp_support(status: &str) -> bool {
+    status.lines().any(|line| line.starts_with("Seccomp:"))
+}
+
+/// Whether landlock is listed in `/sys/kernel/security/lsm`
+pub(crate) fn parse_landlock_support(lsm: &str) -> bool {
+    lsm.split(',').any(|name| name.trim() == "landlock")
+}
+
+/// Parse `MemTotal` (kilobytes) from `/proc/meminfo`
+pub(crate) fn parse_mem_total_kb(meminfo: &str) -> Option<u64> {
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))
+        .and_then(|value| value.split_whitespace().next())
+        .and_then(|value| value.parse().ok())
+}
+
+/// Detect the cgroup hierarchy mounted at `root`
+pub(crate) fn detect_cgroup_mode(root: &Path) -> &'static str {
+    if root.join("cgroup.controllers").exists() {
+        "v2"
+    } else if root.join("unified").join("cgroup.controllers").exists() {
+        "hybrid"
+    } else if root.exists() {
+        "v1"
+    } else {
+        UNKNOWN
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use crate::host::{
+        detect_cgroup_mode, parse_bwrap_version, parse_landlock_support, parse_mem_total_kb,
+        parse_seccomp_support, HostFingerprint, UNKNOWN,
+    };
+
+    // Test for parsing procfs/sysfs contents
+    #[test]
+    fn test_parse_host_properties() {
+        assert_eq!(parse_bwrap_version("bubblewrap 0.8.0\n").as_deref(), Some("0.8.0"));
+        assert_eq!(parse_bwrap_version(""), None);
+
+        assert!(parse_seccomp_support("Name:\tcat\nSeccomp:\t0\nSeccomp_filters:\t0\n"));
+        assert!(!parse_seccomp_support("Name:\tcat\n"));
+
+        assert!(parse_landlock_support("lockdown,capability,landlock,yama,apparmor"));
+        assert!(!parse_landlock_support("capability,yama"));
+
+        let meminfo = "MemTotal:       16318412 kB\nMemFree:         1234 kB\n";
+        assert_eq!(parse_mem_total_kb(meminfo), Some(16318412));
+        assert_eq!(parse_mem_total_kb("MemFree: 1 kB"), None);
+    }
+
+    // Test for cgroup mode detection
+    #[test]
+    fn test_detect_cgroup_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(detect_cgroup_mode(&dir.path().join("missing")), UNKNOWN);
+        assert_eq!(detect_cgroup_mode(dir.path()), "v1");
+
+        std::fs::create_dir(dir.path().join("unified")).unwrap();
+        std::fs::write(dir.path().join("unified/cgroup.controllers"), "").unwrap();
+        assert_eq!(detect_cgroup_mode(dir.path()), "hybrid");
+
+        std::fs::write(dir.path().join("cgroup.controllers"), "cpu memory").unwrap();
+        assert_eq!(detect_cgroup_mode(dir.path()), "v2");
+    }
+
+    // Test for flattening into task metadata
+    #[test]
+    fn test_to_metadata() {
+        let fingerprint = HostFingerprint {
+            kernel_version: "6.1.0".to_string(),
+            bwrap_version: None,
+            cpu_count: 4,
+            ..Default::default()
+        };
+
+        let metadata = fingerprint.to_metadata();
+        assert_eq!(metadata["host.kernel_version"], "6.1.0");
+        assert_eq!(metadata["host.bwrap_version"], UNKNOWN);
+        assert_eq!(metadata["host.cpu_count"], "4");
+        assert_eq!(metadata.len(), fingerprint.to_map().len());
+        assert_eq!(fingerprint.to_map()["kernel_version"], "6.1.0");
+
+        // Collected once and cached
+        assert!(std::ptr::eq(HostFingerprint::current(), HostFingerprint::current()));
+    }
+}
@@ -6,13 +6,116 @@ pub mod executor;
 pub mod models;
 pub mod runner;
 pub mod bubblewrap;
+pub mod capabilities;
+pub mod concurrency;
+pub mod container;
+pub mod dns;
+pub mod dry_run;
+pub mod egress;
+pub mod executable;
+pub mod firecracker;
+pub mod host;
+pub mod mac;
+pub mod monitor;
+pub mod output_capture;
+pub mod output_log;
+pub mod overlay;
+pub mod plan;
+pub mod presets;
+pub mod process;
+pub mod process_tree;
+pub mod quota;
+pub mod retry;
+pub mod rlimit;
 pub mod seccomp;
+pub mod session;
+pub mod staging;
+pub mod syscalls;
+pub mod usage;
+pub mod workspace;
+pub mod workspace_diff;
 
+#[cfg(test)]
+mod bubblewrap_tests;
+#[cfg(test)]
+mod capabilities_tests;
+#[cfg(test)]
+mod concurrency_tests;
+#[cfg(test)]
+mod container_tests;
+#[cfg(test)]
+mod dns_tests;
+#[cfg(test)]
+mod dry_run_tests;
+#[cfg(test)]
+mod egress_tests;
+#[cfg(test)]
+mod executable_tests;
 #[cfg(test)]
 mod executor_tests;
 #[cfg(test)]
+mod firecracker_tests;
+#[cfg(test)]
+mod host_tests;
+#[cfg(test)]
+mod mac_tests;
+#[cfg(test)]
+mod monitor_tests;
+#[cfg(test)]
+mod output_capture_tests;
+#[cfg(test)]
+mod output_log_tests;
+#[cfg(test)]
+mod overlay_tests;
+#[cfg(test)]
+mod plan_tests;
+#[cfg(test)]
+mod presets_tests;
+#[cfg(test)]
+mod process_tests;
+#[cfg(test)]
+mod process_tree_tests;
+#[cfg(test)]
+mod quota_tests;
+#[cfg(test)]
+mod retry_tests;
+#[cfg(test)]
+mod rlimit_tests;
+#[cfg(test)]
 mod runner_tests;
+#[cfg(test)]
+mod seccomp_tests;
+#[cfg(test)]
+mod session_tests;
+#[cfg(test)]
+mod staging_tests;
+#[cfg(test)]
+mod test_support;
+#[cfg(test)]
+mod usage_tests;
+#[cfg(test)]
+mod workspace_diff_tests;
+#[cfg(test)]
+mod workspace_tests;
 
+pub use concurrency::{ConcurrencyLimiter, ConcurrencyLimits, ConcurrencyMetrics, ExecutionPermit};
+pub use container::{ContainerRunner, ContainerRuntime};
+pub use dry_run::{DryRunBackend, DryRunReport, MountKind, SandboxMount};
 pub use executor::CommandExecutor;
-pub use models::{ExecutionRequest, ExecutionResult, ResourceUsage, SandboxConfig};
-pub use runner::SandboxRunner; 
\ No newline at end of file
+pub use host::HostFingerprint;
+pub use firecracker::{FirecrackerBackend, FirecrackerConfig};
+pub use models::{
+    ExecutionRequest, ExecutionResult, OutputChunk, ResourceSample, ResourceUsage, SandboxBackend, SandboxConfig,
+};
+pub use monitor::{RuntimeEvent, RuntimeEventKind, RuntimeMonitor};
+pub use output_capture::{OutputCaptureConfig, SpilledOutput};
+pub use output_log::{OutputLogConfig, OutputLogReader, OutputLogWriter, OutputStream, TailCursor};
+pub use plan::{ExecutionPlan, PlanResult, PlanStep, StepResult, StepStatus};
+pub use presets::SandboxPreset;
+pub use process::{ProcessTracker, TaskGuard};
+pub use process_tree::ProcessRecord;
+pub use retry::RetryPolicy;
+pub use runner::SandboxRunner;
+pub use session::{SandboxSessions, SessionInfo};
+pub use usage::UsageAccounting;
+pub use workspace_diff::{ChangeKind, FileChange}; 
\ No newline at end of file
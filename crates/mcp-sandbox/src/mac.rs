@@ -0,0 +1,188 @@
+//! AppArmor and SELinux confinement of sandboxed commands
+//!
+//! Where mandatory access control policies are mandated, a command can be confined by a
+//! named AppArmor profile or an SELinux context: the `mac_profile` of its sandbox
+//! configuration, or the default of the runner (`MCP_SANDBOX_APPARMOR_PROFILE` or
+//! `MCP_SANDBOX_SELINUX_CONTEXT`). How the label is applied depends on the backend:
+//!
+//! * bubblewrap: an SELinux context is set with `--exec-label`; for AppArmor the command is
+//!   started by `aa-exec -p <profile> --` inside the sandbox (the host's `aa-exec` is mounted
+//!   read-only, its libraries must be below the read-only paths)
+//! * without sandbox: the command is started by `aa-exec -p <profile> --` or `runcon <context>`
+//! * containers: `--security-opt apparmor=<profile>` or `--security-opt label=...`
+//! * microVMs: not supported, the execution fails
+//!
+//! The security modules and the launchers are detected when the runner starts. The label is
+//! validated before every execution: the module must be enabled, the launcher installed if
+//! one is needed, and an AppArmor profile loaded. A command that asked for a label is never
+//! started without it; validation failures are sandbox setup errors.
+
+use crate::models::MacProfile;
+use mcp_common::error::{McpError, McpResult};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Security modules and launchers available on the host
+#[derive(Debug, Clone)]
+pub struct MacSupport {
+    sys_root: PathBuf,
+    aa_exec: Option<PathBuf>,
+    runcon: Option<PathBuf>,
+}
+
+impl MacSupport {
+    /// Detect the security modules of the host and look up `aa-exec` and `runcon` in `PATH`
+    pub fn detect() -> Self {
+        let mut support = Self::new("/sys");
+        support.aa_exec = which::which("aa-exec").ok();
+        support.runcon = which::which("runcon").ok();
+        support
+    }
+
+    /// Read the state of the security modules below a sysfs root, without launchers
+    pub fn new(sys_root: impl Into<PathBuf>) -> Self {
+        Self {
+            sys_root: sys_root.into(),
+            aa_exec: None,
+            runcon: None,
+        }
+    }
+
+    /// Start AppArmor-confined commands with a different `aa-exec`
+    pub fn with_aa_exec(mut self, aa_exec: impl Into<PathBuf>) -> Self {
+        self.aa_exec = Some(aa_exec.into());
+        self
+    }
+
+    /// Start SELinux-confined commands with a different `runcon`
+    pub fn with_runcon(mut self, runcon: impl Into<PathBuf>) -> Self {
+        self.runcon = Some(runcon.into());
+        self
+    }
+
+    /// Whether AppArmor is enabled
+    pub fn apparmor_enabled(&self) -> bool {
+        fs::read_to_string(self.sys_root.join("module/apparmor/parameters/enabled"))
+            .is_ok_and(|enabled| enabled.trim() == "Y")
+    }
+
+    /// Whether SELinux is enabled
+    pub fn selinux_enabled(&self) -> bool {
+        self.sys_root.join("fs/selinux/enforce").is_file()
+    }
+
+    /// Names of the loaded AppArmor profiles, `None` if they cannot be read
+    pub fn apparmor_profiles(&self) -> Option<Vec<String>> {
+        let profiles = fs::read_to_string(self.sys_root.join("kernel/security/apparmor/profiles")).ok()?;
+        // One "<name> (<mode>)" line per profile
+        Some(
+            profiles
+                .lines()
+                .map(|line| line.rsplit_once(" (").map_or(line, |(name, _)| name).to_string())
+                .collect(),
+        )
+    }
+
+    /// Check that a command can be confined by a label on this host
+    ///
+    /// If the loaded AppArmor profiles cannot be read, a missing profile is reported by
+    /// `aa-exec` or the container runtime when the command starts.
+    pub fn validate(&self, profile: &MacProfile) -> McpResult<()> {
+        validate_label(profile)?;
+        match profile {
+            MacProfile::AppArmor(name) => {
+                if !self.apparmor_enabled() {
+                    return Err(setup_error(format!("AppArmor is not enabled, cannot apply profile {}", name)));
+                }
+                if self.apparmor_profiles().is_some_and(|profiles| !profiles.contains(name)) {
+                    return Err(setup_error(format!("AppArmor profile {} is not loaded", name)));
+                }
+            }
+            MacProfile::SeLinux(context) => {
+                if !self.selinux_enabled() {
+                    return Err(setup_error(format!("SELinux is not enabled, cannot apply context {}", context)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Command line prefix that starts a command under a label (`aa-exec` or `runcon`)
+    pub fn launcher(&self, profile: &MacProfile) -> McpResult<Vec<String>> {
+        let (launcher, args) = match profile {
+            MacProfile::AppArmor(name) => (&self.aa_exec, vec!["-p".to_string(), name.clone(), "--".to_string()]),
+            MacProfile::SeLinux(context) => (&self.runcon, vec![context.clone()]),
+        };
+        let Some(launcher) = launcher else {
+            return Err(setup_error(match profile {
+                MacProfile::AppArmor(_) => "aa-exec is not installed, cannot apply an AppArmor profile".to_string(),
+                MacProfile::SeLinux(_) => "runcon is not installed, cannot apply an SELinux context".to_string(),
+            }));
+        };
+        Ok(std::iter::once(launcher.to_string_lossy().to_string()).chain(args).collect())
+    }
+
+    /// Path of `aa-exec`, if installed
+    pub fn aa_exec(&self) -> Option<&Path> {
+        self.aa_exec.as_deref()
+    }
+}
+
+/// Default label of the runner from `MCP_SANDBOX_APPARMOR_PROFILE` or `MCP_SANDBOX_SELINUX_CONTEXT`
+///
+/// Setting both is an error.
+pub fn default_profile_from_env() -> McpResult<Option<MacProfile>> {
+    let env = |name| std::env::var(name).ok().filter(|value: &String| !value.trim().is_empty());
+    let profile = match (env("MCP_SANDBOX_APPARMOR_PROFILE"), env("MCP_SANDBOX_SELINUX_CONTEXT")) {
+        (Some(_), Some(_)) => {
+            return Err(McpError::Internal(
+                "Only one of MCP_SANDBOX_APPARMOR_PROFILE and MCP_SANDBOX_SELINUX_CONTEXT may be set".to_string(),
+            ))
+        }
+        (Some(name), None) => MacProfile::AppArmor(name.trim().to_string()),
+        (None, Some(context)) => MacProfile::SeLinux(context.trim().to_string()),
+        (None, None) => return Ok(None),
+    };
+    validate_label(&profile)?;
+    Ok(Some(profile))
+}
+
+/// Check the syntax of a label
+pub fn validate_label(profile: &MacProfile) -> McpResult<()> {
+    let valid = match profile {
+        MacProfile::AppArmor(name) => {
+            !name.is_empty() && !name.starts_with('-') && !name.chars().any(|c| c.is_whitespace() || c.is_control())
+        }
+        MacProfile::SeLinux(context) => {
+            let fields: Vec<&str> = context.splitn(4, ':').collect();
+            fields.len() >= 3
+                && fields.iter().all(|field| !field.is_empty())
+                && context.chars().all(|c| c.is_ascii_alphanumeric() || "_.,:-".contains(c))
+        }
+    };
+    if !valid {
+        return Err(McpError::InvalidRequest(match profile {
+            MacProfile::AppArmor(name) => format!("Invalid AppArmor profile name: '{}'", name),
+            MacProfile::SeLinux(context) => {
+                format!("Invalid SELinux context, expected user:role:type[:level]: '{}'", context)
+            }
+        }));
+    }
+    Ok(())
+}
+
+/// `--security-opt` values of container runtimes for a label
+pub fn container_security_opts(profile: &MacProfile) -> Vec<String> {
+    match profile {
+        MacProfile::AppArmor(name) => vec![format!("apparmor={}", name)],
+        MacProfile::SeLinux(context) => ["user", "role", "type", "level"]
+            .into_iter()
+            .zip(context.splitn(4, ':'))
+            .map(|(key, value)| format!("label={}:{}", key, value))
+            .collect(),
+    }
+}
+
+fn setup_error(message: String) -> McpError {
+    McpError::Sandbox(format!("Sandbox setup failed: {}", message))
+}
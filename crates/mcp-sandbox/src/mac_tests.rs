@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod tests {
+    use crate::bubblewrap::BubblewrapWrapper;
+    use crate::mac::{container_security_opts, validate_label, MacSupport};
+    use crate::models::{ExecutionRequest, MacProfile, SandboxBackend, SandboxConfig};
+    use crate::runner::SandboxRunner;
+    use mcp_common::error::{error_code, McpError};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    /// sysfs root with AppArmor enabled and the given profiles loaded
+    fn apparmor_sys(root: &Path, profiles: &str) {
+        fs::create_dir_all(root.join("module/apparmor/parameters")).unwrap();
+        fs::write(root.join("module/apparmor/parameters/enabled"), "Y\n").unwrap();
+        fs::create_dir_all(root.join("kernel/security/apparmor")).unwrap();
+        fs::write(root.join("kernel/security/apparmor/profiles"), profiles).unwrap();
+    }
+
+    fn apparmor(name: &str) -> MacProfile {
+        MacProfile::AppArmor(name.to_string())
+    }
+
+    // Test for validating labels against the security modules of the host
+    #[test]
+    fn test_validate() {
+        let dir = tempfile::tempdir().unwrap();
+        let support = MacSupport::new(dir.path());
+        assert!(!support.apparmor_enabled() && !support.selinux_enabled());
+        let selinux = MacProfile::SeLinux("system_u:system_r:container_t:s0:c1,c2".to_string());
+        for profile in [apparmor("mcp-sandbox"), selinux.clone()] {
+            match support.validate(&profile) {
+                Err(error @ McpError::Sandbox(_)) => assert_eq!(error.code(), error_code::SANDBOX_SETUP_FAILED),
+                other => panic!("unexpected result for {:?}: {:?}", profile, other),
+            }
+        }
+
+        apparmor_sys(dir.path(), "mcp-sandbox (enforce)\n/usr/bin/man (complain)\n");
+        fs::create_dir_all(dir.path().join("fs/selinux")).unwrap();
+        fs::write(dir.path().join("fs/selinux/enforce"), "1").unwrap();
+        assert_eq!(support.apparmor_profiles().unwrap(), vec!["mcp-sandbox", "/usr/bin/man"]);
+        support.validate(&apparmor("mcp-sandbox")).unwrap();
+        support.validate(&apparmor("/usr/bin/man")).unwrap();
+        support.validate(&selinux).unwrap();
+        assert!(support.validate(&apparmor("not-loaded")).is_err());
+
+        for profile in [
+            apparmor(""),
+            apparmor("two words"),
+            apparmor("-p"),
+            MacProfile::SeLinux("container_t".to_string()),
+            MacProfile::SeLinux("system_u::container_t".to_string()),
+            MacProfile::SeLinux("system_u:system_r:container_t; rm".to_string()),
+        ] {
+            match validate_label(&profile) {
+                Err(McpError::InvalidRequest(_)) => {}
+                other => panic!("unexpected result for {:?}: {:?}", profile, other),
+            }
+        }
+    }
+
+    // Test for the options each backend applies a label with
+    #[test]
+    fn test_backend_options() {
+        let selinux = MacProfile::SeLinux("system_u:system_r:container_t:s0:c1,c2".to_string());
+        assert_eq!(container_security_opts(&apparmor("mcp-sandbox")), vec!["apparmor=mcp-sandbox"]);
+        assert_eq!(
+            container_security_opts(&selinux),
+            vec!["label=user:system_u", "label=role:system_r", "label=type:container_t", "label=level:s0:c1,c2"]
+        );
+
+        // bubblewrap sets SELinux contexts itself
+        let config = SandboxConfig {
+            mac_profile: Some(selinux),
+            ..Default::default()
+        };
+        let cmd = BubblewrapWrapper::with_path("/usr/bin/bwrap").build_command(&config, None, "id", &[]).unwrap();
+        let args: Vec<String> = cmd.as_std().get_args().map(|arg| arg.to_string_lossy().to_string()).collect();
+        let label = args.iter().position(|arg| arg == "--exec-label").unwrap();
+        assert_eq!(args[label + 1], "system_u:system_r:container_t:s0:c1,c2");
+        assert!(label < args.iter().position(|arg| arg == "--").unwrap());
+
+        // Launchers must be installed
+        let support = MacSupport::new("/nonexistent");
+        assert!(support.launcher(&apparmor("mcp-sandbox")).is_err());
+        assert_eq!(
+            support.with_aa_exec("/usr/sbin/aa-exec").launcher(&apparmor("mcp-sandbox")).unwrap(),
+            vec!["/usr/sbin/aa-exec", "-p", "mcp-sandbox", "--"]
+        );
+    }
+
+    // Test for starting commands under their label
+    #[tokio::test]
+    async fn test_run_with_label() {
+        let dir = tempfile::tempdir().unwrap();
+        let sys = dir.path().join("sys");
+        apparmor_sys(&sys, "mcp-sandbox (enforce)\n");
+        // Stand-in for aa-exec that reports the profile and runs the command
+        let aa_exec = dir.path().join("aa-exec");
+        fs::write(&aa_exec, "#!/bin/sh\necho \"profile $2\"\nshift 3\nexec \"$@\"\n").unwrap();
+        fs::set_permissions(&aa_exec, fs::Permissions::from_mode(0o755)).unwrap();
+        let runner = SandboxRunner::new().with_mac_support(MacSupport::new(&sys).with_aa_exec(&aa_exec));
+
+        let request = |mac_profile: Option<MacProfile>, backend: SandboxBackend| ExecutionRequest {
+            command: "echo".to_string(),
+            args: vec!["hello".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config: SandboxConfig {
+                // The launcher runs on the host unless a backend is selected explicitly
+                enabled: backend != SandboxBackend::Bubblewrap,
+                backend,
+                mac_profile,
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+        let result = runner.run(request(Some(apparmor("mcp-sandbox")), SandboxBackend::Bubblewrap)).await.unwrap();
+        assert_eq!(result.stdout, "profile mcp-sandbox\nhello\n");
+        // Without a label, the command runs as is
+        let result = runner.run(request(None, SandboxBackend::Bubblewrap)).await.unwrap();
+        assert_eq!(result.stdout, "hello\n");
+
+        // Profiles that are not loaded and microVMs fail before the command starts
+        for (profile, backend) in [
+            (apparmor("not-loaded"), SandboxBackend::Bubblewrap),
+            (apparmor("mcp-sandbox"), SandboxBackend::Firecracker),
+        ] {
+            match runner.run(request(Some(profile), backend)).await {
+                Err(error @ McpError::Sandbox(_)) => assert_eq!(error.code(), error_code::SANDBOX_SETUP_FAILED),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        // The default label of the runner applies to commands that name none
+        let runner = runner.with_mac_profile(apparmor("mcp-sandbox"));
+        let result = runner.run(request(None, SandboxBackend::Bubblewrap)).await.unwrap();
+        assert_eq!(result.stdout, "profile mcp-sandbox\nhello\n");
+    }
+}
@@ -1,6 +1,13 @@
+use crate::monitor::RuntimeEvent;
+use crate::output_capture::SpilledOutput;
+use crate::output_log::OutputStream;
+use crate::process_tree::ProcessRecord;
+use crate::staging::StagedFile;
+use crate::workspace::WORKSPACE_MOUNT_POINT;
+use crate::workspace_diff::FileChange;
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Command execution request
 #[derive(Debug, Clone)]
@@ -17,6 +24,11 @@ pub struct ExecutionRequest {
     pub timeout: u32,
     /// Sandbox configuration
     pub sandbox_config: SandboxConfig,
+    /// Files written into the task workspace before the command starts (see [`crate::staging`])
+    pub input_files: Vec<StagedFile>,
+    /// Whether the changes to the read-write paths are reported in the result (see
+    /// [`crate::workspace_diff`])
+    pub diff_workspace: bool,
 }
 
 /// Command execution result
@@ -28,10 +40,33 @@ pub struct ExecutionResult {
     pub stdout: String,
     /// Standard error output
     pub stderr: String,
+    /// Complete standard output if it exceeded the inline limit, `stdout` then only holds its
+    /// beginning (see [`crate::output_capture`])
+    pub stdout_spill: Option<SpilledOutput>,
+    /// Complete standard error output if it exceeded the inline limit
+    pub stderr_spill: Option<SpilledOutput>,
     /// Resource usage
     pub resource_usage: ResourceUsage,
     /// Execution time (milliseconds)
     pub execution_time_ms: u64,
+    /// Processes spawned by the command, the command first (see [`crate::process_tree`])
+    pub process_tree: Vec<ProcessRecord>,
+    /// System calls recorded by the eBPF runtime monitor (see [`crate::monitor`])
+    pub runtime_events: Vec<RuntimeEvent>,
+    /// Files the command added, modified or deleted, if requested and the read-write paths
+    /// were not too large to compare
+    pub workspace_changes: Option<Vec<FileChange>>,
+}
+
+/// Chunk of live command output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputChunk {
+    /// Stream the chunk was read from
+    pub stream: OutputStream,
+    /// Output bytes (not necessarily split at line boundaries)
+    pub data: Vec<u8>,
+    /// Time the chunk was read (milliseconds since the Unix epoch)
+    pub timestamp_ms: u64,
 }
 
 /// Resource usage
@@ -47,13 +82,39 @@ pub struct ResourceUsage {
     pub io_write_bytes: u64,
 }
 
+/// Resource usage of a running command at one point in time (see [`crate::usage`])
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceSample {
+    /// Time the sample was taken (milliseconds since the Unix epoch)
+    pub timestamp_ms: u64,
+    /// CPU usage time so far (milliseconds)
+    pub cpu_time_ms: u64,
+    /// Current memory usage (kilobytes)
+    pub memory_kb: u64,
+    /// Maximum memory usage so far (kilobytes)
+    pub max_memory_kb: u64,
+    /// Number of bytes read so far
+    pub io_read_bytes: u64,
+    /// Number of bytes written so far
+    pub io_write_bytes: u64,
+}
+
+/// User ID of sandboxed commands unless configured (the conventional `nobody`)
+pub const DEFAULT_SANDBOX_UID: u32 = 65534;
+/// Group ID of sandboxed commands unless configured (the conventional `nogroup`)
+pub const DEFAULT_SANDBOX_GID: u32 = 65534;
+/// Hostname of the sandbox unless configured
+pub const DEFAULT_SANDBOX_HOSTNAME: &str = "mcp-sandbox";
+
 /// Sandbox configuration
 #[derive(Debug, Clone)]
 pub struct SandboxConfig {
     /// Whether sandbox is enabled
     pub enabled: bool,
-    /// Path to seccomp profile
+    /// Path to seccomp profile (set by the runner: compiled BPF for bubblewrap, JSON for containers)
     pub seccomp_profile: Option<PathBuf>,
+    /// Name of the seccomp profile to apply (`basic` or `network` by the network access if unset)
+    pub seccomp_profile_name: Option<String>,
     /// Paths with read-write permission
     pub rw_paths: Vec<PathBuf>,
     /// Paths with read-only permission
@@ -64,6 +125,85 @@ pub struct SandboxConfig {
     pub network_access: NetworkAccess,
     /// Resource limits configuration
     pub resource_limits: ResourceLimits,
+    /// Isolation backend
+    pub backend: SandboxBackend,
+    /// Handling of changes to the read-write paths
+    pub workspace_mode: WorkspaceMode,
+    /// Host directory mounted at `/workspace` instead of the host path (see [`crate::workspace`])
+    pub workspace_dir: Option<PathBuf>,
+    /// Cgroup the task cgroup of the command is created in instead of the cgroup root (set by
+    /// the runner for the commands of a session, see [`crate::session`])
+    pub cgroup_parent: Option<PathBuf>,
+    /// User ID of the command inside the user namespace of the sandbox
+    pub uid: u32,
+    /// Group ID of the command inside the user namespace of the sandbox
+    pub gid: u32,
+    /// Capabilities kept in the bounding set for trusted profiles, all others are dropped
+    /// (see [`crate::capabilities`])
+    pub retained_capabilities: Vec<String>,
+    /// Whether a procfs of the sandbox's PID namespace is mounted at `/proc` (bubblewrap only,
+    /// `/proc` is absent otherwise)
+    pub mount_proc: bool,
+    /// Whether a minimal `/dev` (`null`, `zero`, `random`, `tty`, ...) is mounted (bubblewrap
+    /// only, `/dev` is absent otherwise)
+    pub mount_dev: bool,
+    /// AppArmor profile or SELinux context the command is confined by (see [`crate::mac`])
+    pub mac_profile: Option<MacProfile>,
+    /// Whether the IPC namespace of the host (System V IPC, POSIX message queues) is shared
+    /// instead of isolated (bubblewrap and containers)
+    pub share_ipc: bool,
+    /// Whether the UTS namespace of the host is shared, the command then sees the host's
+    /// hostname instead of `hostname` (bubblewrap and containers)
+    pub share_uts: bool,
+    /// Hostname of the sandbox's UTS namespace (see [`crate::bubblewrap::validate_hostname`])
+    pub hostname: String,
+    /// Additional bubblewrap flags from [`crate::bubblewrap::ALLOWED_EXTRA_BWRAP_ARGS`], e.g.
+    /// `--new-session` (bubblewrap only)
+    pub extra_bwrap_args: Vec<String>,
+}
+
+impl SandboxConfig {
+    /// Host path mounted at a path of the sandbox
+    pub fn host_path<'a>(&'a self, path: &'a Path) -> &'a Path {
+        match &self.workspace_dir {
+            Some(workspace_dir) if path == Path::new(WORKSPACE_MOUNT_POINT) => workspace_dir,
+            _ => path,
+        }
+    }
+}
+
+/// Handling of the changes a command makes to the read-write paths
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WorkspaceMode {
+    /// The paths are bind mounted, changes are written directly
+    #[default]
+    Direct,
+    /// The paths are overlays with a throwaway upper layer, every change is discarded
+    Ephemeral,
+    /// Like `Ephemeral`, but the changes are copied into the paths when the command exits
+    /// with status 0 (see [`crate::overlay`])
+    CommitOnSuccess,
+}
+
+/// Mandatory access control label of a sandboxed command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacProfile {
+    /// Name of a loaded AppArmor profile
+    AppArmor(String),
+    /// SELinux security context (`user:role:type[:level]`)
+    SeLinux(String),
+}
+
+/// Isolation backend of the sandbox
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SandboxBackend {
+    /// Namespaces and seccomp with bubblewrap
+    #[default]
+    Bubblewrap,
+    /// Firecracker microVM (see [`crate::firecracker`])
+    Firecracker,
+    /// Podman or Docker container (see [`crate::container`])
+    Container,
 }
 
 /// Network access configuration
@@ -88,6 +228,18 @@ pub struct ResourceLimits {
     pub pids_limit: Option<u32>,
     /// IO weight (priority)
     pub io_weight: Option<u32>,
+    /// Disk quota for writes (bytes, see [`crate::quota`])
+    pub disk_limit: Option<u64>,
+    /// CPU time limit (seconds, independent of the timeout; see [`crate::usage`])
+    pub cpu_time_limit: Option<u64>,
+    /// Maximum number of open files of each process (see [`crate::rlimit`])
+    pub open_files_limit: Option<u64>,
+    /// Maximum size of a file written by a process (bytes)
+    pub file_size_limit: Option<u64>,
+    /// Maximum size of a core dump (bytes; core dumps are disabled when unset)
+    pub core_dump_limit: Option<u64>,
+    /// Maximum stack size of each process (bytes)
+    pub stack_limit: Option<u64>,
 }
 
 impl Default for SandboxConfig {
@@ -95,6 +247,7 @@ impl Default for SandboxConfig {
         Self {
             enabled: true,
             seccomp_profile: None,
+            seccomp_profile_name: None,
             rw_paths: vec![PathBuf::from("/workspace")],
             ro_paths: vec![
                 PathBuf::from("/usr/bin"),
@@ -108,6 +261,20 @@ impl Default for SandboxConfig {
             ],
             network_access: NetworkAccess::None,
             resource_limits: ResourceLimits::default(),
+            backend: SandboxBackend::default(),
+            workspace_mode: WorkspaceMode::default(),
+            workspace_dir: None,
+            cgroup_parent: None,
+            uid: DEFAULT_SANDBOX_UID,
+            gid: DEFAULT_SANDBOX_GID,
+            retained_capabilities: Vec::new(),
+            mount_proc: true,
+            mount_dev: true,
+            mac_profile: None,
+            share_ipc: false,
+            share_uts: false,
+            hostname: DEFAULT_SANDBOX_HOSTNAME.to_string(),
+            extra_bwrap_args: Vec::new(),
         }
     }
 } 
\ No newline at end of file
@@ -0,0 +1,375 @@
+//! eBPF runtime monitoring of sandboxed tasks
+//!
+//! seccomp decides which system calls a command may make, but not what the allowed calls
+//! did. When the gateway is built with the `ebpf` feature and `MCP_EBPF_MONITOR_OBJECT`
+//! names the compiled probes (`bpf/runtime_monitor.bpf.c`), tracepoints on `exec`,
+//! `connect` and `openat` record those calls for every process of a task cgroup (see
+//! [`crate::usage`]), and the events are returned with the execution result.
+//!
+//! The probes filter on the cgroup ID, so only commands that run in a task cgroup are
+//! monitored; the cgroup is joined before `exec`, so the events start with the command
+//! itself. The processes of containers run in the cgroups of the container runtime and the
+//! processes of microVMs are not visible at all. At most [`MAX_RUNTIME_EVENTS`] events are
+//! kept per command; further events are counted and dropped.
+//!
+//! Loading the probes needs `CAP_BPF` and `CAP_PERFMON` (or `CAP_SYS_ADMIN`). The probes
+//! are built with `clang -O2 -g -target bpf -c runtime_monitor.bpf.c`.
+
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::current_timestamp_ms;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Maximum number of runtime events kept per command
+pub const MAX_RUNTIME_EVENTS: usize = 4096;
+
+/// Size of the event records written by the probes (`struct runtime_event`)
+pub(crate) const RAW_EVENT_BYTES: usize = 308;
+
+const AF_UNIX: u16 = 1;
+const AF_INET: u16 = 2;
+const AF_INET6: u16 = 10;
+
+/// Kind of a runtime event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuntimeEventKind {
+    /// A process executed a file
+    Exec,
+    /// A process connected a socket
+    Connect,
+    /// A process opened a file
+    Open,
+}
+
+impl RuntimeEventKind {
+    /// Name of the kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exec => "exec",
+            Self::Connect => "connect",
+            Self::Open => "open",
+        }
+    }
+}
+
+/// System call of a monitored process
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeEvent {
+    /// Time the event was received (milliseconds since the Unix epoch)
+    pub timestamp_ms: u64,
+    /// Process ID (in the PID namespace of the gateway)
+    pub pid: u32,
+    /// Executable name of the process (`comm`)
+    pub name: String,
+    /// Kind of the event
+    pub kind: RuntimeEventKind,
+    /// Executed file, opened path or connected address (`ip:port`, or the path of a Unix socket)
+    pub target: String,
+}
+
+/// Parse an event record of the probes, returning the cgroup ID it was recorded for
+///
+/// The layout is the one of `struct runtime_event` in `bpf/runtime_monitor.bpf.c`.
+pub(crate) fn parse_event(raw: &[u8], timestamp_ms: u64) -> Option<(u64, RuntimeEvent)> {
+    if raw.len() < RAW_EVENT_BYTES {
+        return None;
+    }
+    let u16_at = |offset: usize| u16::from_ne_bytes([raw[offset], raw[offset + 1]]);
+    let u32_at = |offset: usize| u32::from_ne_bytes(raw[offset..offset + 4].try_into().unwrap());
+    let cgroup_id = u64::from_ne_bytes(raw[0..8].try_into().unwrap());
+    let kind = match u32_at(12) {
+        1 => RuntimeEventKind::Exec,
+        2 => RuntimeEventKind::Connect,
+        3 => RuntimeEventKind::Open,
+        _ => return None,
+    };
+    let path = c_string(&raw[52..308]);
+    let target = match kind {
+        RuntimeEventKind::Connect => {
+            // The port is in network byte order
+            let port = u16::from_be_bytes([raw[18], raw[19]]);
+            match u16_at(16) {
+                AF_INET => format!("{}:{}", Ipv4Addr::from(<[u8; 4]>::try_from(&raw[20..24]).unwrap()), port),
+                AF_INET6 => format!("[{}]:{}", Ipv6Addr::from(<[u8; 16]>::try_from(&raw[20..36]).unwrap()), port),
+                // Abstract socket names start with a NUL byte and are not read
+                AF_UNIX if path.is_empty() => "@".to_string(),
+                AF_UNIX => path,
+                family => format!("address family {}", family),
+            }
+        }
+        _ => path,
+    };
+    let event = RuntimeEvent {
+        timestamp_ms,
+        pid: u32_at(8),
+        name: c_string(&raw[36..52]),
+        kind,
+        target,
+    };
+    Some((cgroup_id, event))
+}
+
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).to_string()
+}
+
+/// Events of the monitored cgroups
+#[derive(Debug, Default)]
+pub(crate) struct EventLog {
+    cgroups: Mutex<HashMap<u64, CgroupEvents>>,
+}
+
+#[derive(Debug, Default)]
+struct CgroupEvents {
+    events: Vec<RuntimeEvent>,
+    dropped: u64,
+}
+
+impl EventLog {
+    /// Start collecting the events of a cgroup
+    pub(crate) fn watch(&self, cgroup_id: u64) {
+        self.lock().insert(cgroup_id, CgroupEvents::default());
+    }
+
+    /// Add an event record to the events of its cgroup, ignoring records of other cgroups
+    #[cfg_attr(not(feature = "ebpf"), allow(dead_code))]
+    pub(crate) fn record(&self, raw: &[u8]) {
+        let Some((cgroup_id, event)) = parse_event(raw, current_timestamp_ms()) else {
+            return;
+        };
+        if let Some(cgroup) = self.lock().get_mut(&cgroup_id) {
+            if cgroup.events.len() < MAX_RUNTIME_EVENTS {
+                cgroup.events.push(event);
+            } else {
+                cgroup.dropped += 1;
+            }
+        }
+    }
+
+    /// Stop collecting the events of a cgroup, returning them and the number of dropped events
+    pub(crate) fn take(&self, cgroup_id: u64) -> (Vec<RuntimeEvent>, u64) {
+        let cgroup = self.lock().remove(&cgroup_id).unwrap_or_default();
+        (cgroup.events, cgroup.dropped)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, CgroupEvents>> {
+        self.cgroups.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// eBPF runtime monitor shared by all executions
+#[derive(Debug, Clone)]
+pub struct RuntimeMonitor {
+    log: Arc<EventLog>,
+    #[cfg(feature = "ebpf")]
+    probes: Arc<probes::Probes>,
+}
+
+impl RuntimeMonitor {
+    /// Load the probes named by `MCP_EBPF_MONITOR_OBJECT`
+    ///
+    /// Returns `None` if the variable is not set. Must be called within a tokio runtime.
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let Ok(object) = std::env::var("MCP_EBPF_MONITOR_OBJECT") else {
+            return Ok(None);
+        };
+        #[cfg(feature = "ebpf")]
+        {
+            Self::load(std::path::Path::new(&object)).map(Some)
+        }
+        #[cfg(not(feature = "ebpf"))]
+        {
+            Err(McpError::Internal(format!(
+                "MCP_EBPF_MONITOR_OBJECT is set to {}, but the gateway was built without the ebpf feature",
+                object
+            )))
+        }
+    }
+
+    /// Load the compiled probes and attach them to their tracepoints
+    #[cfg(feature = "ebpf")]
+    pub fn load(object: &std::path::Path) -> McpResult<Self> {
+        let log = Arc::new(EventLog::default());
+        let probes = Arc::new(probes::Probes::load(object)?);
+        probes::spawn_reader(Arc::downgrade(&probes), Arc::downgrade(&log))?;
+        tracing::info!("eBPF runtime monitor loaded from {}", object.display());
+        Ok(Self { log, probes })
+    }
+
+    /// Start recording the events of a task cgroup (see [`crate::usage::UsageMeter::cgroup_id`])
+    pub fn watch(&self, cgroup_id: u64) -> McpResult<MonitorSession> {
+        self.log.watch(cgroup_id);
+        #[cfg(feature = "ebpf")]
+        if let Err(e) = self.probes.watch(cgroup_id) {
+            self.log.take(cgroup_id);
+            return Err(e);
+        }
+        Ok(MonitorSession {
+            monitor: self.clone(),
+            cgroup_id,
+            finished: false,
+        })
+    }
+
+    fn unwatch(&self, cgroup_id: u64) -> Vec<RuntimeEvent> {
+        #[cfg(feature = "ebpf")]
+        {
+            self.probes.unwatch(cgroup_id);
+            // Events submitted before the command exited may not have been read yet
+            self.probes.drain(&self.log);
+        }
+        let (events, dropped) = self.log.take(cgroup_id);
+        if dropped > 0 {
+            warn!(
+                "Dropped {} runtime events of cgroup {} beyond the limit of {}",
+                dropped, cgroup_id, MAX_RUNTIME_EVENTS
+            );
+        }
+        events
+    }
+}
+
+/// Recording of the runtime events of one command
+///
+/// Recording stops when the session is finished or dropped.
+#[derive(Debug)]
+pub struct MonitorSession {
+    monitor: RuntimeMonitor,
+    cgroup_id: u64,
+    finished: bool,
+}
+
+impl MonitorSession {
+    /// Stop recording, returning the events in the order they occurred
+    pub fn finish(mut self) -> Vec<RuntimeEvent> {
+        self.finished = true;
+        self.monitor.unwatch(self.cgroup_id)
+    }
+}
+
+impl Drop for MonitorSession {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.monitor.unwatch(self.cgroup_id);
+        }
+    }
+}
+
+#[cfg(feature = "ebpf")]
+mod probes {
+    use super::{EventLog, McpError, McpResult};
+    use aya::maps::{HashMap as BpfHashMap, MapData, RingBuf};
+    use aya::programs::TracePoint;
+    use aya::Ebpf;
+    use std::os::fd::AsRawFd;
+    use std::path::Path;
+    use std::sync::{Mutex, Weak};
+    use tokio::io::unix::AsyncFd;
+    use tracing::warn;
+
+    /// Programs of the object and the tracepoints they are attached to
+    const TRACEPOINTS: [(&str, &str, &str); 3] = [
+        ("runtime_monitor_exec", "sched", "sched_process_exec"),
+        ("runtime_monitor_connect", "syscalls", "sys_enter_connect"),
+        ("runtime_monitor_openat", "syscalls", "sys_enter_openat"),
+    ];
+
+    /// Attached probes and their maps
+    pub(super) struct Probes {
+        cgroups: Mutex<BpfHashMap<MapData, u64, u8>>,
+        events: Mutex<RingBuf<MapData>>,
+        // Detaches the programs when dropped
+        _ebpf: Ebpf,
+    }
+
+    impl std::fmt::Debug for Probes {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Probes").finish_non_exhaustive()
+        }
+    }
+
+    impl Probes {
+        pub(super) fn load(object: &Path) -> McpResult<Self> {
+            let mut ebpf = Ebpf::load_file(object).map_err(|e| probe_error(object, e))?;
+            for (name, category, tracepoint) in TRACEPOINTS {
+                let program: &mut TracePoint = ebpf
+                    .program_mut(name)
+                    .ok_or_else(|| probe_error(object, format!("program {} not found", name)))?
+                    .try_into()
+                    .map_err(|e| probe_error(object, e))?;
+                program.load().map_err(|e| probe_error(object, e))?;
+                program.attach(category, tracepoint).map_err(|e| probe_error(object, e))?;
+            }
+            let cgroups = ebpf
+                .take_map("MONITORED_CGROUPS")
+                .ok_or_else(|| probe_error(object, "map MONITORED_CGROUPS not found"))?;
+            let events = ebpf
+                .take_map("EVENTS")
+                .ok_or_else(|| probe_error(object, "map EVENTS not found"))?;
+            Ok(Self {
+                cgroups: Mutex::new(BpfHashMap::try_from(cgroups).map_err(|e| probe_error(object, e))?),
+                events: Mutex::new(RingBuf::try_from(events).map_err(|e| probe_error(object, e))?),
+                _ebpf: ebpf,
+            })
+        }
+
+        pub(super) fn watch(&self, cgroup_id: u64) -> McpResult<()> {
+            self.cgroups
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(cgroup_id, 1, 0)
+                .map_err(|e| McpError::Sandbox(format!("Failed to monitor cgroup {}: {}", cgroup_id, e)))
+        }
+
+        pub(super) fn unwatch(&self, cgroup_id: u64) {
+            if let Err(e) = self.cgroups.lock().unwrap_or_else(|e| e.into_inner()).remove(&cgroup_id) {
+                warn!("Failed to stop monitoring cgroup {}: {}", cgroup_id, e);
+            }
+        }
+
+        /// Route the events in the ring buffer to the log
+        pub(super) fn drain(&self, log: &EventLog) {
+            let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+            while let Some(item) = events.next() {
+                log.record(&item);
+            }
+        }
+    }
+
+    /// Read the ring buffer whenever it has events, until the monitor is dropped
+    pub(super) fn spawn_reader(probes: Weak<Probes>, log: Weak<EventLog>) -> McpResult<()> {
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|e| McpError::Internal(format!("eBPF runtime monitor needs a tokio runtime: {}", e)))?;
+        let fd = {
+            let probes = probes.upgrade().expect("probes are alive while the monitor is loaded");
+            let fd = probes.events.lock().unwrap_or_else(|e| e.into_inner()).as_raw_fd();
+            AsyncFd::new(fd).map_err(|e| McpError::Internal(format!("Failed to poll eBPF ring buffer: {}", e)))?
+        };
+        runtime.spawn(async move {
+            loop {
+                let mut ready = match fd.readable().await {
+                    Ok(ready) => ready,
+                    Err(e) => {
+                        warn!("Failed to poll eBPF ring buffer, runtime monitoring stopped: {}", e);
+                        return;
+                    }
+                };
+                let (Some(probes), Some(log)) = (probes.upgrade(), log.upgrade()) else {
+                    return;
+                };
+                probes.drain(&log);
+                ready.clear_ready();
+            }
+        });
+        Ok(())
+    }
+
+    fn probe_error(object: &Path, e: impl std::fmt::Display) -> McpError {
+        McpError::Internal(format!("Failed to load eBPF probes from {}: {}", object.display(), e))
+    }
+}
@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod tests {
+    use crate::monitor::{parse_event, EventLog, RuntimeEventKind, MAX_RUNTIME_EVENTS, RAW_EVENT_BYTES};
+
+    /// Event record as written by the probes
+    fn raw_event(cgroup_id: u64, kind: u32, family: u16, port: u16, addr: &[u8], path: &str) -> Vec<u8> {
+        let mut raw = vec![0; RAW_EVENT_BYTES + 4];
+        raw[0..8].copy_from_slice(&cgroup_id.to_ne_bytes());
+        raw[8..12].copy_from_slice(&4242u32.to_ne_bytes());
+        raw[12..16].copy_from_slice(&kind.to_ne_bytes());
+        raw[16..18].copy_from_slice(&family.to_ne_bytes());
+        raw[18..20].copy_from_slice(&port.to_be_bytes());
+        raw[20..20 + addr.len()].copy_from_slice(addr);
+        raw[36..40].copy_from_slice(b"curl");
+        raw[52..52 + path.len()].copy_from_slice(path.as_bytes());
+        raw
+    }
+
+    // Test for parsing the event records of the probes
+    #[test]
+    fn test_parse_event() {
+        let (cgroup_id, event) = parse_event(&raw_event(7, 1, 0, 0, &[], "/usr/bin/curl"), 1000).unwrap();
+        assert_eq!(cgroup_id, 7);
+        assert_eq!(event.timestamp_ms, 1000);
+        assert_eq!(event.pid, 4242);
+        assert_eq!(event.name, "curl");
+        assert_eq!(event.kind, RuntimeEventKind::Exec);
+        assert_eq!(event.target, "/usr/bin/curl");
+
+        let (_, event) = parse_event(&raw_event(7, 3, 0, 0, &[], "/etc/passwd"), 0).unwrap();
+        assert_eq!((event.kind, event.target.as_str()), (RuntimeEventKind::Open, "/etc/passwd"));
+
+        // Connected addresses of each address family
+        let connect = |family: u16, addr: &[u8], path: &str| {
+            let (_, event) = parse_event(&raw_event(7, 2, family, 443, addr, path), 0).unwrap();
+            assert_eq!(event.kind, RuntimeEventKind::Connect);
+            event.target
+        };
+        assert_eq!(connect(2, &[10, 0, 0, 1], ""), "10.0.0.1:443");
+        let mut ipv6 = [0; 16];
+        ipv6[15] = 1;
+        assert_eq!(connect(10, &ipv6, ""), "[::1]:443");
+        assert_eq!(connect(1, &[], "/run/docker.sock"), "/run/docker.sock");
+        assert_eq!(connect(1, &[], ""), "@");
+        assert_eq!(connect(16, &[], ""), "address family 16");
+
+        // Truncated records and unknown kinds are ignored
+        assert!(parse_event(&raw_event(7, 1, 0, 0, &[], "")[..RAW_EVENT_BYTES - 1], 0).is_none());
+        assert!(parse_event(&raw_event(7, 9, 0, 0, &[], ""), 0).is_none());
+    }
+
+    // Test for collecting the events of the monitored cgroups only
+    #[test]
+    fn test_event_log() {
+        let log = EventLog::default();
+        log.watch(1);
+        log.watch(2);
+        log.record(&raw_event(1, 1, 0, 0, &[], "/bin/sh"));
+        log.record(&raw_event(2, 3, 0, 0, &[], "/tmp/a"));
+        log.record(&raw_event(3, 3, 0, 0, &[], "/tmp/b"));
+
+        let (events, dropped) = log.take(1);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].target, "/bin/sh");
+        assert_eq!(dropped, 0);
+        // Events of a cgroup that is no longer watched are ignored
+        log.record(&raw_event(1, 1, 0, 0, &[], "/bin/ls"));
+        assert!(log.take(1).0.is_empty());
+        assert!(log.take(3).0.is_empty());
+
+        // Events beyond the limit are counted and dropped
+        for _ in 0..MAX_RUNTIME_EVENTS + 5 {
+            log.record(&raw_event(2, 3, 0, 0, &[], "/tmp/a"));
+        }
+        let (events, dropped) = log.take(2);
+        assert_eq!(events.len(), MAX_RUNTIME_EVENTS);
+        assert_eq!(dropped, 6);
+    }
+}
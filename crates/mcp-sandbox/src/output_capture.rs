@@ -0,0 +1,196 @@
+//! Bounded capture of command output
+//!
+//! The output a command writes to stdout and stderr is kept in memory only up to an inline
+//! limit per stream (`MCP_OUTPUT_INLINE_BYTES`, 1 MiB by default). Beyond the limit, the
+//! complete stream is spilled to a file in `MCP_OUTPUT_SPILL_DIR` (`mcp-output` in the
+//! temporary directory by default): the result then holds the beginning of the output
+//! inline, and a [`SpilledOutput`] referencing the file with all of it. The file is removed
+//! once the last reference to it is dropped, so a single verbose command cannot exhaust the
+//! memory of the gateway.
+//!
+//! If the spill file cannot be written, the output beyond the inline limit is discarded
+//! with a warning. The output of microVM commands is collected by the guest protocol and
+//! not bounded (see [`crate::firecracker`]).
+
+use crate::output_log::OutputStream;
+use mcp_common::error::McpResult;
+use mcp_common::utils::{get_env_var_or, parse_positive};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Bytes of each stream kept in memory unless configured
+pub const DEFAULT_INLINE_OUTPUT_BYTES: u64 = 1024 * 1024;
+
+/// Sequence number making the names of spill files unique within the process
+static SPILL_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Output capture configuration
+#[derive(Debug, Clone)]
+pub struct OutputCaptureConfig {
+    /// Bytes of each stream kept in memory and returned inline
+    pub inline_bytes: u64,
+    /// Directory the complete output of larger streams is written to
+    pub spill_dir: PathBuf,
+}
+
+impl Default for OutputCaptureConfig {
+    fn default() -> Self {
+        Self {
+            inline_bytes: DEFAULT_INLINE_OUTPUT_BYTES,
+            spill_dir: std::env::temp_dir().join("mcp-output"),
+        }
+    }
+}
+
+impl OutputCaptureConfig {
+    /// Build the configuration from environment variables
+    ///
+    /// * `MCP_OUTPUT_INLINE_BYTES` - bytes of each stream kept in memory
+    /// * `MCP_OUTPUT_SPILL_DIR` - directory of the spill files
+    pub fn from_env() -> McpResult<Self> {
+        let default = Self::default();
+        let inline_bytes = parse_positive(
+            "MCP_OUTPUT_INLINE_BYTES",
+            &get_env_var_or("MCP_OUTPUT_INLINE_BYTES", &default.inline_bytes.to_string()),
+        )?;
+        let spill_dir = PathBuf::from(get_env_var_or("MCP_OUTPUT_SPILL_DIR", &default.spill_dir.to_string_lossy()));
+        Ok(Self { inline_bytes, spill_dir })
+    }
+}
+
+/// Complete output of a stream that exceeded the inline limit
+#[derive(Debug, Clone)]
+pub struct SpilledOutput {
+    file: Arc<SpillFile>,
+    size: u64,
+}
+
+impl SpilledOutput {
+    /// File with the complete output (removed once every clone is dropped)
+    pub fn path(&self) -> &Path {
+        &self.file.path
+    }
+
+    /// Size of the complete output (bytes)
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Spill file, removed when dropped
+#[derive(Debug)]
+struct SpillFile {
+    path: PathBuf,
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            debug!("Failed to remove output spill file {:?}: {}", self.path, e);
+        }
+    }
+}
+
+/// Output of one stream of a running command
+#[derive(Debug)]
+pub(crate) struct OutputCapture {
+    config: OutputCaptureConfig,
+    stream: OutputStream,
+    /// Beginning of the output, at most `inline_bytes`
+    head: Vec<u8>,
+    /// Spill file with everything written so far, once the output exceeded `inline_bytes`
+    spill: Option<(File, Arc<SpillFile>)>,
+    /// Whether the output beyond `inline_bytes` is discarded because it could not be spilled
+    discarding: bool,
+    len: u64,
+}
+
+impl OutputCapture {
+    pub(crate) fn new(config: &OutputCaptureConfig, stream: OutputStream) -> Self {
+        Self {
+            config: config.clone(),
+            stream,
+            head: Vec::new(),
+            spill: None,
+            discarding: false,
+            len: 0,
+        }
+    }
+
+    /// Total number of bytes written
+    pub(crate) fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Append output
+    pub(crate) fn write(&mut self, data: &[u8]) {
+        self.len += data.len() as u64;
+        let room = (self.config.inline_bytes as usize).saturating_sub(self.head.len());
+        if self.spill.is_none() && !self.discarding && data.len() > room {
+            match self.create_spill_file() {
+                Ok(spill) => self.spill = Some(spill),
+                Err(e) => self.discard(e),
+            }
+        }
+        if let Some((file, _)) = &mut self.spill {
+            if let Err(e) = file.write_all(data) {
+                self.discard(e);
+            }
+        }
+        self.head.extend_from_slice(&data[..room.min(data.len())]);
+    }
+
+    /// The last `max_len` bytes written (of the inline output if the rest was discarded)
+    pub(crate) fn tail(&mut self, max_len: usize) -> Vec<u8> {
+        if let Some((file, _)) = &mut self.spill {
+            let mut tail = Vec::new();
+            // Reading to the end leaves the file positioned for further writes
+            let read = file
+                .seek(SeekFrom::Start(self.len.saturating_sub(max_len as u64)))
+                .and_then(|_| file.read_to_end(&mut tail));
+            match read {
+                Ok(_) => return tail,
+                Err(e) => warn!("Failed to read spilled {} of command: {}", self.stream.as_str(), e),
+            }
+        }
+        self.head[self.head.len().saturating_sub(max_len)..].to_vec()
+    }
+
+    /// Inline output and the spilled complete output, if it exceeded the inline limit
+    pub(crate) fn finish(self) -> (Vec<u8>, Option<SpilledOutput>) {
+        let spilled = self.spill.map(|(_, file)| SpilledOutput { file, size: self.len });
+        (self.head, spilled)
+    }
+
+    fn create_spill_file(&self) -> io::Result<(File, Arc<SpillFile>)> {
+        fs::create_dir_all(&self.config.spill_dir)?;
+        let name = format!(
+            "{}-{}.{}",
+            std::process::id(),
+            SPILL_SEQUENCE.fetch_add(1, Ordering::Relaxed),
+            self.stream.as_str()
+        );
+        let path = self.config.spill_dir.join(name);
+        let mut file = OpenOptions::new().read(true).write(true).create_new(true).mode(0o600).open(&path)?;
+        let spill = Arc::new(SpillFile { path });
+        file.write_all(&self.head)?;
+        debug!("Spilling {} of command to {:?}", self.stream.as_str(), spill.path);
+        Ok((file, spill))
+    }
+
+    fn discard(&mut self, e: io::Error) {
+        warn!(
+            "Failed to spill {} of command, discarding the output beyond {} bytes: {}",
+            self.stream.as_str(),
+            self.config.inline_bytes,
+            e
+        );
+        self.spill = None;
+        self.discarding = true;
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{ExecutionRequest, SandboxConfig};
+    use crate::output_capture::{OutputCapture, OutputCaptureConfig};
+    use crate::output_log::OutputStream;
+    use crate::runner::SandboxRunner;
+    use std::collections::HashMap;
+
+    fn config(dir: &tempfile::TempDir, inline_bytes: u64) -> OutputCaptureConfig {
+        OutputCaptureConfig {
+            inline_bytes,
+            spill_dir: dir.path().join("spill"),
+        }
+    }
+
+    // Test for keeping small output in memory and spilling larger output
+    #[test]
+    fn test_spill_beyond_inline_limit() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut capture = OutputCapture::new(&config(&dir, 8), OutputStream::Stdout);
+        capture.write(b"hello");
+        assert_eq!(capture.tail(3), b"llo");
+        let (inline, spilled) = capture.finish();
+        assert_eq!(inline, b"hello");
+        assert!(spilled.is_none());
+        assert!(!dir.path().join("spill").exists());
+
+        let mut capture = OutputCapture::new(&config(&dir, 8), OutputStream::Stderr);
+        capture.write(b"hello");
+        capture.write(b" world");
+        assert_eq!(capture.tail(4), b"orld");
+        capture.write(b"!");
+        assert_eq!(capture.len(), 12);
+        let (inline, spilled) = capture.finish();
+        // Only the beginning is kept in memory, the file holds everything
+        assert_eq!(inline, b"hello wo");
+        let spilled = spilled.unwrap();
+        assert_eq!(spilled.size(), 12);
+        assert_eq!(std::fs::read(spilled.path()).unwrap(), b"hello world!");
+        assert!(spilled.path().to_string_lossy().ends_with(".stderr"));
+
+        // The file is removed with the last reference
+        let path = spilled.path().to_path_buf();
+        let clone = spilled.clone();
+        drop(spilled);
+        assert!(path.exists());
+        drop(clone);
+        assert!(!path.exists());
+    }
+
+    // Test for discarding the output beyond the inline limit when it cannot be spilled
+    #[test]
+    fn test_spill_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("spill"), "not a directory").unwrap();
+
+        let mut capture = OutputCapture::new(&config(&dir, 4), OutputStream::Stdout);
+        capture.write(b"hello world");
+        assert_eq!(capture.len(), 11);
+        let (inline, spilled) = capture.finish();
+        assert_eq!(inline, b"hell");
+        assert!(spilled.is_none());
+    }
+
+    // Test for the result of a command with large output
+    #[tokio::test]
+    async fn test_run_with_large_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = SandboxRunner::new().with_output_capture(config(&dir, 1024));
+        let request = ExecutionRequest {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "head -c 100000 /dev/zero | tr '\\0' x; echo small >&2".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config: SandboxConfig {
+                enabled: false,
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+
+        let result = runner.run(request).await.unwrap();
+        assert_eq!(result.stdout, "x".repeat(1024));
+        let spilled = result.stdout_spill.as_ref().unwrap();
+        assert_eq!(spilled.size(), 100_000);
+        assert_eq!(std::fs::read(spilled.path()).unwrap(), vec![b'x'; 100_000]);
+        assert_eq!(result.stderr, "small\n");
+        assert!(result.stderr_spill.is_none());
+    }
+}
@@ -0,0 +1,411 @@
+//! Rolling output log for task stdout/stderr
+//!
+//! Output of a task is stored in size/time bounded segment files per stream, together
+//! with an `index.json` describing every segment. Readers can tail the live segment
+//! while older (closed) segments are exposed as downloadable artifacts.
+
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{current_timestamp_ms, get_env_var_or, parse_positive};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Index file name inside a task log directory
+const INDEX_FILE: &str = "index.json";
+
+/// Maximum number of bytes returned by a single tail read per stream
+const MAX_TAIL_READ_BYTES: u64 = 64 * 1024;
+
+/// Output stream type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStream {
+    /// Standard output
+    Stdout,
+    /// Standard error output
+    Stderr,
+}
+
+impl OutputStream {
+    /// All streams, in the order they are read
+    pub const ALL: [OutputStream; 2] = [OutputStream::Stdout, OutputStream::Stderr];
+
+    /// Stream name used in segment file names
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputStream::Stdout => "stdout",
+            OutputStream::Stderr => "stderr",
+        }
+    }
+}
+
+/// Output log configuration
+#[derive(Debug, Clone)]
+pub struct OutputLogConfig {
+    /// Directory under which a log directory is created per task
+    pub base_dir: PathBuf,
+    /// Maximum size of a segment before it is rotated (bytes)
+    pub max_segment_bytes: u64,
+    /// Maximum age of a segment before it is rotated
+    pub max_segment_age: Duration,
+}
+
+impl Default for OutputLogConfig {
+    fn default() -> Self {
+        Self {
+            base_dir: std::env::temp_dir().join("mcp-task-logs"),
+            max_segment_bytes: 8 * 1024 * 1024, // 8 MiB
+            max_segment_age: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl OutputLogConfig {
+    /// Build the configuration from environment variables
+    ///
+    /// * `MCP_TASK_LOG_DIR` - base directory
+    /// * `MCP_TASK_LOG_SEGMENT_BYTES` - maximum segment size
+    /// * `MCP_TASK_LOG_SEGMENT_SECS` - maximum segment age
+    pub fn from_env() -> McpResult<Self> {
+        let default = Self::default();
+
+        let base_dir = PathBuf::from(get_env_var_or(
+            "MCP_TASK_LOG_DIR",
+            &default.base_dir.to_string_lossy(),
+        ));
+        let max_segment_bytes = parse_positive(
+            "MCP_TASK_LOG_SEGMENT_BYTES",
+            &get_env_var_or("MCP_TASK_LOG_SEGMENT_BYTES", &default.max_segment_bytes.to_string()),
+        )?;
+        let max_segment_secs = parse_positive(
+            "MCP_TASK_LOG_SEGMENT_SECS",
+            &get_env_var_or(
+                "MCP_TASK_LOG_SEGMENT_SECS",
+                &default.max_segment_age.as_secs().to_string(),
+            ),
+        )?;
+
+        Ok(Self {
+            base_dir,
+            max_segment_bytes,
+            max_segment_age: Duration::from_secs(max_segment_secs),
+        })
+    }
+
+    /// Log directory of a task
+    pub fn task_dir(&self, task_id: &str) -> McpResult<PathBuf> {
+        let valid = !task_id.is_empty()
+            && task_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(McpError::InvalidRequest(format!("Invalid task ID: '{}'", task_id)));
+        }
+        Ok(self.base_dir.join(task_id))
+    }
+}
+
+/// Segment information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentInfo {
+    /// Segment file name (unique within the task)
+    pub name: String,
+    /// Stream the segment belongs to
+    pub stream: OutputStream,
+    /// Sequence number within the stream (starting at 1)
+    pub sequence: u32,
+    /// Segment size (bytes, final once closed)
+    pub size_bytes: u64,
+    /// Creation time (UNIX milliseconds)
+    pub created_at_ms: u64,
+    /// Time the segment was closed (UNIX milliseconds), `None` while live
+    #[serde(default)]
+    pub closed_at_ms: Option<u64>,
+}
+
+impl SegmentInfo {
+    /// Whether the segment is still being written
+    pub fn is_live(&self) -> bool {
+        self.closed_at_ms.is_none()
+    }
+}
+
+/// Index of the segments of a task
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OutputLogIndex {
+    /// Segments in creation order
+    pub segments: Vec<SegmentInfo>,
+    /// Whether the task has finished writing output
+    #[serde(default)]
+    pub finished: bool,
+}
+
+impl OutputLogIndex {
+    /// Look up a segment by name
+    pub fn segment(&self, name: &str) -> Option<&SegmentInfo> {
+        self.segments.iter().find(|s| s.name == name)
+    }
+
+    /// Segments of a stream in sequence order
+    pub fn stream_segments(&self, stream: OutputStream) -> impl Iterator<Item = &SegmentInfo> {
+        self.segments.iter().filter(move |s| s.stream == stream)
+    }
+
+    fn load(dir: &Path) -> McpResult<Self> {
+        let content = fs::read_to_string(dir.join(INDEX_FILE))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn save(&self, dir: &Path) -> McpResult<()> {
+        // Write atomically so readers never observe a partial index
+        let tmp_path = dir.join(format!("{}.tmp", INDEX_FILE));
+        fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp_path, dir.join(INDEX_FILE))?;
+        Ok(())
+    }
+}
+
+/// Currently open segment of a stream
+#[derive(Debug)]
+struct OpenSegment {
+    file: File,
+    index_pos: usize,
+    opened_at: Instant,
+}
+
+#[derive(Debug)]
+struct WriterState {
+    index: OutputLogIndex,
+    open: HashMap<OutputStream, OpenSegment>,
+}
+
+/// Writer for the output log of one task
+#[derive(Debug)]
+pub struct OutputLogWriter {
+    dir: PathBuf,
+    max_segment_bytes: u64,
+    max_segment_age: Duration,
+    state: Mutex<WriterState>,
+}
+
+impl OutputLogWriter {
+    /// Create the log directory of a task
+    pub fn create(config: &OutputLogConfig, task_id: &str) -> McpResult<Self> {
+        let dir = config.task_dir(task_id)?;
+        fs::create_dir_all(&dir)?;
+
+        let index = OutputLogIndex::default();
+        index.save(&dir)?;
+
+        Ok(Self {
+            dir,
+            max_segment_bytes: config.max_segment_bytes,
+            max_segment_age: config.max_segment_age,
+            state: Mutex::new(WriterState {
+                index,
+                open: HashMap::new(),
+            }),
+        })
+    }
+
+    /// Log directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Current index (segments are listed from the moment they are opened)
+    pub fn index(&self) -> McpResult<OutputLogIndex> {
+        Ok(self.lock()?.index.clone())
+    }
+
+    /// Append output, rotating segments as needed
+    pub fn append(&self, stream: OutputStream, data: &[u8]) -> McpResult<()> {
+        let mut state = self.lock()?;
+        if state.index.finished {
+            return Err(McpError::Internal("Output log is already finished".to_string()));
+        }
+
+        let mut remaining = data;
+        while !remaining.is_empty() {
+            let pos = self.current_segment(&mut state, stream)?;
+            let size = state.index.segments[pos].size_bytes;
+            let len = remaining.len().min((self.max_segment_bytes - size) as usize);
+
+            let segment = state.open.get_mut(&stream).expect("segment must be open");
+            segment.file.write_all(&remaining[..len])?;
+            state.index.segments[pos].size_bytes += len as u64;
+            remaining = &remaining[len..];
+        }
+
+        Ok(())
+    }
+
+    /// Close all segments and mark the log as finished
+    pub fn finish(&self) -> McpResult<()> {
+        let mut state = self.lock()?;
+        if state.index.finished {
+            return Ok(());
+        }
+
+        for stream in OutputStream::ALL {
+            Self::close_segment(&mut state, stream)?;
+        }
+        state.index.finished = true;
+        state.index.save(&self.dir)
+    }
+
+    fn lock(&self) -> McpResult<std::sync::MutexGuard<'_, WriterState>> {
+        self.state
+            .lock()
+            .map_err(|_| McpError::Internal("Output log lock poisoned".to_string()))
+    }
+
+    /// Return the index position of a writable segment, rotating if necessary
+    fn current_segment(&self, state: &mut WriterState, stream: OutputStream) -> McpResult<usize> {
+        if let Some(segment) = state.open.get(&stream) {
+            let full = state.index.segments[segment.index_pos].size_bytes >= self.max_segment_bytes;
+            let expired = segment.opened_at.elapsed() >= self.max_segment_age;
+            if !full && !expired {
+                return Ok(segment.index_pos);
+            }
+            Self::close_segment(state, stream)?;
+        }
+
+        let sequence = state.index.stream_segments(stream).count() as u32 + 1;
+        let name = format!("{}-{:06}.log", stream.as_str(), sequence);
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(self.dir.join(&name))?;
+        debug!("Opened output log segment: {:?}", self.dir.join(&name));
+
+        state.index.segments.push(SegmentInfo {
+            name,
+            stream,
+            sequence,
+            size_bytes: 0,
+            created_at_ms: current_timestamp_ms(),
+            closed_at_ms: None,
+        });
+        let index_pos = state.index.segments.len() - 1;
+        state.open.insert(
+            stream,
+            OpenSegment {
+                file,
+                index_pos,
+                opened_at: Instant::now(),
+            },
+        );
+        state.index.save(&self.dir)?;
+
+        Ok(index_pos)
+    }
+
+    fn close_segment(state: &mut WriterState, stream: OutputStream) -> McpResult<()> {
+        if let Some(segment) = state.open.remove(&stream) {
+            segment.file.sync_all()?;
+            state.index.segments[segment.index_pos].closed_at_ms = Some(current_timestamp_ms());
+        }
+        Ok(())
+    }
+}
+
+/// Read position of a tailing reader
+#[derive(Debug, Clone, Default)]
+pub struct TailCursor {
+    positions: HashMap<OutputStream, (u32, u64)>,
+}
+
+/// Reader for the output log of one task
+#[derive(Debug, Clone)]
+pub struct OutputLogReader {
+    dir: PathBuf,
+}
+
+impl OutputLogReader {
+    /// Create a reader for a task (the log does not need to exist yet)
+    pub fn new(config: &OutputLogConfig, task_id: &str) -> McpResult<Self> {
+        Ok(Self {
+            dir: config.task_dir(task_id)?,
+        })
+    }
+
+    /// Load the current index (`NotFound` if the task has no output log yet)
+    pub fn index(&self) -> McpResult<OutputLogIndex> {
+        OutputLogIndex::load(&self.dir)
+    }
+
+    /// Current size of a segment (live segments are measured on disk)
+    pub fn segment_size(&self, segment: &SegmentInfo) -> u64 {
+        if segment.is_live() {
+            fs::metadata(self.dir.join(&segment.name))
+                .map(|m| m.len())
+                .unwrap_or(segment.size_bytes)
+        } else {
+            segment.size_bytes
+        }
+    }
+
+    /// Read part of a segment listed in the index
+    pub fn read_segment(
+        &self,
+        index: &OutputLogIndex,
+        name: &str,
+        offset: u64,
+        max_len: u64,
+    ) -> McpResult<Vec<u8>> {
+        // Only names listed in the index are accepted to avoid path traversal
+        let segment = index
+            .segment(name)
+            .ok_or_else(|| McpError::NotFound(format!("Output log segment not found: {}", name)))?;
+
+        let mut file = File::open(self.dir.join(&segment.name))?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buf = Vec::new();
+        file.take(max_len).read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Read output appended since the cursor position, advancing the cursor
+    pub fn read_new(
+        &self,
+        index: &OutputLogIndex,
+        cursor: &mut TailCursor,
+    ) -> McpResult<Vec<(OutputStream, Vec<u8>)>> {
+        let mut chunks = Vec::new();
+
+        for stream in OutputStream::ALL {
+            let (mut sequence, mut offset) =
+                cursor.positions.get(&stream).copied().unwrap_or((1, 0));
+            let mut budget = MAX_TAIL_READ_BYTES;
+
+            while budget > 0 {
+                let Some(segment) = index.stream_segments(stream).find(|s| s.sequence == sequence)
+                else {
+                    break;
+                };
+
+                let data = self.read_segment(index, &segment.name, offset, budget)?;
+                offset += data.len() as u64;
+                budget -= data.len() as u64;
+                if !data.is_empty() {
+                    chunks.push((stream, data));
+                }
+
+                // Move to the next segment only once a closed segment is fully read
+                if segment.is_live() || offset < segment.size_bytes {
+                    break;
+                }
+                sequence += 1;
+                offset = 0;
+            }
+
+            cursor.positions.insert(stream, (sequence, offset));
+        }
+
+        Ok(chunks)
+    }
+}
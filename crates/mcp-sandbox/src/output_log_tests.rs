@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use crate::output_log::{
+        OutputLogConfig, OutputLogReader, OutputLogWriter, OutputStream, TailCursor,
+    };
+    use std::time::Duration;
+
+    fn config(dir: &tempfile::TempDir, max_segment_bytes: u64) -> OutputLogConfig {
+        OutputLogConfig {
+            base_dir: dir.path().to_path_buf(),
+            max_segment_bytes,
+            max_segment_age: Duration::from_secs(3600),
+        }
+    }
+
+    // Test for size based segment rotation
+    #[test]
+    fn test_rotate_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(&dir, 4);
+
+        let writer = OutputLogWriter::create(&config, "task-1").unwrap();
+        writer.append(OutputStream::Stdout, b"hello world").unwrap();
+        writer.append(OutputStream::Stderr, b"err").unwrap();
+        writer.finish().unwrap();
+
+        let reader = OutputLogReader::new(&config, "task-1").unwrap();
+        let index = reader.index().unwrap();
+        assert!(index.finished);
+
+        let stdout: Vec<_> = index.stream_segments(OutputStream::Stdout).collect();
+        assert_eq!(stdout.len(), 3);
+        assert_eq!(stdout[0].name, "stdout-000001.log");
+        assert_eq!(stdout[0].size_bytes, 4);
+        assert_eq!(stdout[2].size_bytes, 3);
+        assert!(index.segments.iter().all(|s| !s.is_live()));
+
+        let data = reader.read_segment(&index, "stdout-000002.log", 0, 1024).unwrap();
+        assert_eq!(data, b"o wo");
+    }
+
+    // Test for time based segment rotation
+    #[test]
+    fn test_rotate_by_age() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = config(&dir, 1024);
+        config.max_segment_age = Duration::from_millis(10);
+
+        let writer = OutputLogWriter::create(&config, "task-1").unwrap();
+        writer.append(OutputStream::Stdout, b"first").unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        writer.append(OutputStream::Stdout, b"second").unwrap();
+
+        let reader = OutputLogReader::new(&config, "task-1").unwrap();
+        let index = reader.index().unwrap();
+        let stdout: Vec<_> = index.stream_segments(OutputStream::Stdout).collect();
+        assert_eq!(stdout.len(), 2);
+        assert!(!stdout[0].is_live());
+        assert!(stdout[1].is_live());
+    }
+
+    // Test for tailing a live log across rotations
+    #[test]
+    fn test_tail_across_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(&dir, 4);
+
+        let writer = OutputLogWriter::create(&config, "task-1").unwrap();
+        let reader = OutputLogReader::new(&config, "task-1").unwrap();
+        let mut cursor = TailCursor::default();
+
+        writer.append(OutputStream::Stdout, b"abc").unwrap();
+        let chunks = reader.read_new(&reader.index().unwrap(), &mut cursor).unwrap();
+        let stdout: Vec<u8> = chunks.into_iter().flat_map(|(_, data)| data).collect();
+        assert_eq!(stdout, b"abc");
+
+        writer.append(OutputStream::Stdout, b"defghij").unwrap();
+        writer.finish().unwrap();
+        let chunks = reader.read_new(&reader.index().unwrap(), &mut cursor).unwrap();
+        let stdout: Vec<u8> = chunks.into_iter().flat_map(|(_, data)| data).collect();
+        assert_eq!(stdout, b"defghij");
+
+        // Nothing new once everything has been read
+        let chunks = reader.read_new(&reader.index().unwrap(), &mut cursor).unwrap();
+        assert!(chunks.is_empty());
+    }
+
+    // Test for rejecting unknown segments and invalid task IDs
+    #[test]
+    fn test_invalid_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(&dir, 4);
+
+        let writer = OutputLogWriter::create(&config, "task-1").unwrap();
+        writer.finish().unwrap();
+
+        let reader = OutputLogReader::new(&config, "task-1").unwrap();
+        let index = reader.index().unwrap();
+        assert!(reader.read_segment(&index, "../index.json", 0, 16).is_err());
+        assert!(OutputLogReader::new(&config, "../task-1").is_err());
+        assert!(writer.append(OutputStream::Stdout, b"late").is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! Ephemeral overlay workspaces
+//!
+//! With [`WorkspaceMode::Ephemeral`] or [`WorkspaceMode::CommitOnSuccess`], every read-write
+//! path of the sandbox is mounted as an overlayfs (`bwrap --overlay`, bubblewrap 0.10 or
+//! later) whose lower layer is the path itself and whose upper layer is a throwaway
+//! directory. The command sees a writable filesystem, but its changes only land in the upper
+//! layer, which is removed after the command.
+//!
+//! With [`WorkspaceMode::CommitOnSuccess`] the upper layer is merged into the path when the
+//! command exited with status 0:
+//!
+//! * whiteouts (character devices 0/0) delete the path they hide
+//! * opaque directories (`trusted.overlay.opaque` or `user.overlay.opaque` set to `y`)
+//!   replace the directory instead of being merged into it
+//! * other directories are merged, files and symbolic links replace the path
+//!
+//! [`WorkspaceMode::Ephemeral`]: crate::models::WorkspaceMode::Ephemeral
+//! [`WorkspaceMode::CommitOnSuccess`]: crate::models::WorkspaceMode::CommitOnSuccess
+
+use mcp_common::error::{McpError, McpResult};
+use std::ffi::CString;
+use std::fs::{self, Metadata};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{debug, warn};
+
+/// Sequence number for unique overlay directories
+static NEXT_OVERLAY: AtomicU64 = AtomicU64::new(0);
+
+/// Overlay of one read-write path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlayLayer {
+    /// Read-write path of the sandbox (the lower layer)
+    pub lower: PathBuf,
+    /// Directory receiving the changes
+    pub upper: PathBuf,
+    /// Work directory of overlayfs
+    pub work: PathBuf,
+}
+
+/// Throwaway upper layers of the read-write paths of a command, removed when dropped
+#[derive(Debug)]
+pub struct WorkspaceOverlay {
+    root: PathBuf,
+    layers: Vec<OverlayLayer>,
+}
+
+impl WorkspaceOverlay {
+    /// Create upper layers for the given paths in the temporary directory
+    pub fn create(paths: &[PathBuf]) -> McpResult<Self> {
+        Self::create_in(&std::env::temp_dir(), paths)
+    }
+
+    /// Create upper layers for the given paths below `base`
+    ///
+    /// Overlayfs needs directories as lower layers, so every path must be an existing directory.
+    pub fn create_in(base: &Path, paths: &[PathBuf]) -> McpResult<Self> {
+        let root = base.join(format!(
+            "mcp-overlay-{}-{}",
+            std::process::id(),
+            NEXT_OVERLAY.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&root).map_err(|e| overlay_error(&root, e))?;
+        let mut overlay = Self {
+            root,
+            layers: Vec::new(),
+        };
+
+        for (index, path) in paths.iter().enumerate() {
+            if !path.is_dir() {
+                return Err(McpError::Sandbox(format!(
+                    "Only existing directories can be mounted as overlay: {}",
+                    path.display()
+                )));
+            }
+            let layer = OverlayLayer {
+                lower: path.clone(),
+                upper: overlay.root.join(format!("upper-{}", index)),
+                work: overlay.root.join(format!("work-{}", index)),
+            };
+            for dir in [&layer.upper, &layer.work] {
+                fs::create_dir(dir).map_err(|e| overlay_error(dir, e))?;
+            }
+            overlay.layers.push(layer);
+        }
+        debug!("Created workspace overlay {}", overlay.root.display());
+        Ok(overlay)
+    }
+
+    /// Overlays of the paths
+    pub fn layers(&self) -> &[OverlayLayer] {
+        &self.layers
+    }
+
+    /// Overlay of a path
+    pub fn layer(&self, path: &Path) -> Option<&OverlayLayer> {
+        self.layers.iter().find(|layer| layer.lower == path)
+    }
+
+    /// Merge the changes of the upper layers into the paths
+    ///
+    /// Returns the number of changed entries.
+    pub fn commit(&self) -> McpResult<usize> {
+        let mut changed = 0;
+        for layer in &self.layers {
+            changed += merge_dir(&layer.upper, &layer.lower)?;
+        }
+        debug!("Committed {} changes of workspace overlay {}", changed, self.root.display());
+        Ok(changed)
+    }
+}
+
+impl Drop for WorkspaceOverlay {
+    fn drop(&mut self) {
+        // overlayfs leaves directories without permissions in the work directories
+        if let Err(e) = remove_tree(&self.root) {
+            warn!("Failed to remove workspace overlay {}: {}", self.root.display(), e);
+        }
+    }
+}
+
+/// Merge an upper directory into a lower directory
+fn merge_dir(upper: &Path, lower: &Path) -> McpResult<usize> {
+    let mut changed = 0;
+    let entries = fs::read_dir(upper).map_err(|e| overlay_error(upper, e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| overlay_error(upper, e))?;
+        let source = entry.path();
+        let target = lower.join(entry.file_name());
+        let metadata = fs::symlink_metadata(&source).map_err(|e| overlay_error(&source, e))?;
+        let file_type = metadata.file_type();
+
+        if is_whiteout(&metadata) {
+            remove_path(&target)?;
+        } else if file_type.is_dir() {
+            let target_is_dir = fs::symlink_metadata(&target).is_ok_and(|target| target.is_dir());
+            if !target_is_dir || is_opaque(&source) {
+                remove_path(&target)?;
+                fs::create_dir(&target).map_err(|e| overlay_error(&target, e))?;
+            }
+            fs::set_permissions(&target, fs::Permissions::from_mode(metadata.mode()))
+                .map_err(|e| overlay_error(&target, e))?;
+            changed += merge_dir(&source, &target)?;
+        } else if file_type.is_symlink() {
+            let link = fs::read_link(&source).map_err(|e| overlay_error(&source, e))?;
+            remove_path(&target)?;
+            std::os::unix::fs::symlink(link, &target).map_err(|e| overlay_error(&target, e))?;
+        } else if file_type.is_file() {
+            // Never write through a symbolic link of the lower layer
+            remove_path(&target)?;
+            fs::copy(&source, &target).map_err(|e| overlay_error(&target, e))?;
+        } else {
+            debug!("Not committing special file {}", source.display());
+            continue;
+        }
+        changed += 1;
+    }
+    Ok(changed)
+}
+
+/// Whether an entry of an upper layer is a whiteout
+fn is_whiteout(metadata: &Metadata) -> bool {
+    metadata.file_type().is_char_device() && metadata.rdev() == 0
+}
+
+/// Whether a directory of an upper layer is opaque
+fn is_opaque(path: &Path) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    ["trusted.overlay.opaque", "user.overlay.opaque"].iter().any(|name| {
+        let name = CString::new(*name).unwrap();
+        let mut value = [0u8; 1];
+        // SAFETY: the path and name are NUL-terminated and the buffer length is correct
+        let len = unsafe { libc::lgetxattr(path.as_ptr(), name.as_ptr(), value.as_mut_ptr().cast(), value.len()) };
+        len == 1 && value[0] == b'y'
+    })
+}
+
+/// Remove a file, symbolic link or directory tree if it exists
+fn remove_path(path: &Path) -> McpResult<()> {
+    let result = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(_) => return Ok(()),
+    };
+    result.map_err(|e| overlay_error(path, e))
+}
+
+/// Remove a directory tree, including directories without write or search permission
+pub(crate) fn remove_tree(path: &Path) -> std::io::Result<()> {
+    if fs::remove_dir_all(path).is_ok() {
+        return Ok(());
+    }
+    make_removable(path);
+    fs::remove_dir_all(path)
+}
+
+/// Give the owner full permissions on every directory of a tree
+fn make_removable(path: &Path) {
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir()) {
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o700));
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.flatten() {
+                make_removable(&entry.path());
+            }
+        }
+    }
+}
+
+fn overlay_error(path: &Path, e: std::io::Error) -> McpError {
+    McpError::Sandbox(format!("Workspace overlay error at {}: {}", path.display(), e))
+}
@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{ExecutionRequest, SandboxConfig, WorkspaceMode};
+    use crate::overlay::WorkspaceOverlay;
+    use crate::runner::SandboxRunner;
+    use mcp_common::error::McpError;
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+
+    // Test for merging the upper layer into the workspace
+    #[test]
+    fn test_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("workspace");
+        fs::create_dir_all(workspace.join("src")).unwrap();
+        fs::create_dir_all(workspace.join("build/cache")).unwrap();
+        fs::write(workspace.join("src/main.rs"), "old").unwrap();
+        fs::write(workspace.join("src/lib.rs"), "lib").unwrap();
+        fs::write(workspace.join("build/cache/object"), "object").unwrap();
+        fs::write(workspace.join("outside"), "outside").unwrap();
+        std::os::unix::fs::symlink(workspace.join("outside"), workspace.join("link")).unwrap();
+
+        let overlay = WorkspaceOverlay::create_in(dir.path(), std::slice::from_ref(&workspace)).unwrap();
+        let layer = &overlay.layers()[0];
+        assert!(overlay.layer(&workspace).is_some());
+
+        // Changes the command would make through the overlay
+        let upper = &layer.upper;
+        fs::create_dir_all(upper.join("src")).unwrap();
+        fs::write(upper.join("src/main.rs"), "new").unwrap();
+        fs::create_dir_all(upper.join("docs")).unwrap();
+        fs::write(upper.join("docs/README"), "docs").unwrap();
+        fs::write(upper.join("link"), "replaced").unwrap();
+        let whiteout = CString::new(upper.join("build").as_os_str().as_bytes()).unwrap();
+        // SAFETY: the path is NUL-terminated
+        let whiteouts = unsafe { libc::mknod(whiteout.as_ptr(), libc::S_IFCHR | 0o600, 0) } == 0;
+
+        // Nothing is visible before the commit
+        assert_eq!(fs::read_to_string(workspace.join("src/main.rs")).unwrap(), "old");
+        assert!(!workspace.join("docs").exists());
+
+        let changed = overlay.commit().unwrap();
+        assert_eq!(fs::read_to_string(workspace.join("src/main.rs")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(workspace.join("src/lib.rs")).unwrap(), "lib");
+        assert_eq!(fs::read_to_string(workspace.join("docs/README")).unwrap(), "docs");
+        // A symbolic link is replaced instead of written through
+        assert_eq!(fs::read_to_string(workspace.join("link")).unwrap(), "replaced");
+        assert_eq!(fs::read_to_string(workspace.join("outside")).unwrap(), "outside");
+        if whiteouts {
+            assert!(!workspace.join("build").exists());
+            assert_eq!(changed, 6);
+        } else {
+            assert_eq!(changed, 5);
+        }
+
+        // The layers are removed with the overlay
+        let upper = layer.upper.clone();
+        drop(overlay);
+        assert!(!upper.exists());
+        assert!(workspace.exists());
+    }
+
+    // Test for rejecting paths that cannot be overlaid
+    #[test]
+    fn test_invalid_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        for path in [file, dir.path().join("missing")] {
+            match WorkspaceOverlay::create_in(dir.path(), std::slice::from_ref(&path)) {
+                Err(McpError::Sandbox(_)) => {}
+                other => panic!("unexpected result for {}: {:?}", path.display(), other),
+            }
+        }
+    }
+
+    // Test for refusing an ephemeral workspace without an overlay
+    #[tokio::test]
+    async fn test_unsupported_workspace_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+        let request = ExecutionRequest {
+            command: "touch".to_string(),
+            args: vec![marker.to_string_lossy().to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config: SandboxConfig {
+                enabled: false,
+                workspace_mode: WorkspaceMode::Ephemeral,
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+        match SandboxRunner::new().run(request).await {
+            Err(McpError::Sandbox(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(!marker.exists());
+    }
+}
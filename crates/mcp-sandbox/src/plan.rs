@@ -0,0 +1,204 @@
+//! Multi-step plans
+//!
+//! A plan is a DAG of commands: every step names the steps it depends on and runs only after
+//! all of them have succeeded (exited with 0). The steps of a plan run one at a time under one
+//! task, in dependency order with ties broken by their order in the plan, and share the
+//! workspace of the task, so that a step sees the files left by the steps before it (e.g. a
+//! build followed by its tests).
+//!
+//! The plan is validated before any step runs: step IDs must be unique and non-empty,
+//! dependencies must name steps of the plan, and cycles are rejected. A failed step does not
+//! stop the plan; the steps that depend on it, directly or not, are skipped and the others
+//! still run. Once the task is cancelled, the steps that have not started are skipped.
+
+use crate::models::{ExecutionResult, SandboxConfig};
+use mcp_common::error::{McpError, McpResult};
+use std::collections::{HashMap, HashSet};
+
+/// Step of a plan
+#[derive(Debug, Clone, Default)]
+pub struct PlanStep {
+    /// ID of the step, unique within the plan
+    pub id: String,
+    /// Command to execute
+    pub command: String,
+    /// Command arguments
+    pub args: Vec<String>,
+    /// Environment variables
+    pub env: HashMap<String, String>,
+    /// Working directory
+    pub cwd: Option<String>,
+    /// Timeout in seconds (0 for the default of the executor)
+    pub timeout: u32,
+    /// IDs of the steps that must succeed before this step runs
+    pub depends_on: Vec<String>,
+    /// Sandbox configuration of the step instead of the one of the plan (the workspace of the
+    /// task is still shared)
+    pub sandbox_config: Option<SandboxConfig>,
+}
+
+impl PlanStep {
+    /// Step running a command without dependencies
+    pub fn new(id: impl Into<String>, command: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            id: id.into(),
+            command: command.into(),
+            args,
+            ..Default::default()
+        }
+    }
+
+    /// Run the step after other steps have succeeded
+    pub fn with_depends_on(mut self, depends_on: &[&str]) -> Self {
+        self.depends_on = depends_on.iter().map(|id| id.to_string()).collect();
+        self
+    }
+}
+
+/// DAG of commands executed under one task
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionPlan {
+    pub steps: Vec<PlanStep>,
+}
+
+impl ExecutionPlan {
+    pub fn new(steps: Vec<PlanStep>) -> Self {
+        Self { steps }
+    }
+
+    /// Indices of the steps in the order they run
+    ///
+    /// Fails with an invalid request error if the plan is empty, a step ID is empty or used
+    /// twice, a dependency names no step of the plan, or the dependencies form a cycle.
+    pub fn execution_order(&self) -> McpResult<Vec<usize>> {
+        if self.steps.is_empty() {
+            return Err(McpError::InvalidRequest("The plan has no steps".to_string()));
+        }
+        let mut index = HashMap::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            if step.id.is_empty() {
+                return Err(McpError::InvalidRequest(format!("Step {} of the plan has no ID", i + 1)));
+            }
+            if index.insert(step.id.as_str(), i).is_some() {
+                return Err(McpError::InvalidRequest(format!("Duplicate plan step ID: '{}'", step.id)));
+            }
+        }
+        for step in &self.steps {
+            if let Some(unknown) = step.depends_on.iter().find(|id| !index.contains_key(id.as_str())) {
+                return Err(McpError::InvalidRequest(format!(
+                    "Step '{}' depends on the unknown step '{}'",
+                    step.id, unknown
+                )));
+            }
+        }
+
+        // Repeatedly take the first step in plan order whose dependencies have all been taken
+        let mut order = Vec::with_capacity(self.steps.len());
+        let mut done = HashSet::new();
+        while order.len() < self.steps.len() {
+            let next = self.steps.iter().enumerate().find(|(i, step)| {
+                !done.contains(i) && step.depends_on.iter().all(|id| done.contains(&index[id.as_str()]))
+            });
+            let Some((i, _)) = next else {
+                let cycle: Vec<&str> = self
+                    .steps
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| !done.contains(i))
+                    .map(|(_, step)| step.id.as_str())
+                    .collect();
+                return Err(McpError::InvalidRequest(format!(
+                    "The plan has a dependency cycle between the steps {}",
+                    cycle.join(", ")
+                )));
+            };
+            done.insert(i);
+            order.push(i);
+        }
+        Ok(order)
+    }
+}
+
+/// Outcome of a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepStatus {
+    /// The command exited with 0
+    Succeeded,
+    /// The command exited with another code or could not be executed
+    Failed,
+    /// The step did not run because a dependency did not succeed or the task was cancelled
+    Skipped,
+}
+
+/// Result of a step
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    pub step_id: String,
+    pub status: StepStatus,
+    /// Result of the command, if it ran to completion
+    pub result: Option<ExecutionResult>,
+    /// Why the command failed or the step was skipped
+    pub error: Option<String>,
+}
+
+impl StepResult {
+    pub(crate) fn skipped(step_id: &str, reason: String) -> Self {
+        Self {
+            step_id: step_id.to_string(),
+            status: StepStatus::Skipped,
+            result: None,
+            error: Some(reason),
+        }
+    }
+
+    pub(crate) fn from_execution(step_id: &str, result: McpResult<ExecutionResult>) -> Self {
+        let (status, result, error) = match result {
+            Ok(result) if result.exit_code == Some(0) => (StepStatus::Succeeded, Some(result), None),
+            Ok(result) => {
+                let error = match result.exit_code {
+                    Some(code) => format!("Command exited with code {}", code),
+                    None => "Command was terminated by a signal".to_string(),
+                };
+                (StepStatus::Failed, Some(result), Some(error))
+            }
+            Err(e) => (StepStatus::Failed, None, Some(e.to_string())),
+        };
+        Self {
+            step_id: step_id.to_string(),
+            status,
+            result,
+            error,
+        }
+    }
+}
+
+/// Results of the steps of a plan, in the order they ran
+#[derive(Debug, Clone, Default)]
+pub struct PlanResult {
+    pub steps: Vec<StepResult>,
+}
+
+impl PlanResult {
+    /// Whether every step succeeded
+    pub fn succeeded(&self) -> bool {
+        self.steps.iter().all(|step| step.status == StepStatus::Succeeded)
+    }
+
+    /// Result of a step
+    pub fn step(&self, step_id: &str) -> Option<&StepResult> {
+        self.steps.iter().find(|step| step.step_id == step_id)
+    }
+
+    /// Exit code of the plan: 0 if every step succeeded, otherwise the exit code of the first
+    /// failed step (-1 if it did not exit normally, or if steps were only skipped)
+    pub fn exit_code(&self) -> i32 {
+        if self.succeeded() {
+            return 0;
+        }
+        self.steps
+            .iter()
+            .find(|step| step.status == StepStatus::Failed)
+            .and_then(|step| step.result.as_ref()?.exit_code)
+            .unwrap_or(-1)
+    }
+}
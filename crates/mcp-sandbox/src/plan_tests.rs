@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::SandboxConfig;
+    use crate::plan::{ExecutionPlan, PlanStep, StepStatus};
+    use crate::runner::SandboxRunner;
+    use crate::staging::StagedFile;
+    use crate::workspace::TaskWorkspaces;
+    use mcp_common::error::McpError;
+
+    fn step(id: &str, depends_on: &[&str]) -> PlanStep {
+        PlanStep::new(id, "true", Vec::new()).with_depends_on(depends_on)
+    }
+
+    fn shell(id: &str, script: &str, depends_on: &[&str]) -> PlanStep {
+        PlanStep {
+            timeout: 10,
+            ..PlanStep::new(id, "sh", vec!["-c".to_string(), script.to_string()]).with_depends_on(depends_on)
+        }
+    }
+
+    // Test for ordering and validating the steps of a plan
+    #[test]
+    fn test_execution_order() {
+        let plan = ExecutionPlan::new(vec![
+            step("test", &["build"]),
+            step("lint", &[]),
+            step("build", &["fetch"]),
+            step("fetch", &[]),
+        ]);
+        assert_eq!(plan.execution_order().unwrap(), vec![1, 3, 2, 0]);
+
+        for steps in [
+            Vec::new(),
+            vec![step("", &[])],
+            vec![step("build", &[]), step("build", &[])],
+            vec![step("build", &["fetch"])],
+            vec![step("build", &["build"])],
+            vec![step("build", &["test"]), step("test", &["build"]), step("lint", &[])],
+        ] {
+            match ExecutionPlan::new(steps.clone()).execution_order() {
+                Err(McpError::InvalidRequest(_)) => {}
+                other => panic!("unexpected result for {:?}: {:?}", steps, other),
+            }
+        }
+    }
+
+    // Test for running the steps of a plan in a shared workspace
+    #[tokio::test]
+    async fn test_run_plan() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = SandboxRunner::new().with_task_workspaces(TaskWorkspaces::new(dir.path()));
+        let _task = runner.processes().register("task-1").unwrap();
+        // Without bubblewrap the steps run on the host and see the workspace at its host path
+        let workspace = dir.path().join("task-1/workspace").display().to_string();
+
+        let plan = ExecutionPlan::new(vec![
+            shell("test", &format!("cat {}/build.out", workspace), &["build"]),
+            shell("build", &format!("cat {0}/src.txt > {0}/build.out", workspace), &[]),
+            shell("lint", "echo lint failed >&2; exit 3", &[]),
+            shell("publish", "echo published", &["test", "lint"]),
+            shell("docs", "echo docs", &[]),
+        ]);
+        let input_files = vec![StagedFile::from_bytes("src.txt", "built\n")];
+        let result = runner
+            .run_plan(&plan, SandboxConfig::default(), input_files, Some("task-1"), None)
+            .await
+            .unwrap();
+
+        let order: Vec<&str> = result.steps.iter().map(|step| step.step_id.as_str()).collect();
+        assert_eq!(order, vec!["build", "test", "lint", "publish", "docs"]);
+        if which::which("bwrap").is_err() {
+            let test = result.step("test").unwrap();
+            assert_eq!(test.status, StepStatus::Succeeded);
+            assert_eq!(test.result.as_ref().unwrap().stdout, "built\n");
+        }
+        let lint = result.step("lint").unwrap();
+        assert_eq!((lint.status, lint.result.as_ref().unwrap().exit_code), (StepStatus::Failed, Some(3)));
+        // Steps that depend on a failed step are skipped, the others still run
+        let publish = result.step("publish").unwrap();
+        assert_eq!(publish.status, StepStatus::Skipped);
+        assert!(publish.result.is_none());
+        assert_eq!(result.step("docs").unwrap().status, StepStatus::Succeeded);
+        assert!(!result.succeeded());
+        assert_eq!(result.exit_code(), 3);
+
+        // Invalid plans run no step
+        let plan = ExecutionPlan::new(vec![shell("build", "touch started", &["missing"])]);
+        let result = runner.run_plan(&plan, SandboxConfig::default(), Vec::new(), Some("task-2"), None).await;
+        assert!(matches!(result, Err(McpError::InvalidRequest(_))));
+        assert!(!dir.path().join("task-2").exists());
+    }
+
+    // Test for skipping the remaining steps of a cancelled task
+    #[tokio::test]
+    async fn test_cancelled_plan() {
+        let runner = SandboxRunner::new();
+        let task = runner.processes().register("task-1").unwrap();
+        let cancel = async {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            runner.processes().cancel("task-1", std::time::Duration::from_secs(1)).await
+        };
+        let plan = ExecutionPlan::new(vec![shell("build", "sleep 5", &[]), shell("test", "echo test", &[])]);
+        let config = SandboxConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        // Cancellation waits until the task has finished, i.e. its guard is dropped
+        let run = async {
+            let result = runner.run_plan(&plan, config, Vec::new(), Some("task-1"), None).await;
+            drop(task);
+            result
+        };
+        let (result, cancelled) = tokio::join!(run, cancel);
+        assert!(cancelled.unwrap());
+
+        let result = result.unwrap();
+        assert_eq!(result.step("build").unwrap().status, StepStatus::Failed);
+        assert_eq!(result.step("test").unwrap().status, StepStatus::Skipped);
+        assert_eq!(result.exit_code(), -1);
+    }
+}
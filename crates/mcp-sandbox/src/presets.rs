@@ -0,0 +1,110 @@
+//! Language runtime presets
+//!
+//! A preset bundles the sandbox settings a common toolchain needs, so that clients can
+//! select it by name instead of listing mounts themselves:
+//!
+//! | Preset       | Read-only mounts                                   | Seccomp   |
+//! |--------------|----------------------------------------------------|-----------|
+//! | `python`     | `/usr/local/lib`, `/usr/local/bin`, `/usr/share/python3` | `basic`   |
+//! | `node`       | `/usr/local/lib/node_modules`, `/usr/local/bin`, `/usr/share/nodejs` | `network` |
+//! | `rust-build` | `/usr/local/cargo`, `/usr/local/rustup`, `/usr/include` | `basic`   |
+//!
+//! Mounts that do not exist on the host are left out, so a preset works whatever the
+//! toolchain was installed with. Caches and other files the toolchains write are moved to
+//! `/tmp` with environment variables, since the root of the sandbox is read-only.
+//!
+//! Presets only provide defaults for a request: environment variables and resource limits
+//! set by the client take precedence, the seccomp profile is only used when neither the
+//! executor nor the policy names one, and everything is still bounded by the policy.
+
+use crate::models::ResourceLimits;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Names of the presets
+pub const PRESET_NAMES: [&str; 3] = ["python", "node", "rust-build"];
+
+/// Sandbox settings of a toolchain
+#[derive(Debug, Clone)]
+pub struct SandboxPreset {
+    /// Name of the preset
+    pub name: &'static str,
+    /// Paths with read-only permission (only those that exist on the host)
+    pub ro_paths: Vec<PathBuf>,
+    /// Environment variables
+    pub env: HashMap<String, String>,
+    /// Name of the seccomp profile (see [`crate::seccomp`])
+    pub seccomp_profile: String,
+    /// Default resource limits
+    pub resource_limits: ResourceLimits,
+}
+
+impl SandboxPreset {
+    /// Preset of a name, `None` for unknown names
+    pub fn named(name: &str) -> Option<Self> {
+        let (name, ro_paths, env, seccomp_profile, resource_limits): (_, &[&str], &[(&str, &str)], _, _) = match name {
+            "python" => (
+                "python",
+                &["/usr/local/lib", "/usr/local/bin", "/usr/share/python3"],
+                &[
+                    ("PYTHONDONTWRITEBYTECODE", "1"),
+                    ("PYTHONUNBUFFERED", "1"),
+                    ("PIP_NO_CACHE_DIR", "1"),
+                ],
+                "basic",
+                ResourceLimits {
+                    memory_limit: Some(1 << 30),
+                    pids_limit: Some(64),
+                    ..Default::default()
+                },
+            ),
+            "node" => (
+                "node",
+                &["/usr/local/lib/node_modules", "/usr/local/bin", "/usr/share/nodejs"],
+                &[
+                    ("npm_config_cache", "/tmp/.npm"),
+                    ("npm_config_update_notifier", "false"),
+                ],
+                "network",
+                ResourceLimits {
+                    memory_limit: Some(2 << 30),
+                    pids_limit: Some(128),
+                    ..Default::default()
+                },
+            ),
+            "rust-build" => (
+                "rust-build",
+                &["/usr/local/cargo", "/usr/local/rustup", "/usr/include"],
+                &[
+                    ("CARGO_HOME", "/tmp/cargo"),
+                    ("RUSTUP_HOME", "/usr/local/rustup"),
+                    ("CARGO_INCREMENTAL", "0"),
+                    ("CARGO_TERM_COLOR", "never"),
+                ],
+                "basic",
+                ResourceLimits {
+                    cpu_limit: Some(2.0),
+                    memory_limit: Some(4 << 30),
+                    pids_limit: Some(256),
+                    ..Default::default()
+                },
+            ),
+            _ => return None,
+        };
+
+        Some(Self {
+            name,
+            ro_paths: ro_paths.iter().map(PathBuf::from).filter(|path| path.exists()).collect(),
+            env: env.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            seccomp_profile: seccomp_profile.to_string(),
+            resource_limits,
+        })
+    }
+
+    /// Add the environment variables of the preset that are not set yet
+    pub fn apply_env(&self, env: &mut HashMap<String, String>) {
+        for (name, value) in &self.env {
+            env.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+    }
+}
@@ -0,0 +1,35 @@
+#[cfg(test)]
+mod tests {
+    use crate::presets::{SandboxPreset, PRESET_NAMES};
+    use crate::seccomp::SeccompProfileManager;
+    use std::collections::HashMap;
+
+    // Test for looking up the presets by name
+    #[test]
+    fn test_named_presets() {
+        let dir = tempfile::tempdir().unwrap();
+        let profiles = SeccompProfileManager::new(dir.path().to_path_buf());
+        for name in PRESET_NAMES {
+            let preset = SandboxPreset::named(name).unwrap();
+            assert_eq!(preset.name, name);
+            assert!(profiles.filter(&preset.seccomp_profile).is_some(), "{}", preset.seccomp_profile);
+            assert!(preset.ro_paths.iter().all(|path| path.is_absolute() && path.exists()));
+            assert!(preset.resource_limits.memory_limit.is_some());
+        }
+        assert!(SandboxPreset::named("ruby").is_none());
+        assert!(SandboxPreset::named("").is_none());
+    }
+
+    // Test for adding the environment variables of a preset
+    #[test]
+    fn test_apply_env() {
+        let preset = SandboxPreset::named("python").unwrap();
+        let mut env = HashMap::from([("PYTHONUNBUFFERED".to_string(), "0".to_string())]);
+        preset.apply_env(&mut env);
+
+        // Variables set by the request are kept
+        assert_eq!(env["PYTHONUNBUFFERED"], "0");
+        assert_eq!(env["PYTHONDONTWRITEBYTECODE"], "1");
+        assert_eq!(env.len(), preset.env.len());
+    }
+}
@@ -0,0 +1,274 @@
+//! Tracking of running processes for cancellation
+//!
+//! Each command runs as the leader of its own process group, so that signalling the group
+//! reaches the whole process tree (e.g. bubblewrap and everything started in the sandbox).
+//! A task is registered before its command is started and stays registered until the task
+//! has finished; cancelling a task that has not started its command yet prevents the
+//! command from being started.
+//!
+//! The command of a task can be paused and resumed, e.g. to suspend an expensive task during
+//! incident response without losing its state. A command that runs in a task cgroup is
+//! frozen with the cgroup freezer (`cgroup.freeze`), which also holds processes that left the
+//! process group; otherwise its process group is stopped with `SIGSTOP` and continued with
+//! `SIGCONT`. The timeout of a command keeps running while it is paused; a paused command
+//! that is cancelled or times out is resumed first so that it can exit on `SIGTERM`.
+//!
+//! The resource usage of a command that runs in a task cgroup can be sampled while it runs
+//! (see [`ProcessTracker::usage_sample`]).
+
+use crate::models::ResourceSample;
+use crate::usage::sample_cgroup;
+use mcp_common::error::{McpError, McpResult};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+/// Registry of the processes of running tasks
+#[derive(Debug, Clone, Default)]
+pub struct ProcessTracker {
+    tasks: Arc<Mutex<HashMap<String, TrackedTask>>>,
+}
+
+#[derive(Debug)]
+struct TrackedTask {
+    /// Process group of the running command
+    pgid: Option<i32>,
+    /// Task cgroup of the running command, if it has one
+    cgroup: Option<PathBuf>,
+    /// Whether the running command is paused
+    paused: bool,
+    /// Number of times a command of the task was retried (see [`crate::retry`])
+    retries: u32,
+    /// Becomes true when cancellation has been requested
+    cancelled: watch::Sender<bool>,
+    /// Becomes true when the task has finished
+    finished: watch::Receiver<bool>,
+}
+
+impl ProcessTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a task; it is unregistered when the returned guard is dropped
+    pub fn register(&self, task_id: &str) -> McpResult<TaskGuard> {
+        let mut tasks = self.lock();
+        if tasks.contains_key(task_id) {
+            return Err(McpError::Internal(format!("Task {} is already registered", task_id)));
+        }
+        let (finished_tx, finished_rx) = watch::channel(false);
+        tasks.insert(
+            task_id.to_string(),
+            TrackedTask {
+                pgid: None,
+                cgroup: None,
+                paused: false,
+                retries: 0,
+                cancelled: watch::Sender::new(false),
+                finished: finished_rx,
+            },
+        );
+        Ok(TaskGuard {
+            tracker: self.clone(),
+            task_id: task_id.to_string(),
+            finished: finished_tx,
+        })
+    }
+
+    /// Whether a task is registered
+    pub fn is_registered(&self, task_id: &str) -> bool {
+        self.lock().contains_key(task_id)
+    }
+
+    /// Cancel a task and wait until it has finished
+    ///
+    /// The process group of a running command receives SIGTERM, and SIGKILL if the task
+    /// has not finished after `grace_period`. Returns false if the task is not registered.
+    pub async fn cancel(&self, task_id: &str, grace_period: Duration) -> McpResult<bool> {
+        let (pgid, mut finished) = {
+            let mut tasks = self.lock();
+            let Some(task) = tasks.get_mut(task_id) else {
+                return Ok(false);
+            };
+            task.cancelled.send_replace(true);
+            (task.pgid, task.finished.clone())
+        };
+
+        // Stopped processes would not handle SIGTERM
+        if let Err(e) = self.resume(task_id) {
+            warn!("Failed to resume task {} before cancelling it: {}", task_id, e);
+        }
+        if let Some(pgid) = pgid {
+            info!("Sending SIGTERM to process group {} of task {}", pgid, task_id);
+            signal_group(pgid, libc::SIGTERM)?;
+        }
+        if tokio::time::timeout(grace_period, finished.wait_for(|finished| *finished)).await.is_ok() {
+            return Ok(true);
+        }
+
+        // The command may have been started in the meantime
+        let pgid = self.lock().get(task_id).and_then(|task| task.pgid);
+        if let Some(pgid) = pgid {
+            warn!(
+                "Task {} did not exit within {:?} of SIGTERM, sending SIGKILL to process group {}",
+                task_id, grace_period, pgid
+            );
+            signal_group(pgid, libc::SIGKILL)?;
+        }
+        // An error means the guard is gone, i.e. the task has finished as well
+        let _ = finished.wait_for(|finished| *finished).await;
+        Ok(true)
+    }
+
+    /// Pause the running command of a task
+    ///
+    /// Returns false if the task is not registered. Fails if the task has no running command.
+    pub fn pause(&self, task_id: &str) -> McpResult<bool> {
+        self.set_paused(task_id, true)
+    }
+
+    /// Resume the paused command of a task
+    ///
+    /// Returns false if the task is not registered; resuming a command that is not paused
+    /// does nothing.
+    pub fn resume(&self, task_id: &str) -> McpResult<bool> {
+        self.set_paused(task_id, false)
+    }
+
+    /// Whether the command of a task is paused
+    pub fn is_paused(&self, task_id: &str) -> bool {
+        self.lock().get(task_id).is_some_and(|task| task.paused)
+    }
+
+    fn set_paused(&self, task_id: &str, paused: bool) -> McpResult<bool> {
+        let mut tasks = self.lock();
+        let Some(task) = tasks.get_mut(task_id) else {
+            return Ok(false);
+        };
+        if task.paused == paused {
+            return Ok(true);
+        }
+        let Some(pgid) = task.pgid else {
+            return Err(McpError::InvalidRequest(format!("Task {} has no running command to pause", task_id)));
+        };
+        match &task.cgroup {
+            Some(cgroup) => freeze_cgroup(cgroup, paused)?,
+            None => signal_group(pgid, if paused { libc::SIGSTOP } else { libc::SIGCONT })?,
+        }
+        task.paused = paused;
+        info!("{} task {}", if paused { "Paused" } else { "Resumed" }, task_id);
+        Ok(true)
+    }
+
+    /// Number of times a command of a task was retried after a transient failure
+    ///
+    /// Always 0 for tasks that are not registered.
+    pub fn retries(&self, task_id: &str) -> u32 {
+        self.lock().get(task_id).map(|task| task.retries).unwrap_or_default()
+    }
+
+    /// Count a retry of a command of a task
+    pub(crate) fn record_retry(&self, task_id: &str) {
+        if let Some(task) = self.lock().get_mut(task_id) {
+            task.retries += 1;
+        }
+    }
+
+    /// Current resource usage of the running command of a task
+    ///
+    /// Returns `None` if the task is not registered, has no running command, or its command
+    /// does not run in a task cgroup.
+    pub fn usage_sample(&self, task_id: &str) -> Option<ResourceSample> {
+        let cgroup = self.lock().get(task_id)?.cgroup.clone()?;
+        sample_cgroup(&cgroup)
+    }
+
+    /// Whether cancellation of a task has been requested
+    pub fn is_cancelled(&self, task_id: &str) -> bool {
+        self.lock().get(task_id).is_some_and(|task| *task.cancelled.borrow())
+    }
+
+    /// Receiver that becomes true when cancellation of a registered task is requested
+    pub(crate) fn cancellation(&self, task_id: &str) -> Option<watch::Receiver<bool>> {
+        self.lock().get(task_id).map(|task| task.cancelled.subscribe())
+    }
+
+    /// Record the process group and the task cgroup of the command of a task
+    ///
+    /// Returns false if the task has been cancelled before the command was started; the
+    /// caller must then kill the command.
+    pub(crate) fn set_process_group(&self, task_id: &str, pgid: i32, cgroup: Option<&Path>) -> bool {
+        match self.lock().get_mut(task_id) {
+            Some(task) => {
+                task.pgid = Some(pgid);
+                task.cgroup = cgroup.map(Path::to_path_buf);
+                task.paused = false;
+                !*task.cancelled.borrow()
+            }
+            None => true,
+        }
+    }
+
+    /// Forget the process group of a task whose command has exited
+    pub(crate) fn clear_process_group(&self, task_id: &str) {
+        if let Some(task) = self.lock().get_mut(task_id) {
+            task.pgid = None;
+            task.cgroup = None;
+            task.paused = false;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, TrackedTask>> {
+        self.tasks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Registration of a running task
+#[derive(Debug)]
+pub struct TaskGuard {
+    tracker: ProcessTracker,
+    task_id: String,
+    finished: watch::Sender<bool>,
+}
+
+impl TaskGuard {
+    /// ID the task is registered with
+    pub fn task_id(&self) -> &str {
+        &self.task_id
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.tracker.lock().remove(&self.task_id);
+        let _ = self.finished.send(true);
+    }
+}
+
+/// Freeze or thaw the processes of a cgroup v2 directory
+fn freeze_cgroup(cgroup: &Path, frozen: bool) -> McpResult<()> {
+    fs::write(cgroup.join("cgroup.freeze"), if frozen { "1" } else { "0" }).map_err(|e| {
+        let action = if frozen { "freeze" } else { "thaw" };
+        McpError::Sandbox(format!("Failed to {} cgroup {}: {}", action, cgroup.display(), e))
+    })
+}
+
+/// Send a signal to a process group
+pub(crate) fn signal_group(pgid: i32, signal: libc::c_int) -> McpResult<()> {
+    // SAFETY: kill has no memory safety requirements
+    if unsafe { libc::kill(-pgid, signal) } == 0 {
+        return Ok(());
+    }
+    let e = std::io::Error::last_os_error();
+    if e.raw_os_error() == Some(libc::ESRCH) {
+        // Every process of the group has already exited
+        debug!("Process group {} no longer exists", pgid);
+        return Ok(());
+    }
+    Err(McpError::Sandbox(format!("Failed to signal process group {}: {}", pgid, e)))
+}
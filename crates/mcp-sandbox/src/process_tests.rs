@@ -0,0 +1,201 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{ExecutionRequest, SandboxConfig};
+    use crate::runner::{SandboxRunner, PARTIAL_OUTPUT_BYTES};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    fn shell_request(script: &str) -> ExecutionRequest {
+        ExecutionRequest {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 30,
+            sandbox_config: SandboxConfig {
+                enabled: false,
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        }
+    }
+
+    /// Wait until the command of a task has been started
+    async fn wait_for_start(marker: &std::path::Path) {
+        while !marker.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    // Test for terminating the process tree of a task with SIGTERM
+    #[tokio::test]
+    async fn test_cancel_running_task() {
+        let runner = Arc::new(SandboxRunner::new());
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("started");
+        let guard = runner.processes().register("task-1").unwrap();
+        assert!(runner.processes().register("task-1").is_err());
+
+        let task = {
+            let runner = runner.clone();
+            let script = format!("sleep 30 & touch {}; wait", marker.display());
+            tokio::spawn(async move {
+                let result = runner.run_task(shell_request(&script), Some("task-1"), None).await;
+                drop(guard);
+                result
+            })
+        };
+        wait_for_start(&marker).await;
+
+        let start = Instant::now();
+        assert!(runner.processes().cancel("task-1", Duration::from_secs(10)).await.unwrap());
+        // The background sleep is terminated as well, so the pipes are closed right away
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(!runner.processes().is_registered("task-1"));
+        let err = task.await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("cancelled"), "{}", err);
+
+        // Tasks that are not registered are not cancelled
+        assert!(!runner.processes().cancel("task-1", Duration::from_secs(1)).await.unwrap());
+    }
+
+    // Test for killing a task that ignores SIGTERM after the grace period
+    #[tokio::test]
+    async fn test_cancel_kills_after_grace_period() {
+        let runner = Arc::new(SandboxRunner::new());
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("started");
+        let guard = runner.processes().register("task-2").unwrap();
+
+        let task = {
+            let runner = runner.clone();
+            let script = format!("trap '' TERM; touch {}; sleep 30", marker.display());
+            tokio::spawn(async move {
+                let result = runner.run_task(shell_request(&script), Some("task-2"), None).await;
+                drop(guard);
+                result
+            })
+        };
+        wait_for_start(&marker).await;
+
+        let start = Instant::now();
+        assert!(runner.processes().cancel("task-2", Duration::from_millis(300)).await.unwrap());
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(300) && elapsed < Duration::from_secs(10), "{:?}", elapsed);
+        assert!(task.await.unwrap().is_err());
+    }
+
+    // Test for cancelling a task before its command is started
+    #[tokio::test]
+    async fn test_cancel_before_start() {
+        let runner = Arc::new(SandboxRunner::new());
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("started");
+        let guard = runner.processes().register("task-3").unwrap();
+
+        let cancel = {
+            let runner = runner.clone();
+            tokio::spawn(async move { runner.processes().cancel("task-3", Duration::from_secs(10)).await })
+        };
+        while !runner.processes().is_cancelled("task-3") {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let script = format!("touch {}", marker.display());
+        let err = runner.run_task(shell_request(&script), Some("task-3"), None).await.unwrap_err();
+        assert!(err.to_string().contains("cancelled"), "{}", err);
+        assert!(!marker.exists());
+
+        // Cancellation completes once the task has finished
+        drop(guard);
+        assert!(cancel.await.unwrap().unwrap());
+    }
+
+    // Test for terminating a timed-out command with SIGTERM and reporting its partial output
+    #[tokio::test]
+    async fn test_timeout_terminates_gracefully() {
+        let runner = SandboxRunner::new();
+        let script = "trap 'echo cleaning up; exit 143' TERM; echo started; while :; do sleep 0.1; done";
+        let mut request = shell_request(script);
+        request.timeout = 1;
+
+        let start = Instant::now();
+        let err = runner.run(request).await.unwrap_err();
+        // The command exits on SIGTERM well before the grace period
+        assert!(start.elapsed() < Duration::from_secs(4), "{:?}", start.elapsed());
+        let message = err.to_string();
+        assert!(message.contains("timed out: 1 seconds"), "{}", message);
+        assert!(message.contains("seconds\n--- partial stdout ---\nstarted\ncleaning up\n"), "{}", message);
+    }
+
+    // Test for killing a timed-out command that ignores SIGTERM after the grace period
+    #[tokio::test]
+    async fn test_timeout_kills_after_grace_period() {
+        let runner = SandboxRunner::new().with_termination_grace_period(Duration::from_millis(300));
+        let output = format!("head -c {} /dev/zero | tr '\\0' x >&2", PARTIAL_OUTPUT_BYTES + 10);
+        let script = format!("trap '' TERM; {}; sleep 30", output);
+        let mut request = shell_request(&script);
+        request.timeout = 1;
+
+        let start = Instant::now();
+        let err = runner.run(request).await.unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5), "{:?}", start.elapsed());
+        // Only the end of the output is included
+        let message = err.to_string();
+        assert!(message.contains("--- partial stderr ---\n[10 earlier bytes omitted]\n"), "{}", message);
+        assert!(message.ends_with(&"x".repeat(PARTIAL_OUTPUT_BYTES)), "{}", message);
+    }
+
+    // Test for pausing and resuming the command of a task
+    #[tokio::test]
+    async fn test_pause_and_resume() {
+        let runner = Arc::new(SandboxRunner::new());
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("started");
+        let count = dir.path().join("count");
+        let guard = runner.processes().register("task-4").unwrap();
+        // No command to pause yet
+        assert!(runner.processes().pause("task-4").is_err());
+        assert!(!runner.processes().pause("task-5").unwrap());
+
+        let task = {
+            let runner = runner.clone();
+            let script = format!(
+                "touch {}; i=0; while :; do i=$((i+1)); echo $i > {}; sleep 0.05; done",
+                marker.display(),
+                count.display()
+            );
+            tokio::spawn(async move {
+                let result = runner.run_task(shell_request(&script), Some("task-4"), None).await;
+                drop(guard);
+                result
+            })
+        };
+        wait_for_start(&marker).await;
+        let read_count = || std::fs::read_to_string(&count).unwrap_or_default();
+
+        assert!(runner.processes().pause("task-4").unwrap());
+        assert!(runner.processes().is_paused("task-4"));
+        let paused_at = read_count();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(read_count(), paused_at);
+
+        assert!(runner.processes().resume("task-4").unwrap());
+        assert!(!runner.processes().is_paused("task-4"));
+        let resumed = Instant::now();
+        while read_count() == paused_at {
+            assert!(resumed.elapsed() < Duration::from_secs(5));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // A paused task is resumed to be cancelled with SIGTERM
+        assert!(runner.processes().pause("task-4").unwrap());
+        let start = Instant::now();
+        assert!(runner.processes().cancel("task-4", Duration::from_secs(10)).await.unwrap());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        let err = task.await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("cancelled"), "{}", err);
+    }
+}
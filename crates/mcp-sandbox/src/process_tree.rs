@@ -0,0 +1,232 @@
+//! Reporting of the process tree of a command
+//!
+//! While a command runs, `/proc` is sampled for the processes it spawns: a process whose
+//! parent is the command or one of the processes already seen belongs to the tree, also after
+//! it has been reparented. Each process is recorded with its command line when it is first
+//! seen, and with its exit status if it is seen as a zombie. The exit status of the command
+//! itself is always known.
+//!
+//! Sampling has limits: processes that start and are reaped between two samples are missed,
+//! and the exit status of most processes is only seen when their parent is slow to reap them.
+//! For bubblewrap the command is `bwrap`, which spawns the sandboxed processes; for
+//! containers only the runtime client is a descendant of the gateway, and the processes of
+//! microVMs are not visible at all.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// Interval between two samples of `/proc`
+pub const PROCESS_SAMPLE_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Maximum number of processes recorded per command
+pub const MAX_RECORDED_PROCESSES: usize = 1024;
+
+/// Process spawned during an execution
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessRecord {
+    /// Process ID (in the PID namespace of the gateway)
+    pub pid: u32,
+    /// Parent process ID at the time the process was first seen (`None` for the command)
+    pub parent_pid: Option<u32>,
+    /// Executable name (`comm`)
+    pub name: String,
+    /// Command line (empty if the process had already exited when it was first seen)
+    pub command_line: Vec<String>,
+    /// Exit code, if the process was seen exiting normally
+    pub exit_code: Option<i32>,
+    /// Terminating signal, if the process was seen being killed by a signal
+    pub signal: Option<i32>,
+}
+
+/// Recorder of the process tree of a running command
+#[derive(Debug)]
+pub struct ProcessTreeRecorder {
+    tree: Arc<Mutex<ProcessTree>>,
+    sampler: JoinHandle<()>,
+}
+
+impl ProcessTreeRecorder {
+    /// Start sampling the descendants of a spawned command
+    pub fn start(pid: u32, command_line: Vec<String>) -> Self {
+        let tree = Arc::new(Mutex::new(ProcessTree::new(pid, command_line)));
+        let sampler = tokio::spawn({
+            let tree = tree.clone();
+            async move {
+                loop {
+                    let tree = tree.clone();
+                    if tokio::task::spawn_blocking(move || lock(&tree).sample(Path::new("/proc"))).await.is_err() {
+                        return;
+                    }
+                    tokio::time::sleep(PROCESS_SAMPLE_INTERVAL).await;
+                }
+            }
+        });
+        Self { tree, sampler }
+    }
+
+    /// Stop sampling and return the processes, the command first
+    pub fn finish(self, status: Option<ExitStatus>) -> Vec<ProcessRecord> {
+        self.sampler.abort();
+        let mut tree = lock(&self.tree);
+        tree.sample(Path::new("/proc"));
+        if let (Some(status), Some(command)) = (status, tree.processes.first_mut()) {
+            command.exit_code = status.code();
+            command.signal = status.signal();
+        }
+        std::mem::take(&mut tree.processes)
+    }
+}
+
+impl Drop for ProcessTreeRecorder {
+    fn drop(&mut self) {
+        self.sampler.abort();
+    }
+}
+
+fn lock(tree: &Mutex<ProcessTree>) -> std::sync::MutexGuard<'_, ProcessTree> {
+    tree.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Processes of a tree seen so far
+#[derive(Debug)]
+pub(crate) struct ProcessTree {
+    pub(crate) processes: Vec<ProcessRecord>,
+    /// Index into `processes` and start time of the processes by PID
+    live: HashMap<u32, (usize, Option<u64>)>,
+    /// Whether processes were left out beyond [`MAX_RECORDED_PROCESSES`]
+    truncated: bool,
+}
+
+impl ProcessTree {
+    pub(crate) fn new(pid: u32, command_line: Vec<String>) -> Self {
+        let name = command_line
+            .first()
+            .and_then(|program| Path::new(program).file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Self {
+            processes: vec![ProcessRecord {
+                pid,
+                parent_pid: None,
+                name,
+                command_line,
+                ..Default::default()
+            }],
+            // The start time of the command is taken from the first sample
+            live: HashMap::from([(pid, (0, None))]),
+            truncated: false,
+        }
+    }
+
+    /// Record the processes of the tree found in a procfs
+    pub(crate) fn sample(&mut self, proc_dir: &Path) {
+        let Ok(entries) = fs::read_dir(proc_dir) else {
+            return;
+        };
+        let mut stats: Vec<(u32, ProcStat)> = entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+            .filter_map(|pid| Some((pid, ProcStat::read(&proc_dir.join(pid.to_string()))?)))
+            .collect();
+        // Parents start before their children
+        stats.sort_by_key(|(_, stat)| stat.start_time);
+
+        for (pid, stat) in stats {
+            match self.live.get_mut(&pid) {
+                Some((index, start_time)) if start_time.is_none_or(|start_time| start_time == stat.start_time) => {
+                    *start_time = Some(stat.start_time);
+                    if let Some(status) = stat.exit_status {
+                        let process = &mut self.processes[*index];
+                        (process.exit_code, process.signal) = decode_wait_status(status);
+                    }
+                }
+                _ => self.add(pid, stat, proc_dir),
+            }
+        }
+    }
+
+    /// Record a new process if its parent belongs to the tree
+    fn add(&mut self, pid: u32, stat: ProcStat, proc_dir: &Path) {
+        // A PID reused by another process
+        self.live.remove(&pid);
+        let parent_started_before = |(_, start_time): &(usize, Option<u64>)| {
+            start_time.is_some_and(|start_time| start_time <= stat.start_time)
+        };
+        if !self.live.get(&stat.parent_pid).is_some_and(parent_started_before) {
+            return;
+        }
+        if self.processes.len() >= MAX_RECORDED_PROCESSES {
+            if !self.truncated {
+                warn!("Process tree exceeds {} processes, not recording more", MAX_RECORDED_PROCESSES);
+                self.truncated = true;
+            }
+            return;
+        }
+
+        let command_line = fs::read(proc_dir.join(pid.to_string()).join("cmdline"))
+            .map(|cmdline| {
+                cmdline
+                    .split(|byte| *byte == 0)
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| String::from_utf8_lossy(arg).to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let (exit_code, signal) = stat.exit_status.map(decode_wait_status).unwrap_or_default();
+        self.live.insert(pid, (self.processes.len(), Some(stat.start_time)));
+        self.processes.push(ProcessRecord {
+            pid,
+            parent_pid: Some(stat.parent_pid),
+            name: stat.name,
+            command_line,
+            exit_code,
+            signal,
+        });
+    }
+}
+
+/// Exit code and terminating signal of a wait status
+fn decode_wait_status(status: i32) -> (Option<i32>, Option<i32>) {
+    let status = ExitStatus::from_raw(status);
+    (status.code(), status.signal())
+}
+
+/// Fields of `/proc/<pid>/stat`
+#[derive(Debug)]
+struct ProcStat {
+    name: String,
+    parent_pid: u32,
+    /// Start time after boot (clock ticks)
+    start_time: u64,
+    /// Wait status of a zombie
+    exit_status: Option<i32>,
+}
+
+impl ProcStat {
+    fn read(dir: &Path) -> Option<Self> {
+        let stat = fs::read_to_string(dir.join("stat")).ok()?;
+        // The name may contain spaces and parentheses
+        let (head, rest) = stat.rsplit_once(')')?;
+        let name = head.split_once('(')?.1.to_string();
+        // Fields from the state (field 3) on
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+        let state = *fields.first()?;
+        Some(Self {
+            name,
+            parent_pid: fields.get(1)?.parse().ok()?,
+            start_time: fields.get(19)?.parse().ok()?,
+            exit_status: match state {
+                "Z" => fields.get(49).and_then(|status| status.parse().ok()),
+                _ => None,
+            },
+        })
+    }
+}
@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{ExecutionRequest, SandboxConfig};
+    use crate::process_tree::{ProcessRecord, ProcessTree};
+    use crate::runner::SandboxRunner;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+
+    /// Write `/proc/<pid>/stat` and `cmdline` of a fake process
+    fn write_process(
+        proc_dir: &Path,
+        pid: u32,
+        name: &str,
+        parent_pid: u32,
+        start_time: u64,
+        exit_status: Option<i32>,
+    ) {
+        let dir = proc_dir.join(pid.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        let state = if exit_status.is_some() { "Z" } else { "S" };
+        // Fields 3 to 52, with the parent at 4, the start time at 22 and the exit status at 52
+        let mut fields = vec!["0".to_string(); 50];
+        fields[0] = state.to_string();
+        fields[1] = parent_pid.to_string();
+        fields[19] = start_time.to_string();
+        fields[49] = exit_status.unwrap_or(0).to_string();
+        fs::write(dir.join("stat"), format!("{} ({}) {}\n", pid, name, fields.join(" "))).unwrap();
+        let cmdline = if exit_status.is_some() { String::new() } else { format!("{}\0--flag\0", name) };
+        fs::write(dir.join("cmdline"), cmdline).unwrap();
+    }
+
+    // Test for finding the descendants of a command in a procfs
+    #[test]
+    fn test_process_tree_sampling() {
+        let proc_dir = tempfile::tempdir().unwrap();
+        let proc_dir = proc_dir.path();
+        write_process(proc_dir, 100, "npm", 1, 50, None);
+        write_process(proc_dir, 101, "node-gyp", 100, 60, None);
+        write_process(proc_dir, 102, "unrelated", 1, 55, None);
+        write_process(proc_dir, 103, "curl (x)", 101, 70, None);
+        write_process(proc_dir, 104, "sibling", 102, 80, None);
+
+        let mut tree = ProcessTree::new(100, vec!["/usr/bin/npm".to_string(), "install".to_string()]);
+        tree.sample(proc_dir);
+        let pids: Vec<u32> = tree.processes.iter().map(|process| process.pid).collect();
+        assert_eq!(pids, vec![100, 101, 103]);
+        assert_eq!(tree.processes[0].name, "npm");
+        assert_eq!(tree.processes[0].command_line, vec!["/usr/bin/npm", "install"]);
+        assert_eq!(
+            tree.processes[2],
+            ProcessRecord {
+                pid: 103,
+                parent_pid: Some(101),
+                name: "curl (x)".to_string(),
+                command_line: vec!["curl (x)".to_string(), "--flag".to_string()],
+                exit_code: None,
+                signal: None,
+            }
+        );
+
+        // Exits seen as zombies, a process reparented after its parent has exited, and a PID
+        // reused by another process
+        write_process(proc_dir, 101, "node-gyp", 100, 60, Some(1 << 8));
+        write_process(proc_dir, 103, "curl (x)", 1, 70, Some(libc::SIGKILL));
+        write_process(proc_dir, 105, "sh", 103, 90, None);
+        fs::remove_dir_all(proc_dir.join("100")).unwrap();
+        write_process(proc_dir, 100, "reused", 1, 95, None);
+        tree.sample(proc_dir);
+        let pids: Vec<u32> = tree.processes.iter().map(|process| process.pid).collect();
+        assert_eq!(pids, vec![100, 101, 103, 105]);
+        assert_eq!(tree.processes[0].name, "npm");
+        assert_eq!((tree.processes[1].exit_code, tree.processes[1].signal), (Some(1), None));
+        assert_eq!((tree.processes[2].exit_code, tree.processes[2].signal), (None, Some(libc::SIGKILL)));
+
+        // Children of the process that reused the PID of the command are not part of the tree
+        write_process(proc_dir, 106, "other", 100, 99, None);
+        tree.sample(proc_dir);
+        assert_eq!(tree.processes.len(), 4);
+    }
+
+    // Test for the process tree of an executed command
+    #[tokio::test]
+    async fn test_execution_process_tree() {
+        let request = ExecutionRequest {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "sleep 0.3; exit 3".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config: SandboxConfig {
+                enabled: false,
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+        let result = SandboxRunner::new().run(request).await.unwrap();
+        assert_eq!(result.exit_code, Some(3));
+
+        let command = &result.process_tree[0];
+        assert_eq!(command.name, "sh");
+        assert_eq!(command.parent_pid, None);
+        assert_eq!(command.exit_code, Some(3));
+        let sleep = result.process_tree.iter().find(|process| process.name == "sleep").unwrap();
+        assert_eq!(sleep.parent_pid, Some(command.pid));
+        assert_eq!(sleep.command_line, vec!["sleep", "0.3"]);
+    }
+}
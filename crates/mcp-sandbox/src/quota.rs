@@ -0,0 +1,95 @@
+//! Disk quotas for sandbox writes
+//!
+//! The `disk_limit` of the resource limits bounds how much a command may write, so that a
+//! runaway command cannot fill the host disk:
+//!
+//! * the tmpfs mounts of the sandbox (the denied paths) are limited to that size
+//! * `RLIMIT_FSIZE` bounds every single file to just over the limit (the command gets `SIGXFSZ`/`EFBIG`)
+//! * the disk usage of the writable host directories (the read-write paths, or the upper
+//!   layers of an overlay) is polled while the command runs, and the process group of the
+//!   command is killed once they have grown by more than the limit
+//!
+//! Commands in a microVM never write to host directories, so the limit does not apply to them.
+//!
+//! Only growth counts: data that was in the read-write paths before the command started is
+//! not charged to it. A command that exceeded its quota fails with a sandbox error whose
+//! code is [`SANDBOX_DISK_QUOTA_EXCEEDED`](mcp_common::error::error_code::SANDBOX_DISK_QUOTA_EXCEEDED).
+
+use mcp_common::error::McpError;
+use std::collections::HashSet;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Interval between two measurements of the disk usage of a running command
+pub const QUOTA_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Disk quota of one command
+#[derive(Debug, Clone)]
+pub struct DiskQuota {
+    limit: u64,
+    paths: Vec<PathBuf>,
+    baseline: u64,
+}
+
+impl DiskQuota {
+    /// Quota of `limit` bytes on the given host directories, measured from their current usage
+    pub fn new(limit: u64, paths: Vec<PathBuf>) -> Self {
+        let baseline = disk_usage(&paths);
+        Self { limit, paths, baseline }
+    }
+
+    /// Limit in bytes
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Bytes written since the quota was created
+    pub fn usage(&self) -> u64 {
+        disk_usage(&self.paths).saturating_sub(self.baseline)
+    }
+
+    /// Whether the writes exceed the limit
+    pub fn is_exceeded(&self) -> bool {
+        self.usage() > self.limit
+    }
+
+    /// Error of a command that exceeded the quota
+    pub fn exceeded_error(&self) -> McpError {
+        McpError::Sandbox(format!("Disk quota of {} bytes exceeded", self.limit))
+    }
+}
+
+/// Allocated bytes of the files below some paths
+///
+/// Symbolic links are not followed, hard links are counted once and other filesystems
+/// mounted below the paths are left out.
+pub(crate) fn disk_usage(paths: &[PathBuf]) -> u64 {
+    let mut seen = HashSet::new();
+    let mut total = 0;
+    for path in paths {
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            total += usage_of(path, &metadata, metadata.dev(), &mut seen);
+        }
+    }
+    total
+}
+
+fn usage_of(path: &Path, metadata: &std::fs::Metadata, dev: u64, seen: &mut HashSet<(u64, u64)>) -> u64 {
+    if metadata.dev() != dev || !seen.insert((metadata.dev(), metadata.ino())) {
+        return 0;
+    }
+    // st_blocks is in 512-byte units
+    let mut total = metadata.blocks() * 512;
+    if metadata.is_dir() {
+        if let Ok(entries) = std::fs::read_dir(path) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+                    total += usage_of(&path, &metadata, dev, seen);
+                }
+            }
+        }
+    }
+    total
+}
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use crate::container::{ContainerRunner, ContainerRuntime};
+    use crate::models::{ExecutionRequest, ResourceLimits, SandboxConfig};
+    use crate::quota::DiskQuota;
+    use crate::runner::SandboxRunner;
+    use mcp_common::error::{error_code, McpError};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    // Test for measuring the writes against the quota
+    #[test]
+    fn test_disk_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        // Existing data is not charged
+        fs::write(dir.path().join("existing"), vec![1u8; 256 * 1024]).unwrap();
+        let quota = DiskQuota::new(128 * 1024, vec![dir.path().to_path_buf()]);
+        assert_eq!(quota.limit(), 128 * 1024);
+        assert!(!quota.is_exceeded());
+
+        fs::write(dir.path().join("small"), vec![1u8; 16 * 1024]).unwrap();
+        assert!(quota.usage() >= 16 * 1024);
+        assert!(!quota.is_exceeded());
+
+        // Hard links are counted once
+        fs::hard_link(dir.path().join("small"), dir.path().join("link")).unwrap();
+        assert!(!quota.is_exceeded());
+
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/large"), vec![1u8; 256 * 1024]).unwrap();
+        assert!(quota.is_exceeded());
+
+        let error = quota.exceeded_error();
+        assert!(matches!(error, McpError::Sandbox(_)));
+        assert_eq!(error.code(), error_code::SANDBOX_DISK_QUOTA_EXCEEDED);
+    }
+
+    // Test for limiting the tmpfs mounts and file sizes of containers
+    #[test]
+    fn test_container_disk_limit() {
+        let config = SandboxConfig {
+            denied_paths: vec![PathBuf::from("/etc")],
+            resource_limits: ResourceLimits {
+                disk_limit: Some(1048576),
+                cpu_time_limit: Some(60),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let runner = ContainerRunner::new(ContainerRuntime::Docker, "docker", "example.com/sandbox:1");
+        let cmd = runner.build_command("mcp-test", &config, "ls", &[], &HashMap::new(), None).unwrap();
+        let args: Vec<String> = cmd.as_std().get_args().map(|arg| arg.to_string_lossy().to_string()).collect();
+
+        assert!(args.windows(2).any(|window| window == ["--mount", "type=tmpfs,target=/etc,tmpfs-size=1048576"]));
+        assert!(args.windows(2).any(|window| window == ["--ulimit", "fsize=1048577:1048577"]));
+        assert!(args.windows(2).any(|window| window == ["--ulimit", "cpu=60:61"]));
+    }
+
+    // Test for killing a command that writes beyond its quota
+    #[tokio::test]
+    async fn test_run_exceeding_disk_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        let request = ExecutionRequest {
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                format!("head -c 4000000 /dev/zero > {}/file; sleep 10", dir.path().display()),
+            ],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 30,
+            sandbox_config: SandboxConfig {
+                enabled: false,
+                rw_paths: vec![dir.path().to_path_buf()],
+                resource_limits: ResourceLimits {
+                    disk_limit: Some(1048576),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+
+        let started = Instant::now();
+        match SandboxRunner::new().run(request).await {
+            Err(error @ McpError::Sandbox(_)) => {
+                assert_eq!(error.code(), error_code::SANDBOX_DISK_QUOTA_EXCEEDED);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_secs(10));
+        // The file size limit stopped the write
+        assert!(fs::metadata(dir.path().join("file")).unwrap().len() <= 1048577);
+    }
+}
@@ -0,0 +1,88 @@
+//! Retries of transient execution failures
+//!
+//! A command whose execution fails with a temporary error ([`McpError::Temporary`], e.g. a
+//! process that cannot be forked for lack of resources or a full session pool) is executed
+//! again after a backoff, up to the maximum number of attempts of the [`RetryPolicy`]. Temporary
+//! errors are raised before the command starts, so a retry never runs a command twice; other
+//! errors and non-zero exit codes are not retried.
+//!
+//! The backoff doubles after every attempt up to the maximum backoff. A cancelled task is not
+//! retried, and the concurrency permit of the command is released while it backs off. The
+//! number of retries of a task can be read while the task is registered (see
+//! [`crate::CommandExecutor::task_retries`]).
+
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::parse_positive;
+use std::time::Duration;
+
+/// Retry policy of the commands of an executor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one (1 disables retries)
+    pub max_attempts: u32,
+    /// Backoff before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound of the backoff
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that executes every command once
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Build the policy from environment variables
+    ///
+    /// * `MCP_SANDBOX_RETRY_MAX_ATTEMPTS` - maximum number of attempts (1 disables retries)
+    /// * `MCP_SANDBOX_RETRY_BACKOFF_MS` - backoff before the first retry
+    /// * `MCP_SANDBOX_RETRY_MAX_BACKOFF_MS` - upper bound of the backoff
+    pub fn from_env() -> McpResult<Self> {
+        let default = Self::default();
+        let max_attempts = match value_from_env("MCP_SANDBOX_RETRY_MAX_ATTEMPTS")? {
+            Some(max_attempts) => u32::try_from(max_attempts).map_err(|_| {
+                McpError::InvalidRequest(format!("MCP_SANDBOX_RETRY_MAX_ATTEMPTS is too large: {}", max_attempts))
+            })?,
+            None => default.max_attempts,
+        };
+        let initial_backoff = value_from_env("MCP_SANDBOX_RETRY_BACKOFF_MS")?
+            .map(Duration::from_millis)
+            .unwrap_or(default.initial_backoff);
+        let max_backoff = value_from_env("MCP_SANDBOX_RETRY_MAX_BACKOFF_MS")?
+            .map(Duration::from_millis)
+            .unwrap_or(default.max_backoff);
+        Ok(Self {
+            max_attempts,
+            initial_backoff,
+            max_backoff,
+        })
+    }
+
+    /// Whether a failed attempt (1 for the first one) is followed by another one
+    pub fn should_retry(&self, error: &McpError, attempt: u32) -> bool {
+        matches!(error, McpError::Temporary(_)) && attempt < self.max_attempts
+    }
+
+    /// Backoff before the retry that follows a failed attempt (1 for the first one)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+fn value_from_env(name: &str) -> McpResult<Option<u64>> {
+    std::env::var(name).ok().map(|value| parse_positive(name, &value)).transpose()
+}
@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod tests {
+    use crate::executor::CommandExecutor;
+    use crate::models::SandboxConfig;
+    use crate::retry::RetryPolicy;
+    use mcp_common::error::McpError;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    // Test for deciding which failures are retried and how long to back off
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+        };
+        let temporary = McpError::Temporary("Resource temporarily unavailable".to_string());
+        assert!(policy.should_retry(&temporary, 1));
+        assert!(policy.should_retry(&temporary, 2));
+        assert!(!policy.should_retry(&temporary, 3));
+        assert!(!policy.should_retry(&McpError::Execution("Command execution timed out".to_string()), 1));
+        assert!(!RetryPolicy::disabled().should_retry(&temporary, 1));
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(300));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(300));
+    }
+
+    // Test for not retrying commands that ran and failed
+    #[tokio::test]
+    async fn test_failed_command_is_not_retried() {
+        let executor = CommandExecutor::new()
+            .with_sandbox_config(SandboxConfig {
+                enabled: false,
+                ..Default::default()
+            })
+            .with_retry_policy(RetryPolicy {
+                max_attempts: 5,
+                ..Default::default()
+            })
+            .with_task_id("task-1");
+        let _task = executor.register_task("task-1").unwrap();
+
+        let args = vec!["-c".to_string(), "exit 3".to_string()];
+        let result = executor.execute("sh", args, HashMap::new(), None, Some(10)).await.unwrap();
+        assert_eq!(result.exit_code, Some(3));
+        let result = executor.execute("/nonexistent/command", Vec::new(), HashMap::new(), None, Some(10)).await;
+        assert!(!matches!(result, Ok(_) | Err(McpError::Temporary(_))));
+        assert_eq!(executor.task_retries("task-1"), 0);
+    }
+}
@@ -0,0 +1,106 @@
+//! Per-process rlimits
+//!
+//! Cgroups bound the processes of a command together, but only when a delegated cgroup is
+//! configured (see [`crate::usage`]). The classic rlimits set here bound every single process
+//! even without one; they are set in the child before it executes and inherited by its
+//! descendants:
+//!
+//! * `RLIMIT_NOFILE` - `open_files_limit` of the resource limits
+//! * `RLIMIT_FSIZE` - `file_size_limit`, or just over the disk quota if that is smaller (see
+//!   [`crate::quota`])
+//! * `RLIMIT_CORE` - `core_dump_limit`; core dumps are disabled unless it is set
+//! * `RLIMIT_STACK` - `stack_limit`
+//!
+//! Soft and hard limits are the same, so that a command cannot raise them again. A limit
+//! above the hard limit of the gateway is lowered to it, since only privileged processes may
+//! raise hard limits. Containers get the same limits as `--ulimit` options; commands in a
+//! microVM are not limited.
+
+use crate::models::ResourceLimits;
+use std::io;
+use tokio::process::Command;
+
+/// Resource bounded by an rlimit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rlimit {
+    /// Open file descriptors
+    OpenFiles,
+    /// Size of a written file (bytes)
+    FileSize,
+    /// Size of a core dump (bytes)
+    CoreDump,
+    /// Stack size (bytes)
+    Stack,
+}
+
+impl Rlimit {
+    /// Name of the limit in `ulimit` options of container runtimes
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rlimit::OpenFiles => "nofile",
+            Rlimit::FileSize => "fsize",
+            Rlimit::CoreDump => "core",
+            Rlimit::Stack => "stack",
+        }
+    }
+}
+
+/// Rlimits of the processes of a command with the given resource limits
+pub fn process_rlimits(limits: &ResourceLimits) -> Vec<(Rlimit, u64)> {
+    // One byte beyond the disk quota, so that a write stopped by the limit also exceeds the quota
+    let quota_file_size = limits.disk_limit.map(|disk_limit| disk_limit.saturating_add(1));
+    let file_size = match (limits.file_size_limit, quota_file_size) {
+        (Some(limit), Some(quota)) => Some(limit.min(quota)),
+        (limit, quota) => limit.or(quota),
+    };
+    [
+        (Rlimit::OpenFiles, limits.open_files_limit),
+        (Rlimit::FileSize, file_size),
+        (Rlimit::CoreDump, Some(limits.core_dump_limit.unwrap_or(0))),
+        (Rlimit::Stack, limits.stack_limit),
+    ]
+    .into_iter()
+    .filter_map(|(rlimit, value)| value.map(|value| (rlimit, value)))
+    .collect()
+}
+
+/// Make a command set the rlimits of its resource limits before it executes
+pub(crate) fn set_rlimits(cmd: &mut Command, limits: &ResourceLimits) {
+    let rlimits = process_rlimits(limits);
+    // SAFETY: getrlimit and setrlimit are async-signal-safe and the closure does not allocate
+    unsafe {
+        cmd.pre_exec(move || {
+            for (rlimit, value) in &rlimits {
+                set_rlimit(*rlimit, *value)?;
+            }
+            Ok(())
+        });
+    }
+}
+
+fn set_rlimit(rlimit: Rlimit, value: u64) -> io::Result<()> {
+    let resource = match rlimit {
+        Rlimit::OpenFiles => libc::RLIMIT_NOFILE,
+        Rlimit::FileSize => libc::RLIMIT_FSIZE,
+        Rlimit::CoreDump => libc::RLIMIT_CORE,
+        Rlimit::Stack => libc::RLIMIT_STACK,
+    };
+    let mut current = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit only writes to the given struct
+    if unsafe { libc::getrlimit(resource, &mut current) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let value = (value as libc::rlim_t).min(current.rlim_max);
+    let limit = libc::rlimit {
+        rlim_cur: value,
+        rlim_max: value,
+    };
+    // SAFETY: setrlimit only reads the given struct
+    if unsafe { libc::setrlimit(resource, &limit) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
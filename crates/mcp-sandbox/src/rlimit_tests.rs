@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{ExecutionRequest, ResourceLimits, SandboxConfig};
+    use crate::rlimit::{process_rlimits, Rlimit};
+    use crate::runner::SandboxRunner;
+    use std::collections::HashMap;
+
+    // Test for deriving the rlimits of a command from its resource limits
+    #[test]
+    fn test_process_rlimits() {
+        // Core dumps are disabled by default
+        assert_eq!(process_rlimits(&ResourceLimits::default()), vec![(Rlimit::CoreDump, 0)]);
+
+        let limits = ResourceLimits {
+            open_files_limit: Some(256),
+            file_size_limit: Some(1 << 20),
+            core_dump_limit: Some(1 << 10),
+            stack_limit: Some(8 << 20),
+            ..Default::default()
+        };
+        assert_eq!(
+            process_rlimits(&limits),
+            vec![
+                (Rlimit::OpenFiles, 256),
+                (Rlimit::FileSize, 1 << 20),
+                (Rlimit::CoreDump, 1 << 10),
+                (Rlimit::Stack, 8 << 20),
+            ]
+        );
+
+        // The file size is bounded by the smaller of its limit and the disk quota
+        let limits = ResourceLimits {
+            file_size_limit: Some(1 << 20),
+            disk_limit: Some(1024),
+            ..Default::default()
+        };
+        assert!(process_rlimits(&limits).contains(&(Rlimit::FileSize, 1025)));
+        let limits = ResourceLimits {
+            disk_limit: Some(1024),
+            ..Default::default()
+        };
+        assert!(process_rlimits(&limits).contains(&(Rlimit::FileSize, 1025)));
+    }
+
+    // Test for setting the rlimits in the command before it executes
+    #[tokio::test]
+    async fn test_rlimits_are_set() {
+        let request = ExecutionRequest {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "ulimit -n; ulimit -c; grep 'Max file size' /proc/self/limits".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config: SandboxConfig {
+                enabled: false,
+                resource_limits: ResourceLimits {
+                    open_files_limit: Some(64),
+                    file_size_limit: Some(1 << 20),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+        let result = SandboxRunner::new().run(request).await.unwrap();
+        assert_eq!(result.exit_code, Some(0), "{}", result.stderr);
+        let lines: Vec<&str> = result.stdout.lines().collect();
+        assert_eq!(lines[..2], ["64", "0"]);
+        // Soft and hard limits are the same
+        assert_eq!(lines[2].split_whitespace().skip(3).take(2).collect::<Vec<_>>(), vec!["1048576", "1048576"]);
+    }
+}
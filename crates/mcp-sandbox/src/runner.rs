@@ -1,82 +1,737 @@
-use crate::models::{ExecutionRequest, ExecutionResult, ResourceUsage, NetworkAccess};
+use crate::models::{
+    ExecutionRequest, ExecutionResult, MacProfile, NetworkAccess, OutputChunk, SandboxBackend, SandboxConfig,
+    WorkspaceMode,
+};
 use crate::bubblewrap::BubblewrapWrapper;
-use crate::seccomp::{SeccompProfileManager, SeccompProfileType};
+use crate::capabilities::restrict_privileges;
+use crate::container::ContainerRunner;
+use crate::dry_run::{DryRunBackend, DryRunReport};
+use crate::egress::{EgressPolicy, EgressProxy};
+use crate::executable::resolve_executable;
+use crate::firecracker::{FirecrackerBackend, FirecrackerConfig};
+use crate::mac::{default_profile_from_env, MacSupport};
+use crate::monitor::RuntimeMonitor;
+use crate::output_capture::{OutputCapture, OutputCaptureConfig, SpilledOutput};
+use crate::output_log::OutputStream;
+use crate::overlay::WorkspaceOverlay;
+use crate::plan::{ExecutionPlan, PlanResult, StepResult, StepStatus};
+use crate::process::{signal_group, ProcessTracker};
+use crate::process_tree::ProcessTreeRecorder;
+use crate::quota::{DiskQuota, QUOTA_POLL_INTERVAL};
+use crate::rlimit::set_rlimits;
+use crate::seccomp::{CompiledProfile, SeccompConfig, SeccompProfileManager, SeccompProfileType};
+use crate::session::{SandboxSessions, SessionInfo};
+use crate::staging::{stage_files, ContentStore, StagedFile};
+use crate::usage::{
+    cpu_time_exceeded_error, out_of_memory_error, UsageAccounting, UsageMeter, CPU_TIME_POLL_INTERVAL,
+};
+use crate::workspace::{TaskWorkspaces, WORKSPACE_MOUNT_POINT};
+use crate::workspace_diff::{WorkspaceSnapshot, MAX_DIFF_ENTRIES};
 use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::{current_timestamp_ms, get_env_var_or};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::os::unix::process::ExitStatusExt;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use tracing::{debug, error, info, warn};
-use tokio::process::Command;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio::time::timeout;
 use std::time::Duration;
 
+/// Size of the buffer output is read with (the maximum size of an output chunk)
+const OUTPUT_READ_BUFFER_BYTES: usize = 8 * 1024;
+
+/// Time a timed-out command is given to exit after SIGTERM before it is killed
+pub const DEFAULT_TERMINATION_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Time the output of a stopped command is read for before it is discarded
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Bytes at the end of each stream included in the error of a timed-out command
+pub const PARTIAL_OUTPUT_BYTES: usize = 4096;
+
 /// Runner for executing commands in a sandbox
 #[derive(Debug)]
 pub struct SandboxRunner {
     bubblewrap: Option<BubblewrapWrapper>,
     seccomp_manager: SeccompProfileManager,
+    usage_accounting: UsageAccounting,
+    firecracker: Option<FirecrackerBackend>,
+    container: Option<ContainerRunner>,
+    workspaces: Option<TaskWorkspaces>,
+    processes: ProcessTracker,
+    termination_grace_period: Duration,
+    sandbox_required: bool,
+    content_store: Option<ContentStore>,
+    runtime_monitor: Option<RuntimeMonitor>,
+    mac: MacSupport,
+    mac_profile: Option<MacProfile>,
+    sessions: Option<SandboxSessions>,
+    output_capture: OutputCaptureConfig,
 }
 
 impl SandboxRunner {
     /// Create a new SandboxRunner
+    ///
+    /// `MCP_SANDBOX_REQUIRED=true` makes commands that would otherwise fall back to running
+    /// without isolation fail instead (see [`Self::with_sandbox_required`]).
     pub fn new() -> Self {
         let bubblewrap = BubblewrapWrapper::new();
-        let seccomp_manager = SeccompProfileManager::default();
+        let seccomp_manager = SeccompConfig::from_env()
+            .and_then(|config| SeccompProfileManager::default().with_config(config))
+            .unwrap_or_else(|e| {
+                error!("Invalid seccomp configuration, using the built-in profiles only: {}", e);
+                SeccompProfileManager::default()
+            });
+        let container = ContainerRunner::from_env().unwrap_or_else(|e| {
+            warn!("Invalid container runtime settings, the container backend is disabled: {}", e);
+            None
+        });
         
-        if bubblewrap.is_none() {
-            warn!("bubblewrap is not available, executing without sandbox. This is a security vulnerability.");
-        } else {
+        let sandbox_required = get_env_var_or("MCP_SANDBOX_REQUIRED", "false") == "true";
+        if bubblewrap.is_some() {
             info!("Using bubblewrap sandbox.");
+        } else if let Some(container) = &container {
+            info!("bubblewrap is not available, using {} containers as sandbox.", container.runtime().as_str());
+        } else if sandbox_required {
+            error!("bubblewrap is not available and a sandbox is required, sandboxed executions will fail.");
+        } else {
+            warn!("bubblewrap is not available, executing without sandbox. This is a security vulnerability.");
         }
+
+        let usage_accounting = UsageAccounting::from_env().unwrap_or_else(|e| {
+            warn!("Invalid resource usage accounting settings, using getrusage only: {}", e);
+            UsageAccounting::new()
+        });
+
+        let firecracker = FirecrackerConfig::from_env()
+            .and_then(|config| config.map(FirecrackerBackend::new).transpose())
+            .unwrap_or_else(|e| {
+                warn!("Invalid firecracker backend settings, microVM execution is disabled: {}", e);
+                None
+            });
+        if firecracker.is_some() {
+            info!("Firecracker microVM backend is available.");
+        }
+
+        let workspaces = TaskWorkspaces::from_env().unwrap_or_else(|e| {
+            warn!("Invalid task workspace settings, tasks share the host workspace: {}", e);
+            None
+        });
+
+        let mac = MacSupport::detect();
+        let mac_profile = default_profile_from_env().unwrap_or_else(|e| {
+            error!("Invalid AppArmor/SELinux settings, commands are not confined by default: {}", e);
+            None
+        });
+        if let Some(profile) = &mac_profile {
+            match mac.validate(profile) {
+                Ok(()) => info!("Confining sandboxed commands with {:?}", profile),
+                Err(e) => error!("Default AppArmor/SELinux label cannot be applied, executions will fail: {}", e),
+            }
+        }
+
+        let sessions = SandboxSessions::from_env().unwrap_or_else(|e| {
+            warn!("Invalid sandbox session settings, sessions are disabled: {}", e);
+            None
+        });
+
+        let runtime_monitor = RuntimeMonitor::from_env().unwrap_or_else(|e| {
+            error!("Failed to load the eBPF runtime monitor, runtime events are not recorded: {}", e);
+            None
+        });
+
+        let output_capture = OutputCaptureConfig::from_env().unwrap_or_else(|e| {
+            warn!("Invalid output capture settings, using the defaults: {}", e);
+            OutputCaptureConfig::default()
+        });
         
         Self {
             bubblewrap,
             seccomp_manager,
+            usage_accounting,
+            firecracker,
+            container,
+            workspaces,
+            processes: ProcessTracker::new(),
+            termination_grace_period: DEFAULT_TERMINATION_GRACE_PERIOD,
+            sandbox_required,
+            content_store: ContentStore::from_env(),
+            runtime_monitor,
+            mac,
+            mac_profile,
+            sessions,
+            output_capture,
+        }
+    }
+
+    /// Measure resource usage with different accounting settings
+    pub fn with_usage_accounting(mut self, usage_accounting: UsageAccounting) -> Self {
+        self.usage_accounting = usage_accounting;
+        self
+    }
+
+    /// Run commands selecting the firecracker backend with different settings
+    pub fn with_firecracker(mut self, firecracker: FirecrackerBackend) -> Self {
+        self.firecracker = Some(firecracker);
+        self
+    }
+
+    /// Generate seccomp profiles with a different manager
+    pub fn with_seccomp_manager(mut self, seccomp_manager: SeccompProfileManager) -> Self {
+        self.seccomp_manager = seccomp_manager;
+        self
+    }
+
+    /// Sandbox commands with a different bubblewrap executable
+    pub fn with_bubblewrap(mut self, bubblewrap: BubblewrapWrapper) -> Self {
+        self.bubblewrap = Some(bubblewrap);
+        self
+    }
+
+    /// Run containers with a different runtime or image
+    pub fn with_container_runner(mut self, container: ContainerRunner) -> Self {
+        self.container = Some(container);
+        self
+    }
+
+    /// Provision a workspace for every task
+    pub fn with_task_workspaces(mut self, workspaces: TaskWorkspaces) -> Self {
+        self.workspaces = Some(workspaces);
+        self
+    }
+
+    /// Give timed-out commands a different time to exit after SIGTERM
+    pub fn with_termination_grace_period(mut self, grace_period: Duration) -> Self {
+        self.termination_grace_period = grace_period;
+        self
+    }
+
+    /// Fail commands with sandbox enabled when neither bubblewrap nor a container runtime is
+    /// available, instead of running them without isolation
+    pub fn with_sandbox_required(mut self, sandbox_required: bool) -> Self {
+        self.sandbox_required = sandbox_required;
+        self
+    }
+
+    /// Read the blobs of staged input files from a content store
+    pub fn with_content_store(mut self, content_store: ContentStore) -> Self {
+        self.content_store = Some(content_store);
+        self
+    }
+
+    /// Content store of the blobs of staged input files, if configured
+    pub fn content_store(&self) -> Option<&ContentStore> {
+        self.content_store.as_ref()
+    }
+
+    /// Keep a different amount of output in memory, or spill larger output elsewhere
+    pub fn with_output_capture(mut self, output_capture: OutputCaptureConfig) -> Self {
+        self.output_capture = output_capture;
+        self
+    }
+
+    /// Record the system calls of commands that run in a task cgroup
+    pub fn with_runtime_monitor(mut self, runtime_monitor: RuntimeMonitor) -> Self {
+        self.runtime_monitor = Some(runtime_monitor);
+        self
+    }
+
+    /// Detect AppArmor and SELinux with different settings
+    pub fn with_mac_support(mut self, mac: MacSupport) -> Self {
+        self.mac = mac;
+        self
+    }
+
+    /// Confine commands whose sandbox configuration names no label by an AppArmor profile or SELinux context
+    pub fn with_mac_profile(mut self, mac_profile: MacProfile) -> Self {
+        self.mac_profile = Some(mac_profile);
+        self
+    }
+
+    /// Keep sandbox sessions with different settings
+    pub fn with_sandbox_sessions(mut self, sessions: SandboxSessions) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// Open sandbox sessions, if sessions are configured
+    pub fn sessions(&self) -> Option<&SandboxSessions> {
+        self.sessions.as_ref()
+    }
+
+    /// Processes of the tasks run by this runner
+    pub fn processes(&self) -> &ProcessTracker {
+        &self.processes
+    }
+
+    /// Absolute path of the executable a command runs with a sandbox configuration
+    ///
+    /// Commands run by bubblewrap are looked up in the paths mounted into the sandbox, other
+    /// commands on the host (see [`crate::executable`]). Returns `None` for containers and
+    /// microVMs, which resolve the command in their own file system.
+    pub fn resolve_executable(
+        &self,
+        command: &str,
+        env: &HashMap<String, String>,
+        config: &SandboxConfig,
+    ) -> McpResult<Option<PathBuf>> {
+        if config.enabled {
+            let own_file_system = match config.backend {
+                SandboxBackend::Firecracker | SandboxBackend::Container => true,
+                SandboxBackend::Bubblewrap => self.bubblewrap.is_none() && self.container.is_some(),
+            };
+            if own_file_system {
+                return Ok(None);
+            }
         }
+        let sandbox = (config.enabled && self.bubblewrap.is_some()).then_some(config);
+        resolve_executable(command, env, sandbox).map(Some)
     }
 
     /// Execute command in sandbox
     pub async fn run(&self, request: ExecutionRequest) -> McpResult<ExecutionResult> {
-        let _start_time = Instant::now();
+        self.run_streaming(request, None).await
+    }
+
+    /// Execute command in sandbox, sending its output to `output` while it runs
+    ///
+    /// Chunks are sent in the order they are read from each stream; the result still
+    /// contains the complete output. If the receiver is dropped, the command keeps running
+    /// and only the result collects the output.
+    pub async fn run_streaming(
+        &self,
+        request: ExecutionRequest,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<ExecutionResult> {
+        self.run_task(request, None, output).await
+    }
+
+    /// Execute command in sandbox as part of a task registered with [`Self::processes`]
+    ///
+    /// The command can then be terminated with [`ProcessTracker::cancel`]; a cancelled
+    /// command results in an execution error.
+    pub async fn run_task(
+        &self,
+        request: ExecutionRequest,
+        task_id: Option<&str>,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<ExecutionResult> {
         debug!("Starting command execution: {} {:?}", request.command, request.args);
 
-        // Determine whether to use sandbox
-        let use_sandbox = request.sandbox_config.enabled && self.bubblewrap.is_some();
-        
-        if use_sandbox {
-            info!("Executing in bubblewrap sandbox mode");
-            self.execute_in_sandbox(&request).await
-        } else {
-            if request.sandbox_config.enabled {
-                warn!("bubblewrap is disabled or not available, executing without sandbox!");
-            } else {
-                warn!("Sandbox is disabled! Executing in unsafe environment.");
+        // Sandboxed tasks get their own workspace when configured
+        let (Some(workspaces), Some(task_id), true) = (&self.workspaces, task_id, request.sandbox_config.enabled) else {
+            if !request.input_files.is_empty() {
+                return Err(McpError::InvalidRequest(
+                    "Input files can only be staged into the workspace of a sandboxed task".to_string(),
+                ));
             }
-            self.execute_without_sandbox(&request).await
+            return self.dispatch(&request, task_id, output).await;
+        };
+        let mut request = request;
+        self.provision_workspace(workspaces, task_id, &mut request.sandbox_config)?;
+        // Input files are in place before the command starts
+        let staged = match &request.sandbox_config.workspace_dir {
+            Some(workspace_dir) => stage_files(workspace_dir, &request.input_files, self.content_store.as_ref()),
+            None => Ok(0),
+        };
+        let result = match staged {
+            Ok(_) => self.dispatch(&request, Some(task_id), output).await,
+            Err(e) => Err(e),
+        };
+        workspaces.release(task_id);
+        result
+    }
+
+    /// Execute the steps of a plan under one task (see [`crate::plan`])
+    ///
+    /// Every step runs with the sandbox configuration unless it has its own; the input files
+    /// are staged into the workspace of the task before the first step. Fails without running any step if the
+    /// plan is invalid; failures of the steps are reported in their results.
+    pub async fn run_plan(
+        &self,
+        plan: &ExecutionPlan,
+        sandbox_config: SandboxConfig,
+        input_files: Vec<StagedFile>,
+        task_id: Option<&str>,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<PlanResult> {
+        let order = plan.execution_order()?;
+        debug!("Starting plan execution with {} steps", order.len());
+
+        // The steps share the workspace of the task
+        let mut sandbox_config = sandbox_config;
+        let workspace = match (&self.workspaces, task_id, sandbox_config.enabled) {
+            (Some(workspaces), Some(task_id), true) => {
+                self.provision_workspace(workspaces, task_id, &mut sandbox_config)?;
+                Some((workspaces, task_id))
+            }
+            _ => None,
+        };
+        let staged = match (&sandbox_config.workspace_dir, input_files.is_empty()) {
+            (_, true) => Ok(0),
+            (Some(workspace_dir), false) => stage_files(workspace_dir, &input_files, self.content_store.as_ref()),
+            (None, false) => Err(McpError::InvalidRequest(
+                "Input files can only be staged into the workspace of a sandboxed task".to_string(),
+            )),
+        };
+
+        let result = match staged {
+            Ok(_) => Ok(self.run_steps(plan, &order, &sandbox_config, task_id, output).await),
+            Err(e) => Err(e),
+        };
+        if let Some((workspaces, task_id)) = workspace {
+            workspaces.release(task_id);
+        }
+        result
+    }
+
+    async fn run_steps(
+        &self,
+        plan: &ExecutionPlan,
+        order: &[usize],
+        sandbox_config: &SandboxConfig,
+        task_id: Option<&str>,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> PlanResult {
+        let mut result = PlanResult::default();
+        for &i in order {
+            let step = &plan.steps[i];
+            if task_id.is_some_and(|task_id| self.processes.is_cancelled(task_id)) {
+                result.steps.push(StepResult::skipped(&step.id, "The task was cancelled".to_string()));
+                continue;
+            }
+            let failed_dependency = step.depends_on.iter().find(|id| {
+                result.step(id).is_none_or(|dependency| dependency.status != StepStatus::Succeeded)
+            });
+            if let Some(dependency) = failed_dependency {
+                info!("Skipping plan step {}: step {} did not succeed", step.id, dependency);
+                result
+                    .steps
+                    .push(StepResult::skipped(&step.id, format!("Dependency '{}' did not succeed", dependency)));
+                continue;
+            }
+
+            debug!("Starting plan step {}: {} {:?}", step.id, step.command, step.args);
+            let step_config = match &step.sandbox_config {
+                Some(config) => {
+                    let mut config = config.clone();
+                    if let Some(workspace_dir) = &sandbox_config.workspace_dir {
+                        mount_workspace(&mut config, workspace_dir);
+                    }
+                    config
+                }
+                None => sandbox_config.clone(),
+            };
+            let request = ExecutionRequest {
+                command: step.command.clone(),
+                args: step.args.clone(),
+                env: step.env.clone(),
+                cwd: step.cwd.as_ref().map(PathBuf::from),
+                timeout: step.timeout,
+                sandbox_config: step_config,
+                input_files: Vec::new(),
+                diff_workspace: false,
+            };
+            let executed = self.dispatch(&request, task_id, output.clone()).await;
+            let step_result = StepResult::from_execution(&step.id, executed);
+            if let Some(error) = &step_result.error {
+                info!("Plan step {} failed: {}", step.id, error);
+            }
+            result.steps.push(step_result);
+        }
+        result
+    }
+
+    /// Open a session whose commands run with a sandbox configuration (see [`crate::session`])
+    pub fn open_session(&self, session_id: &str, config: SandboxConfig) -> McpResult<SessionInfo> {
+        self.session_registry()?.open(session_id, config, self.usage_accounting.cgroup_root())
+    }
+
+    /// Execute command in a session
+    ///
+    /// The command runs with the sandbox configuration of the session instead of the one of
+    /// the request; input files are staged into the workspace of the session. As with
+    /// [`Self::run_task`], a command with a task ID can be cancelled.
+    pub async fn run_in_session(
+        &self,
+        session_id: &str,
+        request: ExecutionRequest,
+        task_id: Option<&str>,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<ExecutionResult> {
+        debug!("Starting command execution in session {}: {} {:?}", session_id, request.command, request.args);
+        let (sandbox_config, _session) = self.session_registry()?.begin(session_id)?;
+        let request = ExecutionRequest {
+            sandbox_config,
+            ..request
+        };
+        if let Some(workspace_dir) = &request.sandbox_config.workspace_dir {
+            stage_files(workspace_dir, &request.input_files, self.content_store.as_ref())?;
+        }
+        self.dispatch(&request, task_id, output).await
+    }
+
+    /// Close a session, removing its workspace; returns false if the session is not open
+    pub fn close_session(&self, session_id: &str) -> McpResult<bool> {
+        self.session_registry()?.close(session_id)
+    }
+
+    fn session_registry(&self) -> McpResult<&SandboxSessions> {
+        self.sessions
+            .as_ref()
+            .ok_or_else(|| McpError::InvalidRequest("Sandbox sessions are not configured".to_string()))
+    }
+
+    /// Create the workspace of a task and mount it at `/workspace`
+    fn provision_workspace(
+        &self,
+        workspaces: &TaskWorkspaces,
+        task_id: &str,
+        config: &mut SandboxConfig,
+    ) -> McpResult<()> {
+        if let Err(e) = workspaces.collect_garbage(|task_id| self.processes.is_registered(task_id)) {
+            warn!("Task workspace garbage collection failed: {}", e);
+        }
+        let workspace_dir = workspaces.provision(task_id)?;
+        mount_workspace(config, &workspace_dir);
+        Ok(())
+    }
+
+    /// Execute command with the backend selected by the sandbox configuration
+    ///
+    /// The changes to the read-write paths are added to the result if the request asks for them.
+    async fn dispatch(
+        &self,
+        request: &ExecutionRequest,
+        task_id: Option<&str>,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<ExecutionResult> {
+        let request = &self.prepare(request)?;
+        let before = match request.diff_workspace {
+            true => workspace_snapshot(&request.sandbox_config),
+            false => None,
+        };
+        let mut result = self.execute_with_backend(request, task_id, output).await?;
+        if let Some(before) = before {
+            result.workspace_changes = workspace_snapshot(&request.sandbox_config).map(|after| before.diff(&after));
+        }
+        Ok(result)
+    }
+
+    async fn execute_with_backend(
+        &self,
+        request: &ExecutionRequest,
+        task_id: Option<&str>,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<ExecutionResult> {
+        match self.select_backend(&request.sandbox_config)? {
+            Backend::Firecracker => {
+                info!("Executing in firecracker microVM");
+                self.execute_in_vm(request, task_id, output).await
+            }
+            Backend::Container => {
+                if request.sandbox_config.backend == SandboxBackend::Container {
+                    info!("Executing in container");
+                } else {
+                    info!("bubblewrap is not available, executing in container");
+                }
+                self.execute_in_container(request, task_id, output).await
+            }
+            Backend::Bubblewrap => {
+                info!("Executing in bubblewrap sandbox mode");
+                self.execute_in_sandbox(request, task_id, output).await
+            }
+            Backend::Host => {
+                if request.sandbox_config.enabled {
+                    warn!("bubblewrap is disabled or not available, executing without sandbox!");
+                } else {
+                    warn!("Sandbox is disabled! Executing in unsafe environment.");
+                }
+                self.execute_without_sandbox(request, task_id, output).await
+            }
+        }
+    }
+
+    /// Report how a command would be started without starting it (see [`crate::dry_run`])
+    ///
+    /// The request goes through the same preparation and backend selection as an execution,
+    /// so a request that would fail before its command starts fails the dry run as well.
+    pub fn dry_run(&self, request: &ExecutionRequest) -> McpResult<DryRunReport> {
+        let request = &self.prepare(request)?;
+        let config = &request.sandbox_config;
+        match self.select_backend(config)? {
+            Backend::Firecracker => Err(McpError::InvalidRequest(
+                "Dry runs are not supported by the firecracker backend".to_string(),
+            )),
+            Backend::Container => {
+                let container = self.container_runner()?;
+                let name = ContainerRunner::container_name();
+                let (cmd, sandbox_config) = self.container_command(container, &name, request)?;
+                let seccomp_profile = sandbox_config.seccomp_profile.is_some().then(|| seccomp_profile_name(config));
+                Ok(DryRunReport::new(
+                    DryRunBackend::Container,
+                    &cmd,
+                    &sandbox_config,
+                    None,
+                    seccomp_profile,
+                    request.timeout,
+                ))
+            }
+            Backend::Bubblewrap => {
+                // The upper layers are removed with the overlay once the report is built
+                let (cmd, sandbox_config, overlay) = self.sandbox_command(request)?;
+                let seccomp_profile = sandbox_config.seccomp_profile.is_some().then(|| seccomp_profile_name(config));
+                Ok(DryRunReport::new(
+                    DryRunBackend::Bubblewrap,
+                    &cmd,
+                    &sandbox_config,
+                    overlay.as_ref(),
+                    seccomp_profile,
+                    request.timeout,
+                ))
+            }
+            Backend::Host => {
+                let cmd = self.host_command(request)?;
+                Ok(DryRunReport::new(DryRunBackend::Host, &cmd, config, None, None, request.timeout))
+            }
+        }
+    }
+
+    /// Request as its command is started: the executable resolved and the default label applied
+    fn prepare(&self, request: &ExecutionRequest) -> McpResult<ExecutionRequest> {
+        let mut request = request.clone();
+        // Commands started by bubblewrap or on the host run by their absolute path
+        if let Some(executable) = self.resolve_executable(&request.command, &request.env, &request.sandbox_config)? {
+            debug!("Resolved {} to {}", request.command, executable.display());
+            request.command = executable.to_string_lossy().to_string();
+        }
+
+        // Commands are confined by the default label unless their configuration names one
+        if request.sandbox_config.mac_profile.is_none() {
+            request.sandbox_config.mac_profile = self.mac_profile.clone();
+        }
+        if let Some(profile) = &request.sandbox_config.mac_profile {
+            self.mac.validate(profile)?;
+        }
+        Ok(request)
+    }
+
+    /// Backend a sandbox configuration runs its commands with
+    fn select_backend(&self, config: &SandboxConfig) -> McpResult<Backend> {
+        if !config.enabled {
+            return Ok(Backend::Host);
+        }
+        // An explicitly selected backend is never replaced by another sandbox
+        match config.backend {
+            SandboxBackend::Firecracker => {
+                if config.mac_profile.is_some() {
+                    return Err(McpError::Sandbox(
+                        "Sandbox setup failed: AppArmor and SELinux labels are not supported by microVMs".to_string(),
+                    ));
+                }
+                return Ok(Backend::Firecracker);
+            }
+            SandboxBackend::Container => return Ok(Backend::Container),
+            SandboxBackend::Bubblewrap => {}
+        }
+
+        if self.bubblewrap.is_some() {
+            Ok(Backend::Bubblewrap)
+        } else if self.container.is_some() {
+            Ok(Backend::Container)
+        } else if self.sandbox_required {
+            error!("bubblewrap is not available, refusing to execute without sandbox");
+            Err(McpError::Sandbox(
+                "Sandbox setup failed: bubblewrap is not available and execution without sandbox is disabled"
+                    .to_string(),
+            ))
+        } else {
+            Ok(Backend::Host)
         }
     }
 
     /// Execute command in sandbox
-    async fn execute_in_sandbox(&self, request: &ExecutionRequest) -> McpResult<ExecutionResult> {
-        let bubblewrap = self.bubblewrap.as_ref().unwrap();
-        let start_time = Instant::now();
-        
-        // Get seccomp profile
-        let seccomp_profile = match &request.sandbox_config.network_access {
-            NetworkAccess::None => self.seccomp_manager.get_profile_path(SeccompProfileType::Basic),
-            _ => self.seccomp_manager.get_profile_path(SeccompProfileType::Network),
+    async fn execute_in_sandbox(
+        &self,
+        request: &ExecutionRequest,
+        task_id: Option<&str>,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<ExecutionResult> {
+        let (mut cmd, sandbox_config, overlay) = self.sandbox_command(request)?;
+        debug!("bubblewrap command: {:?}", cmd);
+
+        // Measure the resource usage of the command and its descendants
+        let usage_meter = self
+            .usage_accounting
+            .start_in(sandbox_config.cgroup_parent.as_deref())
+            .with_disk_quota(disk_quota(&sandbox_config, overlay.as_ref()))
+            .with_cpu_time_limit(sandbox_config.resource_limits.cpu_time_limit);
+        usage_meter.attach(&mut cmd)?;
+        set_rlimits(&mut cmd, &sandbox_config.resource_limits);
+
+        // Restricted network access only reaches the allowed hosts through the egress proxy
+        let _egress_proxy = match &sandbox_config.network_access {
+            NetworkAccess::Restricted(hosts) => Some(EgressProxy::attach(EgressPolicy::new(hosts)?, &mut cmd)?),
+            _ => None,
         };
-        
+
+        let result = self.execute(cmd, request.timeout, usage_meter, task_id, output, "Sandbox").await?;
+        if let Some(overlay) = &overlay {
+            if sandbox_config.workspace_mode == WorkspaceMode::CommitOnSuccess && result.exit_code == Some(0) {
+                let changed = overlay.commit()?;
+                info!("Committed {} workspace changes", changed);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Build the bubblewrap command of a request
+    ///
+    /// Returns the command with the sandbox configuration it applies (with the compiled
+    /// seccomp profile) and the throwaway upper layers of the read-write paths, if any.
+    fn sandbox_command(
+        &self,
+        request: &ExecutionRequest,
+    ) -> McpResult<(Command, SandboxConfig, Option<WorkspaceOverlay>)> {
+        let bubblewrap = self
+            .bubblewrap
+            .as_ref()
+            .ok_or_else(|| McpError::Sandbox("bubblewrap is not available".to_string()))?;
+
         // Clone and modify sandbox configuration
         let mut sandbox_config = request.sandbox_config.clone();
-        if seccomp_profile.is_ok() {
-            sandbox_config.seccomp_profile = Some(seccomp_profile?);
+        if let Some(seccomp_profile) = self.seccomp_profile(&sandbox_config)? {
+            sandbox_config.seccomp_profile = Some(seccomp_profile.bpf_path);
         }
         
+        // Throwaway upper layers for the read-write paths
+        let overlay = match sandbox_config.workspace_mode {
+            WorkspaceMode::Direct => None,
+            _ => {
+                let lower: Vec<PathBuf> =
+                    sandbox_config.rw_paths.iter().map(|path| sandbox_config.host_path(path).to_path_buf()).collect();
+                Some(WorkspaceOverlay::create(&lower)?)
+            }
+        };
+
+        // AppArmor profiles are applied by aa-exec inside the sandbox
+        let (command, args) = match &sandbox_config.mac_profile {
+            Some(profile @ MacProfile::AppArmor(_)) => {
+                let launcher = self.mac.launcher(profile)?;
+                sandbox_config.ro_paths.push(PathBuf::from(&launcher[0]));
+                launch_under(launcher, &request.command, &request.args)
+            }
+            _ => (request.command.clone(), request.args.clone()),
+        };
+
         // Build bubblewrap command
-        let mut cmd = bubblewrap.build_command(
-            &sandbox_config,
-            &request.command,
-            &request.args,
-        );
+        let mut cmd = bubblewrap.build_command(&sandbox_config, overlay.as_ref(), &command, &args)?;
         
         // Set environment variables
         for (key, value) in &request.env {
@@ -88,58 +743,42 @@ impl SandboxRunner {
             cmd.env("PWD", cwd);
             // Note: current_dir doesn't work with bubblewrap, so we set the PWD environment variable
         }
+        Ok((cmd, sandbox_config, overlay))
+    }
 
-        // Set timeout
-        let timeout_duration = Duration::from_secs(request.timeout as u64);
-        
-        debug!("bubblewrap command: {:?}", cmd);
-        
-        // Execute command
-        let output = match timeout(timeout_duration, cmd.output()).await {
-            Ok(result) => match result {
-                Ok(output) => output,
-                Err(e) => {
-                    error!("bubblewrap command execution error: {}", e);
-                    return Err(McpError::Execution(format!("Sandbox execution failed: {}", e)));
-                }
-            },
-            Err(_) => {
-                error!("bubblewrap command execution timed out: {} seconds", request.timeout);
-                return Err(McpError::Execution(format!(
-                    "Sandbox execution timed out: {} seconds",
-                    request.timeout
-                )));
-            }
-        };
+    /// Execute command without sandbox (reusing milestone 1 implementation)
+    async fn execute_without_sandbox(
+        &self,
+        request: &ExecutionRequest,
+        task_id: Option<&str>,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<ExecutionResult> {
+        let mut cmd = self.host_command(request)?;
 
-        let execution_time = start_time.elapsed();
-        let execution_time_ms = execution_time.as_millis() as u64;
-        
-        // Measure resource usage (in the future, this will be obtained from cgroups, etc.)
-        // Currently returns dummy values
-        let resource_usage = ResourceUsage {
-            cpu_time_ms: execution_time_ms,
-            max_memory_kb: 0,
-            io_read_bytes: 0,
-            io_write_bytes: 0,
-        };
+        // Measure the resource usage of the command
+        let usage_meter = self
+            .usage_accounting
+            .start_in(request.sandbox_config.cgroup_parent.as_deref())
+            .with_disk_quota(disk_quota(&request.sandbox_config, None))
+            .with_cpu_time_limit(request.sandbox_config.resource_limits.cpu_time_limit);
+        usage_meter.attach(&mut cmd)?;
+        set_rlimits(&mut cmd, &request.sandbox_config.resource_limits);
 
-        Ok(ExecutionResult {
-            exit_code: Some(output.status.code().unwrap_or(-1)),
-            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-            resource_usage,
-            execution_time_ms,
-        })
+        self.execute(cmd, request.timeout, usage_meter, task_id, output, "Command").await
     }
 
-    /// Execute command without sandbox (reusing milestone 1 implementation)
-    async fn execute_without_sandbox(&self, request: &ExecutionRequest) -> McpResult<ExecutionResult> {
-        let start_time = Instant::now();
-        let mut cmd = Command::new(&request.command);
+    /// Build the command of a request that runs on the host
+    fn host_command(&self, request: &ExecutionRequest) -> McpResult<Command> {
+        require_workspace_mode(&request.sandbox_config, &[WorkspaceMode::Direct], "Unsandboxed execution")?;
+        // The command is started by aa-exec or runcon when it is confined by a label
+        let (command, args) = match &request.sandbox_config.mac_profile {
+            Some(profile) => launch_under(self.mac.launcher(profile)?, &request.command, &request.args),
+            None => (request.command.clone(), request.args.clone()),
+        };
+        let mut cmd = Command::new(&command);
         
         // Set arguments
-        cmd.args(&request.args);
+        cmd.args(&args);
         
         // Set environment variables
         for (key, value) in &request.env {
@@ -151,47 +790,577 @@ impl SandboxRunner {
             cmd.current_dir(cwd);
         }
 
-        // Set timeout
-        let timeout_duration = Duration::from_secs(request.timeout as u64);
-        
-        // Execute command
-        let output = match timeout(timeout_duration, cmd.output()).await {
-            Ok(result) => match result {
-                Ok(output) => output,
-                Err(e) => {
-                    error!("Command execution error: {}", e);
-                    return Err(McpError::Execution(format!("Command execution failed: {}", e)));
+        // Setuid binaries and file capabilities must not give the command privileges
+        restrict_privileges(&mut cmd, &request.sandbox_config.retained_capabilities)?;
+        Ok(cmd)
+    }
+
+    /// Execute command in a container
+    async fn execute_in_container(
+        &self,
+        request: &ExecutionRequest,
+        task_id: Option<&str>,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<ExecutionResult> {
+        let container = self.container_runner()?;
+        let name = ContainerRunner::container_name();
+        let (cmd, sandbox_config) = self.container_command(container, &name, request)?;
+        debug!("container command: {:?}", cmd);
+
+        // Only the runtime client is measured; the container runs under the runtime, which
+        // applies the file size and CPU time limits itself
+        let usage_meter = self
+            .usage_accounting
+            .start_in(sandbox_config.cgroup_parent.as_deref())
+            .with_disk_quota(disk_quota(&sandbox_config, None));
+        let result = self.execute(cmd, request.timeout, usage_meter, task_id, output, "Container").await;
+        if result.is_err() {
+            // The container outlives a killed client (timeout or cancellation)
+            container.remove(&name).await;
+        }
+        // The runtime client exits with 128 + SIGKILL when the OOM killer stopped the container
+        // at its memory limit; the container's cgroup is not visible to the meter
+        let memory_limited = sandbox_config.resource_limits.memory_limit.is_some();
+        match result {
+            Ok(result) if memory_limited && result.exit_code == Some(128 + libc::SIGKILL) => {
+                error!("Container command was killed at its memory limit");
+                Err(out_of_memory_error(None))
+            }
+            result => result,
+        }
+    }
+
+    fn container_runner(&self) -> McpResult<&ContainerRunner> {
+        self.container
+            .as_ref()
+            .ok_or_else(|| McpError::Sandbox("No container runtime is configured".to_string()))
+    }
+
+    /// Build the command running a request in the container `name`
+    ///
+    /// Returns the command with the sandbox configuration it applies (with the seccomp profile).
+    fn container_command(
+        &self,
+        container: &ContainerRunner,
+        name: &str,
+        request: &ExecutionRequest,
+    ) -> McpResult<(Command, SandboxConfig)> {
+        require_workspace_mode(&request.sandbox_config, &[WorkspaceMode::Direct], "The container backend")?;
+
+        let mut sandbox_config = request.sandbox_config.clone();
+        if let Some(seccomp_profile) = self.seccomp_profile(&sandbox_config)? {
+            sandbox_config.seccomp_profile = Some(seccomp_profile.json_path);
+        }
+
+        let cmd = container.build_command(
+            name,
+            &sandbox_config,
+            &request.command,
+            &request.args,
+            &request.env,
+            request.cwd.as_deref(),
+        )?;
+        Ok((cmd, sandbox_config))
+    }
+
+    /// Seccomp profile of a sandbox configuration
+    ///
+    /// A profile named by the configuration must be applied; when the default profile cannot
+    /// be generated, the command runs without seccomp.
+    fn seccomp_profile(&self, config: &SandboxConfig) -> McpResult<Option<CompiledProfile>> {
+        if config.seccomp_profile_name.is_some() {
+            return self.seccomp_manager.profile(&seccomp_profile_name(config)).map(Some);
+        }
+        match self.seccomp_manager.profile(&seccomp_profile_name(config)) {
+            Ok(profile) => Ok(Some(profile)),
+            Err(e) => {
+                warn!("Running without seccomp profile: {}", e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Execute command in a firecracker microVM
+    async fn execute_in_vm(
+        &self,
+        request: &ExecutionRequest,
+        task_id: Option<&str>,
+        output: Option<mpsc::Sender<OutputChunk>>,
+    ) -> McpResult<ExecutionResult> {
+        let firecracker = self
+            .firecracker
+            .as_ref()
+            .ok_or_else(|| McpError::Sandbox("The firecracker backend is not configured".to_string()))?;
+        // Changes made in the guest are never copied back
+        require_workspace_mode(
+            &request.sandbox_config,
+            &[WorkspaceMode::Direct, WorkspaceMode::Ephemeral],
+            "The firecracker backend",
+        )?;
+        let start_time = Instant::now();
+
+        let vm = firecracker.prepare(request)?;
+        let (mut child, pgid) = self.spawn_tracked(firecracker.command(&vm), None, task_id, "MicroVM")?;
+
+        // The command timeout starts once the VM has booted, which the agent enforces as well
+        let timeout_duration = firecracker.config().boot_timeout + Duration::from_secs(request.timeout as u64);
+        let result = tokio::select! {
+            result = timeout(timeout_duration, firecracker.run(&vm, request, output)) => match result {
+                Ok(result) => result,
+                Err(_) => {
+                    error!("MicroVM command execution timed out: {} seconds", request.timeout);
+                    Err(McpError::Execution(format!("MicroVM execution timed out: {} seconds", request.timeout)))
                 }
             },
-            Err(_) => {
-                error!("Command execution timed out: {} seconds", request.timeout);
-                return Err(McpError::Execution(format!(
-                    "Command execution timed out: {} seconds",
-                    request.timeout
-                )));
+            status = child.wait() => Err(McpError::Sandbox(match status {
+                Ok(status) => format!("MicroVM exited before the command finished: {}", status),
+                Err(e) => format!("MicroVM execution failed: {}", e),
+            })),
+        };
+
+        // The VM does not shut down by itself
+        if let Some(pgid) = pgid {
+            if let Err(e) = signal_group(pgid, libc::SIGKILL) {
+                warn!("Failed to kill microVM: {}", e);
             }
+        }
+        if let Err(e) = child.kill().await {
+            debug!("Failed to kill microVM: {}", e);
+        }
+        self.clear_process_group(task_id);
+
+        if task_id.is_some_and(|task_id| self.processes.is_cancelled(task_id)) {
+            return Err(McpError::Execution("MicroVM execution cancelled".to_string()));
+        }
+        let outcome = result?;
+        if let Some(error) = outcome.exit.error {
+            return Err(McpError::Execution(format!("MicroVM execution failed: {}", error)));
+        }
+
+        Ok(ExecutionResult {
+            exit_code: Some(outcome.exit.exit_code.unwrap_or(-1)),
+            stdout: String::from_utf8_lossy(&outcome.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&outcome.stderr).to_string(),
+            stdout_spill: None,
+            stderr_spill: None,
+            resource_usage: outcome.exit.resource_usage,
+            execution_time_ms: start_time.elapsed().as_millis() as u64,
+            // The processes of the guest are not visible
+            process_tree: Vec::new(),
+            runtime_events: Vec::new(),
+            workspace_changes: None,
+        })
+    }
+
+    /// Spawn a command in its own process group, recording the group and the task cgroup for the task
+    ///
+    /// Returns the child and its process group.
+    fn spawn_tracked(
+        &self,
+        mut cmd: Command,
+        cgroup: Option<&Path>,
+        task_id: Option<&str>,
+        kind: &str,
+    ) -> McpResult<(Child, Option<i32>)> {
+        cmd.process_group(0);
+        if task_id.is_some_and(|task_id| self.processes.is_cancelled(task_id)) {
+            return Err(McpError::Execution(format!("{} execution cancelled", kind)));
+        }
+        let child = cmd.spawn().map_err(|e| {
+            error!("{} command execution error: {}", kind, e);
+            // Out of processes or memory for the moment (see [`crate::retry`])
+            match e.raw_os_error() {
+                Some(libc::EAGAIN) | Some(libc::ENOMEM) => {
+                    McpError::Temporary(format!("{} execution failed: {}", kind, e))
+                }
+                _ => McpError::Execution(format!("{} execution failed: {}", kind, e)),
+            }
+        })?;
+        // The command is the leader of its process group
+        let pgid = child.id().map(|pid| pid as i32);
+        if let (Some(task_id), Some(pgid)) = (task_id, pgid) {
+            if !self.processes.set_process_group(task_id, pgid, cgroup) {
+                // Cancelled while the command was being started
+                signal_group(pgid, libc::SIGKILL)?;
+            }
+        }
+        Ok((child, pgid))
+    }
+
+    /// Spawn a command with piped output and wait for it within the timeout
+    ///
+    /// `kind` ("Sandbox" or "Command") prefixes the error messages. The command runs in its
+    /// own process group. When the command times out, the group receives SIGTERM, and SIGKILL
+    /// after the termination grace period; the timeout error ends with the output produced
+    /// until then.
+    async fn execute(
+        &self,
+        mut cmd: Command,
+        timeout_secs: u32,
+        usage_meter: UsageMeter,
+        task_id: Option<&str>,
+        output: Option<mpsc::Sender<OutputChunk>>,
+        kind: &str,
+    ) -> McpResult<ExecutionResult> {
+        let start_time = Instant::now();
+        cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        let command_line = std::iter::once(cmd.as_std().get_program())
+            .chain(cmd.as_std().get_args())
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        // Record the system calls of the task cgroup from the start of the command
+        let runtime_monitor = match (&self.runtime_monitor, usage_meter.cgroup_id()) {
+            (Some(monitor), Some(cgroup_id)) => monitor
+                .watch(cgroup_id)
+                .map_err(|e| warn!("Runtime events of the command are not recorded: {}", e))
+                .ok(),
+            _ => None,
         };
+        let (mut child, pgid) = self.spawn_tracked(cmd, usage_meter.cgroup_path(), task_id, kind)?;
+        // Record the processes the command spawns
+        let process_tree = child.id().map(|pid| ProcessTreeRecorder::start(pid, command_line));
+        let stdout = forward_output(child.stdout.take(), OutputStream::Stdout, &self.output_capture, output.clone());
+        let stderr = forward_output(child.stderr.take(), OutputStream::Stderr, &self.output_capture, output);
 
-        let execution_time = start_time.elapsed();
-        let execution_time_ms = execution_time.as_millis() as u64;
-        
-        // Measure resource usage (to be implemented in milestone 2)
-        // Currently returns dummy values
-        let resource_usage = ResourceUsage {
-            cpu_time_ms: execution_time_ms,
-            max_memory_kb: 0,
-            io_read_bytes: 0,
-            io_write_bytes: 0,
+        // Set timeout
+        let timeout_duration = Duration::from_secs(timeout_secs as u64);
+        let disk_quota = usage_meter.disk_quota().cloned();
+
+        let mut timed_out = false;
+        let stopped = tokio::select! {
+            result = timeout(timeout_duration, child.wait()) => match result {
+                Ok(Ok(status)) => Ok(status),
+                Ok(Err(e)) => {
+                    error!("{} command execution error: {}", kind, e);
+                    stdout.abort();
+                    stderr.abort();
+                    self.clear_process_group(task_id);
+                    return Err(McpError::Execution(format!("{} execution failed: {}", kind, e)));
+                }
+                Err(_) => {
+                    error!("{} command execution timed out: {} seconds", kind, timeout_secs);
+                    timed_out = true;
+                    Err(McpError::Execution(format!("{} execution timed out: {} seconds", kind, timeout_secs)))
+                }
+            },
+            error = watch_disk_quota(disk_quota.clone()) => {
+                error!("{} command exceeded its disk quota", kind);
+                Err(error)
+            }
+            error = watch_cpu_time(&usage_meter) => {
+                error!("{} command exceeded its CPU time limit", kind);
+                Err(error)
+            }
+        };
+        let status = match stopped {
+            Ok(status) => status,
+            Err(error) => {
+                if timed_out {
+                    // A paused command could not handle SIGTERM
+                    if let Some(task_id) = task_id {
+                        if let Err(e) = self.processes.resume(task_id) {
+                            warn!("Failed to resume timed-out command: {}", e);
+                        }
+                    }
+                    self.terminate(&mut child, pgid, kind).await;
+                }
+                if let Some(pgid) = pgid {
+                    if let Err(e) = signal_group(pgid, libc::SIGKILL) {
+                        warn!("Failed to kill stopped command: {}", e);
+                    }
+                }
+                if let Err(e) = child.kill().await {
+                    warn!("Failed to kill stopped command: {}", e);
+                }
+                // Descendants that left the process group may still hold the pipes open
+                let stdout = stdout.partial(OUTPUT_DRAIN_TIMEOUT).await;
+                let stderr = stderr.partial(OUTPUT_DRAIN_TIMEOUT).await;
+                self.clear_process_group(task_id);
+                if timed_out {
+                    return Err(with_partial_output(error, stdout, stderr));
+                }
+                return Err(error);
+            }
         };
 
+        // Read the rest of the output (until every process holding the pipes has exited)
+        let (stdout, stdout_spill) = stdout.finish().await;
+        let (stderr, stderr_spill) = stderr.finish().await;
+        self.clear_process_group(task_id);
+        let process_tree = process_tree.map(|tree| tree.finish(Some(status))).unwrap_or_default();
+        let runtime_events = runtime_monitor.map(|monitor| monitor.finish()).unwrap_or_default();
+
+        let execution_time_ms = start_time.elapsed().as_millis() as u64;
+        let cpu_time_limit = usage_meter.cpu_time_limit();
+        let cpu_time_exceeded = usage_meter.is_cpu_time_exceeded();
+        let oom_kills = usage_meter.oom_kills();
+        let resource_usage = usage_meter.finish();
+
+        if task_id.is_some_and(|task_id| self.processes.is_cancelled(task_id)) {
+            return Err(McpError::Execution(format!("{} execution cancelled", kind)));
+        }
+        if let Some(disk_quota) = &disk_quota {
+            // Writes between the last measurement and the exit, or beyond the file size limit
+            if disk_quota.is_exceeded() || status.signal() == Some(libc::SIGXFSZ) {
+                return Err(disk_quota.exceeded_error());
+            }
+        }
+        if let Some(cpu_time_limit) = cpu_time_limit {
+            // bubblewrap and shells exit with 128 + the signal that killed their child; SIGKILL
+            // is the hard limit of processes that ignored SIGXCPU
+            let signal = status.signal().or(status.code().map(|code| code - 128));
+            let killed_at_limit =
+                signal == Some(libc::SIGKILL) && resource_usage.cpu_time_ms >= cpu_time_limit.saturating_mul(1000);
+            if cpu_time_exceeded || signal == Some(libc::SIGXCPU) || killed_at_limit {
+                return Err(cpu_time_exceeded_error(cpu_time_limit));
+            }
+        }
+        if oom_kills > 0 {
+            // A command may survive the loss of a child, e.g. a shell that goes on
+            if !status.success() {
+                error!("{} command failed after {} processes were killed by the OOM killer", kind, oom_kills);
+                return Err(out_of_memory_error(Some(oom_kills)));
+            }
+            warn!("{} command succeeded although {} processes were killed by the OOM killer", kind, oom_kills);
+        }
+
         Ok(ExecutionResult {
-            exit_code: Some(output.status.code().unwrap_or(-1)),
-            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            exit_code: Some(status.code().unwrap_or(-1)),
+            stdout: String::from_utf8_lossy(&stdout).to_string(),
+            stderr: String::from_utf8_lossy(&stderr).to_string(),
+            stdout_spill,
+            stderr_spill,
             resource_usage,
             execution_time_ms,
+            process_tree,
+            runtime_events,
+            workspace_changes: None,
         })
     }
+
+    /// Ask the process group of a timed-out command to exit, waiting up to the grace period
+    async fn terminate(&self, child: &mut Child, pgid: Option<i32>, kind: &str) {
+        let Some(pgid) = pgid else {
+            return;
+        };
+        if let Err(e) = signal_group(pgid, libc::SIGTERM) {
+            warn!("Failed to terminate timed-out command: {}", e);
+            return;
+        }
+        if timeout(self.termination_grace_period, child.wait()).await.is_err() {
+            warn!(
+                "{} command did not exit within {:?} of SIGTERM, sending SIGKILL to process group {}",
+                kind, self.termination_grace_period, pgid
+            );
+        }
+    }
+
+    /// Stop signalling the process group of a task once its command is done
+    fn clear_process_group(&self, task_id: Option<&str>) {
+        if let Some(task_id) = task_id {
+            self.processes.clear_process_group(task_id);
+        }
+    }
+}
+
+/// Backend selected for a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Bubblewrap,
+    Container,
+    Firecracker,
+    Host,
+}
+
+/// Name of the seccomp profile of a sandbox configuration: the named one, or the built-in
+/// profile for its network access
+fn seccomp_profile_name(config: &SandboxConfig) -> String {
+    if let Some(name) = &config.seccomp_profile_name {
+        return name.clone();
+    }
+    let profile_type = match &config.network_access {
+        NetworkAccess::None => SeccompProfileType::Basic,
+        _ => SeccompProfileType::Network,
+    };
+    profile_type.name().to_string()
+}
+
+/// Files of the read-write paths for a change report, `None` if they cannot be compared
+fn workspace_snapshot(config: &SandboxConfig) -> Option<WorkspaceSnapshot> {
+    match WorkspaceSnapshot::capture(config, MAX_DIFF_ENTRIES) {
+        Ok(Some(snapshot)) => Some(snapshot),
+        Ok(None) => {
+            warn!("Not reporting workspace changes, the read-write paths have more than {} files", MAX_DIFF_ENTRIES);
+            None
+        }
+        Err(e) => {
+            warn!("Not reporting workspace changes: {}", e);
+            None
+        }
+    }
+}
+
+/// Mount a task workspace at `/workspace`
+fn mount_workspace(config: &mut SandboxConfig, workspace_dir: &Path) {
+    config.workspace_dir = Some(workspace_dir.to_path_buf());
+    let mount_point = PathBuf::from(WORKSPACE_MOUNT_POINT);
+    if !config.rw_paths.contains(&mount_point) {
+        config.rw_paths.push(mount_point);
+    }
+}
+
+/// Disk quota on the writable host directories of a command, if it has a disk limit
+fn disk_quota(config: &SandboxConfig, overlay: Option<&WorkspaceOverlay>) -> Option<DiskQuota> {
+    let disk_limit = config.resource_limits.disk_limit?;
+    let paths = match overlay {
+        Some(overlay) => overlay.layers().iter().map(|layer| layer.upper.clone()).collect(),
+        None => config.rw_paths.iter().map(|path| config.host_path(path).to_path_buf()).collect(),
+    };
+    Some(DiskQuota::new(disk_limit, paths))
+}
+
+/// Program and arguments that start a command with a launcher prefix (see [`MacSupport::launcher`])
+fn launch_under(launcher: Vec<String>, command: &str, args: &[String]) -> (String, Vec<String>) {
+    let mut argv = launcher.into_iter().chain(std::iter::once(command.to_string())).chain(args.iter().cloned());
+    let program = argv.next().unwrap_or_default();
+    (program, argv.collect())
+}
+
+/// Wait until the processes of a running command together exceed its CPU time limit
+///
+/// Waits forever without a limit or a task cgroup; each process is limited by `RLIMIT_CPU` anyway.
+async fn watch_cpu_time(usage_meter: &UsageMeter) -> McpError {
+    let (Some(cpu_time_limit), true) = (usage_meter.cpu_time_limit(), usage_meter.has_cgroup()) else {
+        return std::future::pending().await;
+    };
+    loop {
+        tokio::time::sleep(CPU_TIME_POLL_INTERVAL).await;
+        if usage_meter.is_cpu_time_exceeded() {
+            return cpu_time_exceeded_error(cpu_time_limit);
+        }
+    }
+}
+
+/// Wait until a running command exceeds its disk quota (forever without a quota)
+async fn watch_disk_quota(disk_quota: Option<DiskQuota>) -> McpError {
+    let Some(disk_quota) = disk_quota else {
+        return std::future::pending().await;
+    };
+    loop {
+        tokio::time::sleep(QUOTA_POLL_INTERVAL).await;
+        let quota = disk_quota.clone();
+        if tokio::task::spawn_blocking(move || quota.is_exceeded()).await.unwrap_or(false) {
+            return disk_quota.exceeded_error();
+        }
+    }
+}
+
+/// Fail commands whose workspace mode a backend cannot provide, so that changes never persist unexpectedly
+fn require_workspace_mode(config: &SandboxConfig, supported: &[WorkspaceMode], backend: &str) -> McpResult<()> {
+    if supported.contains(&config.workspace_mode) {
+        return Ok(());
+    }
+    Err(McpError::Sandbox(format!("{} does not support the workspace mode {:?}", backend, config.workspace_mode)))
+}
+
+/// Append the end of the output of a timed-out command to its error
+///
+/// `stdout` and `stderr` are the end of each stream with the total number of bytes written.
+fn with_partial_output(error: McpError, stdout: (Vec<u8>, u64), stderr: (Vec<u8>, u64)) -> McpError {
+    let McpError::Execution(mut message) = error else {
+        return error;
+    };
+    for (name, (tail, len)) in [("stdout", stdout), ("stderr", stderr)] {
+        if len == 0 {
+            continue;
+        }
+        if !message.ends_with('\n') {
+            message.push('\n');
+        }
+        message.push_str(&format!("--- partial {} ---\n", name));
+        if (tail.len() as u64) < len {
+            message.push_str(&format!("[{} earlier bytes omitted]\n", len - tail.len() as u64));
+        }
+        message.push_str(&String::from_utf8_lossy(&tail));
+    }
+    McpError::Execution(message)
+}
+
+/// Output of a command collected in the background
+struct OutputReader {
+    collected: Arc<Mutex<Option<OutputCapture>>>,
+    reader: JoinHandle<()>,
+}
+
+impl OutputReader {
+    /// Inline output and the spilled complete output, once every process holding the pipe has closed it
+    async fn finish(self) -> (Vec<u8>, Option<SpilledOutput>) {
+        let _ = self.reader.await;
+        take_output(&self.collected).finish()
+    }
+
+    /// End of the output read so far and the number of bytes read, after waiting at most
+    /// `drain` for the rest
+    async fn partial(mut self, drain: Duration) -> (Vec<u8>, u64) {
+        if timeout(drain, &mut self.reader).await.is_err() {
+            self.reader.abort();
+        }
+        let mut output = take_output(&self.collected);
+        (output.tail(PARTIAL_OUTPUT_BYTES), output.len())
+    }
+
+    fn abort(&self) {
+        self.reader.abort();
+    }
+}
+
+fn take_output(collected: &Mutex<Option<OutputCapture>>) -> OutputCapture {
+    // The output is only taken once, by `finish` or `partial`
+    collected.lock().unwrap_or_else(|e| e.into_inner()).take().expect("output already taken")
+}
+
+/// Read a pipe to the end, sending each chunk to `output` and collecting everything read
+fn forward_output(
+    pipe: Option<impl AsyncRead + Unpin + Send + 'static>,
+    stream: OutputStream,
+    capture: &OutputCaptureConfig,
+    mut output: Option<mpsc::Sender<OutputChunk>>,
+) -> OutputReader {
+    let collected = Arc::new(Mutex::new(Some(OutputCapture::new(capture, stream))));
+    let reader = tokio::spawn({
+        let collected = collected.clone();
+        async move {
+            let Some(mut pipe) = pipe else {
+                return;
+            };
+            let mut buffer = vec![0; OUTPUT_READ_BUFFER_BYTES];
+            loop {
+                let read = match pipe.read(&mut buffer).await {
+                    Ok(0) => break,
+                    Ok(read) => read,
+                    Err(e) => {
+                        warn!("Failed to read {} of command: {}", stream.as_str(), e);
+                        break;
+                    }
+                };
+                if let Some(capture) = collected.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+                    capture.write(&buffer[..read]);
+                }
+                if let Some(sender) = &output {
+                    let chunk = OutputChunk {
+                        stream,
+                        data: buffer[..read].to_vec(),
+                        timestamp_ms: current_timestamp_ms(),
+                    };
+                    if sender.send(chunk).await.is_err() {
+                        // The receiver is gone; keep collecting the output for the result
+                        output = None;
+                    }
+                }
+            }
+        }
+    });
+    OutputReader { collected, reader }
 }
 
 impl Default for SandboxRunner {
@@ -36,6 +36,8 @@ mod tests {
             cwd,
             timeout,
             sandbox_config,
+            input_files: Vec::new(),
+            diff_workspace: false,
         };
         
         let result = runner.run(request).await;
@@ -70,6 +72,8 @@ mod tests {
             cwd,
             timeout,
             sandbox_config,
+            input_files: Vec::new(),
+            diff_workspace: false,
         };
         
         let result = runner.run(request).await;
@@ -102,6 +106,8 @@ mod tests {
             cwd,
             timeout,
             sandbox_config,
+            input_files: Vec::new(),
+            diff_workspace: false,
         };
         
         let result = runner.run(request).await;
@@ -136,6 +142,8 @@ mod tests {
             cwd,
             timeout,
             sandbox_config,
+            input_files: Vec::new(),
+            diff_workspace: false,
         };
         
         let result = runner.run(request).await;
@@ -175,6 +183,8 @@ mod tests {
             cwd,
             timeout,
             sandbox_config,
+            input_files: Vec::new(),
+            diff_workspace: false,
         };
         
         let result = runner.run(request).await;
@@ -189,4 +199,87 @@ mod tests {
         #[cfg(not(target_os = "windows"))]
         assert!(output.stdout.trim() == "/tmp");
     }
-} 
\ No newline at end of file
+
+    // Test for streaming output while the command runs
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn test_run_streaming() {
+        use crate::output_log::OutputStream;
+        use std::time::Duration;
+
+        let runner = std::sync::Arc::new(SandboxRunner::new());
+        let sandbox_config = SandboxConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let request = ExecutionRequest {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "echo first; echo oops >&2; sleep 1; echo second".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config,
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let running = {
+            let runner = runner.clone();
+            tokio::spawn(async move { runner.run_streaming(request, Some(tx)).await })
+        };
+
+        // The first line arrives before the command has finished
+        let first = tokio::time::timeout(Duration::from_millis(900), rx.recv()).await.unwrap().unwrap();
+        assert!(!running.is_finished());
+        assert!(first.timestamp_ms > 0);
+
+        let mut chunks = vec![first];
+        while let Some(chunk) = rx.recv().await {
+            chunks.push(chunk);
+        }
+        let output = running.await.unwrap().unwrap();
+        let collect = |stream: OutputStream| {
+            chunks
+                .iter()
+                .filter(|chunk| chunk.stream == stream)
+                .flat_map(|chunk| chunk.data.clone())
+                .collect::<Vec<u8>>()
+        };
+        assert_eq!(collect(OutputStream::Stdout), b"first\nsecond\n");
+        assert_eq!(collect(OutputStream::Stderr), b"oops\n");
+        // The result still contains the complete output
+        assert_eq!(output.stdout, "first\nsecond\n");
+        assert_eq!(output.stderr, "oops\n");
+    }
+
+    // Test for failing instead of executing without sandbox when a sandbox is required
+    #[tokio::test]
+    async fn test_run_sandbox_required() {
+        use mcp_common::error::error_code;
+
+        if which::which("bwrap").is_ok() {
+            // Only executions without bubblewrap fall back to running without sandbox
+            return;
+        }
+        let runner = SandboxRunner::new().with_sandbox_required(true);
+        let request = |enabled: bool| ExecutionRequest {
+            command: "echo".to_string(),
+            args: vec!["hello".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config: SandboxConfig {
+                enabled,
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+
+        let error = runner.run(request(true)).await.unwrap_err();
+        assert_eq!(error.code(), error_code::SANDBOX_SETUP_FAILED);
+        // Commands whose sandbox is disabled explicitly still run
+        assert_eq!(runner.run(request(false)).await.unwrap().stdout, "hello\n");
+    }
+}
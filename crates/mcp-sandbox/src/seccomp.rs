@@ -1,6 +1,47 @@
+//! Seccomp profiles
+//!
+//! A profile is a declarative system call filter: either an allowlist (every other system
+//! call is denied) or a denylist (every other system call is allowed). The built-in `basic`
+//! and `network` profiles come from the bundled JSON files; more profiles, or replacements
+//! for the built-in ones, are read from the file named by `MCP_SECCOMP_CONFIG`:
+//!
+//! ```json
+//! {
+//!   "profiles": {
+//!     "build": { "allow": ["read", "write", "execve", "..."] },
+//!     "untrusted": { "deny": ["ptrace", "mount", "bpf"], "action": "kill" }
+//!   }
+//! }
+//! ```
+//!
+//! `action` is what a denied system call results in: `"errno"` (EPERM, the default) or
+//! `"kill"` (the process is killed). Unknown system call names fail the validation.
+//!
+//! Profiles can also be kept in the directory named by `MCP_SECCOMP_PROFILES_DIR`, one file
+//! per profile named after it: `build.json` holds the filter of the `build` profile, e.g.
+//! `{ "allow": ["read", "write", "..."] }`. A profile defined both there and in the
+//! configuration file fails the configuration.
+//!
+//! Policies select the profile of a command by name with the `seccomp_profile` directive,
+//! and clients may request one in the sandbox configuration of a task when the policy does
+//! not. Without either, the `basic` or `network` profile is chosen by the network access of
+//! the command.
+//!
+//! Each profile is generated in two formats under the profile directory: a compiled
+//! classic BPF program for `bwrap --seccomp`, and a Docker format JSON profile for the
+//! container backend. The file names contain a hash of the filter, so compiled profiles are
+//! reused as long as the filter does not change.
+
+use crate::syscalls::{syscall_number, AUDIT_ARCH};
+use mcp_common::error::{McpError, McpResult};
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use tracing::{debug, error};
 
 /// Seccomp profile types
@@ -12,20 +53,355 @@ pub enum SeccompProfileType {
     Network,
 }
 
+impl SeccompProfileType {
+    /// Name of the profile
+    pub fn name(&self) -> &'static str {
+        match self {
+            SeccompProfileType::Basic => "basic",
+            SeccompProfileType::Network => "network",
+        }
+    }
+}
+
+/// Result of a denied system call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DenyAction {
+    /// The system call fails with EPERM
+    #[default]
+    Errno,
+    /// The process is killed
+    Kill,
+}
+
+/// Declarative system call filter
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyscallFilter {
+    /// Allowed system calls (every other one is denied)
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Denied system calls (every other one is allowed)
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Result of a denied system call
+    #[serde(default)]
+    pub action: DenyAction,
+}
+
+impl SyscallFilter {
+    /// Filter allowing only the given system calls
+    pub fn allowlist(names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            allow: names.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Filter denying the given system calls
+    pub fn denylist(names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            deny: names.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the result of a denied system call
+    pub fn with_action(mut self, action: DenyAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Check that the filter is either an allowlist or a denylist of known system calls
+    pub fn validate(&self) -> McpResult<()> {
+        self.syscall_numbers().map(|_| ())
+    }
+
+    /// Numbers of the listed system calls, in ascending order
+    fn syscall_numbers(&self) -> McpResult<Vec<u32>> {
+        let names = match (self.allow.is_empty(), self.deny.is_empty()) {
+            (false, true) => &self.allow,
+            (true, false) => &self.deny,
+            (false, false) => return Err(invalid_filter("cannot have both \"allow\" and \"deny\"")),
+            (true, true) => return Err(invalid_filter("must have \"allow\" or \"deny\"")),
+        };
+        let mut numbers = names
+            .iter()
+            .map(|name| syscall_number(name).ok_or_else(|| invalid_filter(&format!("unknown system call '{}'", name))))
+            .collect::<McpResult<Vec<_>>>()?;
+        numbers.sort_unstable();
+        numbers.dedup();
+        Ok(numbers)
+    }
+
+    /// Compile the filter into a classic BPF program (an array of `struct sock_filter`)
+    pub fn compile(&self) -> McpResult<Vec<u8>> {
+        let Some(audit_arch) = AUDIT_ARCH else {
+            return Err(McpError::Sandbox("Seccomp filters are not supported on this architecture".to_string()));
+        };
+        let numbers = self.syscall_numbers()?;
+        let deny = match self.action {
+            DenyAction::Errno => SECCOMP_RET_ERRNO | libc::EPERM as u32,
+            DenyAction::Kill => SECCOMP_RET_KILL_PROCESS,
+        };
+        let (matched, default) = if self.allow.is_empty() {
+            (deny, SECCOMP_RET_ALLOW)
+        } else {
+            (SECCOMP_RET_ALLOW, deny)
+        };
+
+        let mut program = vec![
+            // Kill processes of other architectures, whose system call numbers differ
+            bpf_stmt(BPF_LD_W_ABS, SECCOMP_DATA_ARCH_OFFSET),
+            bpf_jump(BPF_JEQ_K, audit_arch, 1, 0),
+            bpf_stmt(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+            bpf_stmt(BPF_LD_W_ABS, SECCOMP_DATA_NR_OFFSET),
+        ];
+        if cfg!(target_arch = "x86_64") {
+            // Deny the x32 ABI, whose numbers would bypass the lists
+            program.push(bpf_jump(BPF_JGE_K, X32_SYSCALL_BIT, 0, 1));
+            program.push(bpf_stmt(BPF_RET_K, deny));
+        }
+        for number in numbers {
+            program.push(bpf_jump(BPF_JEQ_K, number, 0, 1));
+            program.push(bpf_stmt(BPF_RET_K, matched));
+        }
+        program.push(bpf_stmt(BPF_RET_K, default));
+
+        if program.len() > BPF_MAXINSNS {
+            return Err(invalid_filter("lists too many system calls"));
+        }
+        Ok(program.into_iter().flat_map(SockFilter::to_bytes).collect())
+    }
+
+    /// Docker format JSON profile of the filter
+    pub fn to_docker_profile(&self) -> McpResult<serde_json::Value> {
+        self.validate()?;
+        let deny = match self.action {
+            DenyAction::Errno => "SCMP_ACT_ERRNO",
+            DenyAction::Kill => "SCMP_ACT_KILL_PROCESS",
+        };
+        let (names, matched, default) = if self.allow.is_empty() {
+            (&self.deny, deny, "SCMP_ACT_ALLOW")
+        } else {
+            (&self.allow, "SCMP_ACT_ALLOW", deny)
+        };
+        Ok(serde_json::json!({
+            "defaultAction": default,
+            "architectures": DOCKER_ARCHITECTURES,
+            "syscalls": [{ "names": names, "action": matched }],
+        }))
+    }
+
+    /// Allowlist of the `SCMP_ACT_ALLOW` rules of a Docker format profile
+    ///
+    /// Like libseccomp, system calls that do not exist on this architecture are ignored.
+    fn from_docker_profile(profile: &str) -> McpResult<Self> {
+        let profile: serde_json::Value = serde_json::from_str(profile)
+            .map_err(|e| McpError::Internal(format!("Invalid bundled seccomp profile: {}", e)))?;
+        let names = profile["syscalls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|rule| rule["action"] == "SCMP_ACT_ALLOW")
+            .filter_map(|rule| rule["names"].as_array())
+            .flatten()
+            .filter_map(|name| name.as_str())
+            .filter(|name| syscall_number(name).is_some());
+        Ok(Self::allowlist(names))
+    }
+}
+
+/// Seccomp profile configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeccompConfig {
+    /// Profiles by name
+    #[serde(default)]
+    pub profiles: HashMap<String, SyscallFilter>,
+}
+
+impl SeccompConfig {
+    /// Load the configuration from the file named by `MCP_SECCOMP_CONFIG` and the profiles of
+    /// the directory named by `MCP_SECCOMP_PROFILES_DIR` (empty if neither is set)
+    pub fn from_env() -> McpResult<Self> {
+        let mut config = match std::env::var("MCP_SECCOMP_CONFIG") {
+            Ok(path) => Self::load(path)?,
+            Err(_) => Self::default(),
+        };
+        if let Ok(dir) = std::env::var("MCP_SECCOMP_PROFILES_DIR") {
+            config.merge(Self::load_dir(dir)?)?;
+        }
+        Ok(config)
+    }
+
+    /// Load and validate a configuration file
+    pub fn load(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| McpError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+        let config: Self = serde_json::from_str(&content).map_err(|e| {
+            McpError::InvalidRequest(format!("Invalid seccomp configuration {}: {}", path.display(), e))
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Load and validate the profiles of a directory, one `<name>.json` file per profile
+    ///
+    /// Files with other extensions are ignored.
+    pub fn load_dir(dir: impl AsRef<Path>) -> McpResult<Self> {
+        let dir = dir.as_ref();
+        let read_error = |path: &Path, e: std::io::Error| {
+            McpError::Internal(format!("Failed to read {}: {}", path.display(), e))
+        };
+        let mut profiles = HashMap::new();
+        for entry in std::fs::read_dir(dir).map_err(|e| read_error(dir, e))? {
+            let path = entry.map_err(|e| read_error(dir, e))?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let content = std::fs::read_to_string(&path).map_err(|e| read_error(&path, e))?;
+            let filter = serde_json::from_str(&content).map_err(|e| {
+                McpError::InvalidRequest(format!("Invalid seccomp profile {}: {}", path.display(), e))
+            })?;
+            profiles.insert(name, filter);
+        }
+        let config = Self { profiles };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Add the profiles of another configuration (a profile defined in both fails)
+    pub fn merge(&mut self, other: Self) -> McpResult<()> {
+        for (name, filter) in other.profiles {
+            if self.profiles.contains_key(&name) {
+                return Err(McpError::InvalidRequest(format!("Seccomp profile '{}' is defined twice", name)));
+            }
+            self.profiles.insert(name, filter);
+        }
+        Ok(())
+    }
+
+    /// Check the names and filters of all profiles
+    pub fn validate(&self) -> McpResult<()> {
+        for (name, filter) in &self.profiles {
+            validate_profile_name(name)?;
+            filter
+                .validate()
+                .map_err(|e| McpError::InvalidRequest(format!("Seccomp profile '{}': {}", name, e)))?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the name of a profile (ASCII letters, digits, `-` and `_`)
+pub fn validate_profile_name(name: &str) -> McpResult<()> {
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(McpError::InvalidRequest(format!("Invalid seccomp profile name: '{}'", name)));
+    }
+    Ok(())
+}
+
+/// Generated files of a profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledProfile {
+    /// Compiled BPF program (for `bwrap --seccomp`)
+    pub bpf_path: PathBuf,
+    /// Docker format JSON profile (for the container backend)
+    pub json_path: PathBuf,
+}
+
 /// Seccomp profile management
 #[derive(Debug)]
 pub struct SeccompProfileManager {
     profile_dir: PathBuf,
+    profiles: HashMap<String, SyscallFilter>,
+    compiled: Mutex<HashMap<String, CompiledProfile>>,
 }
 
 impl SeccompProfileManager {
-    /// Create a new SeccompProfileManager
+    /// Create a new SeccompProfileManager with the built-in profiles
     pub fn new(profile_dir: PathBuf) -> Self {
         std::fs::create_dir_all(&profile_dir).unwrap_or_else(|e| {
             error!("Failed to create seccomp profile directory: {}", e);
         });
-        
-        Self { profile_dir }
+
+        let mut profiles = HashMap::new();
+        for (profile_type, bundled) in [
+            (SeccompProfileType::Basic, include_str!("../profiles/basic.json")),
+            (SeccompProfileType::Network, include_str!("../profiles/network.json")),
+        ] {
+            match SyscallFilter::from_docker_profile(bundled) {
+                Ok(filter) => {
+                    profiles.insert(profile_type.name().to_string(), filter);
+                }
+                Err(e) => error!("Failed to load the built-in seccomp profile {}: {}", profile_type.name(), e),
+            }
+        }
+
+        Self {
+            profile_dir,
+            profiles,
+            compiled: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Add the profiles of a configuration, replacing built-in profiles of the same name
+    pub fn with_config(mut self, config: SeccompConfig) -> McpResult<Self> {
+        config.validate()?;
+        self.profiles.extend(config.profiles);
+        self.compiled.get_mut().unwrap_or_else(|e| e.into_inner()).clear();
+        Ok(self)
+    }
+
+    /// Names of the available profiles
+    pub fn profile_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Filter of a profile
+    pub fn filter(&self, name: &str) -> Option<&SyscallFilter> {
+        self.profiles.get(name)
+    }
+
+    /// Get the path to the Docker format JSON profile of a built-in profile type
+    pub fn get_profile_path(&self, profile_type: SeccompProfileType) -> McpResult<PathBuf> {
+        Ok(self.profile(profile_type.name())?.json_path)
+    }
+
+    /// Generated files of a profile, generating them on first use
+    pub fn profile(&self, name: &str) -> McpResult<CompiledProfile> {
+        let mut compiled = self.compiled.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(profile) = compiled.get(name) {
+            return Ok(profile.clone());
+        }
+
+        let filter = self
+            .profiles
+            .get(name)
+            .ok_or_else(|| McpError::Sandbox(format!("Unknown seccomp profile: {}", name)))?;
+        let mut hasher = DefaultHasher::new();
+        filter.hash(&mut hasher);
+        let stem = format!("{}-{:016x}", name, hasher.finish());
+        let profile = CompiledProfile {
+            bpf_path: self.profile_dir.join(format!("{}.bpf", stem)),
+            json_path: self.profile_dir.join(format!("{}.json", stem)),
+        };
+        if !profile.bpf_path.exists() {
+            write_atomically(&profile.bpf_path, &filter.compile()?)?;
+        }
+        if !profile.json_path.exists() {
+            write_atomically(&profile.json_path, filter.to_docker_profile()?.to_string().as_bytes())?;
+        }
+        debug!("Generated seccomp profile {}: {:?}", name, profile);
+
+        compiled.insert(name.to_string(), profile.clone());
+        Ok(profile)
     }
 }
 
@@ -36,44 +412,83 @@ impl Default for SeccompProfileManager {
     }
 }
 
-impl SeccompProfileManager {
-    /// Get the path to a seccomp profile
-    pub fn get_profile_path(&self, profile_type: SeccompProfileType) -> McpResult<PathBuf> {
-        let profile_name = match profile_type {
-            SeccompProfileType::Basic => "basic.json",
-            SeccompProfileType::Network => "network.json",
-        };
-        
-        let profile_path = self.profile_dir.join(profile_name);
-        
-        if !profile_path.exists() {
-            self.generate_profile(profile_type, &profile_path)?;
-        }
-        
-        Ok(profile_path)
-    }
-    
-    /// Generate a seccomp profile
-    fn generate_profile(&self, profile_type: SeccompProfileType, path: &PathBuf) -> McpResult<()> {
-        let profile_content = match profile_type {
-            SeccompProfileType::Basic => include_str!("../profiles/basic.json"),
-            SeccompProfileType::Network => include_str!("../profiles/network.json"),
-        };
-        
-        let mut file = File::create(path).map_err(|e| {
-            error!("Failed to create seccomp profile: {}", e);
-            McpError::Internal(format!("Failed to create seccomp profile: {}", e))
-        })?;
-        
-        file.write_all(profile_content.as_bytes()).map_err(|e| {
-            error!("Failed to write seccomp profile: {}", e);
-            McpError::Internal(format!("Failed to write seccomp profile: {}", e))
-        })?;
-        
-        debug!("Generated seccomp profile: {:?}", path);
-        
-        Ok(())
+/// Write a file under a temporary name and rename it, so that readers never see a partial file
+fn write_atomically(path: &Path, content: &[u8]) -> McpResult<()> {
+    let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+    let write = || -> std::io::Result<()> {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    };
+    write().map_err(|e| {
+        let _ = std::fs::remove_file(&tmp_path);
+        error!("Failed to write seccomp profile: {}", e);
+        McpError::Internal(format!("Failed to write seccomp profile {}: {}", path.display(), e))
+    })
+}
+
+fn invalid_filter(reason: &str) -> McpError {
+    McpError::InvalidRequest(format!("Invalid system call filter: {}", reason))
+}
+
+/// Architectures of the Docker format profiles
+#[cfg(target_arch = "aarch64")]
+const DOCKER_ARCHITECTURES: &[&str] = &["SCMP_ARCH_AARCH64", "SCMP_ARCH_ARM"];
+#[cfg(not(target_arch = "aarch64"))]
+const DOCKER_ARCHITECTURES: &[&str] = &["SCMP_ARCH_X86_64", "SCMP_ARCH_X86", "SCMP_ARCH_X32"];
+
+// Classic BPF and seccomp constants (linux/filter.h, linux/seccomp.h)
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JEQ_K: u16 = 0x15;
+const BPF_JGE_K: u16 = 0x35;
+const BPF_RET_K: u16 = 0x06;
+const BPF_MAXINSNS: usize = 4096;
+const SECCOMP_DATA_NR_OFFSET: u32 = 0;
+const SECCOMP_DATA_ARCH_OFFSET: u32 = 4;
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+/// `struct sock_filter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SockFilter {
+    pub(crate) code: u16,
+    pub(crate) jt: u8,
+    pub(crate) jf: u8,
+    pub(crate) k: u32,
+}
+
+impl SockFilter {
+    fn to_bytes(self) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0..2].copy_from_slice(&self.code.to_ne_bytes());
+        bytes[2] = self.jt;
+        bytes[3] = self.jf;
+        bytes[4..8].copy_from_slice(&self.k.to_ne_bytes());
+        bytes
+    }
+
+    /// Decode a compiled program
+    #[cfg(test)]
+    pub(crate) fn parse_program(bytes: &[u8]) -> Vec<Self> {
+        bytes
+            .chunks_exact(8)
+            .map(|chunk| Self {
+                code: u16::from_ne_bytes([chunk[0], chunk[1]]),
+                jt: chunk[2],
+                jf: chunk[3],
+                k: u32::from_ne_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]),
+            })
+            .collect()
     }
 }
 
-use mcp_common::error::{McpError, McpResult}; 
\ No newline at end of file
+fn bpf_stmt(code: u16, k: u32) -> SockFilter {
+    SockFilter { code, jt: 0, jf: 0, k }
+}
+
+fn bpf_jump(code: u16, k: u32, jt: u8, jf: u8) -> SockFilter {
+    SockFilter { code, jt, jf, k }
+}
@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod tests {
+    use crate::seccomp::{DenyAction, SeccompConfig, SeccompProfileManager, SockFilter, SyscallFilter};
+    use crate::syscalls::{syscall_number, AUDIT_ARCH};
+    use mcp_common::error::McpError;
+
+    const RET_ALLOW: u32 = 0x7fff_0000;
+    const RET_KILL_PROCESS: u32 = 0x8000_0000;
+    const RET_EPERM: u32 = 0x0005_0000 | libc::EPERM as u32;
+
+    /// Instructions before the system call comparisons (architecture check, x32 check)
+    const PROLOGUE: usize = if cfg!(target_arch = "x86_64") { 6 } else { 4 };
+
+    // Test for compiling an allowlist into a BPF program
+    #[test]
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn test_compile_allowlist() {
+        let filter = SyscallFilter::allowlist(["write", "read", "read"]);
+        let program = SockFilter::parse_program(&filter.compile().unwrap());
+
+        // Duplicates are compiled once, two instructions per system call
+        assert_eq!(program.len(), PROLOGUE + 2 * 2 + 1);
+        assert_eq!((program[0].code, program[0].k), (0x20, 4));
+        assert_eq!((program[1].code, program[1].k), (0x15, AUDIT_ARCH.unwrap()));
+        assert_eq!(program[2].k, RET_KILL_PROCESS);
+        assert_eq!((program[3].code, program[3].k), (0x20, 0));
+
+        let read = &program[PROLOGUE..PROLOGUE + 2];
+        assert_eq!((read[0].code, read[0].k, read[0].jt, read[0].jf), (0x15, syscall_number("read").unwrap(), 0, 1));
+        assert_eq!((read[1].code, read[1].k), (0x06, RET_ALLOW));
+        assert_eq!(program.last().unwrap().k, RET_EPERM);
+    }
+
+    // Test for compiling a denylist that kills the process
+    #[test]
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn test_compile_denylist() {
+        let filter = SyscallFilter::denylist(["ptrace"]).with_action(DenyAction::Kill);
+        let program = SockFilter::parse_program(&filter.compile().unwrap());
+
+        assert_eq!(program.len(), PROLOGUE + 2 + 1);
+        assert_eq!(program[PROLOGUE].k, syscall_number("ptrace").unwrap());
+        assert_eq!(program[PROLOGUE + 1].k, RET_KILL_PROCESS);
+        assert_eq!(program.last().unwrap().k, RET_ALLOW);
+
+        let profile = filter.to_docker_profile().unwrap();
+        assert_eq!(profile["defaultAction"], "SCMP_ACT_ALLOW");
+        assert_eq!(profile["syscalls"][0]["action"], "SCMP_ACT_KILL_PROCESS");
+        assert_eq!(profile["syscalls"][0]["names"][0], "ptrace");
+    }
+
+    // Test for rejecting invalid filters
+    #[test]
+    fn test_invalid_filters() {
+        let both = SyscallFilter {
+            allow: vec!["read".to_string()],
+            deny: vec!["ptrace".to_string()],
+            ..Default::default()
+        };
+        for filter in [both, SyscallFilter::default(), SyscallFilter::allowlist(["read", "no_such_syscall"])] {
+            match filter.validate() {
+                Err(McpError::InvalidRequest(_)) => {}
+                other => panic!("unexpected result for {:?}: {:?}", filter, other),
+            }
+        }
+
+        let json = r#"{"profiles": {"build": {"allow": ["read"], "action": "trap"}}}"#;
+        assert!(serde_json::from_str::<SeccompConfig>(json).is_err());
+        let json = r#"{"profiles": {"build": {"allowed": ["read"]}}}"#;
+        assert!(serde_json::from_str::<SeccompConfig>(json).is_err());
+    }
+
+    // Test for loading profiles from a configuration file
+    #[test]
+    fn test_load_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seccomp.json");
+        std::fs::write(&path, r#"{"profiles": {"build": {"deny": ["ptrace", "mount"], "action": "kill"}}}"#).unwrap();
+        let config = SeccompConfig::load(&path).unwrap();
+        assert_eq!(
+            config.profiles["build"],
+            SyscallFilter::denylist(["ptrace", "mount"]).with_action(DenyAction::Kill)
+        );
+
+        for content in [r#"{"profiles": {"../build": {"deny": ["ptrace"]}}}"#, r#"{"profiles": {"build": {}}}"#] {
+            std::fs::write(&path, content).unwrap();
+            assert!(matches!(SeccompConfig::load(&path), Err(McpError::InvalidRequest(_))), "{}", content);
+        }
+    }
+
+    // Test for loading profiles from a directory, one file per profile
+    #[test]
+    fn test_load_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("build.json"), r#"{"allow": ["read", "write", "exit_group"]}"#).unwrap();
+        std::fs::write(dir.path().join("untrusted.json"), r#"{"deny": ["ptrace"], "action": "kill"}"#).unwrap();
+        std::fs::write(dir.path().join("README.md"), "not a profile").unwrap();
+        let mut config = SeccompConfig::load_dir(dir.path()).unwrap();
+        let mut names: Vec<_> = config.profiles.keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, vec!["build", "untrusted"]);
+        assert_eq!(config.profiles["build"], SyscallFilter::allowlist(["read", "write", "exit_group"]));
+
+        // A profile may only be defined once
+        let other = SeccompConfig {
+            profiles: [("build".to_string(), SyscallFilter::denylist(["mount"]))].into(),
+        };
+        assert!(matches!(config.merge(other), Err(McpError::InvalidRequest(_))));
+
+        for (file, content) in [("bad name.json", r#"{"deny": ["ptrace"]}"#), ("empty.json", "{}")] {
+            let dir = tempfile::tempdir().unwrap();
+            std::fs::write(dir.path().join(file), content).unwrap();
+            assert!(matches!(SeccompConfig::load_dir(dir.path()), Err(McpError::InvalidRequest(_))), "{}", file);
+        }
+    }
+
+    // Test for generating and caching the profile files
+    #[test]
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn test_profile_generation() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = SeccompProfileManager::new(dir.path().to_path_buf());
+        assert_eq!(manager.profile_names(), vec!["basic", "network"]);
+        // Names of the bundled profiles that are not system calls are ignored
+        assert!(!manager.filter("network").unwrap().allow.iter().any(|name| name == "getaddrinfo"));
+
+        let basic = manager.profile("basic").unwrap();
+        assert_eq!(manager.profile("basic").unwrap(), basic);
+        let bpf = std::fs::read(&basic.bpf_path).unwrap();
+        assert_eq!(bpf.len() % 8, 0);
+        let json: serde_json::Value = serde_json::from_slice(&std::fs::read(&basic.json_path).unwrap()).unwrap();
+        assert_eq!(json["defaultAction"], "SCMP_ACT_ERRNO");
+        assert!(matches!(manager.profile("build"), Err(McpError::Sandbox(_))));
+
+        // Configured profiles replace the built-in ones and are generated under a new name
+        let config = SeccompConfig {
+            profiles: [("basic".to_string(), SyscallFilter::allowlist(["read", "write", "exit_group"]))].into(),
+        };
+        let manager = manager.with_config(config).unwrap();
+        let replaced = manager.profile("basic").unwrap();
+        assert_ne!(replaced.bpf_path, basic.bpf_path);
+        assert_eq!(std::fs::read(&replaced.bpf_path).unwrap().len(), (PROLOGUE + 3 * 2 + 1) * 8);
+    }
+}
@@ -0,0 +1,367 @@
+//! Persistent sandbox sessions
+//!
+//! A session is set up once and then runs any number of commands: its sandbox configuration
+//! is fixed when it is opened, its workspace `<root>/<session_id>/workspace` is mounted
+//! read-write at [`WORKSPACE_MOUNT_POINT`] in every command, and with usage accounting the
+//! task cgroups of its commands are created in a cgroup of the session, whose counters
+//! accumulate over all of them. Files a command leaves in the workspace are seen by the
+//! next one, so that agents running many small related commands do not pay for the setup
+//! of each. Every command still starts in fresh namespaces, and processes do not outlive
+//! the command that started them.
+//!
+//! Sessions are closed explicitly or once they have been idle (no command running) for the
+//! idle timeout. Expired sessions are closed whenever a session is opened or used; call
+//! [`SandboxSessions::expire_idle`] periodically to also close them while no sessions are
+//! used. Closing a session removes its workspace and its cgroup.
+
+use crate::models::{SandboxBackend, SandboxConfig};
+use crate::overlay::remove_tree;
+use crate::workspace::WORKSPACE_MOUNT_POINT;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::current_timestamp_ms;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Idle timeout used when `MCP_SANDBOX_SESSION_IDLE_TIMEOUT_SECS` is not set
+pub const DEFAULT_SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Name of the workspace below the session directory
+const WORKSPACE_DIR: &str = "workspace";
+
+/// Controllers enabled for the task cgroups in the cgroup of a session
+const SESSION_CGROUP_CONTROLLERS: [&str; 4] = ["+cpu", "+memory", "+io", "+pids"];
+
+/// Registry of the open sessions of a runner
+#[derive(Debug, Clone)]
+pub struct SandboxSessions {
+    root: PathBuf,
+    idle_timeout: Duration,
+    max_sessions: Option<usize>,
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+}
+
+#[derive(Debug)]
+struct Session {
+    /// Configuration of the commands, with the workspace and the cgroup of the session
+    config: SandboxConfig,
+    /// Directory of the session below the root
+    dir: PathBuf,
+    created_at_ms: u64,
+    last_used: Instant,
+    last_used_ms: u64,
+    /// Number of commands currently running
+    running: usize,
+    /// Number of commands started so far
+    commands: u64,
+}
+
+/// State of an open session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    pub session_id: String,
+    /// Host directory mounted at `/workspace` in the commands of the session
+    pub workspace_dir: PathBuf,
+    /// Cgroup the task cgroups of the commands are created in, if usage accounting uses cgroups
+    pub cgroup: Option<PathBuf>,
+    pub created_at_ms: u64,
+    pub last_used_ms: u64,
+    /// Number of commands started so far
+    pub commands: u64,
+}
+
+impl SandboxSessions {
+    /// Keep sessions below a root directory with the default idle timeout
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            idle_timeout: DEFAULT_SESSION_IDLE_TIMEOUT,
+            max_sessions: None,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Close sessions after a different idle period
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Keep at most `max_sessions` sessions open
+    pub fn with_max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = Some(max_sessions);
+        self
+    }
+
+    /// Build the settings from environment variables
+    ///
+    /// * `MCP_SANDBOX_SESSION_ROOT` - directory of the session workspaces (e.g.
+    ///   `/var/lib/mcp/sessions`); sessions are disabled when it is not set
+    /// * `MCP_SANDBOX_SESSION_IDLE_TIMEOUT_SECS` - how long a session may be idle before it is closed
+    /// * `MCP_SANDBOX_MAX_SESSIONS` - maximum number of open sessions
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let root = match std::env::var("MCP_SANDBOX_SESSION_ROOT") {
+            Ok(root) if !root.trim().is_empty() => root,
+            _ => return Ok(None),
+        };
+        let mut sessions = Self::new(root.trim());
+
+        if let Ok(value) = std::env::var("MCP_SANDBOX_SESSION_IDLE_TIMEOUT_SECS") {
+            let secs = value.trim().parse::<u64>().map_err(|_| {
+                McpError::InvalidRequest(format!(
+                    "MCP_SANDBOX_SESSION_IDLE_TIMEOUT_SECS must be a number of seconds: '{}'",
+                    value
+                ))
+            })?;
+            sessions.idle_timeout = Duration::from_secs(secs);
+        }
+        if let Ok(value) = std::env::var("MCP_SANDBOX_MAX_SESSIONS") {
+            sessions.max_sessions = Some(value.trim().parse::<usize>().map_err(|_| {
+                McpError::InvalidRequest(format!("MCP_SANDBOX_MAX_SESSIONS must be a number: '{}'", value))
+            })?);
+        }
+        Ok(Some(sessions))
+    }
+
+    /// Directory of the sessions
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Idle period after which sessions are closed
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Open a session whose commands run with a sandbox configuration
+    ///
+    /// The workspace of the session is created and mounted at `/workspace`. With a cgroup
+    /// root, a cgroup of the session is created below it; if that fails, the task cgroups of
+    /// the commands are created in the root as usual.
+    pub fn open(&self, session_id: &str, config: SandboxConfig, cgroup_root: Option<&Path>) -> McpResult<SessionInfo> {
+        if !config.enabled {
+            return Err(McpError::InvalidRequest("Sandbox sessions require an enabled sandbox".to_string()));
+        }
+        if config.backend == SandboxBackend::Firecracker {
+            return Err(McpError::InvalidRequest("Sandbox sessions are not supported by microVMs".to_string()));
+        }
+        let dir = self.session_dir(session_id)?;
+        self.expire_idle();
+
+        let mut sessions = self.lock();
+        if sessions.contains_key(session_id) {
+            return Err(McpError::InvalidRequest(format!("Sandbox session {} is already open", session_id)));
+        }
+        if let Some(max_sessions) = self.max_sessions.filter(|max_sessions| sessions.len() >= *max_sessions) {
+            return Err(McpError::Temporary(format!(
+                "Too many sandbox sessions, at most {} may be open",
+                max_sessions
+            )));
+        }
+
+        fs::create_dir_all(&self.root).map_err(|e| session_error(&self.root, e))?;
+        fs::DirBuilder::new().mode(0o700).create(&dir).map_err(|e| session_error(&dir, e))?;
+        let workspace = dir.join(WORKSPACE_DIR);
+        if let Err(e) = fs::create_dir(&workspace) {
+            let _ = remove_tree(&dir);
+            return Err(session_error(&workspace, e));
+        }
+
+        let mut config = config;
+        config.workspace_dir = Some(workspace);
+        let mount_point = PathBuf::from(WORKSPACE_MOUNT_POINT);
+        if !config.rw_paths.contains(&mount_point) {
+            config.rw_paths.push(mount_point);
+        }
+        config.cgroup_parent = cgroup_root.and_then(|root| create_session_cgroup(root, session_id));
+
+        let now_ms = current_timestamp_ms();
+        let session = Session {
+            config,
+            dir,
+            created_at_ms: now_ms,
+            last_used: Instant::now(),
+            last_used_ms: now_ms,
+            running: 0,
+            commands: 0,
+        };
+        let info = session.info(session_id);
+        sessions.insert(session_id.to_string(), session);
+        info!("Opened sandbox session {}", session_id);
+        Ok(info)
+    }
+
+    /// Start a command in a session, returning the configuration it runs with
+    ///
+    /// The session stays open at least until the returned guard is dropped.
+    pub fn begin(&self, session_id: &str) -> McpResult<(SandboxConfig, SessionGuard)> {
+        self.expire_idle();
+        let mut sessions = self.lock();
+        let session = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| McpError::NotFound(format!("Sandbox session {}", session_id)))?;
+        session.running += 1;
+        session.commands += 1;
+        session.touch();
+        Ok((
+            session.config.clone(),
+            SessionGuard {
+                sessions: self.clone(),
+                session_id: session_id.to_string(),
+            },
+        ))
+    }
+
+    /// State of an open session
+    pub fn info(&self, session_id: &str) -> Option<SessionInfo> {
+        self.lock().get(session_id).map(|session| session.info(session_id))
+    }
+
+    /// State of all open sessions
+    pub fn list(&self) -> Vec<SessionInfo> {
+        let mut sessions: Vec<SessionInfo> =
+            self.lock().iter().map(|(session_id, session)| session.info(session_id)).collect();
+        sessions.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        sessions
+    }
+
+    /// Close a session, removing its workspace and cgroup
+    ///
+    /// A session that is running a command cannot be closed. Returns false if the session
+    /// is not open.
+    pub fn close(&self, session_id: &str) -> McpResult<bool> {
+        let session = {
+            let mut sessions = self.lock();
+            match sessions.get(session_id) {
+                None => return Ok(false),
+                Some(session) if session.running > 0 => {
+                    return Err(McpError::InvalidRequest(format!(
+                        "Sandbox session {} is running a command and cannot be closed",
+                        session_id
+                    )));
+                }
+                Some(_) => sessions.remove(session_id),
+            }
+        };
+        if let Some(session) = session {
+            session.remove();
+            info!("Closed sandbox session {}", session_id);
+        }
+        Ok(true)
+    }
+
+    /// Close the sessions that have been idle for the idle timeout
+    ///
+    /// Returns the IDs of the closed sessions.
+    pub fn expire_idle(&self) -> Vec<String> {
+        let expired: Vec<(String, Session)> = {
+            let mut sessions = self.lock();
+            let ids: Vec<String> = sessions
+                .iter()
+                .filter(|(_, session)| session.running == 0 && session.last_used.elapsed() >= self.idle_timeout)
+                .map(|(session_id, _)| session_id.clone())
+                .collect();
+            ids.into_iter()
+                .filter_map(|session_id| sessions.remove(&session_id).map(|session| (session_id, session)))
+                .collect()
+        };
+        for (session_id, session) in &expired {
+            session.remove();
+            info!("Closed sandbox session {} after {:?} without commands", session_id, self.idle_timeout);
+        }
+        expired.into_iter().map(|(session_id, _)| session_id).collect()
+    }
+
+    /// Directory of a session, rejecting IDs that are not a single path component
+    fn session_dir(&self, session_id: &str) -> McpResult<PathBuf> {
+        let valid = !session_id.is_empty()
+            && session_id != "."
+            && session_id != ".."
+            && session_id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            return Err(McpError::InvalidRequest(format!("Invalid sandbox session ID: '{}'", session_id)));
+        }
+        Ok(self.root.join(session_id))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Session>> {
+        self.sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Session {
+    fn info(&self, session_id: &str) -> SessionInfo {
+        SessionInfo {
+            session_id: session_id.to_string(),
+            workspace_dir: self.dir.join(WORKSPACE_DIR),
+            cgroup: self.config.cgroup_parent.clone(),
+            created_at_ms: self.created_at_ms,
+            last_used_ms: self.last_used_ms,
+            commands: self.commands,
+        }
+    }
+
+    fn touch(&mut self) {
+        self.last_used = Instant::now();
+        self.last_used_ms = current_timestamp_ms();
+    }
+
+    /// Remove the directory and the cgroup of a closed session
+    fn remove(&self) {
+        // The commands may have left directories without write permission
+        if let Err(e) = remove_tree(&self.dir) {
+            warn!("Failed to remove sandbox session directory {}: {}", self.dir.display(), e);
+        }
+        if let Some(cgroup) = &self.config.cgroup_parent {
+            // Fails while processes of a command (e.g. after a timeout) are still alive
+            if let Err(e) = fs::remove_dir(cgroup) {
+                debug!("Failed to remove session cgroup {}: {}", cgroup.display(), e);
+            }
+        }
+    }
+}
+
+/// Command running in a session; the session becomes idle when all guards are dropped
+#[derive(Debug)]
+pub struct SessionGuard {
+    sessions: SandboxSessions,
+    session_id: String,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        if let Some(session) = self.sessions.lock().get_mut(&self.session_id) {
+            session.running = session.running.saturating_sub(1);
+            session.touch();
+        }
+    }
+}
+
+/// Create the cgroup of a session below the cgroup root, `None` if that fails
+fn create_session_cgroup(cgroup_root: &Path, session_id: &str) -> Option<PathBuf> {
+    let cgroup = cgroup_root.join(format!("mcp-session-{}", session_id));
+    if let Err(e) = fs::create_dir(&cgroup) {
+        warn!("Failed to create session cgroup {}, using the cgroup root: {}", cgroup.display(), e);
+        return None;
+    }
+    // Controllers not enabled in the root are not available to the task cgroups
+    for controller in SESSION_CGROUP_CONTROLLERS {
+        let written = OpenOptions::new()
+            .write(true)
+            .open(cgroup.join("cgroup.subtree_control"))
+            .and_then(|mut file| file.write_all(controller.as_bytes()));
+        if let Err(e) = written {
+            debug!("Failed to enable {} for session cgroup {}: {}", controller, cgroup.display(), e);
+        }
+    }
+    Some(cgroup)
+}
+
+fn session_error(path: &Path, e: std::io::Error) -> McpError {
+    McpError::Sandbox(format!("Sandbox session error at {}: {}", path.display(), e))
+}
@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{ExecutionRequest, SandboxBackend, SandboxConfig};
+    use crate::runner::SandboxRunner;
+    use crate::session::SandboxSessions;
+    use crate::staging::StagedFile;
+    use mcp_common::error::McpError;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    // Test for opening and closing sessions
+    #[test]
+    fn test_open_and_close() {
+        let dir = tempfile::tempdir().unwrap();
+        let sessions = SandboxSessions::new(dir.path().join("sessions")).with_max_sessions(2);
+
+        let info = sessions.open("session-1", SandboxConfig::default(), None).unwrap();
+        assert_eq!(info.workspace_dir, dir.path().join("sessions/session-1/workspace"));
+        assert!(info.workspace_dir.is_dir());
+        assert_eq!((info.cgroup.clone(), info.commands), (None, 0));
+        assert_eq!(sessions.info("session-1"), Some(info.clone()));
+
+        // The commands run with the configuration of the session and its workspace at /workspace
+        let (config, guard) = sessions.begin("session-1").unwrap();
+        assert_eq!(config.workspace_dir, Some(info.workspace_dir.clone()));
+        assert!(config.rw_paths.contains(&PathBuf::from("/workspace")));
+        assert_eq!(sessions.info("session-1").unwrap().commands, 1);
+        // A session that is running a command stays open
+        assert!(matches!(sessions.close("session-1"), Err(McpError::InvalidRequest(_))));
+        drop(guard);
+
+        for (session_id, config) in [
+            // Already open
+            ("session-1", SandboxConfig::default()),
+            ("../escape", SandboxConfig::default()),
+            (
+                "session-2",
+                SandboxConfig {
+                    enabled: false,
+                    ..Default::default()
+                },
+            ),
+            (
+                "session-2",
+                SandboxConfig {
+                    backend: SandboxBackend::Firecracker,
+                    ..Default::default()
+                },
+            ),
+        ] {
+            match sessions.open(session_id, config, None) {
+                Err(McpError::InvalidRequest(_)) => {}
+                other => panic!("unexpected result for {}: {:?}", session_id, other),
+            }
+        }
+        sessions.open("session-2", SandboxConfig::default(), None).unwrap();
+        assert!(matches!(sessions.open("session-3", SandboxConfig::default(), None), Err(McpError::Temporary(_))));
+        assert_eq!(sessions.list().len(), 2);
+
+        assert!(sessions.close("session-1").unwrap());
+        assert!(!info.workspace_dir.exists());
+        assert!(!sessions.close("session-1").unwrap());
+        assert!(matches!(sessions.begin("session-1"), Err(McpError::NotFound(_))));
+        assert_eq!(sessions.list().len(), 1);
+    }
+
+    // Test for closing sessions after the idle timeout
+    #[test]
+    fn test_idle_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let idle_timeout = Duration::from_millis(200);
+        let sessions = SandboxSessions::new(dir.path()).with_idle_timeout(idle_timeout);
+        let cgroup_root = dir.path().join("cgroup");
+        std::fs::create_dir(&cgroup_root).unwrap();
+
+        let info = sessions.open("session-1", SandboxConfig::default(), Some(&cgroup_root)).unwrap();
+        let cgroup = info.cgroup.clone().unwrap();
+        assert_eq!(cgroup, cgroup_root.join("mcp-session-session-1"));
+        let (config, guard) = sessions.begin("session-1").unwrap();
+        assert_eq!(config.cgroup_parent, Some(cgroup.clone()));
+        // Sessions running a command are not idle, and the idle period starts when it ends
+        std::thread::sleep(idle_timeout * 2);
+        assert!(sessions.expire_idle().is_empty());
+        drop(guard);
+        assert!(sessions.expire_idle().is_empty());
+
+        std::thread::sleep(idle_timeout * 2);
+        assert_eq!(sessions.expire_idle(), vec!["session-1".to_string()]);
+        assert!(sessions.info("session-1").is_none());
+        assert!(!info.workspace_dir.exists());
+        assert!(!cgroup.exists());
+    }
+
+    // Test for sharing the workspace between the commands of a session
+    #[tokio::test]
+    async fn test_run_in_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = SandboxRunner::new().with_sandbox_sessions(SandboxSessions::new(dir.path()));
+        let request = |script: &str, input_files: Vec<StagedFile>| ExecutionRequest {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            // Replaced by the configuration of the session
+            sandbox_config: SandboxConfig {
+                enabled: false,
+                ..Default::default()
+            },
+            input_files,
+            diff_workspace: false,
+        };
+
+        // Without bubblewrap the commands run on the host and see the workspace at its host path
+        let info = runner.open_session("session-1", SandboxConfig::default()).unwrap();
+        if which::which("bwrap").is_err() {
+            let workspace = info.workspace_dir.display().to_string();
+            let first = request(
+                &format!("cat {0}/input.txt > {0}/state && echo second >> {0}/state", workspace),
+                vec![StagedFile::from_bytes("input.txt", "first\n")],
+            );
+            runner.run_in_session("session-1", first, None, None).await.unwrap();
+            let result = runner
+                .run_in_session("session-1", request(&format!("cat {}/state", workspace), Vec::new()), None, None)
+                .await
+                .unwrap();
+            assert_eq!(result.stdout, "first\nsecond\n");
+            assert_eq!(runner.sessions().unwrap().info("session-1").unwrap().commands, 2);
+        }
+
+        assert!(runner.close_session("session-1").unwrap());
+        assert!(matches!(
+            runner.run_in_session("session-1", request("true", Vec::new()), None, None).await,
+            Err(McpError::NotFound(_))
+        ));
+        // Sessions must be configured
+        if std::env::var("MCP_SANDBOX_SESSION_ROOT").is_err() {
+            assert!(SandboxRunner::new().open_session("session-2", SandboxConfig::default()).is_err());
+        }
+    }
+}
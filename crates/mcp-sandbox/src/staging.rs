@@ -0,0 +1,235 @@
+//! Staging of input files into the task workspace
+//!
+//! The `input_files` of an [`ExecutionRequest`](crate::models::ExecutionRequest) are written
+//! into the workspace of the task (see [`crate::workspace`]) after it has been provisioned and
+//! before the command starts, so that clients can hand inputs to a command without a
+//! writable mount shared with the gateway. A file is given either as bytes or as the
+//! SHA-256 digest of a blob in a [`ContentStore`]; blobs are verified against their digest
+//! while they are copied, so a tampered store entry fails the execution.
+//!
+//! Paths are relative to the workspace and must not contain `.` or `..` components. Files
+//! are created exclusively, without following symbolic links, so two entries for the same
+//! path fail the execution as well.
+
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::hex;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Component, Path, PathBuf};
+use tracing::debug;
+
+/// File written into the task workspace before the command starts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedFile {
+    /// Path relative to the workspace
+    pub path: PathBuf,
+    /// Content of the file
+    pub content: StagedContent,
+    /// Whether the file is executable (mode `0755` instead of `0644`)
+    pub executable: bool,
+}
+
+impl StagedFile {
+    /// File with the given bytes
+    pub fn from_bytes(path: impl Into<PathBuf>, bytes: impl Into<Vec<u8>>) -> Self {
+        Self {
+            path: path.into(),
+            content: StagedContent::Bytes(bytes.into()),
+            executable: false,
+        }
+    }
+
+    /// File with the content of a blob of the content store
+    pub fn from_digest(path: impl Into<PathBuf>, digest: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            content: StagedContent::Digest(digest.into()),
+            executable: false,
+        }
+    }
+
+    /// Make the file executable
+    pub fn executable(mut self) -> Self {
+        self.executable = true;
+        self
+    }
+}
+
+/// Content of a staged file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StagedContent {
+    /// Literal bytes
+    Bytes(Vec<u8>),
+    /// Hex SHA-256 digest of a blob in the content store
+    Digest(String),
+}
+
+/// Content-addressed store of blobs, kept as `<root>/sha256/<hex digest>`
+#[derive(Debug, Clone)]
+pub struct ContentStore {
+    root: PathBuf,
+}
+
+impl ContentStore {
+    /// Store blobs below a root directory
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Build the store from environment variables
+    ///
+    /// * `MCP_CONTENT_STORE_ROOT` - directory of the blobs; the store is disabled when it is
+    ///   not set
+    pub fn from_env() -> Option<Self> {
+        match std::env::var("MCP_CONTENT_STORE_ROOT") {
+            Ok(root) if !root.trim().is_empty() => Some(Self::new(root.trim())),
+            _ => None,
+        }
+    }
+
+    /// Directory of the blobs
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Add a blob and return its digest (adding the same content again is a no-op)
+    pub fn put(&self, content: &[u8]) -> McpResult<String> {
+        let digest = sha256_hex(content);
+        let path = self.path(&digest)?;
+        if path.is_file() {
+            return Ok(digest);
+        }
+        let dir = self.root.join("sha256");
+        fs::create_dir_all(&dir).map_err(|e| store_error(&dir, e))?;
+        // Written under a temporary name, so that readers never see a partial blob
+        let temporary = dir.join(format!(".{}.{}", digest, std::process::id()));
+        fs::write(&temporary, content).map_err(|e| store_error(&temporary, e))?;
+        fs::rename(&temporary, &path).map_err(|e| store_error(&path, e))?;
+        debug!("Stored blob {} ({} bytes)", digest, content.len());
+        Ok(digest)
+    }
+
+    /// Whether the store has a blob
+    pub fn contains(&self, digest: &str) -> bool {
+        self.path(digest).is_ok_and(|path| path.is_file())
+    }
+
+    /// Path of a blob
+    fn path(&self, digest: &str) -> McpResult<PathBuf> {
+        if digest.len() != 64 || !digest.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(McpError::InvalidRequest(format!("Invalid SHA-256 digest: '{}'", digest)));
+        }
+        Ok(self.root.join("sha256").join(digest.to_ascii_lowercase()))
+    }
+
+    /// Copy a blob to a file, verifying its digest
+    fn copy_to(&self, digest: &str, file: &mut File) -> McpResult<u64> {
+        let path = self.path(digest)?;
+        let mut blob = File::open(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => McpError::NotFound(format!("Blob {} is not in the content store", digest)),
+            _ => store_error(&path, e),
+        })?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 64 * 1024];
+        let mut copied = 0;
+        loop {
+            let read = blob.read(&mut buffer).map_err(|e| store_error(&path, e))?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            file.write_all(&buffer[..read]).map_err(|e| staging_error(&path, e))?;
+            copied += read as u64;
+        }
+        if !hex(&hasher.finalize()).eq_ignore_ascii_case(digest) {
+            return Err(McpError::Sandbox(format!("Blob {} does not match its digest", digest)));
+        }
+        Ok(copied)
+    }
+}
+
+/// Write files into a workspace
+///
+/// Returns the number of bytes written. Blobs are read from `store`; staging a blob without a
+/// store fails.
+pub fn stage_files(workspace: &Path, files: &[StagedFile], store: Option<&ContentStore>) -> McpResult<u64> {
+    let mut staged = 0;
+    for staged_file in files {
+        let relative = &staged_file.path;
+        let valid = relative.components().next().is_some()
+            && relative.components().all(|component| matches!(component, Component::Normal(_)));
+        if !valid {
+            return Err(McpError::InvalidRequest(format!(
+                "Staged file path must be relative to the workspace: {}",
+                relative.display()
+            )));
+        }
+
+        let path = workspace.join(relative);
+        if let Some(parent) = path.parent() {
+            create_dirs(workspace, parent)?;
+        }
+        let mode = if staged_file.executable { 0o755 } else { 0o644 };
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(mode)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(&path)
+            .map_err(|e| staging_error(relative, e))?;
+        let written = match &staged_file.content {
+            StagedContent::Bytes(bytes) => file
+                .write_all(bytes)
+                .map(|_| bytes.len() as u64)
+                .map_err(|e| staging_error(relative, e)),
+            StagedContent::Digest(digest) => match store {
+                Some(store) => store.copy_to(digest, &mut file),
+                None => Err(McpError::InvalidRequest(format!(
+                    "No content store is configured for blob {}",
+                    digest
+                ))),
+            },
+        };
+        // A file without its full content is not left behind
+        staged += written.inspect_err(|_| {
+            let _ = fs::remove_file(&path);
+        })?;
+    }
+    if !files.is_empty() {
+        debug!("Staged {} files ({} bytes) into {}", files.len(), staged, workspace.display());
+    }
+    Ok(staged)
+}
+
+/// Create the directories below the workspace leading to a staged file, refusing symbolic links
+fn create_dirs(workspace: &Path, dir: &Path) -> McpResult<()> {
+    let mut current = workspace.to_path_buf();
+    for component in dir.strip_prefix(workspace).unwrap_or(dir).components() {
+        current.push(component);
+        match fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.is_dir() => continue,
+            Ok(_) => {
+                return Err(McpError::InvalidRequest(format!(
+                    "Staged file path crosses a file or link: {}",
+                    current.display()
+                )))
+            }
+            Err(_) => fs::create_dir(&current).map_err(|e| staging_error(&current, e))?,
+        }
+    }
+    Ok(())
+}
+
+fn sha256_hex(content: &[u8]) -> String {
+    hex(&Sha256::digest(content))
+}
+
+fn store_error(path: &Path, e: std::io::Error) -> McpError {
+    McpError::Internal(format!("Content store error at {}: {}", path.display(), e))
+}
+
+fn staging_error(path: &Path, e: std::io::Error) -> McpError {
+    McpError::Sandbox(format!("Failed to stage input file {}: {}", path.display(), e))
+}
@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{ExecutionRequest, SandboxConfig};
+    use crate::runner::SandboxRunner;
+    use crate::staging::{stage_files, ContentStore, StagedFile};
+    use crate::workspace::TaskWorkspaces;
+    use mcp_common::error::McpError;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    // Test for staging files from bytes and from the content store
+    #[test]
+    fn test_stage_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ContentStore::new(dir.path().join("store"));
+        let digest = store.put(b"blob content").unwrap();
+        assert_eq!(digest.len(), 64);
+        assert!(store.contains(&digest));
+        // Adding the same content again returns the same digest
+        assert_eq!(store.put(b"blob content").unwrap(), digest);
+
+        let workspace = dir.path().join("workspace");
+        fs::create_dir(&workspace).unwrap();
+        let files = vec![
+            StagedFile::from_bytes("input.txt", "hello"),
+            StagedFile::from_bytes("bin/run.sh", "#!/bin/sh\n").executable(),
+            StagedFile::from_digest("data/blob", digest.to_ascii_uppercase()),
+        ];
+        assert_eq!(stage_files(&workspace, &files, Some(&store)).unwrap(), 5 + 10 + 12);
+        assert_eq!(fs::read_to_string(workspace.join("input.txt")).unwrap(), "hello");
+        assert_eq!(fs::read_to_string(workspace.join("data/blob")).unwrap(), "blob content");
+        let mode = |path: &str| fs::metadata(workspace.join(path)).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode("bin/run.sh"), 0o755);
+        assert_eq!(mode("input.txt") & 0o111, 0);
+
+        // Staging the same path twice fails
+        assert!(stage_files(&workspace, &files[..1], Some(&store)).is_err());
+    }
+
+    // Test for rejecting staged files that escape the workspace or have no valid content
+    #[test]
+    fn test_stage_invalid_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ContentStore::new(dir.path().join("store"));
+        let workspace = dir.path().join("workspace");
+        fs::create_dir(&workspace).unwrap();
+        std::os::unix::fs::symlink(dir.path(), workspace.join("link")).unwrap();
+
+        for path in ["../escape", "/etc/escape", "./input", "", "link/escape"] {
+            match stage_files(&workspace, &[StagedFile::from_bytes(path, "x")], Some(&store)) {
+                Err(McpError::InvalidRequest(_)) => {}
+                other => panic!("unexpected result for {:?}: {:?}", path, other),
+            }
+        }
+        assert!(!dir.path().join("escape").exists());
+
+        // Blobs need a store that has them
+        let digest = store.put(b"original").unwrap();
+        let blob = [StagedFile::from_digest("blob", digest.clone())];
+        match stage_files(&workspace, &blob, None) {
+            Err(McpError::InvalidRequest(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(!workspace.join("blob").exists());
+        match stage_files(&workspace, &[StagedFile::from_digest("missing", "0".repeat(64))], Some(&store)) {
+            Err(McpError::NotFound(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(stage_files(&workspace, &[StagedFile::from_digest("invalid", "abc")], Some(&store)).is_err());
+
+        // A blob whose content no longer matches its digest fails
+        fs::write(store.root().join("sha256").join(&digest), "tampered").unwrap();
+        match stage_files(&workspace, &blob, Some(&store)) {
+            Err(error @ McpError::Sandbox(_)) => assert!(error.to_string().contains("does not match")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    // Test for running a task with input files staged into its workspace
+    #[tokio::test]
+    async fn test_run_task_with_input_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspaces = TaskWorkspaces::new(dir.path().join("workspaces"));
+        let store = ContentStore::new(dir.path().join("store"));
+        let digest = store.put(b"from the store").unwrap();
+        let runner = SandboxRunner::new()
+            .with_task_workspaces(workspaces.clone())
+            .with_content_store(store);
+        let _guard = runner.processes().register("task-1").unwrap();
+
+        // Without bubblewrap the command runs on the host and sees the workspace at its host path
+        let workspace = workspaces.path("task-1").unwrap();
+        let request = |sandbox_config: SandboxConfig| ExecutionRequest {
+            command: "cat".to_string(),
+            args: vec![
+                workspace.join("input.txt").display().to_string(),
+                workspace.join("data/blob").display().to_string(),
+            ],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config,
+            input_files: vec![
+                StagedFile::from_bytes("input.txt", "inline "),
+                StagedFile::from_digest("data/blob", digest.clone()),
+            ],
+            diff_workspace: false,
+        };
+        if which::which("bwrap").is_err() {
+            let result = runner.run_task(request(SandboxConfig::default()), Some("task-1"), None).await.unwrap();
+            assert_eq!(result.stdout, "inline from the store");
+        }
+
+        // Input files need the workspace of a sandboxed task
+        let unsandboxed = SandboxConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        match runner.run_task(request(unsandboxed), Some("task-2"), None).await {
+            Err(McpError::InvalidRequest(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
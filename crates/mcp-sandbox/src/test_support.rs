@@ -0,0 +1,11 @@
+//! Helpers shared by the unit tests
+
+/// Arguments of a prepared command, lossily converted to strings
+pub(crate) fn args_of(cmd: &tokio::process::Command) -> Vec<String> {
+    cmd.as_std().get_args().map(|arg| arg.to_string_lossy().to_string()).collect()
+}
+
+/// Whether `expected` appears in `args` as a contiguous sequence
+pub(crate) fn contains(args: &[String], expected: &[&str]) -> bool {
+    args.windows(expected.len()).any(|window| window == expected)
+}
@@ -0,0 +1,431 @@
+//! Resource usage accounting
+//!
+//! When a delegated cgroup v2 directory is configured (`MCP_SANDBOX_CGROUP_ROOT`), each
+//! command runs in its own child cgroup, which it joins before `exec`. After the command
+//! exits, `cpu.stat`, `memory.peak` and `io.stat` of that cgroup give the usage of the
+//! command and all of its descendants, and the cgroup is removed.
+//!
+//! Values the cgroup cannot provide (no cgroup configured, or the memory/io controller is
+//! not enabled for it) are measured with `getrusage(RUSAGE_CHILDREN)` around the command.
+//! That fallback is approximate: children of concurrent tasks that exit in the meantime are
+//! included, and the memory peak is the largest peak of any child of the gateway so far.
+//!
+//! A CPU time limit (`cpu_time_limit` of the resource limits), independent of the
+//! wall-clock timeout, is enforced with `RLIMIT_CPU` on every process of the command (`SIGXCPU`
+//! at the limit, `SIGKILL` a second later). With a task cgroup, the CPU time of all processes
+//! together is polled as well, so that a command cannot get around the limit by forking.
+//! A command that exceeded the limit fails with a sandbox error whose code is
+//! [`SANDBOX_CPU_TIME_EXCEEDED`](mcp_common::error::error_code::SANDBOX_CPU_TIME_EXCEEDED),
+//! unlike one that exceeded the wall-clock timeout.
+//!
+//! Processes of a task cgroup killed by the OOM killer are counted in its `memory.events`
+//! (`oom_kill`). A command that failed after such a kill fails with a sandbox error whose
+//! code is [`SANDBOX_OUT_OF_MEMORY`](mcp_common::error::error_code::SANDBOX_OUT_OF_MEMORY),
+//! so that it can be told apart from a command that failed on its own.
+//!
+//! While a command runs, its task cgroup can be sampled for live usage (cumulative CPU time
+//! and IO, current and peak memory), e.g. for dashboards of running tasks; see
+//! [`sample_cgroup`]. Commands without a task cgroup cannot be sampled.
+
+use crate::models::{ResourceSample, ResourceUsage};
+use crate::quota::DiskQuota;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::current_timestamp_ms;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Interval between two measurements of the CPU time of a running command
+pub const CPU_TIME_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Sequence number for unique cgroup names
+static NEXT_CGROUP: AtomicU64 = AtomicU64::new(0);
+
+/// Resource usage accounting settings
+#[derive(Debug, Clone, Default)]
+pub struct UsageAccounting {
+    /// Delegated cgroup v2 directory below which task cgroups are created
+    cgroup_root: Option<PathBuf>,
+}
+
+impl UsageAccounting {
+    /// Accounting with `getrusage` only
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create task cgroups below a delegated cgroup v2 directory
+    pub fn with_cgroup_root(mut self, cgroup_root: impl Into<PathBuf>) -> Self {
+        self.cgroup_root = Some(cgroup_root.into());
+        self
+    }
+
+    /// Load the settings from `MCP_SANDBOX_CGROUP_ROOT`
+    ///
+    /// The directory must be a cgroup v2 directory the gateway may create cgroups in.
+    pub fn from_env() -> McpResult<Self> {
+        let Ok(root) = std::env::var("MCP_SANDBOX_CGROUP_ROOT") else {
+            return Ok(Self::new());
+        };
+        let root = PathBuf::from(root);
+        if !root.join("cgroup.controllers").is_file() {
+            return Err(McpError::Internal(format!(
+                "MCP_SANDBOX_CGROUP_ROOT {} is not a cgroup v2 directory",
+                root.display()
+            )));
+        }
+        Ok(Self::new().with_cgroup_root(root))
+    }
+
+    /// Delegated cgroup v2 directory, if configured
+    pub fn cgroup_root(&self) -> Option<&Path> {
+        self.cgroup_root.as_deref()
+    }
+
+    /// Start measuring one command
+    ///
+    /// If the task cgroup cannot be created, the command is measured with `getrusage` only.
+    pub fn start(&self) -> UsageMeter {
+        self.start_in(None)
+    }
+
+    /// Start measuring one command whose task cgroup is created in `parent`, a cgroup below the
+    /// cgroup root, instead of the root itself
+    pub fn start_in(&self, parent: Option<&Path>) -> UsageMeter {
+        let cgroup = self.cgroup_root.as_deref().and_then(|root| {
+            let root = parent.filter(|parent| parent.starts_with(root)).unwrap_or(root);
+            TaskCgroup::create(root)
+                .map_err(|e| warn!("Failed to create task cgroup, falling back to getrusage: {}", e))
+                .ok()
+        });
+        UsageMeter {
+            cgroup,
+            before: children_rusage(),
+            disk_quota: None,
+            cpu_time_limit: None,
+        }
+    }
+}
+
+/// Measurement of one command
+#[derive(Debug)]
+pub struct UsageMeter {
+    cgroup: Option<TaskCgroup>,
+    before: Option<ChildrenUsage>,
+    disk_quota: Option<DiskQuota>,
+    cpu_time_limit: Option<u64>,
+}
+
+impl UsageMeter {
+    /// Enforce a disk quota on the command
+    pub fn with_disk_quota(mut self, disk_quota: Option<DiskQuota>) -> Self {
+        self.disk_quota = disk_quota;
+        self
+    }
+
+    /// Disk quota of the command
+    pub fn disk_quota(&self) -> Option<&DiskQuota> {
+        self.disk_quota.as_ref()
+    }
+
+    /// Limit the CPU time of the command (seconds)
+    pub fn with_cpu_time_limit(mut self, cpu_time_limit: Option<u64>) -> Self {
+        self.cpu_time_limit = cpu_time_limit;
+        self
+    }
+
+    /// CPU time limit of the command (seconds)
+    pub fn cpu_time_limit(&self) -> Option<u64> {
+        self.cpu_time_limit
+    }
+
+    /// Whether the command runs in a task cgroup
+    pub fn has_cgroup(&self) -> bool {
+        self.cgroup.is_some()
+    }
+
+    /// Directory of the task cgroup, if the command runs in one
+    pub fn cgroup_path(&self) -> Option<&Path> {
+        self.cgroup.as_ref().map(|cgroup| cgroup.path.as_path())
+    }
+
+    /// ID of the task cgroup (the inode number of its directory), as eBPF programs see it
+    pub fn cgroup_id(&self) -> Option<u64> {
+        let cgroup = self.cgroup.as_ref()?;
+        fs::metadata(&cgroup.path).ok().map(|metadata| metadata.ino())
+    }
+
+    /// Whether the processes of the task cgroup together used more CPU time than the limit
+    ///
+    /// Always false without a task cgroup or a limit.
+    pub fn is_cpu_time_exceeded(&self) -> bool {
+        let (Some(cgroup), Some(limit)) = (&self.cgroup, self.cpu_time_limit) else {
+            return false;
+        };
+        let cpu_usec = fs::read_to_string(cgroup.path.join("cpu.stat"))
+            .ok()
+            .and_then(|content| parse_cpu_stat(&content));
+        cpu_usec.is_some_and(|usec| usec >= limit.saturating_mul(1_000_000))
+    }
+
+    /// Number of processes of the task cgroup killed by the OOM killer so far
+    ///
+    /// Always 0 without a task cgroup or when the memory controller is not enabled for it.
+    pub fn oom_kills(&self) -> u64 {
+        let Some(cgroup) = &self.cgroup else {
+            return 0;
+        };
+        fs::read_to_string(cgroup.path.join("memory.events"))
+            .ok()
+            .and_then(|content| parse_memory_events(&content))
+            .unwrap_or_default()
+    }
+
+    /// Make the command join the task cgroup and limit its file size and CPU time before it executes
+    pub fn attach(&self, cmd: &mut tokio::process::Command) -> McpResult<()> {
+        if let Some(cpu_time_limit) = self.cpu_time_limit {
+            // SIGXCPU at the limit, SIGKILL for processes that ignore it
+            let limit = libc::rlimit {
+                rlim_cur: cpu_time_limit as libc::rlim_t,
+                rlim_max: cpu_time_limit.saturating_add(1) as libc::rlim_t,
+            };
+            // SAFETY: setrlimit is async-signal-safe and the closure does not allocate
+            unsafe {
+                cmd.pre_exec(move || {
+                    if libc::setrlimit(libc::RLIMIT_CPU, &limit) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+        if let Some(disk_quota) = &self.disk_quota {
+            // One byte beyond the quota, so that a write stopped by the limit also exceeds the quota
+            let size = disk_quota.limit().saturating_add(1) as libc::rlim_t;
+            let limit = libc::rlimit {
+                rlim_cur: size,
+                rlim_max: size,
+            };
+            // SAFETY: setrlimit is async-signal-safe and the closure does not allocate
+            unsafe {
+                cmd.pre_exec(move || {
+                    if libc::setrlimit(libc::RLIMIT_FSIZE, &limit) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+        let Some(cgroup) = &self.cgroup else {
+            return Ok(());
+        };
+        let procs = cgroup
+            .procs
+            .try_clone()
+            .map_err(|e| McpError::Sandbox(format!("Failed to prepare task cgroup: {}", e)))?;
+        // SAFETY: the closure only writes to an already open file, which does not allocate
+        unsafe {
+            cmd.pre_exec(move || (&procs).write_all(b"0"));
+        }
+        Ok(())
+    }
+
+    /// Usage of the command, once it has exited and been waited for
+    pub fn finish(self) -> ResourceUsage {
+        let cgroup_usage = self.cgroup.as_ref().map(|cgroup| read_cgroup_usage(&cgroup.path)).unwrap_or_default();
+
+        let rusage = match (self.before, children_rusage()) {
+            (Some(before), Some(after)) => Some(RusageDelta::between(&before, &after)),
+            _ => None,
+        };
+        ResourceUsage {
+            cpu_time_ms: cgroup_usage
+                .cpu_usec
+                .map(|usec| usec / 1000)
+                .or(rusage.as_ref().map(|delta| delta.cpu_time_ms))
+                .unwrap_or_default(),
+            max_memory_kb: cgroup_usage
+                .memory_peak_bytes
+                .map(|bytes| bytes.div_ceil(1024))
+                .or(rusage.as_ref().map(|delta| delta.max_memory_kb))
+                .unwrap_or_default(),
+            io_read_bytes: cgroup_usage
+                .io_bytes
+                .map(|(read, _)| read)
+                .or(rusage.as_ref().map(|delta| delta.io_read_bytes))
+                .unwrap_or_default(),
+            io_write_bytes: cgroup_usage
+                .io_bytes
+                .map(|(_, write)| write)
+                .or(rusage.as_ref().map(|delta| delta.io_write_bytes))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Cgroup of one command, removed when dropped
+#[derive(Debug)]
+struct TaskCgroup {
+    path: PathBuf,
+    procs: File,
+}
+
+impl TaskCgroup {
+    fn create(root: &Path) -> std::io::Result<Self> {
+        let name = format!("mcp-task-{}-{}", std::process::id(), NEXT_CGROUP.fetch_add(1, Ordering::Relaxed));
+        let path = root.join(name);
+        fs::create_dir(&path)?;
+        match OpenOptions::new().write(true).open(path.join("cgroup.procs")) {
+            Ok(procs) => Ok(Self { path, procs }),
+            Err(e) => {
+                let _ = fs::remove_dir(&path);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Drop for TaskCgroup {
+    fn drop(&mut self) {
+        // Fails while processes of the command (e.g. after a timeout) are still alive
+        if let Err(e) = fs::remove_dir(&self.path) {
+            debug!("Failed to remove task cgroup {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Usage read from a cgroup (`None` for files the cgroup does not have)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct CgroupUsage {
+    pub(crate) cpu_usec: Option<u64>,
+    pub(crate) memory_peak_bytes: Option<u64>,
+    pub(crate) io_bytes: Option<(u64, u64)>,
+}
+
+/// Read the usage files of a cgroup v2 directory
+pub(crate) fn read_cgroup_usage(path: &Path) -> CgroupUsage {
+    let read = |name: &str| fs::read_to_string(path.join(name)).ok();
+    CgroupUsage {
+        cpu_usec: read("cpu.stat").and_then(|content| parse_cpu_stat(&content)),
+        memory_peak_bytes: read("memory.peak").and_then(|content| content.trim().parse().ok()),
+        io_bytes: read("io.stat").map(|content| parse_io_stat(&content)),
+    }
+}
+
+/// Sample the usage of a running command from its task cgroup
+///
+/// Returns `None` once the cgroup is gone; values of controllers that are not enabled are 0.
+pub fn sample_cgroup(path: &Path) -> Option<ResourceSample> {
+    if !path.is_dir() {
+        return None;
+    }
+    let usage = read_cgroup_usage(path);
+    let memory_current_bytes = fs::read_to_string(path.join("memory.current"))
+        .ok()
+        .and_then(|content| content.trim().parse::<u64>().ok());
+    let (io_read_bytes, io_write_bytes) = usage.io_bytes.unwrap_or_default();
+    Some(ResourceSample {
+        timestamp_ms: current_timestamp_ms(),
+        cpu_time_ms: usage.cpu_usec.unwrap_or_default() / 1000,
+        memory_kb: memory_current_bytes.unwrap_or_default().div_ceil(1024),
+        max_memory_kb: usage.memory_peak_bytes.unwrap_or_default().div_ceil(1024),
+        io_read_bytes,
+        io_write_bytes,
+    })
+}
+
+/// `usage_usec` of `cpu.stat`
+pub(crate) fn parse_cpu_stat(content: &str) -> Option<u64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("usage_usec "))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// `oom_kill` of `memory.events`
+pub(crate) fn parse_memory_events(content: &str) -> Option<u64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Bytes read and written over all devices of `io.stat`
+pub(crate) fn parse_io_stat(content: &str) -> (u64, u64) {
+    let mut total = (0, 0);
+    for field in content.split_whitespace() {
+        let value = |prefix: &str| field.strip_prefix(prefix).and_then(|value| value.parse::<u64>().ok());
+        if let Some(bytes) = value("rbytes=") {
+            total.0 += bytes;
+        } else if let Some(bytes) = value("wbytes=") {
+            total.1 += bytes;
+        }
+    }
+    total
+}
+
+/// Resource usage of the waited-for children of the gateway
+#[derive(Debug, Clone, Copy)]
+struct ChildrenUsage {
+    cpu_us: u64,
+    max_rss_kb: u64,
+    in_blocks: u64,
+    out_blocks: u64,
+}
+
+fn children_rusage() -> Option<ChildrenUsage> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+    // SAFETY: getrusage initializes the structure when it succeeds
+    if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, usage.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // SAFETY: initialized by the successful call above
+    let usage = unsafe { usage.assume_init() };
+    let micros = |time: libc::timeval| time.tv_sec.max(0) as u64 * 1_000_000 + time.tv_usec.max(0) as u64;
+    Some(ChildrenUsage {
+        cpu_us: micros(usage.ru_utime) + micros(usage.ru_stime),
+        max_rss_kb: usage.ru_maxrss.max(0) as u64,
+        in_blocks: usage.ru_inblock.max(0) as u64,
+        out_blocks: usage.ru_oublock.max(0) as u64,
+    })
+}
+
+/// Usage of the children waited for between two `getrusage` calls
+#[derive(Debug)]
+struct RusageDelta {
+    cpu_time_ms: u64,
+    max_memory_kb: u64,
+    io_read_bytes: u64,
+    io_write_bytes: u64,
+}
+
+impl RusageDelta {
+    fn between(before: &ChildrenUsage, after: &ChildrenUsage) -> Self {
+        // Block counts are in units of 512 bytes
+        Self {
+            cpu_time_ms: after.cpu_us.saturating_sub(before.cpu_us) / 1000,
+            max_memory_kb: after.max_rss_kb,
+            io_read_bytes: after.in_blocks.saturating_sub(before.in_blocks) * 512,
+            io_write_bytes: after.out_blocks.saturating_sub(before.out_blocks) * 512,
+        }
+    }
+}
+
+/// Error of a command that exceeded its CPU time limit (seconds)
+pub fn cpu_time_exceeded_error(cpu_time_limit: u64) -> McpError {
+    McpError::Sandbox(format!("CPU time limit of {} seconds exceeded", cpu_time_limit))
+}
+
+/// Error of a command that failed after processes of it were killed by the OOM killer
+///
+/// The number of killed processes is included when it is known.
+pub fn out_of_memory_error(oom_kills: Option<u64>) -> McpError {
+    McpError::Sandbox(match oom_kills {
+        Some(oom_kills) => format!("Command ran out of memory ({} processes killed by the OOM killer)", oom_kills),
+        None => "Command ran out of memory and was killed by the OOM killer".to_string(),
+    })
+}
@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod tests {
+    use crate::container::{ContainerRunner, ContainerRuntime};
+    use crate::models::{ExecutionRequest, ResourceLimits, SandboxBackend, SandboxConfig};
+    use crate::runner::SandboxRunner;
+    use crate::usage::{
+        parse_cpu_stat, parse_io_stat, parse_memory_events, read_cgroup_usage, sample_cgroup, CgroupUsage,
+        UsageAccounting,
+    };
+    use mcp_common::error::{error_code, McpError};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, Instant};
+
+    fn spinning_request(script: &str, timeout: u32, cpu_time_limit: u64) -> ExecutionRequest {
+        ExecutionRequest {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout,
+            sandbox_config: SandboxConfig {
+                enabled: false,
+                resource_limits: ResourceLimits {
+                    cpu_time_limit: Some(cpu_time_limit),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        }
+    }
+
+    // Test for parsing cgroup v2 usage files
+    #[test]
+    fn test_parse_cgroup_usage() {
+        let cpu_stat = "usage_usec 1523000\nuser_usec 1200000\nsystem_usec 323000\n";
+        assert_eq!(parse_cpu_stat(cpu_stat), Some(1523000));
+        assert_eq!(parse_cpu_stat("user_usec 1\n"), None);
+
+        let io_stat = "8:0 rbytes=4096 wbytes=1024 rios=1 wios=1 dbytes=0 dios=0\n\
+                       259:0 rbytes=8192 wbytes=0 rios=2 wios=0 dbytes=0 dios=0\n";
+        assert_eq!(parse_io_stat(io_stat), (12288, 1024));
+        assert_eq!(parse_io_stat(""), (0, 0));
+
+        let memory_events = "low 0\nhigh 0\nmax 12\noom 2\noom_kill 1\noom_group_kill 0\n";
+        assert_eq!(parse_memory_events(memory_events), Some(1));
+        assert_eq!(parse_memory_events("oom_group_kill 3\n"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(read_cgroup_usage(dir.path()), CgroupUsage::default());
+        std::fs::write(dir.path().join("cpu.stat"), cpu_stat).unwrap();
+        std::fs::write(dir.path().join("memory.peak"), "10485760\n").unwrap();
+        std::fs::write(dir.path().join("io.stat"), io_stat).unwrap();
+        assert_eq!(
+            read_cgroup_usage(dir.path()),
+            CgroupUsage {
+                cpu_usec: Some(1523000),
+                memory_peak_bytes: Some(10485760),
+                io_bytes: Some((12288, 1024)),
+            }
+        );
+    }
+
+    // Test for sampling the live usage of the task cgroup of a running command
+    #[test]
+    fn test_sample_cgroup() {
+        let dir = tempfile::tempdir().unwrap();
+        let cgroup = dir.path().join("mcp-task-1");
+        assert!(sample_cgroup(&cgroup).is_none());
+
+        fs::create_dir(&cgroup).unwrap();
+        fs::write(cgroup.join("cpu.stat"), "usage_usec 2500000\nuser_usec 2000000\n").unwrap();
+        fs::write(cgroup.join("memory.current"), "4194304\n").unwrap();
+        fs::write(cgroup.join("memory.peak"), "8389632\n").unwrap();
+        fs::write(cgroup.join("io.stat"), "8:0 rbytes=4096 wbytes=512 rios=1 wios=1\n").unwrap();
+        let sample = sample_cgroup(&cgroup).unwrap();
+        assert_eq!(
+            (sample.cpu_time_ms, sample.memory_kb, sample.max_memory_kb, sample.io_read_bytes, sample.io_write_bytes),
+            (2500, 4096, 8193, 4096, 512)
+        );
+        assert!(sample.timestamp_ms > 0);
+
+        // The running command of a task is sampled through its task cgroup
+        let runner = SandboxRunner::new();
+        let _task = runner.processes().register("task-1").unwrap();
+        assert!(runner.processes().usage_sample("task-1").is_none());
+        assert!(runner.processes().set_process_group("task-1", i32::MAX, Some(&cgroup)));
+        assert_eq!(runner.processes().usage_sample("task-1").unwrap().cpu_time_ms, 2500);
+        runner.processes().clear_process_group("task-1");
+        assert!(runner.processes().usage_sample("task-1").is_none());
+    }
+
+    // Test for measuring a command with getrusage when no cgroup is available
+    #[tokio::test]
+    async fn test_getrusage_fallback() {
+        // A directory that is not a cgroup: the task cgroup cannot be joined
+        let dir = tempfile::tempdir().unwrap();
+        let accounting = UsageAccounting::new().with_cgroup_root(dir.path());
+
+        let meter = accounting.start();
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args(["-c", "head -c 1048576 /dev/zero > /dev/null"]);
+        meter.attach(&mut cmd).unwrap();
+        assert!(cmd.status().await.unwrap().success());
+
+        let usage = meter.finish();
+        assert!(usage.max_memory_kb > 0);
+        // The directory of the task cgroup is removed
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    // Test for killing a spinning command at its CPU time limit before the wall-clock timeout
+    #[tokio::test]
+    async fn test_cpu_time_limit() {
+        let started = Instant::now();
+        match SandboxRunner::new().run(spinning_request("while :; do :; done", 30, 1)).await {
+            Err(error @ McpError::Sandbox(_)) => {
+                assert_eq!(error.code(), error_code::SANDBOX_CPU_TIME_EXCEEDED);
+                assert!(error.to_string().contains("CPU time limit of 1 seconds exceeded"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        // A command that waits is stopped by the wall-clock timeout instead
+        match SandboxRunner::new().run(spinning_request("sleep 5", 1, 1)).await {
+            Err(error @ McpError::Execution(_)) => {
+                assert_ne!(error.code(), error_code::SANDBOX_CPU_TIME_EXCEEDED);
+                assert!(error.to_string().contains("timed out"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // Commands within the limit are not affected
+        let result = SandboxRunner::new().run(spinning_request("exit 0", 10, 1)).await.unwrap();
+        assert_eq!(result.exit_code, Some(0));
+    }
+
+    // Test for reporting containers killed at their memory limit as out of memory
+    #[tokio::test]
+    async fn test_out_of_memory() {
+        // A runtime whose container is killed by SIGKILL
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = dir.path().join("docker");
+        fs::write(&runtime, "#!/bin/sh\nexit 137\n").unwrap();
+        fs::set_permissions(&runtime, fs::Permissions::from_mode(0o755)).unwrap();
+        let runner = SandboxRunner::new().with_container_runner(ContainerRunner::new(
+            ContainerRuntime::Docker,
+            runtime.to_str().unwrap(),
+            "example.com/sandbox:1",
+        ));
+        let request = |memory_limit: Option<u64>| ExecutionRequest {
+            command: "true".to_string(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config: SandboxConfig {
+                backend: SandboxBackend::Container,
+                resource_limits: ResourceLimits {
+                    memory_limit,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+
+        match runner.run(request(Some(64 * 1024 * 1024))).await {
+            Err(error @ McpError::Sandbox(_)) => {
+                assert_eq!(error.code(), error_code::SANDBOX_OUT_OF_MEMORY);
+                assert!(error.to_string().contains("out of memory"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // Without a memory limit, the kill is an ordinary failure of the command
+        let result = runner.run(request(None)).await.unwrap();
+        assert_eq!(result.exit_code, Some(137));
+    }
+}
@@ -0,0 +1,192 @@
+//! Per-task workspaces
+//!
+//! When configured, every task gets its own host directory `<root>/<task_id>/workspace`,
+//! which is mounted read-write at [`WORKSPACE_MOUNT_POINT`] in the sandbox instead of the
+//! host path of the same name, so that tasks never see each other's files.
+//!
+//! After the task, the directory is kept for the retention period so that its results can be
+//! inspected or copied out. Expired directories are removed by the garbage collection that
+//! runs whenever a workspace is provisioned; the directories of running tasks are never
+//! removed. With a maximum number of retained workspaces, the oldest finished ones are also
+//! removed beyond that number.
+
+use crate::overlay::remove_tree;
+use mcp_common::error::{McpError, McpResult};
+use std::fs;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+/// Path of the workspace inside the sandbox
+pub const WORKSPACE_MOUNT_POINT: &str = "/workspace";
+
+/// Retention period used when `MCP_TASK_WORKSPACE_RETENTION_SECS` is not set
+pub const DEFAULT_WORKSPACE_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// Name of the workspace below the task directory
+const WORKSPACE_DIR: &str = "workspace";
+/// Marker written next to the workspace when the task has finished
+const FINISHED_MARKER: &str = "finished";
+
+/// Provisioning of per-task workspaces
+#[derive(Debug, Clone)]
+pub struct TaskWorkspaces {
+    root: PathBuf,
+    retention: Duration,
+    max_retained: Option<usize>,
+}
+
+impl TaskWorkspaces {
+    /// Provision workspaces below a root directory with the default retention
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            retention: DEFAULT_WORKSPACE_RETENTION,
+            max_retained: None,
+        }
+    }
+
+    /// Keep the workspaces of finished tasks for a different period
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Keep at most `max_retained` workspaces of finished tasks
+    pub fn with_max_retained(mut self, max_retained: usize) -> Self {
+        self.max_retained = Some(max_retained);
+        self
+    }
+
+    /// Build the settings from environment variables
+    ///
+    /// * `MCP_TASK_WORKSPACE_ROOT` - directory of the task workspaces (e.g. `/var/lib/mcp/tasks`);
+    ///   per-task workspaces are disabled when it is not set
+    /// * `MCP_TASK_WORKSPACE_RETENTION_SECS` - how long workspaces of finished tasks are kept
+    /// * `MCP_TASK_WORKSPACE_MAX_RETAINED` - maximum number of workspaces of finished tasks
+    pub fn from_env() -> McpResult<Option<Self>> {
+        let root = match std::env::var("MCP_TASK_WORKSPACE_ROOT") {
+            Ok(root) if !root.trim().is_empty() => root,
+            _ => return Ok(None),
+        };
+        let mut workspaces = Self::new(root.trim());
+
+        if let Ok(value) = std::env::var("MCP_TASK_WORKSPACE_RETENTION_SECS") {
+            let secs = value.trim().parse::<u64>().map_err(|_| {
+                McpError::InvalidRequest(format!(
+                    "MCP_TASK_WORKSPACE_RETENTION_SECS must be a number of seconds: '{}'",
+                    value
+                ))
+            })?;
+            workspaces.retention = Duration::from_secs(secs);
+        }
+        if let Ok(value) = std::env::var("MCP_TASK_WORKSPACE_MAX_RETAINED") {
+            workspaces.max_retained = Some(value.trim().parse::<usize>().map_err(|_| {
+                McpError::InvalidRequest(format!("MCP_TASK_WORKSPACE_MAX_RETAINED must be a number: '{}'", value))
+            })?);
+        }
+        Ok(Some(workspaces))
+    }
+
+    /// Directory of the task workspaces
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Host directory of the workspace of a task
+    pub fn path(&self, task_id: &str) -> McpResult<PathBuf> {
+        Ok(self.task_dir(task_id)?.join(WORKSPACE_DIR))
+    }
+
+    /// Create the workspace of a task, accessible only to the gateway user
+    pub fn provision(&self, task_id: &str) -> McpResult<PathBuf> {
+        let task_dir = self.task_dir(task_id)?;
+        fs::create_dir_all(&self.root).map_err(|e| workspace_error(&self.root, e))?;
+        fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&task_dir)
+            .map_err(|e| workspace_error(&task_dir, e))?;
+        let workspace = task_dir.join(WORKSPACE_DIR);
+        fs::create_dir(&workspace).map_err(|e| workspace_error(&workspace, e))?;
+        debug!("Provisioned workspace {} for task {}", workspace.display(), task_id);
+        Ok(workspace)
+    }
+
+    /// Mark the workspace of a task as finished, starting its retention period
+    pub fn release(&self, task_id: &str) {
+        let marker = match self.task_dir(task_id) {
+            Ok(task_dir) => task_dir.join(FINISHED_MARKER),
+            Err(_) => return,
+        };
+        if let Err(e) = fs::write(&marker, b"") {
+            warn!("Failed to mark workspace of task {} as finished: {}", task_id, e);
+        }
+    }
+
+    /// Remove the workspaces of finished tasks that are past the retention policy
+    ///
+    /// `is_active` tells whether a task is still running; the workspaces of running tasks
+    /// are never removed, even if they were never marked as finished (e.g. after a restart
+    /// of the gateway, unmarked workspaces expire by the time they were last modified).
+    /// Returns the number of removed workspaces.
+    pub fn collect_garbage(&self, is_active: impl Fn(&str) -> bool) -> McpResult<usize> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(workspace_error(&self.root, e)),
+        };
+
+        // Finished workspaces with the time their retention started
+        let mut finished = Vec::new();
+        for entry in entries.flatten() {
+            let task_id = entry.file_name().to_string_lossy().to_string();
+            let path = entry.path();
+            if !entry.file_type().is_ok_and(|file_type| file_type.is_dir()) || is_active(&task_id) {
+                continue;
+            }
+            let finished_at = fs::metadata(path.join(FINISHED_MARKER))
+                .or_else(|_| fs::metadata(&path))
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            finished.push((finished_at, path));
+        }
+        // Newest first
+        finished.sort_by_key(|(finished_at, _)| std::cmp::Reverse(*finished_at));
+
+        let now = SystemTime::now();
+        let mut removed = 0;
+        for (index, (finished_at, path)) in finished.iter().enumerate() {
+            let expired = now.duration_since(*finished_at).is_ok_and(|age| age >= self.retention);
+            let over_limit = self.max_retained.is_some_and(|max_retained| index >= max_retained);
+            if !expired && !over_limit {
+                continue;
+            }
+            // The command may have left directories without write permission
+            match remove_tree(path) {
+                Ok(()) => removed += 1,
+                Err(e) => warn!("Failed to remove workspace {}: {}", path.display(), e),
+            }
+        }
+        if removed > 0 {
+            info!("Removed {} expired task workspaces", removed);
+        }
+        Ok(removed)
+    }
+
+    /// Directory of a task, rejecting IDs that are not a single path component
+    fn task_dir(&self, task_id: &str) -> McpResult<PathBuf> {
+        let valid = !task_id.is_empty()
+            && task_id != "."
+            && task_id != ".."
+            && task_id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            return Err(McpError::InvalidRequest(format!("Invalid task ID for a workspace: '{}'", task_id)));
+        }
+        Ok(self.root.join(task_id))
+    }
+}
+
+fn workspace_error(path: &Path, e: std::io::Error) -> McpError {
+    McpError::Sandbox(format!("Task workspace error at {}: {}", path.display(), e))
+}
@@ -0,0 +1,170 @@
+//! Workspace change reports
+//!
+//! On request, the files below the read-write paths of a command are recorded before it
+//! starts (after the input files are staged) and again after it exits, and the result lists
+//! every file that was added, modified or deleted in between, with sizes and SHA-256
+//! hashes. Reviewers can then see exactly what a command changed before accepting it.
+//!
+//! Paths are reported as the command sees them in the sandbox, e.g. `/workspace/out.txt`
+//! for a file of the per-task workspace. Only regular files and symbolic links are
+//! compared (a link by its target); empty directories and special files are not reported.
+//! With an ephemeral workspace nothing is ever changed, and with `commit_on_success` only
+//! the committed changes are reported.
+//!
+//! Read-write paths with more than [`MAX_DIFF_ENTRIES`] files are not compared; the result
+//! then has no change report.
+
+use crate::models::SandboxConfig;
+use mcp_common::error::{McpError, McpResult};
+use mcp_common::utils::hex;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Maximum number of files of the read-write paths that are compared
+pub const MAX_DIFF_ENTRIES: usize = 10_000;
+
+/// Kind of a change to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Deleted => "deleted",
+        }
+    }
+}
+
+/// File changed by a command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    /// Path in the sandbox
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+    /// Size after the command (bytes, `None` if deleted)
+    pub size: Option<u64>,
+    /// SHA-256 hash after the command (hex, `None` if deleted)
+    pub sha256: Option<String>,
+    /// Size before the command (bytes, `None` if added)
+    pub previous_size: Option<u64>,
+    /// SHA-256 hash before the command (hex, `None` if added)
+    pub previous_sha256: Option<String>,
+}
+
+/// Size and hash of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileState {
+    size: u64,
+    sha256: String,
+}
+
+/// Files below the read-write paths of a sandbox configuration at one point in time
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceSnapshot {
+    files: BTreeMap<PathBuf, FileState>,
+}
+
+impl WorkspaceSnapshot {
+    /// Record the files below the read-write paths
+    ///
+    /// Returns `None` if there are more than `max_entries` files.
+    pub fn capture(config: &SandboxConfig, max_entries: usize) -> McpResult<Option<Self>> {
+        let mut snapshot = Self::default();
+        for path in &config.rw_paths {
+            let host_path = config.host_path(path);
+            if fs::symlink_metadata(host_path).is_err() {
+                continue;
+            }
+            if !snapshot.record_dir(host_path, path, max_entries)? {
+                return Ok(None);
+            }
+        }
+        Ok(Some(snapshot))
+    }
+
+    /// Changes from this snapshot to a later one, ordered by path
+    pub fn diff(&self, after: &WorkspaceSnapshot) -> Vec<FileChange> {
+        let mut changes = Vec::new();
+        for (path, state) in &after.files {
+            let previous = self.files.get(path);
+            let kind = match previous {
+                None => ChangeKind::Added,
+                Some(previous) if previous != state => ChangeKind::Modified,
+                Some(_) => continue,
+            };
+            changes.push(FileChange {
+                path: path.clone(),
+                kind,
+                size: Some(state.size),
+                sha256: Some(state.sha256.clone()),
+                previous_size: previous.map(|previous| previous.size),
+                previous_sha256: previous.map(|previous| previous.sha256.clone()),
+            });
+        }
+        for (path, previous) in &self.files {
+            if !after.files.contains_key(path) {
+                changes.push(FileChange {
+                    path: path.clone(),
+                    kind: ChangeKind::Deleted,
+                    size: None,
+                    sha256: None,
+                    previous_size: Some(previous.size),
+                    previous_sha256: Some(previous.sha256.clone()),
+                });
+            }
+        }
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        changes
+    }
+
+    /// Record a file or directory tree; returns false when there are too many files
+    fn record_dir(&mut self, host_path: &Path, sandbox_path: &Path, max_entries: usize) -> McpResult<bool> {
+        let metadata = fs::symlink_metadata(host_path).map_err(|e| snapshot_error(host_path, e))?;
+        let file_type = metadata.file_type();
+        let state = if file_type.is_dir() {
+            let entries = fs::read_dir(host_path).map_err(|e| snapshot_error(host_path, e))?;
+            for entry in entries {
+                let entry = entry.map_err(|e| snapshot_error(host_path, e))?;
+                let name = entry.file_name();
+                if !self.record_dir(&entry.path(), &sandbox_path.join(&name), max_entries)? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        } else if !file_type.is_symlink() && !file_type.is_file() {
+            return Ok(true);
+        } else if self.files.len() >= max_entries {
+            return Ok(false);
+        } else if file_type.is_symlink() {
+            let target = fs::read_link(host_path).map_err(|e| snapshot_error(host_path, e))?;
+            let target = target.as_os_str().as_encoded_bytes();
+            FileState {
+                size: target.len() as u64,
+                sha256: hex(&Sha256::digest(target)),
+            }
+        } else {
+            let mut file = File::open(host_path).map_err(|e| snapshot_error(host_path, e))?;
+            let mut hasher = Sha256::new();
+            io::copy(&mut file, &mut hasher).map_err(|e| snapshot_error(host_path, e))?;
+            FileState {
+                size: metadata.len(),
+                sha256: hex(&hasher.finalize()),
+            }
+        };
+        self.files.insert(sandbox_path.to_path_buf(), state);
+        Ok(true)
+    }
+}
+
+fn snapshot_error(path: &Path, e: io::Error) -> McpError {
+    McpError::Sandbox(format!("Failed to record workspace file {}: {}", path.display(), e))
+}
@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{ExecutionRequest, SandboxConfig};
+    use crate::runner::SandboxRunner;
+    use crate::workspace_diff::{ChangeKind, WorkspaceSnapshot};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    // Test for comparing snapshots of the read-write paths
+    #[test]
+    fn test_snapshot_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("workspace");
+        std::fs::create_dir_all(workspace.join("src")).unwrap();
+        std::fs::write(workspace.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(workspace.join("README"), "readme").unwrap();
+        std::fs::write(workspace.join("unchanged"), "same").unwrap();
+        let config = SandboxConfig {
+            rw_paths: vec![PathBuf::from("/workspace"), PathBuf::from("/nonexistent")],
+            workspace_dir: Some(workspace.clone()),
+            ..Default::default()
+        };
+
+        let before = WorkspaceSnapshot::capture(&config, 100).unwrap().unwrap();
+        std::fs::write(workspace.join("src/main.rs"), "fn main() { println!(); }").unwrap();
+        std::fs::remove_file(workspace.join("README")).unwrap();
+        std::fs::write(workspace.join("out.txt"), "hello").unwrap();
+        std::os::unix::fs::symlink("out.txt", workspace.join("latest")).unwrap();
+        let after = WorkspaceSnapshot::capture(&config, 100).unwrap().unwrap();
+
+        // Paths are reported as seen in the sandbox, ordered by path
+        let changes = before.diff(&after);
+        let summary: Vec<_> = changes.iter().map(|change| (change.path.to_str().unwrap(), change.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("/workspace/README", ChangeKind::Deleted),
+                ("/workspace/latest", ChangeKind::Added),
+                ("/workspace/out.txt", ChangeKind::Added),
+                ("/workspace/src/main.rs", ChangeKind::Modified),
+            ]
+        );
+        let added = &changes[2];
+        assert_eq!(added.size, Some(5));
+        assert_eq!(
+            added.sha256.as_deref(),
+            Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+        );
+        assert_eq!(added.previous_sha256, None);
+        let deleted = &changes[0];
+        assert_eq!((deleted.size, deleted.previous_size), (None, Some(6)));
+        let modified = &changes[3];
+        assert_ne!(modified.sha256, modified.previous_sha256);
+        assert_eq!(modified.previous_size, Some(12));
+
+        // Too many files are not compared
+        assert!(WorkspaceSnapshot::capture(&config, 3).unwrap().is_none());
+        assert!(before.diff(&before).is_empty());
+    }
+
+    // Test for reporting the changes of a command in its result
+    #[tokio::test]
+    async fn test_run_with_workspace_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("input.txt"), "input").unwrap();
+        let runner = SandboxRunner::new();
+        let request = |diff_workspace: bool| ExecutionRequest {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "echo output >> output.txt".to_string()],
+            env: HashMap::new(),
+            cwd: Some(dir.path().to_path_buf()),
+            timeout: 10,
+            sandbox_config: SandboxConfig {
+                enabled: false,
+                rw_paths: vec![dir.path().to_path_buf()],
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace,
+        };
+
+        let result = runner.run(request(true)).await.unwrap();
+        let changes = result.workspace_changes.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, dir.path().join("output.txt"));
+        assert_eq!(changes[0].kind, ChangeKind::Added);
+
+        let result = runner.run(request(true)).await.unwrap();
+        assert_eq!(result.workspace_changes.unwrap()[0].kind, ChangeKind::Modified);
+
+        // Changes are only reported on request
+        let result = runner.run(request(false)).await.unwrap();
+        assert!(result.workspace_changes.is_none());
+    }
+}
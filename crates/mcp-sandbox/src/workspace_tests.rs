@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{ExecutionRequest, SandboxConfig};
+    use crate::runner::SandboxRunner;
+    use crate::workspace::TaskWorkspaces;
+    use mcp_common::error::McpError;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    // Test for provisioning workspaces
+    #[test]
+    fn test_provision() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspaces = TaskWorkspaces::new(dir.path().join("tasks"));
+
+        let workspace = workspaces.provision("task-1").unwrap();
+        assert_eq!(workspace, dir.path().join("tasks/task-1/workspace"));
+        assert_eq!(workspaces.path("task-1").unwrap(), workspace);
+        assert!(workspace.is_dir());
+        // Task IDs are never reused
+        assert!(workspaces.provision("task-1").is_err());
+
+        for task_id in ["", "..", "../escape", "a/b"] {
+            match workspaces.provision(task_id) {
+                Err(McpError::InvalidRequest(_)) => {}
+                other => panic!("unexpected result for '{}': {:?}", task_id, other),
+            }
+        }
+
+        // The workspace replaces the host /workspace only
+        let config = SandboxConfig {
+            workspace_dir: Some(workspace.clone()),
+            ..Default::default()
+        };
+        assert_eq!(config.host_path(Path::new("/workspace")), workspace);
+        assert_eq!(config.host_path(Path::new("/workspace/sub")), Path::new("/workspace/sub"));
+        assert_eq!(SandboxConfig::default().host_path(Path::new("/workspace")), Path::new("/workspace"));
+    }
+
+    // Test for the retention policy
+    #[test]
+    fn test_collect_garbage() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspaces = TaskWorkspaces::new(dir.path()).with_retention(Duration::ZERO);
+        for task_id in ["finished", "running"] {
+            workspaces.provision(task_id).unwrap();
+        }
+        workspaces.release("finished");
+
+        // Running tasks are kept, finished ones expire
+        assert_eq!(workspaces.collect_garbage(|task_id| task_id == "running").unwrap(), 1);
+        assert!(!dir.path().join("finished").exists());
+        assert!(dir.path().join("running/workspace").exists());
+
+        // Workspaces within the retention period are kept up to the maximum number
+        let workspaces = TaskWorkspaces::new(dir.path()).with_retention(Duration::from_secs(3600)).with_max_retained(1);
+        assert_eq!(workspaces.collect_garbage(|_| false).unwrap(), 0);
+        workspaces.provision("newer").unwrap();
+        workspaces.release("newer");
+        assert_eq!(workspaces.collect_garbage(|_| false).unwrap(), 1);
+        assert!(dir.path().join("newer").exists());
+        assert!(!dir.path().join("running").exists());
+
+        // A missing root has nothing to collect
+        assert_eq!(TaskWorkspaces::new(dir.path().join("missing")).collect_garbage(|_| false).unwrap(), 0);
+    }
+
+    // Test for running a task with its own workspace
+    #[tokio::test]
+    async fn test_run_task_with_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspaces = TaskWorkspaces::new(dir.path());
+        let runner = SandboxRunner::new().with_task_workspaces(workspaces.clone());
+        let _guard = runner.processes().register("task-1").unwrap();
+
+        let request = ExecutionRequest {
+            command: "echo".to_string(),
+            args: vec!["hello".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            timeout: 10,
+            sandbox_config: SandboxConfig {
+                rw_paths: Vec::<PathBuf>::new(),
+                ..Default::default()
+            },
+            input_files: Vec::new(),
+            diff_workspace: false,
+        };
+        let result = runner.run_task(request, Some("task-1"), None).await.unwrap();
+        assert_eq!(result.stdout.trim(), "hello");
+        assert!(workspaces.path("task-1").unwrap().is_dir());
+        assert!(dir.path().join("task-1/finished").exists());
+    }
+}
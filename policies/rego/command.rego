@@ -19,6 +19,13 @@ allowed_commands := {
     "npm"
 }
 
+# 結果をキャッシュしてよい読み取り専用コマンド
+cacheable_commands := {
+    "ls",
+    "cat",
+    "grep"
+}
+
 # 危険と見なされるコマンド
 dangerous_commands := {
     "rm",
@@ -31,14 +38,29 @@ dangerous_commands := {
     "su"
 }
 
+# コマンドごとに禁止される引数
+denied_args := {
+    "python": {"-c"},
+    "python3": {"-c"},
+    "node": {"-e", "--eval", "-p", "--print"},
+    "find": {"-exec", "-execdir", "-ok", "-okdir", "-delete", "-fprint", "-fprint0", "-fprintf", "-fls"}
+}
+
+# 全てのコマンドで禁止される引数
+globally_denied_args := {
+    "--privileged"
+}
+
 # コマンド実行を許可するルール
 allow if {
     not is_dangerous_command
+    not has_denied_arg
     input.user.roles[_] == "admin" # 管理者権限を持つユーザーは実行可能
 }
 
 allow if {
     not is_dangerous_command
+    not has_denied_arg
     is_allowed_command
 }
 
@@ -52,7 +74,33 @@ is_dangerous_command if {
     input.command.name in dangerous_commands
 }
 
+# 禁止された引数かどうかをチェック
+is_denied_arg(arg) if {
+    arg in denied_args[input.command.name]
+}
+
+is_denied_arg(arg) if {
+    some flag in globally_denied_args
+    arg == flag
+}
+
+is_denied_arg(arg) if {
+    some flag in globally_denied_args
+    startswith(arg, concat("", [flag, "="]))
+}
+
+has_denied_arg if {
+    some arg in input.command.args
+    is_denied_arg(arg)
+}
+
 # 拒否理由
+deny_reasons contains reason if {
+    some arg in input.command.args
+    is_denied_arg(arg)
+    reason := sprintf("引数 '%s' はコマンド '%s' では許可されていません", [arg, input.command.name])
+}
+
 deny_reasons contains reason if {
     is_dangerous_command
     reason := sprintf("コマンド '%s' は危険なため禁止されています", [input.command.name])
@@ -68,5 +116,13 @@ deny_reasons contains reason if {
 warnings contains message if {
     input.user.roles[_] == "admin"
     is_allowed_command
+    not has_denied_arg
     message := "管理者として実行中。全ての操作が監査されます。"
-} 
\ No newline at end of file
+}
+
+# 結果キャッシュの可否
+default cacheable = false
+
+cacheable if {
+    input.command.name in cacheable_commands
+}
@@ -9,6 +9,7 @@ import future.keywords.if
 default allow = false
 default deny_reasons = []
 default warnings = []
+default metadata = {}
 
 # メインの許可ルール
 allow if {
@@ -83,4 +84,9 @@ warnings = msgs if {
     
     task_type == "network"
     msgs := network.warnings
-} else = [] 
\ No newline at end of file
+} else = []
+
+# 決定に付与するメタデータ
+metadata = {"cacheable": command.cacheable} if {
+    get_task_type == "command"
+}
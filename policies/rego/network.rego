@@ -6,12 +6,8 @@ import future.keywords.in
 # デフォルトのルール: 拒否
 default allow = false
 
-# 許可されたホスト
-allowed_hosts := {
-    "api.example.com",
-    "cdn.example.com",
-    "data.example.com"
-}
+# 許可されたホスト（組織データ data.json の organization.allowed_hosts で管理）
+allowed_hosts := {host | some host in data.organization.allowed_hosts}
 
 # 許可されたポート
 allowed_ports := {